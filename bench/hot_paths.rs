@@ -0,0 +1,142 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+// Benchmarks for the code paths that run on every user interaction with
+// large collections or busy mixers.
+use std::str::FromStr;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use pw_gix::gtk::{self, prelude::*};
+use pw_gix::gtkx::list_store::*;
+
+use epaint::art_paint::*;
+use epaint::basic_paint::*;
+use epaint::cairox::*;
+use epaint::colln_paint::collection::*;
+use epaint::colour::*;
+use epaint::colour_mix::*;
+use epaint::mixed_paint::hue_wheel::*;
+use epaint::shape::*;
+
+const LARGE_COLLN_SIZE: usize = 5000;
+const MIXER_COMPONENTS: usize = 50;
+
+fn large_series_text(n_paints: usize) -> String {
+    let mut text = "Manufacturer: Benchmark\nSeries: Large Series\n".to_string();
+    for i in 0..n_paints {
+        text += &format!(
+            "ArtPaint(name=\"Paint {:05}\", rgb=RGB16(red=0x{:04X}, green=0x{:04X}, blue=0x{:04X}), transparency=\"O\", permanence=\"A\", notes=\"PB{}\")\n",
+            i,
+            (i * 7919) % 0x10000,
+            (i * 104729) % 0x10000,
+            (i * 1299709) % 0x10000,
+            i
+        );
+    }
+    text
+}
+
+fn large_series(n_paints: usize) -> ArtPaintSeries {
+    let spec = ArtPaintSeriesSpec::from_str(&large_series_text(n_paints)).unwrap();
+    ArtPaintSeries::from_spec(&spec)
+}
+
+fn spec_parsing(c: &mut Criterion) {
+    let text = large_series_text(LARGE_COLLN_SIZE);
+    c.bench_function("parse large series spec", |b| {
+        b.iter(|| ArtPaintSeriesSpec::from_str(black_box(&text)).unwrap())
+    });
+}
+
+fn list_store_population(c: &mut Criterion) {
+    if gtk::init().is_err() {
+        eprintln!("list store population: skipped (no display)");
+        return;
+    }
+    let series = large_series(LARGE_COLLN_SIZE);
+    let len = ArtSeriesPaint::tv_row_len();
+    let list_store = gtk::ListStore::new(&STANDARD_PAINT_ROW_SPEC[0..len]);
+    c.bench_function("populate list store", |b| {
+        b.iter(|| {
+            list_store.clear();
+            for paint in series.get_paints().iter() {
+                list_store.append_row(&paint.tv_rows());
+            }
+        })
+    });
+}
+
+fn mixer_updates(c: &mut Criterion) {
+    let series = large_series(MIXER_COMPONENTS);
+    let components: Vec<(Colour, u32)> = series
+        .get_paints()
+        .iter()
+        .enumerate()
+        .map(|(i, paint)| (paint.colour(), (i % 7) as u32 + 1))
+        .collect();
+    for model in [MixingModel::SRGB, MixingModel::LinearLight].iter() {
+        let name = format!("mix {} components ({:?})", MIXER_COMPONENTS, model);
+        c.bench_function(&name, |b| {
+            b.iter(|| {
+                let mut colour_mixer = ColourMixer::with_model(*model);
+                for (colour, parts) in components.iter() {
+                    colour_mixer.add(colour, *parts);
+                }
+                black_box(colour_mixer.get_colour())
+            })
+        });
+    }
+}
+
+fn wheel_hit_testing(c: &mut Criterion) {
+    let series = large_series(LARGE_COLLN_SIZE);
+    let shapes = SeriesPaintShapeList::<ArtPaintCharacteristics>::new(ScalarAttribute::Value);
+    for paint in series.get_paints().iter() {
+        shapes.add_coloured_item(paint);
+    }
+    let points: Vec<Point> = (0..100)
+        .map(|i| {
+            Point(
+                ((i * 37) % 200) as f64 / 100.0 - 1.0,
+                ((i * 53) % 200) as f64 / 100.0 - 1.0,
+            )
+        })
+        .collect();
+    c.bench_function("wheel hit test", |b| {
+        b.iter(|| {
+            for point in points.iter() {
+                black_box(shapes.get_coloured_item_at(*point));
+            }
+        })
+    });
+    // the pointer wandering about within a single shape (as when waiting
+    // for a tooltip)
+    let paints = series.get_paints();
+    let paint = &paints[LARGE_COLLN_SIZE / 2];
+    let centre = colour_wheel_xy(&paint.colour(), ScalarAttribute::Value);
+    let jitter: Vec<Point> = (0..100)
+        .map(|i| {
+            centre
+                + Point(
+                    ((i % 5) as f64 - 2.0) * 0.002,
+                    ((i % 3) as f64 - 1.0) * 0.002,
+                )
+        })
+        .collect();
+    c.bench_function("wheel hit test (pointer within shape)", |b| {
+        b.iter(|| {
+            for point in jitter.iter() {
+                black_box(shapes.get_coloured_item_at(*point));
+            }
+        })
+    });
+}
+
+criterion_group!(
+    hot_paths,
+    spec_parsing,
+    list_store_population,
+    mixer_updates,
+    wheel_hit_testing
+);
+criterion_main!(hot_paths);
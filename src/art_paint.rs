@@ -5,6 +5,8 @@ use std::fmt;
 use std::rc::Rc;
 use std::str::FromStr;
 
+use serde_derive::*;
+
 use pw_gix::{
     glib,
     gtk::{self, prelude::*},
@@ -26,7 +28,7 @@ pub use crate::mixed_paint::mixer::*;
 pub use crate::struct_traits::SimpleCreation;
 use colour_math_gtk::attributes::*;
 
-#[derive(Debug, PartialEq, Hash, Clone, Copy)]
+#[derive(Debug, PartialEq, Hash, Clone, Copy, Serialize, Deserialize)]
 pub struct ArtPaintCharacteristics {
     pub permanence: Permanence,
     pub transparency: Transparency,
@@ -101,6 +103,14 @@ impl CharacteristicsInterface for ArtPaintCharacteristics {
             transparency,
         })
     }
+
+    fn paint_types() -> Vec<&'static str> {
+        vec!["ArtPaint"]
+    }
+
+    fn legacy_assumptions() -> Vec<(&'static str, &'static str)> {
+        vec![("permanence", "A"), ("transparency", "O")]
+    }
 }
 
 impl fmt::Display for ArtPaintCharacteristics {
@@ -310,6 +320,10 @@ impl MixerConfig for ArtPaintMixerConfig {
     fn mixing_mode() -> MixingMode {
         MixingMode::MatchSamples
     }
+
+    fn recollection_name_for(item_name: &str) -> String {
+        format!("art_paint_mixer::{}", item_name)
+    }
 }
 
 pub type ArtSeriesPaint = SeriesPaint<ArtPaintCharacteristics>;
@@ -327,6 +341,7 @@ pub type ArtPaintFactoryDisplay =
     BasicPaintFactoryDisplay<ArtPaintAttributes, ArtPaintCharacteristics>;
 pub type BasicArtPaintEditor = SeriesPaintEditor<ArtPaintAttributes, ArtPaintCharacteristics>;
 pub type ArtPaintSeriesSpec = SeriesPaintCollnSpec<ArtPaintCharacteristics>;
+pub type ArtPaintSeriesBuilder = SeriesPaintCollnBuilder<ArtPaintCharacteristics>;
 
 const IDEAL_PAINT_STR: &str =
 "Manufacturer: Imaginary
@@ -534,6 +549,77 @@ mod tests {
         }
     }
 
+    #[test]
+    fn art_paint_series_builder() {
+        let characteristics = ArtPaintCharacteristics {
+            permanence: Permanence::Permanent,
+            transparency: Transparency::Opaque,
+        };
+        let mut builder = ArtPaintSeriesBuilder::new("Ideal Paint Colours Series", "Imaginary");
+        for pair in [
+            ("Red", RGB::RED),
+            ("Green", RGB::GREEN),
+            ("Blue", RGB::BLUE),
+            ("Cyan", RGB::CYAN),
+            ("Magenta", RGB::MAGENTA),
+            ("Yellow", RGB::YELLOW),
+            ("Black", RGB::BLACK),
+            ("White", RGB::WHITE),
+        ]
+        .iter()
+        {
+            assert!(builder.add_paint(pair.0, pair.1, characteristics).is_ok());
+        }
+        assert!(builder.add_paint("Red", RGB::RED, characteristics).is_err());
+        let expected = ArtPaintSeriesSpec::from_str(IDEAL_PAINT_STR).unwrap();
+        let spec = builder.spec();
+        assert_eq!(spec.colln_id, expected.colln_id);
+        assert_eq!(spec.paint_specs, expected.paint_specs);
+        let series = builder.build();
+        assert_eq!(series.len(), 8);
+        assert!(series.has_paint_named("Magenta"));
+    }
+
+    #[test]
+    fn art_paint_series_colln_notes() {
+        let spec = ArtPaintSeriesSpec::from_str(IDEAL_PAINT_STR).unwrap();
+        assert_eq!(spec.colln_notes, "");
+        let mut builder = ArtPaintSeriesBuilder::new("Ideal Paint Colours Series", "Imaginary");
+        builder.set_colln_notes("Primary and secondary colours.\nFor testing only.");
+        let text = builder.spec().to_string();
+        let spec = ArtPaintSeriesSpec::from_str(&text).unwrap();
+        assert_eq!(
+            spec.colln_notes,
+            "Primary and secondary colours.\nFor testing only."
+        );
+        assert_eq!(spec.to_string(), text);
+    }
+
+    #[test]
+    fn art_paint_series_paint_type_check() {
+        let text =
+            IDEAL_PAINT_STR.replacen("ArtPaint(name=\"Cyan\"", "ModelPaint(name=\"Cyan\"", 1);
+        let (spec, warnings) =
+            ArtPaintSeriesSpec::from_str_checked(&text, PaintTypeCheck::Lenient).unwrap();
+        assert_eq!(spec.paint_specs.len(), 8);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("Cyan"));
+        match ArtPaintSeriesSpec::from_str_checked(&text, PaintTypeCheck::Strict) {
+            Err(err) => match err.error_type() {
+                PaintErrorType::WrongPaintType(line) => assert!(line.contains("Cyan")),
+                _ => panic!("File: {:?} Line: {:?}", file!(), line!()),
+            },
+            Ok(_) => panic!("File: {:?} Line: {:?}", file!(), line!()),
+        }
+        let (_, warnings) =
+            ArtPaintSeriesSpec::from_str_checked(IDEAL_PAINT_STR, PaintTypeCheck::Strict).unwrap();
+        assert_eq!(warnings.len(), 0);
+        let generic = ArtPaintSeriesSpec::from_str(IDEAL_PAINT_STR)
+            .unwrap()
+            .to_string();
+        assert!(ArtPaintSeriesSpec::from_str_checked(&generic, PaintTypeCheck::Strict).is_ok());
+    }
+
     //    #[test]
     //    fn art_paint_spec_obsolete_series() {
     //        match ArtPaintSeriesSpec::from_str(OBSOLETE_PAINT_STR) {
@@ -0,0 +1,212 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+use std::rc::Rc;
+
+use pw_gix::{
+    glib::StaticType,
+    gtk::{self, prelude::*},
+    gtkx::dialog::*,
+    wrapper::*,
+};
+
+use crate::app_name;
+use crate::basic_paint::*;
+
+// Only those characteristics that the user actually changed (relative
+// to the values the entry was primed with) are applied to each paint.
+pub fn merge_characteristics<C>(current: &C, initial: &C, edited: &C) -> C
+where
+    C: CharacteristicsInterface,
+{
+    let floats: Vec<f64> = current
+        .to_floats()
+        .iter()
+        .zip(initial.to_floats().iter())
+        .zip(edited.to_floats().iter())
+        .map(|((c, i), e)| if e != i { *e } else { *c })
+        .collect();
+    C::from_floats(&floats)
+}
+
+pub struct CharacteristicsBatchDialogCore<C>
+where
+    C: CharacteristicsInterface + 'static,
+{
+    dialog: gtk::Dialog,
+    characteristics_entry: Rc<C::Entry>,
+    preview_store: gtk::ListStore,
+    paints: Vec<BasicPaint<C>>,
+    initial: C,
+}
+
+pub type CharacteristicsBatchDialog<C> = Rc<CharacteristicsBatchDialogCore<C>>;
+
+pub trait CharacteristicsBatchDialogInterface<C>
+where
+    C: CharacteristicsInterface + 'static,
+{
+    fn create<W: WidgetWrapper>(
+        paints: &Vec<BasicPaint<C>>,
+        caller: &Rc<W>,
+    ) -> CharacteristicsBatchDialog<C>;
+}
+
+impl<C> CharacteristicsBatchDialogInterface<C> for CharacteristicsBatchDialog<C>
+where
+    C: CharacteristicsInterface + 'static,
+{
+    fn create<W: WidgetWrapper>(
+        paints: &Vec<BasicPaint<C>>,
+        caller: &Rc<W>,
+    ) -> CharacteristicsBatchDialog<C> {
+        let title = format!("{}: Edit Characteristics of Selected Paints", app_name());
+        let dialog = caller.new_dialog_with_buttons(
+            Some(&title),
+            gtk::DialogFlags::DESTROY_WITH_PARENT,
+            CANCEL_OK_BUTTONS,
+        );
+        dialog.set_size_from_recollections("characteristics_batch_dialog", (400, 300));
+        let initial = paints[0].characteristics();
+        let characteristics_entry = C::Entry::create();
+        characteristics_entry.set_characteristics(Some(&initial));
+
+        let preview_store = gtk::ListStore::new(&[
+            String::static_type(),
+            String::static_type(),
+            String::static_type(),
+        ]);
+        let view = gtk::TreeView::with_model(&preview_store);
+        view.set_headers_visible(true);
+        view.get_selection().set_mode(gtk::SelectionMode::None);
+        for (col_id, title) in ["Paint", "Current", "New"].iter().enumerate() {
+            let col = gtk::TreeViewColumn::new();
+            col.set_title(title);
+            col.set_resizable(true);
+            let cell = gtk::CellRendererText::new();
+            col.pack_start(&cell, true);
+            col.add_attribute(&cell, "text", col_id as i32);
+            view.append_column(&col);
+        }
+        let adj: Option<&gtk::Adjustment> = None;
+        let scrolled_window = gtk::ScrolledWindow::new(adj, adj);
+        scrolled_window.add(&view);
+
+        let content_area = dialog.get_content_area();
+        let label = gtk::Label::new(Some(
+            "Only the characteristics that are changed will be applied to each paint.",
+        ));
+        content_area.pack_start(&label, false, false, 0);
+        content_area.pack_start(&characteristics_entry.pwo(), false, false, 0);
+        content_area.pack_start(&scrolled_window, true, true, 0);
+        content_area.show_all();
+
+        let cbd = Rc::new(CharacteristicsBatchDialogCore::<C> {
+            dialog,
+            characteristics_entry,
+            preview_store,
+            paints: paints.clone(),
+            initial,
+        });
+        cbd.update_preview();
+
+        let cbd_c = cbd.clone();
+        cbd.characteristics_entry
+            .connect_changed(move || cbd_c.update_preview());
+
+        cbd
+    }
+}
+
+impl<C> CharacteristicsBatchDialogCore<C>
+where
+    C: CharacteristicsInterface + 'static,
+{
+    fn new_characteristics_for(&self, paint: &BasicPaint<C>) -> Option<C> {
+        if let Some(edited) = self.characteristics_entry.get_characteristics() {
+            Some(merge_characteristics(
+                &paint.characteristics(),
+                &self.initial,
+                &edited,
+            ))
+        } else {
+            None
+        }
+    }
+
+    fn update_preview(&self) {
+        self.preview_store.clear();
+        for paint in self.paints.iter() {
+            let new_text = if let Some(characteristics) = self.new_characteristics_for(paint) {
+                characteristics.to_string()
+            } else {
+                "".to_string()
+            };
+            self.preview_store.insert_with_values(
+                None,
+                &[0, 1, 2],
+                &[
+                    &paint.name(),
+                    &paint.characteristics().to_string(),
+                    &new_text,
+                ],
+            );
+        }
+        self.dialog.set_response_sensitive(
+            gtk::ResponseType::Ok,
+            self.characteristics_entry.get_characteristics().is_some(),
+        );
+    }
+
+    // Returns the paints whose characteristics will change along with
+    // their new specifications.
+    pub fn get_batch_edits(&self) -> Vec<(BasicPaint<C>, BasicPaintSpec<C>)> {
+        let mut edits = vec![];
+        if gtk::ResponseType::from(self.dialog.run()) == gtk::ResponseType::Ok {
+            for paint in self.paints.iter() {
+                if let Some(characteristics) = self.new_characteristics_for(paint) {
+                    if characteristics != paint.characteristics() {
+                        let mut spec = paint.get_spec();
+                        spec.characteristics = characteristics;
+                        edits.push((paint.clone(), spec));
+                    }
+                }
+            }
+        };
+        unsafe { self.dialog.destroy() };
+        edits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::characteristics::*;
+    use crate::model_paint::ModelPaintCharacteristics;
+
+    #[test]
+    fn merge_only_changed_characteristics() {
+        let current = ModelPaintCharacteristics {
+            finish: Finish::Gloss,
+            transparency: Transparency::Transparent,
+            fluorescence: Fluorescence::Nonfluorescent,
+            metallic: Metallic::Metal,
+            drying_time: DryingTime::Slow,
+        };
+        let initial = ModelPaintCharacteristics {
+            finish: Finish::Gloss,
+            transparency: Transparency::Opaque,
+            fluorescence: Fluorescence::Nonfluorescent,
+            metallic: Metallic::Nonmetallic,
+            drying_time: DryingTime::Unspecified,
+        };
+        let edited = ModelPaintCharacteristics {
+            finish: Finish::Flat,
+            ..initial
+        };
+        let merged = merge_characteristics(&current, &initial, &edited);
+        assert_eq!(merged.finish, Finish::Flat);
+        assert_eq!(merged.transparency, Transparency::Transparent);
+        assert_eq!(merged.metallic, Metallic::Metal);
+        assert_eq!(merge_characteristics(&current, &initial, &initial), current);
+    }
+}
@@ -57,7 +57,7 @@ where
         caller: &Rc<W>,
         button_specs: Vec<PaintDisplayButtonSpec>,
     ) -> BasicPaintDisplayDialog<A, C> {
-        let dialog = new_display_dialog(&paint.name(), caller, &[]);
+        let dialog = new_display_dialog(&paint.name(), "basic_paint_display", caller, &[]);
         dialog.set_size_from_recollections("basic_paint_display", (60, 330));
         let content_area = dialog.get_content_area();
         let vbox = gtk::Box::new(gtk::Orientation::Vertical, 0);
@@ -5,6 +5,8 @@ use std::rc::Rc;
 
 use crate::basic_paint::*;
 use crate::colour_edit::*;
+use crate::colour_names::notes_with_css_colour;
+use crate::spell_check::enable_spell_checking;
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum EntryStatus {
@@ -76,6 +78,16 @@ where
         self.inform_status_changed();
     }
 
+    pub fn set_new_spec_from(&self, spec: &BasicPaintSpec<C>) {
+        *self.edited_spec.borrow_mut() = None;
+        self.colour_editor.set_rgb(spec.rgb);
+        self.name_entry.set_text(&spec.name);
+        self.notes_entry.set_text(&spec.notes);
+        self.characteristics_entry
+            .set_characteristics(Some(&spec.characteristics));
+        self.inform_status_changed();
+    }
+
     pub fn get_basic_paint_spec(&self) -> Option<BasicPaintSpec<C>> {
         if let Some(characteristics) = self.characteristics_entry.get_characteristics() {
             let name = self.name_entry.get_text();
@@ -166,6 +178,7 @@ where
 
         spe.name_entry.set_hexpand(true);
         spe.notes_entry.set_hexpand(true);
+        enable_spell_checking(&spe.notes_entry);
 
         let grid = spe.characteristics_entry.pwo();
         grid.insert_row(0);
@@ -209,6 +222,12 @@ where
         spe.colour_editor
             .connect_colour_changed(move |_| spe_c.inform_status_changed());
 
+        let spe_c = spe.clone();
+        spe.colour_editor.connect_named_colour_chosen(move |name| {
+            let notes = notes_with_css_colour(&spe_c.notes_entry.get_text(), name);
+            spe_c.notes_entry.set_text(&notes);
+        });
+
         spe
     }
 }
@@ -13,9 +13,22 @@ use pw_gix::{
 };
 
 use crate::basic_paint::*;
+use crate::blend_preview::*;
+use crate::colour::*;
+use crate::colour_difference::{close_pairs, DeltaEMetric};
+use crate::dialogue::*;
+use crate::error::*;
+use crate::graticule::{
+    append_vision_deficiency_items, is_activate_key, is_menu_key,
+    set_vision_deficiency_items_visibility, update_vision_deficiency_items, FocusMove,
+};
 pub use crate::struct_traits::SimpleCreation;
+use crate::text_search::*;
+use crate::value_ramp::*;
 
+use super::batch::*;
 use super::display::*;
+use super::filter::*;
 use super::hue_wheel::*;
 
 // FACTORY
@@ -81,6 +94,17 @@ where
         self.find_name(name).is_ok()
     }
 
+    // Pairs of paints whose colours are within "max_delta_e" of each other
+    pub fn suspected_duplicates(
+        &self,
+        max_delta_e: f64,
+        metric: DeltaEMetric,
+    ) -> Vec<(BasicPaint<C>, BasicPaint<C>, f64)> {
+        close_pairs(&self.paints.borrow(), max_delta_e, metric, |paint| {
+            paint.rgb()
+        })
+    }
+
     pub fn add_paint(&self, spec: &BasicPaintSpec<C>) -> Result<BasicPaint<C>, PaintError<C>> {
         match self.find_name(&spec.name) {
             Ok(_) => Err(PaintErrorType::AlreadyExists(spec.name.clone()).into()),
@@ -103,6 +127,22 @@ where
         }
     }
 
+    pub fn remove_paints(&self, paints: &[BasicPaint<C>]) {
+        for paint in paints.iter() {
+            self.remove_paint(paint)
+        }
+    }
+
+    // The specifications of those of "paints" that are in the factory
+    pub fn get_paint_specs_for(&self, paints: &[BasicPaint<C>]) -> Vec<BasicPaintSpec<C>> {
+        self.paints
+            .borrow()
+            .iter()
+            .filter(|paint| paints.contains(paint))
+            .map(|paint| paint.get_spec())
+            .collect()
+    }
+
     pub fn replace_paint(
         &self,
         paint: &BasicPaint<C>,
@@ -129,6 +169,8 @@ where
 }
 
 // FACTORY VIEW
+const FACTORY_VIEW_ROW_COLOURING: &str = "basic_paint_factory_view::row_colouring";
+
 #[derive(PWO, Wrapper)]
 pub struct BasicPaintFactoryViewCore<A, C>
 where
@@ -137,9 +179,13 @@ where
 {
     scrolled_window: gtk::ScrolledWindow,
     list_store: gtk::ListStore,
+    filter_model: gtk::TreeModelFilter,
+    sort_model: gtk::TreeModelSort,
     view: gtk::TreeView,
     paint_factory: BasicPaintFactory<C>,
+    filter: Rc<RefCell<PaintFilter>>,
     chosen_paint: RefCell<Option<BasicPaint<C>>>,
+    row_colouring: Cell<RowColouring>,
     spec: PhantomData<A>,
 }
 
@@ -154,27 +200,32 @@ where
         self.paint_factory.clear();
     }
 
-    pub fn get_paint_at(&self, posn: (f64, f64)) -> Option<BasicPaint<C>> {
+    // The view shows a sorted and filtered proxy of the list store
+    fn list_store_path(&self, view_path: &gtk::TreePath) -> Option<gtk::TreePath> {
+        let filter_path = self.sort_model.convert_path_to_child_path(view_path)?;
+        self.filter_model.convert_path_to_child_path(&filter_path)
+    }
+
+    fn try_get_paint_at(&self, posn: (f64, f64)) -> PaintResult<Option<BasicPaint<C>>, C> {
         let x = posn.0 as i32;
         let y = posn.1 as i32;
         if let Some(location) = self.view.get_path_at_pos(x, y) {
-            if let Some(path) = location.0 {
+            if let Some(path) = location.0.and_then(|path| self.list_store_path(&path)) {
                 if let Some(iter) = self.list_store.get_iter(&path) {
-                    let name: String = self
-                        .list_store
-                        .get_value(&iter, 0)
-                        .get()
-                        .unwrap()
-                        .unwrap_or_else(|| panic!("File: {:?} Line: {:?}", file!(), line!()));
+                    let name = self.list_store.row_string(&iter, 0)?;
                     let paint = self
                         .paint_factory
                         .get_paint(&name)
-                        .unwrap_or_else(|| panic!("File: {:?} Line: {:?}", file!(), line!()));
-                    return Some(paint);
+                        .ok_or(PaintError::from(PaintErrorType::ModelInconsistency(name)))?;
+                    return Ok(Some(paint));
                 }
             }
         };
-        None
+        Ok(None)
+    }
+
+    pub fn get_paint_at(&self, posn: (f64, f64)) -> Option<BasicPaint<C>> {
+        reported_row_value(self, self.try_get_paint_at(posn))
     }
 
     pub fn set_chosen_paint_from(&self, posn: (f64, f64)) -> Option<BasicPaint<C>> {
@@ -191,10 +242,50 @@ where
         self.paint_factory.len()
     }
 
+    pub fn row_colouring(&self) -> RowColouring {
+        self.row_colouring.get()
+    }
+
+    pub fn set_row_colouring(&self, row_colouring: RowColouring) {
+        let cols = standard_paint_tv_columns::<A, C>(row_colouring);
+        set_tv_columns(&self.view, &cols, row_colouring);
+        self.row_colouring.set(row_colouring);
+        row_colouring.remember(FACTORY_VIEW_ROW_COLOURING);
+    }
+
+    pub fn get_selected_paints(&self) -> Vec<BasicPaint<C>> {
+        let mut paints = vec![];
+        let (paths, _) = self.view.get_selection().get_selected_rows();
+        for path in paths.iter().filter_map(|path| self.list_store_path(path)) {
+            if let Some(iter) = self.list_store.get_iter(&path) {
+                let r_name = self.list_store.row_string(&iter, 0).map(Some);
+                if let Some(name) = reported_row_value(self, r_name) {
+                    if let Some(paint) = self.paint_factory.get_paint(&name) {
+                        paints.push(paint);
+                    }
+                }
+            }
+        }
+        paints
+    }
+
     pub fn get_paint(&self, name: &str) -> Option<BasicPaint<C>> {
         self.paint_factory.get_paint(name)
     }
 
+    pub fn filter(&self) -> PaintFilter {
+        self.filter.borrow().clone()
+    }
+
+    pub fn set_filter(&self, filter: PaintFilter) {
+        *self.filter.borrow_mut() = filter;
+        self.filter_model.refilter();
+    }
+
+    pub fn visible_len(&self) -> usize {
+        self.filter_model.iter_n_children(None) as usize
+    }
+
     pub fn get_paints(&self) -> Vec<BasicPaint<C>> {
         self.paint_factory.get_paints()
     }
@@ -211,6 +302,14 @@ where
         self.paint_factory.has_paint_named(name)
     }
 
+    pub fn suspected_duplicates(
+        &self,
+        max_delta_e: f64,
+        metric: DeltaEMetric,
+    ) -> Vec<(BasicPaint<C>, BasicPaint<C>, f64)> {
+        self.paint_factory.suspected_duplicates(max_delta_e, metric)
+    }
+
     pub fn add_paint(&self, spec: &BasicPaintSpec<C>) -> Result<BasicPaint<C>, PaintError<C>> {
         match self.paint_factory.add_paint(spec) {
             Ok(paint) => {
@@ -221,19 +320,26 @@ where
         }
     }
 
-    fn find_paint_named(&self, name: &str) -> Option<(i32, gtk::TreeIter)> {
-        self.list_store.find_row_where(|list_store, iter| {
-            list_store.get_value(iter, 0).get().unwrap() == Some(name)
-        })
+    pub fn remove_paint(&self, paint: &BasicPaint<C>) -> Result<(), PaintError<C>> {
+        self.remove_row_for(paint)?;
+        self.paint_factory.remove_paint(paint);
+        Ok(())
     }
 
-    pub fn remove_paint(&self, paint: &BasicPaint<C>) {
-        self.paint_factory.remove_paint(paint);
-        if let Some((_, iter)) = self.find_paint_named(&paint.name()) {
-            self.list_store.remove(&iter);
-        } else {
-            panic!("File: {} Line: {}", file!(), line!())
+    // NB: none are removed unless all of them are present
+    pub fn remove_paints(&self, paints: &[BasicPaint<C>]) -> Result<(), PaintError<C>> {
+        if let Some(paint) = paints.iter().find(|paint| !self.has_row_for(paint)) {
+            return Err(PaintErrorType::NotFound(paint.name()).into());
         }
+        for paint in paints.iter() {
+            self.remove_row_for(paint)?;
+        }
+        self.paint_factory.remove_paints(paints);
+        Ok(())
+    }
+
+    pub fn get_paint_specs_for(&self, paints: &[BasicPaint<C>]) -> Vec<BasicPaintSpec<C>> {
+        self.paint_factory.get_paint_specs_for(paints)
     }
 
     pub fn replace_paint(
@@ -241,14 +347,15 @@ where
         paint: &BasicPaint<C>,
         spec: &BasicPaintSpec<C>,
     ) -> Result<BasicPaint<C>, PaintError<C>> {
+        if !self.has_row_for(paint) {
+            return Err(PaintErrorType::NotFound(paint.name()).into());
+        }
         let new_paint = self.paint_factory.replace_paint(paint, spec)?;
-        if let Some((index, iter)) = self.find_paint_named(&paint.name()) {
+        if let Some((index, iter)) = self.find_row_named(&paint.name()) {
             self.list_store.remove(&iter);
             self.list_store.insert_row(index, &new_paint.tv_rows());
-            return Ok(new_paint);
-        } else {
-            panic!("File: {} Line: {}", file!(), line!())
-        }
+        };
+        Ok(new_paint)
     }
 
     pub fn connect_button_press_event<
@@ -261,6 +368,20 @@ where
     }
 }
 
+impl<A, C> PaintListRows<C, BasicPaint<C>> for BasicPaintFactoryViewCore<A, C>
+where
+    A: ColourAttributesInterface + 'static,
+    C: CharacteristicsInterface + 'static,
+{
+    fn rows_list_store(&self) -> &gtk::ListStore {
+        &self.list_store
+    }
+
+    fn rows_paints(&self) -> Vec<BasicPaint<C>> {
+        self.paint_factory.get_paints()
+    }
+}
+
 pub type BasicPaintFactoryView<A, C> = Rc<BasicPaintFactoryViewCore<A, C>>;
 
 pub trait BasicPaintFactoryViewInterface<A, C>
@@ -279,34 +400,50 @@ where
     fn create() -> BasicPaintFactoryView<A, C> {
         let len = BasicPaint::<C>::tv_row_len();
         let list_store = gtk::ListStore::new(&STANDARD_PAINT_ROW_SPEC[0..len]);
-        let view = gtk::TreeView::with_model(&list_store.clone());
+        let filter_model = gtk::TreeModelFilter::new(&list_store, None);
+        let sort_model = gtk::TreeModelSort::new(&filter_model);
+        let view = gtk::TreeView::with_model(&sort_model);
         view.set_headers_visible(true);
-        view.get_selection().set_mode(gtk::SelectionMode::None);
+        view.get_selection().set_mode(gtk::SelectionMode::Multiple);
 
         let adj: Option<&gtk::Adjustment> = None;
         let mspl = Rc::new(BasicPaintFactoryViewCore::<A, C> {
             scrolled_window: gtk::ScrolledWindow::new(adj, adj),
             list_store: list_store,
+            filter_model: filter_model,
+            sort_model: sort_model,
             paint_factory: BasicPaintFactory::<C>::create(),
             view: view,
+            filter: Rc::new(RefCell::new(PaintFilter::default())),
             chosen_paint: RefCell::new(None),
+            row_colouring: Cell::new(RowColouring::default()),
             spec: PhantomData,
         });
 
-        mspl.view.append_column(&simple_text_column(
-            "Name", SP_NAME, SP_NAME, SP_RGB, SP_RGB_FG, -1, true,
-        ));
-        mspl.view.append_column(&simple_text_column(
-            "Notes", SP_NOTES, SP_NOTES, SP_RGB, SP_RGB_FG, -1, true,
-        ));
-        for col in A::tv_columns() {
-            mspl.view.append_column(&col);
-        }
-        for col in C::tv_columns(SP_CHARS_0) {
-            mspl.view.append_column(&col);
-        }
+        // Rows that can't (yet) be matched to a paint are left visible
+        let paint_factory = mspl.paint_factory.clone();
+        let filter = mspl.filter.clone();
+        let normaliser = SearchNormaliser::default();
+        mspl.filter_model.set_visible_func(move |model, iter| {
+            let filter = filter.borrow();
+            if !filter.is_active() {
+                return true;
+            }
+            match model.get_value(iter, SP_NAME).get::<String>() {
+                Ok(Some(name)) => match paint_factory.get_paint(&name) {
+                    Some(paint) => filter.matches(&paint, &normaliser),
+                    None => true,
+                },
+                _ => true,
+            }
+        });
 
-        mspl.view.show_all();
+        mspl.set_row_colouring(RowColouring::recall(FACTORY_VIEW_ROW_COLOURING));
+        set_normalised_search(
+            &mspl.view,
+            &[SP_NAME, SP_NOTES],
+            SearchNormaliser::default(),
+        );
 
         mspl.scrolled_window.add(&mspl.view.clone());
         mspl.scrolled_window.show_all();
@@ -324,13 +461,33 @@ where
 {
     notebook: gtk::Notebook,
     paint_factory_view: BasicPaintFactoryView<A, C>,
+    filtered_view: FilteredBasicPaintFactoryView<A, C>,
     hue_attr_wheels: Vec<BasicPaintHueAttrWheel<C>>,
     chosen_paint: RefCell<Option<BasicPaint<C>>>,
+    chosen_wheel: RefCell<Option<BasicPaintHueAttrWheel<C>>>,
     popup_menu: WrappedMenu,
     initiate_edit_ok: Cell<bool>,
     paint_dialogs: RefCell<HashMap<u32, BasicPaintDisplayDialog<A, C>>>,
     paint_removed_callbacks: RefCell<Vec<Box<dyn Fn(&BasicPaint<C>)>>>,
     edit_paint_callbacks: RefCell<Vec<Box<dyn Fn(&BasicPaint<C>)>>>,
+    duplicate_paint_callbacks: RefCell<Vec<Box<dyn Fn(&BasicPaint<C>)>>>,
+    paint_replaced_callbacks: RefCell<Vec<Box<dyn Fn(&BasicPaint<C>, &BasicPaint<C>)>>>,
+    export_paints_callbacks: RefCell<Vec<Box<dyn Fn(&[BasicPaint<C>])>>>,
+    last_batch_edit: RefCell<Vec<(BasicPaint<C>, BasicPaintSpec<C>)>>,
+}
+
+impl<A, C> DisplayDialogHost for BasicPaintFactoryDisplayCore<A, C>
+where
+    A: ColourAttributesInterface + 'static,
+    C: CharacteristicsInterface + 'static,
+{
+    fn display_dialogs(&self) -> Vec<gtk::Dialog> {
+        self.paint_dialogs
+            .borrow()
+            .values()
+            .map(|dialog| dialog.dialog())
+            .collect()
+    }
 }
 
 impl<A, C> BasicPaintFactoryDisplayCore<A, C>
@@ -347,7 +504,9 @@ where
             dialog.close();
         }
         *self.chosen_paint.borrow_mut() = None;
+        self.last_batch_edit.borrow_mut().clear();
         self.paint_factory_view.clear();
+        self.filtered_view.set_filter(&PaintFilter::default());
         for wheel in self.hue_attr_wheels.iter() {
             wheel.clear()
         }
@@ -385,6 +544,9 @@ where
         spec: &BasicPaintSpec<C>,
     ) -> Result<BasicPaint<C>, PaintError<C>> {
         let new_paint = self.paint_factory_view.replace_paint(old_paint, spec)?;
+        self.last_batch_edit
+            .borrow_mut()
+            .retain(|(p, _)| p != old_paint);
         for wheel in self.hue_attr_wheels.iter() {
             wheel.replace_paint(old_paint, &new_paint)
         }
@@ -392,8 +554,77 @@ where
         Ok(new_paint)
     }
 
-    fn remove_paint(&self, paint: &BasicPaint<C>) {
-        self.paint_factory_view.remove_paint(paint);
+    // Returns the edits that will reverse those applied
+    fn apply_batch_edits(
+        &self,
+        edits: &Vec<(BasicPaint<C>, BasicPaintSpec<C>)>,
+    ) -> Vec<(BasicPaint<C>, BasicPaintSpec<C>)> {
+        let mut reversals = vec![];
+        for (old_paint, spec) in edits.iter() {
+            match self.replace_paint(old_paint, spec) {
+                Ok(new_paint) => {
+                    reversals.push((new_paint.clone(), old_paint.get_spec()));
+                    self.inform_paint_replaced(old_paint, &new_paint);
+                }
+                Err(err) => self.report_error("Failed to change characteristics", &err),
+            }
+        }
+        reversals
+    }
+
+    pub fn batch_edit_characteristics(&self) {
+        let paints = self.paint_factory_view.get_selected_paints();
+        if paints.len() == 0 {
+            self.inform_user("No paints are selected.", None);
+            return;
+        };
+        self.batch_edit_characteristics_of(&paints);
+    }
+
+    pub fn batch_edit_characteristics_of(&self, paints: &[BasicPaint<C>]) {
+        let paints = paints.to_vec();
+        let dialog = CharacteristicsBatchDialog::<C>::create(&paints, &self.paint_factory_view);
+        let edits = dialog.get_batch_edits();
+        if edits.len() > 0 {
+            *self.last_batch_edit.borrow_mut() = self.apply_batch_edits(&edits);
+        }
+    }
+
+    pub fn audit_selected_value_ramp(&self) {
+        let members: Vec<(String, RGB)> = self
+            .paint_factory_view
+            .get_selected_paints()
+            .iter()
+            .map(|paint| (paint.name(), paint.rgb()))
+            .collect();
+        show_value_ramp_audit(&members, &self.paint_factory_view);
+    }
+
+    pub fn preview_selected_blend(&self) {
+        let paints = self.paint_factory_view.get_selected_paints();
+        if paints.len() == 2 {
+            show_blend_preview(
+                (paints[0].name().as_str(), &paints[0].rgb()),
+                (paints[1].name().as_str(), &paints[1].rgb()),
+                &self.paint_factory_view,
+            );
+        }
+    }
+
+    pub fn undo_last_batch_edit(&self) {
+        let edits: Vec<(BasicPaint<C>, BasicPaintSpec<C>)> =
+            self.last_batch_edit.borrow_mut().drain(..).collect();
+        self.apply_batch_edits(&edits);
+    }
+
+    pub fn remove_paint(&self, paint: &BasicPaint<C>) {
+        if let Err(err) = self.paint_factory_view.remove_paint(paint) {
+            self.report_error("Failed to remove paint", &err);
+            return;
+        }
+        self.last_batch_edit
+            .borrow_mut()
+            .retain(|(p, _)| p != paint);
         for wheel in self.hue_attr_wheels.iter() {
             wheel.remove_paint(paint)
         }
@@ -401,6 +632,75 @@ where
         self.inform_paint_removed(paint);
     }
 
+    pub fn remove_paints(&self, paints: &[BasicPaint<C>]) {
+        if let Err(err) = self.paint_factory_view.remove_paints(paints) {
+            self.report_error("Failed to remove paints", &err);
+            return;
+        }
+        self.last_batch_edit
+            .borrow_mut()
+            .retain(|(p, _)| !paints.contains(p));
+        for paint in paints.iter() {
+            for wheel in self.hue_attr_wheels.iter() {
+                wheel.remove_paint(paint)
+            }
+            self.close_dialogs_for_paint(paint);
+            self.inform_paint_removed(paint);
+        }
+    }
+
+    pub fn remove_selected_paints_after_confirmation(&self) {
+        let paints = self.paint_factory_view.get_selected_paints();
+        if paints.len() == 0 {
+            self.inform_user("No paints are selected.", None);
+            return;
+        };
+        let question = format!("Confirm remove {} selected paints?", paints.len());
+        let names: Vec<String> = paints.iter().map(|paint| paint.name()).collect();
+        if self.ask_confirm_action(&question, Some(&names.join("\n"))) {
+            self.remove_paints(&paints)
+        }
+    }
+
+    pub fn export_selected_paints(&self) {
+        let paints = self.paint_factory_view.get_selected_paints();
+        if paints.len() == 0 {
+            self.inform_user("No paints are selected.", None);
+            return;
+        };
+        self.inform_export_paints(&paints);
+    }
+
+    pub fn get_paint_specs_for(&self, paints: &[BasicPaint<C>]) -> Vec<BasicPaintSpec<C>> {
+        self.paint_factory_view.get_paint_specs_for(paints)
+    }
+
+    fn prepare_wheel_popup_menu(
+        &self,
+        wheel: &BasicPaintHueAttrWheel<C>,
+        o_paint: Option<BasicPaint<C>>,
+    ) {
+        *self.chosen_wheel.borrow_mut() = Some(wheel.clone());
+        if o_paint.is_some() {
+            self.popup_menu
+                .set_sensitivities(self.initiate_edit_ok.get(), &["edit", "duplicate"]);
+            self.popup_menu.set_sensitivities(true, &["info", "remove"]);
+        } else {
+            self.popup_menu
+                .set_sensitivities(false, &["edit", "duplicate", "info", "remove"]);
+        };
+        *self.chosen_paint.borrow_mut() = o_paint;
+        self.update_batch_edit_sensitivities();
+        let has_dialogs = self.paint_dialogs.borrow().len() > 0;
+        self.popup_menu
+            .set_sensitivities(has_dialogs, &DISPLAY_DIALOGS_MENU_KEYS);
+        self.popup_menu
+            .set_visibilities(false, &ROW_COLOURING_MENU_KEYS);
+        self.popup_menu.set_visibilities(true, &["export_image"]);
+        set_vision_deficiency_items_visibility(&self.popup_menu, true);
+        update_vision_deficiency_items(&self.popup_menu, wheel.vision_deficiency());
+    }
+
     fn remove_paint_after_confirmation(&self, paint: &BasicPaint<C>) {
         let question = format!("Confirm remove {}?", paint.name());
         if self.ask_confirm_action(&question, None) {
@@ -416,6 +716,23 @@ where
         self.paint_factory_view.matches_paint_specs(specs)
     }
 
+    pub fn get_paint(&self, name: &str) -> Option<BasicPaint<C>> {
+        self.paint_factory_view.get_paint(name)
+    }
+
+    pub fn has_paint_named(&self, name: &str) -> bool {
+        self.paint_factory_view.has_paint_named(name)
+    }
+
+    pub fn suspected_duplicates(
+        &self,
+        max_delta_e: f64,
+        metric: DeltaEMetric,
+    ) -> Vec<(BasicPaint<C>, BasicPaint<C>, f64)> {
+        self.paint_factory_view
+            .suspected_duplicates(max_delta_e, metric)
+    }
+
     pub fn connect_edit_paint<F: 'static + Fn(&BasicPaint<C>)>(&self, callback: F) {
         self.edit_paint_callbacks
             .borrow_mut()
@@ -428,6 +745,71 @@ where
         }
     }
 
+    pub fn connect_duplicate_paint<F: 'static + Fn(&BasicPaint<C>)>(&self, callback: F) {
+        self.duplicate_paint_callbacks
+            .borrow_mut()
+            .push(Box::new(callback))
+    }
+
+    fn inform_duplicate_paint(&self, paint: &BasicPaint<C>) {
+        for callback in self.duplicate_paint_callbacks.borrow().iter() {
+            callback(&paint);
+        }
+    }
+
+    pub fn connect_paint_replaced<F: 'static + Fn(&BasicPaint<C>, &BasicPaint<C>)>(
+        &self,
+        callback: F,
+    ) {
+        self.paint_replaced_callbacks
+            .borrow_mut()
+            .push(Box::new(callback))
+    }
+
+    fn inform_paint_replaced(&self, old_paint: &BasicPaint<C>, new_paint: &BasicPaint<C>) {
+        for callback in self.paint_replaced_callbacks.borrow().iter() {
+            callback(old_paint, new_paint);
+        }
+    }
+
+    // For saving paints (e.g. the selected ones) as a new collection file
+    pub fn connect_export_paints<F: 'static + Fn(&[BasicPaint<C>])>(&self, callback: F) {
+        self.export_paints_callbacks
+            .borrow_mut()
+            .push(Box::new(callback))
+    }
+
+    fn inform_export_paints(&self, paints: &[BasicPaint<C>]) {
+        for callback in self.export_paints_callbacks.borrow().iter() {
+            callback(paints);
+        }
+    }
+
+    fn update_batch_edit_sensitivities(&self) {
+        let edit_ok = self.initiate_edit_ok.get();
+        let have_selection = self.paint_factory_view.get_selected_paints().len() > 0;
+        self.popup_menu
+            .set_sensitivities(edit_ok && have_selection, &["batch_edit"]);
+        self.popup_menu.set_sensitivities(
+            edit_ok && self.last_batch_edit.borrow().len() > 0,
+            &["undo_batch_edit"],
+        );
+        self.popup_menu.set_sensitivities(
+            self.paint_factory_view.get_selected_paints().len() > 1,
+            &["audit_ramp"],
+        );
+        self.popup_menu.set_sensitivities(
+            self.paint_factory_view.get_selected_paints().len() == 2,
+            &["blend_preview"],
+        );
+        self.popup_menu
+            .set_sensitivities(have_selection, &["remove_selected", "export_selected"]);
+        self.popup_menu.set_visibilities(
+            self.export_paints_callbacks.borrow().len() > 0,
+            &["export_selected"],
+        );
+    }
+
     pub fn connect_paint_removed<F: 'static + Fn(&BasicPaint<C>)>(&self, callback: F) {
         self.paint_removed_callbacks
             .borrow_mut()
@@ -454,8 +836,9 @@ where
         notebook.set_scrollable(true);
         notebook.popup_enable();
         let paint_factory_view = BasicPaintFactoryView::<A, C>::create();
+        let filtered_view = FilteredBasicPaintFactoryView::<A, C>::create(&paint_factory_view);
         notebook.append_page(
-            &paint_factory_view.pwo(),
+            &filtered_view.pwo(),
             Some(&gtk::Label::new(Some("Paint List"))),
         );
         let mut hue_attr_wheels = Vec::new();
@@ -470,13 +853,19 @@ where
         let bpf = Rc::new(BasicPaintFactoryDisplayCore::<A, C> {
             notebook: notebook,
             paint_factory_view: paint_factory_view,
+            filtered_view: filtered_view,
             hue_attr_wheels: hue_attr_wheels,
             chosen_paint: RefCell::new(None),
+            chosen_wheel: RefCell::new(None),
             popup_menu: WrappedMenu::new(&vec![]),
             initiate_edit_ok: Cell::new(false),
             paint_dialogs: RefCell::new(HashMap::new()),
             paint_removed_callbacks: RefCell::new(Vec::new()),
             edit_paint_callbacks: RefCell::new(Vec::new()),
+            duplicate_paint_callbacks: RefCell::new(Vec::new()),
+            paint_replaced_callbacks: RefCell::new(Vec::new()),
+            export_paints_callbacks: RefCell::new(Vec::new()),
+            last_batch_edit: RefCell::new(Vec::new()),
         });
 
         let bpf_c = bpf.clone();
@@ -491,45 +880,59 @@ where
         let bpf_c = bpf.clone();
         bpf.popup_menu
             .append_item(
-                "info",
-                "Paint Information",
-                "Display this paint's information",
+                "duplicate",
+                "Duplicate Paint",
+                "Load a copy of this paint into the editor as the starting point for a new paint",
             )
             .connect_activate(move |_| {
                 if let Some(ref paint) = *bpf_c.chosen_paint.borrow() {
-                    let bpf_c_c = bpf_c.clone();
-                    let paint_c = paint.clone();
-                    let edit_btn_spec = PaintDisplayButtonSpec {
-                        label: "Edit".to_string(),
-                        tooltip_text: "load this paint into the editor.".to_string(),
-                        callback: Box::new(move || bpf_c_c.inform_edit_paint(&paint_c)),
-                    };
-                    let bpf_c_c = bpf_c.clone();
-                    let paint_c = paint.clone();
-                    let remove_btn_spec = PaintDisplayButtonSpec {
-                        label: "Remove".to_string(),
-                        tooltip_text: "Remove this paint from the collection.".to_string(),
-                        callback: Box::new(move || {
-                            bpf_c_c.remove_paint_after_confirmation(&paint_c)
-                        }),
-                    };
-                    let dialog = BasicPaintDisplayDialog::<A, C>::create(
-                        &paint,
-                        &bpf_c,
-                        vec![edit_btn_spec, remove_btn_spec],
-                    );
-                    let bpf_c_c = bpf_c.clone();
-                    dialog.connect_destroyed(move |id| {
-                        bpf_c_c.paint_dialogs.borrow_mut().remove(&id);
-                    });
-                    bpf_c
-                        .paint_dialogs
-                        .borrow_mut()
-                        .insert(dialog.id_no(), dialog.clone());
-                    dialog.show();
+                    bpf_c.inform_duplicate_paint(paint)
                 }
             });
 
+        let bpf_c = bpf.clone();
+        let show_chosen_paint_info: Rc<dyn Fn()> = Rc::new(move || {
+            if let Some(ref paint) = *bpf_c.chosen_paint.borrow() {
+                let bpf_c_c = bpf_c.clone();
+                let paint_c = paint.clone();
+                let edit_btn_spec = PaintDisplayButtonSpec {
+                    label: "Edit".to_string(),
+                    tooltip_text: "load this paint into the editor.".to_string(),
+                    callback: Box::new(move || bpf_c_c.inform_edit_paint(&paint_c)),
+                };
+                let bpf_c_c = bpf_c.clone();
+                let paint_c = paint.clone();
+                let remove_btn_spec = PaintDisplayButtonSpec {
+                    label: "Remove".to_string(),
+                    tooltip_text: "Remove this paint from the collection.".to_string(),
+                    callback: Box::new(move || bpf_c_c.remove_paint_after_confirmation(&paint_c)),
+                };
+                let dialog = BasicPaintDisplayDialog::<A, C>::create(
+                    &paint,
+                    &bpf_c,
+                    vec![edit_btn_spec, remove_btn_spec],
+                );
+                let bpf_c_c = bpf_c.clone();
+                dialog.connect_destroyed(move |id| {
+                    bpf_c_c.paint_dialogs.borrow_mut().remove(&id);
+                });
+                bpf_c
+                    .paint_dialogs
+                    .borrow_mut()
+                    .insert(dialog.id_no(), dialog.clone());
+                dialog.show();
+            }
+        });
+
+        let show_chosen_paint_info_c = show_chosen_paint_info.clone();
+        bpf.popup_menu
+            .append_item(
+                "info",
+                "Paint Information",
+                "Display this paint's information",
+            )
+            .connect_activate(move |_| show_chosen_paint_info_c());
+
         let bpf_c = bpf.clone();
         bpf.popup_menu
             .append_item(
@@ -543,15 +946,105 @@ where
                 }
             });
 
+        let bpf_c = bpf.clone();
+        bpf.popup_menu
+            .append_item(
+                "remove_selected",
+                "Remove Selected Paints",
+                "Remove all of the selected paints from the collection",
+            )
+            .connect_activate(move |_| bpf_c.remove_selected_paints_after_confirmation());
+
+        let bpf_c = bpf.clone();
+        bpf.popup_menu
+            .append_item(
+                "export_selected",
+                "Export Selected Paints...",
+                "Save the selected paints as a new collection file",
+            )
+            .connect_activate(move |_| bpf_c.export_selected_paints());
+
+        let bpf_c = bpf.clone();
+        bpf.popup_menu
+            .append_item(
+                "batch_edit",
+                "Edit Selected Characteristics",
+                "Change characteristics of all of the selected paints at once",
+            )
+            .connect_activate(move |_| bpf_c.batch_edit_characteristics());
+
+        let bpf_c = bpf.clone();
+        bpf.popup_menu
+            .append_item(
+                "undo_batch_edit",
+                "Undo Characteristics Edit",
+                "Restore the characteristics changed by the last batch edit",
+            )
+            .connect_activate(move |_| bpf_c.undo_last_batch_edit());
+
+        let bpf_c = bpf.clone();
+        bpf.popup_menu
+            .append_item(
+                "audit_ramp",
+                "Audit Value Ramp",
+                "Report the value steps between the selected paints from darkest to lightest",
+            )
+            .connect_activate(move |_| bpf_c.audit_selected_value_ramp());
+
+        let bpf_c = bpf.clone();
+        bpf.popup_menu
+            .append_item(
+                "blend_preview",
+                "Preview Blend",
+                "Show the mixtures of the two selected paints at evenly spaced ratios",
+            )
+            .connect_activate(move |_| bpf_c.preview_selected_blend());
+
+        for row_colouring in RowColouring::values() {
+            let bpf_c = bpf.clone();
+            bpf.popup_menu
+                .append_item(
+                    row_colouring.name(),
+                    row_colouring.label(),
+                    row_colouring.tooltip_text(),
+                )
+                .connect_activate(move |_| {
+                    bpf_c.paint_factory_view.set_row_colouring(row_colouring)
+                });
+        }
+
+        let bpf_c = bpf.clone();
+        bpf.popup_menu
+            .append_item(
+                "export_image",
+                "Export Image...",
+                "Save an image (PNG or SVG) of this wheel",
+            )
+            .connect_activate(move |_| {
+                if let Some(ref wheel) = *bpf_c.chosen_wheel.borrow() {
+                    wheel.export_image();
+                }
+            });
+
+        let bpf_c = bpf.clone();
+        append_vision_deficiency_items(&bpf.popup_menu, move |o_deficiency| {
+            if let Some(ref wheel) = *bpf_c.chosen_wheel.borrow() {
+                wheel.set_vision_deficiency(o_deficiency);
+            }
+        });
+
+        append_display_dialogs_items(&bpf.popup_menu, &bpf);
+
         let bpf_c = bpf.clone();
         bpf.paint_factory_view
             .connect_button_press_event(move |_, event| {
                 if event.get_button() == 3 {
                     if let Some(paint) = bpf_c.paint_factory_view.get_paint_at(event.get_position())
                     {
-                        bpf_c
-                            .popup_menu
-                            .set_sensitivities(bpf_c.initiate_edit_ok.get(), &["edit"]);
+                        bpf_c.popup_menu.set_sensitivities(
+                            bpf_c.initiate_edit_ok.get(),
+                            &["edit", "duplicate"],
+                        );
                         bpf_c
                             .popup_menu
                             .set_sensitivities(true, &["info", "remove"]);
@@ -559,9 +1052,19 @@ where
                     } else {
                         bpf_c
                             .popup_menu
-                            .set_sensitivities(false, &["edit", "info", "remove"]);
+                            .set_sensitivities(false, &["edit", "duplicate", "info", "remove"]);
                         *bpf_c.chosen_paint.borrow_mut() = None;
                     };
+                    bpf_c.update_batch_edit_sensitivities();
+                    let has_dialogs = bpf_c.paint_dialogs.borrow().len() > 0;
+                    bpf_c
+                        .popup_menu
+                        .set_sensitivities(has_dialogs, &DISPLAY_DIALOGS_MENU_KEYS);
+                    bpf_c
+                        .popup_menu
+                        .set_visibilities(true, &ROW_COLOURING_MENU_KEYS);
+                    bpf_c.popup_menu.set_visibilities(false, &["export_image"]);
+                    set_vision_deficiency_items_visibility(&bpf_c.popup_menu, false);
                     bpf_c.popup_menu.popup_at_event(event);
                     return Inhibit(true);
                 };
@@ -573,25 +1076,35 @@ where
             let wheel_c = wheel.clone();
             wheel.connect_button_press_event(move |_, event| {
                 if event.get_button() == 3 {
-                    if let Some(paint) = wheel_c.get_paint_at(event.get_position()) {
-                        bpf_c
-                            .popup_menu
-                            .set_sensitivities(bpf_c.initiate_edit_ok.get(), &["edit"]);
-                        bpf_c
-                            .popup_menu
-                            .set_sensitivities(true, &["info", "remove"]);
-                        *bpf_c.chosen_paint.borrow_mut() = Some(paint);
-                    } else {
-                        bpf_c
-                            .popup_menu
-                            .set_sensitivities(false, &["edit", "info", "remove"]);
-                        *bpf_c.chosen_paint.borrow_mut() = None;
-                    };
+                    bpf_c.prepare_wheel_popup_menu(
+                        &wheel_c,
+                        wheel_c.get_paint_at(event.get_position()),
+                    );
                     bpf_c.popup_menu.popup_at_event(event);
                     return Inhibit(true);
                 };
                 Inhibit(false)
             });
+            let bpf_c = bpf.clone();
+            let wheel_c = wheel.clone();
+            let show_chosen_paint_info_c = show_chosen_paint_info.clone();
+            wheel.connect_key_press_event(move |_, event| {
+                if let Some(focus_move) = FocusMove::from_key(&event.get_keyval()) {
+                    return Inhibit(wheel_c.move_focus(focus_move));
+                }
+                if is_activate_key(event) {
+                    if let Some(paint) = wheel_c.focused_paint() {
+                        *bpf_c.chosen_paint.borrow_mut() = Some(paint);
+                        show_chosen_paint_info_c();
+                        return Inhibit(true);
+                    }
+                } else if is_menu_key(event) {
+                    bpf_c.prepare_wheel_popup_menu(&wheel_c, wheel_c.focused_paint());
+                    wheel_c.popup_menu_at_focus(&bpf_c.popup_menu.pwo());
+                    return Inhibit(true);
+                }
+                Inhibit(false)
+            });
         }
 
         bpf
@@ -600,5 +1113,36 @@ where
 
 #[cfg(test)]
 mod tests {
-    //use super::*;
+    use super::*;
+    use crate::model_paint::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn factory_bulk_operations() {
+        let factory = BasicPaintFactory::<ModelPaintCharacteristics>::create();
+        for line in [
+            "ModelPaint(name=\"XF 1: Flat Black *\", rgb=#2D2B30, transparency=\"O\", finish=\"F\", metallic=\"NM\", fluorescence=\"NF\", notes=\"\")",
+            "ModelPaint(name=\"XF 2: Flat White *\", rgb=#FEFEFE, transparency=\"O\", finish=\"F\", metallic=\"NM\", fluorescence=\"NF\", notes=\"\")",
+            "ModelPaint(name=\"XF 3: Flat Yellow *\", rgb=#F7E318, transparency=\"O\", finish=\"F\", metallic=\"NM\", fluorescence=\"NF\", notes=\"\")",
+        ]
+        .iter()
+        {
+            let spec = BasicPaintSpec::<ModelPaintCharacteristics>::from_str(line).unwrap();
+            factory.add_paint(&spec).unwrap();
+        }
+        let chosen = vec![
+            factory.get_paint("XF 3: Flat Yellow *").unwrap(),
+            factory.get_paint("XF 1: Flat Black *").unwrap(),
+        ];
+        let names: Vec<String> = factory
+            .get_paint_specs_for(&chosen)
+            .iter()
+            .map(|spec| spec.name.clone())
+            .collect();
+        assert_eq!(names, vec!["XF 1: Flat Black *", "XF 3: Flat Yellow *"]);
+        factory.remove_paints(&chosen);
+        assert_eq!(factory.len(), 1);
+        assert!(factory.has_paint_named("XF 2: Flat White *"));
+        assert_eq!(factory.get_paint_specs_for(&chosen).len(), 0);
+    }
 }
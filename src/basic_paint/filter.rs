@@ -0,0 +1,244 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+use std::rc::Rc;
+
+use pw_gix::{
+    gtk::{self, prelude::*},
+    wrapper::*,
+};
+
+use crate::basic_paint::factory::*;
+use crate::basic_paint::*;
+use crate::text_search::*;
+
+// PAINT FILTER
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaintFilter {
+    pub text: String,
+    pub value: (f64, f64),
+    pub chroma: (f64, f64),
+    pub warmth: (f64, f64),
+}
+
+impl Default for PaintFilter {
+    fn default() -> PaintFilter {
+        PaintFilter {
+            text: String::new(),
+            value: (0.0, 1.0),
+            chroma: (0.0, 1.0),
+            warmth: (0.0, 1.0),
+        }
+    }
+}
+
+impl PaintFilter {
+    pub fn is_active(&self) -> bool {
+        *self != PaintFilter::default()
+    }
+
+    // Text is matched (as for interactive searches) against the name or
+    // the notes and the ranges are inclusive
+    pub fn matches<C, P>(&self, paint: &P, normaliser: &SearchNormaliser) -> bool
+    where
+        C: CharacteristicsInterface,
+        P: BasicPaintInterface<C>,
+    {
+        let in_range = |x: f64, range: (f64, f64)| x >= range.0 && x <= range.1;
+        (normaliser.matches(&paint.name(), &self.text)
+            || normaliser.matches(&paint.notes(), &self.text))
+            && in_range(paint.value(), self.value)
+            && in_range(paint.chroma(), self.chroma)
+            && in_range(paint.warmth(), self.warmth)
+    }
+}
+
+// FILTERED FACTORY VIEW
+struct RangeEntry {
+    min_spin: gtk::SpinButton,
+    max_spin: gtk::SpinButton,
+}
+
+impl RangeEntry {
+    fn new() -> RangeEntry {
+        let min_spin = gtk::SpinButton::with_range(0.0, 1.0, 0.05);
+        let max_spin = gtk::SpinButton::with_range(0.0, 1.0, 0.05);
+        min_spin.set_digits(2);
+        max_spin.set_digits(2);
+        max_spin.set_value(1.0);
+        RangeEntry { min_spin, max_spin }
+    }
+
+    fn pack_into(&self, hbox: &gtk::Box, label: &str) {
+        hbox.pack_start(&gtk::Label::new(Some(label)), false, false, 0);
+        hbox.pack_start(&self.min_spin, false, false, 0);
+        hbox.pack_start(&gtk::Label::new(Some("-")), false, false, 0);
+        hbox.pack_start(&self.max_spin, false, false, 0);
+    }
+
+    fn range(&self) -> (f64, f64) {
+        (self.min_spin.get_value(), self.max_spin.get_value())
+    }
+
+    fn set_range(&self, range: (f64, f64)) {
+        self.min_spin.set_value(range.0);
+        self.max_spin.set_value(range.1);
+    }
+}
+
+#[derive(PWO, Wrapper)]
+pub struct FilteredBasicPaintFactoryViewCore<A, C>
+where
+    A: ColourAttributesInterface + 'static,
+    C: CharacteristicsInterface + 'static,
+{
+    vbox: gtk::Box,
+    text_entry: gtk::SearchEntry,
+    value_range: RangeEntry,
+    chroma_range: RangeEntry,
+    warmth_range: RangeEntry,
+    clear_btn: gtk::Button,
+    paint_factory_view: BasicPaintFactoryView<A, C>,
+}
+
+impl<A, C> FilteredBasicPaintFactoryViewCore<A, C>
+where
+    A: ColourAttributesInterface + 'static,
+    C: CharacteristicsInterface + 'static,
+{
+    pub fn paint_factory_view(&self) -> BasicPaintFactoryView<A, C> {
+        self.paint_factory_view.clone()
+    }
+
+    pub fn filter(&self) -> PaintFilter {
+        PaintFilter {
+            text: self.text_entry.get_text().to_string(),
+            value: self.value_range.range(),
+            chroma: self.chroma_range.range(),
+            warmth: self.warmth_range.range(),
+        }
+    }
+
+    pub fn set_filter(&self, filter: &PaintFilter) {
+        self.text_entry.set_text(&filter.text);
+        self.value_range.set_range(filter.value);
+        self.chroma_range.set_range(filter.chroma);
+        self.warmth_range.set_range(filter.warmth);
+        self.apply_filter();
+    }
+
+    fn apply_filter(&self) {
+        let filter = self.filter();
+        self.clear_btn.set_sensitive(filter.is_active());
+        self.paint_factory_view.set_filter(filter);
+    }
+}
+
+pub type FilteredBasicPaintFactoryView<A, C> = Rc<FilteredBasicPaintFactoryViewCore<A, C>>;
+
+pub trait FilteredBasicPaintFactoryViewInterface<A, C>
+where
+    A: ColourAttributesInterface + 'static,
+    C: CharacteristicsInterface + 'static,
+{
+    fn create(
+        paint_factory_view: &BasicPaintFactoryView<A, C>,
+    ) -> FilteredBasicPaintFactoryView<A, C>;
+}
+
+impl<A, C> FilteredBasicPaintFactoryViewInterface<A, C> for FilteredBasicPaintFactoryView<A, C>
+where
+    A: ColourAttributesInterface + 'static,
+    C: CharacteristicsInterface + 'static,
+{
+    fn create(
+        paint_factory_view: &BasicPaintFactoryView<A, C>,
+    ) -> FilteredBasicPaintFactoryView<A, C> {
+        let fbpfv = Rc::new(FilteredBasicPaintFactoryViewCore::<A, C> {
+            vbox: gtk::Box::new(gtk::Orientation::Vertical, 0),
+            text_entry: gtk::SearchEntry::new(),
+            value_range: RangeEntry::new(),
+            chroma_range: RangeEntry::new(),
+            warmth_range: RangeEntry::new(),
+            clear_btn: gtk::Button::with_label("Clear"),
+            paint_factory_view: paint_factory_view.clone(),
+        });
+        fbpfv.text_entry.set_tooltip_text(Some(
+            "Show only paints whose name or notes contain this text",
+        ));
+        fbpfv
+            .clear_btn
+            .set_tooltip_text(Some("Remove the filter and show all paints"));
+        fbpfv.clear_btn.set_sensitive(false);
+
+        let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 2);
+        hbox.pack_start(&gtk::Label::new(Some("Filter:")), false, false, 0);
+        hbox.pack_start(&fbpfv.text_entry, true, true, 0);
+        hbox.pack_start(&fbpfv.clear_btn, false, false, 0);
+        fbpfv.vbox.pack_start(&hbox, false, false, 0);
+        let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 2);
+        fbpfv.value_range.pack_into(&hbox, "Value:");
+        fbpfv.chroma_range.pack_into(&hbox, " Chroma:");
+        fbpfv.warmth_range.pack_into(&hbox, " Warmth:");
+        fbpfv.vbox.pack_start(&hbox, false, false, 0);
+        fbpfv
+            .vbox
+            .pack_start(&fbpfv.paint_factory_view.pwo(), true, true, 0);
+        fbpfv.vbox.show_all();
+
+        let fbpfv_c = fbpfv.clone();
+        fbpfv
+            .text_entry
+            .connect_search_changed(move |_| fbpfv_c.apply_filter());
+        for range in [&fbpfv.value_range, &fbpfv.chroma_range, &fbpfv.warmth_range].iter() {
+            for spin in [&range.min_spin, &range.max_spin].iter() {
+                let fbpfv_c = fbpfv.clone();
+                spin.connect_value_changed(move |_| fbpfv_c.apply_filter());
+            }
+        }
+        let fbpfv_c = fbpfv.clone();
+        fbpfv
+            .clear_btn
+            .connect_clicked(move |_| fbpfv_c.set_filter(&PaintFilter::default()));
+
+        fbpfv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::art_paint::*;
+    use crate::colour::*;
+
+    #[test]
+    fn paint_filter_matches() {
+        let spec = BasicPaintSpec::<ArtPaintCharacteristics> {
+            rgb: RGB::from([0.8, 0.1, 0.1]),
+            name: "Cadmium Red".to_string(),
+            notes: "PR108".to_string(),
+            characteristics: ArtPaintCharacteristics {
+                permanence: Permanence::A,
+                transparency: Transparency::Opaque,
+            },
+        };
+        let paint = BasicPaint::<ArtPaintCharacteristics>::from_spec(&spec);
+        let normaliser = SearchNormaliser::default();
+        let mut filter = PaintFilter::default();
+        assert!(!filter.is_active());
+        assert!(filter.matches(&paint, &normaliser));
+        filter.text = "cadmium".to_string();
+        assert!(filter.is_active());
+        assert!(filter.matches(&paint, &normaliser));
+        filter.text = "pr108".to_string();
+        assert!(filter.matches(&paint, &normaliser));
+        filter.text = "blue".to_string();
+        assert!(!filter.matches(&paint, &normaliser));
+        filter.text = String::new();
+        filter.value = (0.5, 1.0);
+        assert!(!filter.matches(&paint, &normaliser));
+        filter.value = (0.0, 0.5);
+        assert!(filter.matches(&paint, &normaliser));
+        filter.chroma = (paint.chroma() + 0.01, 1.0);
+        assert!(!filter.matches(&paint, &normaliser));
+    }
+}
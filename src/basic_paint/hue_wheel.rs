@@ -1,7 +1,9 @@
 // Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
 
 use std::cell::RefCell;
+use std::io;
 use std::marker::PhantomData;
+use std::path::Path;
 use std::rc::Rc;
 
 use pw_gix::{
@@ -117,9 +119,17 @@ where
                 //let rectangle = gtk::Rectangle{x: x, y: y, width: 10, height: -10};
                 //println!("Rectangle: {:?}", rectangle);
                 //tooltip.set_tip_area(&rectangle);
-                match wheel_c.get_paint_at((x as f64, y as f64)) {
-                    Some(paint) => {
-                        tooltip.set_text(Some(paint.tooltip_text().as_str()));
+                let o_text =
+                    wheel_c
+                        .graticule
+                        .throttled_tooltip_text(Point(x as f64, y as f64), |raw_xy| {
+                            wheel_c
+                                .get_paint_at((raw_xy.x(), raw_xy.y()))
+                                .map(|paint| paint.tooltip_text())
+                        });
+                match o_text {
+                    Some(text) => {
+                        tooltip.set_text(Some(text.as_str()));
                         true
                     }
                     None => false,
@@ -133,6 +143,11 @@ where
                 cairo_context.set_line_width(2.0);
                 wheel_c.paints.draw(graticule, cairo_context);
             });
+        let wheel_c = wheel.clone();
+        wheel.graticule.connect_clicked(move |graticule, raw_xy| {
+            let point = graticule.reverse_transform(raw_xy);
+            wheel_c.paints.expand_cluster_at(graticule, point)
+        });
         wheel
     }
 }
@@ -143,6 +158,7 @@ where
 {
     pub fn clear(&self) {
         *self.chosen_paint.borrow_mut() = None;
+        self.graticule.set_chosen_xy(None);
         self.paints.clear();
     }
 
@@ -166,9 +182,44 @@ where
         self.graticule.attr()
     }
 
+    // Draw overlapping paints as one shape with a count (expanded by clicking it)
+    pub fn set_clustering(&self, clustering: bool) {
+        self.paints.set_clustering(clustering);
+    }
+
+    // Paints rejected by "filter" are hidden or dimmed (see set_filtered_display())
+    pub fn set_filter(&self, filter: Option<ColouredItemFilter<BasicPaint<C>>>) {
+        self.paints.set_filter(filter);
+        self.graticule.forget_tooltip();
+        self.graticule.queue_draw();
+    }
+
+    pub fn set_filtered_display(&self, filtered_display: FilteredItemDisplay) {
+        self.paints.set_filtered_display(filtered_display);
+        self.graticule.forget_tooltip();
+        self.graticule.queue_draw();
+    }
+
+    // The whole wheel at "size" x "size" pixels as PNG or SVG (by extension)
+    pub fn render_to_file(&self, path: &Path, size: i32) -> io::Result<()> {
+        self.graticule.render_to_file(path, size)
+    }
+
+    pub fn export_image(&self) {
+        export_wheel_image(self, &self.graticule)
+    }
+
+    pub fn vision_deficiency(&self) -> Option<VisionDeficiency> {
+        self.graticule.vision_deficiency()
+    }
+
+    pub fn set_vision_deficiency(&self, o_deficiency: Option<VisionDeficiency>) {
+        self.graticule.set_vision_deficiency(o_deficiency)
+    }
+
     pub fn get_paint_at(&self, posn: (f64, f64)) -> Option<BasicPaint<C>> {
         let point = self.graticule.reverse_transform(Point::from(posn));
-        let opr = self.paints.get_coloured_item_at(point);
+        let opr = self.paints.get_coloured_item_at(&*self.graticule, point);
         if let Some((paint, _)) = opr {
             Some(paint)
         } else {
@@ -179,9 +230,12 @@ where
     pub fn set_chosen_paint_from(&self, posn: (f64, f64)) -> Option<BasicPaint<C>> {
         if let Some(paint) = self.get_paint_at(posn) {
             *self.chosen_paint.borrow_mut() = Some(paint.clone());
+            self.graticule
+                .set_chosen_xy(self.paints.coloured_item_xy(&paint));
             Some(paint)
         } else {
             *self.chosen_paint.borrow_mut() = None;
+            self.graticule.set_chosen_xy(None);
             None
         }
     }
@@ -194,6 +248,35 @@ where
     ) -> SignalHandlerId {
         self.graticule.connect_button_press_event(f)
     }
+
+    pub fn connect_key_press_event<
+        F: Fn(&gtk::DrawingArea, &gdk::EventKey) -> Inhibit + 'static,
+    >(
+        &self,
+        f: F,
+    ) -> SignalHandlerId {
+        self.graticule.connect_key_press_event(f)
+    }
+
+    fn paint_points(&self) -> (Vec<BasicPaint<C>>, Vec<Point>) {
+        self.paints.coloured_items_xy().into_iter().unzip()
+    }
+
+    pub fn move_focus(&self, focus_move: FocusMove) -> bool {
+        let (_, points) = self.paint_points();
+        self.graticule.move_focus(&points, focus_move).is_some()
+    }
+
+    pub fn focused_paint(&self) -> Option<BasicPaint<C>> {
+        let (paints, points) = self.paint_points();
+        self.graticule
+            .focused_index(&points)
+            .map(|index| paints[index].clone())
+    }
+
+    pub fn popup_menu_at_focus(&self, menu: &gtk::Menu) {
+        self.graticule.popup_menu_at_focus(menu)
+    }
 }
 
 #[cfg(test)]
@@ -1,5 +1,6 @@
 // Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
 
+use std::cell::RefCell;
 use std::cmp::Ordering;
 use std::convert::From;
 use std::fmt;
@@ -9,29 +10,156 @@ use std::rc::Rc;
 use std::str::FromStr;
 
 use regex::*;
+use serde::de::DeserializeOwned;
+use serde_derive::*;
 
 use pw_gix::{
     gdk,
     glib::{self, StaticType},
     gtk::{self, prelude::*},
+    gtkx::{dialog::*, list_store::*, tree_view_column::*},
+    recollections::{recall, remember},
     wrapper::*,
 };
 
+use crate::app_name;
+use crate::cairox::*;
 use crate::colour::*;
+use crate::colour_difference::DeltaEMetric;
 use crate::error::*;
 
+pub mod batch;
 pub mod display;
 pub mod entry;
 pub mod factory;
+pub mod filter;
 pub mod hue_wheel;
 
-pub trait CharacteristicsInterface: Debug + Hash + PartialEq + Clone + Copy + ToString {
+// Views whose list store holds one row (keyed by the paint's name in
+// column 0) for each of their paints
+pub trait PaintListRows<C, P>
+where
+    C: CharacteristicsInterface,
+    P: BasicPaintInterface<C>,
+{
+    fn rows_list_store(&self) -> &gtk::ListStore;
+    fn rows_paints(&self) -> Vec<P>;
+
+    fn find_row_named(&self, name: &str) -> Option<(i32, gtk::TreeIter)> {
+        self.rows_list_store().find_row_where(|list_store, iter| {
+            list_store.row_string::<C>(iter, 0).ok().as_deref() == Some(name)
+        })
+    }
+
+    fn has_row_for(&self, paint: &P) -> bool {
+        self.find_row_named(&paint.name()).is_some()
+    }
+
+    // Bring the row's values into line with the paint's current state
+    fn update_row_for(&self, paint: &P) -> Result<(), PaintError<C>> {
+        let list_store = self.rows_list_store();
+        if let Some((_, iter)) = self.find_row_named(&paint.name()) {
+            for (col, value) in paint.tv_rows().iter().enumerate() {
+                list_store.set_value(&iter, col as u32, value);
+            }
+            Ok(())
+        } else {
+            Err(PaintErrorType::NotFound(paint.name()).into())
+        }
+    }
+
+    fn remove_row_for(&self, paint: &P) -> Result<(), PaintError<C>> {
+        if let Some((_, iter)) = self.find_row_named(&paint.name()) {
+            self.rows_list_store().remove(&iter);
+            Ok(())
+        } else {
+            Err(PaintErrorType::NotFound(paint.name()).into())
+        }
+    }
+
+    // Rebuild all rows from the current paints
+    fn refresh_all(&self) {
+        let list_store = self.rows_list_store();
+        list_store.clear();
+        for paint in self.rows_paints().iter() {
+            list_store.append_row(&paint.tv_rows());
+        }
+    }
+}
+
+// Fallible access to the values in list store rows so that a model that
+// is out of step with its paints is reported rather than crashing the app
+pub trait RowValues {
+    fn row_string<C: CharacteristicsInterface>(
+        &self,
+        iter: &gtk::TreeIter,
+        col: i32,
+    ) -> PaintResult<String, C>;
+    fn row_u32<C: CharacteristicsInterface>(
+        &self,
+        iter: &gtk::TreeIter,
+        col: i32,
+    ) -> PaintResult<u32, C>;
+}
+
+impl RowValues for gtk::ListStore {
+    fn row_string<C: CharacteristicsInterface>(
+        &self,
+        iter: &gtk::TreeIter,
+        col: i32,
+    ) -> PaintResult<String, C> {
+        match self.get_value(iter, col).get::<String>() {
+            Ok(Some(text)) => Ok(text),
+            _ => Err(PaintErrorType::ModelInconsistency(format!("text column {}", col)).into()),
+        }
+    }
+
+    fn row_u32<C: CharacteristicsInterface>(
+        &self,
+        iter: &gtk::TreeIter,
+        col: i32,
+    ) -> PaintResult<u32, C> {
+        match self.get_value(iter, col).get::<u32>() {
+            Ok(Some(value)) => Ok(value),
+            _ => Err(PaintErrorType::ModelInconsistency(format!("integer column {}", col)).into()),
+        }
+    }
+}
+
+// The value of a row access (if any) with any failure reported to the user
+pub fn reported_row_value<W, T, C>(caller: &W, result: PaintResult<Option<T>, C>) -> Option<T>
+where
+    W: WidgetWrapper,
+    C: CharacteristicsInterface,
+{
+    match result {
+        Ok(o_value) => o_value,
+        Err(err) => {
+            caller.report_error("Paint list problem", &err);
+            None
+        }
+    }
+}
+
+pub trait CharacteristicsInterface:
+    Debug + Hash + PartialEq + Clone + Copy + ToString + serde::Serialize + DeserializeOwned
+{
     type Entry: CharacteristicsEntryInterface<Self>;
 
     fn tv_row_len() -> usize;
     fn tv_columns(start_col_id: i32) -> Vec<gtk::TreeViewColumn>;
     fn from_floats(floats: &Vec<f64>) -> Self;
     fn from_str(string: &str) -> Result<Self, PaintError<Self>>;
+    // The paint type prefixes expected in specification text (only the
+    // generic one unless the implementation knows better)
+    fn paint_types() -> Vec<&'static str> {
+        vec![GENERIC_PAINT_TYPE]
+    }
+    // The values assumed (key, value) when upgrading legacy files that lack
+    // (or have unrecognisable) characteristics
+    fn legacy_assumptions() -> Vec<(&'static str, &'static str)> {
+        vec![]
+    }
 
     fn tv_rows(&self) -> Vec<glib::Value>;
     fn gui_display_widget(&self) -> gtk::Box;
@@ -105,6 +233,11 @@ pub trait ColouredItemInterface {
     fn scalar_attribute(&self, attr: ScalarAttribute) -> f64 {
         self.colour().scalar_attribute(attr)
     }
+
+    // "other" (usually a target) is the reference colour for CIE94
+    fn delta_e_to(&self, other: &Colour, metric: DeltaEMetric) -> f64 {
+        metric.delta_e(&other.rgb(), &self.rgb())
+    }
 }
 
 pub trait BasicPaintInterface<C>: Clone + PartialEq + Ord + Debug + ColouredItemInterface
@@ -116,6 +249,24 @@ where
     fn tooltip_text(&self) -> String;
     fn characteristics(&self) -> C;
 
+    // In grams per millilitre (if known)
+    fn density(&self) -> Option<f64> {
+        None
+    }
+
+    fn display_name(&self, policy: DisplayNamePolicy) -> String {
+        policy.display_name(&self.name(), &self.notes())
+    }
+
+    fn tooltip_text_for(&self, policy: DisplayNamePolicy) -> String {
+        let display_name = self.display_name(policy);
+        if display_name == self.name() {
+            self.tooltip_text()
+        } else {
+            format!("{}\n{}", display_name, self.tooltip_text())
+        }
+    }
+
     fn get_spec(&self) -> BasicPaintSpec<C> {
         BasicPaintSpec::<C> {
             rgb: self.rgb(),
@@ -179,10 +330,11 @@ where
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct BasicPaintSpec<C: CharacteristicsInterface> {
     pub rgb: RGB,
     pub name: String,
+    #[serde(default)]
     pub notes: String,
     pub characteristics: C,
 }
@@ -276,20 +428,71 @@ where
 
 lazy_static! {
     pub static ref BASIC_PAINT_RE: Regex = Regex::new(
-        r#"^(?P<ptype>\w+)\((name=)?"(?P<name>.+)", rgb=(?P<rgb>RGB(16)?\([^)]+\))(?P<characteristics>(?:, \w+="\w+")*)(, notes="(?P<notes>.*)")?\)$"#
+        r#"^(?P<ptype>\w+)\((name=)?"(?P<name>.+)",\s*rgb=(?P<rgb>RGB(16)?\([^)]+\)|#[0-9A-Fa-f]{6})(,\s*notes="(?P<leading_notes>.*?)")?(?P<characteristics>(?:,\s*\w+="\w+")*)(,\s*notes="(?P<notes>.*)")?\)$"#
     ).unwrap();
 }
 
-impl<C: CharacteristicsInterface> FromStr for BasicPaintSpec<C> {
-    type Err = PaintError<C>;
+// Written by BasicPaintSpec's Display so always acceptable
+pub const GENERIC_PAINT_TYPE: &str = "PaintSpec";
 
-    fn from_str(string: &str) -> Result<BasicPaintSpec<C>, PaintError<C>> {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaintTypeCheck {
+    Strict,
+    Lenient,
+}
+
+impl Default for PaintTypeCheck {
+    fn default() -> PaintTypeCheck {
+        PaintTypeCheck::Lenient
+    }
+}
+
+impl<C: CharacteristicsInterface> BasicPaintSpec<C> {
+    // Parse the text checking its paint type: a mismatch is an error in
+    // strict mode and is reported in "warnings" in lenient mode.
+    pub fn from_str_checked(
+        string: &str,
+        check: PaintTypeCheck,
+        warnings: &mut Vec<String>,
+    ) -> Result<BasicPaintSpec<C>, PaintError<C>> {
         let captures = BASIC_PAINT_RE.captures(string).ok_or(PaintError::from(
             PaintErrorType::MalformedText(string.to_string()),
         ))?;
+        if let Some(ptype_match) = captures.name("ptype") {
+            let ptype = ptype_match.as_str();
+            if ptype != GENERIC_PAINT_TYPE && !C::paint_types().contains(&ptype) {
+                match check {
+                    PaintTypeCheck::Strict => {
+                        return Err(PaintErrorType::WrongPaintType(string.to_string()).into())
+                    }
+                    PaintTypeCheck::Lenient => warnings.push(format!(
+                        "{}: unexpected paint type (expected {})",
+                        string,
+                        C::paint_types().join(" or ")
+                    )),
+                }
+            }
+        }
+        BasicPaintSpec::<C>::from_captures(string, &captures)
+    }
+
+    fn from_captures(
+        string: &str,
+        captures: &Captures,
+    ) -> Result<BasicPaintSpec<C>, PaintError<C>> {
         let c_match = captures.name("characteristics").ok_or(PaintError::from(
             PaintErrorType::MalformedText(string.to_string()),
         ))?;
+        let characteristics = C::from_str(c_match.as_str())?;
+        BasicPaintSpec::<C>::from_captures_with(string, captures, characteristics)
+    }
+
+    // As for from_captures() but with the characteristics already decided
+    pub(crate) fn from_captures_with(
+        string: &str,
+        captures: &Captures,
+        characteristics: C,
+    ) -> Result<BasicPaintSpec<C>, PaintError<C>> {
         let rgb_match =
             captures
                 .name("rgb")
@@ -302,14 +505,23 @@ impl<C: CharacteristicsInterface> FromStr for BasicPaintSpec<C> {
                 .ok_or(PaintError::from(PaintErrorType::MalformedText(
                     string.to_string(),
                 )))?;
-        let characteristics = C::from_str(c_match.as_str())?;
-        let rgb16 = RGB16::from_str(rgb_match.as_str())?;
-        let notes = match captures.name("notes") {
+        let rgb = if rgb_match.as_str().starts_with('#') {
+            rgb_from_css_hex(rgb_match.as_str()).ok_or(PaintError::from(
+                PaintErrorType::MalformedText(string.to_string()),
+            ))?
+        } else {
+            RGB::from(RGB16::from_str(rgb_match.as_str())?)
+        };
+        // Notes may be written before or after the characteristics
+        let notes = match captures
+            .name("leading_notes")
+            .or_else(|| captures.name("notes"))
+        {
             Some(notes_match) => notes_match.as_str().to_string(),
             None => "".to_string(),
         };
         Ok(BasicPaintSpec::<C> {
-            rgb: RGB::from(rgb16),
+            rgb: rgb,
             name: name_match.as_str().to_string().replace("\\\"", "\""),
             notes: notes.replace("\\\"", "\""),
             characteristics: characteristics,
@@ -317,6 +529,15 @@ impl<C: CharacteristicsInterface> FromStr for BasicPaintSpec<C> {
     }
 }
 
+impl<C: CharacteristicsInterface> FromStr for BasicPaintSpec<C> {
+    type Err = PaintError<C>;
+
+    fn from_str(string: &str) -> Result<BasicPaintSpec<C>, PaintError<C>> {
+        let mut warnings: Vec<String> = Vec::new();
+        BasicPaintSpec::<C>::from_str_checked(string, PaintTypeCheck::Lenient, &mut warnings)
+    }
+}
+
 impl<C: CharacteristicsInterface> fmt::Display for BasicPaintSpec<C> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
@@ -348,9 +569,10 @@ pub const SP_CHARS_0: i32 = 14;
 pub const SP_CHARS_1: i32 = 15;
 pub const SP_CHARS_2: i32 = 16;
 pub const SP_CHARS_3: i32 = 17;
+pub const SP_CHARS_4: i32 = 18;
 
 lazy_static! {
-    pub static ref STANDARD_PAINT_ROW_SPEC: [glib::Type; 18] =
+    pub static ref STANDARD_PAINT_ROW_SPEC: [glib::Type; 19] =
         [
             glib::Type::String,          // 0 Name
             glib::Type::String,          // 1 Notes
@@ -370,6 +592,7 @@ lazy_static! {
             glib::Type::String,          // 15 Characteristic #2
             glib::Type::String,          // 16 Characteristic #3
             glib::Type::String,          // 17 Characteristic #4
+            glib::Type::String,          // 18 Characteristic #5
         ];
 }
 
@@ -377,10 +600,455 @@ pub trait PaintTreeViewColumnSpec {
     fn tv_columns() -> Vec<gtk::TreeViewColumn>;
 }
 
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RowColouring {
+    Paint,
+    PaintSeparated,
+    NeutralWithSwatch,
+}
+
+impl Default for RowColouring {
+    fn default() -> RowColouring {
+        RowColouring::Paint
+    }
+}
+
+pub const ROW_COLOURING_MENU_KEYS: [&str; 3] = ["paint", "paint_separated", "neutral_with_swatch"];
+
+static ROW_COLOURING_VALUES: &[RowColouring] = &[
+    RowColouring::Paint,
+    RowColouring::PaintSeparated,
+    RowColouring::NeutralWithSwatch,
+];
+
+impl RowColouring {
+    pub fn values() -> Vec<RowColouring> {
+        ROW_COLOURING_VALUES.to_vec()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            RowColouring::Paint => "paint",
+            RowColouring::PaintSeparated => "paint_separated",
+            RowColouring::NeutralWithSwatch => "neutral_with_swatch",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match *self {
+            RowColouring::Paint => "Paint Coloured Rows",
+            RowColouring::PaintSeparated => "Paint Coloured Rows (Separated)",
+            RowColouring::NeutralWithSwatch => "Neutral Rows With Swatch",
+        }
+    }
+
+    pub fn tooltip_text(&self) -> &'static str {
+        match *self {
+            RowColouring::Paint => "Use each paint's colour as the background of its row",
+            RowColouring::PaintSeparated => {
+                "Use each paint's colour as the background of its row with lines between rows"
+            }
+            RowColouring::NeutralWithSwatch => {
+                "Use neutral row backgrounds and show each paint's colour in a swatch column"
+            }
+        }
+    }
+
+    pub fn recall(key: &str) -> RowColouring {
+        if let Some(text) = recall(key) {
+            if let Ok(row_colouring) = RowColouring::from_str(&text) {
+                return row_colouring;
+            }
+        };
+        RowColouring::default()
+    }
+
+    pub fn remember(&self, key: &str) {
+        remember(key, self.name())
+    }
+}
+
+impl FromStr for RowColouring {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<RowColouring, String> {
+        for row_colouring in ROW_COLOURING_VALUES.iter() {
+            if row_colouring.name() == string {
+                return Ok(*row_colouring);
+            }
+        }
+        Err(format!("{}: unknown row colouring", string))
+    }
+}
+
+impl fmt::Display for RowColouring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// Catalogue codes such as "PB29" or "W123" at the start of the notes
+pub const DEFAULT_PAINT_CODE_PATTERN: &str = r"^\s*(?P<code>[A-Z]{0,4}[0-9]{1,5}[A-Z]?)\b";
+pub const PAINT_CODE_PATTERN_KEY: &str = "paint_code_extraction_regex";
+
+// Display names are wanted for every paint in lists, reports and tooltips
+// so the regex is only compiled again when the pattern is changed
+thread_local! {
+    static PAINT_CODE_REGEX: RefCell<Option<Regex>> = RefCell::new(None);
+}
+
+// The user's code extraction regex (from preferences) or the default if
+// none has been set or it is no longer valid.
+pub fn paint_code_regex() -> Regex {
+    PAINT_CODE_REGEX.with(|cache| {
+        if let Some(ref regex) = *cache.borrow() {
+            return regex.clone();
+        }
+        let regex = recall(PAINT_CODE_PATTERN_KEY)
+            .and_then(|text| Regex::new(&text).ok())
+            .unwrap_or_else(|| Regex::new(DEFAULT_PAINT_CODE_PATTERN).unwrap());
+        *cache.borrow_mut() = Some(regex.clone());
+        regex
+    })
+}
+
+pub fn set_paint_code_pattern(pattern: &str) -> Result<(), regex::Error> {
+    let regex = Regex::new(pattern)?;
+    remember(PAINT_CODE_PATTERN_KEY, pattern);
+    PAINT_CODE_REGEX.with(|cache| *cache.borrow_mut() = Some(regex));
+    Ok(())
+}
+
+// The preferences entry for the code extraction regex (an empty entry
+// restores the default).  Returns whether the pattern was changed.
+pub fn ask_paint_code_pattern<W: WidgetWrapper>(caller: &W) -> bool {
+    let title = format!("{}: Paint Code Pattern", app_name());
+    let dialog = caller.new_dialog_with_buttons(
+        Some(&title),
+        gtk::DialogFlags::DESTROY_WITH_PARENT,
+        CANCEL_OK_BUTTONS,
+    );
+    let pattern_entry = gtk::Entry::new();
+    pattern_entry.set_width_chars(48);
+    pattern_entry.set_text(paint_code_regex().as_str());
+    pattern_entry.set_tooltip_text(Some(
+        "A regular expression matching the paint's code in its notes (its \"code\" group if it has one)",
+    ));
+    let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 2);
+    hbox.pack_start(&gtk::Label::new(Some("Pattern:")), false, false, 0);
+    hbox.pack_start(&pattern_entry, true, true, 0);
+    let content_area = dialog.get_content_area();
+    content_area.pack_start(&hbox, false, false, 0);
+    content_area.pack_start(
+        &gtk::Label::new(Some(&format!("Default: {}", DEFAULT_PAINT_CODE_PATTERN))),
+        false,
+        false,
+        0,
+    );
+    content_area.show_all();
+    let mut changed = false;
+    while gtk::ResponseType::from(dialog.run()) == gtk::ResponseType::Ok {
+        let text = pattern_entry.get_text();
+        let pattern = if text.trim().len() == 0 {
+            DEFAULT_PAINT_CODE_PATTERN
+        } else {
+            text.as_str()
+        };
+        match set_paint_code_pattern(pattern) {
+            Ok(()) => {
+                changed = true;
+                break;
+            }
+            Err(err) => caller.report_error("Invalid paint code pattern", &err),
+        }
+    }
+    unsafe { dialog.destroy() };
+    changed
+}
+
+// The "code" group if the regex has one otherwise the whole match
+pub fn extract_paint_code(notes: &str, regex: &Regex) -> Option<String> {
+    let captures = regex.captures(notes)?;
+    let code = match captures.name("code") {
+        Some(code) => code.as_str(),
+        None => captures.get(0)?.as_str(),
+    };
+    let code = code.trim();
+    if code.len() > 0 {
+        Some(code.to_string())
+    } else {
+        None
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DisplayNamePolicy {
+    Name,
+    Code,
+    Both,
+}
+
+impl Default for DisplayNamePolicy {
+    fn default() -> DisplayNamePolicy {
+        DisplayNamePolicy::Name
+    }
+}
+
+pub const DISPLAY_NAME_POLICY_MENU_KEYS: [&str; 3] = ["name", "code", "code_and_name"];
+
+static DISPLAY_NAME_POLICY_VALUES: &[DisplayNamePolicy] = &[
+    DisplayNamePolicy::Name,
+    DisplayNamePolicy::Code,
+    DisplayNamePolicy::Both,
+];
+
+impl DisplayNamePolicy {
+    pub fn values() -> Vec<DisplayNamePolicy> {
+        DISPLAY_NAME_POLICY_VALUES.to_vec()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            DisplayNamePolicy::Name => "name",
+            DisplayNamePolicy::Code => "code",
+            DisplayNamePolicy::Both => "code_and_name",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match *self {
+            DisplayNamePolicy::Name => "Show Paint Names",
+            DisplayNamePolicy::Code => "Show Paint Codes",
+            DisplayNamePolicy::Both => "Show Paint Codes and Names",
+        }
+    }
+
+    pub fn tooltip_text(&self) -> &'static str {
+        match *self {
+            DisplayNamePolicy::Name => "Identify paints by their names",
+            DisplayNamePolicy::Code => {
+                "Identify paints by the catalogue code found in their notes (or their name if none)"
+            }
+            DisplayNamePolicy::Both => {
+                "Identify paints by the catalogue code found in their notes and their names"
+            }
+        }
+    }
+
+    pub fn display_name(&self, name: &str, notes: &str) -> String {
+        if *self == DisplayNamePolicy::Name {
+            return name.to_string();
+        }
+        self.display_name_using(name, notes, &paint_code_regex())
+    }
+
+    pub fn display_name_using(&self, name: &str, notes: &str, regex: &Regex) -> String {
+        if *self == DisplayNamePolicy::Name {
+            return name.to_string();
+        }
+        match extract_paint_code(notes, regex) {
+            Some(code) => match *self {
+                DisplayNamePolicy::Code => code,
+                _ => format!("{}: {}", code, name),
+            },
+            None => name.to_string(),
+        }
+    }
+
+    pub fn recall(key: &str) -> DisplayNamePolicy {
+        if let Some(text) = recall(key) {
+            if let Ok(policy) = DisplayNamePolicy::from_str(&text) {
+                return policy;
+            }
+        };
+        DisplayNamePolicy::default()
+    }
+
+    pub fn remember(&self, key: &str) {
+        remember(key, self.name())
+    }
+}
+
+impl FromStr for DisplayNamePolicy {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<DisplayNamePolicy, String> {
+        for policy in DISPLAY_NAME_POLICY_VALUES.iter() {
+            if policy.name() == string {
+                return Ok(*policy);
+            }
+        }
+        Err(format!("{}: unknown display name policy", string))
+    }
+}
+
+impl fmt::Display for DisplayNamePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// Remove the colour attributes from the column's cells leaving just the text
+pub fn neutralise_tv_column(col: &gtk::TreeViewColumn, text_id: i32) {
+    for cell in col.get_cells().iter() {
+        col.clear_attributes(cell);
+        col.add_attribute(cell, "text", text_id);
+    }
+}
+
+pub const SWATCH_CHIP_WIDTH: i32 = 24;
+pub const SWATCH_CHIP_HEIGHT: i32 = 14;
+
+// Render a rounded colour chip with an outline that contrasts with the colour
+pub fn colour_chip_pixbuf(rgba: &gdk::RGBA, width: i32, height: i32) -> Option<Pixbuf> {
+    let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, width, height).ok()?;
+    let cairo_context = cairo::Context::new(&surface);
+    let corner = Point(1.0, 1.0);
+    let (chip_width, chip_height) = (width as f64 - 2.0, height as f64 - 2.0);
+    let radius = chip_height / 4.0;
+    let rgb = RGB::from([rgba.red, rgba.green, rgba.blue]);
+    cairo_context.set_source_colour_rgb(&rgb);
+    cairo_context.draw_rounded_rectangle(corner, chip_width, chip_height, radius, true);
+    let outline = rgb.best_foreground_rgb();
+    cairo_context.set_source_rgb(outline[CCI::Red], outline[CCI::Green], outline[CCI::Blue]);
+    cairo_context.set_line_width(1.0);
+    cairo_context.draw_rounded_rectangle(corner, chip_width, chip_height, radius, false);
+    drop(cairo_context);
+    gdk::pixbuf_get_from_surface(&surface, 0, 0, width, height)
+}
+
+// A column that shows the colour in the rgb_id column of the model as a chip
+pub fn swatch_tv_column(rgb_id: i32) -> gtk::TreeViewColumn {
+    let col = gtk::TreeViewColumn::new();
+    col.set_title("");
+    col.set_resizable(false);
+    col.set_fixed_width(SWATCH_CHIP_WIDTH + 6);
+    let cell = gtk::CellRendererPixbuf::new();
+    col.pack_start(&cell, false);
+    col.set_cell_data_func(
+        &cell,
+        Some(Box::new(move |_, cell, model, iter| {
+            if let Some(cell) = cell.downcast_ref::<gtk::CellRendererPixbuf>() {
+                let pixbuf =
+                    if let Ok(Some(rgba)) = model.get_value(iter, rgb_id).get::<gdk::RGBA>() {
+                        colour_chip_pixbuf(&rgba, SWATCH_CHIP_WIDTH, SWATCH_CHIP_HEIGHT)
+                    } else {
+                        None
+                    };
+                cell.set_property_pixbuf(pixbuf.as_ref());
+            }
+        })),
+    );
+    col
+}
+
+pub fn standard_paint_tv_columns<A, C>(row_colouring: RowColouring) -> Vec<gtk::TreeViewColumn>
+where
+    A: ColourAttributesInterface,
+    C: CharacteristicsInterface,
+{
+    let mut cols: Vec<gtk::TreeViewColumn> = Vec::new();
+    if row_colouring == RowColouring::NeutralWithSwatch {
+        cols.push(swatch_tv_column(SP_RGB));
+        cols.push(simple_text_column(
+            "Name", SP_NAME, SP_NAME, -1, -1, -1, true,
+        ));
+        cols.push(simple_text_column(
+            "Notes", SP_NOTES, SP_NOTES, -1, -1, -1, true,
+        ));
+    } else {
+        cols.push(simple_text_column(
+            "Name", SP_NAME, SP_NAME, SP_RGB, SP_RGB_FG, -1, true,
+        ));
+        cols.push(simple_text_column(
+            "Notes", SP_NOTES, SP_NOTES, SP_RGB, SP_RGB_FG, -1, true,
+        ));
+    }
+    for col in A::tv_columns() {
+        cols.push(col);
+    }
+    for (i, col) in C::tv_columns(SP_CHARS_0).iter().enumerate() {
+        if row_colouring == RowColouring::NeutralWithSwatch {
+            neutralise_tv_column(col, SP_CHARS_0 + i as i32);
+        }
+        cols.push(col.clone());
+    }
+    cols
+}
+
+pub fn set_tv_columns(
+    view: &gtk::TreeView,
+    cols: &Vec<gtk::TreeViewColumn>,
+    row_colouring: RowColouring,
+) {
+    for col in view.get_columns().iter() {
+        view.remove_column(col);
+    }
+    for col in cols.iter() {
+        view.append_column(col);
+    }
+    if row_colouring == RowColouring::PaintSeparated {
+        view.set_grid_lines(gtk::TreeViewGridLines::Horizontal);
+    } else {
+        view.set_grid_lines(gtk::TreeViewGridLines::None);
+    }
+    view.show_all();
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn row_colouring_names() {
+        for row_colouring in RowColouring::values().iter() {
+            assert_eq!(
+                RowColouring::from_str(&row_colouring.to_string()),
+                Ok(*row_colouring)
+            );
+        }
+        assert!(RowColouring::from_str("garish").is_err());
+    }
+
+    #[test]
+    fn display_name_policies() {
+        let regex = Regex::new(DEFAULT_PAINT_CODE_PATTERN).unwrap();
+        assert_eq!(
+            extract_paint_code("PB29 Ultramarine", &regex),
+            Some("PB29".to_string())
+        );
+        assert_eq!(extract_paint_code("very blue", &regex), None);
+        let name = "French Ultramarine";
+        let notes = "W263 Series 1";
+        assert_eq!(
+            DisplayNamePolicy::Name.display_name_using(name, notes, &regex),
+            name
+        );
+        assert_eq!(
+            DisplayNamePolicy::Code.display_name_using(name, notes, &regex),
+            "W263"
+        );
+        assert_eq!(
+            DisplayNamePolicy::Both.display_name_using(name, notes, &regex),
+            "W263: French Ultramarine"
+        );
+        assert_eq!(
+            DisplayNamePolicy::Code.display_name_using(name, "no code", &regex),
+            name
+        );
+        let regex = Regex::new(r"#\d+").unwrap();
+        assert_eq!(
+            DisplayNamePolicy::Code.display_name_using(name, "Cat #42", &regex),
+            "#42"
+        );
+        for policy in DisplayNamePolicy::values().iter() {
+            assert_eq!(
+                DisplayNamePolicy::from_str(&policy.to_string()),
+                Ok(*policy)
+            );
+        }
+    }
+
     #[test]
     fn basic_paint_basic_paint_regex() {
         let test_str = r#"ModelPaint(name="71.001 White", rgb=RGB16(red=0xF800, green=0xFA00, blue=0xF600), transparency="O", finish="F", metallic="NM", fluorescence="NF", notes="FS37925 RAL9016 RLM21")"#.to_string();
@@ -0,0 +1,234 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+// A strip of the mixtures of two paints at evenly spaced ratios to
+// preview how one blends into the other.
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use pw_gix::{
+    cairo,
+    gtk::{self, prelude::*},
+    recollections::{recall, remember},
+    wrapper::*,
+};
+
+use crate::app_name;
+use crate::cairox::*;
+use crate::colour::*;
+use crate::colour_mix::*;
+
+pub const DEFAULT_BLEND_STEPS: u32 = 10;
+pub const MAX_BLEND_STEPS: u32 = 40;
+
+const BLEND_STEPS_RECOLLECTION: &str = "blend_preview::steps";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlendStep {
+    pub first_parts: u32,
+    pub second_parts: u32,
+    pub rgb: RGB,
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+impl BlendStep {
+    // The parts with any common factor removed (e.g. 6:4 becomes 3:2)
+    pub fn reduced_parts(&self) -> (u32, u32) {
+        let divisor = gcd(self.first_parts, self.second_parts).max(1);
+        (self.first_parts / divisor, self.second_parts / divisor)
+    }
+}
+
+// The "n_steps" + 1 mixtures from all of "first" to all of "second"
+pub fn blend_steps(first: &RGB, second: &RGB, n_steps: u32, model: MixingModel) -> Vec<BlendStep> {
+    let n_steps = n_steps.max(1);
+    (0..=n_steps)
+        .map(|i| {
+            let mut colour_mixer = ColourMixer::with_model(model);
+            colour_mixer.add(&Colour::from(*first), n_steps - i);
+            colour_mixer.add(&Colour::from(*second), i);
+            let rgb = colour_mixer
+                .get_colour()
+                .unwrap_or_else(|| panic!("File: {:?} Line: {:?}", file!(), line!()))
+                .rgb();
+            BlendStep {
+                first_parts: n_steps - i,
+                second_parts: i,
+                rgb,
+            }
+        })
+        .collect()
+}
+
+#[derive(PWO, Wrapper)]
+pub struct BlendPreviewCore {
+    vbox: gtk::Box,
+    drawing_area: gtk::DrawingArea,
+    steps_spin: gtk::SpinButton,
+    paints: [(String, RGB); 2],
+    blend: RefCell<Vec<BlendStep>>,
+    model: Cell<MixingModel>,
+}
+
+pub type BlendPreview = Rc<BlendPreviewCore>;
+
+impl BlendPreviewCore {
+    pub fn new(first: (&str, &RGB), second: (&str, &RGB)) -> BlendPreview {
+        let drawing_area = gtk::DrawingArea::new();
+        drawing_area.set_size_request(400, 60);
+        drawing_area.set_has_tooltip(true);
+        let steps_spin = gtk::SpinButton::with_range(1.0, MAX_BLEND_STEPS as f64, 1.0);
+        steps_spin.set_tooltip_text(Some("The number of steps from one paint to the other"));
+        let n_steps = recall(BLEND_STEPS_RECOLLECTION)
+            .and_then(|text| text.parse::<u32>().ok())
+            .unwrap_or(DEFAULT_BLEND_STEPS);
+        steps_spin.set_value(n_steps as f64);
+        let bp = Rc::new(BlendPreviewCore {
+            vbox: gtk::Box::new(gtk::Orientation::Vertical, 2),
+            drawing_area,
+            steps_spin,
+            paints: [
+                (first.0.to_string(), *first.1),
+                (second.0.to_string(), *second.1),
+            ],
+            blend: RefCell::new(Vec::new()),
+            model: Cell::new(MixingModel::default()),
+        });
+        let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 2);
+        hbox.pack_start(&gtk::Label::new(Some("Steps:")), false, false, 0);
+        hbox.pack_start(&bp.steps_spin, false, false, 0);
+        bp.vbox.pack_start(&bp.drawing_area, true, true, 0);
+        bp.vbox.pack_start(&hbox, false, false, 0);
+        bp.vbox.show_all();
+        bp.update();
+
+        let bp_c = bp.clone();
+        bp.steps_spin.connect_value_changed(move |spin| {
+            remember(
+                BLEND_STEPS_RECOLLECTION,
+                &spin.get_value_as_int().to_string(),
+            );
+            bp_c.update();
+        });
+
+        let bp_c = bp.clone();
+        bp.drawing_area.connect_draw(move |da, cctx| {
+            bp_c.draw(da, cctx);
+            gtk::Inhibit(true)
+        });
+
+        let bp_c = bp.clone();
+        bp.drawing_area
+            .connect_query_tooltip(move |da, x, _, _, tooltip| {
+                match bp_c.tooltip_text_at(x as f64, da.get_allocated_width() as f64) {
+                    Some(text) => {
+                        tooltip.set_text(Some(text.as_str()));
+                        true
+                    }
+                    None => false,
+                }
+            });
+
+        bp
+    }
+
+    pub fn set_mixing_model(&self, model: MixingModel) {
+        self.model.set(model);
+        self.update();
+    }
+
+    pub fn blend(&self) -> Vec<BlendStep> {
+        self.blend.borrow().clone()
+    }
+
+    fn update(&self) {
+        let n_steps = self.steps_spin.get_value_as_int().max(1) as u32;
+        let paints = &self.paints;
+        *self.blend.borrow_mut() =
+            blend_steps(&paints[0].1, &paints[1].1, n_steps, self.model.get());
+        self.drawing_area.queue_draw();
+    }
+
+    fn tooltip_text_at(&self, x: f64, width: f64) -> Option<String> {
+        let blend = self.blend.borrow();
+        if blend.len() == 0 || x < 0.0 || x >= width {
+            return None;
+        }
+        let step = &blend[(x * blend.len() as f64 / width) as usize];
+        let paints = &self.paints;
+        let (first_parts, second_parts) = step.reduced_parts();
+        Some(format!(
+            "{} x {} : {} x {}\nRGB: {}",
+            first_parts,
+            paints[0].0,
+            second_parts,
+            paints[1].0,
+            step.rgb.into_css_hex()
+        ))
+    }
+
+    fn draw(&self, drawing_area: &gtk::DrawingArea, cairo_context: &cairo::Context) {
+        let width = drawing_area.get_allocated_width() as f64;
+        let height = drawing_area.get_allocated_height() as f64;
+        let blend = self.blend.borrow();
+        let step_width = width / blend.len().max(1) as f64;
+        for (i, step) in blend.iter().enumerate() {
+            cairo_context.set_source_colour_rgb(&step.rgb);
+            // overlap slightly to avoid hairline gaps between the steps
+            cairo_context.rectangle(i as f64 * step_width, 0.0, step_width + 1.0, height);
+            cairo_context.fill();
+        }
+    }
+}
+
+pub fn show_blend_preview<W: WidgetWrapper>(
+    first: (&str, &RGB),
+    second: (&str, &RGB),
+    caller: &Rc<W>,
+) {
+    let title = format!("{}: Blend Preview", app_name());
+    let dialog = caller.new_dialog_with_buttons(
+        Some(&title),
+        gtk::DialogFlags::DESTROY_WITH_PARENT,
+        &[("Close", gtk::ResponseType::Close)],
+    );
+    dialog.set_size_from_recollections("blend_preview_dialog", (450, 120));
+    let label = gtk::Label::new(Some(&format!("{} \u{2192} {}", first.0, second.0)));
+    let blend_preview = BlendPreviewCore::new(first, second);
+    let content_area = dialog.get_content_area();
+    content_area.pack_start(&label, false, false, 0);
+    content_area.pack_start(&blend_preview.pwo(), true, true, 0);
+    content_area.show_all();
+    dialog.run();
+    unsafe { dialog.destroy() };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blend_steps_span_the_paints() {
+        let steps = blend_steps(&RGB::BLACK, &RGB::WHITE, 4, MixingModel::SRGB);
+        assert_eq!(steps.len(), 5);
+        assert_eq!(steps[0].rgb, RGB::BLACK);
+        assert_eq!(steps[4].rgb, RGB::WHITE);
+        assert_eq!((steps[1].first_parts, steps[1].second_parts), (3, 1));
+        assert_eq!(steps[2].reduced_parts(), (1, 1));
+        assert_eq!(steps[0].reduced_parts(), (1, 0));
+        assert!((steps[2].rgb.value() - 0.5).abs() < 1.0e-9);
+        let linear = blend_steps(&RGB::BLACK, &RGB::WHITE, 4, MixingModel::LinearLight);
+        assert!(linear[2].rgb.value() > steps[2].rgb.value());
+        assert_eq!(
+            blend_steps(&RGB::RED, &RGB::BLUE, 0, MixingModel::SRGB).len(),
+            2
+        );
+    }
+}
@@ -1,9 +1,30 @@
 // Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
 
+use std::cell::Cell;
 use std::f64::consts;
 
 pub use pw_gix::{cairo, gdk::prelude::GdkContextExt, gdk_pixbuf::Pixbuf, geometry::*};
 
+use crate::colour::{VisionDeficiency, CCI, RGB};
+use crate::colour_management::display_rgb;
+
+thread_local! {
+    static VISION_DEFICIENCY: Cell<Option<VisionDeficiency>> = Cell::new(None);
+}
+
+/// Colours (and pixbufs) drawn by "draw" are shown as they would be seen
+/// by someone with the given colour vision deficiency (if any).
+pub fn with_vision_deficiency<F: FnOnce()>(o_deficiency: Option<VisionDeficiency>, draw: F) {
+    let saved = VISION_DEFICIENCY.with(|cell| cell.replace(o_deficiency));
+    draw();
+    VISION_DEFICIENCY.with(|cell| cell.set(saved));
+}
+
+fn simulated_display_rgb(rgb: &RGB) -> RGB {
+    let o_deficiency = VISION_DEFICIENCY.with(|cell| cell.get());
+    display_rgb(&VisionDeficiency::simulated_rgb_for(o_deficiency, rgb))
+}
+
 /// Direction in which to draw indicators
 pub enum Dirn {
     Down,
@@ -18,11 +39,21 @@ pub trait Draw {
     fn draw_line(&self, start: Point, end: Point);
     fn draw_polygon(&self, polygon: Points, fill: bool);
     fn draw_square(&self, centre: Point, side_length: f64, filled: bool);
+    fn draw_rounded_rectangle(
+        &self,
+        corner: Point,
+        width: f64,
+        height: f64,
+        radius: f64,
+        fill: bool,
+    );
     fn draw_indicator(&self, position: Point, dirn: Dirn, size: f64);
     fn move_to_point(&self, point: Point);
     fn line_to_point(&self, point: Point);
     fn set_source_surface_at(&self, surface: &cairo::Surface, position: Point);
     fn set_source_pixbuf_at(&self, pixbuf: &Pixbuf, position: Point);
+    fn set_source_colour_rgb(&self, rgb: &RGB);
+    fn set_source_colour_rgba(&self, rgb: &RGB, alpha: f64);
 }
 
 impl Draw for cairo::Context {
@@ -83,6 +114,54 @@ impl Draw for cairo::Context {
         }
     }
 
+    fn draw_rounded_rectangle(
+        &self,
+        corner: Point,
+        width: f64,
+        height: f64,
+        radius: f64,
+        fill: bool,
+    ) {
+        let radius = radius.min(width / 2.0).min(height / 2.0);
+        let (left, top) = (corner.0, corner.1);
+        let (right, bottom) = (left + width, top + height);
+        self.new_sub_path();
+        self.arc(
+            right - radius,
+            top + radius,
+            radius,
+            -consts::FRAC_PI_2,
+            0.0,
+        );
+        self.arc(
+            right - radius,
+            bottom - radius,
+            radius,
+            0.0,
+            consts::FRAC_PI_2,
+        );
+        self.arc(
+            left + radius,
+            bottom - radius,
+            radius,
+            consts::FRAC_PI_2,
+            consts::PI,
+        );
+        self.arc(
+            left + radius,
+            top + radius,
+            radius,
+            consts::PI,
+            3.0 * consts::FRAC_PI_2,
+        );
+        self.close_path();
+        if fill {
+            self.fill();
+        } else {
+            self.stroke();
+        }
+    }
+
     fn draw_indicator(&self, position: Point, dirn: Dirn, size: f64) {
         self.move_to(position.0, position.1);
         match dirn {
@@ -124,6 +203,91 @@ impl Draw for cairo::Context {
     }
 
     fn set_source_pixbuf_at(&self, pixbuf: &Pixbuf, position: Point) {
-        self.set_source_pixbuf(pixbuf, position.0, position.1);
+        match VISION_DEFICIENCY.with(|cell| cell.get()) {
+            Some(deficiency) => {
+                let simulated = simulated_pixbuf(pixbuf, deficiency);
+                self.set_source_pixbuf(&simulated, position.0, position.1);
+            }
+            None => self.set_source_pixbuf(pixbuf, position.0, position.1),
+        }
+    }
+
+    // NB: these are colour managed (see colour_management) and subject to
+    // any vision deficiency simulation (see with_vision_deficiency())
+    fn set_source_colour_rgb(&self, rgb: &RGB) {
+        let rgb = simulated_display_rgb(rgb);
+        self.set_source_rgb(rgb[CCI::Red], rgb[CCI::Green], rgb[CCI::Blue]);
+    }
+
+    fn set_source_colour_rgba(&self, rgb: &RGB, alpha: f64) {
+        let rgb = simulated_display_rgb(rgb);
+        self.set_source_rgba(rgb[CCI::Red], rgb[CCI::Green], rgb[CCI::Blue], alpha);
+    }
+}
+
+// Big samples are thinned so that at most about this many pixels are used
+const MAX_PIXBUF_RGBS: i32 = 10000;
+
+/// The colours of (a spread of) the pixels in an 8 bit per sample pixbuf
+pub fn pixbuf_rgbs(pixbuf: &Pixbuf) -> Vec<RGB> {
+    assert_eq!(pixbuf.get_bits_per_sample(), 8);
+    let nc = pixbuf.get_n_channels();
+    let rs = pixbuf.get_rowstride();
+    let width = pixbuf.get_width();
+    let n_rows = pixbuf.get_height();
+    let step = ((width * n_rows) as f64 / MAX_PIXBUF_RGBS as f64)
+        .sqrt()
+        .ceil()
+        .max(1.0) as usize;
+    let mut rgbs: Vec<RGB> = Vec::new();
+    unsafe {
+        let data = pixbuf.get_pixels();
+        for row_num in (0..n_rows).step_by(step) {
+            let row_start = row_num * rs;
+            for j in (0..width).step_by(step) {
+                let offset = (row_start + j * nc) as usize;
+                let array: [f64; 3] = [
+                    data[offset] as f64 / 255.0,
+                    data[offset + 1] as f64 / 255.0,
+                    data[offset + 2] as f64 / 255.0,
+                ];
+                rgbs.push(array.into());
+            }
+        }
+    }
+    rgbs
+}
+
+/// A copy of an 8 bit per sample pixbuf as seen with the given deficiency
+pub fn simulated_pixbuf(pixbuf: &Pixbuf, deficiency: VisionDeficiency) -> Pixbuf {
+    let simulated = match pixbuf.copy() {
+        Some(copy) => copy,
+        None => return pixbuf.clone(),
+    };
+    if simulated.get_bits_per_sample() != 8 {
+        return simulated;
+    }
+    let nc = simulated.get_n_channels();
+    let rs = simulated.get_rowstride();
+    let width = simulated.get_width();
+    let n_rows = simulated.get_height();
+    unsafe {
+        let data = simulated.get_pixels();
+        for row_num in 0..n_rows {
+            let row_start = row_num * rs;
+            for j in 0..width {
+                let offset = (row_start + j * nc) as usize;
+                let array: [f64; 3] = [
+                    data[offset] as f64 / 255.0,
+                    data[offset + 1] as f64 / 255.0,
+                    data[offset + 2] as f64 / 255.0,
+                ];
+                let rgb = deficiency.simulated_rgb(&array.into());
+                data[offset] = (rgb[CCI::Red] * 255.0).round() as u8;
+                data[offset + 1] = (rgb[CCI::Green] * 255.0).round() as u8;
+                data[offset + 2] = (rgb[CCI::Blue] * 255.0).round() as u8;
+            }
+        }
     }
+    simulated
 }
@@ -8,6 +8,7 @@ use std::str::FromStr;
 use pw_gix::gtk::{self, prelude::ComboBoxExtManual, ComboBoxTextExt};
 
 use regex::*;
+use serde_derive::*;
 
 #[derive(Debug)]
 pub struct CharacteristicError {
@@ -108,7 +109,7 @@ macro_rules! implement_entry_core {
 }
 
 // FINISH
-#[derive(Debug, PartialEq, Hash, Clone, Copy)]
+#[derive(Debug, PartialEq, Hash, Clone, Copy, Serialize, Deserialize)]
 pub enum Finish {
     Gloss,
     SemiGloss,
@@ -211,7 +212,7 @@ implement_entry_core!(Finish, FinishEntryCore);
 pub type FinishEntry = Rc<FinishEntryCore>;
 
 // TRANSPARENCY
-#[derive(Debug, PartialEq, Hash, Clone, Copy)]
+#[derive(Debug, PartialEq, Hash, Clone, Copy, Serialize, Deserialize)]
 pub enum Transparency {
     Opaque,
     SemiOpaque,
@@ -320,7 +321,7 @@ implement_entry_core!(Transparency, TransparencyEntryCore);
 pub type TransparencyEntry = Rc<TransparencyEntryCore>;
 
 // PERMANENCE
-#[derive(Debug, PartialEq, Hash, Clone, Copy)]
+#[derive(Debug, PartialEq, Hash, Clone, Copy, Serialize, Deserialize)]
 pub enum Permanence {
     ExtremelyPermanent,
     Permanent,
@@ -422,7 +423,7 @@ implement_entry_core!(Permanence, PermanenceEntryCore);
 pub type PermanenceEntry = Rc<PermanenceEntryCore>;
 
 // FLUORESCENCE
-#[derive(Debug, PartialEq, Hash, Clone, Copy)]
+#[derive(Debug, PartialEq, Hash, Clone, Copy, Serialize, Deserialize)]
 pub enum Fluorescence {
     Fluorescent,
     SemiFluorescent,
@@ -524,7 +525,7 @@ implement_entry_core!(Fluorescence, FluorescenceEntryCore);
 pub type FluorescenceEntry = Rc<FluorescenceEntryCore>;
 
 // METALLIC
-#[derive(Debug, PartialEq, Hash, Clone, Copy)]
+#[derive(Debug, PartialEq, Hash, Clone, Copy, Serialize, Deserialize)]
 pub enum Metallic {
     Metal,
     Metallic,
@@ -625,6 +626,128 @@ implement_entry_core!(Metallic, MetallicEntryCore);
 
 pub type MetallicEntry = Rc<MetallicEntryCore>;
 
+// DRYING TIME
+// NB: this is optional (most collection files don't give it)
+#[derive(Debug, PartialEq, Hash, Clone, Copy, Serialize, Deserialize)]
+pub enum DryingTime {
+    Unspecified,
+    Fast,
+    Medium,
+    Slow,
+    VerySlow,
+}
+
+impl Default for DryingTime {
+    fn default() -> DryingTime {
+        DryingTime::Unspecified
+    }
+}
+
+static DRYING_TIME_VALUES: &[DryingTime] = &[
+    DryingTime::Unspecified,
+    DryingTime::Fast,
+    DryingTime::Medium,
+    DryingTime::Slow,
+    DryingTime::VerySlow,
+];
+
+impl DryingTime {
+    pub fn is_specified(&self) -> bool {
+        *self != DryingTime::Unspecified
+    }
+}
+
+impl CharacteristicInterface for DryingTime {
+    fn name() -> &'static str {
+        "Drying Time"
+    }
+
+    fn abbrev(&self) -> &'static str {
+        match *self {
+            DryingTime::Unspecified => "U",
+            DryingTime::Fast => "F",
+            DryingTime::Medium => "M",
+            DryingTime::Slow => "S",
+            DryingTime::VerySlow => "VS",
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match *self {
+            DryingTime::Unspecified => "Unspecified",
+            DryingTime::Fast => "Fast (minutes)",
+            DryingTime::Medium => "Medium (an hour or so)",
+            DryingTime::Slow => "Slow (several hours)",
+            DryingTime::VerySlow => "Very slow (a day or more)",
+        }
+    }
+
+    fn values() -> &'static [DryingTime] {
+        DRYING_TIME_VALUES
+    }
+}
+
+lazy_static! {
+    pub static ref DRYING_TIME_RE: Regex =
+        Regex::new(r#"drying_time\s*=\s*"(?P<drying_time>\w+)""#).unwrap();
+}
+
+impl FromStr for DryingTime {
+    type Err = CharacteristicError;
+
+    fn from_str(string: &str) -> Result<DryingTime, CharacteristicError> {
+        let mut mstr = string;
+        if let Some(c) = DRYING_TIME_RE.captures(string) {
+            if let Some(m) = c.name("drying_time") {
+                mstr = m.as_str()
+            }
+        }
+        match mstr {
+            "U" | "Unspecified" => Ok(DryingTime::Unspecified),
+            "F" | "Fast (minutes)" => Ok(DryingTime::Fast),
+            "M" | "Medium (an hour or so)" => Ok(DryingTime::Medium),
+            "S" | "Slow (several hours)" => Ok(DryingTime::Slow),
+            "VS" | "Very slow (a day or more)" => Ok(DryingTime::VerySlow),
+            _ => Err(CharacteristicError::new(string)),
+        }
+    }
+}
+
+impl From<f64> for DryingTime {
+    fn from(float: f64) -> DryingTime {
+        match float.round() as u8 {
+            4 => DryingTime::Fast,
+            3 => DryingTime::Medium,
+            2 => DryingTime::Slow,
+            1 => DryingTime::VerySlow,
+            0 => DryingTime::Unspecified,
+            _ => panic!("{:?}: out of bounds DryingTime", float),
+        }
+    }
+}
+
+impl From<DryingTime> for f64 {
+    fn from(drying_time: DryingTime) -> f64 {
+        match drying_time {
+            DryingTime::Fast => 4.0,
+            DryingTime::Medium => 3.0,
+            DryingTime::Slow => 2.0,
+            DryingTime::VerySlow => 1.0,
+            DryingTime::Unspecified => 0.0,
+        }
+    }
+}
+
+impl fmt::Display for DryingTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "drying_time=\"{}\"", self.abbrev())
+    }
+}
+
+implement_entry_core!(DryingTime, DryingTimeEntryCore);
+
+pub type DryingTimeEntry = Rc<DryingTimeEntryCore>;
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -657,5 +780,15 @@ mod tests {
             Metallic::from_str(" metallic = \"NM\"").unwrap(),
             Metallic::Nonmetallic
         );
+
+        assert_eq!(
+            DryingTime::from_str(" drying_time = \"VS\"").unwrap(),
+            DryingTime::VerySlow
+        );
+        assert!(DryingTime::from_str(" metallic = \"NM\"").is_err());
+        for value in DryingTime::values().iter() {
+            assert_eq!(DryingTime::from(f64::from(*value)), *value);
+            assert_eq!(DryingTime::from_str(value.description()).unwrap(), *value);
+        }
     }
 }
@@ -0,0 +1,191 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::prelude::*;
+
+use pw_gix::{
+    glib::StaticType,
+    gtk::{self, prelude::*},
+    gtkx::dialog::*,
+    wrapper::*,
+};
+
+use crate::app_name;
+
+pub const BACKUP_SUFFIX: &str = "bak";
+pub const BACKUP_TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S";
+pub const DEFAULT_BACKUPS_KEPT: u32 = 3;
+pub const MAX_BACKUPS_KEPT: u32 = 20;
+
+// e.g. "my_paints.txt" is backed up as "my_paints.txt.20171225-093000.bak"
+pub fn backup_path_for(path: &Path, timestamp: &NaiveDateTime) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .unwrap_or_else(|| panic!("File: {:?} Line: {:?}", file!(), line!()));
+    let backup_name = format!(
+        "{}.{}.{}",
+        file_name.to_string_lossy(),
+        timestamp.format(BACKUP_TIMESTAMP_FORMAT),
+        BACKUP_SUFFIX
+    );
+    path.with_file_name(backup_name)
+}
+
+// The timestamp of the backup if "backup_name" names a backup of "file_name"
+pub fn backup_timestamp(file_name: &str, backup_name: &str) -> Option<NaiveDateTime> {
+    let middle = backup_name
+        .strip_prefix(file_name)?
+        .strip_prefix('.')?
+        .strip_suffix(BACKUP_SUFFIX)?
+        .strip_suffix('.')?;
+    NaiveDateTime::parse_from_str(middle, BACKUP_TIMESTAMP_FORMAT).ok()
+}
+
+// Backups of the file at "path" (most recent first)
+pub fn list_backups(path: &Path) -> Vec<(PathBuf, NaiveDateTime)> {
+    let mut backups: Vec<(PathBuf, NaiveDateTime)> = Vec::new();
+    let file_name = match path.file_name() {
+        Some(file_name) => file_name.to_string_lossy().to_string(),
+        None => return backups,
+    };
+    let dir = match path.parent() {
+        Some(dir) if dir.as_os_str().len() > 0 => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    if let Ok(entries) = fs::read_dir(&dir) {
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let backup_name = entry.file_name().to_string_lossy().to_string();
+            if let Some(timestamp) = backup_timestamp(&file_name, &backup_name) {
+                backups.push((entry.path(), timestamp));
+            }
+        }
+    }
+    backups.sort_by(|a, b| b.1.cmp(&a.1));
+    backups
+}
+
+// Copy the existing file (if any) to a new backup keeping at most "keep" backups.
+pub fn make_backup(path: &Path, keep: u32) -> io::Result<Option<PathBuf>> {
+    if keep == 0 || !path.exists() {
+        return Ok(None);
+    }
+    let backup_path = backup_path_for(path, &Local::now().naive_local());
+    fs::copy(path, &backup_path)?;
+    for (old_backup_path, _) in list_backups(path).iter().skip(keep as usize) {
+        fs::remove_file(old_backup_path)?;
+    }
+    Ok(Some(backup_path))
+}
+
+// Let the user choose one of the backups to restore
+pub fn choose_backup<W: WidgetWrapper>(
+    backups: &[(PathBuf, NaiveDateTime)],
+    caller: &W,
+) -> Option<PathBuf> {
+    let title = format!("{}: Restore from Backup", app_name());
+    let dialog = caller.new_dialog_with_buttons(
+        Some(&title),
+        gtk::DialogFlags::DESTROY_WITH_PARENT,
+        CANCEL_OK_BUTTONS,
+    );
+    dialog.set_size_from_recollections("restore_backup_dialog", (400, 250));
+    let list_store = gtk::ListStore::new(&[String::static_type(), String::static_type()]);
+    for (backup_path, timestamp) in backups.iter() {
+        let file_name = backup_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        list_store.insert_with_values(
+            None,
+            &[0, 1],
+            &[
+                &timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+                &file_name,
+            ],
+        );
+    }
+    let view = gtk::TreeView::with_model(&list_store);
+    view.set_headers_visible(true);
+    view.get_selection().set_mode(gtk::SelectionMode::Single);
+    for (col_id, title) in ["Saved", "File"].iter().enumerate() {
+        let col = gtk::TreeViewColumn::new();
+        col.set_title(title);
+        col.set_resizable(true);
+        let cell = gtk::CellRendererText::new();
+        col.pack_start(&cell, true);
+        col.add_attribute(&cell, "text", col_id as i32);
+        view.append_column(&col);
+    }
+    if let Some(iter) = list_store.get_iter_first() {
+        view.get_selection().select_iter(&iter);
+    }
+    let adj: Option<&gtk::Adjustment> = None;
+    let scrolled_window = gtk::ScrolledWindow::new(adj, adj);
+    scrolled_window.add(&view);
+    let content_area = dialog.get_content_area();
+    content_area.pack_start(&scrolled_window, true, true, 0);
+    content_area.show_all();
+    let mut o_chosen: Option<PathBuf> = None;
+    if gtk::ResponseType::from(dialog.run()) == gtk::ResponseType::Ok {
+        if let Some((model, iter)) = view.get_selection().get_selected() {
+            let index = model
+                .get_path(&iter)
+                .and_then(|path| path.get_indices().first().cloned());
+            if let Some(index) = index {
+                o_chosen = Some(backups[index as usize].0.clone());
+            }
+        }
+    }
+    unsafe { dialog.destroy() };
+    o_chosen
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backup_names_round_trip() {
+        let timestamp = NaiveDate::from_ymd(2017, 12, 25).and_hms(9, 30, 5);
+        let path = Path::new("/tmp/paints/my_paints.txt");
+        let backup_path = backup_path_for(path, &timestamp);
+        assert_eq!(
+            backup_path,
+            Path::new("/tmp/paints/my_paints.txt.20171225-093005.bak")
+        );
+        let backup_name = backup_path.file_name().unwrap().to_string_lossy();
+        assert_eq!(
+            backup_timestamp("my_paints.txt", &backup_name),
+            Some(timestamp)
+        );
+        assert_eq!(backup_timestamp("my_paints", &backup_name), None);
+        assert_eq!(
+            backup_timestamp("my_paints.txt", "my_paints.txt.garbage.bak"),
+            None
+        );
+    }
+
+    #[test]
+    fn make_backup_rotates() {
+        let dir = std::env::temp_dir().join(format!("epaint_backup_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("colln.txt");
+        assert_eq!(make_backup(&path, 2).unwrap(), None);
+        fs::write(&path, "contents").unwrap();
+        assert_eq!(make_backup(&path, 0).unwrap(), None);
+        for i in 0..4 {
+            let stale =
+                backup_path_for(&path, &NaiveDate::from_ymd(2000 + i, 1, 1).and_hms(0, 0, 0));
+            fs::write(&stale, "old").unwrap();
+        }
+        let backup_path = make_backup(&path, 2).unwrap().unwrap();
+        let backups = list_backups(&path);
+        assert_eq!(backups.len(), 2);
+        assert_eq!(backups[0].0, backup_path);
+        assert_eq!(fs::read_to_string(&backup_path).unwrap(), "contents");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,22 +1,81 @@
 // Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
 
 use std::cell::{Cell, RefCell};
-use std::fs::File;
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File};
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::str::FromStr;
+use std::time::SystemTime;
 
 use pw_gix::{
-    gtkx::notebook::*,
+    glib,
+    gtkx::{dialog::*, notebook::*},
     recollections::{recall, remember},
     wrapper::*,
 };
 
 use pw_pathux;
 
+use super::backup::{make_backup, DEFAULT_BACKUPS_KEPT};
 use super::collection::*;
+use super::formats::*;
+use super::inventory::Inventory;
+use super::legacy::*;
+use super::loader::*;
 use super::*;
+use crate::app_name;
 use crate::basic_paint::*;
+use crate::colour_difference::nearest_by_delta_e;
+use crate::recent_files::*;
+use crate::shape::{
+    boxed_filter, ColouredItemFilter, FilteredItemDisplay, SharedColouredItemFilter,
+};
+
+// Paints closer than this are listed as similar in paint information dialogs
+pub const SIMILAR_PAINT_MAX_DELTA_E: f64 = 3.0;
+// How often (in seconds) loaded files are checked for external changes
+pub const COLLN_FILE_WATCH_INTERVAL: u32 = 3;
+
+// e.g. "3" or "3!" when a load has failed
+pub fn load_status_badge(colln_count: usize, failure_count: usize) -> String {
+    if failure_count > 0 {
+        format!("{}!", colln_count)
+    } else {
+        format!("{}", colln_count)
+    }
+}
+
+pub fn load_status_text(
+    colln_count: usize,
+    paint_count: usize,
+    failures: &[(PathBuf, String)],
+) -> String {
+    let mut text = format!(
+        "{} collection{} loaded ({} paint{})",
+        colln_count,
+        if colln_count == 1 { "" } else { "s" },
+        paint_count,
+        if paint_count == 1 { "" } else { "s" },
+    );
+    if let Some((path, message)) = failures.last() {
+        text += &format!(
+            "\n{} failed load{}. Last: {:?}: {}",
+            failures.len(),
+            if failures.len() == 1 { "" } else { "s" },
+            path,
+            message
+        );
+    }
+    text
+}
+
+fn modification_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
 
 #[derive(PWO, Wrapper)]
 pub struct CollnPaintCollnBinderCore<A, C, CID>
@@ -30,8 +89,26 @@ where
     load_colln_button: gtk::Button,
     initiate_select_ok: Cell<bool>,
     paint_selected_callbacks: RefCell<Vec<Box<dyn Fn(&CollnPaint<C, CID>)>>>,
+    collns_changed_callbacks: RefCell<Vec<Box<dyn Fn()>>>,
+    load_status_changed_callbacks: RefCell<Vec<Box<dyn Fn()>>>,
+    load_failures: RefCell<Vec<(PathBuf, String)>>,
     paint_collns: RefCell<Vec<(CollnPaintCollnWidget<A, C, CID>, PathBuf)>>,
     paint_colln_files_data_path: PathBuf,
+    member_display_combo: gtk::ComboBoxText,
+    membership_test: RefCell<Option<PaintMembershipTest<C, CID>>>,
+    usage_source: RefCell<Option<PaintUsageSource<C, CID>>>,
+    inventory_edit: RefCell<Option<PaintInventoryEdit<C, CID>>>,
+    filter: RefCell<Option<SharedColouredItemFilter<CollnPaint<C, CID>>>>,
+    filtered_display: Cell<FilteredItemDisplay>,
+    current_target: RefCell<Option<Colour>>,
+    watch_files_btn: gtk::CheckButton,
+    auto_reload: Cell<bool>,
+    file_modification_times: RefCell<HashMap<PathBuf, SystemTime>>,
+    // Files are loaded (in the background) one at a time in order and
+    // those loaded at the user's request are added to the recent files
+    load_queue: RefCell<VecDeque<(PathBuf, bool)>>,
+    loading: RefCell<Option<PathBuf>>,
+    load_progress_bar: gtk::ProgressBar,
 }
 
 impl<A, C, CID> CollnPaintCollnBinderCore<A, C, CID>
@@ -72,6 +149,189 @@ where
         let selector = self.paint_collns.borrow_mut().remove(index);
         let page_num = self.notebook.page_num(&selector.0.pwo());
         self.notebook.remove_page(page_num);
+        self.inform_collns_changed();
+    }
+
+    // Paints in the other loaded collections within "max_delta_e" of "paint"
+    pub fn similar_paints_in_other_collns(
+        &self,
+        paint: &CollnPaint<C, CID>,
+        max_delta_e: f64,
+    ) -> Vec<(CollnPaint<C, CID>, f64)> {
+        let colln_id = paint.colln_id();
+        let others: Vec<CollnPaint<C, CID>> = self
+            .get_all_paints()
+            .into_iter()
+            .filter(|other| other.colln_id() != colln_id)
+            .collect();
+        nearest_by_delta_e(&others, &paint.rgb(), max_delta_e, |other| other.rgb())
+    }
+
+    pub fn get_all_paints(&self) -> Vec<CollnPaint<C, CID>> {
+        let mut paints = vec![];
+        for colln_data in self.paint_collns.borrow().iter() {
+            paints.extend(colln_data.0.get_paints().iter().cloned());
+        }
+        paints
+    }
+
+    pub fn connect_collns_changed<F: 'static + Fn()>(&self, callback: F) {
+        self.collns_changed_callbacks
+            .borrow_mut()
+            .push(Box::new(callback))
+    }
+
+    fn inform_collns_changed(&self) {
+        for callback in self.collns_changed_callbacks.borrow().iter() {
+            callback();
+        }
+        self.inform_load_status_changed();
+    }
+
+    pub fn colln_count(&self) -> usize {
+        self.paint_collns.borrow().len()
+    }
+
+    pub fn paint_count(&self) -> usize {
+        self.paint_collns
+            .borrow()
+            .iter()
+            .map(|(colln, _)| colln.get_paints().len())
+            .sum()
+    }
+
+    // The files that couldn't be loaded (oldest first) and why
+    pub fn load_failures(&self) -> Vec<(PathBuf, String)> {
+        self.load_failures.borrow().clone()
+    }
+
+    pub fn clear_load_failures(&self) {
+        self.load_failures.borrow_mut().clear();
+        self.inform_load_status_changed();
+    }
+
+    fn record_load_failure(&self, path: &Path, message: &str) {
+        self.load_failures
+            .borrow_mut()
+            .push((path.to_path_buf(), message.to_string()));
+        self.inform_load_status_changed();
+    }
+
+    fn forget_load_failure(&self, path: &Path) {
+        self.load_failures
+            .borrow_mut()
+            .retain(|(failed_path, _)| failed_path != path);
+    }
+
+    // Returns the path of the file that should be loaded (the original if
+    // the user declines or the upgrade fails)
+    fn offer_legacy_upgrade(&self, path: &Path) -> PathBuf {
+        let (colln_spec, assumptions) = match PaintCollnSpec::<C, CID>::from_legacy_file(path) {
+            Ok(result) => result,
+            // the normal load will report the problem
+            Err(_) => return path.to_path_buf(),
+        };
+        let upgraded_path = upgraded_file_path(path);
+        let mut expln = format!(
+            "\"{:?}\" was written by an older version of the program.\nAn upgraded copy can be written to \"{:?}\"{}.",
+            path,
+            upgraded_path,
+            if upgraded_path.exists() {
+                " (replacing the existing file)"
+            } else {
+                ""
+            }
+        );
+        if assumptions.len() > 0 {
+            expln += &format!("\n\nAssumptions made:\n{}", assumptions.join("\n"));
+        }
+        let buttons = [
+            ("Load Original", gtk::ResponseType::Other(0)),
+            ("Write and Load Upgraded Copy", gtk::ResponseType::Other(1)),
+        ];
+        if self.ask_question("Legacy Collection File", Some(expln.as_str()), &buttons)
+            == gtk::ResponseType::Other(1)
+        {
+            match colln_spec.write_upgraded_file(path) {
+                Ok(upgraded_path) => return upgraded_path,
+                Err(err) => self.report_error("Failed to write upgraded file", &err),
+            }
+        }
+        path.to_path_buf()
+    }
+
+    // Most recently loaded first
+    pub fn recent_colln_files(&self) -> Vec<PathBuf> {
+        recent_files(&CID::recollection_name_for("recent_colln_files"))
+    }
+
+    pub fn clear_recent_colln_files(&self) {
+        clear_recent_files(&CID::recollection_name_for("recent_colln_files"))
+    }
+
+    pub fn load_status_badge(&self) -> String {
+        load_status_badge(self.colln_count(), self.load_failures.borrow().len())
+    }
+
+    pub fn load_status_text(&self) -> String {
+        load_status_text(
+            self.colln_count(),
+            self.paint_count(),
+            &self.load_failures.borrow(),
+        )
+    }
+
+    pub fn connect_load_status_changed<F: 'static + Fn()>(&self, callback: F) {
+        self.load_status_changed_callbacks
+            .borrow_mut()
+            .push(Box::new(callback))
+    }
+
+    fn inform_load_status_changed(&self) {
+        for callback in self.load_status_changed_callbacks.borrow().iter() {
+            callback();
+        }
+    }
+
+    pub fn show_load_failures(&self) {
+        let title = format!("{}: Collection Load Failures", app_name());
+        let dialog = self.new_dialog_with_buttons(
+            Some(&title),
+            gtk::DialogFlags::DESTROY_WITH_PARENT,
+            &[
+                ("Clear", gtk::ResponseType::Other(1)),
+                ("Close", gtk::ResponseType::Close),
+            ],
+        );
+        if let Some(button) = dialog.get_widget_for_response(gtk::ResponseType::Other(1)) {
+            button.set_tooltip_text(Some(
+                "Forget these failures (and stop trying to load these collections at start up)",
+            ));
+        }
+        let failures_view = gtk::TextView::new();
+        failures_view.set_editable(false);
+        if let Some(buffer) = failures_view.get_buffer() {
+            let lines: Vec<String> = self
+                .load_failures
+                .borrow()
+                .iter()
+                .map(|(path, message)| format!("{:?}: {}", path, message))
+                .collect();
+            buffer.set_text(&lines.join("\n"));
+        }
+        let adj: Option<&gtk::Adjustment> = None;
+        let scrolled_window = gtk::ScrolledWindow::new(adj, adj);
+        scrolled_window.set_min_content_height(120);
+        scrolled_window.set_min_content_width(400);
+        scrolled_window.add(&failures_view);
+        let content_area = dialog.get_content_area();
+        content_area.pack_start(&scrolled_window, true, true, 0);
+        content_area.show_all();
+        if gtk::ResponseType::from(dialog.run()) == gtk::ResponseType::Other(1) {
+            self.clear_load_failures();
+            self.write_colln_file_paths();
+        }
+        unsafe { dialog.destroy() };
     }
 
     fn remove_paint_colln(&self, ps_id: &Rc<CID>) {
@@ -82,10 +342,163 @@ where
         }
     }
 
+    // Collections loaded later are also given the target
     pub fn set_target_colour(&self, ocolour: Option<&Colour>) {
         for selector in self.paint_collns.borrow().iter() {
             selector.0.set_target_colour(ocolour);
         }
+        *self.current_target.borrow_mut() = ocolour.cloned();
+    }
+
+    // The member display option is only offered once there's a membership test
+    pub fn set_membership_test(&self, membership_test: Option<PaintMembershipTest<C, CID>>) {
+        self.member_display_combo
+            .set_visible(membership_test.is_some());
+        for selector in self.paint_collns.borrow().iter() {
+            selector.0.set_membership_test(membership_test.clone());
+        }
+        *self.membership_test.borrow_mut() = membership_test;
+    }
+
+    // Collections loaded later are also filtered
+    pub fn set_filter(&self, filter: Option<ColouredItemFilter<CollnPaint<C, CID>>>) {
+        let o_filter: Option<SharedColouredItemFilter<CollnPaint<C, CID>>> = filter.map(Rc::from);
+        for selector in self.paint_collns.borrow().iter() {
+            selector.0.set_filter(o_filter.as_ref().map(boxed_filter));
+        }
+        *self.filter.borrow_mut() = o_filter;
+    }
+
+    pub fn set_filtered_display(&self, filtered_display: FilteredItemDisplay) {
+        for selector in self.paint_collns.borrow().iter() {
+            selector.0.set_filtered_display(filtered_display);
+        }
+        self.filtered_display.set(filtered_display);
+    }
+
+    pub fn member_display(&self) -> MemberPaintDisplay {
+        if let Some(id) = self.member_display_combo.get_active_id() {
+            if let Ok(member_display) = MemberPaintDisplay::from_str(&id) {
+                return member_display;
+            }
+        };
+        MemberPaintDisplay::default()
+    }
+
+    pub fn set_member_display(&self, member_display: MemberPaintDisplay) {
+        self.member_display_combo
+            .set_active_id(Some(member_display.name()));
+    }
+
+    pub fn refresh_membership(&self) {
+        for selector in self.paint_collns.borrow().iter() {
+            selector.0.refresh_membership();
+        }
+    }
+
+    // Collections loaded later also show the usage
+    pub fn set_usage_source(&self, usage_source: Option<PaintUsageSource<C, CID>>) {
+        for selector in self.paint_collns.borrow().iter() {
+            selector.0.set_usage_source(usage_source.clone());
+        }
+        *self.usage_source.borrow_mut() = usage_source;
+    }
+
+    pub fn set_inventory_edit(&self, inventory_edit: Option<PaintInventoryEdit<C, CID>>) {
+        for selector in self.paint_collns.borrow().iter() {
+            selector.0.set_inventory_edit(inventory_edit.clone());
+        }
+        *self.inventory_edit.borrow_mut() = inventory_edit;
+    }
+
+    // The inventory is recorded in the paint's collection file (unless
+    // it's CSV which has nowhere to put it) as well as in the paint.
+    // NB: JSON files are rewritten as full documents.
+    pub fn update_paint_inventory(
+        &self,
+        paint: &CollnPaint<C, CID>,
+        o_inventory: Option<Inventory>,
+    ) -> Result<(), PaintError<C>> {
+        let path = match self.find_cid(&paint.colln_id()) {
+            Ok(index) => self.paint_collns.borrow()[index].1.clone(),
+            Err(_) => return Err(PaintErrorType::NotFound(paint.name()).into()),
+        };
+        let (mut spec, _, format) =
+            PaintCollnSpec::<C, CID>::from_file_any_format(&path, PaintTypeCheck::Lenient, true)?;
+        if spec.get_index_for_name(&paint.name()).is_none() {
+            return Err(PaintErrorType::NotFound(paint.name()).into());
+        }
+        spec.set_inventory(&paint.name(), o_inventory.clone());
+        let backups_kept = recall(&CID::recollection_name_for("colln_backups_kept"))
+            .and_then(|text| u32::from_str(&text).ok())
+            .unwrap_or(DEFAULT_BACKUPS_KEPT);
+        match format {
+            CollnFileFormat::Text => {
+                let options =
+                    CollnWriteOptions::recall(&CID::recollection_name_for("colln_write_options"));
+                make_backup(&path, backups_kept)?;
+                fs::write(&path, spec.to_string_with(&options))?;
+            }
+            CollnFileFormat::Json => {
+                make_backup(&path, backups_kept)?;
+                spec.to_json_file(&path)?;
+            }
+            CollnFileFormat::Csv => {
+                let msg = format!("{:?}: CSV files can't record inventories", path);
+                return Err(PaintErrorType::MalformedText(msg).into());
+            }
+        }
+        // our own change isn't news
+        self.record_modification_time(&path);
+        paint.set_inventory(o_inventory);
+        self.refresh_membership();
+        Ok(())
+    }
+
+    pub fn is_watching_files(&self) -> bool {
+        self.watch_files_btn.get_active()
+    }
+
+    pub fn set_watch_files(&self, watch: bool) {
+        self.watch_files_btn.set_active(watch);
+    }
+
+    // Read only views can reload changed files without asking
+    pub fn set_auto_reload(&self, auto_reload: bool) {
+        self.auto_reload.set(auto_reload);
+    }
+
+    fn record_modification_time(&self, path: &Path) {
+        if let Some(time) = modification_time(path) {
+            self.file_modification_times
+                .borrow_mut()
+                .insert(path.to_path_buf(), time);
+        }
+    }
+
+    // The loaded files that have been modified since they were loaded (or
+    // last checked).  Each modification is only reported once.
+    fn changed_colln_files(&self) -> Vec<PathBuf> {
+        let mut changed: Vec<PathBuf> = vec![];
+        let mut times = self.file_modification_times.borrow_mut();
+        for (_, path) in self.paint_collns.borrow().iter() {
+            if let Some(time) = modification_time(path) {
+                if times.get(path).map_or(false, |recorded| *recorded != time) {
+                    changed.push(path.clone());
+                }
+                times.insert(path.clone(), time);
+            }
+        }
+        changed
+    }
+
+    fn reload_colln_file(&self, path: &Path) -> bool {
+        if let Some(index) = self.find_file_path(path) {
+            self.remove_paint_colln_at_index(index);
+            true
+        } else {
+            false
+        }
     }
 
     fn read_colln_file_paths(&self) -> Vec<PathBuf> {
@@ -105,11 +518,52 @@ where
         vpb
     }
 
+    pub fn is_loading(&self) -> bool {
+        self.loading.borrow().is_some()
+    }
+
+    fn is_queued_for_loading(&self, path: &Path) -> bool {
+        self.loading.borrow().as_ref().map_or(false, |p| p == path)
+            || self.load_queue.borrow().iter().any(|(p, _)| p == path)
+    }
+
+    fn update_load_progress(&self, fraction: f64) {
+        if let Some(ref path) = *self.loading.borrow() {
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            let n_queued = self.load_queue.borrow().len();
+            let text = if n_queued > 0 {
+                format!("Loading {} ({} more)", file_name, n_queued)
+            } else {
+                format!("Loading {}", file_name)
+            };
+            self.load_progress_bar.set_text(Some(&text));
+            self.load_progress_bar.set_fraction(fraction);
+            self.load_progress_bar.show();
+        } else {
+            self.load_progress_bar.hide();
+        }
+    }
+
+    // NB: files still waiting to be loaded are included as are those that
+    // failed to load (e.g. because their drive wasn't mounted) until the
+    // user clears the failures
     fn write_colln_file_paths(&self) {
+        let persisted = self.read_colln_file_paths();
         let mut text = String::new();
         for colln_data in self.paint_collns.borrow().iter() {
             text += (pw_pathux::path_to_string(&colln_data.1) + "\n").as_str();
         }
+        if let Some(ref path) = *self.loading.borrow() {
+            text += (pw_pathux::path_to_string(path) + "\n").as_str();
+        }
+        for (path, _) in self.load_queue.borrow().iter() {
+            text += (pw_pathux::path_to_string(path) + "\n").as_str();
+        }
+        for (path, _) in self.load_failures.borrow().iter() {
+            if persisted.contains(path) && !self.is_queued_for_loading(path) {
+                text += (pw_pathux::path_to_string(path) + "\n").as_str();
+            }
+        }
         match File::create(&self.paint_colln_files_data_path) {
             Ok(mut file) => file.write(&text.into_bytes()).unwrap_or_else(|err| {
                 panic!("File: {:?} Line: {:?} : {:?}", file!(), line!(), err)
@@ -136,7 +590,12 @@ where
     fn create(data_path: &Path) -> CollnPaintCollnBinder<A, C, CID>;
     fn _insert_paint_colln(&self, spec: &PaintCollnSpec<C, CID>, path: &Path, index: usize);
     fn _add_paint_colln_from_file(&self, path: &Path);
+    fn _queue_paint_colln_load(&self, path: &Path, remember_as_recent: bool);
+    fn _start_next_paint_colln_load(&self);
+    fn _add_paint_colln_from_result(&self, path: &Path, result: CollnLoadResult<C, CID>);
     fn load_paint_colln_from_file(&self);
+    fn load_paint_colln_from_path(&self, path: &Path);
+    fn offer_to_reload_changed_files(&self);
 }
 
 impl<A, C, CID> CollnPaintCollnBinderInterface<A, C, CID> for CollnPaintCollnBinder<A, C, CID>
@@ -152,8 +611,24 @@ where
             load_colln_button: gtk::Button::new(),
             initiate_select_ok: Cell::new(false),
             paint_selected_callbacks: RefCell::new(Vec::new()),
+            collns_changed_callbacks: RefCell::new(Vec::new()),
+            load_status_changed_callbacks: RefCell::new(Vec::new()),
+            load_failures: RefCell::new(Vec::new()),
             paint_collns: RefCell::new(Vec::new()),
             paint_colln_files_data_path: data_path.to_path_buf(),
+            member_display_combo: gtk::ComboBoxText::new(),
+            membership_test: RefCell::new(None),
+            usage_source: RefCell::new(None),
+            inventory_edit: RefCell::new(None),
+            filter: RefCell::new(None),
+            filtered_display: Cell::new(FilteredItemDisplay::default()),
+            current_target: RefCell::new(None),
+            watch_files_btn: gtk::CheckButton::with_label("Watch Files"),
+            auto_reload: Cell::new(false),
+            file_modification_times: RefCell::new(HashMap::new()),
+            load_queue: RefCell::new(VecDeque::new()),
+            loading: RefCell::new(None),
+            load_progress_bar: gtk::ProgressBar::new(),
         });
         cpcb.notebook.set_scrollable(true);
         cpcb.notebook.popup_enable();
@@ -163,8 +638,72 @@ where
         cpcb.load_colln_button
             .set_image(Some(&CID::colln_load_image(24)));
 
+        for member_display in MemberPaintDisplay::values() {
+            cpcb.member_display_combo
+                .append(Some(member_display.name()), member_display.label());
+        }
+        cpcb.member_display_combo
+            .set_tooltip_text(Some("How to display paints that have already been added"));
+        cpcb.set_member_display(MemberPaintDisplay::recall(&CID::recollection_name_for(
+            "member_paint_display",
+        )));
+        let cpcb_c = cpcb.clone();
+        cpcb.member_display_combo.connect_changed(move |_| {
+            let member_display = cpcb_c.member_display();
+            for selector in cpcb_c.paint_collns.borrow().iter() {
+                selector.0.set_member_display(member_display);
+            }
+            member_display.remember(&CID::recollection_name_for("member_paint_display"));
+        });
+        cpcb.member_display_combo.set_no_show_all(true);
+
+        cpcb.watch_files_btn.set_tooltip_text(Some(
+            "Check the loaded collection files for changes made by other programs\nand offer to reload them.",
+        ));
+        cpcb.watch_files_btn.set_active(
+            recall(&CID::recollection_name_for("watch_colln_files")) == Some("true".to_string()),
+        );
+        let cpcb_c = cpcb.clone();
+        cpcb.watch_files_btn.connect_toggled(move |button| {
+            let text = if button.get_active() { "true" } else { "false" };
+            remember(&CID::recollection_name_for("watch_colln_files"), text);
+            // Changes made while not watching aren't news
+            let paths: Vec<PathBuf> = cpcb_c
+                .paint_collns
+                .borrow()
+                .iter()
+                .map(|(_, path)| path.clone())
+                .collect();
+            for path in paths.iter() {
+                cpcb_c.record_modification_time(path);
+            }
+        });
+        let weak_cpcb = Rc::downgrade(&cpcb);
+        glib::timeout_add_seconds_local(COLLN_FILE_WATCH_INTERVAL, move || {
+            if let Some(cpcb) = weak_cpcb.upgrade() {
+                if cpcb.is_watching_files() {
+                    cpcb.offer_to_reload_changed_files();
+                }
+                glib::Continue(true)
+            } else {
+                glib::Continue(false)
+            }
+        });
+
         let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 0);
         hbox.pack_start(&cpcb.load_colln_button, false, true, 2);
+        let cpcb_c = cpcb.clone();
+        let recent_colln_button = recent_files_button(
+            &CID::recollection_name_for("recent_colln_files"),
+            "Load a recently used paint collection file",
+            move |path| cpcb_c.load_paint_colln_from_path(path),
+        );
+        hbox.pack_start(&recent_colln_button, false, true, 2);
+        hbox.pack_start(&cpcb.watch_files_btn, false, true, 2);
+        cpcb.load_progress_bar.set_show_text(true);
+        cpcb.load_progress_bar.set_no_show_all(true);
+        hbox.pack_start(&cpcb.load_progress_bar, true, true, 2);
+        hbox.pack_end(&cpcb.member_display_combo, false, true, 2);
         cpcb.vbox.pack_start(&hbox, false, false, 2);
         cpcb.vbox.pack_start(&cpcb.notebook, true, true, 0);
 
@@ -186,7 +725,19 @@ where
     fn _insert_paint_colln(&self, colln_spec: &PaintCollnSpec<C, CID>, path: &Path, index: usize) {
         let mut paint_collns = self.paint_collns.borrow_mut();
         let paint_colln = CollnPaintCollnWidget::<A, C, CID>::create(&colln_spec);
+        self.record_modification_time(path);
         paint_colln.set_initiate_select_ok(self.initiate_select_ok.get());
+        paint_colln.set_member_display(self.member_display());
+        paint_colln.set_membership_test(self.membership_test.borrow().clone());
+        paint_colln.set_usage_source(self.usage_source.borrow().clone());
+        paint_colln.set_inventory_edit(self.inventory_edit.borrow().clone());
+        paint_colln.set_filtered_display(self.filtered_display.get());
+        paint_colln.set_filter(self.filter.borrow().as_ref().map(boxed_filter));
+        paint_colln.set_target_colour(self.current_target.borrow().as_ref());
+        let cpcb_c = self.clone();
+        paint_colln.set_similar_paints_search(Some(Rc::new(move |paint| {
+            cpcb_c.similar_paints_in_other_collns(paint, SIMILAR_PAINT_MAX_DELTA_E)
+        })));
         paint_collns.insert(index, (paint_colln.clone(), path.to_path_buf()));
         let cpcb_c = self.clone();
         paint_colln.connect_paint_selected(move |paint| cpcb_c.inform_paint_selected(paint));
@@ -201,6 +752,9 @@ where
             colln_spec.colln_id.colln_owner()
         );
         let label = TabRemoveLabel::create(Some(l_text.as_str()), Some(&tt_text.as_str()));
+        label
+            .pwo()
+            .set_tooltip_text(Some(colln_spec.tooltip_text().as_str()));
         let l_text = format!(
             "{} ({})",
             colln_spec.colln_id.colln_name(),
@@ -218,54 +772,139 @@ where
         );
     }
 
+    // The file is loaded in the background (after any already queued)
     fn _add_paint_colln_from_file(&self, path: &Path) {
-        match PaintCollnSpec::<C, CID>::from_file(path) {
-            Ok(colln_spec) => match self.find_cid(&colln_spec.colln_id) {
-                Ok(index) => {
-                    let other_file_path = &self.paint_collns.borrow()[index].1;
-                    let expln = format!(
+        self._queue_paint_colln_load(path, false);
+    }
+
+    fn _queue_paint_colln_load(&self, path: &Path, remember_as_recent: bool) {
+        if self.is_queued_for_loading(path) {
+            return;
+        }
+        self.load_queue
+            .borrow_mut()
+            .push_back((path.to_path_buf(), remember_as_recent));
+        if !self.is_loading() {
+            self._start_next_paint_colln_load();
+        } else {
+            self.update_load_progress(self.load_progress_bar.get_fraction());
+        }
+    }
+
+    fn _start_next_paint_colln_load(&self) {
+        let next = self.load_queue.borrow_mut().pop_front();
+        let (path, remember_as_recent) = match next {
+            Some(item) => item,
+            None => {
+                *self.loading.borrow_mut() = None;
+                self.update_load_progress(0.0);
+                return;
+            }
+        };
+        *self.loading.borrow_mut() = Some(path.clone());
+        self.update_load_progress(0.0);
+        let cpcb_c = self.clone();
+        let cpcb_d = self.clone();
+        let path_c = path.clone();
+        load_colln_spec_async(
+            &path,
+            PaintTypeCheck::Lenient,
+            false,
+            move |fraction| cpcb_c.update_load_progress(fraction),
+            move |result| {
+                *cpcb_d.loading.borrow_mut() = None;
+                cpcb_d._add_paint_colln_from_result(&path_c, result);
+                if remember_as_recent && cpcb_d.find_file_path(&path_c).is_some() {
+                    add_recent_file(&CID::recollection_name_for("recent_colln_files"), &path_c);
+                }
+                cpcb_d._start_next_paint_colln_load();
+            },
+        );
+    }
+
+    fn _add_paint_colln_from_result(&self, path: &Path, result: CollnLoadResult<C, CID>) {
+        match result {
+            Ok((mut colln_spec, mut warnings, format)) => {
+                self.forget_load_failure(path);
+                if format != CollnFileFormat::Text {
+                    warnings.push(format!("Read as {} format", format));
+                }
+                let colln_id = colln_spec.colln_id.canonicalized();
+                if colln_id != *colln_spec.colln_id {
+                    warnings.push(format!(
+                        "{} \"{}\" treated as \"{}\"",
+                        CID::colln_owner_label(),
+                        colln_spec.colln_id.colln_owner(),
+                        colln_id.colln_owner()
+                    ));
+                    colln_spec.colln_id = Rc::new(colln_id);
+                }
+                if warnings.len() > 0 {
+                    let expln = format!("\"{:?}\":\n{}\n", path, warnings.join("\n"));
+                    let msg = "Collection Loaded With Warnings";
+                    self.warn_user(msg, Some(expln.as_str()));
+                }
+                match self.find_cid(&colln_spec.colln_id) {
+                    Ok(index) => {
+                        let other_file_path = &self.paint_collns.borrow()[index].1;
+                        let expln = format!(
                             "\"{}\" ({}): already included in the tool box.\nLoaded from file \"{:?}\".",
                             colln_spec.colln_id.colln_name(),
                             colln_spec.colln_id.colln_owner(),
                             other_file_path,
                         );
-                    let buttons = [
-                        ("Skip", gtk::ResponseType::Other(0)),
-                        ("Replace", gtk::ResponseType::Other(1)),
-                    ];
-                    if self.ask_question("Duplicate Collection", Some(expln.as_str()), &buttons)
-                        == gtk::ResponseType::Other(1)
-                    {
-                        self.remove_paint_colln_at_index(index);
+                        let buttons = [
+                            ("Skip", gtk::ResponseType::Other(0)),
+                            ("Replace", gtk::ResponseType::Other(1)),
+                        ];
+                        if self.ask_question("Duplicate Collection", Some(expln.as_str()), &buttons)
+                            == gtk::ResponseType::Other(1)
+                        {
+                            self.remove_paint_colln_at_index(index);
+                            self._insert_paint_colln(&colln_spec, path, index);
+                            self.notebook.show_all();
+                            self.write_colln_file_paths();
+                            self.inform_collns_changed();
+                        }
+                    }
+                    Err(index) => {
                         self._insert_paint_colln(&colln_spec, path, index);
                         self.notebook.show_all();
                         self.write_colln_file_paths();
+                        self.inform_collns_changed();
                     }
                 }
-                Err(index) => {
-                    self._insert_paint_colln(&colln_spec, path, index);
-                    self.notebook.show_all();
-                    self.write_colln_file_paths();
-                }
-            },
-            Err(err) => match err.error_type() {
-                &PaintErrorType::IOError(ref io_error) => {
-                    let expln = format!("\"{:?}\" \"{}\"\n", path, io_error.to_string());
-                    let msg = "I/O Error";
-                    self.warn_user(msg, Some(expln.as_str()));
-                }
-                &PaintErrorType::MalformedText(_) => {
-                    let expln = format!("Error parsing \"{:?}\"\n", path);
-                    let msg = "Malformed Collection Specification Text";
-                    self.warn_user(msg, Some(expln.as_str()));
-                }
-                &PaintErrorType::AlreadyExists(ref text) => {
-                    let expln = format!("\"{:?}\" contains two paints named\"{}\"\n", path, text);
-                    let msg = "Malformed Collection (Duplicate Paints)";
-                    self.warn_user(msg, Some(expln.as_str()));
+            }
+            Err(err) => {
+                self.record_load_failure(path, &err.to_string());
+                match err.error_type() {
+                    &PaintErrorType::IOError(ref io_error) => {
+                        let expln = format!("\"{:?}\" \"{}\"\n", path, io_error.to_string());
+                        let msg = "I/O Error";
+                        self.warn_user(msg, Some(expln.as_str()));
+                    }
+                    &PaintErrorType::MalformedText(_) => {
+                        let expln = format!("Error parsing \"{:?}\"\n", path);
+                        let msg = "Malformed Collection Specification Text";
+                        self.warn_user(msg, Some(expln.as_str()));
+                    }
+                    &PaintErrorType::AlreadyExists(ref text) => {
+                        let expln =
+                            format!("\"{:?}\" contains two paints named\"{}\"\n", path, text);
+                        let msg = "Malformed Collection (Duplicate Paints)";
+                        self.warn_user(msg, Some(expln.as_str()));
+                    }
+                    &PaintErrorType::WrongPaintType(ref text) => {
+                        let expln = format!("\"{:?}\" contains \"{}\"\n", path, text);
+                        let msg = "Wrong Paint Type In Collection";
+                        self.warn_user(msg, Some(expln.as_str()));
+                    }
+                    _ => {
+                        let msg = format!("{:?}: Failed to load", path);
+                        self.report_error(&msg, &err);
+                    }
                 }
-                _ => panic!("Unexpected error."),
-            },
+            }
         }
     }
 
@@ -277,40 +916,68 @@ where
             None
         };
         if let Some(path) = self.ask_file_path(Some("Collection File Name:"), last_file, true) {
-            match pw_pathux::expand_home_dir_or_mine(&path).canonicalize() {
-                Ok(abs_file_path) => {
-                    if let Some(index) = self.find_file_path(&abs_file_path) {
-                        let colln_id = &self.paint_collns.borrow()[index].0.colln_id();
-                        let expln = format!(
-                            "\"{:?}\": already loaded providing \"{}\" ({}).",
-                            path,
-                            colln_id.colln_name(),
-                            colln_id.colln_owner(),
-                        );
-                        let buttons = [
-                            ("Cancel", gtk::ResponseType::Other(0)),
-                            ("Reload", gtk::ResponseType::Other(1)),
-                        ];
-                        if self.ask_question("Duplicate Collection", Some(expln.as_str()), &buttons)
-                            == gtk::ResponseType::Other(1)
-                        {
-                            self.remove_paint_colln_at_index(index);
-                        } else {
-                            return;
-                        }
-                    };
-                    self._add_paint_colln_from_file(&abs_file_path);
-                    let path_text = pw_pathux::path_to_string(&abs_file_path);
-                    remember(
-                        &CID::recollection_name_for("last_colln_loaded_file"),
-                        &path_text,
+            self.load_paint_colln_from_path(&path);
+        }
+    }
+
+    fn load_paint_colln_from_path(&self, path: &Path) {
+        match pw_pathux::expand_home_dir_or_mine(path).canonicalize() {
+            Ok(abs_file_path) => {
+                if let Some(index) = self.find_file_path(&abs_file_path) {
+                    let colln_id = &self.paint_collns.borrow()[index].0.colln_id();
+                    let expln = format!(
+                        "\"{:?}\": already loaded providing \"{}\" ({}).",
+                        path,
+                        colln_id.colln_name(),
+                        colln_id.colln_owner(),
                     );
+                    let buttons = [
+                        ("Cancel", gtk::ResponseType::Other(0)),
+                        ("Reload", gtk::ResponseType::Other(1)),
+                    ];
+                    if self.ask_question("Duplicate Collection", Some(expln.as_str()), &buttons)
+                        == gtk::ResponseType::Other(1)
+                    {
+                        self.remove_paint_colln_at_index(index);
+                    } else {
+                        return;
+                    }
+                };
+                let abs_file_path = if is_legacy_file(&abs_file_path) {
+                    self.offer_legacy_upgrade(&abs_file_path)
+                } else {
+                    abs_file_path
+                };
+                self._queue_paint_colln_load(&abs_file_path, true);
+                let path_text = pw_pathux::path_to_string(&abs_file_path);
+                remember(
+                    &CID::recollection_name_for("last_colln_loaded_file"),
+                    &path_text,
+                );
+            }
+            Err(err) => {
+                self.record_load_failure(path, &err.to_string());
+                let expln = format!("\"{:?}\" \"{}\"\n", path, err.to_string());
+                let msg = "I/O Error";
+                self.warn_user(msg, Some(expln.as_str()));
+            }
+        }
+    }
+
+    fn offer_to_reload_changed_files(&self) {
+        for path in self.changed_colln_files().iter() {
+            if !self.auto_reload.get() {
+                let msg = format!("\"{:?}\" has been changed by another program.", path);
+                let buttons = [
+                    ("Keep Current", gtk::ResponseType::Other(0)),
+                    ("Reload", gtk::ResponseType::Other(1)),
+                ];
+                if self.ask_question(&msg, None, &buttons) != gtk::ResponseType::Other(1) {
+                    continue;
                 }
-                Err(err) => {
-                    let expln = format!("\"{:?}\" \"{}\"\n", path, err.to_string());
-                    let msg = "I/O Error";
-                    self.warn_user(msg, Some(expln.as_str()));
-                }
+            }
+            if self.reload_colln_file(path) {
+                self._add_paint_colln_from_file(path);
             }
         }
     }
@@ -318,8 +985,20 @@ where
 
 #[cfg(test)]
 mod tests {
-    //use super::*;
+    use super::*;
 
     #[test]
-    fn it_works() {}
+    fn load_status() {
+        assert_eq!(load_status_badge(3, 0), "3");
+        assert_eq!(load_status_badge(3, 2), "3!");
+        assert_eq!(
+            load_status_text(1, 12, &[]),
+            "1 collection loaded (12 paints)"
+        );
+        let failures = vec![(PathBuf::from("a.txt"), "No such file".to_string())];
+        assert_eq!(
+            load_status_text(2, 1, &failures),
+            "2 collections loaded (1 paint)\n1 failed load. Last: \"a.txt\": No such file"
+        );
+    }
 }
@@ -1,21 +1,32 @@
 // Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
 
 use std::cell::{Cell, RefCell};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::io;
 use std::marker::PhantomData;
+use std::path::Path;
 use std::rc::Rc;
+use std::str::FromStr;
 
 use pw_gix::{
     gdk,
-    glib::signal::SignalHandlerId,
+    glib::{self, signal::SignalHandlerId, StaticType, ToValue},
     gtk::{self, prelude::*},
     gtkx::{list_store::*, menu::*, paned::*, tree_view_column::*},
+    recollections::{recall, remember},
 };
 
 use crate::basic_paint::*;
+use crate::blend_preview::*;
 use crate::cairox::*;
+use crate::colour_difference::{close_pairs, DeltaEMetric};
+use crate::dialogue::*;
+use crate::error::*;
 use crate::graticule::*;
+use crate::palette_export::*;
 use crate::shape::*;
+use crate::text_search::*;
+use crate::wheel_pages::*;
 
 use super::display::*;
 use super::*;
@@ -62,6 +73,33 @@ where
     pub fn has_paint_named(&self, name: &str) -> bool {
         self.find_name(name).is_ok()
     }
+
+    // Pairs of paints whose colours are within "max_delta_e" of each other
+    pub fn suspected_duplicates(
+        &self,
+        max_delta_e: f64,
+        metric: DeltaEMetric,
+    ) -> Vec<(CollnPaint<C, CID>, CollnPaint<C, CID>, f64)> {
+        close_pairs(&self.paints, max_delta_e, metric, |paint| paint.rgb())
+    }
+
+    // e.g. "Flat Acrylic (Tamiya)"
+    pub fn palette_name(&self) -> String {
+        format!(
+            "{} ({})",
+            self.colln_id.colln_name(),
+            self.colln_id.colln_owner()
+        )
+    }
+
+    pub fn write_gpl_palette(&self, path: &Path) -> io::Result<()> {
+        let entries: Vec<(String, RGB)> = self
+            .paints
+            .iter()
+            .map(|paint| (paint.name(), paint.rgb()))
+            .collect();
+        write_gpl_palette(path, &self.palette_name(), &entries)
+    }
 }
 
 pub type CollnPaintColln<C, CID> = Rc<CollnPaintCollnCore<C, CID>>;
@@ -86,6 +124,7 @@ where
             // Assume that the spec list is ordered and names are unique
             let basic_paint = BasicPaint::<C>::from_spec(paint_spec);
             let colln_paint = CollnPaint::<C, CID>::create(&basic_paint, &colln_id);
+            colln_paint.set_inventory(colln_spec.inventory_for(&paint_spec.name));
             paints.push(colln_paint);
         }
         Rc::new(CollnPaintCollnCore::<C, CID> {
@@ -95,6 +134,239 @@ where
     }
 }
 
+// COLLECTION BUILDER
+pub struct PaintCollnBuilder<C, CID>
+where
+    C: CharacteristicsInterface,
+    CID: CollnIdInterface,
+{
+    colln_id: Rc<CID>,
+    colln_notes: String,
+    paint_specs: Vec<BasicPaintSpec<C>>, // sorted
+}
+
+impl<C, CID> PaintCollnBuilder<C, CID>
+where
+    C: CharacteristicsInterface,
+    CID: CollnIdInterface,
+{
+    pub fn new(colln_name: &str, colln_owner: &str) -> PaintCollnBuilder<C, CID> {
+        PaintCollnBuilder::<C, CID> {
+            colln_id: Rc::new(CID::new(colln_name, colln_owner)),
+            colln_notes: String::new(),
+            paint_specs: Vec::new(),
+        }
+    }
+
+    pub fn set_colln_notes(&mut self, notes: &str) {
+        self.colln_notes = notes.to_string();
+    }
+
+    pub fn add_paint_spec(&mut self, spec: BasicPaintSpec<C>) -> Result<(), PaintError<C>> {
+        match self
+            .paint_specs
+            .binary_search_by_key(&spec.name, |bps| bps.name.clone())
+        {
+            Ok(_) => Err(PaintErrorType::AlreadyExists(spec.name).into()),
+            Err(index) => {
+                self.paint_specs.insert(index, spec);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn add_paint(
+        &mut self,
+        name: &str,
+        rgb: RGB,
+        characteristics: C,
+    ) -> Result<(), PaintError<C>> {
+        self.add_paint_with_notes(name, rgb, characteristics, "")
+    }
+
+    pub fn add_paint_with_notes(
+        &mut self,
+        name: &str,
+        rgb: RGB,
+        characteristics: C,
+        notes: &str,
+    ) -> Result<(), PaintError<C>> {
+        self.add_paint_spec(BasicPaintSpec::<C> {
+            rgb,
+            name: name.to_string(),
+            notes: notes.to_string(),
+            characteristics,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.paint_specs.len()
+    }
+
+    pub fn spec(&self) -> PaintCollnSpec<C, CID> {
+        PaintCollnSpec::<C, CID> {
+            colln_id: self.colln_id.clone(),
+            colln_notes: self.colln_notes.clone(),
+            paint_specs: self.paint_specs.clone(),
+            inventories: BTreeMap::new(),
+            unknown_lines: Vec::new(),
+        }
+    }
+
+    pub fn build(&self) -> CollnPaintColln<C, CID> {
+        CollnPaintColln::<C, CID>::from_spec(&self.spec())
+    }
+}
+
+// PAGINATION
+pub const DEFAULT_PAGE_SIZE: usize = 200;
+pub const MIN_PAGE_SIZE: usize = 10;
+pub const MAX_PAGE_SIZE: usize = 5000;
+pub const INDEX_LETTERS: &str = "#ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+pub fn page_count(n_items: usize, page_size: usize) -> usize {
+    if n_items == 0 || page_size == 0 {
+        1
+    } else {
+        (n_items + page_size - 1) / page_size
+    }
+}
+
+pub fn page_range(n_items: usize, page_size: usize, page: usize) -> std::ops::Range<usize> {
+    if page_size == 0 {
+        return 0..n_items;
+    }
+    let start = (page * page_size).min(n_items);
+    start..(start + page_size).min(n_items)
+}
+
+// Names that don't start with a letter are indexed under "#"
+pub fn index_letter(name: &str) -> char {
+    match normalise(name).chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => c.to_ascii_uppercase(),
+        _ => '#',
+    }
+}
+
+// The index of the first of the names indexed under "letter" or, if
+// there are none, of the first of those under the nearest following
+// letter.  The names needn't be sorted by their (normalised) letters as
+// the display order is case sensitive and accents sort after "z".
+pub fn first_index_at_letter(names: &[String], letter: char) -> Option<usize> {
+    if names.len() == 0 {
+        return None;
+    }
+    let letters: Vec<char> = names.iter().map(|name| index_letter(name)).collect();
+    if let Some(index) = letters
+        .iter()
+        .position(|name_letter| *name_letter == letter)
+    {
+        return Some(index);
+    }
+    let index = letters
+        .iter()
+        .enumerate()
+        .filter(|(_, name_letter)| **name_letter != '#' && **name_letter > letter)
+        .min_by_key(|(index, name_letter)| (**name_letter, *index))
+        .map(|(index, _)| index)
+        .unwrap_or(names.len() - 1);
+    Some(index)
+}
+
+// MEMBERSHIP
+// Whether a paint is already a member of some other set (e.g. a mixer's palette)
+pub type PaintMembershipTest<C, CID> = Rc<dyn Fn(&CollnPaint<C, CID>) -> bool>;
+
+// USAGE
+// How much a paint has been used (e.g. in a mixer's mixtures)
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct PaintUsage {
+    pub mixture_count: u32,
+    pub total_parts: u32,
+}
+
+pub type PaintUsageSource<C, CID> = Rc<dyn Fn(&CollnPaint<C, CID>) -> PaintUsage>;
+
+// Finds paints (with their ΔE) similar to the given paint
+pub type SimilarPaintsSearch<C, CID> =
+    Rc<dyn Fn(&CollnPaint<C, CID>) -> Vec<(CollnPaint<C, CID>, f64)>>;
+
+// Lets the user edit a paint's inventory (e.g. series paints only)
+pub type PaintInventoryEdit<C, CID> = Rc<dyn Fn(&CollnPaint<C, CID>)>;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum MemberPaintDisplay {
+    Show,
+    GreyOut,
+    Hide,
+}
+
+impl Default for MemberPaintDisplay {
+    fn default() -> MemberPaintDisplay {
+        MemberPaintDisplay::Show
+    }
+}
+
+static MEMBER_PAINT_DISPLAY_VALUES: &[MemberPaintDisplay] = &[
+    MemberPaintDisplay::Show,
+    MemberPaintDisplay::GreyOut,
+    MemberPaintDisplay::Hide,
+];
+
+impl MemberPaintDisplay {
+    pub fn values() -> Vec<MemberPaintDisplay> {
+        MEMBER_PAINT_DISPLAY_VALUES.to_vec()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            MemberPaintDisplay::Show => "show",
+            MemberPaintDisplay::GreyOut => "grey_out",
+            MemberPaintDisplay::Hide => "hide",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match *self {
+            MemberPaintDisplay::Show => "Show Added Paints",
+            MemberPaintDisplay::GreyOut => "Grey Out Added Paints",
+            MemberPaintDisplay::Hide => "Hide Added Paints",
+        }
+    }
+
+    pub fn recall(key: &str) -> MemberPaintDisplay {
+        if let Some(text) = recall(key) {
+            if let Ok(member_display) = MemberPaintDisplay::from_str(&text) {
+                return member_display;
+            }
+        };
+        MemberPaintDisplay::default()
+    }
+
+    pub fn remember(&self, key: &str) {
+        remember(key, self.name())
+    }
+}
+
+impl FromStr for MemberPaintDisplay {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<MemberPaintDisplay, String> {
+        for member_display in MEMBER_PAINT_DISPLAY_VALUES.iter() {
+            if member_display.name() == string {
+                return Ok(*member_display);
+            }
+        }
+        Err(format!("{}: unknown member paint display", string))
+    }
+}
+
+// Grey out the row by using the paint's monochrome colours
+fn grey_out_tv_rows(rows: &mut Vec<glib::Value>) {
+    rows[SP_RGB as usize] = rows[SP_MONO_RGB as usize].clone();
+    rows[SP_RGB_FG as usize] = rows[SP_MONO_RGB_FG as usize].clone();
+}
+
 #[derive(PWO, Wrapper)]
 pub struct CollnPaintCollnViewCore<A, C, CID>
 where
@@ -102,10 +374,27 @@ where
     C: CharacteristicsInterface + 'static,
     CID: CollnIdInterface,
 {
+    vbox: gtk::Box,
     scrolled_window: gtk::ScrolledWindow,
     list_store: gtk::ListStore,
     view: gtk::TreeView,
     colln: CollnPaintColln<C, CID>,
+    row_colouring: Cell<RowColouring>,
+    filter_entry: gtk::SearchEntry,
+    paginate_btn: gtk::CheckButton,
+    page_size_spin: gtk::SpinButton,
+    prev_page_btn: gtk::Button,
+    next_page_btn: gtk::Button,
+    page_label: gtk::Label,
+    index_letter_combo: gtk::ComboBoxText,
+    current_page: Cell<usize>,
+    filtered_paints: RefCell<Vec<CollnPaint<C, CID>>>,
+    normaliser: SearchNormaliser,
+    membership_test: RefCell<Option<PaintMembershipTest<C, CID>>>,
+    member_display: Cell<MemberPaintDisplay>,
+    filter: RefCell<Option<ColouredItemFilter<CollnPaint<C, CID>>>>,
+    filtered_display: Cell<FilteredItemDisplay>,
+    usage_source: RefCell<Option<PaintUsageSource<C, CID>>>,
     phantom_data: PhantomData<A>,
 }
 
@@ -115,27 +404,42 @@ where
     C: CharacteristicsInterface + 'static,
     CID: CollnIdInterface,
 {
-    pub fn get_paint_at(&self, posn: (f64, f64)) -> Option<CollnPaint<C, CID>> {
+    fn try_get_paint_at(&self, posn: (f64, f64)) -> PaintResult<Option<CollnPaint<C, CID>>, C> {
         let x = posn.0 as i32;
         let y = posn.1 as i32;
         if let Some(location) = self.view.get_path_at_pos(x, y) {
             if let Some(path) = location.0 {
                 if let Some(iter) = self.list_store.get_iter(&path) {
-                    let name: String = self
-                        .list_store
-                        .get_value(&iter, 0)
-                        .get()
-                        .unwrap()
-                        .unwrap_or_else(|| panic!("File: {:?} Line: {:?}", file!(), line!()));
+                    let name = self.list_store.row_string(&iter, 0)?;
                     let paint = self
                         .colln
                         .get_paint(&name)
-                        .unwrap_or_else(|| panic!("File: {:?} Line: {:?}", file!(), line!()));
-                    return Some(paint);
+                        .ok_or(PaintError::from(PaintErrorType::ModelInconsistency(name)))?;
+                    return Ok(Some(paint));
                 }
             }
         };
-        None
+        Ok(None)
+    }
+
+    pub fn get_paint_at(&self, posn: (f64, f64)) -> Option<CollnPaint<C, CID>> {
+        reported_row_value(self, self.try_get_paint_at(posn))
+    }
+
+    pub fn get_selected_paints(&self) -> Vec<CollnPaint<C, CID>> {
+        let mut paints = vec![];
+        let (paths, _) = self.view.get_selection().get_selected_rows();
+        for path in paths.iter() {
+            if let Some(iter) = self.list_store.get_iter(path) {
+                let r_name = self.list_store.row_string(&iter, 0).map(Some);
+                if let Some(name) = reported_row_value(self, r_name) {
+                    if let Some(paint) = self.colln.get_paint(&name) {
+                        paints.push(paint);
+                    }
+                }
+            }
+        }
+        paints
     }
 
     pub fn colln_id(&self) -> Rc<CID> {
@@ -146,6 +450,32 @@ where
         self.colln.len()
     }
 
+    pub fn row_colouring(&self) -> RowColouring {
+        self.row_colouring.get()
+    }
+
+    pub fn set_row_colouring(&self, row_colouring: RowColouring) {
+        let mut cols = standard_paint_tv_columns::<A, C>(row_colouring);
+        if self.usage_source.borrow().is_some() {
+            let col_id = CollnPaint::<C, CID>::tv_row_len() as i32;
+            cols.push(simple_text_column(
+                "Mixes", col_id, col_id, -1, -1, -1, true,
+            ));
+            cols.push(simple_text_column(
+                "Parts",
+                col_id + 1,
+                col_id + 1,
+                -1,
+                -1,
+                -1,
+                true,
+            ));
+        }
+        set_tv_columns(&self.view, &cols, row_colouring);
+        self.row_colouring.set(row_colouring);
+        row_colouring.remember(&CID::recollection_name_for("colln_view_row_colouring"));
+    }
+
     pub fn get_paint(&self, name: &str) -> Option<CollnPaint<C, CID>> {
         self.colln.get_paint(name)
     }
@@ -158,6 +488,10 @@ where
         self.colln.has_paint_named(name)
     }
 
+    pub fn write_gpl_palette(&self, path: &Path) -> io::Result<()> {
+        self.colln.write_gpl_palette(path)
+    }
+
     pub fn connect_button_press_event<
         F: Fn(&gtk::TreeView, &gdk::EventButton) -> Inhibit + 'static,
     >(
@@ -166,6 +500,204 @@ where
     ) -> SignalHandlerId {
         self.view.connect_button_press_event(f)
     }
+
+    pub fn is_paginated(&self) -> bool {
+        self.paginate_btn.get_active()
+    }
+
+    pub fn set_paginated(&self, paginated: bool) {
+        self.paginate_btn.set_active(paginated);
+    }
+
+    // NB: a page size of zero means "no pagination"
+    pub fn page_size(&self) -> usize {
+        if self.is_paginated() {
+            self.page_size_spin.get_value_as_int() as usize
+        } else {
+            0
+        }
+    }
+
+    pub fn set_page_size(&self, page_size: usize) {
+        self.page_size_spin
+            .set_value(page_size.max(MIN_PAGE_SIZE).min(MAX_PAGE_SIZE) as f64);
+    }
+
+    pub fn page_count(&self) -> usize {
+        page_count(self.filtered_paints.borrow().len(), self.page_size())
+    }
+
+    pub fn current_page(&self) -> usize {
+        self.current_page.get()
+    }
+
+    pub fn set_current_page(&self, page: usize) {
+        let page = page.min(self.page_count() - 1);
+        self.current_page.set(page);
+        self.fill_list_store();
+    }
+
+    pub fn next_page(&self) {
+        self.set_current_page(self.current_page.get() + 1);
+    }
+
+    pub fn prev_page(&self) {
+        if self.current_page.get() > 0 {
+            self.set_current_page(self.current_page.get() - 1);
+        }
+    }
+
+    pub fn filter_text(&self) -> String {
+        self.filter_entry.get_text().to_string()
+    }
+
+    pub fn set_filter_text(&self, text: &str) {
+        self.filter_entry.set_text(text);
+    }
+
+    // The filter is applied to the whole collection before it's divided into pages
+    fn apply_filter(&self) {
+        let key = self.filter_text();
+        *self.filtered_paints.borrow_mut() = self
+            .colln
+            .get_paints()
+            .iter()
+            .filter(|paint| {
+                self.normaliser.matches(&paint.name(), &key)
+                    || self.normaliser.matches(&paint.notes(), &key)
+            })
+            .filter(|paint| {
+                self.member_display.get() != MemberPaintDisplay::Hide || !self.is_member(paint)
+            })
+            .filter(|paint| {
+                self.filtered_display.get() != FilteredItemDisplay::Hide
+                    || self.passes_filter(paint)
+            })
+            .cloned()
+            .collect();
+        self.set_current_page(0);
+    }
+
+    pub fn is_member(&self, paint: &CollnPaint<C, CID>) -> bool {
+        if let Some(ref membership_test) = *self.membership_test.borrow() {
+            membership_test(paint)
+        } else {
+            false
+        }
+    }
+
+    pub fn set_membership_test(&self, membership_test: Option<PaintMembershipTest<C, CID>>) {
+        *self.membership_test.borrow_mut() = membership_test;
+        self.refresh_membership();
+    }
+
+    // Adds (sortable) columns showing how much each paint has been used
+    pub fn set_usage_source(&self, usage_source: Option<PaintUsageSource<C, CID>>) {
+        *self.usage_source.borrow_mut() = usage_source;
+        self.set_row_colouring(self.row_colouring.get());
+        self.refresh_membership();
+    }
+
+    pub fn paint_usage(&self, paint: &CollnPaint<C, CID>) -> PaintUsage {
+        match *self.usage_source.borrow() {
+            Some(ref usage_source) => usage_source(paint),
+            None => PaintUsage::default(),
+        }
+    }
+
+    pub fn passes_filter(&self, paint: &CollnPaint<C, CID>) -> bool {
+        match *self.filter.borrow() {
+            Some(ref filter) => filter(paint),
+            None => true,
+        }
+    }
+
+    // Paints rejected by the filter are hidden or greyed out (see set_filtered_display)
+    pub fn set_filter(&self, filter: Option<ColouredItemFilter<CollnPaint<C, CID>>>) {
+        *self.filter.borrow_mut() = filter;
+        self.refresh_membership();
+    }
+
+    pub fn filtered_display(&self) -> FilteredItemDisplay {
+        self.filtered_display.get()
+    }
+
+    pub fn set_filtered_display(&self, filtered_display: FilteredItemDisplay) {
+        self.filtered_display.set(filtered_display);
+        self.refresh_membership();
+    }
+
+    pub fn member_display(&self) -> MemberPaintDisplay {
+        self.member_display.get()
+    }
+
+    pub fn set_member_display(&self, member_display: MemberPaintDisplay) {
+        self.member_display.set(member_display);
+        self.refresh_membership();
+    }
+
+    // Redisplay (staying on the same page if possible) after membership changes
+    pub fn refresh_membership(&self) {
+        let page = self.current_page.get();
+        self.apply_filter();
+        self.set_current_page(page);
+    }
+
+    fn fill_list_store(&self) {
+        let filtered_paints = self.filtered_paints.borrow();
+        let n_paints = filtered_paints.len();
+        let page_size = self.page_size();
+        let page = self.current_page.get();
+        self.list_store.clear();
+        let grey_out = self.member_display.get() == MemberPaintDisplay::GreyOut;
+        for paint in filtered_paints[page_range(n_paints, page_size, page)].iter() {
+            let mut rows = paint.tv_rows();
+            if (grey_out && self.is_member(paint)) || !self.passes_filter(paint) {
+                grey_out_tv_rows(&mut rows);
+            }
+            let usage = self.paint_usage(paint);
+            rows.push(usage.mixture_count.to_value());
+            rows.push(usage.total_parts.to_value());
+            self.list_store.append_row(&rows);
+        }
+        let n_pages = page_count(n_paints, page_size);
+        let text = if page_size > 0 {
+            format!("Page {} of {} ({} paints)", page + 1, n_pages, n_paints)
+        } else {
+            format!("{} paints", n_paints)
+        };
+        self.page_label.set_text(&text);
+        self.prev_page_btn.set_sensitive(page > 0);
+        self.next_page_btn.set_sensitive(page + 1 < n_pages);
+    }
+
+    pub fn jump_to_letter(&self, letter: char) {
+        let names: Vec<String> = self
+            .filtered_paints
+            .borrow()
+            .iter()
+            .map(|paint| paint.name())
+            .collect();
+        if let Some(index) = first_index_at_letter(&names, letter) {
+            let page_size = self.page_size();
+            let row = if page_size > 0 {
+                self.set_current_page(index / page_size);
+                index % page_size
+            } else {
+                index
+            };
+            let path = gtk::TreePath::new_from_indicesv(&[row as i32]);
+            self.view
+                .scroll_to_cell(Some(&path), None::<&gtk::TreeViewColumn>, true, 0.0, 0.0);
+        }
+    }
+
+    fn update_pagination_controls(&self) {
+        let paginated = self.is_paginated();
+        self.page_size_spin.set_sensitive(paginated);
+        self.prev_page_btn.set_visible(paginated);
+        self.next_page_btn.set_visible(paginated);
+    }
 }
 
 pub type CollnPaintCollnView<A, C, CID> = Rc<CollnPaintCollnViewCore<A, C, CID>>;
@@ -187,40 +719,137 @@ where
 {
     fn create(colln: &CollnPaintColln<C, CID>) -> CollnPaintCollnView<A, C, CID> {
         let len = CollnPaint::<C, CID>::tv_row_len();
-        let list_store = gtk::ListStore::new(&STANDARD_PAINT_ROW_SPEC[0..len]);
-        for paint in colln.get_paints().iter() {
-            list_store.append_row(&paint.tv_rows());
-        }
+        // plus the paint usage (mixture count and total parts)
+        let mut row_spec = STANDARD_PAINT_ROW_SPEC[0..len].to_vec();
+        row_spec.extend_from_slice(&[u32::static_type(), u32::static_type()]);
+        let list_store = gtk::ListStore::new(&row_spec);
         let view = gtk::TreeView::with_model(&list_store.clone());
         view.set_headers_visible(true);
-        view.get_selection().set_mode(gtk::SelectionMode::None);
+        view.get_selection().set_mode(gtk::SelectionMode::Multiple);
+
+        let paginate_btn = gtk::CheckButton::with_label("Paginate");
+        paginate_btn.set_tooltip_text(Some(
+            "Only display one page of the collection's paints at a time",
+        ));
+        let page_size_spin =
+            gtk::SpinButton::with_range(MIN_PAGE_SIZE as f64, MAX_PAGE_SIZE as f64, 10.0);
+        page_size_spin.set_tooltip_text(Some("The number of paints to display per page"));
+        let prev_page_btn = gtk::Button::with_label("<");
+        prev_page_btn.set_tooltip_text(Some("Display the previous page"));
+        let next_page_btn = gtk::Button::with_label(">");
+        next_page_btn.set_tooltip_text(Some("Display the next page"));
+        let index_letter_combo = gtk::ComboBoxText::new();
+        index_letter_combo.set_tooltip_text(Some(
+            "Jump to the first paint whose name starts with the chosen letter",
+        ));
+        for letter in INDEX_LETTERS.chars() {
+            index_letter_combo.append_text(&letter.to_string());
+        }
+        let filter_entry = gtk::SearchEntry::new();
+        filter_entry.set_tooltip_text(Some(
+            "Only display paints whose name or notes contain this text",
+        ));
 
         let adj: Option<&gtk::Adjustment> = None;
         let mspl = Rc::new(CollnPaintCollnViewCore::<A, C, CID> {
+            vbox: gtk::Box::new(gtk::Orientation::Vertical, 0),
             scrolled_window: gtk::ScrolledWindow::new(adj, adj),
             list_store: list_store,
             colln: colln.clone(),
             view: view,
+            row_colouring: Cell::new(RowColouring::default()),
+            filter_entry: filter_entry,
+            paginate_btn: paginate_btn,
+            page_size_spin: page_size_spin,
+            prev_page_btn: prev_page_btn,
+            next_page_btn: next_page_btn,
+            page_label: gtk::Label::new(None),
+            index_letter_combo: index_letter_combo,
+            current_page: Cell::new(0),
+            filtered_paints: RefCell::new(Vec::new()),
+            normaliser: SearchNormaliser::default(),
+            membership_test: RefCell::new(None),
+            filter: RefCell::new(None),
+            filtered_display: Cell::new(FilteredItemDisplay::default()),
+            member_display: Cell::new(MemberPaintDisplay::default()),
+            usage_source: RefCell::new(None),
             phantom_data: PhantomData,
         });
 
-        mspl.view.append_column(&simple_text_column(
-            "Name", SP_NAME, SP_NAME, SP_RGB, SP_RGB_FG, -1, true,
-        ));
-        mspl.view.append_column(&simple_text_column(
-            "Notes", SP_NOTES, SP_NOTES, SP_RGB, SP_RGB_FG, -1, true,
-        ));
-        for col in A::tv_columns() {
-            mspl.view.append_column(&col);
-        }
-        for col in C::tv_columns(SP_CHARS_0) {
-            mspl.view.append_column(&col);
-        }
-
-        mspl.view.show_all();
+        mspl.set_row_colouring(RowColouring::recall(&CID::recollection_name_for(
+            "colln_view_row_colouring",
+        )));
+        set_normalised_search(
+            &mspl.view,
+            &[SP_NAME, SP_NOTES],
+            SearchNormaliser::default(),
+        );
+        let page_size = recall(&CID::recollection_name_for("colln_view_page_size"))
+            .and_then(|text| text.parse::<usize>().ok())
+            .unwrap_or(DEFAULT_PAGE_SIZE);
+        mspl.set_page_size(page_size);
+        let paginated = recall(&CID::recollection_name_for("colln_view_paginated"))
+            .and_then(|text| text.parse::<bool>().ok())
+            .unwrap_or(false);
+        mspl.set_paginated(paginated);
 
+        let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+        hbox.pack_start(&mspl.filter_entry, true, true, 0);
+        hbox.pack_start(&mspl.index_letter_combo, false, false, 0);
+        hbox.pack_start(&mspl.paginate_btn, false, false, 0);
+        hbox.pack_start(&mspl.page_size_spin, false, false, 0);
+        hbox.pack_start(&mspl.prev_page_btn, false, false, 0);
+        hbox.pack_start(&mspl.page_label, false, false, 0);
+        hbox.pack_start(&mspl.next_page_btn, false, false, 0);
+        mspl.prev_page_btn.set_no_show_all(true);
+        mspl.next_page_btn.set_no_show_all(true);
+        mspl.vbox.pack_start(&hbox, false, false, 0);
         mspl.scrolled_window.add(&mspl.view.clone());
-        mspl.scrolled_window.show_all();
+        mspl.vbox.pack_start(&mspl.scrolled_window, true, true, 0);
+        mspl.vbox.show_all();
+
+        mspl.update_pagination_controls();
+        mspl.apply_filter();
+
+        let mspl_c = mspl.clone();
+        mspl.filter_entry
+            .connect_search_changed(move |_| mspl_c.apply_filter());
+
+        let mspl_c = mspl.clone();
+        mspl.paginate_btn.connect_toggled(move |btn| {
+            remember(
+                &CID::recollection_name_for("colln_view_paginated"),
+                &btn.get_active().to_string(),
+            );
+            mspl_c.update_pagination_controls();
+            mspl_c.set_current_page(0);
+        });
+
+        let mspl_c = mspl.clone();
+        mspl.page_size_spin.connect_value_changed(move |spin| {
+            remember(
+                &CID::recollection_name_for("colln_view_page_size"),
+                &spin.get_value_as_int().to_string(),
+            );
+            mspl_c.set_current_page(0);
+        });
+
+        let mspl_c = mspl.clone();
+        mspl.prev_page_btn
+            .connect_clicked(move |_| mspl_c.prev_page());
+
+        let mspl_c = mspl.clone();
+        mspl.next_page_btn
+            .connect_clicked(move |_| mspl_c.next_page());
+
+        let mspl_c = mspl.clone();
+        mspl.index_letter_combo.connect_changed(move |combo| {
+            if let Some(text) = combo.get_active_text() {
+                if let Some(letter) = text.chars().next() {
+                    mspl_c.jump_to_letter(letter);
+                }
+            }
+        });
 
         mspl
     }
@@ -284,6 +913,7 @@ where
 {
     paints: CollnPaintShapeList<C, CID>,
     graticule: Graticule,
+    display_name_policy: Cell<DisplayNamePolicy>,
 }
 
 impl<C, CID> PackableWidgetObject for CollnPaintHueAttrWheelCore<C, CID>
@@ -323,7 +953,11 @@ where
         let wheel = Rc::new(CollnPaintHueAttrWheelCore::<C, CID> {
             paints: CollnPaintShapeList::<C, CID>::new(attr),
             graticule: Graticule::create(attr),
+            display_name_policy: Cell::new(DisplayNamePolicy::default()),
         });
+        wheel
+            .graticule
+            .set_legend_entries(&[(ShapeType::Square, CID::paint_kind_label().as_str())]);
         for paint in paints.iter() {
             wheel.add_paint(paint)
         }
@@ -337,9 +971,18 @@ where
                 //let rectangle = gtk::Rectangle{x: x, y: y, width: 10, height: -10};
                 //println!("Rectangle: {:?}", rectangle);
                 //tooltip.set_tip_area(&rectangle);
-                match wheel_c.get_paint_at((x as f64, y as f64)) {
-                    Some(paint) => {
-                        tooltip.set_text(Some(paint.tooltip_text().as_str()));
+                let o_text =
+                    wheel_c
+                        .graticule
+                        .throttled_tooltip_text(Point(x as f64, y as f64), |raw_xy| {
+                            let policy = wheel_c.display_name_policy.get();
+                            wheel_c
+                                .get_paint_at((raw_xy.x(), raw_xy.y()))
+                                .map(|paint| paint.tooltip_text_for(policy))
+                        });
+                match o_text {
+                    Some(text) => {
+                        tooltip.set_text(Some(text.as_str()));
                         true
                     }
                     None => false,
@@ -352,6 +995,11 @@ where
                 cairo_context.set_line_width(2.0);
                 wheel_c.paints.draw(graticule, cairo_context);
             });
+        let wheel_c = wheel.clone();
+        wheel.graticule.connect_clicked(move |graticule, raw_xy| {
+            let point = graticule.reverse_transform(raw_xy);
+            wheel_c.paints.expand_cluster_at(graticule, point)
+        });
         wheel
     }
 }
@@ -373,9 +1021,27 @@ where
         self.graticule.attr()
     }
 
+    pub fn set_display_name_policy(&self, policy: DisplayNamePolicy) {
+        self.display_name_policy.set(policy);
+        self.graticule.forget_tooltip();
+    }
+
+    // Paints rejected by the filter are hidden or dimmed (see set_filtered_display)
+    pub fn set_filter(&self, filter: Option<ColouredItemFilter<CollnPaint<C, CID>>>) {
+        self.paints.set_filter(filter);
+        self.graticule.forget_tooltip();
+        self.graticule.queue_draw();
+    }
+
+    pub fn set_filtered_display(&self, filtered_display: FilteredItemDisplay) {
+        self.paints.set_filtered_display(filtered_display);
+        self.graticule.forget_tooltip();
+        self.graticule.queue_draw();
+    }
+
     pub fn get_paint_at(&self, posn: (f64, f64)) -> Option<CollnPaint<C, CID>> {
         let point = self.graticule.reverse_transform(Point::from(posn));
-        let opr = self.paints.get_coloured_item_at(point);
+        let opr = self.paints.get_coloured_item_at(&*self.graticule, point);
         if let Some((paint, _)) = opr {
             Some(paint)
         } else {
@@ -391,6 +1057,71 @@ where
     ) -> SignalHandlerId {
         self.graticule.connect_button_press_event(f)
     }
+
+    pub fn connect_key_press_event<
+        F: Fn(&gtk::DrawingArea, &gdk::EventKey) -> Inhibit + 'static,
+    >(
+        &self,
+        f: F,
+    ) -> SignalHandlerId {
+        self.graticule.connect_key_press_event(f)
+    }
+
+    fn paint_points(&self) -> (Vec<CollnPaint<C, CID>>, Vec<Point>) {
+        self.paints.coloured_items_xy().into_iter().unzip()
+    }
+
+    pub fn move_focus(&self, focus_move: FocusMove) -> bool {
+        let (_, points) = self.paint_points();
+        self.graticule.move_focus(&points, focus_move).is_some()
+    }
+
+    pub fn focused_paint(&self) -> Option<CollnPaint<C, CID>> {
+        let (paints, points) = self.paint_points();
+        self.graticule
+            .focused_index(&points)
+            .map(|index| paints[index].clone())
+    }
+
+    pub fn popup_menu_at_focus(&self, menu: &gtk::Menu) {
+        self.graticule.popup_menu_at_focus(menu)
+    }
+
+    pub fn legend_is_shown(&self) -> bool {
+        self.graticule.legend_is_shown()
+    }
+
+    pub fn is_clustering(&self) -> bool {
+        self.paints.is_clustering()
+    }
+
+    // Draw overlapping paints as one shape with a count (expanded by clicking it)
+    pub fn set_clustering(&self, clustering: bool) {
+        self.paints.set_clustering(clustering);
+        self.graticule.forget_tooltip();
+        self.graticule.queue_draw();
+    }
+
+    // The whole wheel at "size" x "size" pixels as PNG or SVG (by extension)
+    pub fn render_to_file(&self, path: &Path, size: i32) -> io::Result<()> {
+        self.graticule.render_to_file(path, size)
+    }
+
+    pub fn export_image(&self) {
+        export_wheel_image(self, &self.graticule)
+    }
+
+    pub fn set_show_legend(&self, show_legend: bool) {
+        self.graticule.set_show_legend(show_legend)
+    }
+
+    pub fn vision_deficiency(&self) -> Option<VisionDeficiency> {
+        self.graticule.vision_deficiency()
+    }
+
+    pub fn set_vision_deficiency(&self, o_deficiency: Option<VisionDeficiency>) {
+        self.graticule.set_vision_deficiency(o_deficiency)
+    }
 }
 
 // WIDGET
@@ -402,13 +1133,18 @@ where
     CID: CollnIdInterface + 'static,
 {
     vbox: gtk::Box,
+    notebook: gtk::Notebook,
     hue_attr_wheels: Vec<CollnPaintHueAttrWheel<C, CID>>,
     paint_colln_view: CollnPaintCollnView<A, C, CID>,
     popup_menu: WrappedMenu,
     paint_dialogs: RefCell<HashMap<u32, CollnPaintDisplayDialog<A, C, CID>>>,
     initiate_select_ok: Cell<bool>,
     chosen_paint: RefCell<Option<CollnPaint<C, CID>>>,
+    chosen_wheel: RefCell<Option<CollnPaintHueAttrWheel<C, CID>>>,
     current_target: RefCell<Option<Colour>>,
+    display_name_policy: Cell<DisplayNamePolicy>,
+    similar_paints_search: RefCell<Option<SimilarPaintsSearch<C, CID>>>,
+    inventory_edit: RefCell<Option<PaintInventoryEdit<C, CID>>>,
     paint_selected_callbacks: RefCell<Vec<Box<dyn Fn(&CollnPaint<C, CID>)>>>,
 }
 
@@ -423,6 +1159,21 @@ where
     fn create(colln_spec: &PaintCollnSpec<C, CID>) -> CollnPaintCollnWidget<A, C, CID>;
 }
 
+impl<A, C, CID> DisplayDialogHost for CollnPaintCollnWidgetCore<A, C, CID>
+where
+    A: ColourAttributesInterface + 'static,
+    C: CharacteristicsInterface + 'static,
+    CID: CollnIdInterface + 'static,
+{
+    fn display_dialogs(&self) -> Vec<gtk::Dialog> {
+        self.paint_dialogs
+            .borrow()
+            .values()
+            .map(|dialog| dialog.dialog())
+            .collect()
+    }
+}
+
 impl<A, C, CID> CollnPaintCollnWidgetCore<A, C, CID>
 where
     A: ColourAttributesInterface + 'static,
@@ -433,6 +1184,10 @@ where
         self.paint_colln_view.colln_id()
     }
 
+    pub fn get_paints(&self) -> Rc<Vec<CollnPaint<C, CID>>> {
+        self.paint_colln_view.get_paints()
+    }
+
     fn inform_paint_selected(&self, paint: &CollnPaint<C, CID>) {
         for callback in self.paint_selected_callbacks.borrow().iter() {
             callback(&paint);
@@ -452,6 +1207,152 @@ where
             .push(Box::new(callback))
     }
 
+    pub fn display_name_policy(&self) -> DisplayNamePolicy {
+        self.display_name_policy.get()
+    }
+
+    pub fn set_membership_test(&self, membership_test: Option<PaintMembershipTest<C, CID>>) {
+        self.paint_colln_view.set_membership_test(membership_test);
+    }
+
+    pub fn set_member_display(&self, member_display: MemberPaintDisplay) {
+        self.paint_colln_view.set_member_display(member_display);
+    }
+
+    // Paint information dialogs list the similar paints this finds
+    pub fn set_similar_paints_search(&self, search: Option<SimilarPaintsSearch<C, CID>>) {
+        *self.similar_paints_search.borrow_mut() = search;
+    }
+
+    pub fn refresh_membership(&self) {
+        self.paint_colln_view.refresh_membership();
+    }
+
+    // The popup menu only offers to edit inventories if this is set
+    pub fn set_inventory_edit(&self, inventory_edit: Option<PaintInventoryEdit<C, CID>>) {
+        *self.inventory_edit.borrow_mut() = inventory_edit;
+    }
+
+    fn update_inventory_menu_item(&self, paint_chosen: bool) {
+        let has_inventory_edit = self.inventory_edit.borrow().is_some();
+        self.popup_menu
+            .set_visibilities(has_inventory_edit, &["inventory"]);
+        self.popup_menu
+            .set_sensitivities(has_inventory_edit && paint_chosen, &["inventory"]);
+    }
+
+    pub fn set_usage_source(&self, usage_source: Option<PaintUsageSource<C, CID>>) {
+        self.paint_colln_view.set_usage_source(usage_source);
+    }
+
+    // The filter is shared by the list view and the wheels
+    pub fn set_filter(&self, filter: Option<ColouredItemFilter<CollnPaint<C, CID>>>) {
+        let o_filter: Option<SharedColouredItemFilter<CollnPaint<C, CID>>> = filter.map(Rc::from);
+        for wheel in self.hue_attr_wheels.iter() {
+            wheel.set_filter(o_filter.as_ref().map(boxed_filter));
+        }
+        self.paint_colln_view
+            .set_filter(o_filter.as_ref().map(boxed_filter));
+    }
+
+    pub fn set_filtered_display(&self, filtered_display: FilteredItemDisplay) {
+        for wheel in self.hue_attr_wheels.iter() {
+            wheel.set_filtered_display(filtered_display);
+        }
+        self.paint_colln_view.set_filtered_display(filtered_display);
+    }
+
+    fn wheel_pages(&self) -> Vec<(ScalarAttribute, gtk::DrawingArea)> {
+        self.hue_attr_wheels
+            .iter()
+            .map(|wheel| (wheel.attr(), wheel.pwo()))
+            .collect()
+    }
+
+    pub fn visible_wheels(&self) -> Vec<ScalarAttribute> {
+        wheel_page_order(&self.notebook, &self.wheel_pages())
+    }
+
+    // Show only the wheels for "attrs" in the given order
+    pub fn set_visible_wheels(&self, attrs: &[ScalarAttribute]) {
+        let attrs = valid_wheel_pages(attrs, &A::scalar_attributes());
+        arrange_wheel_pages(&self.notebook, &self.wheel_pages(), &attrs);
+        self.remember_wheel_pages();
+    }
+
+    fn remember_wheel_pages(&self) {
+        remember_wheel_pages(
+            &CID::recollection_name_for("colln_widget_wheel_pages"),
+            &self.visible_wheels(),
+            &A::scalar_attributes(),
+        );
+    }
+
+    fn select_ok_for(&self, paint: &CollnPaint<C, CID>) -> bool {
+        self.initiate_select_ok.get() && !self.paint_colln_view.is_member(paint)
+    }
+
+    // Blends need exactly two paints selected in the list
+    fn update_blend_preview_menu_item(&self) {
+        let n_selected = self.paint_colln_view.get_selected_paints().len();
+        self.popup_menu
+            .set_sensitivities(n_selected == 2, &["blend_preview"]);
+    }
+
+    pub fn preview_selected_blend(&self) {
+        let paints = self.paint_colln_view.get_selected_paints();
+        if paints.len() == 2 {
+            show_blend_preview(
+                (paints[0].name().as_str(), &paints[0].rgb()),
+                (paints[1].name().as_str(), &paints[1].rgb()),
+                &self.paint_colln_view,
+            );
+        }
+    }
+
+    fn prepare_wheel_popup_menu(
+        &self,
+        wheel: &CollnPaintHueAttrWheel<C, CID>,
+        o_paint: Option<CollnPaint<C, CID>>,
+    ) {
+        *self.chosen_wheel.borrow_mut() = Some(wheel.clone());
+        if let Some(paint) = o_paint {
+            self.popup_menu
+                .set_sensitivities(self.select_ok_for(&paint), &["select"]);
+            self.popup_menu.set_sensitivities(true, &["info"]);
+            *self.chosen_paint.borrow_mut() = Some(paint);
+        } else {
+            self.popup_menu
+                .set_sensitivities(false, &["info", "select"]);
+            *self.chosen_paint.borrow_mut() = None;
+        };
+        self.update_inventory_menu_item(self.chosen_paint.borrow().is_some());
+        self.update_blend_preview_menu_item();
+        let has_dialogs = self.paint_dialogs.borrow().len() > 0;
+        self.popup_menu
+            .set_sensitivities(has_dialogs, &DISPLAY_DIALOGS_MENU_KEYS);
+        self.popup_menu
+            .set_visibilities(false, &ROW_COLOURING_MENU_KEYS);
+        self.popup_menu
+            .set_visibilities(true, &DISPLAY_NAME_POLICY_MENU_KEYS);
+        self.popup_menu.set_visibilities(
+            true,
+            &["paint_code_pattern", "legend", "cluster", "export_image"],
+        );
+        set_vision_deficiency_items_visibility(&self.popup_menu, true);
+        update_vision_deficiency_items(&self.popup_menu, wheel.vision_deficiency());
+    }
+
+    pub fn set_display_name_policy(&self, policy: DisplayNamePolicy) {
+        for wheel in self.hue_attr_wheels.iter() {
+            wheel.set_display_name_policy(policy);
+        }
+        self.display_name_policy.set(policy);
+        policy.remember(&CID::recollection_name_for(
+            "colln_widget_display_name_policy",
+        ));
+    }
+
     pub fn set_target_colour(&self, o_colour: Option<&Colour>) {
         for wheel in self.hue_attr_wheels.iter() {
             wheel.set_target_colour(o_colour);
@@ -484,15 +1385,23 @@ where
         }
         let cpcw = Rc::new(CollnPaintCollnWidgetCore::<A, C, CID> {
             vbox: gtk::Box::new(gtk::Orientation::Vertical, 0),
+            notebook: gtk::Notebook::new(),
             hue_attr_wheels: view_attr_wheels,
             paint_colln_view: CollnPaintCollnView::<A, C, CID>::create(&paint_colln),
             paint_dialogs: RefCell::new(HashMap::new()),
             popup_menu: WrappedMenu::new(&vec![]),
             initiate_select_ok: Cell::new(false),
             chosen_paint: RefCell::new(None),
+            chosen_wheel: RefCell::new(None),
             current_target: RefCell::new(None),
+            display_name_policy: Cell::new(DisplayNamePolicy::default()),
+            similar_paints_search: RefCell::new(None),
+            inventory_edit: RefCell::new(None),
             paint_selected_callbacks: RefCell::new(Vec::new()),
         });
+        cpcw.set_display_name_policy(DisplayNamePolicy::recall(&CID::recollection_name_for(
+            "colln_widget_display_name_policy",
+        )));
         let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 0);
         let colln_name = format!(
             "{} {}",
@@ -506,73 +1415,81 @@ where
             colln_spec.colln_id.colln_owner()
         );
         hbox.pack_start(&gtk::Label::new(Some(colln_owner.as_str())), true, true, 0);
+        hbox.set_tooltip_text(Some(colln_spec.tooltip_text().as_str()));
 
-        let notebook = gtk::Notebook::new();
-        for wheel in cpcw.hue_attr_wheels.iter() {
-            let label_text = format!("Hue/{} Wheel", wheel.attr().to_string());
-            let label = gtk::Label::new(Some(label_text.as_str()));
-            notebook.append_page(&wheel.pwo(), Some(&label));
-        }
-        notebook.set_scrollable(true);
-        notebook.popup_enable();
+        let visible_wheels = recall_wheel_pages(
+            &CID::recollection_name_for("colln_widget_wheel_pages"),
+            &A::scalar_attributes(),
+        );
+        arrange_wheel_pages(&cpcw.notebook, &cpcw.wheel_pages(), &visible_wheels);
+        cpcw.notebook.set_scrollable(true);
+        cpcw.notebook.popup_enable();
+        let cpcw_c = cpcw.clone();
+        cpcw.notebook
+            .connect_page_reordered(move |_, _, _| cpcw_c.remember_wheel_pages());
         let hpaned = gtk::Paned::new(gtk::Orientation::Horizontal);
-        hpaned.pack1(&notebook, true, true);
+        hpaned.pack1(&cpcw.notebook, true, true);
         hpaned.pack2(&cpcw.paint_colln_view.pwo(), true, true);
         hpaned.set_position_from_recollections("colln_paint_colln_widget", 200);
         cpcw.vbox.pack_start(&hpaned, true, true, 0);
 
         let cpcw_c = cpcw.clone();
+        let show_chosen_paint_info: Rc<dyn Fn()> = Rc::new(move || {
+            if let Some(ref paint) = *cpcw_c.chosen_paint.borrow() {
+                let cpcw_c_c = cpcw_c.clone();
+                let paint_c = paint.clone();
+                let select_btn_spec = PaintDisplayButtonSpec {
+                    label: CID::paint_select_label().to_string(),
+                    tooltip_text: CID::paint_select_tooltip_text().to_string(),
+                    callback: Box::new(move || cpcw_c_c.inform_paint_selected(&paint_c)),
+                };
+                let dialog = if CID::display_current_target() {
+                    let target_colour = cpcw_c.current_target.borrow().clone();
+                    let target = if let Some(ref colour) = target_colour {
+                        Some(colour)
+                    } else {
+                        None
+                    };
+                    CollnPaintDisplayDialog::<A, C, CID>::create(
+                        &paint,
+                        target,
+                        &cpcw_c,
+                        vec![select_btn_spec],
+                    )
+                } else {
+                    CollnPaintDisplayDialog::<A, C, CID>::create(
+                        &paint,
+                        None,
+                        &cpcw_c,
+                        vec![select_btn_spec],
+                    )
+                };
+                dialog.set_response_sensitive(
+                    gtk::ResponseType::Other(0),
+                    cpcw_c.initiate_select_ok.get(),
+                );
+                if let Some(ref search) = *cpcw_c.similar_paints_search.borrow() {
+                    dialog.set_similar_paints(&search(paint));
+                }
+                let cpcw_c_c = cpcw_c.clone();
+                dialog.connect_destroyed(move |id| {
+                    cpcw_c_c.paint_dialogs.borrow_mut().remove(&id);
+                });
+                cpcw_c
+                    .paint_dialogs
+                    .borrow_mut()
+                    .insert(dialog.id_no(), dialog.clone());
+                dialog.show();
+            }
+        });
+        let show_chosen_paint_info_c = show_chosen_paint_info.clone();
         cpcw.popup_menu
             .append_item(
                 "info",
                 "Paint Information",
                 "Display this paint's information",
             )
-            .connect_activate(move |_| {
-                if let Some(ref paint) = *cpcw_c.chosen_paint.borrow() {
-                    let cpcw_c_c = cpcw_c.clone();
-                    let paint_c = paint.clone();
-                    let select_btn_spec = PaintDisplayButtonSpec {
-                        label: CID::paint_select_label().to_string(),
-                        tooltip_text: CID::paint_select_tooltip_text().to_string(),
-                        callback: Box::new(move || cpcw_c_c.inform_paint_selected(&paint_c)),
-                    };
-                    let dialog = if CID::display_current_target() {
-                        let target_colour = cpcw_c.current_target.borrow().clone();
-                        let target = if let Some(ref colour) = target_colour {
-                            Some(colour)
-                        } else {
-                            None
-                        };
-                        CollnPaintDisplayDialog::<A, C, CID>::create(
-                            &paint,
-                            target,
-                            &cpcw_c,
-                            vec![select_btn_spec],
-                        )
-                    } else {
-                        CollnPaintDisplayDialog::<A, C, CID>::create(
-                            &paint,
-                            None,
-                            &cpcw_c,
-                            vec![select_btn_spec],
-                        )
-                    };
-                    dialog.set_response_sensitive(
-                        gtk::ResponseType::Other(0),
-                        cpcw_c.initiate_select_ok.get(),
-                    );
-                    let cpcw_c_c = cpcw_c.clone();
-                    dialog.connect_destroyed(move |id| {
-                        cpcw_c_c.paint_dialogs.borrow_mut().remove(&id);
-                    });
-                    cpcw_c
-                        .paint_dialogs
-                        .borrow_mut()
-                        .insert(dialog.id_no(), dialog.clone());
-                    dialog.show();
-                }
-            });
+            .connect_activate(move |_| show_chosen_paint_info_c());
 
         let cpcw_c = cpcw.clone();
         cpcw.popup_menu
@@ -587,6 +1504,132 @@ where
                 }
             });
 
+        let cpcw_c = cpcw.clone();
+        cpcw.popup_menu
+            .append_item(
+                "inventory",
+                "Edit Inventory...",
+                "Record how much of this paint is owned (and when it was bought and for how much)",
+            )
+            .connect_activate(move |_| {
+                let o_paint = cpcw_c.chosen_paint.borrow().clone();
+                let o_inventory_edit = cpcw_c.inventory_edit.borrow().clone();
+                if let (Some(paint), Some(inventory_edit)) = (o_paint, o_inventory_edit) {
+                    inventory_edit(&paint)
+                }
+            });
+
+        let cpcw_c = cpcw.clone();
+        cpcw.popup_menu
+            .append_item(
+                "blend_preview",
+                "Preview Blend",
+                "Show the mixtures of the two selected paints at evenly spaced ratios",
+            )
+            .connect_activate(move |_| cpcw_c.preview_selected_blend());
+
+        for row_colouring in RowColouring::values() {
+            let cpcw_c = cpcw.clone();
+            cpcw.popup_menu
+                .append_item(
+                    row_colouring.name(),
+                    row_colouring.label(),
+                    row_colouring.tooltip_text(),
+                )
+                .connect_activate(move |_| {
+                    cpcw_c.paint_colln_view.set_row_colouring(row_colouring)
+                });
+        }
+
+        for policy in DisplayNamePolicy::values() {
+            let cpcw_c = cpcw.clone();
+            cpcw.popup_menu
+                .append_item(policy.name(), policy.label(), policy.tooltip_text())
+                .connect_activate(move |_| cpcw_c.set_display_name_policy(policy));
+        }
+
+        let cpcw_c = cpcw.clone();
+        cpcw.popup_menu
+            .append_item(
+                "paint_code_pattern",
+                "Paint Code Pattern...",
+                "Change the regular expression used to find paints' codes in their notes",
+            )
+            .connect_activate(move |_| {
+                if ask_paint_code_pattern(&*cpcw_c) {
+                    cpcw_c.set_display_name_policy(cpcw_c.display_name_policy());
+                }
+            });
+
+        let cpcw_c = cpcw.clone();
+        cpcw.popup_menu
+            .append_item(
+                "legend",
+                "Show/Hide Legend",
+                "Toggle the wheels' explanation of their markers",
+            )
+            .connect_activate(move |_| {
+                let show_legend = !cpcw_c
+                    .hue_attr_wheels
+                    .first()
+                    .map_or(false, |wheel| wheel.legend_is_shown());
+                for wheel in cpcw_c.hue_attr_wheels.iter() {
+                    wheel.set_show_legend(show_legend);
+                }
+            });
+
+        let cpcw_c = cpcw.clone();
+        cpcw.popup_menu
+            .append_item(
+                "cluster",
+                "Cluster/Uncluster Overlaps",
+                "Toggle drawing the wheels' overlapping paints as one shape showing their number (click it to see them)",
+            )
+            .connect_activate(move |_| {
+                let clustering = !cpcw_c
+                    .hue_attr_wheels
+                    .first()
+                    .map_or(false, |wheel| wheel.is_clustering());
+                for wheel in cpcw_c.hue_attr_wheels.iter() {
+                    wheel.set_clustering(clustering);
+                }
+            });
+
+        let cpcw_c = cpcw.clone();
+        cpcw.popup_menu
+            .append_item(
+                "export_image",
+                "Export Image...",
+                "Save an image (PNG or SVG) of this wheel",
+            )
+            .connect_activate(move |_| {
+                if let Some(ref wheel) = *cpcw_c.chosen_wheel.borrow() {
+                    wheel.export_image();
+                }
+            });
+
+        let cpcw_c = cpcw.clone();
+        cpcw.popup_menu
+            .append_item(
+                "export_palette",
+                "Export GIMP Palette...",
+                "Save this collection's paints as a GIMP/Inkscape palette (.gpl) file",
+            )
+            .connect_activate(move |_| {
+                export_gpl_palette(&*cpcw_c, |path| {
+                    cpcw_c.paint_colln_view.write_gpl_palette(path)
+                })
+            });
+
+        let cpcw_c = cpcw.clone();
+        append_vision_deficiency_items(&cpcw.popup_menu, move |o_deficiency| {
+            if let Some(ref wheel) = *cpcw_c.chosen_wheel.borrow() {
+                wheel.set_vision_deficiency(o_deficiency);
+            }
+        });
+
+        append_display_dialogs_items(&cpcw.popup_menu, &cpcw);
+
         let cpcw_c = cpcw.clone();
         cpcw.paint_colln_view
             .connect_button_press_event(move |_, event| {
@@ -595,7 +1638,7 @@ where
                     {
                         cpcw_c
                             .popup_menu
-                            .set_sensitivities(cpcw_c.initiate_select_ok.get(), &["select"]);
+                            .set_sensitivities(cpcw_c.select_ok_for(&paint), &["select"]);
                         cpcw_c.popup_menu.set_sensitivities(true, &["info"]);
                         *cpcw_c.chosen_paint.borrow_mut() = Some(paint);
                     } else {
@@ -604,6 +1647,23 @@ where
                             .set_sensitivities(false, &["info", "select"]);
                         *cpcw_c.chosen_paint.borrow_mut() = None;
                     };
+                    cpcw_c.update_inventory_menu_item(cpcw_c.chosen_paint.borrow().is_some());
+                    cpcw_c.update_blend_preview_menu_item();
+                    let has_dialogs = cpcw_c.paint_dialogs.borrow().len() > 0;
+                    cpcw_c
+                        .popup_menu
+                        .set_sensitivities(has_dialogs, &DISPLAY_DIALOGS_MENU_KEYS);
+                    cpcw_c
+                        .popup_menu
+                        .set_visibilities(true, &ROW_COLOURING_MENU_KEYS);
+                    cpcw_c
+                        .popup_menu
+                        .set_visibilities(false, &DISPLAY_NAME_POLICY_MENU_KEYS);
+                    cpcw_c.popup_menu.set_visibilities(
+                        false,
+                        &["paint_code_pattern", "legend", "cluster", "export_image"],
+                    );
+                    set_vision_deficiency_items_visibility(&cpcw_c.popup_menu, false);
                     cpcw_c.popup_menu.popup_at_event(event);
                     return Inhibit(true);
                 };
@@ -615,23 +1675,35 @@ where
             let wheel_c = wheel.clone();
             wheel.connect_button_press_event(move |_, event| {
                 if event.get_button() == 3 {
-                    if let Some(paint) = wheel_c.get_paint_at(event.get_position()) {
-                        cpcw_c
-                            .popup_menu
-                            .set_sensitivities(cpcw_c.initiate_select_ok.get(), &["select"]);
-                        cpcw_c.popup_menu.set_sensitivities(true, &["info"]);
-                        *cpcw_c.chosen_paint.borrow_mut() = Some(paint);
-                    } else {
-                        cpcw_c
-                            .popup_menu
-                            .set_sensitivities(false, &["info", "select"]);
-                        *cpcw_c.chosen_paint.borrow_mut() = None;
-                    };
+                    cpcw_c.prepare_wheel_popup_menu(
+                        &wheel_c,
+                        wheel_c.get_paint_at(event.get_position()),
+                    );
                     cpcw_c.popup_menu.popup_at_event(event);
                     return Inhibit(true);
                 };
                 Inhibit(false)
             });
+            let cpcw_c = cpcw.clone();
+            let wheel_c = wheel.clone();
+            let show_chosen_paint_info_c = show_chosen_paint_info.clone();
+            wheel.connect_key_press_event(move |_, event| {
+                if let Some(focus_move) = FocusMove::from_key(&event.get_keyval()) {
+                    return Inhibit(wheel_c.move_focus(focus_move));
+                }
+                if is_activate_key(event) {
+                    if let Some(paint) = wheel_c.focused_paint() {
+                        *cpcw_c.chosen_paint.borrow_mut() = Some(paint);
+                        show_chosen_paint_info_c();
+                        return Inhibit(true);
+                    }
+                } else if is_menu_key(event) {
+                    cpcw_c.prepare_wheel_popup_menu(&wheel_c, wheel_c.focused_paint());
+                    wheel_c.popup_menu_at_focus(&cpcw_c.popup_menu.pwo());
+                    return Inhibit(true);
+                }
+                Inhibit(false)
+            });
         }
 
         cpcw
@@ -640,5 +1712,49 @@ where
 
 #[cfg(test)]
 mod tests {
-    //use super::*;
+    use super::*;
+
+    #[test]
+    fn pagination_page_ranges() {
+        assert_eq!(page_count(0, 10), 1);
+        assert_eq!(page_count(25, 10), 3);
+        assert_eq!(page_count(30, 10), 3);
+        assert_eq!(page_count(25, 0), 1);
+        assert_eq!(page_range(25, 10, 0), 0..10);
+        assert_eq!(page_range(25, 10, 2), 20..25);
+        assert_eq!(page_range(25, 10, 5), 25..25);
+        assert_eq!(page_range(25, 0, 3), 0..25);
+    }
+
+    #[test]
+    fn pagination_letter_index() {
+        assert_eq!(index_letter("Écru"), 'E');
+        assert_eq!(index_letter("burnt sienna"), 'B');
+        assert_eq!(index_letter("123 Red"), '#');
+        let names: Vec<String> = ["1 Blue", "Alizarin", "Cobalt", "Cobalt Teal", "Red"]
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+        assert_eq!(first_index_at_letter(&names, '#'), Some(0));
+        assert_eq!(first_index_at_letter(&names, 'A'), Some(1));
+        assert_eq!(first_index_at_letter(&names, 'B'), Some(2));
+        assert_eq!(first_index_at_letter(&names, 'R'), Some(4));
+        assert_eq!(first_index_at_letter(&names, 'Z'), Some(4));
+        assert_eq!(first_index_at_letter(&[], 'A'), None);
+    }
+
+    #[test]
+    fn pagination_letter_index_mixed_case() {
+        let names: Vec<String> = ["Cobalt", "Red", "alizarin", "burnt sienna", "Écru"]
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+        assert_eq!(first_index_at_letter(&names, 'A'), Some(2));
+        assert_eq!(first_index_at_letter(&names, 'B'), Some(3));
+        assert_eq!(first_index_at_letter(&names, 'C'), Some(0));
+        assert_eq!(first_index_at_letter(&names, 'D'), Some(4));
+        assert_eq!(first_index_at_letter(&names, 'F'), Some(1));
+        assert_eq!(first_index_at_letter(&names, '#'), Some(2));
+        assert_eq!(first_index_at_letter(&names, 'S'), Some(4));
+    }
 }
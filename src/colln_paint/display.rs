@@ -19,6 +19,7 @@ where
     dialog: gtk::Dialog,
     paint: CollnPaint<C, CID>,
     current_target_label: gtk::Label,
+    similar_paints_expander: gtk::Expander,
     cads: Rc<A>,
     id_no: u32,
     destroyed_callbacks: RefCell<Vec<Box<dyn Fn(u32)>>>,
@@ -52,6 +53,43 @@ where
     }
 }
 
+impl<A, C, CID> CollnPaintDisplayDialogCore<A, C, CID>
+where
+    A: ColourAttributesInterface + 'static,
+    C: CharacteristicsInterface + 'static,
+    CID: CollnIdInterface + 'static,
+{
+    // List paints (e.g. from other collections) close to this one
+    pub fn set_similar_paints(&self, similar_paints: &[(CollnPaint<C, CID>, f64)]) {
+        if let Some(child) = self.similar_paints_expander.get_child() {
+            self.similar_paints_expander.remove(&child);
+        }
+        if similar_paints.len() == 0 {
+            self.similar_paints_expander.hide();
+            return;
+        }
+        let vbox = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        for (paint, distance) in similar_paints.iter() {
+            let colln_id = paint.colln_id();
+            let text = format!(
+                "{} ({} {}) ΔE {:.2}",
+                paint.name(),
+                colln_id.colln_name(),
+                colln_id.colln_owner(),
+                distance
+            );
+            let label = gtk::Label::new(Some(text.as_str()));
+            label.set_widget_colour(&paint.colour());
+            vbox.pack_start(&label, false, false, 0);
+        }
+        self.similar_paints_expander.set_label(Some(
+            format!("Similar Paints ({})", similar_paints.len()).as_str(),
+        ));
+        self.similar_paints_expander.add(&vbox);
+        self.similar_paints_expander.show_all();
+    }
+}
+
 pub trait CollnPaintDisplayDialogInterface<A, C, CID>
 where
     C: CharacteristicsInterface + 'static,
@@ -79,7 +117,7 @@ where
         caller: &Rc<W>,
         button_specs: Vec<PaintDisplayButtonSpec>,
     ) -> CollnPaintDisplayDialog<A, C, CID> {
-        let dialog = new_display_dialog(&paint.name(), caller, &[]);
+        let dialog = new_display_dialog(&paint.name(), "colln_paint_display", caller, &[]);
         if CID::display_current_target() {
             dialog.set_size_from_recollections("colln_paint_display", (60, 330));
         } else {
@@ -115,7 +153,10 @@ where
         content_area.pack_start(&cads.pwo(), true, true, 1);
         let characteristics_display = paint.characteristics().gui_display_widget();
         content_area.pack_start(&characteristics_display, false, false, 0);
+        let similar_paints_expander = gtk::Expander::new(Some("Similar Paints"));
+        content_area.pack_start(&similar_paints_expander, false, false, 0);
         content_area.show_all();
+        similar_paints_expander.hide();
         for (response_id, spec) in button_specs.iter().enumerate() {
             let button = dialog.add_button(
                 spec.label.as_str(),
@@ -134,6 +175,7 @@ where
             dialog: dialog,
             paint: paint.clone(),
             current_target_label: current_target_label,
+            similar_paints_expander: similar_paints_expander,
             cads: cads,
             id_no: get_id_for_dialog(),
             destroyed_callbacks: DestroyedCallbacks::create(),
@@ -0,0 +1,164 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+// Finding paints that have (probably) been entered more than once e.g.
+// under different names in a manufacturer's collection file.
+
+use std::rc::Rc;
+
+use pw_gix::{
+    glib::StaticType,
+    gtk::{self, prelude::*},
+    gtkx::dialog::*,
+    recollections::{recall, remember},
+    wrapper::*,
+};
+
+use crate::app_name;
+use crate::colour_difference::DEFAULT_DUPLICATE_DELTA_E;
+
+const MAX_DELTA_E_RECOLLECTION: &str = "suspected_duplicates::max_delta_e";
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DuplicateAction {
+    // Keep the first paint (with both sets of notes) and drop the second
+    Merge,
+    // Keep both paints with notes cross referencing them
+    Annotate,
+}
+
+pub fn merged_notes(notes: &str, other_notes: &str) -> String {
+    if other_notes.len() == 0 || notes.contains(other_notes) {
+        notes.to_string()
+    } else if notes.len() == 0 {
+        other_notes.to_string()
+    } else {
+        format!("{}; {}", notes, other_notes)
+    }
+}
+
+pub fn annotated_notes(notes: &str, other_name: &str) -> String {
+    let annotation = format!("possible duplicate of {}", other_name);
+    merged_notes(notes, &annotation)
+}
+
+pub fn recall_max_delta_e() -> f64 {
+    recall(MAX_DELTA_E_RECOLLECTION)
+        .and_then(|text| text.parse::<f64>().ok())
+        .unwrap_or(DEFAULT_DUPLICATE_DELTA_E)
+}
+
+// List the pairs of paint names (and their colour difference) returned
+// by "find" for the chosen maximum colour difference and let the user
+// choose what to do about one of them (None if they close the dialog)
+pub fn choose_duplicate_action<W: WidgetWrapper>(
+    caller: &W,
+    find: Rc<dyn Fn(f64) -> Vec<(String, String, f64)>>,
+) -> Option<(DuplicateAction, String, String)> {
+    let title = format!("{}: Suspected Duplicate Paints", app_name());
+    let dialog = caller.new_dialog_with_buttons(
+        Some(&title),
+        gtk::DialogFlags::DESTROY_WITH_PARENT,
+        &[
+            ("Merge", gtk::ResponseType::Other(1)),
+            ("Annotate", gtk::ResponseType::Other(2)),
+            ("Close", gtk::ResponseType::Close),
+        ],
+    );
+    dialog.set_size_from_recollections("suspected_duplicates_dialog", (450, 300));
+    if let Some(widget) = dialog.get_widget_for_response(gtk::ResponseType::Other(1)) {
+        widget.set_tooltip_text(Some(
+            "Remove the second paint adding its notes to those of the first",
+        ));
+    }
+    if let Some(widget) = dialog.get_widget_for_response(gtk::ResponseType::Other(2)) {
+        widget.set_tooltip_text(Some("Keep both paints noting that they may be the same"));
+    }
+
+    let max_delta_e_spin = gtk::SpinButton::with_range(0.0, 20.0, 0.1);
+    max_delta_e_spin.set_digits(1);
+    max_delta_e_spin.set_value(recall_max_delta_e());
+    max_delta_e_spin.set_tooltip_text(Some(
+        "Paints whose colours differ by no more than this are listed",
+    ));
+    let list_store = gtk::ListStore::new(&[
+        String::static_type(),
+        String::static_type(),
+        String::static_type(),
+    ]);
+    let fill = {
+        let list_store = list_store.clone();
+        move |max_delta_e: f64| {
+            list_store.clear();
+            for (name, other_name, delta_e) in find(max_delta_e).iter() {
+                list_store.insert_with_values(
+                    None,
+                    &[0, 1, 2],
+                    &[name, other_name, &format!("{:.2}", delta_e)],
+                );
+            }
+        }
+    };
+    fill(max_delta_e_spin.get_value());
+    max_delta_e_spin.connect_value_changed(move |spin| {
+        remember(MAX_DELTA_E_RECOLLECTION, &spin.get_value().to_string());
+        fill(spin.get_value());
+    });
+
+    let view = gtk::TreeView::with_model(&list_store);
+    view.set_headers_visible(true);
+    view.get_selection().set_mode(gtk::SelectionMode::Single);
+    for (col_id, title) in ["Paint", "Suspected Duplicate", "ΔE"].iter().enumerate() {
+        let col = gtk::TreeViewColumn::new();
+        col.set_title(title);
+        col.set_resizable(true);
+        let cell = gtk::CellRendererText::new();
+        col.pack_start(&cell, true);
+        col.add_attribute(&cell, "text", col_id as i32);
+        view.append_column(&col);
+    }
+    let adj: Option<&gtk::Adjustment> = None;
+    let scrolled_window = gtk::ScrolledWindow::new(adj, adj);
+    scrolled_window.add(&view);
+
+    let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 2);
+    hbox.pack_start(&gtk::Label::new(Some("Max ΔE:")), false, false, 0);
+    hbox.pack_start(&max_delta_e_spin, false, false, 0);
+    let content_area = dialog.get_content_area();
+    content_area.pack_start(&hbox, false, false, 0);
+    content_area.pack_start(&scrolled_window, true, true, 0);
+    content_area.show_all();
+    let mut o_chosen: Option<(DuplicateAction, String, String)> = None;
+    loop {
+        let action = match gtk::ResponseType::from(dialog.run()) {
+            gtk::ResponseType::Other(1) => DuplicateAction::Merge,
+            gtk::ResponseType::Other(2) => DuplicateAction::Annotate,
+            _ => break,
+        };
+        if let Some((model, iter)) = view.get_selection().get_selected() {
+            let name: Option<String> = model.get_value(&iter, 0).get().ok().flatten();
+            let other_name: Option<String> = model.get_value(&iter, 1).get().ok().flatten();
+            if let (Some(name), Some(other_name)) = (name, other_name) {
+                o_chosen = Some((action, name, other_name));
+                break;
+            }
+        }
+    }
+    unsafe { dialog.destroy() };
+    o_chosen
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicate_notes() {
+        assert_eq!(merged_notes("", "thin"), "thin");
+        assert_eq!(merged_notes("gloss", ""), "gloss");
+        assert_eq!(merged_notes("gloss", "thin"), "gloss; thin");
+        assert_eq!(merged_notes("gloss; thin", "thin"), "gloss; thin");
+        let notes = annotated_notes("gloss", "XF 1");
+        assert_eq!(notes, "gloss; possible duplicate of XF 1");
+        assert_eq!(annotated_notes(&notes, "XF 1"), notes);
+    }
+}
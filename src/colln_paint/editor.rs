@@ -1,26 +1,39 @@
 // Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::str::FromStr;
 
 use colour_math_gtk::coloured::*;
+use pw_gix::gtkx::dialog::*;
 use pw_gix::gtkx::paned::RememberPosition;
 use pw_gix::recollections::{recall, remember};
 pub use pw_gix::wrapper::WidgetWrapper;
 
 use pw_pathux;
 
+use crate::app_name;
+use crate::colour_difference::DeltaEMetric;
 use crate::icons::colln_xpms;
 use crate::icons::file_status_xpms::*;
+use crate::interop::*;
+use crate::recent_files::*;
 
 pub use crate::struct_traits::SimpleCreation;
 
 use crate::basic_paint::entry::*;
 use crate::basic_paint::factory::*;
 
+use super::backup::*;
+use super::duplicates::*;
+use super::formats::*;
+use super::inventory::Inventory;
+use super::loader::*;
+use super::validation::*;
 use super::*;
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -51,6 +64,171 @@ impl FileStatus {
     }
 }
 
+// Let the user choose how the file is laid out (None if they cancel)
+fn ask_write_options<W: WidgetWrapper>(
+    options: &CollnWriteOptions,
+    caller: &W,
+) -> Option<CollnWriteOptions> {
+    let title = format!("{}: Save Options", app_name());
+    let dialog = caller.new_dialog_with_buttons(
+        Some(&title),
+        gtk::DialogFlags::DESTROY_WITH_PARENT,
+        CANCEL_OK_BUTTONS,
+    );
+    let field_order_combo = gtk::ComboBoxText::new();
+    for field_order in FieldOrder::values().iter() {
+        field_order_combo.append(Some(field_order.name()), field_order.label());
+    }
+    field_order_combo.set_active_id(Some(options.field_order.name()));
+    let rgb_format_combo = gtk::ComboBoxText::new();
+    for rgb_format in RGBFormat::values().iter() {
+        rgb_format_combo.append(Some(rgb_format.name()), rgb_format.label());
+    }
+    rgb_format_combo.set_active_id(Some(options.rgb_format.name()));
+    let align_btn = gtk::CheckButton::with_label("Align Colour Fields");
+    align_btn.set_active(options.align);
+    let omit_empty_notes_btn = gtk::CheckButton::with_label("Omit Empty Notes");
+    omit_empty_notes_btn.set_active(options.omit_empty_notes);
+    let grid = gtk::Grid::new();
+    grid.set_row_spacing(2);
+    grid.set_column_spacing(4);
+    grid.attach(&gtk::Label::new(Some("Field Order:")), 0, 0, 1, 1);
+    grid.attach(&field_order_combo, 1, 0, 1, 1);
+    grid.attach(&gtk::Label::new(Some("Colour Format:")), 0, 1, 1, 1);
+    grid.attach(&rgb_format_combo, 1, 1, 1, 1);
+    grid.attach(&align_btn, 1, 2, 1, 1);
+    grid.attach(&omit_empty_notes_btn, 1, 3, 1, 1);
+    let content_area = dialog.get_content_area();
+    content_area.pack_start(&grid, true, true, 0);
+    content_area.show_all();
+    let mut o_options: Option<CollnWriteOptions> = None;
+    if gtk::ResponseType::from(dialog.run()) == gtk::ResponseType::Ok {
+        let mut new_options = *options;
+        if let Some(id) = field_order_combo.get_active_id() {
+            new_options.field_order = FieldOrder::from_str(&id).unwrap_or(options.field_order);
+        }
+        if let Some(id) = rgb_format_combo.get_active_id() {
+            new_options.rgb_format = RGBFormat::from_str(&id).unwrap_or(options.rgb_format);
+        }
+        new_options.align = align_btn.get_active();
+        new_options.omit_empty_notes = omit_empty_notes_btn.get_active();
+        o_options = Some(new_options);
+    }
+    unsafe { dialog.destroy() };
+    o_options
+}
+
+// What to do with imported paints whose names are already in use
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ImportConflictAction {
+    Skip,
+    Overwrite,
+    Rename,
+}
+
+static IMPORT_CONFLICT_ACTION_VALUES: &[ImportConflictAction] = &[
+    ImportConflictAction::Skip,
+    ImportConflictAction::Overwrite,
+    ImportConflictAction::Rename,
+];
+
+impl ImportConflictAction {
+    pub fn values() -> Vec<ImportConflictAction> {
+        IMPORT_CONFLICT_ACTION_VALUES.to_vec()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ImportConflictAction::Skip => "skip",
+            ImportConflictAction::Overwrite => "overwrite",
+            ImportConflictAction::Rename => "rename",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match *self {
+            ImportConflictAction::Skip => "Keep the existing paints (skip the imported ones)",
+            ImportConflictAction::Overwrite => "Replace the existing paints with the imported ones",
+            ImportConflictAction::Rename => "Add the imported paints under new names",
+        }
+    }
+}
+
+impl FromStr for ImportConflictAction {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<ImportConflictAction, String> {
+        for action in IMPORT_CONFLICT_ACTION_VALUES.iter() {
+            if action.name() == string {
+                return Ok(*action);
+            }
+        }
+        Err(format!("{}: unknown import conflict action", string))
+    }
+}
+
+// The first of "name (2)", "name (3)", etc. that isn't taken
+pub fn unique_paint_name<F: Fn(&str) -> bool>(name: &str, is_taken: F) -> String {
+    let mut count = 2;
+    loop {
+        let candidate = format!("{} ({})", name, count);
+        if !is_taken(&candidate) {
+            return candidate;
+        }
+        count += 1;
+    }
+}
+
+// Ask the user what to do about the duplicates (None if they cancel)
+fn ask_import_conflict_action<W: WidgetWrapper>(
+    duplicates: &[String],
+    caller: &W,
+) -> Option<ImportConflictAction> {
+    let title = format!("{}: Duplicate Paint Names", app_name());
+    let dialog = caller.new_dialog_with_buttons(
+        Some(&title),
+        gtk::DialogFlags::DESTROY_WITH_PARENT,
+        CANCEL_OK_BUTTONS,
+    );
+    let label = gtk::Label::new(Some(
+        "The collection already contains paints with these names:",
+    ));
+    label.set_xalign(0.0);
+    let names_view = gtk::TextView::new();
+    names_view.set_editable(false);
+    if let Some(buffer) = names_view.get_buffer() {
+        buffer.set_text(&duplicates.join("\n"));
+    }
+    let adj: Option<&gtk::Adjustment> = None;
+    let scrolled_window = gtk::ScrolledWindow::new(adj, adj);
+    scrolled_window.set_min_content_height(120);
+    scrolled_window.add(&names_view);
+    let action_combo = gtk::ComboBoxText::new();
+    for action in ImportConflictAction::values().iter() {
+        action_combo.append(Some(action.name()), action.label());
+    }
+    let last_action = recall("colln_editor::import_conflict_action")
+        .and_then(|text| ImportConflictAction::from_str(&text).ok())
+        .unwrap_or(ImportConflictAction::Skip);
+    action_combo.set_active_id(Some(last_action.name()));
+    let content_area = dialog.get_content_area();
+    content_area.pack_start(&label, false, false, 0);
+    content_area.pack_start(&scrolled_window, true, true, 0);
+    content_area.pack_start(&action_combo, false, false, 0);
+    content_area.show_all();
+    let mut o_action: Option<ImportConflictAction> = None;
+    if gtk::ResponseType::from(dialog.run()) == gtk::ResponseType::Ok {
+        if let Some(id) = action_combo.get_active_id() {
+            if let Ok(action) = ImportConflictAction::from_str(&id) {
+                remember("colln_editor::import_conflict_action", action.name());
+                o_action = Some(action);
+            }
+        }
+    }
+    unsafe { dialog.destroy() };
+    o_action
+}
+
 #[derive(Debug)]
 struct FileData<C, CID>
 where
@@ -84,7 +262,20 @@ where
     load_colln_btn: gtk::Button,
     save_colln_btn: gtk::Button,
     save_as_colln_btn: gtk::Button,
+    restore_backup_btn: gtk::Button,
+    import_btn: gtk::Button,
+    find_duplicates_btn: gtk::Button,
+    backups_kept_spin: gtk::SpinButton,
+    write_options: Cell<CollnWriteOptions>,
     file_status_btn: gtk::Button,
+    // Lines from the loaded file that weren't understood
+    unknown_lines: RefCell<Vec<String>>,
+    // Inventories of the loaded file's paints (not edited here)
+    inventories: RefCell<BTreeMap<String, Inventory>>,
+    unknown_lines_frame: gtk::Frame,
+    unknown_lines_view: gtk::TextView,
+    discard_unknown_lines_btn: gtk::Button,
+    load_progress_bar: gtk::ProgressBar,
 }
 
 impl<A, C, CID> CollnPaintEditorCore<A, C, CID>
@@ -160,6 +351,8 @@ where
                 self.save_as_colln_btn.set_sensitive(true);
             }
         };
+        self.restore_backup_btn
+            .set_sensitive(self.saved_file_path().is_some());
         self.update_file_status_button(file_status);
     }
 
@@ -212,6 +405,9 @@ where
                 FileStatus::NotUpToDateNotReady
             } else if let Some(cid) = self.cid_entry.get_colln_id() {
                 if cid == file_data.spec.colln_id
+                    && self.cid_entry.get_colln_notes() == file_data.spec.colln_notes
+                    && *self.unknown_lines.borrow() == file_data.spec.unknown_lines
+                    && *self.inventories.borrow() == file_data.spec.inventories
                     && self
                         .basic_paint_factory
                         .matches_paint_specs(&file_data.spec.paint_specs)
@@ -309,6 +505,14 @@ where
         self.update_button_sensitivities();
     }
 
+    fn duplicate_paint(&self, paint: &BasicPaint<C>) {
+        let mut spec = paint.get_spec();
+        spec.name = format!("{} (copy)", spec.name);
+        *self.edited_paint.borrow_mut() = None;
+        self.paint_spec_entry.set_new_spec_from(&spec);
+        self.update_button_sensitivities();
+    }
+
     fn set_file_data(&self, o_file_data: Option<FileData<C, CID>>) {
         // TODO: update displayed file path
         *self.file_data.borrow_mut() = o_file_data;
@@ -319,20 +523,35 @@ where
                 &CID::recollection_name_for("last_colln_edited_file"),
                 &path_text,
             );
+            add_recent_file(
+                &CID::recollection_name_for("recent_colln_edited_files"),
+                file_path,
+            );
         } else {
             self.file_path_text.set_label("");
         };
         self.update_file_button_sensitivities();
     }
 
+    fn backups_kept(&self) -> u32 {
+        self.backups_kept_spin.get_value_as_int() as u32
+    }
+
     fn write_to_file(&self, path: &Path) -> Result<(), PaintError<C>> {
         if let Some(colln_id) = self.cid_entry.get_colln_id() {
-            let spec = PaintCollnSpec::<C, CID> {
+            let mut spec = PaintCollnSpec::<C, CID> {
                 colln_id: colln_id,
+                colln_notes: self.cid_entry.get_colln_notes(),
                 paint_specs: self.basic_paint_factory.get_paint_specs(),
+                inventories: self.inventories.borrow().clone(),
+                unknown_lines: self.unknown_lines.borrow().clone(),
             };
+            // those of deleted (or renamed) paints go too
+            spec.prune_inventories();
+            *self.inventories.borrow_mut() = spec.inventories.clone();
+            make_backup(path, self.backups_kept())?;
             let mut file = File::create(path)?;
-            let spec_text = spec.to_string();
+            let spec_text = spec.to_string_with(&self.write_options.get());
             match file.write(&spec_text.into_bytes()) {
                 Ok(_) => {
                     let file_data = FileData::<C, CID> {
@@ -358,6 +577,37 @@ where
         }
     }
 
+    // Save (some of) the paints as a new collection with the same id
+    fn export_paints(&self, paints: &[BasicPaint<C>]) -> Result<(), PaintError<C>> {
+        let colln_id = self
+            .cid_entry
+            .get_colln_id()
+            .ok_or(PaintError::from(PaintErrorType::NoCollectionId))?;
+        let o_last_file = recall(&CID::recollection_name_for("last_colln_export_file"));
+        let last_file = if let Some(ref text) = o_last_file {
+            Some(text.as_str())
+        } else {
+            None
+        };
+        if let Some(path) = self.ask_file_path(Some("Export to:"), last_file, false) {
+            let mut spec = PaintCollnSpec::<C, CID> {
+                colln_id: colln_id,
+                colln_notes: self.cid_entry.get_colln_notes(),
+                paint_specs: self.basic_paint_factory.get_paint_specs_for(paints),
+                inventories: self.inventories.borrow().clone(),
+                unknown_lines: vec![],
+            };
+            spec.prune_inventories();
+            let mut file = File::create(&path)?;
+            file.write_all(spec.to_string_with(&self.write_options.get()).as_bytes())?;
+            remember(
+                &CID::recollection_name_for("last_colln_export_file"),
+                &pw_pathux::path_to_string(&path),
+            );
+        };
+        Ok(())
+    }
+
     fn save_as(&self) -> Result<(), PaintError<C>> {
         let o_last_file = recall(&CID::recollection_name_for("last_colln_edited_file"));
         let last_file = if let Some(ref text) = o_last_file {
@@ -365,7 +615,11 @@ where
         } else {
             None
         };
+        let options = ask_write_options(&self.write_options.get(), self)
+            .ok_or(PaintError::from(PaintErrorType::UserCancelled))?;
         if let Some(path) = self.ask_file_path(Some("Save as:"), last_file, false) {
+            self.write_options.set(options);
+            options.remember(&CID::recollection_name_for("colln_write_options"));
             self.write_to_file(&path)
         } else {
             Err(PaintErrorType::UserCancelled.into())
@@ -420,36 +674,285 @@ where
         if self.ok_to_reset() {
             self.paint_spec_entry.set_edited_spec(None);
             self.cid_entry.set_colln_id(None);
+            self.cid_entry.set_colln_notes("");
             self.basic_paint_factory.clear();
+            self.set_unknown_lines(&[]);
+            self.inventories.borrow_mut().clear();
             self.set_file_data(None);
         }
     }
 
-    pub fn load_from_file(&self) {
-        if !self.ok_to_reset() {
-            return;
-        };
-        let o_last_file = recall(&CID::recollection_name_for("last_colln_edited_file"));
+    pub fn is_loading(&self) -> bool {
+        self.load_progress_bar.get_visible()
+    }
+
+    // Editing is suspended while a file is being loaded
+    fn set_loading(&self, path: Option<&Path>) {
+        match path {
+            Some(path) => {
+                let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+                self.load_progress_bar
+                    .set_text(Some(&format!("Loading {}", file_name)));
+                self.load_progress_bar.set_fraction(0.0);
+                self.load_progress_bar.show();
+            }
+            None => self.load_progress_bar.hide(),
+        }
+        let editable = path.is_none();
+        self.h_paned.set_sensitive(editable);
+        self.new_colln_btn.set_sensitive(editable);
+        self.load_colln_btn.set_sensitive(editable);
+    }
+
+    fn set_loaded_content(&self, path: &Path, result: CollnLoadResult<C, CID>) {
+        match result {
+            Ok((spec, mut warnings, format)) => {
+                let spec_has_unknown_lines = spec.unknown_lines.len() > 0;
+                self.set_editor_content(&spec);
+                if format == CollnFileFormat::Text {
+                    let path = path.to_path_buf();
+                    self.set_file_data(Some(FileData { path, spec }));
+                } else {
+                    // Saving must not overwrite the original in another format
+                    warnings.push(format!(
+                        "Imported from {} format: save as a new collection file.",
+                        format
+                    ));
+                    self.set_file_data(None);
+                }
+                if format == CollnFileFormat::Text && spec_has_unknown_lines {
+                    self.show_problems_or_report(path, None, &warnings);
+                } else {
+                    self.warn_about_load(path, &warnings);
+                }
+            }
+            Err(err) => self.show_problems_or_report(path, Some(err), &[]),
+        }
+    }
+
+    // List every problem in a (hand edited) text file so that they can
+    // all be fixed in one go
+    fn show_problems_or_report(
+        &self,
+        path: &Path,
+        o_err: Option<PaintError<C>>,
+        warnings: &[String],
+    ) {
+        match PaintCollnSpec::<C, CID>::validate_file(path, PaintTypeCheck::Lenient) {
+            Ok(validation) if !validation.is_valid() => {
+                show_validation_problems(self, path, &validation)
+            }
+            _ => match o_err {
+                Some(err) => {
+                    let msg = format!("{:?}: Failed to load", path);
+                    self.report_error(&msg, &err)
+                }
+                None => self.warn_about_load(path, warnings),
+            },
+        }
+    }
+
+    // Most recently edited first
+    pub fn recent_colln_files(&self) -> Vec<PathBuf> {
+        recent_files(&CID::recollection_name_for("recent_colln_edited_files"))
+    }
+
+    pub fn clear_recent_colln_files(&self) {
+        clear_recent_files(&CID::recollection_name_for("recent_colln_edited_files"))
+    }
+
+    // Merge the paints from another collection file into the editor's
+    pub fn import_from_file(&self) {
+        let o_last_file = recall(&CID::recollection_name_for("last_colln_import_file"));
         let last_file = if let Some(ref text) = o_last_file {
             Some(text.as_str())
         } else {
             None
         };
-        if let Some(path) = self.ask_file_path(Some("Load from:"), last_file, true) {
-            match PaintCollnSpec::from_file(&path) {
-                Ok(spec) => {
-                    self.paint_spec_entry.set_edited_spec(None);
-                    self.cid_entry.set_colln_id(Some(&spec.colln_id));
-                    self.basic_paint_factory.clear();
-                    for paint_spec in spec.paint_specs.iter() {
-                        if let Err(err) = self.basic_paint_factory.add_paint(paint_spec) {
-                            self.report_error("Error", &err)
-                        }
+        if let Some(path) = self.ask_file_path(Some("Import from:"), last_file, true) {
+            // digital swatch libraries (.ase and .aco) as well as collections
+            let result = if SwatchFileFormat::for_path(&path).is_some() {
+                swatch_paint_specs::<C>(&path)
+            } else {
+                PaintCollnSpec::<C, CID>::from_file_any_format(&path, PaintTypeCheck::Lenient, true)
+                    .map(|(spec, warnings, _)| (spec.paint_specs, warnings))
+            };
+            match result {
+                Ok((paint_specs, warnings)) => {
+                    remember(
+                        &CID::recollection_name_for("last_colln_import_file"),
+                        &pw_pathux::path_to_string(&path),
+                    );
+                    self.warn_about_load(&path, &warnings);
+                    self.import_paint_specs(&paint_specs);
+                }
+                Err(err) => {
+                    let msg = format!("{:?}: Failed to import", path);
+                    self.report_error(&msg, &err)
+                }
+            }
+        }
+    }
+
+    fn import_paint_specs(&self, paint_specs: &[BasicPaintSpec<C>]) {
+        let duplicates: Vec<String> = paint_specs
+            .iter()
+            .filter(|spec| self.basic_paint_factory.has_paint_named(&spec.name))
+            .map(|spec| spec.name.clone())
+            .collect();
+        let action = if duplicates.len() > 0 {
+            match ask_import_conflict_action(&duplicates, self) {
+                Some(action) => action,
+                None => return,
+            }
+        } else {
+            ImportConflictAction::Skip
+        };
+        let (mut added, mut replaced, mut skipped) = (0, 0, 0);
+        for spec in paint_specs.iter() {
+            let result = match self.basic_paint_factory.get_paint(&spec.name) {
+                None => self.basic_paint_factory.add_paint(spec).map(|_| added += 1),
+                Some(old_paint) => match action {
+                    ImportConflictAction::Skip => {
+                        skipped += 1;
+                        Ok(())
+                    }
+                    ImportConflictAction::Overwrite => self
+                        .basic_paint_factory
+                        .replace_paint(&old_paint, spec)
+                        .map(|new_paint| {
+                            let o_edited_paint = self.edited_paint.borrow().clone();
+                            if o_edited_paint == Some(old_paint) {
+                                self.set_edited_paint(Some(&new_paint))
+                            }
+                            replaced += 1
+                        }),
+                    ImportConflictAction::Rename => {
+                        let mut spec = spec.clone();
+                        spec.name = unique_paint_name(&spec.name, |name| {
+                            self.basic_paint_factory.has_paint_named(name)
+                        });
+                        self.basic_paint_factory
+                            .add_paint(&spec)
+                            .map(|_| added += 1)
+                    }
+                },
+            };
+            if let Err(err) = result {
+                let msg = format!("{}: Failed to import", spec.name);
+                self.report_error(&msg, &err)
+            }
+        }
+        self.update_file_button_sensitivities();
+        let expln = format!(
+            "{} added, {} replaced and {} skipped.",
+            added, replaced, skipped
+        );
+        self.inform_user("Import complete.", Some(&expln));
+    }
+
+    fn replace_notes(&self, paint: &BasicPaint<C>, notes: &str) {
+        let mut spec = paint.get_spec();
+        spec.notes = notes.to_string();
+        match self.basic_paint_factory.replace_paint(paint, &spec) {
+            Ok(new_paint) => {
+                let o_edited_paint = self.edited_paint.borrow().clone();
+                if o_edited_paint.as_ref() == Some(paint) {
+                    self.set_edited_paint(Some(&new_paint))
+                }
+            }
+            Err(err) => self.report_error("Failed to update notes", &err),
+        }
+    }
+
+    pub fn review_suspected_duplicates(&self) {
+        let basic_paint_factory = self.basic_paint_factory.clone();
+        let metric = DeltaEMetric::recall();
+        let find = Rc::new(move |max_delta_e: f64| {
+            basic_paint_factory
+                .suspected_duplicates(max_delta_e, metric)
+                .iter()
+                .map(|(paint, other, delta_e)| (paint.name(), other.name(), *delta_e))
+                .collect::<Vec<(String, String, f64)>>()
+        });
+        while let Some((action, name, other_name)) = choose_duplicate_action(self, find.clone()) {
+            let paint = self.basic_paint_factory.get_paint(&name);
+            let other = self.basic_paint_factory.get_paint(&other_name);
+            if let (Some(paint), Some(other)) = (paint, other) {
+                match action {
+                    DuplicateAction::Merge => {
+                        let notes = merged_notes(&paint.notes(), &other.notes());
+                        self.basic_paint_factory.remove_paint(&other);
+                        self.replace_notes(&paint, &notes);
+                    }
+                    DuplicateAction::Annotate => {
+                        self.replace_notes(&paint, &annotated_notes(&paint.notes(), &other_name));
+                        self.replace_notes(&other, &annotated_notes(&other.notes(), &name));
                     }
-                    self.set_file_data(Some(FileData { path, spec }));
+                }
+                self.update_file_button_sensitivities();
+            }
+        }
+    }
+
+    fn warn_about_load(&self, path: &Path, warnings: &[String]) {
+        if warnings.len() > 0 {
+            let expln = format!("\"{:?}\":\n{}\n", path, warnings.join("\n"));
+            self.warn_user("Collection Loaded With Warnings", Some(expln.as_str()));
+        }
+    }
+
+    fn set_unknown_lines(&self, lines: &[String]) {
+        *self.unknown_lines.borrow_mut() = lines.to_vec();
+        if let Some(buffer) = self.unknown_lines_view.get_buffer() {
+            buffer.set_text(&lines.join("\n"));
+        }
+        self.unknown_lines_frame.set_visible(lines.len() > 0);
+    }
+
+    fn set_editor_content(&self, spec: &PaintCollnSpec<C, CID>) {
+        self.set_unknown_lines(&spec.unknown_lines);
+        *self.inventories.borrow_mut() = spec.inventories.clone();
+        self.paint_spec_entry.set_edited_spec(None);
+        self.cid_entry.set_colln_id(Some(&spec.colln_id));
+        self.cid_entry.set_colln_notes(&spec.colln_notes);
+        self.basic_paint_factory.clear();
+        for paint_spec in spec.paint_specs.iter() {
+            if let Err(err) = self.basic_paint_factory.add_paint(paint_spec) {
+                self.report_error("Error", &err)
+            }
+        }
+    }
+
+    // The restored content replaces the editor's content but isn't saved
+    // until the user chooses to do so.
+    pub fn restore_from_backup(&self) {
+        let path = if let Some(path) = self.saved_file_path() {
+            path
+        } else {
+            return;
+        };
+        let backups = list_backups(&path);
+        if backups.len() == 0 {
+            let expln = format!("{:?}: has no backups.", path);
+            self.inform_user("No backups found.", Some(&expln));
+            return;
+        }
+        if !self.ok_to_reset() {
+            return;
+        };
+        if let Some(backup_path) = choose_backup(&backups, self) {
+            match PaintCollnSpec::<C, CID>::from_file_tolerant(
+                &backup_path,
+                PaintTypeCheck::Lenient,
+            ) {
+                Ok((spec, warnings)) => {
+                    self.warn_about_load(&backup_path, &warnings);
+                    self.set_editor_content(&spec);
+                    self.update_file_button_sensitivities();
                 }
                 Err(err) => {
-                    let msg = format!("{:?}: Failed to load", path);
+                    let msg = format!("{:?}: Failed to load", backup_path);
                     self.report_error(&msg, &err)
                 }
             }
@@ -459,6 +962,61 @@ where
 
 pub type CollnPaintEditor<A, C, CID> = Rc<CollnPaintEditorCore<A, C, CID>>;
 
+pub trait CollnPaintEditorInterface {
+    fn load_from_file(&self);
+    fn load_from_recent_file(&self, path: &Path);
+    fn load_from_path(&self, path: &Path);
+}
+
+impl<A, C, CID> CollnPaintEditorInterface for CollnPaintEditor<A, C, CID>
+where
+    A: ColourAttributesInterface + 'static,
+    C: CharacteristicsInterface + 'static,
+    CID: CollnIdInterface + 'static,
+{
+    fn load_from_file(&self) {
+        if !self.ok_to_reset() {
+            return;
+        };
+        let o_last_file = recall(&CID::recollection_name_for("last_colln_edited_file"));
+        let last_file = if let Some(ref text) = o_last_file {
+            Some(text.as_str())
+        } else {
+            None
+        };
+        if let Some(path) = self.ask_file_path(Some("Load from:"), last_file, true) {
+            self.load_from_path(&path);
+        }
+    }
+
+    fn load_from_recent_file(&self, path: &Path) {
+        if self.ok_to_reset() {
+            self.load_from_path(path);
+        }
+    }
+
+    // The file is loaded in the background (so big files don't freeze the GUI)
+    fn load_from_path(&self, path: &Path) {
+        if self.is_loading() {
+            return;
+        }
+        self.set_loading(Some(path));
+        let bpe_c = self.clone();
+        let bpe_d = self.clone();
+        let path_c = path.to_path_buf();
+        load_colln_spec_async(
+            path,
+            PaintTypeCheck::Lenient,
+            true,
+            move |fraction| bpe_c.load_progress_bar.set_fraction(fraction),
+            move |result| {
+                bpe_d.set_loading(None);
+                bpe_d.set_loaded_content(&path_c, result);
+            },
+        );
+    }
+}
+
 impl<A, C, CID> SimpleCreation for CollnPaintEditor<A, C, CID>
 where
     A: ColourAttributesInterface + 'static,
@@ -495,6 +1053,26 @@ where
         save_as_colln_btn.set_image(Some(&colln_xpms::colln_save_as_image(24)));
         save_as_colln_btn
             .set_tooltip_text(Some("Save the current editor content to a nominated file."));
+        let restore_backup_btn = gtk::Button::with_label("Restore from Backup...");
+        restore_backup_btn.set_tooltip_text(Some(
+            "Replace the editor content with one of the current file's backups",
+        ));
+        let import_btn = gtk::Button::with_label("Import from File...");
+        import_btn.set_tooltip_text(Some(
+            "Add the paints from another collection file (or an Adobe .ase or .aco swatch file) to those being edited",
+        ));
+        let find_duplicates_btn = gtk::Button::with_label("Find Duplicates...");
+        find_duplicates_btn.set_tooltip_text(Some(
+            "List paints with (nearly) the same colour so that they can be merged or annotated",
+        ));
+        let backups_kept_spin = gtk::SpinButton::with_range(0.0, MAX_BACKUPS_KEPT as f64, 1.0);
+        backups_kept_spin.set_tooltip_text(Some(
+            "The number of time stamped backup copies to keep when saving (0 for none)",
+        ));
+        let backups_kept = recall(&CID::recollection_name_for("colln_backups_kept"))
+            .and_then(|text| text.parse::<u32>().ok())
+            .unwrap_or(DEFAULT_BACKUPS_KEPT);
+        backups_kept_spin.set_value(backups_kept.min(MAX_BACKUPS_KEPT) as f64);
 
         let file_status_btn = gtk::Button::new();
         file_status_btn.set_image(Some(&up_to_date_image(24)));
@@ -514,8 +1092,21 @@ where
             load_colln_btn: load_colln_btn,
             save_colln_btn: save_colln_btn,
             save_as_colln_btn: save_as_colln_btn,
+            restore_backup_btn: restore_backup_btn,
+            import_btn: import_btn,
+            find_duplicates_btn: find_duplicates_btn,
+            backups_kept_spin: backups_kept_spin,
+            write_options: Cell::new(CollnWriteOptions::recall(&CID::recollection_name_for(
+                "colln_write_options",
+            ))),
             file_path_text: gtk::Label::new(None),
             file_status_btn: file_status_btn,
+            unknown_lines: RefCell::new(Vec::new()),
+            inventories: RefCell::new(BTreeMap::new()),
+            unknown_lines_frame: gtk::Frame::new(Some("Preserved Lines (Not Understood)")),
+            unknown_lines_view: gtk::TextView::new(),
+            discard_unknown_lines_btn: gtk::Button::with_label("Discard"),
+            load_progress_bar: gtk::ProgressBar::new(),
         });
         bpe.file_path_text.set_justify(gtk::Justification::Left);
         bpe.file_path_text.set_xalign(0.01);
@@ -523,16 +1114,61 @@ where
         let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 2);
         hbox.pack_start(&bpe.new_colln_btn, false, false, 0);
         hbox.pack_start(&bpe.load_colln_btn, false, false, 0);
+        let bpe_c = bpe.clone();
+        let recent_colln_btn = recent_files_button(
+            &CID::recollection_name_for("recent_colln_edited_files"),
+            "Load a recently edited paint collection file for editing",
+            move |path| bpe_c.load_from_recent_file(path),
+        );
+        let recent_colln_btn_c = recent_colln_btn.clone();
+        bpe.load_colln_btn
+            .connect_property_sensitive_notify(move |button| {
+                recent_colln_btn_c.set_sensitive(button.get_sensitive())
+            });
+        hbox.pack_start(&recent_colln_btn, false, false, 0);
         hbox.pack_start(&bpe.save_colln_btn, false, false, 0);
         hbox.pack_start(&bpe.save_as_colln_btn, false, false, 0);
+        hbox.pack_start(&bpe.restore_backup_btn, false, false, 0);
+        hbox.pack_start(&bpe.import_btn, false, false, 0);
+        hbox.pack_start(&bpe.find_duplicates_btn, false, false, 0);
+        hbox.pack_start(&gtk::Label::new(Some("Backups:")), false, false, 0);
+        hbox.pack_start(&bpe.backups_kept_spin, false, false, 0);
         hbox.pack_start(&gtk::Label::new(Some("Current File:")), false, false, 0);
         hbox.pack_start(&bpe.file_path_text, true, true, 0);
         hbox.pack_start(&bpe.file_status_btn, false, false, 0);
+        bpe.load_progress_bar.set_show_text(true);
+        bpe.load_progress_bar.set_no_show_all(true);
+        hbox.pack_start(&bpe.load_progress_bar, false, false, 0);
         bpe.vbox.pack_start(&hbox, false, false, 0);
 
+        bpe.unknown_lines_view.set_editable(false);
+        bpe.unknown_lines_view.set_tooltip_text(Some(
+            "These lines will be written back to the file unchanged when it is saved",
+        ));
+        bpe.discard_unknown_lines_btn
+            .set_tooltip_text(Some("Drop these lines from the collection"));
+        let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 2);
+        let adj: Option<&gtk::Adjustment> = None;
+        let scrolled_window = gtk::ScrolledWindow::new(adj, adj);
+        scrolled_window.set_min_content_height(60);
+        scrolled_window.add(&bpe.unknown_lines_view);
+        hbox.pack_start(&scrolled_window, true, true, 0);
+        hbox.pack_start(&bpe.discard_unknown_lines_btn, false, false, 0);
+        bpe.unknown_lines_frame.add(&hbox);
+        bpe.unknown_lines_frame.show_all();
+        bpe.unknown_lines_frame.set_no_show_all(true);
+        bpe.unknown_lines_frame.hide();
+
         let vbox = gtk::Box::new(gtk::Orientation::Vertical, 1);
         vbox.pack_start(&bpe.cid_entry.pwo(), false, false, 0);
+        vbox.pack_start(&bpe.unknown_lines_frame, false, false, 0);
         vbox.pack_start(&bpe.basic_paint_factory.pwo(), true, true, 0);
+
+        let bpe_c = bpe.clone();
+        bpe.discard_unknown_lines_btn.connect_clicked(move |_| {
+            bpe_c.set_unknown_lines(&[]);
+            bpe_c.update_file_button_sensitivities();
+        });
         bpe.h_paned.add1(&vbox);
         bpe.h_paned.add2(&bpe.paint_spec_entry.pwo());
         bpe.h_paned
@@ -555,6 +1191,34 @@ where
         bpe.basic_paint_factory
             .connect_edit_paint(move |paint| bpe_c.set_edited_paint(Some(paint)));
 
+        let bpe_c = bpe.clone();
+        bpe.basic_paint_factory
+            .connect_paint_replaced(move |old_paint, new_paint| {
+                let o_edited_paint = bpe_c.edited_paint.borrow().clone();
+                if let Some(ref edited_paint) = o_edited_paint {
+                    if *edited_paint == *old_paint {
+                        bpe_c.set_edited_paint(Some(new_paint))
+                    }
+                };
+                bpe_c.update_file_button_sensitivities();
+            });
+
+        let bpe_c = bpe.clone();
+        bpe.basic_paint_factory
+            .connect_duplicate_paint(move |paint| {
+                if bpe_c.ok_to_reset_entry() {
+                    bpe_c.duplicate_paint(paint)
+                }
+            });
+
+        let bpe_c = bpe.clone();
+        bpe.basic_paint_factory
+            .connect_export_paints(move |paints| {
+                if let Err(err) = bpe_c.export_paints(paints) {
+                    bpe_c.report_error("Failed to export paints", &err);
+                }
+            });
+
         let bpe_c = bpe.clone();
         bpe.paint_spec_entry
             .connect_status_changed(move |_| bpe_c.update_button_sensitivities());
@@ -601,6 +1265,25 @@ where
             }
         });
 
+        let bpe_c = bpe.clone();
+        bpe.restore_backup_btn
+            .connect_clicked(move |_| bpe_c.restore_from_backup());
+
+        let bpe_c = bpe.clone();
+        bpe.import_btn
+            .connect_clicked(move |_| bpe_c.import_from_file());
+
+        let bpe_c = bpe.clone();
+        bpe.find_duplicates_btn
+            .connect_clicked(move |_| bpe_c.review_suspected_duplicates());
+
+        bpe.backups_kept_spin.connect_value_changed(|spin| {
+            remember(
+                &CID::recollection_name_for("colln_backups_kept"),
+                &spin.get_value_as_int().to_string(),
+            );
+        });
+
         let bpe_c = bpe.clone();
         bpe.save_as_colln_btn.connect_clicked(move |_| {
             if let Err(err) = bpe_c.save_as() {
@@ -631,3 +1314,19 @@ where
         bpe
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unique_paint_names() {
+        let taken = ["Red", "Red (2)", "Blue"];
+        let is_taken = |name: &str| taken.contains(&name);
+        assert_eq!(unique_paint_name("Red", is_taken), "Red (3)");
+        assert_eq!(unique_paint_name("Blue", is_taken), "Blue (2)");
+        for action in ImportConflictAction::values().iter() {
+            assert_eq!(ImportConflictAction::from_str(action.name()), Ok(*action));
+        }
+    }
+}
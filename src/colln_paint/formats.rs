@@ -0,0 +1,790 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use pw_gix::recollections::{recall, remember};
+use serde_derive::*;
+
+use crate::basic_paint::*;
+use crate::colour::*;
+use crate::error::*;
+
+use super::inventory::Inventory;
+use super::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollnFileFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+static COLLN_FILE_FORMAT_VALUES: &[CollnFileFormat] = &[
+    CollnFileFormat::Text,
+    CollnFileFormat::Json,
+    CollnFileFormat::Csv,
+];
+
+impl CollnFileFormat {
+    pub fn values() -> &'static [CollnFileFormat] {
+        COLLN_FILE_FORMAT_VALUES
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            CollnFileFormat::Text => "text",
+            CollnFileFormat::Json => "json",
+            CollnFileFormat::Csv => "csv",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match *self {
+            CollnFileFormat::Text => "Collection Text",
+            CollnFileFormat::Json => "JSON",
+            CollnFileFormat::Csv => "CSV",
+        }
+    }
+
+    // Decide the format from the content alone (file extensions are too
+    // often wrong or missing to be trusted).  Anything not recognisably
+    // JSON or CSV is assumed to be the native text format so that its
+    // parser gets to report what's wrong with it.
+    pub fn detect(content: &str) -> CollnFileFormat {
+        let content = content.trim_start_matches('\u{feff}').trim_start();
+        if content.starts_with('{') {
+            return CollnFileFormat::Json;
+        }
+        if let Some(first_line) = content.lines().next() {
+            if first_line.contains(',') {
+                let fields = csv_records(first_line).pop().unwrap_or_default();
+                if fields
+                    .iter()
+                    .any(|field| field.trim().eq_ignore_ascii_case("name"))
+                {
+                    return CollnFileFormat::Csv;
+                }
+            }
+        }
+        CollnFileFormat::Text
+    }
+}
+
+impl FromStr for CollnFileFormat {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<CollnFileFormat, String> {
+        for value in CollnFileFormat::values().iter() {
+            if value.name() == string {
+                return Ok(*value);
+            }
+        }
+        Err(format!("{}: unknown collection file format", string))
+    }
+}
+
+impl fmt::Display for CollnFileFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.label())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldOrder {
+    NotesLast,
+    NotesFirst,
+}
+
+static FIELD_ORDER_VALUES: &[FieldOrder] = &[FieldOrder::NotesLast, FieldOrder::NotesFirst];
+
+impl FieldOrder {
+    pub fn values() -> &'static [FieldOrder] {
+        FIELD_ORDER_VALUES
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            FieldOrder::NotesLast => "notes_last",
+            FieldOrder::NotesFirst => "notes_first",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match *self {
+            FieldOrder::NotesLast => "Notes After Characteristics",
+            FieldOrder::NotesFirst => "Notes Before Characteristics",
+        }
+    }
+}
+
+impl FromStr for FieldOrder {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<FieldOrder, String> {
+        for value in FieldOrder::values().iter() {
+            if value.name() == string {
+                return Ok(*value);
+            }
+        }
+        Err(format!("{}: unknown field order", string))
+    }
+}
+
+impl fmt::Display for FieldOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.label())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RGBFormat {
+    RGB16,
+    Hex,
+}
+
+static RGB_FORMAT_VALUES: &[RGBFormat] = &[RGBFormat::RGB16, RGBFormat::Hex];
+
+impl RGBFormat {
+    pub fn values() -> &'static [RGBFormat] {
+        RGB_FORMAT_VALUES
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            RGBFormat::RGB16 => "rgb16",
+            RGBFormat::Hex => "hex",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match *self {
+            RGBFormat::RGB16 => "RGB16(...)",
+            RGBFormat::Hex => "#RRGGBB (8 bit)",
+        }
+    }
+
+    pub fn rgb_text(&self, rgb: &RGB) -> String {
+        match *self {
+            RGBFormat::RGB16 => RGB16::from(*rgb).to_string(),
+            RGBFormat::Hex => rgb.into_css_hex(),
+        }
+    }
+}
+
+impl FromStr for RGBFormat {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<RGBFormat, String> {
+        for value in RGBFormat::values().iter() {
+            if value.name() == string {
+                return Ok(*value);
+            }
+        }
+        Err(format!("{}: unknown RGB format", string))
+    }
+}
+
+impl fmt::Display for RGBFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.label())
+    }
+}
+
+// How collection text files are laid out.  The default is the layout
+// written by PaintCollnSpec's Display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CollnWriteOptions {
+    pub field_order: FieldOrder,
+    pub rgb_format: RGBFormat,
+    pub align: bool,
+    pub omit_empty_notes: bool,
+}
+
+impl Default for CollnWriteOptions {
+    fn default() -> CollnWriteOptions {
+        CollnWriteOptions {
+            field_order: FieldOrder::NotesLast,
+            rgb_format: RGBFormat::RGB16,
+            align: false,
+            omit_empty_notes: false,
+        }
+    }
+}
+
+impl CollnWriteOptions {
+    pub fn recall(key: &str) -> CollnWriteOptions {
+        if let Some(text) = recall(key) {
+            if let Ok(options) = CollnWriteOptions::from_str(&text) {
+                return options;
+            }
+        };
+        CollnWriteOptions::default()
+    }
+
+    pub fn remember(&self, key: &str) {
+        remember(key, &self.to_string())
+    }
+
+    // "name_width" is the widest name field to be aligned with
+    pub fn paint_spec_text<C: CharacteristicsInterface>(
+        &self,
+        spec: &BasicPaintSpec<C>,
+        name_width: usize,
+    ) -> String {
+        let name_field = format!("name=\"{}\",", spec.name.replace("\"", "\\\""));
+        let padding = if self.align && name_field.chars().count() < name_width {
+            name_width - name_field.chars().count()
+        } else {
+            0
+        };
+        let mut text = format!(
+            "{}({}{} rgb={}",
+            GENERIC_PAINT_TYPE,
+            name_field,
+            " ".repeat(padding),
+            self.rgb_format.rgb_text(&spec.rgb)
+        );
+        let characteristics = spec.characteristics.to_string();
+        let notes = if self.omit_empty_notes && spec.notes.len() == 0 {
+            None
+        } else {
+            Some(format!("notes=\"{}\"", spec.notes.replace("\"", "\\\"")))
+        };
+        let fields = match self.field_order {
+            FieldOrder::NotesLast => vec![Some(characteristics), notes],
+            FieldOrder::NotesFirst => vec![notes, Some(characteristics)],
+        };
+        for field in fields.into_iter().filter_map(|field| field) {
+            if field.len() > 0 {
+                text += ", ";
+                text += &field;
+            }
+        }
+        text + ")"
+    }
+}
+
+impl FromStr for CollnWriteOptions {
+    type Err = String;
+
+    // e.g. "notes_last,rgb16,aligned,omit_empty_notes"
+    fn from_str(string: &str) -> Result<CollnWriteOptions, String> {
+        let fields: Vec<&str> = string.split(',').collect();
+        if fields.len() != 4 {
+            return Err(format!("{}: malformed collection write options", string));
+        }
+        Ok(CollnWriteOptions {
+            field_order: FieldOrder::from_str(fields[0])?,
+            rgb_format: RGBFormat::from_str(fields[1])?,
+            align: fields[2] == "aligned",
+            omit_empty_notes: fields[3] == "omit_empty_notes",
+        })
+    }
+}
+
+impl fmt::Display for CollnWriteOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{},{},{},{}",
+            self.field_order.name(),
+            self.rgb_format.name(),
+            if self.align { "aligned" } else { "unaligned" },
+            if self.omit_empty_notes {
+                "omit_empty_notes"
+            } else {
+                "keep_empty_notes"
+            }
+        )
+    }
+}
+
+// Split CSV text into records allowing for quoted fields containing
+// commas, doubled quotes and line breaks.
+pub fn csv_records(text: &str) -> Vec<Vec<String>> {
+    let mut records: Vec<Vec<String>> = Vec::new();
+    let mut record: Vec<String> = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            if c == '"' {
+                if chars.peek() == Some(&'"') {
+                    field.push('"');
+                    chars.next();
+                } else {
+                    in_quotes = false;
+                }
+            } else {
+                field.push(c);
+            }
+        } else {
+            match c {
+                '"' => in_quotes = true,
+                ',' => record.push(std::mem::take(&mut field)),
+                '\r' => (),
+                '\n' => {
+                    record.push(std::mem::take(&mut field));
+                    records.push(std::mem::take(&mut record));
+                }
+                _ => field.push(c),
+            }
+        }
+    }
+    if field.len() > 0 || record.len() > 0 {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+// The CSV column/JSON key for a collection id label e.g. "Series:" -> "series"
+fn colln_id_key(label: &str) -> String {
+    label.trim().trim_end_matches(':').trim().to_lowercase()
+}
+
+fn rgb_from_field(text: &str) -> Option<RGB> {
+    let text = text.trim();
+    if text.starts_with('#') {
+        rgb_from_css_hex(text)
+    } else {
+        RGB16::from_str(text).ok().map(RGB::from)
+    }
+}
+
+fn unit_from_field(text: &str) -> Option<f64> {
+    match f64::from_str(text.trim()) {
+        Ok(value) if value >= 0.0 && value <= 1.0 => Some(value),
+        _ => None,
+    }
+}
+
+// Build a paint spec from its named values.  Values with keys other than
+// "name", "rgb", "red", "green", "blue" and "notes" are offered to the
+// characteristics parser as key="value" pairs (the text format's syntax).
+fn paint_spec_from_fields<C: CharacteristicsInterface>(
+    fields: &[(String, String)],
+) -> Result<BasicPaintSpec<C>, String> {
+    let mut name = String::new();
+    let mut notes = String::new();
+    let mut o_rgb: Option<RGB> = None;
+    let mut rgb_parts: [Option<f64>; 3] = [None, None, None];
+    let mut characteristics: Vec<String> = Vec::new();
+    for (key, value) in fields.iter() {
+        match key.as_str() {
+            "name" => name = value.trim().to_string(),
+            "notes" => notes = value.trim().to_string(),
+            "rgb" | "colour" | "color" | "hex" => {
+                o_rgb = Some(rgb_from_field(value).ok_or(format!("{}: bad colour", value))?)
+            }
+            "red" => rgb_parts[0] = unit_from_field(value),
+            "green" => rgb_parts[1] = unit_from_field(value),
+            "blue" => rgb_parts[2] = unit_from_field(value),
+            _ => {
+                if value.trim().len() > 0 {
+                    characteristics.push(format!("{}=\"{}\"", key, value.trim()))
+                }
+            }
+        }
+    }
+    if name.len() == 0 {
+        return Err("missing paint name".to_string());
+    }
+    let rgb = match o_rgb {
+        Some(rgb) => rgb,
+        None => match rgb_parts {
+            [Some(red), Some(green), Some(blue)] => RGB::from([red, green, blue]),
+            _ => return Err(format!("{}: missing or bad colour", name)),
+        },
+    };
+    let characteristics = C::from_str(&characteristics.join(", "))
+        .map_err(|err| format!("{}: {}", name, err.to_string()))?;
+    Ok(BasicPaintSpec::<C> {
+        rgb,
+        name,
+        notes,
+        characteristics,
+    })
+}
+
+pub const COLLN_DOCUMENT_VERSION: u32 = 1;
+
+// The JSON document written by PaintCollnSpec::to_json_file().  Any
+// fields added in later versions need defaults so that documents
+// written by earlier versions still load.
+#[derive(Serialize, Deserialize)]
+struct PaintCollnDocument<C: CharacteristicsInterface> {
+    format_version: u32,
+    colln_name: String,
+    colln_owner: String,
+    #[serde(default)]
+    colln_notes: String,
+    paint_specs: Vec<BasicPaintSpec<C>>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    inventories: BTreeMap<String, Inventory>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    unknown_lines: Vec<String>,
+}
+
+impl<C, CID> PaintCollnSpec<C, CID>
+where
+    C: CharacteristicsInterface,
+    CID: CollnIdInterface,
+{
+    fn from_parsed_paints(
+        colln_name: &str,
+        colln_owner: &str,
+        colln_notes: &str,
+        paints: Vec<Result<BasicPaintSpec<C>, String>>,
+        tolerant: bool,
+    ) -> Result<(PaintCollnSpec<C, CID>, Vec<String>), PaintError<C>> {
+        if colln_name.len() == 0 || colln_owner.len() == 0 {
+            return Err(PaintErrorType::NoCollectionId.into());
+        }
+        let mut warnings: Vec<String> = Vec::new();
+        let mut paint_specs: Vec<BasicPaintSpec<C>> = Vec::new();
+        for paint in paints.into_iter() {
+            let spec = match paint {
+                Ok(spec) => spec,
+                Err(msg) => {
+                    if tolerant {
+                        warnings.push(format!("{}: not understood (skipped)", msg));
+                        continue;
+                    } else {
+                        return Err(PaintErrorType::MalformedText(msg).into());
+                    }
+                }
+            };
+            match paint_specs.binary_search_by_key(&spec.name, |bps| bps.name.clone()) {
+                Ok(_) => return Err(PaintErrorType::AlreadyExists(spec.name).into()),
+                Err(index) => paint_specs.insert(index, spec),
+            }
+        }
+        let psc = PaintCollnSpec::<C, CID> {
+            colln_id: Rc::new(CID::new(colln_name, colln_owner)),
+            colln_notes: colln_notes.to_string(),
+            paint_specs,
+            inventories: BTreeMap::new(),
+            unknown_lines: vec![],
+        };
+        Ok((psc, warnings))
+    }
+
+    // A header row naming the columns followed by one row per paint.  The
+    // collection's name and owner are given by columns with the same
+    // names as their labels (e.g. "Series" and "Manufacturer") and are
+    // taken from the first row that has them (as are the collection's
+    // notes from a column named e.g. "Series Notes").
+    pub fn from_csv_str(
+        string: &str,
+        tolerant: bool,
+    ) -> Result<(PaintCollnSpec<C, CID>, Vec<String>), PaintError<C>> {
+        let string = string.trim_start_matches('\u{feff}');
+        let mut records = csv_records(string)
+            .into_iter()
+            .filter(|record| record.iter().any(|field| field.trim().len() > 0));
+        let header: Vec<String> = match records.next() {
+            Some(header) => header.iter().map(|key| key.trim().to_lowercase()).collect(),
+            None => return Err(PaintErrorType::MalformedText(string.to_string()).into()),
+        };
+        let name_key = colln_id_key(&CID::colln_name_label());
+        let owner_key = colln_id_key(&CID::colln_owner_label());
+        let notes_key = colln_id_key(COLLN_NOTES_LABEL);
+        let mut colln_name = String::new();
+        let mut colln_owner = String::new();
+        let mut colln_notes = String::new();
+        let mut paints: Vec<Result<BasicPaintSpec<C>, String>> = Vec::new();
+        for record in records {
+            let mut fields: Vec<(String, String)> = Vec::new();
+            for (key, value) in header.iter().zip(record.into_iter()) {
+                if *key == name_key {
+                    if colln_name.len() == 0 {
+                        colln_name = value.trim().to_string();
+                    }
+                } else if *key == owner_key {
+                    if colln_owner.len() == 0 {
+                        colln_owner = value.trim().to_string();
+                    }
+                } else if *key == format!("{} {}", name_key, notes_key) {
+                    if colln_notes.len() == 0 {
+                        colln_notes = value.trim().to_string();
+                    }
+                } else {
+                    fields.push((key.clone(), value));
+                }
+            }
+            paints.push(paint_spec_from_fields::<C>(&fields));
+        }
+        Self::from_parsed_paints(&colln_name, &colln_owner, &colln_notes, paints, tolerant)
+    }
+
+    // An object with the collection's name and owner (keyed by their
+    // lower cased labels e.g. "series" and "manufacturer"), optional
+    // "notes" and a "paints" array of objects whose keys are as for the
+    // CSV columns.
+    pub fn from_json_str(
+        string: &str,
+        tolerant: bool,
+    ) -> Result<(PaintCollnSpec<C, CID>, Vec<String>), PaintError<C>> {
+        let value: serde_json::Value = serde_json::from_str(string.trim_start_matches('\u{feff}'))
+            .map_err(|err| PaintError::from(PaintErrorType::MalformedText(err.to_string())))?;
+        let object = value
+            .as_object()
+            .ok_or(PaintError::from(PaintErrorType::MalformedText(
+                string.to_string(),
+            )))?;
+        if object.contains_key("paint_specs") {
+            return Ok((Self::from_json_document(string)?, vec![]));
+        }
+        let text_for = |key: &str| -> String {
+            match object.get(key) {
+                Some(serde_json::Value::String(text)) => text.trim().to_string(),
+                _ => String::new(),
+            }
+        };
+        let colln_name = text_for(&colln_id_key(&CID::colln_name_label()));
+        let colln_owner = text_for(&colln_id_key(&CID::colln_owner_label()));
+        let colln_notes = text_for(&colln_id_key(COLLN_NOTES_LABEL));
+        let mut paints: Vec<Result<BasicPaintSpec<C>, String>> = Vec::new();
+        if let Some(array) = object.get("paints").and_then(|paints| paints.as_array()) {
+            for item in array.iter() {
+                match item.as_object() {
+                    Some(paint) => {
+                        let fields: Vec<(String, String)> = paint
+                            .iter()
+                            .map(|(key, value)| {
+                                let text = match value {
+                                    serde_json::Value::String(text) => text.clone(),
+                                    _ => value.to_string(),
+                                };
+                                (key.to_lowercase(), text)
+                            })
+                            .collect();
+                        paints.push(paint_spec_from_fields::<C>(&fields));
+                    }
+                    None => paints.push(Err(item.to_string())),
+                }
+            }
+        } else {
+            return Err(PaintErrorType::MalformedText("no \"paints\" array".to_string()).into());
+        }
+        Self::from_parsed_paints(&colln_name, &colln_owner, &colln_notes, paints, tolerant)
+    }
+
+    // The serde form of the whole specification (as opposed to the
+    // looser layout accepted by from_json_str() for imports)
+    pub fn to_json_document(&self) -> String {
+        let document = PaintCollnDocument::<C> {
+            format_version: COLLN_DOCUMENT_VERSION,
+            colln_name: self.colln_id.colln_name(),
+            colln_owner: self.colln_id.colln_owner(),
+            colln_notes: self.colln_notes.clone(),
+            paint_specs: self.paint_specs.clone(),
+            inventories: self.inventories.clone(),
+            unknown_lines: self.unknown_lines.clone(),
+        };
+        serde_json::to_string_pretty(&document).expect("paint specs are always serialisable")
+    }
+
+    pub fn from_json_document(string: &str) -> Result<PaintCollnSpec<C, CID>, PaintError<C>> {
+        let document: PaintCollnDocument<C> =
+            serde_json::from_str(string.trim_start_matches('\u{feff}'))
+                .map_err(|err| PaintError::from(PaintErrorType::MalformedText(err.to_string())))?;
+        if document.format_version > COLLN_DOCUMENT_VERSION {
+            let msg = format!(
+                "format version {} is newer than this program understands",
+                document.format_version
+            );
+            return Err(PaintErrorType::MalformedText(msg).into());
+        }
+        let paints = document.paint_specs.into_iter().map(Ok).collect();
+        let (mut spec, _) = Self::from_parsed_paints(
+            &document.colln_name,
+            &document.colln_owner,
+            &document.colln_notes,
+            paints,
+            false,
+        )?;
+        spec.inventories = document.inventories;
+        spec.prune_inventories();
+        spec.unknown_lines = document.unknown_lines;
+        Ok(spec)
+    }
+
+    pub fn from_json_file(path: &Path) -> Result<PaintCollnSpec<C, CID>, PaintError<C>> {
+        let mut file = File::open(path)?;
+        let mut string = String::new();
+        file.read_to_string(&mut string)?;
+        Self::from_json_document(&string)
+    }
+
+    pub fn to_json_file(&self, path: &Path) -> Result<(), PaintError<C>> {
+        let mut file = File::create(path)?;
+        file.write_all(self.to_json_document().as_bytes())?;
+        Ok(())
+    }
+
+    // As for the Display text but laid out as "options" say
+    pub fn to_string_with(&self, options: &CollnWriteOptions) -> String {
+        let mut text = format!(
+            "{} {}\n{} {}\n",
+            CID::colln_name_label(),
+            self.colln_id.colln_name(),
+            CID::colln_owner_label(),
+            self.colln_id.colln_owner()
+        );
+        for line in self.colln_notes.lines() {
+            text += &format!("{} {}\n", COLLN_NOTES_LABEL, line);
+        }
+        let name_width = self
+            .paint_specs
+            .iter()
+            .map(|spec| spec.name.replace("\"", "\\\"").chars().count() + 8)
+            .max()
+            .unwrap_or(0);
+        for paint_spec in self.paint_specs.iter() {
+            text += &options.paint_spec_text(paint_spec, name_width);
+            text += "\n";
+        }
+        for line in self.inventory_lines().iter() {
+            text += &format!("{}\n", line);
+        }
+        for line in self.unknown_lines.iter() {
+            text += &format!("{}\n", line);
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::art_paint::*;
+    use crate::series_paint::*;
+
+    type Spec = PaintCollnSpec<ArtPaintCharacteristics, PaintSeriesId>;
+
+    const CSV: &str = "Manufacturer,Series,Name,RGB,Permanence,Transparency,Notes
+Imaginary,Spreadsheet Series,Black,#000000,A,O,
+,,White,#FFFFFF,A,O,\"Titanium, mostly\"
+";
+
+    const JSON: &str = r##"{
+    "manufacturer": "Imaginary",
+    "series": "Exported Series",
+    "paints": [
+        {"name": "Black", "rgb": "#000000", "permanence": "A", "transparency": "O"},
+        {"name": "Red", "red": 1.0, "green": 0, "blue": 0, "permanence": "B", "transparency": "T"}
+    ]
+}"##;
+
+    #[test]
+    fn formats_detected() {
+        assert_eq!(CollnFileFormat::detect(CSV), CollnFileFormat::Csv);
+        assert_eq!(CollnFileFormat::detect(JSON), CollnFileFormat::Json);
+        assert_eq!(
+            CollnFileFormat::detect("Manufacturer: Imaginary\nSeries: Text, Mostly\n"),
+            CollnFileFormat::Text
+        );
+        for format in CollnFileFormat::values().iter() {
+            assert_eq!(CollnFileFormat::from_str(format.name()), Ok(*format));
+        }
+    }
+
+    #[test]
+    fn csv_and_json_collections_parse() {
+        let (spec, warnings) = Spec::from_csv_str(CSV, false).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(spec.colln_id.colln_name(), "Spreadsheet Series");
+        assert_eq!(spec.colln_id.colln_owner(), "Imaginary");
+        assert_eq!(spec.paint_specs.len(), 2);
+        assert_eq!(spec.paint_specs[1].name, "White");
+        assert_eq!(spec.paint_specs[1].notes, "Titanium, mostly");
+        assert_eq!(spec.paint_specs[1].rgb, RGB::WHITE);
+
+        let (spec, warnings) = Spec::from_json_str(JSON, false).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(spec.colln_id.colln_name(), "Exported Series");
+        assert_eq!(spec.paint_specs.len(), 2);
+        assert_eq!(spec.paint_specs[1].rgb, RGB::from([1.0, 0.0, 0.0]));
+        let (reloaded, _) =
+            Spec::from_str_checked(&spec.to_string(), PaintTypeCheck::Lenient).unwrap();
+        assert_eq!(reloaded.paint_specs.len(), 2);
+
+        let bad = CSV.to_string() + ",,Grey,not a colour,A,O,\n";
+        assert!(Spec::from_csv_str(&bad, false).is_err());
+        let (spec, warnings) = Spec::from_csv_str(&bad, true).unwrap();
+        assert_eq!(spec.paint_specs.len(), 2);
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn json_document_round_trip() {
+        let (mut spec, _) = Spec::from_csv_str(CSV, false).unwrap();
+        spec.colln_notes = "Notes\nover two lines".to_string();
+        let document = spec.to_json_document();
+        assert_eq!(CollnFileFormat::detect(&document), CollnFileFormat::Json);
+        let reloaded = Spec::from_json_document(&document).unwrap();
+        assert_eq!(reloaded.colln_id, spec.colln_id);
+        assert_eq!(reloaded.colln_notes, spec.colln_notes);
+        assert_eq!(reloaded.paint_specs, spec.paint_specs);
+        let (reloaded, _) = Spec::from_json_str(&document, false).unwrap();
+        assert_eq!(reloaded.paint_specs, spec.paint_specs);
+        let newer = document.replacen("\"format_version\": 1", "\"format_version\": 99", 1);
+        assert!(Spec::from_json_document(&newer).is_err());
+    }
+
+    #[test]
+    fn write_options_round_trip() {
+        let (spec, _) = Spec::from_csv_str(CSV, false).unwrap();
+        let default = CollnWriteOptions::default();
+        assert_eq!(spec.to_string_with(&default), spec.to_string());
+        for field_order in FieldOrder::values().iter() {
+            for rgb_format in RGBFormat::values().iter() {
+                for align in [false, true].iter() {
+                    for omit_empty_notes in [false, true].iter() {
+                        let options = CollnWriteOptions {
+                            field_order: *field_order,
+                            rgb_format: *rgb_format,
+                            align: *align,
+                            omit_empty_notes: *omit_empty_notes,
+                        };
+                        assert_eq!(
+                            CollnWriteOptions::from_str(&options.to_string()),
+                            Ok(options)
+                        );
+                        let text = spec.to_string_with(&options);
+                        let reloaded = Spec::from_str(&text).unwrap();
+                        assert_eq!(reloaded.paint_specs, spec.paint_specs);
+                    }
+                }
+            }
+        }
+        let options = CollnWriteOptions {
+            field_order: FieldOrder::NotesFirst,
+            rgb_format: RGBFormat::Hex,
+            align: true,
+            omit_empty_notes: true,
+        };
+        let lines: Vec<String> = spec
+            .to_string_with(&options)
+            .lines()
+            .map(|line| line.to_string())
+            .collect();
+        assert_eq!(
+            lines[2],
+            "PaintSpec(name=\"Black\", rgb=#000000, permanence=\"A\", transparency=\"O\")"
+        );
+        assert_eq!(
+            lines[3],
+            "PaintSpec(name=\"White\", rgb=#FFFFFF, notes=\"Titanium, mostly\", permanence=\"A\", transparency=\"O\")"
+        );
+    }
+}
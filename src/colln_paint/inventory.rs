@@ -0,0 +1,346 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+// What the user owns of each series paint (stock level, purchase date and
+// price).  These are kept in the series file as "Inventory(...)" lines
+// after the paints so that they travel with the collection.
+
+use chrono::NaiveDate;
+use regex::Regex;
+use serde_derive::*;
+
+use pw_gix::{
+    gtk::{self, prelude::*},
+    gtkx::dialog::*,
+    wrapper::*,
+};
+
+use crate::app_name;
+use crate::basic_paint::*;
+use crate::series_paint::PaintSeriesId;
+
+use super::*;
+
+pub const INVENTORY_LINE_PREFIX: &str = "Inventory(";
+pub const PURCHASE_DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Inventory {
+    #[serde(default)]
+    pub stock_level: Option<u32>,
+    // As PURCHASE_DATE_FORMAT (i.e. "YYYY-MM-DD")
+    #[serde(default)]
+    pub purchase_date: Option<String>,
+    #[serde(default)]
+    pub price: Option<f64>,
+}
+
+impl Inventory {
+    // NB: an unknown stock level isn't out of stock
+    pub fn is_out_of_stock(&self) -> bool {
+        self.stock_level == Some(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Inventory::default()
+    }
+
+    pub fn tooltip_text(&self) -> String {
+        let mut lines: Vec<String> = Vec::new();
+        match self.stock_level {
+            Some(0) => lines.push("Out of stock".to_string()),
+            Some(stock_level) => lines.push(format!("In stock: {}", stock_level)),
+            None => (),
+        }
+        if let Some(ref purchase_date) = self.purchase_date {
+            lines.push(format!("Purchased: {}", purchase_date));
+        }
+        if let Some(price) = self.price {
+            lines.push(format!("Price: {:.2}", price));
+        }
+        lines.join("\n")
+    }
+}
+
+lazy_static! {
+    static ref INVENTORY_RE: Regex =
+        Regex::new(r#"^Inventory\(name="(?P<name>.+?)"(?P<fields>(?:,\s*\w+="[^"]*")*)\)$"#)
+            .unwrap();
+    static ref INVENTORY_FIELD_RE: Regex =
+        Regex::new(r#"(?P<key>\w+)="(?P<value>[^"]*)""#).unwrap();
+}
+
+pub fn is_inventory_line(line: &str) -> bool {
+    line.starts_with(INVENTORY_LINE_PREFIX)
+}
+
+// The date in the canonical format (e.g. "2017-2-3" becomes "2017-02-03")
+pub fn checked_purchase_date(text: &str) -> Result<String, String> {
+    NaiveDate::parse_from_str(text.trim(), PURCHASE_DATE_FORMAT)
+        .map(|date| date.format(PURCHASE_DATE_FORMAT).to_string())
+        .map_err(|_| format!("{}: not a date (YYYY-MM-DD)", text))
+}
+
+pub fn parse_inventory_line(line: &str) -> Result<(String, Inventory), String> {
+    let captures = INVENTORY_RE
+        .captures(line)
+        .ok_or(format!("{}: malformed inventory", line))?;
+    let name = captures["name"].replace("\\\"", "\"");
+    let mut inventory = Inventory::default();
+    for field in INVENTORY_FIELD_RE.captures_iter(&captures["fields"]) {
+        let value = &field["value"];
+        match &field["key"] {
+            "stock" => {
+                let stock_level = value
+                    .parse::<u32>()
+                    .map_err(|_| format!("{}: {}: bad stock level", name, value))?;
+                inventory.stock_level = Some(stock_level);
+            }
+            "purchased" => {
+                let purchase_date =
+                    checked_purchase_date(value).map_err(|msg| format!("{}: {}", name, msg))?;
+                inventory.purchase_date = Some(purchase_date);
+            }
+            "price" => match value.parse::<f64>() {
+                Ok(price) if price >= 0.0 => inventory.price = Some(price),
+                _ => return Err(format!("{}: {}: bad price", name, value)),
+            },
+            key => return Err(format!("{}: {}: unknown inventory field", name, key)),
+        }
+    }
+    Ok((name, inventory))
+}
+
+pub fn inventory_line(name: &str, inventory: &Inventory) -> String {
+    let mut text = format!(
+        "{}name=\"{}\"",
+        INVENTORY_LINE_PREFIX,
+        name.replace("\"", "\\\"")
+    );
+    if let Some(stock_level) = inventory.stock_level {
+        text += &format!(", stock=\"{}\"", stock_level);
+    }
+    if let Some(ref purchase_date) = inventory.purchase_date {
+        text += &format!(", purchased=\"{}\"", purchase_date);
+    }
+    if let Some(price) = inventory.price {
+        text += &format!(", price=\"{:.2}\"", price);
+    }
+    text + ")"
+}
+
+// Only series paints (i.e. paints that can be bought) have inventories
+pub trait InventoryInterface {
+    fn inventory(&self) -> Option<Inventory>;
+
+    fn is_out_of_stock(&self) -> bool {
+        self.inventory()
+            .map_or(false, |inventory| inventory.is_out_of_stock())
+    }
+}
+
+impl<C> InventoryInterface for CollnPaint<C, PaintSeriesId>
+where
+    C: CharacteristicsInterface,
+{
+    fn inventory(&self) -> Option<Inventory> {
+        self.inventory.borrow().clone()
+    }
+}
+
+impl<C, CID> CollnPaintCore<C, CID>
+where
+    C: CharacteristicsInterface,
+    CID: CollnIdInterface,
+{
+    // NB: keeping the collection file up to date is the caller's business
+    pub(crate) fn set_inventory(&self, o_inventory: Option<Inventory>) {
+        *self.inventory.borrow_mut() = o_inventory.filter(|inventory| !inventory.is_empty());
+    }
+}
+
+impl<C, CID> PaintCollnSpec<C, CID>
+where
+    C: CharacteristicsInterface,
+    CID: CollnIdInterface,
+{
+    pub fn inventory_for(&self, name: &str) -> Option<Inventory> {
+        self.inventories.get(name).cloned()
+    }
+
+    pub fn set_inventory(&mut self, name: &str, o_inventory: Option<Inventory>) {
+        match o_inventory.filter(|inventory| !inventory.is_empty()) {
+            Some(inventory) => {
+                self.inventories.insert(name.to_string(), inventory);
+            }
+            None => {
+                self.inventories.remove(name);
+            }
+        }
+    }
+
+    // Drop the inventories of paints that aren't in the collection
+    // returning their names
+    pub fn prune_inventories(&mut self) -> Vec<String> {
+        let orphans: Vec<String> = self
+            .inventories
+            .keys()
+            .filter(|name| self.get_index_for_name(name).is_none())
+            .cloned()
+            .collect();
+        for name in orphans.iter() {
+            self.inventories.remove(name);
+        }
+        orphans
+    }
+
+    pub fn inventory_lines(&self) -> Vec<String> {
+        self.inventories
+            .iter()
+            .map(|(name, inventory)| inventory_line(name, inventory))
+            .collect()
+    }
+}
+
+// An empty inventory means that the paint's inventory should be removed
+pub fn ask_inventory<W: WidgetWrapper>(
+    caller: &W,
+    paint_name: &str,
+    o_inventory: Option<&Inventory>,
+) -> Option<Inventory> {
+    let title = format!("{}: Inventory: {}", app_name(), paint_name);
+    let dialog = caller.new_dialog_with_buttons(
+        Some(&title),
+        gtk::DialogFlags::DESTROY_WITH_PARENT,
+        CANCEL_OK_BUTTONS,
+    );
+    let inventory = o_inventory.cloned().unwrap_or_default();
+    let grid = gtk::Grid::new();
+    grid.set_column_spacing(4);
+
+    let stock_btn = gtk::CheckButton::with_label("Stock level:");
+    stock_btn.set_tooltip_text(Some(
+        "How many of this paint are owned (zero means out of stock)",
+    ));
+    let stock_spin = gtk::SpinButton::with_range(0.0, 999.0, 1.0);
+    stock_btn.set_active(inventory.stock_level.is_some());
+    stock_spin.set_value(inventory.stock_level.unwrap_or(1) as f64);
+    stock_spin.set_sensitive(stock_btn.get_active());
+    let stock_spin_c = stock_spin.clone();
+    stock_btn.connect_toggled(move |button| stock_spin_c.set_sensitive(button.get_active()));
+    grid.attach(&stock_btn, 0, 0, 1, 1);
+    grid.attach(&stock_spin, 1, 0, 1, 1);
+
+    let label = gtk::Label::new(Some("Purchase date:"));
+    label.set_halign(gtk::Align::End);
+    let date_entry = gtk::Entry::new();
+    date_entry.set_placeholder_text(Some("YYYY-MM-DD"));
+    date_entry.set_text(&inventory.purchase_date.clone().unwrap_or_default());
+    grid.attach(&label, 0, 1, 1, 1);
+    grid.attach(&date_entry, 1, 1, 1, 1);
+
+    let price_btn = gtk::CheckButton::with_label("Price:");
+    let price_spin = gtk::SpinButton::with_range(0.0, 99999.99, 0.01);
+    price_spin.set_digits(2);
+    price_btn.set_active(inventory.price.is_some());
+    price_spin.set_value(inventory.price.unwrap_or(0.0));
+    price_spin.set_sensitive(price_btn.get_active());
+    let price_spin_c = price_spin.clone();
+    price_btn.connect_toggled(move |button| price_spin_c.set_sensitive(button.get_active()));
+    grid.attach(&price_btn, 0, 2, 1, 1);
+    grid.attach(&price_spin, 1, 2, 1, 1);
+
+    let content_area = dialog.get_content_area();
+    content_area.pack_start(&grid, false, false, 0);
+    content_area.show_all();
+    let mut o_new_inventory: Option<Inventory> = None;
+    while gtk::ResponseType::from(dialog.run()) == gtk::ResponseType::Ok {
+        let date_text = date_entry.get_text();
+        let purchase_date = if date_text.trim().len() > 0 {
+            match checked_purchase_date(&date_text) {
+                Ok(purchase_date) => Some(purchase_date),
+                Err(msg) => {
+                    caller.warn_user("Invalid purchase date", Some(&msg));
+                    continue;
+                }
+            }
+        } else {
+            None
+        };
+        o_new_inventory = Some(Inventory {
+            stock_level: if stock_btn.get_active() {
+                Some(stock_spin.get_value_as_int() as u32)
+            } else {
+                None
+            },
+            purchase_date,
+            price: if price_btn.get_active() {
+                Some(price_spin.get_value())
+            } else {
+                None
+            },
+        });
+        break;
+    }
+    unsafe { dialog.destroy() };
+    o_new_inventory
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::art_paint::*;
+    use crate::colln_paint::collection::*;
+    use crate::series_paint::*;
+
+    const TEXT: &str = "Manufacturer: Imaginary
+Series: Stocked Series
+PaintSpec(name=\"Black\", rgb=RGB16(red=0x0000, green=0x0000, blue=0x0000), transparency=\"O\", permanence=\"A\", notes=\"\")
+PaintSpec(name=\"White\", rgb=RGB16(red=0xFFFF, green=0xFFFF, blue=0xFFFF), transparency=\"O\", permanence=\"A\", notes=\"\")
+Inventory(name=\"Black\", stock=\"0\", purchased=\"2017-2-3\", price=\"12.5\")
+Inventory(name=\"Grey\", stock=\"1\")
+";
+
+    #[test]
+    fn inventory_lines() {
+        let (name, inventory) =
+            parse_inventory_line("Inventory(name=\"A \\\"B\\\"\", stock=\"2\")").unwrap();
+        assert_eq!(name, "A \"B\"");
+        assert_eq!(inventory.stock_level, Some(2));
+        assert!(!inventory.is_out_of_stock());
+        assert_eq!(
+            parse_inventory_line(&inventory_line(&name, &inventory)),
+            Ok((name, inventory))
+        );
+        assert!(parse_inventory_line("Inventory(name=\"A\", stock=\"-1\")").is_err());
+        assert!(parse_inventory_line("Inventory(name=\"A\", purchased=\"today\")").is_err());
+        assert!(parse_inventory_line("Inventory(name=\"A\", colour=\"red\")").is_err());
+        assert_eq!(
+            checked_purchase_date("2017-2-3"),
+            Ok("2017-02-03".to_string())
+        );
+    }
+
+    #[test]
+    fn inventories_in_series_files() {
+        let (spec, warnings) =
+            PaintCollnSpec::<ArtPaintCharacteristics, PaintSeriesId>::from_str_checked(
+                TEXT,
+                PaintTypeCheck::Lenient,
+            )
+            .unwrap();
+        assert_eq!(warnings.len(), 1);
+        let inventory = spec.inventory_for("Black").unwrap();
+        assert!(inventory.is_out_of_stock());
+        assert_eq!(inventory.purchase_date, Some("2017-02-03".to_string()));
+        assert_eq!(inventory.price, Some(12.5));
+        assert_eq!(spec.inventory_for("White"), None);
+        assert_eq!(spec.inventory_for("Grey"), None);
+        let text = spec.to_string();
+        assert!(text.ends_with(
+            "Inventory(name=\"Black\", stock=\"0\", purchased=\"2017-02-03\", price=\"12.50\")\n"
+        ));
+        let colln = SeriesPaintColln::<ArtPaintCharacteristics>::from_spec(&spec);
+        assert!(colln.get_paint("Black").unwrap().is_out_of_stock());
+        assert!(!colln.get_paint("White").unwrap().is_out_of_stock());
+    }
+}
@@ -0,0 +1,242 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+// Upgrading collection files written by much older versions of the
+// program e.g. "NamedColour(...)" paints with "RGB(...)" colours and
+// without some of the characteristics that are now required.
+
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+
+use crate::basic_paint::*;
+use crate::error::*;
+
+use super::*;
+
+// Paint type prefixes that are only found in legacy files
+pub const LEGACY_PAINT_TYPES: &[&str] = &["NamedColour"];
+
+lazy_static! {
+    static ref CHARACTERISTIC_RE: Regex =
+        Regex::new(r#"(?P<key>\w+)\s*=\s*"(?P<value>\w+)""#).unwrap();
+}
+
+pub fn is_legacy_paint_line(line: &str) -> bool {
+    match BASIC_PAINT_RE.captures(line) {
+        Some(captures) => {
+            let ptype = captures.name("ptype").map_or("", |m| m.as_str());
+            let rgb = captures.name("rgb").map_or("", |m| m.as_str());
+            LEGACY_PAINT_TYPES.contains(&ptype) || rgb.starts_with("RGB(")
+        }
+        None => false,
+    }
+}
+
+pub fn is_legacy_text(string: &str) -> bool {
+    string.lines().any(|line| is_legacy_paint_line(line))
+}
+
+fn characteristics_text(pairs: &[(String, String)]) -> String {
+    let items: Vec<String> = pairs
+        .iter()
+        .map(|(key, value)| format!("{}=\"{}\"", key, value))
+        .collect();
+    items.join(", ")
+}
+
+// The characteristics given in "text" with those that are missing (or not
+// understood) replaced by C::legacy_assumptions() and a note of each one
+fn upgraded_characteristics<C: CharacteristicsInterface>(
+    name: &str,
+    text: &str,
+    assumptions: &mut Vec<String>,
+) -> Result<C, PaintError<C>> {
+    let legacy_assumptions = C::legacy_assumptions();
+    if legacy_assumptions.len() == 0 {
+        return C::from_str(text);
+    }
+    let given: Vec<(String, String)> = CHARACTERISTIC_RE
+        .captures_iter(text)
+        .map(|captures| (captures["key"].to_string(), captures["value"].to_string()))
+        .collect();
+    let defaults: Vec<(String, String)> = legacy_assumptions
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+    let mut chosen = defaults.clone();
+    for (index, (key, assumed)) in defaults.iter().enumerate() {
+        match given.iter().find(|(given_key, _)| given_key == key) {
+            Some((_, value)) => {
+                // the defaults are all acceptable so only this value is on trial
+                let mut trial = defaults.clone();
+                trial[index].1 = value.clone();
+                if C::from_str(&characteristics_text(&trial)).is_ok() {
+                    chosen[index].1 = value.clone();
+                } else {
+                    assumptions.push(format!(
+                        "{}: {}=\"{}\" not understood (assumed \"{}\")",
+                        name, key, value, assumed
+                    ));
+                }
+            }
+            None => assumptions.push(format!(
+                "{}: no {} given (assumed \"{}\")",
+                name, key, assumed
+            )),
+        }
+    }
+    for (key, value) in given.iter() {
+        if !defaults.iter().any(|(default_key, _)| default_key == key) {
+            assumptions.push(format!("{}: {}=\"{}\" dropped", name, key, value));
+        }
+    }
+    C::from_str(&characteristics_text(&chosen))
+}
+
+// The characteristics assumed for paints whose source doesn't give any
+// (e.g. those imported from digital swatch libraries)
+pub fn assumed_characteristics<C: CharacteristicsInterface>() -> Result<C, PaintError<C>> {
+    let defaults: Vec<(String, String)> = C::legacy_assumptions()
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+    C::from_str(&characteristics_text(&defaults))
+}
+
+pub fn upgrade_paint_line<C: CharacteristicsInterface>(
+    line: &str,
+    assumptions: &mut Vec<String>,
+) -> Result<BasicPaintSpec<C>, PaintError<C>> {
+    let captures =
+        BASIC_PAINT_RE
+            .captures(line)
+            .ok_or(PaintError::from(PaintErrorType::MalformedText(
+                line.to_string(),
+            )))?;
+    let name = captures.name("name").map_or("", |m| m.as_str());
+    let c_text = captures.name("characteristics").map_or("", |m| m.as_str());
+    let characteristics = upgraded_characteristics::<C>(name, c_text, assumptions)?;
+    BasicPaintSpec::<C>::from_captures_with(line, &captures, characteristics)
+}
+
+// The text rewritten in the current format (lines other than legacy paint
+// lines are left as they are) and the assumptions that were made
+pub fn upgrade_legacy_text<C: CharacteristicsInterface>(
+    string: &str,
+) -> Result<(String, Vec<String>), PaintError<C>> {
+    let mut text = String::new();
+    let mut assumptions: Vec<String> = Vec::new();
+    for line in string.lines() {
+        if is_legacy_paint_line(line) {
+            let spec = upgrade_paint_line::<C>(line, &mut assumptions)?;
+            text += &format!("{}\n", spec);
+        } else {
+            text += &format!("{}\n", line);
+        }
+    }
+    Ok((text, assumptions))
+}
+
+// e.g. "paints/tamiya" becomes "paints/tamiya.upgraded"
+pub fn upgraded_file_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".upgraded");
+    path.with_file_name(file_name)
+}
+
+impl<C, CID> PaintCollnSpec<C, CID>
+where
+    C: CharacteristicsInterface,
+    CID: CollnIdInterface,
+{
+    // Also returns the assumptions made (e.g. for missing characteristics)
+    pub fn from_legacy_str(
+        string: &str,
+    ) -> Result<(PaintCollnSpec<C, CID>, Vec<String>), PaintError<C>> {
+        let (text, assumptions) = upgrade_legacy_text::<C>(string)?;
+        let (spec, _) = PaintCollnSpec::<C, CID>::from_str_checked(&text, PaintTypeCheck::Lenient)?;
+        Ok((spec, assumptions))
+    }
+
+    pub fn from_legacy_file(
+        path: &Path,
+    ) -> Result<(PaintCollnSpec<C, CID>, Vec<String>), PaintError<C>> {
+        let mut file = File::open(path)?;
+        let mut string = String::new();
+        file.read_to_string(&mut string)?;
+        PaintCollnSpec::<C, CID>::from_legacy_str(&string)
+    }
+
+    // Write the upgraded file alongside the original returning its path
+    pub fn write_upgraded_file(&self, legacy_path: &Path) -> Result<PathBuf, PaintError<C>> {
+        let path = upgraded_file_path(legacy_path);
+        fs::write(&path, self.to_string())?;
+        Ok(path)
+    }
+}
+
+pub fn is_legacy_file(path: &Path) -> bool {
+    match fs::read_to_string(path) {
+        Ok(string) => is_legacy_text(&string),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::art_paint::*;
+    use crate::characteristics::*;
+    use crate::model_paint::*;
+    use crate::series_paint::*;
+
+    const LEGACY_MODEL_TEXT: &str = "Manufacturer: Tamiya
+Series: Flat Acrylic
+NamedColour(name=\"XF 1: Flat Black *\", rgb=RGB(0x2D00, 0x2B00, 0x3000), transparency=\"O\")
+NamedColour(name=\"XF 2: Flat White *\", rgb=RGB(0xFE00, 0xFE00, 0xFE00), transparency=\"O\", finish=\"F\")
+";
+
+    const LEGACY_ART_TEXT: &str = "Manufacturer: Imaginary
+Series: Old Oils
+NamedColour(name=\"Black\", rgb=RGB(0x2D00, 0x2B00, 0x3000), transparency=\"O\", permanence=\"C\")
+";
+
+    #[test]
+    fn legacy_model_paints_upgraded() {
+        assert!(is_legacy_text(LEGACY_MODEL_TEXT));
+        let (spec, assumptions) =
+            PaintCollnSpec::<ModelPaintCharacteristics, PaintSeriesId>::from_legacy_str(
+                LEGACY_MODEL_TEXT,
+            )
+            .unwrap();
+        assert_eq!(spec.paint_specs.len(), 2);
+        assert_eq!(spec.paint_specs[0].characteristics.finish, Finish::Gloss);
+        assert_eq!(spec.paint_specs[1].characteristics.finish, Finish::Flat);
+        assert!(assumptions
+            .contains(&"XF 1: Flat Black *: no finish given (assumed \"G\")".to_string()));
+        assert!(!is_legacy_text(&spec.to_string()));
+    }
+
+    #[test]
+    fn legacy_art_paints_upgraded() {
+        let (spec, assumptions) =
+            PaintCollnSpec::<ArtPaintCharacteristics, PaintSeriesId>::from_legacy_str(
+                LEGACY_ART_TEXT,
+            )
+            .unwrap();
+        assert_eq!(
+            spec.paint_specs[0].characteristics.permanence,
+            Permanence::Permanent
+        );
+        assert_eq!(
+            assumptions,
+            vec!["Black: permanence=\"C\" not understood (assumed \"A\")".to_string()]
+        );
+        assert_eq!(
+            upgraded_file_path(Path::new("/a/old_oils")),
+            PathBuf::from("/a/old_oils.upgraded")
+        );
+    }
+}
@@ -0,0 +1,337 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+// Loading big collection files without freezing the GUI.  The file is
+// read in a background thread and (as the specification's collection id
+// is reference counted and so can't be sent between threads) its lines
+// are then parsed a batch at a time in idle callbacks on the main thread.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Read};
+use std::marker::PhantomData;
+use std::path::Path;
+use std::rc::Rc;
+use std::thread;
+
+use pw_gix::glib;
+
+use crate::basic_paint::*;
+use crate::error::*;
+
+use super::formats::CollnFileFormat;
+use super::inventory::{is_inventory_line, parse_inventory_line, Inventory};
+use super::*;
+
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+// The number of lines parsed per idle callback
+const PARSE_BATCH_SIZE: usize = 200;
+// The part of the progress attributed to reading (the rest is parsing)
+const READ_PROGRESS_SHARE: f64 = 0.2;
+
+// Text format collection specifications parsed a line at a time
+pub struct TextCollnParser<C, CID>
+where
+    C: CharacteristicsInterface,
+    CID: CollnIdInterface,
+{
+    check: PaintTypeCheck,
+    tolerant: bool,
+    header_lines: Vec<String>,
+    colln_name: String,
+    colln_owner: String,
+    in_notes: bool,
+    notes_lines: Vec<String>,
+    paint_specs: Vec<BasicPaintSpec<C>>,
+    inventories: BTreeMap<String, Inventory>,
+    unknown_lines: Vec<String>,
+    warnings: Vec<String>,
+    phantom: PhantomData<CID>,
+}
+
+impl<C, CID> TextCollnParser<C, CID>
+where
+    C: CharacteristicsInterface,
+    CID: CollnIdInterface,
+{
+    // If "tolerant" lines that can't be parsed are preserved (with a
+    // warning) rather than causing the parse to fail
+    pub fn new(check: PaintTypeCheck, tolerant: bool) -> TextCollnParser<C, CID> {
+        TextCollnParser::<C, CID> {
+            check,
+            tolerant,
+            header_lines: vec![],
+            colln_name: String::new(),
+            colln_owner: String::new(),
+            in_notes: true,
+            notes_lines: vec![],
+            paint_specs: vec![],
+            inventories: BTreeMap::new(),
+            unknown_lines: vec![],
+            warnings: vec![],
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn parse_line(&mut self, line: &str) -> Result<(), PaintError<C>> {
+        // The collection's name and owner (in either order) come first
+        if self.header_lines.len() < 2 {
+            // NB: a bad header line still counts (for validation's sake)
+            self.header_lines.push(line.to_string());
+            if let Some(tail) = line.strip_prefix(&CID::colln_name_label()) {
+                self.colln_name = tail.trim().to_string();
+            } else if let Some(tail) = line.strip_prefix(&CID::colln_owner_label()) {
+                self.colln_owner = tail.trim().to_string();
+            } else {
+                return Err(PaintErrorType::MalformedText(line.to_string()).into());
+            }
+            return Ok(());
+        }
+        // Optional (possibly multi-line) notes precede the paints
+        if self.in_notes {
+            if let Some(tail) = line.strip_prefix(COLLN_NOTES_LABEL) {
+                let note = tail.strip_prefix(' ').unwrap_or(tail).trim_end();
+                self.notes_lines.push(note.to_string());
+                return Ok(());
+            }
+            self.in_notes = false;
+        }
+        if is_inventory_line(line) {
+            match parse_inventory_line(line) {
+                Ok((name, inventory)) => {
+                    self.inventories.insert(name, inventory);
+                }
+                Err(msg) => {
+                    if self.tolerant {
+                        self.warnings
+                            .push(format!("{}: not understood (preserved)", msg));
+                        self.unknown_lines.push(line.to_string());
+                    } else {
+                        return Err(PaintErrorType::MalformedText(msg).into());
+                    }
+                }
+            }
+            return Ok(());
+        }
+        let spec = match BasicPaintSpec::<C>::from_str_checked(line, self.check, &mut self.warnings)
+        {
+            Ok(spec) => spec,
+            Err(err) => {
+                if self.tolerant {
+                    if line.trim().len() > 0 {
+                        self.warnings
+                            .push(format!("{}: not understood (preserved)", line));
+                        self.unknown_lines.push(line.to_string());
+                    }
+                    return Ok(());
+                } else {
+                    return Err(err);
+                }
+            }
+        };
+        match self
+            .paint_specs
+            .binary_search_by_key(&spec.name, |bps| bps.name.clone())
+        {
+            Ok(_) => Err(PaintErrorType::AlreadyExists(spec.name).into()),
+            Err(index) => {
+                self.paint_specs.insert(index, spec);
+                Ok(())
+            }
+        }
+    }
+
+    // The paints understood so far (in name order)
+    pub fn paint_specs(&self) -> &[BasicPaintSpec<C>] {
+        &self.paint_specs
+    }
+
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    pub fn finish(self) -> Result<(PaintCollnSpec<C, CID>, Vec<String>), PaintError<C>> {
+        if self.header_lines.len() < 2 || self.colln_name.len() == 0 || self.colln_owner.len() == 0
+        {
+            return Err(PaintErrorType::MalformedText(self.header_lines.join("\n")).into());
+        }
+        let mut warnings = self.warnings;
+        let mut psc = PaintCollnSpec::<C, CID> {
+            colln_id: Rc::new(CID::new(&self.colln_name, &self.colln_owner)),
+            colln_notes: self.notes_lines.join("\n"),
+            paint_specs: self.paint_specs,
+            inventories: self.inventories,
+            unknown_lines: self.unknown_lines,
+        };
+        for name in psc.prune_inventories().iter() {
+            warnings.push(format!("{}: inventory for unknown paint (dropped)", name));
+        }
+        Ok((psc, warnings))
+    }
+}
+
+// Read the whole file passing the fraction read so far to "progress"
+pub fn read_with_progress<F: Fn(f64)>(path: &Path, progress: F) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len().max(1);
+    let mut bytes: Vec<u8> = Vec::new();
+    let mut buffer = vec![0_u8; READ_CHUNK_SIZE];
+    loop {
+        let n = file.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        bytes.extend_from_slice(&buffer[..n]);
+        progress((bytes.len() as f64 / size as f64).min(1.0));
+    }
+    String::from_utf8(bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+enum ReadMessage {
+    Progress(f64),
+    Done(io::Result<String>),
+}
+
+pub type CollnLoadResult<C, CID> =
+    Result<(PaintCollnSpec<C, CID>, Vec<String>, CollnFileFormat), PaintError<C>>;
+
+// As for PaintCollnSpec::from_file_any_format() but returning immediately
+// and passing the outcome to "done" when the load is finished (and the
+// fraction of the work done so far to "progress" in the meantime).
+// NB: must be called from the GTK main thread.
+pub fn load_colln_spec_async<C, CID, P, D>(
+    path: &Path,
+    check: PaintTypeCheck,
+    tolerant: bool,
+    progress: P,
+    done: D,
+) where
+    C: CharacteristicsInterface + 'static,
+    CID: CollnIdInterface + 'static,
+    P: Fn(f64) + 'static,
+    D: FnOnce(CollnLoadResult<C, CID>) + 'static,
+{
+    let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+    let path = path.to_path_buf();
+    thread::spawn(move || {
+        let result = read_with_progress(&path, |fraction| {
+            let _ = sender.send(ReadMessage::Progress(fraction));
+        });
+        let _ = sender.send(ReadMessage::Done(result));
+    });
+    let progress: Rc<dyn Fn(f64)> = Rc::new(progress);
+    let mut o_done = Some(done);
+    receiver.attach(None, move |message| match message {
+        ReadMessage::Progress(fraction) => {
+            progress(fraction * READ_PROGRESS_SHARE);
+            glib::Continue(true)
+        }
+        ReadMessage::Done(result) => {
+            if let Some(done) = o_done.take() {
+                match result {
+                    Ok(text) => parse_in_stages(text, check, tolerant, progress.clone(), done),
+                    Err(err) => done(Err(err.into())),
+                }
+            }
+            glib::Continue(false)
+        }
+    });
+}
+
+fn parse_in_stages<C, CID, D>(
+    text: String,
+    check: PaintTypeCheck,
+    tolerant: bool,
+    progress: Rc<dyn Fn(f64)>,
+    done: D,
+) where
+    C: CharacteristicsInterface + 'static,
+    CID: CollnIdInterface + 'static,
+    D: FnOnce(CollnLoadResult<C, CID>) + 'static,
+{
+    let format = CollnFileFormat::detect(&text);
+    if format != CollnFileFormat::Text {
+        // these are rarely big enough to matter
+        let result = PaintCollnSpec::<C, CID>::from_str_any_format(&text, check, tolerant);
+        progress(1.0);
+        done(result);
+        return;
+    }
+    let mut o_parser = Some(TextCollnParser::<C, CID>::new(check, tolerant));
+    let mut o_done = Some(done);
+    let mut offset: usize = 0;
+    glib::idle_add_local(move || {
+        let parser = match o_parser.as_mut() {
+            Some(parser) => parser,
+            None => return glib::Continue(false),
+        };
+        let mut n_lines = 0;
+        while offset < text.len() && n_lines < PARSE_BATCH_SIZE {
+            let end = text[offset..]
+                .find('\n')
+                .map_or(text.len(), |index| offset + index);
+            let line = &text[offset..end];
+            offset = (end + 1).min(text.len());
+            n_lines += 1;
+            if let Err(err) = parser.parse_line(line.strip_suffix('\r').unwrap_or(line)) {
+                o_parser = None;
+                if let Some(done) = o_done.take() {
+                    done(Err(err));
+                }
+                return glib::Continue(false);
+            }
+        }
+        if offset < text.len() {
+            let parsed = offset as f64 / text.len() as f64;
+            progress(READ_PROGRESS_SHARE + (1.0 - READ_PROGRESS_SHARE) * parsed);
+            return glib::Continue(true);
+        }
+        progress(1.0);
+        if let (Some(parser), Some(done)) = (o_parser.take(), o_done.take()) {
+            done(
+                parser
+                    .finish()
+                    .map(|(spec, warnings)| (spec, warnings, format)),
+            );
+        }
+        glib::Continue(false)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_paint::*;
+    use crate::series_paint::*;
+
+    #[test]
+    fn staged_reading_and_parsing() {
+        let text = "Manufacturer: Tamiya\nSeries: Flat Acrylic\nNotes: thinned\n\
+            ModelPaint(name=\"XF 1: Flat Black *\", rgb=#2D2B30, transparency=\"O\", finish=\"F\", metallic=\"NM\", fluorescence=\"NF\", notes=\"\")\r\n";
+        let path = std::env::temp_dir().join(format!("epaint_loader_test_{}", std::process::id()));
+        std::fs::write(&path, text).unwrap();
+        let fractions = std::cell::RefCell::new(vec![]);
+        let read = read_with_progress(&path, |fraction| fractions.borrow_mut().push(fraction));
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(read.unwrap(), text);
+        assert_eq!(fractions.borrow().last(), Some(&1.0));
+
+        let mut parser = TextCollnParser::<ModelPaintCharacteristics, PaintSeriesId>::new(
+            PaintTypeCheck::Lenient,
+            false,
+        );
+        for line in text.lines() {
+            parser.parse_line(line).unwrap();
+        }
+        let (spec, warnings) = parser.finish().unwrap();
+        assert_eq!(spec.paint_specs.len(), 1);
+        assert_eq!(spec.colln_notes, "thinned");
+        assert_eq!(warnings.len(), 0);
+
+        let mut parser = TextCollnParser::<ModelPaintCharacteristics, PaintSeriesId>::new(
+            PaintTypeCheck::Lenient,
+            false,
+        );
+        parser.parse_line("Manufacturer: Tamiya").unwrap();
+        assert!(parser.finish().is_err());
+    }
+}
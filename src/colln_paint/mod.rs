@@ -2,6 +2,7 @@
 
 use std::cell::RefCell;
 use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use std::fmt;
 use std::fmt::Debug;
 use std::fs::File;
@@ -14,17 +15,30 @@ use std::str::FromStr;
 
 use pw_gix::{
     gtk::{self, prelude::*},
+    recollections::{recall, remember},
     wrapper::*,
 };
 
+pub mod backup;
 pub mod binder;
 pub mod collection;
 pub mod display;
+pub mod duplicates;
 pub mod editor;
+pub mod formats;
+pub mod inventory;
+pub mod legacy;
+pub mod loader;
+pub mod owners;
+pub mod validation;
 
 use crate::basic_paint::*;
 use crate::colour::*;
 use crate::error::*;
+
+use self::formats::CollnFileFormat;
+use self::inventory::Inventory;
+use self::loader::TextCollnParser;
 pub use crate::struct_traits::SimpleCreation;
 
 pub trait CollnIdInterface:
@@ -37,6 +51,11 @@ pub trait CollnIdInterface:
     fn paint_select_label() -> String;
     fn paint_select_tooltip_text() -> String;
 
+    // What the collection's paints are called e.g. in a wheel's legend
+    fn paint_kind_label() -> String {
+        "Paint".to_string()
+    }
+
     fn recollection_name_for(item_name: &str) -> String;
 
     fn colln_load_image(size: i32) -> gtk::Image;
@@ -55,8 +74,18 @@ pub trait CollnIdInterface:
     fn rc_new(colln_name: &str, colln_owner: &str) -> Rc<Self> {
         Rc::new(Self::new(colln_name, colln_owner))
     }
+
+    // A copy of this id with the owner's name replaced by its canonical form
+    fn canonicalized(&self) -> Self {
+        Self::new(
+            &self.colln_name(),
+            &owners::canonical_owner_name(&self.colln_owner()),
+        )
+    }
 }
 
+pub const COLLN_NOTES_LABEL: &str = "Notes:";
+
 #[derive(PWO, Wrapper)]
 pub struct CollnIdEntryData<CID>
 where
@@ -65,6 +94,7 @@ where
     grid: gtk::Grid,
     colln_name_entry: gtk::Entry,
     colln_owner_entry: gtk::Entry,
+    colln_notes_view: gtk::TextView,
     changed_callbacks: RefCell<Vec<Box<dyn Fn()>>>,
     phantom_data: PhantomData<CID>,
 }
@@ -80,6 +110,7 @@ where
             grid: gtk::Grid::new(),
             colln_owner_entry: gtk::Entry::new(),
             colln_name_entry: gtk::Entry::new(),
+            colln_notes_view: gtk::TextView::new(),
             changed_callbacks: RefCell::new(Vec::new()),
             phantom_data: PhantomData,
         });
@@ -106,6 +137,23 @@ where
             1,
         );
 
+        let label = gtk::Label::new(Some(COLLN_NOTES_LABEL));
+        label.set_halign(gtk::Align::End);
+        label.set_valign(gtk::Align::Start);
+        psie.grid.attach(&label, 0, 2, 1, 1);
+        let adj: Option<&gtk::Adjustment> = None;
+        let scrolled_window = gtk::ScrolledWindow::new(adj, adj);
+        scrolled_window.set_hexpand(true);
+        scrolled_window.set_min_content_height(48);
+        scrolled_window.add(&psie.colln_notes_view);
+        psie.grid.attach_next_to(
+            &scrolled_window,
+            Some(&label),
+            gtk::PositionType::Right,
+            1,
+            1,
+        );
+
         let psie_c = psie.clone();
         psie.colln_name_entry
             .connect_changed(move |_| psie_c.inform_changed());
@@ -114,6 +162,11 @@ where
         psie.colln_owner_entry
             .connect_changed(move |_| psie_c.inform_changed());
 
+        if let Some(buffer) = psie.colln_notes_view.get_buffer() {
+            let psie_c = psie.clone();
+            buffer.connect_changed(move |_| psie_c.inform_changed());
+        }
+
         psie
     }
 }
@@ -143,6 +196,22 @@ where
         }
     }
 
+    pub fn get_colln_notes(&self) -> String {
+        if let Some(buffer) = self.colln_notes_view.get_buffer() {
+            let (start, end) = buffer.get_bounds();
+            if let Some(text) = buffer.get_text(&start, &end, false) {
+                return text.trim_end().to_string();
+            }
+        };
+        String::new()
+    }
+
+    pub fn set_colln_notes(&self, notes: &str) {
+        if let Some(buffer) = self.colln_notes_view.get_buffer() {
+            buffer.set_text(notes);
+        }
+    }
+
     pub fn connect_changed<F: 'static + Fn()>(&self, callback: F) {
         self.changed_callbacks.borrow_mut().push(Box::new(callback));
     }
@@ -163,6 +232,7 @@ where
 {
     colln_id: Rc<CID>,
     paint: BasicPaint<C>,
+    inventory: RefCell<Option<Inventory>>,
 }
 
 impl<C, CID> PartialEq for CollnPaintCore<C, CID>
@@ -220,6 +290,31 @@ where
     }
 }
 
+impl<C, CID> CollnPaintCore<C, CID>
+where
+    C: CharacteristicsInterface,
+    CID: CollnIdInterface,
+{
+    // Densities aren't part of the collection files so are kept as
+    // recollections (e.g. measured by the user)
+    fn density_recollection_name(&self) -> String {
+        CID::recollection_name_for(&format!(
+            "density::{}::{}::{}",
+            self.colln_id.colln_owner(),
+            self.colln_id.colln_name(),
+            self.paint.name()
+        ))
+    }
+
+    pub fn set_density(&self, o_density: Option<f64>) {
+        let text = match o_density {
+            Some(density) if density > 0.0 => density.to_string(),
+            _ => String::new(),
+        };
+        remember(&self.density_recollection_name(), &text);
+    }
+}
+
 // COLLECTION PAINT
 pub type CollnPaint<C, CID> = Rc<CollnPaintCore<C, CID>>;
 
@@ -257,6 +352,12 @@ where
     fn characteristics(&self) -> C {
         self.paint.characteristics()
     }
+
+    fn density(&self) -> Option<f64> {
+        recall(&self.density_recollection_name())
+            .and_then(|text| f64::from_str(&text).ok())
+            .filter(|density| *density > 0.0)
+    }
 }
 
 pub trait CollnPaintInterface<C, CID>: BasicPaintInterface<C>
@@ -277,6 +378,7 @@ where
         Rc::new(CollnPaintCore::<C, CID> {
             colln_id: cid.clone(),
             paint: paint.clone(),
+            inventory: RefCell::new(None),
         })
     }
 
@@ -293,7 +395,12 @@ where
     CID: CollnIdInterface,
 {
     pub colln_id: Rc<CID>,
+    pub colln_notes: String,
     pub paint_specs: Vec<BasicPaintSpec<C>>, // sorted
+    // Keyed by paint name (see inventory.rs)
+    pub inventories: BTreeMap<String, Inventory>,
+    // Lines not understood by a tolerant load (re-emitted verbatim)
+    pub unknown_lines: Vec<String>,
 }
 
 impl<C, CID> PaintCollnSpec<C, CID>
@@ -308,6 +415,49 @@ where
         PaintCollnSpec::<C, CID>::from_str(string.as_str())
     }
 
+    // As for from_file() but also returning any warnings about the content
+    pub fn from_file_checked(
+        path: &Path,
+        check: PaintTypeCheck,
+    ) -> Result<(PaintCollnSpec<C, CID>, Vec<String>), PaintError<C>> {
+        let mut file = File::open(path)?;
+        let mut string = String::new();
+        file.read_to_string(&mut string)?;
+        PaintCollnSpec::<C, CID>::from_str_checked(string.as_str(), check)
+    }
+
+    // As for from_file_checked() but preserving lines that can't be parsed
+    pub fn from_file_tolerant(
+        path: &Path,
+        check: PaintTypeCheck,
+    ) -> Result<(PaintCollnSpec<C, CID>, Vec<String>), PaintError<C>> {
+        let mut file = File::open(path)?;
+        let mut string = String::new();
+        file.read_to_string(&mut string)?;
+        PaintCollnSpec::<C, CID>::from_str_tolerant(string.as_str(), check)
+    }
+
+    // As for from_file_checked() (or from_file_tolerant() if "tolerant")
+    // but accepting any supported format and also returning the format
+    pub fn from_file_any_format(
+        path: &Path,
+        check: PaintTypeCheck,
+        tolerant: bool,
+    ) -> Result<(PaintCollnSpec<C, CID>, Vec<String>, CollnFileFormat), PaintError<C>> {
+        let mut file = File::open(path)?;
+        let mut string = String::new();
+        file.read_to_string(&mut string)?;
+        PaintCollnSpec::<C, CID>::from_str_any_format(string.as_str(), check, tolerant)
+    }
+
+    pub fn tooltip_text(&self) -> String {
+        if self.colln_notes.len() > 0 {
+            format!("{}\n\n{}", self.colln_id.tooltip_text(), self.colln_notes)
+        } else {
+            self.colln_id.tooltip_text()
+        }
+    }
+
     pub fn get_index_for_name(&self, name: &str) -> Option<usize> {
         match self
             .paint_specs
@@ -319,6 +469,55 @@ where
     }
 }
 
+impl<C, CID> PaintCollnSpec<C, CID>
+where
+    C: CharacteristicsInterface,
+    CID: CollnIdInterface,
+{
+    pub fn from_str_checked(
+        string: &str,
+        check: PaintTypeCheck,
+    ) -> Result<(PaintCollnSpec<C, CID>, Vec<String>), PaintError<C>> {
+        PaintCollnSpec::<C, CID>::parse(string, check, false)
+    }
+
+    // Paint lines that can't be parsed (e.g. future fields or foreign paint
+    // types) are kept in "unknown_lines" and reported as warnings rather
+    // than causing the whole load to fail.
+    pub fn from_str_tolerant(
+        string: &str,
+        check: PaintTypeCheck,
+    ) -> Result<(PaintCollnSpec<C, CID>, Vec<String>), PaintError<C>> {
+        PaintCollnSpec::<C, CID>::parse(string, check, true)
+    }
+
+    pub fn from_str_any_format(
+        string: &str,
+        check: PaintTypeCheck,
+        tolerant: bool,
+    ) -> Result<(PaintCollnSpec<C, CID>, Vec<String>, CollnFileFormat), PaintError<C>> {
+        let format = CollnFileFormat::detect(string);
+        let (spec, warnings) = match format {
+            CollnFileFormat::Text => PaintCollnSpec::<C, CID>::parse(string, check, tolerant)?,
+            CollnFileFormat::Json => PaintCollnSpec::<C, CID>::from_json_str(string, tolerant)?,
+            CollnFileFormat::Csv => PaintCollnSpec::<C, CID>::from_csv_str(string, tolerant)?,
+        };
+        Ok((spec, warnings, format))
+    }
+
+    fn parse(
+        string: &str,
+        check: PaintTypeCheck,
+        tolerant: bool,
+    ) -> Result<(PaintCollnSpec<C, CID>, Vec<String>), PaintError<C>> {
+        let mut parser = TextCollnParser::<C, CID>::new(check, tolerant);
+        for line in string.lines() {
+            parser.parse_line(line)?;
+        }
+        parser.finish()
+    }
+}
+
 impl<C, CID> FromStr for PaintCollnSpec<C, CID>
 where
     C: CharacteristicsInterface,
@@ -327,42 +526,7 @@ where
     type Err = PaintError<C>;
 
     fn from_str(string: &str) -> Result<PaintCollnSpec<C, CID>, PaintError<C>> {
-        let mut lines = string.lines();
-        let mut colln_name = "";
-        let mut colln_owner = "";
-        for _ in 0..2 {
-            if let Some(line) = lines.next() {
-                if line.starts_with(&CID::colln_name_label()) {
-                    if let Some(tail) = line.get(CID::colln_name_label().len()..) {
-                        colln_name = tail.trim();
-                    }
-                } else if line.starts_with(&CID::colln_owner_label()) {
-                    if let Some(tail) = line.get(CID::colln_owner_label().len()..) {
-                        colln_owner = tail.trim();
-                    }
-                } else {
-                    return Err(PaintErrorType::MalformedText(line.to_string()).into());
-                }
-            } else {
-                return Err(PaintErrorType::MalformedText(string.to_string()).into());
-            }
-        }
-        if colln_name.len() == 0 || colln_owner.len() == 0 {
-            return Err(PaintErrorType::MalformedText(string.to_string()).into());
-        };
-        let colln_id = Rc::new(CID::new(colln_name, colln_owner));
-        let mut paint_specs: Vec<BasicPaintSpec<C>> = Vec::new();
-        for line in lines {
-            let spec = BasicPaintSpec::<C>::from_str(line)?;
-            match paint_specs.binary_search_by_key(&spec.name, |bps| bps.name.clone()) {
-                Ok(_) => return Err(PaintErrorType::AlreadyExists(spec.name).into()),
-                Err(index) => paint_specs.insert(index, spec),
-            }
-        }
-        let psc = PaintCollnSpec::<C, CID> {
-            colln_id,
-            paint_specs,
-        };
+        let (psc, _) = PaintCollnSpec::<C, CID>::from_str_checked(string, PaintTypeCheck::Lenient)?;
         Ok(psc)
     }
 }
@@ -385,14 +549,59 @@ where
             CID::colln_owner_label(),
             self.colln_id.colln_owner()
         )?;
+        for line in self.colln_notes.lines() {
+            write!(f, "{} {}\n", COLLN_NOTES_LABEL, line)?;
+        }
         for paint_spec in self.paint_specs.iter() {
             write!(f, "{}\n", paint_spec)?;
         }
+        for line in self.inventory_lines().iter() {
+            write!(f, "{}\n", line)?;
+        }
+        for line in self.unknown_lines.iter() {
+            write!(f, "{}\n", line)?;
+        }
         Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    //use super::*;
+    use super::*;
+    use crate::art_paint::*;
+    use crate::series_paint::*;
+
+    const TEXT: &str = "Manufacturer: Imaginary
+Series: Tolerant Series
+ArtPaint(name=\"Black\", rgb=RGB16(red=0x0000, green=0x0000, blue=0x0000), transparency=\"O\", permanence=\"A\", notes=\"\")
+FuturePaint(name=\"Mystery\", colour=\"unknown\")
+ArtPaint(name=\"White\", rgb=RGB16(red=0xFFFF, green=0xFFFF, blue=0xFFFF), transparency=\"O\", permanence=\"A\", notes=\"\")
+";
+
+    #[test]
+    fn tolerant_load_preserves_unknown_lines() {
+        assert!(PaintCollnSpec::<ArtPaintCharacteristics, PaintSeriesId>::from_str(TEXT).is_err());
+        let (spec, warnings) =
+            PaintCollnSpec::<ArtPaintCharacteristics, PaintSeriesId>::from_str_tolerant(
+                TEXT,
+                PaintTypeCheck::Lenient,
+            )
+            .unwrap();
+        assert_eq!(spec.paint_specs.len(), 2);
+        assert_eq!(
+            spec.unknown_lines,
+            vec!["FuturePaint(name=\"Mystery\", colour=\"unknown\")".to_string()]
+        );
+        assert_eq!(warnings.len(), 1);
+        let text = spec.to_string();
+        assert!(text.ends_with("FuturePaint(name=\"Mystery\", colour=\"unknown\")\n"));
+        let (reloaded, _) =
+            PaintCollnSpec::<ArtPaintCharacteristics, PaintSeriesId>::from_str_tolerant(
+                &text,
+                PaintTypeCheck::Lenient,
+            )
+            .unwrap();
+        assert_eq!(reloaded.unknown_lines, spec.unknown_lines);
+        assert_eq!(reloaded.paint_specs.len(), 2);
+    }
 }
@@ -0,0 +1,125 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+use pw_gix::recollections::{recall, remember};
+
+// Well known alternative names for the owners of paint collections
+pub const DEFAULT_OWNER_ALIASES: &[(&str, &str)] = &[
+    ("Citadel", "Games Workshop"),
+    ("Citadel Colour", "Games Workshop"),
+    ("GW", "Games Workshop"),
+    ("Acrylicos Vallejo", "Vallejo"),
+    ("Army Painter", "The Army Painter"),
+    ("Winsor and Newton", "Winsor & Newton"),
+    ("W&N", "Winsor & Newton"),
+    ("Daler Rowney", "Daler-Rowney"),
+];
+
+const OWNER_ALIASES_KEY: &str = "colln_owner_aliases";
+
+fn normalised_key(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+// User registered aliases are recorded as "alias=canonical" lines
+fn parse_owner_aliases(text: &str) -> Vec<(String, String)> {
+    text.lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(2, '=');
+            let alias = parts.next()?.trim();
+            let canonical = parts.next()?.trim();
+            if alias.len() > 0 && canonical.len() > 0 {
+                Some((alias.to_string(), canonical.to_string()))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+fn user_owner_aliases() -> Vec<(String, String)> {
+    match recall(OWNER_ALIASES_KEY) {
+        Some(text) => parse_owner_aliases(&text),
+        None => vec![],
+    }
+}
+
+// User registered aliases take precedence over the defaults
+pub fn owner_aliases() -> Vec<(String, String)> {
+    let mut aliases = user_owner_aliases();
+    for (alias, canonical) in DEFAULT_OWNER_ALIASES.iter() {
+        if !aliases
+            .iter()
+            .any(|(a, _)| normalised_key(a) == normalised_key(alias))
+        {
+            aliases.push((alias.to_string(), canonical.to_string()));
+        }
+    }
+    aliases
+}
+
+pub fn register_owner_alias(alias: &str, canonical: &str) {
+    let mut aliases: Vec<(String, String)> = user_owner_aliases()
+        .into_iter()
+        .filter(|(a, _)| normalised_key(a) != normalised_key(alias))
+        .collect();
+    aliases.push((alias.trim().to_string(), canonical.trim().to_string()));
+    let text: Vec<String> = aliases
+        .iter()
+        .map(|(alias, canonical)| format!("{}={}", alias, canonical))
+        .collect();
+    remember(OWNER_ALIASES_KEY, &text.join("\n"));
+}
+
+pub fn canonical_owner_name_using(owner: &str, aliases: &[(String, String)]) -> String {
+    let key = normalised_key(owner);
+    for (alias, canonical) in aliases.iter() {
+        if normalised_key(alias) == key || normalised_key(canonical) == key {
+            return canonical.to_string();
+        }
+    }
+    owner.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+pub fn canonical_owner_name(owner: &str) -> String {
+    canonical_owner_name_using(owner, &owner_aliases())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn owner_names_canonicalize() {
+        let aliases: Vec<(String, String)> = DEFAULT_OWNER_ALIASES
+            .iter()
+            .map(|(a, c)| (a.to_string(), c.to_string()))
+            .collect();
+        assert_eq!(
+            canonical_owner_name_using("citadel", &aliases),
+            "Games Workshop"
+        );
+        assert_eq!(
+            canonical_owner_name_using(" games  workshop ", &aliases),
+            "Games Workshop"
+        );
+        assert_eq!(
+            canonical_owner_name_using("Some  Owner", &aliases),
+            "Some Owner"
+        );
+    }
+
+    #[test]
+    fn owner_alias_text() {
+        let aliases = parse_owner_aliases("Foo = Bar\nbad line\n=Baz\nQux=Quux");
+        assert_eq!(
+            aliases,
+            vec![
+                ("Foo".to_string(), "Bar".to_string()),
+                ("Qux".to_string(), "Quux".to_string())
+            ]
+        );
+    }
+}
@@ -0,0 +1,193 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+// Checking a whole (hand edited) text collection file in one pass so that
+// all of its problems can be reported at once rather than one per load.
+
+use std::fs;
+use std::path::Path;
+
+use pw_gix::{
+    gtk::{self, prelude::*},
+    gtkx::dialog::*,
+    wrapper::*,
+};
+
+use crate::app_name;
+use crate::basic_paint::*;
+use crate::error::*;
+
+use super::formats::CollnFileFormat;
+use super::loader::TextCollnParser;
+use super::*;
+
+pub struct CollnValidation<C: CharacteristicsInterface> {
+    // The paints that were understood (in name order)
+    pub paint_specs: Vec<BasicPaintSpec<C>>,
+    // The line number (counting from 1) and what's wrong with it
+    pub diagnostics: Vec<(usize, PaintError<C>)>,
+    pub warnings: Vec<String>,
+}
+
+impl<C: CharacteristicsInterface> CollnValidation<C> {
+    pub fn is_valid(&self) -> bool {
+        self.diagnostics.len() == 0
+    }
+
+    // e.g. "Line 7: XF 2: already exists."
+    pub fn diagnostic_lines(&self) -> Vec<String> {
+        self.diagnostics
+            .iter()
+            .map(|(line_number, err)| format!("Line {}: {}", line_number, err.message()))
+            .collect()
+    }
+}
+
+impl<C, CID> PaintCollnSpec<C, CID>
+where
+    C: CharacteristicsInterface,
+    CID: CollnIdInterface,
+{
+    // Unlike from_str() parsing carries on past malformed lines.
+    // NB: only the text format is checked line by line (the others are
+    // written by programs) so any problem with them is put on line 1.
+    pub fn validate_str(string: &str, check: PaintTypeCheck) -> CollnValidation<C> {
+        if CollnFileFormat::detect(string) != CollnFileFormat::Text {
+            return match PaintCollnSpec::<C, CID>::from_str_any_format(string, check, false) {
+                Ok((spec, warnings, _)) => CollnValidation {
+                    paint_specs: spec.paint_specs,
+                    diagnostics: vec![],
+                    warnings,
+                },
+                Err(err) => CollnValidation {
+                    paint_specs: vec![],
+                    diagnostics: vec![(1, err)],
+                    warnings: vec![],
+                },
+            };
+        }
+        let mut parser = TextCollnParser::<C, CID>::new(check, false);
+        let mut diagnostics: Vec<(usize, PaintError<C>)> = vec![];
+        let mut n_lines = 0;
+        for (index, line) in string.lines().enumerate() {
+            if let Err(err) = parser.parse_line(line) {
+                diagnostics.push((index + 1, err));
+            }
+            n_lines = index + 1;
+        }
+        let paint_specs = parser.paint_specs().to_vec();
+        let mut warnings = parser.warnings().to_vec();
+        match parser.finish() {
+            Ok((_, all_warnings)) => warnings = all_warnings,
+            // the name and owner are missing or incomplete
+            Err(err) => {
+                let line_number = if n_lines < 2 { n_lines + 1 } else { 1 };
+                if !diagnostics.iter().any(|(n, _)| *n == line_number) {
+                    diagnostics.insert(0, (line_number, err));
+                }
+            }
+        }
+        CollnValidation {
+            paint_specs,
+            diagnostics,
+            warnings,
+        }
+    }
+
+    pub fn validate_file(
+        path: &Path,
+        check: PaintTypeCheck,
+    ) -> Result<CollnValidation<C>, PaintError<C>> {
+        let string = fs::read_to_string(path)?;
+        Ok(PaintCollnSpec::<C, CID>::validate_str(&string, check))
+    }
+}
+
+// Show all of the problems found in the file at "path" (with any warnings)
+pub fn show_validation_problems<W, C>(caller: &W, path: &Path, validation: &CollnValidation<C>)
+where
+    W: WidgetWrapper,
+    C: CharacteristicsInterface,
+{
+    let title = format!("{}: Collection File Problems", app_name());
+    let dialog = caller.new_dialog_with_buttons(
+        Some(&title),
+        gtk::DialogFlags::DESTROY_WITH_PARENT,
+        &[("Close", gtk::ResponseType::Close)],
+    );
+    let summary = format!(
+        "{:?}: {} problem{} ({} paint{} understood)",
+        path,
+        validation.diagnostics.len(),
+        if validation.diagnostics.len() == 1 {
+            ""
+        } else {
+            "s"
+        },
+        validation.paint_specs.len(),
+        if validation.paint_specs.len() == 1 {
+            ""
+        } else {
+            "s"
+        },
+    );
+    let mut text = validation.diagnostic_lines().join("\n");
+    if validation.warnings.len() > 0 {
+        text += &format!("\n\nWarnings:\n{}", validation.warnings.join("\n"));
+    }
+    let problems_view = gtk::TextView::new();
+    problems_view.set_editable(false);
+    problems_view.set_monospace(true);
+    if let Some(buffer) = problems_view.get_buffer() {
+        buffer.set_text(&text);
+    }
+    let adj: Option<&gtk::Adjustment> = None;
+    let scrolled_window = gtk::ScrolledWindow::new(adj, adj);
+    scrolled_window.set_min_content_height(200);
+    scrolled_window.set_min_content_width(500);
+    scrolled_window.add(&problems_view);
+    let content_area = dialog.get_content_area();
+    content_area.pack_start(&gtk::Label::new(Some(&summary)), false, false, 4);
+    content_area.pack_start(&scrolled_window, true, true, 0);
+    content_area.show_all();
+    dialog.run();
+    unsafe { dialog.destroy() };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_paint::*;
+    use crate::series_paint::*;
+
+    const HAND_EDITED_TEXT: &str = "Manufacturer: Tamiya
+Series: Flat Acrylic
+ModelPaint(name=\"XF 1: Flat Black *\", rgb=#2D2B30, transparency=\"O\", finish=\"F\", metallic=\"NM\", fluorescence=\"NF\", notes=\"\")
+ModelPaint(name=\"XF 2: Flat White *\", rgb=#FEFEFE, transparency=\"O\", finish=\"F\", metallic=\"NM\", fluorescence=\"NF\"
+ModelPaint(name=\"XF 3: Flat Yellow *\", rgb=#F7E318, transparency=\"O\", finish=\"F\", metallic=\"NM\", fluorescence=\"NF\", notes=\"\")
+ModelPaint(name=\"XF 1: Flat Black *\", rgb=#2D2B30, transparency=\"O\", finish=\"F\", metallic=\"NM\", fluorescence=\"NF\", notes=\"\")
+";
+
+    #[test]
+    fn all_problems_reported() {
+        let validation = PaintCollnSpec::<ModelPaintCharacteristics, PaintSeriesId>::validate_str(
+            HAND_EDITED_TEXT,
+            PaintTypeCheck::Lenient,
+        );
+        assert!(!validation.is_valid());
+        let line_numbers: Vec<usize> = validation.diagnostics.iter().map(|(n, _)| *n).collect();
+        assert_eq!(line_numbers, vec![4, 6]);
+        assert_eq!(
+            validation.diagnostics[1].1.error_code(),
+            PaintErrorCode::AlreadyExists
+        );
+        assert_eq!(validation.paint_specs.len(), 2);
+        assert!(validation.diagnostic_lines()[0].starts_with("Line 4: "));
+
+        let validation = PaintCollnSpec::<ModelPaintCharacteristics, PaintSeriesId>::validate_str(
+            "Series: Flat Acrylic\n",
+            PaintTypeCheck::Lenient,
+        );
+        let line_numbers: Vec<usize> = validation.diagnostics.iter().map(|(n, _)| *n).collect();
+        assert_eq!(line_numbers, vec![2]);
+    }
+}
@@ -0,0 +1,502 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+use std::fmt;
+use std::str::FromStr;
+
+use pw_gix::recollections::{recall, remember};
+
+use crate::colour::*;
+use crate::colour_mix::{linear_to_srgb, srgb_to_linear};
+
+// D65 reference white
+const XN: f64 = 0.95047;
+const YN: f64 = 1.0;
+const ZN: f64 = 1.08883;
+
+fn lab_f(t: f64) -> f64 {
+    const DELTA: f64 = 6.0 / 29.0;
+    if t > DELTA * DELTA * DELTA {
+        t.cbrt()
+    } else {
+        t / (3.0 * DELTA * DELTA) + 4.0 / 29.0
+    }
+}
+
+pub fn rgb_to_lab(rgb: &RGB) -> [f64; 3] {
+    let r = srgb_to_linear(rgb[CCI::Red]);
+    let g = srgb_to_linear(rgb[CCI::Green]);
+    let b = srgb_to_linear(rgb[CCI::Blue]);
+    let x = 0.4124 * r + 0.3576 * g + 0.1805 * b;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = 0.0193 * r + 0.1192 * g + 0.9505 * b;
+    let (fx, fy, fz) = (lab_f(x / XN), lab_f(y / YN), lab_f(z / ZN));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+fn lab_f_inverse(t: f64) -> f64 {
+    const DELTA: f64 = 6.0 / 29.0;
+    if t > DELTA {
+        t * t * t
+    } else {
+        3.0 * DELTA * DELTA * (t - 4.0 / 29.0)
+    }
+}
+
+// The inverse of rgb_to_lab() (clamped to the RGB gamut)
+pub fn lab_to_rgb(lab: &[f64; 3]) -> RGB {
+    let fy = (lab[0] + 16.0) / 116.0;
+    let fx = fy + lab[1] / 500.0;
+    let fz = fy - lab[2] / 200.0;
+    let (x, y, z) = (
+        XN * lab_f_inverse(fx),
+        YN * lab_f_inverse(fy),
+        ZN * lab_f_inverse(fz),
+    );
+    let r = 3.2406 * x - 1.5372 * y - 0.4986 * z;
+    let g = -0.9689 * x + 1.8758 * y + 0.0415 * z;
+    let b = 0.0557 * x - 0.2040 * y + 1.0570 * z;
+    let unit = |linear: f64| linear_to_srgb(linear.max(0.0)).max(0.0).min(1.0);
+    RGB::from([unit(r), unit(g), unit(b)])
+}
+
+// CIE76 colour difference
+pub fn delta_e(rgb1: &RGB, rgb2: &RGB) -> f64 {
+    let lab1 = rgb_to_lab(rgb1);
+    let lab2 = rgb_to_lab(rgb2);
+    lab1.iter()
+        .zip(lab2.iter())
+        .map(|(a, b)| (a - b) * (a - b))
+        .sum::<f64>()
+        .sqrt()
+}
+
+fn lab_chroma(lab: &[f64; 3]) -> f64 {
+    (lab[1] * lab[1] + lab[2] * lab[2]).sqrt()
+}
+
+// CIE94 colour difference (graphic arts weightings) measured from "lab1"
+// as the reference colour (so it isn't quite symmetric)
+pub fn delta_e_cie94_lab(lab1: &[f64; 3], lab2: &[f64; 3]) -> f64 {
+    const K1: f64 = 0.045;
+    const K2: f64 = 0.015;
+    let delta_l = lab1[0] - lab2[0];
+    let c1 = lab_chroma(lab1);
+    let c2 = lab_chroma(lab2);
+    let delta_c = c1 - c2;
+    let delta_a = lab1[1] - lab2[1];
+    let delta_b = lab1[2] - lab2[2];
+    let delta_h_sq = (delta_a * delta_a + delta_b * delta_b - delta_c * delta_c).max(0.0);
+    let s_c = 1.0 + K1 * c1;
+    let s_h = 1.0 + K2 * c1;
+    (delta_l * delta_l + (delta_c / s_c).powi(2) + delta_h_sq / (s_h * s_h)).sqrt()
+}
+
+// CIEDE2000 colour difference (with unit weighting factors)
+pub fn delta_e_ciede2000_lab(lab1: &[f64; 3], lab2: &[f64; 3]) -> f64 {
+    let pow7 = |x: f64| x.powi(7);
+    let c_bar = (lab_chroma(lab1) + lab_chroma(lab2)) / 2.0;
+    let g = 0.5 * (1.0 - (pow7(c_bar) / (pow7(c_bar) + pow7(25.0))).sqrt());
+    let a1 = (1.0 + g) * lab1[1];
+    let a2 = (1.0 + g) * lab2[1];
+    let c1 = (a1 * a1 + lab1[2] * lab1[2]).sqrt();
+    let c2 = (a2 * a2 + lab2[2] * lab2[2]).sqrt();
+    let hue = |b: f64, a: f64| {
+        if a == 0.0 && b == 0.0 {
+            0.0
+        } else {
+            let h = b.atan2(a).to_degrees();
+            if h < 0.0 {
+                h + 360.0
+            } else {
+                h
+            }
+        }
+    };
+    let h1 = hue(lab1[2], a1);
+    let h2 = hue(lab2[2], a2);
+
+    let delta_l = lab2[0] - lab1[0];
+    let delta_c = c2 - c1;
+    let delta_h = if c1 * c2 == 0.0 {
+        0.0
+    } else if (h2 - h1).abs() <= 180.0 {
+        h2 - h1
+    } else if h2 - h1 > 180.0 {
+        h2 - h1 - 360.0
+    } else {
+        h2 - h1 + 360.0
+    };
+    let delta_big_h = 2.0 * (c1 * c2).sqrt() * (delta_h / 2.0).to_radians().sin();
+
+    let l_bar = (lab1[0] + lab2[0]) / 2.0;
+    let c_bar = (c1 + c2) / 2.0;
+    let h_bar = if c1 * c2 == 0.0 {
+        h1 + h2
+    } else if (h1 - h2).abs() <= 180.0 {
+        (h1 + h2) / 2.0
+    } else if h1 + h2 < 360.0 {
+        (h1 + h2 + 360.0) / 2.0
+    } else {
+        (h1 + h2 - 360.0) / 2.0
+    };
+    let t = 1.0 - 0.17 * (h_bar - 30.0).to_radians().cos()
+        + 0.24 * (2.0 * h_bar).to_radians().cos()
+        + 0.32 * (3.0 * h_bar + 6.0).to_radians().cos()
+        - 0.20 * (4.0 * h_bar - 63.0).to_radians().cos();
+    let delta_theta = 30.0 * (-((h_bar - 275.0) / 25.0).powi(2)).exp();
+    let r_c = 2.0 * (pow7(c_bar) / (pow7(c_bar) + pow7(25.0))).sqrt();
+    let l_50_sq = (l_bar - 50.0).powi(2);
+    let s_l = 1.0 + 0.015 * l_50_sq / (20.0 + l_50_sq).sqrt();
+    let s_c = 1.0 + 0.045 * c_bar;
+    let s_h = 1.0 + 0.015 * c_bar * t;
+    let r_t = -(2.0 * delta_theta).to_radians().sin() * r_c;
+
+    let l_term = delta_l / s_l;
+    let c_term = delta_c / s_c;
+    let h_term = delta_big_h / s_h;
+    (l_term * l_term + c_term * c_term + h_term * h_term + r_t * c_term * h_term).sqrt()
+}
+
+// The user's choice of metric is shared by all of the matching UI
+pub const DELTA_E_METRIC_RECOLLECTION: &str = "colour_difference::delta_e_metric";
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DeltaEMetric {
+    CIE76,
+    CIE94,
+    CIEDE2000,
+}
+
+impl Default for DeltaEMetric {
+    fn default() -> DeltaEMetric {
+        DeltaEMetric::CIE76
+    }
+}
+
+static DELTA_E_METRIC_VALUES: &[DeltaEMetric] = &[
+    DeltaEMetric::CIE76,
+    DeltaEMetric::CIE94,
+    DeltaEMetric::CIEDE2000,
+];
+
+impl DeltaEMetric {
+    pub fn values() -> Vec<DeltaEMetric> {
+        DELTA_E_METRIC_VALUES.to_vec()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            DeltaEMetric::CIE76 => "cie76",
+            DeltaEMetric::CIE94 => "cie94",
+            DeltaEMetric::CIEDE2000 => "ciede2000",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match *self {
+            DeltaEMetric::CIE76 => "CIE76",
+            DeltaEMetric::CIE94 => "CIE94",
+            DeltaEMetric::CIEDE2000 => "CIEDE2000",
+        }
+    }
+
+    pub fn tooltip_text(&self) -> &'static str {
+        match *self {
+            DeltaEMetric::CIE76 => "Straight line distance in CIELAB space",
+            DeltaEMetric::CIE94 => "CIELAB distance weighted for chroma and hue differences",
+            DeltaEMetric::CIEDE2000 => "The most perceptually uniform (and expensive) measure",
+        }
+    }
+
+    pub fn delta_e(&self, rgb1: &RGB, rgb2: &RGB) -> f64 {
+        match *self {
+            DeltaEMetric::CIE76 => delta_e(rgb1, rgb2),
+            DeltaEMetric::CIE94 => delta_e_cie94_lab(&rgb_to_lab(rgb1), &rgb_to_lab(rgb2)),
+            DeltaEMetric::CIEDE2000 => delta_e_ciede2000_lab(&rgb_to_lab(rgb1), &rgb_to_lab(rgb2)),
+        }
+    }
+
+    pub fn recall() -> DeltaEMetric {
+        if let Some(text) = recall(DELTA_E_METRIC_RECOLLECTION) {
+            if let Ok(metric) = DeltaEMetric::from_str(&text) {
+                return metric;
+            }
+        };
+        DeltaEMetric::default()
+    }
+
+    pub fn remember(&self) {
+        remember(DELTA_E_METRIC_RECOLLECTION, self.name())
+    }
+}
+
+impl FromStr for DeltaEMetric {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<DeltaEMetric, String> {
+        for metric in DELTA_E_METRIC_VALUES.iter() {
+            if metric.name() == string {
+                return Ok(*metric);
+            }
+        }
+        Err(format!("{}: unknown delta E metric", string))
+    }
+}
+
+impl fmt::Display for DeltaEMetric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.label())
+    }
+}
+
+// The items within "max_delta_e" of "rgb" paired with their distance
+// from it (nearest first)
+pub fn nearest_by_delta_e<T, F>(
+    items: &[T],
+    rgb: &RGB,
+    max_delta_e: f64,
+    rgb_of: F,
+) -> Vec<(T, f64)>
+where
+    T: Clone,
+    F: Fn(&T) -> RGB,
+{
+    let mut nearest: Vec<(T, f64)> = items
+        .iter()
+        .map(|item| (item.clone(), delta_e(&rgb_of(item), rgb)))
+        .filter(|(_, distance)| *distance <= max_delta_e)
+        .collect();
+    nearest.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+    nearest
+}
+
+// The pairs of items within "max_delta_e" of each other (by "metric")
+// paired with their distance apart (closest first)
+pub fn close_pairs<T, F>(
+    items: &[T],
+    max_delta_e: f64,
+    metric: DeltaEMetric,
+    rgb_of: F,
+) -> Vec<(T, T, f64)>
+where
+    T: Clone,
+    F: Fn(&T) -> RGB,
+{
+    let rgbs: Vec<RGB> = items.iter().map(|item| rgb_of(item)).collect();
+    let mut pairs: Vec<(T, T, f64)> = vec![];
+    for i in 0..items.len() {
+        for j in i + 1..items.len() {
+            let distance = metric.delta_e(&rgbs[i], &rgbs[j]);
+            if distance <= max_delta_e {
+                pairs.push((items[i].clone(), items[j].clone(), distance));
+            }
+        }
+    }
+    pairs.sort_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(std::cmp::Ordering::Equal));
+    pairs
+}
+
+// Paints closer than this are probably the same paint entered twice
+pub const DEFAULT_DUPLICATE_DELTA_E: f64 = 1.0;
+
+// Roughly a "just noticeable difference"
+pub const DEFAULT_TARGET_TOLERANCE: f64 = 2.3;
+
+pub fn within_tolerance(target: &RGB, rgb: &RGB, tolerance: f64) -> bool {
+    delta_e(target, rgb) <= tolerance
+}
+
+// How much a set of sample colours vary about their mean
+#[derive(Debug, Clone, Copy)]
+pub struct ColourSpread {
+    pub mean: RGB,
+    pub count: usize,
+    pub rgb_std_dev: [f64; 3],
+    pub lab_std_dev: [f64; 3],
+    // root mean square colour difference from the mean
+    pub delta_e_std_dev: f64,
+}
+
+impl ColourSpread {
+    pub fn description(&self) -> String {
+        format!(
+            "ΔE ±{:.1} (R ±{:.3} G ±{:.3} B ±{:.3})",
+            self.delta_e_std_dev, self.rgb_std_dev[0], self.rgb_std_dev[1], self.rgb_std_dev[2]
+        )
+    }
+
+    pub fn tooltip_text(&self) -> String {
+        format!(
+            "Standard deviations over {} sampled pixels:\nL* ±{:.2}\na* ±{:.2}\nb* ±{:.2}",
+            self.count, self.lab_std_dev[0], self.lab_std_dev[1], self.lab_std_dev[2]
+        )
+    }
+}
+
+fn std_devs(values: &[[f64; 3]]) -> ([f64; 3], [f64; 3]) {
+    let n = values.len() as f64;
+    let mut mean = [0.0_f64; 3];
+    for value in values.iter() {
+        for i in 0..3 {
+            mean[i] += value[i] / n;
+        }
+    }
+    let mut std_dev = [0.0_f64; 3];
+    for value in values.iter() {
+        for i in 0..3 {
+            std_dev[i] += (value[i] - mean[i]).powi(2) / n;
+        }
+    }
+    for i in 0..3 {
+        std_dev[i] = std_dev[i].sqrt();
+    }
+    (mean, std_dev)
+}
+
+pub fn colour_spread(rgbs: &[RGB]) -> Option<ColourSpread> {
+    if rgbs.len() == 0 {
+        return None;
+    }
+    let rgb_values: Vec<[f64; 3]> = rgbs
+        .iter()
+        .map(|rgb| [rgb[CCI::Red], rgb[CCI::Green], rgb[CCI::Blue]])
+        .collect();
+    let (rgb_mean, rgb_std_dev) = std_devs(&rgb_values);
+    let lab_values: Vec<[f64; 3]> = rgbs.iter().map(|rgb| rgb_to_lab(rgb)).collect();
+    let (_, lab_std_dev) = std_devs(&lab_values);
+    Some(ColourSpread {
+        mean: rgb_mean.into(),
+        count: rgbs.len(),
+        rgb_std_dev,
+        lab_std_dev,
+        delta_e_std_dev: lab_std_dev.iter().map(|sd| sd * sd).sum::<f64>().sqrt(),
+    })
+}
+
+// A mixture can't be expected to match a target more closely than the
+// target's own samples agree with each other so widen the tolerance to suit.
+pub fn effective_tolerance(tolerance: f64, o_spread: Option<&ColourSpread>) -> f64 {
+    match o_spread {
+        Some(spread) => tolerance.hypot(spread.delta_e_std_dev),
+        None => tolerance,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lab_of_black_and_white() {
+        let black = rgb_to_lab(&RGB::BLACK);
+        assert!(black.iter().all(|v| v.abs() < 1.0e-6));
+        let white = rgb_to_lab(&RGB::WHITE);
+        assert!((white[0] - 100.0).abs() < 0.01);
+        assert!(white[1].abs() < 0.1 && white[2].abs() < 0.1);
+    }
+
+    #[test]
+    fn delta_e_is_a_distance() {
+        assert_eq!(delta_e(&RGB::RED, &RGB::RED), 0.0);
+        assert_eq!(
+            delta_e(&RGB::RED, &RGB::BLUE),
+            delta_e(&RGB::BLUE, &RGB::RED)
+        );
+        assert!((delta_e(&RGB::BLACK, &RGB::WHITE) - 100.0).abs() < 0.01);
+        assert!(delta_e(&RGB::RED, &(RGB::RED * 0.95)) < delta_e(&RGB::RED, &RGB::GREEN));
+    }
+
+    #[test]
+    fn nearest_items_first() {
+        let items = vec![RGB::GREEN, RGB::RED * 0.95, RGB::RED, RGB::BLUE];
+        let nearest = nearest_by_delta_e(&items, &RGB::RED, 10.0, |rgb| *rgb);
+        assert_eq!(nearest.len(), 2);
+        assert_eq!(nearest[0], (RGB::RED, 0.0));
+        assert_eq!(nearest[1].0, RGB::RED * 0.95);
+        assert!(nearest_by_delta_e(&items, &RGB::WHITE, 10.0, |rgb| *rgb).is_empty());
+    }
+
+    #[test]
+    fn close_pairs_closest_first() {
+        let items = vec![RGB::RED, RGB::GREEN, RGB::RED * 0.95, RGB::RED];
+        let pairs = close_pairs(&items, 10.0, DeltaEMetric::CIE76, |rgb| *rgb);
+        assert_eq!(pairs.len(), 3);
+        assert_eq!(
+            (pairs[0].0, pairs[0].1, pairs[0].2),
+            (RGB::RED, RGB::RED, 0.0)
+        );
+        assert!(pairs
+            .iter()
+            .all(|(a, b, _)| *a != RGB::GREEN && *b != RGB::GREEN));
+        assert_eq!(
+            close_pairs(&items, 0.0, DeltaEMetric::CIEDE2000, |rgb| *rgb).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn tolerance_check() {
+        assert!(within_tolerance(&RGB::RED, &RGB::RED, 0.0));
+        let nearly_red = RGB::from([0.99, 0.0, 0.0]);
+        assert!(within_tolerance(
+            &RGB::RED,
+            &nearly_red,
+            DEFAULT_TARGET_TOLERANCE
+        ));
+        assert!(!within_tolerance(
+            &RGB::RED,
+            &RGB::BLUE,
+            DEFAULT_TARGET_TOLERANCE
+        ));
+    }
+
+    #[test]
+    fn spread_of_samples() {
+        assert!(colour_spread(&[]).is_none());
+        let spread = colour_spread(&[RGB::RED, RGB::RED]).unwrap();
+        assert_eq!(spread.count, 2);
+        assert!(spread.delta_e_std_dev.abs() < 1.0e-6);
+        assert_eq!(effective_tolerance(2.0, Some(&spread)), 2.0);
+        let spread = colour_spread(&[RGB::BLACK, RGB::WHITE]).unwrap();
+        assert!((spread.rgb_std_dev[0] - 0.5).abs() < 1.0e-6);
+        assert!((spread.lab_std_dev[0] - 50.0).abs() < 0.01);
+        assert!((spread.mean[CCI::Green] - 0.5).abs() < 1.0e-6);
+        assert!(effective_tolerance(2.0, Some(&spread)) > 50.0);
+        assert_eq!(effective_tolerance(2.0, None), 2.0);
+    }
+
+    #[test]
+    fn ciede2000_reference_values() {
+        // From Sharma, Wu and Dalal's test data
+        let pairs = [
+            ([50.0, 2.6772, -79.7751], [50.0, 0.0, -82.7485], 2.0425),
+            ([50.0, 2.5, 0.0], [73.0, 25.0, -18.0], 27.1492),
+            ([50.0, -0.001, 2.49], [50.0, 0.0009, -2.49], 4.8045),
+        ];
+        for (lab1, lab2, expected) in pairs.iter() {
+            assert!((delta_e_ciede2000_lab(lab1, lab2) - expected).abs() < 1.0e-4);
+            assert!((delta_e_ciede2000_lab(lab2, lab1) - expected).abs() < 1.0e-4);
+        }
+    }
+
+    #[test]
+    fn delta_e_metrics() {
+        for metric in DeltaEMetric::values().iter() {
+            assert_eq!(DeltaEMetric::from_str(metric.name()), Ok(*metric));
+            assert!(metric.delta_e(&RGB::RED, &RGB::RED).abs() < 1.0e-9);
+            assert!(
+                metric.delta_e(&RGB::RED, &(RGB::RED * 0.95))
+                    < metric.delta_e(&RGB::RED, &RGB::GREEN)
+            );
+        }
+        // Greys differ only in lightness where CIE94 equals CIE76
+        let grey = RGB::from([0.5, 0.5, 0.5]);
+        assert!(
+            (DeltaEMetric::CIE94.delta_e(&RGB::BLACK, &grey)
+                - DeltaEMetric::CIE76.delta_e(&RGB::BLACK, &grey))
+            .abs()
+                < 1.0e-6
+        );
+        assert!(DeltaEMetric::from_str("cie2020").is_err());
+    }
+}
@@ -1,14 +1,18 @@
 // Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
 
 use std::cell::{Cell, RefCell};
+use std::path::PathBuf;
 use std::rc::Rc;
 
 use pw_gix::{
     cairo, gdk, gdk_pixbuf,
+    glib::StaticType,
     gtk::{self, prelude::*},
     gtkx::menu::*,
+    recollections::{recall, remember},
     wrapper::*,
 };
+use serde_derive::*;
 
 use normalised_angles::Degrees;
 
@@ -20,6 +24,10 @@ use colour_math_gtk::{
 
 use crate::basic_paint::*;
 use crate::colour::*;
+use crate::colour_difference::colour_spread;
+use crate::colour_names::*;
+use crate::screen_picker::*;
+use crate::struct_traits::SimpleCreation;
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 enum DeltaSize {
@@ -54,9 +62,244 @@ impl DeltaSize {
     }
 }
 
+const SAMPLE_FILE_RECOLLECTION: &str = "colour_editor::last_sample_file";
+
 struct Sample {
     pix_buf: gdk_pixbuf::Pixbuf,
     position: Point,
+    // None for samples pasted from the clipboard
+    o_path: Option<PathBuf>,
+}
+
+// Where a sample came from and where it was placed in the sample area
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SampleSource {
+    pub path: Option<PathBuf>,
+    pub x: f64,
+    pub y: f64,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl SampleSource {
+    pub fn description(&self) -> String {
+        let source = match self.path {
+            Some(ref path) => path.to_string_lossy().to_string(),
+            None => "clipboard".to_string(),
+        };
+        format!(
+            "{} ({}x{} at {:.0}, {:.0})",
+            source, self.width, self.height, self.x, self.y
+        )
+    }
+}
+
+// Mediator between a colour editor and a tool with a target colour
+// (e.g. a paint mixer) so that colours can be transferred between them.
+pub trait TargetColourExchange {
+    fn target_colour(&self) -> Option<Colour>;
+    fn accept_target_colour(&self, colour: &Colour);
+}
+
+const RGB_BITS_RECOLLECTION: &str = "colour_editor::rgb_bits";
+
+fn max_component(bits: u32) -> f64 {
+    ((1u32 << bits) - 1) as f64
+}
+
+// The (clamped) components of "rgb" as integers of "bits" bits
+pub fn rgb_to_components(rgb: &RGB, bits: u32) -> [u32; 3] {
+    let max = max_component(bits);
+    let mut components = [0; 3];
+    for (component, cci) in components
+        .iter_mut()
+        .zip([CCI::Red, CCI::Green, CCI::Blue].iter())
+    {
+        *component = (rgb[*cci].max(0.0).min(1.0) * max).round() as u32;
+    }
+    components
+}
+
+pub fn components_to_rgb(components: [u32; 3], bits: u32) -> RGB {
+    let max = max_component(bits);
+    RGB::from([
+        components[0] as f64 / max,
+        components[1] as f64 / max,
+        components[2] as f64 / max,
+    ])
+}
+
+// Direct entry of exact values (e.g. from a specification) as a hex
+// string, 8 or 16 bit RGB components or hue, chroma and value
+#[derive(PWO)]
+pub struct ColourNumericEntryCore {
+    grid: gtk::Grid,
+    hex_entry: gtk::Entry,
+    bits_combo: gtk::ComboBoxText,
+    rgb_spins: [gtk::SpinButton; 3],
+    hue_spin: gtk::SpinButton,
+    chroma_spin: gtk::SpinButton,
+    value_spin: gtk::SpinButton,
+    rgb: Cell<RGB>,
+    // stops the widgets' change signals feeding back while they're set
+    updating: Cell<bool>,
+    value_changed_callbacks: RefCell<Vec<Box<dyn Fn(RGB)>>>,
+}
+
+pub type ColourNumericEntry = Rc<ColourNumericEntryCore>;
+
+impl ColourNumericEntryCore {
+    pub fn new() -> ColourNumericEntry {
+        let new_spin = |max: f64, step: f64, digits: u32, tooltip: &str| {
+            let spin = gtk::SpinButton::with_range(0.0, max, step);
+            spin.set_digits(digits);
+            spin.set_tooltip_text(Some(tooltip));
+            spin
+        };
+        let cne = Rc::new(ColourNumericEntryCore {
+            grid: gtk::Grid::new(),
+            hex_entry: gtk::Entry::new(),
+            bits_combo: gtk::ComboBoxText::new(),
+            rgb_spins: [
+                new_spin(255.0, 1.0, 0, "Red"),
+                new_spin(255.0, 1.0, 0, "Green"),
+                new_spin(255.0, 1.0, 0, "Blue"),
+            ],
+            hue_spin: new_spin(360.0, 1.0, 1, "Hue (degrees).  Ignored for greys."),
+            chroma_spin: new_spin(1.0, 0.005, 3, "Chroma (no more than the value)"),
+            value_spin: new_spin(1.0, 0.005, 3, "Value"),
+            rgb: Cell::new(RGB::BLACK),
+            updating: Cell::new(false),
+            value_changed_callbacks: RefCell::new(Vec::new()),
+        });
+        cne.hex_entry.set_width_chars(8);
+        cne.hex_entry.set_tooltip_text(Some(
+            "The colour as \"#RRGGBB\" (or \"#RGB\").  Press Enter to apply.",
+        ));
+        cne.bits_combo.append(Some("8"), "8 bit");
+        cne.bits_combo.append(Some("16"), "16 bit");
+        let bits = recall(RGB_BITS_RECOLLECTION).unwrap_or("8".to_string());
+        if !cne.bits_combo.set_active_id(Some(bits.as_str())) {
+            cne.bits_combo.set_active_id(Some("8"));
+        }
+        cne.hue_spin.set_wrap(true);
+
+        cne.grid.set_column_spacing(2);
+        cne.grid.attach(&gtk::Label::new(Some("Hex:")), 0, 0, 1, 1);
+        cne.grid.attach(&cne.hex_entry, 1, 0, 1, 1);
+        cne.grid.attach(&cne.bits_combo, 3, 0, 1, 1);
+        cne.grid.attach(&gtk::Label::new(Some("RGB:")), 0, 1, 1, 1);
+        for (i, spin) in cne.rgb_spins.iter().enumerate() {
+            cne.grid.attach(spin, i as i32 + 1, 1, 1, 1);
+        }
+        cne.grid.attach(&gtk::Label::new(Some("HCV:")), 0, 2, 1, 1);
+        cne.grid.attach(&cne.hue_spin, 1, 2, 1, 1);
+        cne.grid.attach(&cne.chroma_spin, 2, 2, 1, 1);
+        cne.grid.attach(&cne.value_spin, 3, 2, 1, 1);
+        cne.grid.show_all();
+        cne.set_bits(cne.bits());
+
+        let cne_c = cne.clone();
+        cne.hex_entry
+            .connect_activate(move |entry| match rgb_from_css_hex(&entry.get_text()) {
+                Some(rgb) => cne_c.set_rgb_and_inform(rgb),
+                None => cne_c.set_rgb(&cne_c.rgb.get()),
+            });
+        let cne_c = cne.clone();
+        cne.hex_entry.connect_focus_out_event(move |_, _| {
+            // abandon any unapplied (or invalid) edit
+            cne_c.set_rgb(&cne_c.rgb.get());
+            Inhibit(false)
+        });
+
+        let cne_c = cne.clone();
+        cne.bits_combo.connect_changed(move |combo| {
+            if let Some(id) = combo.get_active_id() {
+                remember(RGB_BITS_RECOLLECTION, &id);
+            }
+            cne_c.set_bits(cne_c.bits());
+        });
+
+        for spin in cne.rgb_spins.iter() {
+            let cne_c = cne.clone();
+            spin.connect_value_changed(move |_| {
+                if !cne_c.updating.get() {
+                    let components = [
+                        cne_c.rgb_spins[0].get_value_as_int() as u32,
+                        cne_c.rgb_spins[1].get_value_as_int() as u32,
+                        cne_c.rgb_spins[2].get_value_as_int() as u32,
+                    ];
+                    cne_c.set_rgb_and_inform(components_to_rgb(components, cne_c.bits()));
+                }
+            });
+        }
+
+        for spin in [&cne.hue_spin, &cne.chroma_spin, &cne.value_spin].iter() {
+            let cne_c = cne.clone();
+            spin.connect_value_changed(move |_| {
+                if !cne_c.updating.get() {
+                    let value = cne_c.value_spin.get_value();
+                    let chroma = cne_c.chroma_spin.get_value().min(value);
+                    let hue = if chroma > 0.0 {
+                        Some(cne_c.hue_spin.get_value())
+                    } else {
+                        None
+                    };
+                    cne_c.set_rgb_and_inform(hexcone_rgb(hue, chroma, value));
+                }
+            });
+        }
+
+        cne
+    }
+
+    pub fn bits(&self) -> u32 {
+        match self.bits_combo.get_active_id() {
+            Some(ref id) if id.as_str() == "16" => 16,
+            _ => 8,
+        }
+    }
+
+    fn set_bits(&self, bits: u32) {
+        self.updating.set(true);
+        for spin in self.rgb_spins.iter() {
+            spin.set_range(0.0, max_component(bits));
+        }
+        self.updating.set(false);
+        self.set_rgb(&self.rgb.get());
+    }
+
+    // Show "rgb" without informing anybody
+    pub fn set_rgb(&self, rgb: &RGB) {
+        self.rgb.set(*rgb);
+        self.updating.set(true);
+        self.hex_entry.set_text(&rgb.into_css_hex());
+        let components = rgb_to_components(rgb, self.bits());
+        for (spin, component) in self.rgb_spins.iter().zip(components.iter()) {
+            spin.set_value(*component as f64);
+        }
+        let (o_hue, chroma, value) = hexcone_hcv(rgb);
+        // greys keep the last hue so that chroma can be added back to it
+        if let Some(hue) = o_hue {
+            self.hue_spin.set_value(hue);
+        }
+        self.chroma_spin.set_value(chroma);
+        self.value_spin.set_value(value);
+        self.updating.set(false);
+    }
+
+    fn set_rgb_and_inform(&self, rgb: RGB) {
+        self.set_rgb(&rgb);
+        for callback in self.value_changed_callbacks.borrow().iter() {
+            callback(rgb);
+        }
+    }
+
+    pub fn connect_value_changed<F: 'static + Fn(RGB)>(&self, callback: F) {
+        self.value_changed_callbacks
+            .borrow_mut()
+            .push(Box::new(callback))
+    }
 }
 
 pub trait ColourEditorInterface {
@@ -72,6 +315,7 @@ where
     rgb_manipulator: RefCell<RGBManipulator>,
     cads: Rc<A>,
     rgb_entry: Rc<RGBHexEntry<u16>>,
+    numeric_entry: ColourNumericEntry,
     drawing_area: gtk::DrawingArea,
     incr_value_btn: gtk::Button,
     decr_value_btn: gtk::Button,
@@ -87,6 +331,12 @@ where
     popup_menu_position: Cell<Point>,
     auto_match_btn: gtk::Button,
     auto_match_on_paste_btn: gtk::CheckButton,
+    use_as_target_btn: gtk::Button,
+    load_target_btn: gtk::Button,
+    screen_picker: ScreenPicker,
+    named_colour_entry: gtk::Entry,
+    named_colour_chosen_callbacks: RefCell<Vec<Box<dyn Fn(&str)>>>,
+    target_exchange: RefCell<Option<Rc<dyn TargetColourExchange>>>,
     colour_changed_callbacks: RefCell<Vec<Box<dyn Fn(&Colour)>>>,
 }
 
@@ -97,6 +347,7 @@ where
     pub fn set_rgb(&self, rgb: RGB) -> Colour {
         let colour = Colour::from(rgb);
         self.rgb_entry.set_rgb(&rgb.into());
+        self.numeric_entry.set_rgb(&rgb);
         self.rgb_manipulator.borrow_mut().set_rgb(&rgb);
         self.cads.set_colour(Some(&colour));
         self.incr_value_btn
@@ -145,7 +396,7 @@ where
 
     fn draw(&self, _drawing_area: &gtk::DrawingArea, cairo_context: &cairo::Context) {
         let rgb = self.rgb_manipulator.borrow().rgb();
-        cairo_context.set_source_rgb(rgb[CCI::Red], rgb[CCI::Green], rgb[CCI::Blue]);
+        cairo_context.set_source_colour_rgb(&rgb);
         cairo_context.paint();
         for sample in self.samples.borrow().iter() {
             cairo_context.set_source_pixbuf_at(&sample.pix_buf, sample.position);
@@ -155,51 +406,127 @@ where
     }
 
     fn auto_match_samples(&self) {
-        let mut red: u64 = 0;
-        let mut green: u64 = 0;
-        let mut blue: u64 = 0;
-        let mut npixels: u32 = 0;
+        let mut rgbs: Vec<RGB> = Vec::new();
         for sample in self.samples.borrow().iter() {
-            assert_eq!(sample.pix_buf.get_bits_per_sample(), 8);
-            let nc = sample.pix_buf.get_n_channels();
-            let rs = sample.pix_buf.get_rowstride();
-            let width = sample.pix_buf.get_width();
-            let n_rows = sample.pix_buf.get_height();
-            unsafe {
-                let data = sample.pix_buf.get_pixels();
-                for row_num in 0..n_rows {
-                    let row_start = row_num * rs;
-                    for j in 0..width {
-                        let offset = (row_start + j * nc) as usize;
-                        red += data[offset] as u64;
-                        green += data[offset + 1] as u64;
-                        blue += data[offset + 2] as u64;
-                    }
+            rgbs.extend(pixbuf_rgbs(&sample.pix_buf));
+        }
+        if let Some(spread) = colour_spread(&rgbs) {
+            self.set_rgb_and_inform(spread.mean);
+        }
+    }
+
+    // The samples (if any) in the order that they were added
+    pub fn sample_sources(&self) -> Vec<SampleSource> {
+        self.samples
+            .borrow()
+            .iter()
+            .map(|sample| SampleSource {
+                path: sample.o_path.clone(),
+                x: sample.position.0,
+                y: sample.position.1,
+                width: sample.pix_buf.get_width(),
+                height: sample.pix_buf.get_height(),
+            })
+            .collect()
+    }
+
+    fn add_sample(&self, sample: Sample) {
+        self.samples.borrow_mut().push(sample);
+        if self.auto_match_on_paste_btn.get_active() {
+            self.auto_match_samples();
+        } else {
+            self.drawing_area.queue_draw();
+        };
+        self.auto_match_btn.set_sensitive(true);
+    }
+
+    fn load_sample_from_file(&self) {
+        let o_last_file = recall(SAMPLE_FILE_RECOLLECTION);
+        let last_file = if let Some(ref text) = o_last_file {
+            Some(text.as_str())
+        } else {
+            None
+        };
+        if let Some(path) = self.ask_file_path(Some("Sample Image File:"), last_file, true) {
+            match gdk_pixbuf::Pixbuf::from_file(&path) {
+                Ok(pixbuf) => {
+                    remember(SAMPLE_FILE_RECOLLECTION, &path.to_string_lossy());
+                    self.add_sample(Sample {
+                        pix_buf: pixbuf,
+                        position: self.popup_menu_position.get(),
+                        o_path: Some(path),
+                    });
+                }
+                Err(err) => {
+                    let msg = format!("{:?}: Failed to load sample image", path);
+                    self.report_error(&msg, &err);
                 }
             }
-            npixels += (width * n_rows) as u32;
-        }
-        if npixels > 0 {
-            let divisor = (npixels * 255) as f64;
-            let array: [f64; 3] = [
-                red as f64 / divisor,
-                green as f64 / divisor,
-                blue as f64 / divisor,
-            ];
-            self.set_rgb_and_inform(array.into());
         }
     }
 
     pub fn reset(&self) {
         self.samples.borrow_mut().clear();
+        self.named_colour_entry.set_text("");
         self.set_rgb_and_inform(RGB::WHITE * 0.5);
     }
 
+    // e.g. so that a dialog can describe where its colour came from
+    pub fn connect_screen_colour_picked<F: 'static + Fn(&RGB)>(&self, callback: F) {
+        self.screen_picker.connect_picked(callback)
+    }
+
+    fn choose_named_colour(&self, name: &str) {
+        if let Some(rgb) = css_colour_rgb(name) {
+            self.set_rgb_and_inform(rgb);
+            for callback in self.named_colour_chosen_callbacks.borrow().iter() {
+                callback(name);
+            }
+        } else {
+            let msg = format!("\"{}\" is not a CSS/X11 colour name.", name);
+            self.inform_user(&msg, None);
+        }
+    }
+
+    // e.g. so that the name can be recorded in the paint's notes
+    pub fn connect_named_colour_chosen<F: 'static + Fn(&str)>(&self, callback: F) {
+        self.named_colour_chosen_callbacks
+            .borrow_mut()
+            .push(Box::new(callback))
+    }
+
     pub fn connect_colour_changed<F: 'static + Fn(&Colour)>(&self, callback: F) {
         self.colour_changed_callbacks
             .borrow_mut()
             .push(Box::new(callback))
     }
+
+    // The transfer buttons are only shown while there is an exchange
+    pub fn set_target_exchange(&self, o_exchange: Option<Rc<dyn TargetColourExchange>>) {
+        let visible = o_exchange.is_some();
+        *self.target_exchange.borrow_mut() = o_exchange;
+        self.use_as_target_btn.set_visible(visible);
+        self.load_target_btn.set_visible(visible);
+    }
+
+    fn use_as_target(&self) {
+        if let Some(ref exchange) = *self.target_exchange.borrow() {
+            exchange.accept_target_colour(&self.get_colour());
+        }
+    }
+
+    fn load_target(&self) {
+        let o_colour = if let Some(ref exchange) = *self.target_exchange.borrow() {
+            exchange.target_colour()
+        } else {
+            None
+        };
+        if let Some(colour) = o_colour {
+            self.set_rgb_and_inform(colour.rgb());
+        } else {
+            self.inform_user("There is no target colour to load.", None);
+        }
+    }
 }
 
 impl<A> ColourEditorInterface for Rc<ColourEditorCore<A>>
@@ -213,6 +540,7 @@ where
             rgb_manipulator: RefCell::new(ColourManipulatorBuilder::new().clamped(false).build()),
             cads: A::create(),
             rgb_entry,
+            numeric_entry: ColourNumericEntryCore::new(),
             drawing_area: gtk::DrawingArea::new(),
             incr_value_btn: gtk::Button::with_label("Value++"),
             decr_value_btn: gtk::Button::with_label("Value--"),
@@ -227,6 +555,12 @@ where
             delta_size: Cell::new(DeltaSize::Normal),
             auto_match_btn: gtk::Button::with_label("Auto Match"),
             auto_match_on_paste_btn: gtk::CheckButton::with_label("On Paste?"),
+            use_as_target_btn: gtk::Button::with_label("Use as Mixer Target"),
+            load_target_btn: gtk::Button::with_label("Load Mixer Target"),
+            screen_picker: ScreenPicker::create(),
+            named_colour_entry: gtk::Entry::new(),
+            named_colour_chosen_callbacks: RefCell::new(Vec::new()),
+            target_exchange: RefCell::new(None),
             popup_menu_position: Cell::new(Point(0.0, 0.0)),
             colour_changed_callbacks: RefCell::new(Vec::new()),
         });
@@ -236,6 +570,67 @@ where
         ced.drawing_area.set_size_request(200, 200);
 
         ced.vbox.pack_start(&ced.rgb_entry.pwo(), false, false, 0);
+        let expander = gtk::Expander::new(Some("Exact Values"));
+        expander.add(&ced.numeric_entry.pwo());
+        ced.vbox.pack_start(&expander, false, false, 0);
+        ced.vbox
+            .pack_start(&ced.screen_picker.pwo(), false, false, 0);
+
+        let list_store = gtk::ListStore::new(&[String::static_type()]);
+        for name in css_colour_names().iter() {
+            list_store.insert_with_values(None, &[0], &[name]);
+        }
+        let completion = gtk::EntryCompletion::new();
+        completion.set_model(Some(&list_store));
+        completion.set_text_column(0);
+        completion.set_minimum_key_length(1);
+        // match anywhere in the name ignoring case and spaces (with the key
+        // normalised once per keystroke rather than once per row)
+        let normalised_key: RefCell<(String, String)> =
+            RefCell::new((String::new(), String::new()));
+        completion.set_match_func(move |completion, key, iter| {
+            if normalised_key.borrow().0 != key {
+                *normalised_key.borrow_mut() = (key.to_string(), normalised_css_name(key));
+            }
+            completion
+                .get_model()
+                .and_then(|model| model.get_value(iter, 0).get::<String>().ok())
+                .and_then(|name| name)
+                .map_or(false, |name| {
+                    normalised_css_name(&name).contains(&normalised_key.borrow().1)
+                })
+        });
+        ced.named_colour_entry.set_completion(Some(&completion));
+        ced.named_colour_entry
+            .set_placeholder_text(Some("e.g. dark olive green"));
+        ced.named_colour_entry.set_tooltip_text(Some(
+            "Start from a standard (CSS/X11) named colour.  Type part of the name and choose from the list (or press Enter).",
+        ));
+        let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 2);
+        hbox.pack_start(&gtk::Label::new(Some("Named Colour:")), false, false, 0);
+        hbox.pack_start(&ced.named_colour_entry, true, true, 0);
+        ced.vbox.pack_start(&hbox, false, false, 0);
+
+        let ced_c = ced.clone();
+        completion.connect_match_selected(move |_, model, iter| {
+            let o_name = model
+                .get_value(iter, 0)
+                .get::<String>()
+                .ok()
+                .and_then(|name| name);
+            if let Some(name) = o_name {
+                ced_c.named_colour_entry.set_text(&name);
+                ced_c.choose_named_colour(&name);
+            }
+            Inhibit(true)
+        });
+        let ced_c = ced.clone();
+        ced.named_colour_entry.connect_activate(move |entry| {
+            let text = entry.get_text();
+            if text.trim().len() > 0 {
+                ced_c.choose_named_colour(text.trim());
+            }
+        });
         ced.vbox.pack_start(&ced.cads.pwo(), false, false, 0);
 
         ced.vbox
@@ -268,6 +663,13 @@ where
         bbox.pack_start(&ced.auto_match_on_paste_btn.clone(), false, false, 0);
         ced.vbox.pack_start(&bbox, false, false, 0);
 
+        let bbox = gtk::Box::new(gtk::Orientation::Horizontal, 2);
+        bbox.pack_start(&ced.use_as_target_btn.clone(), true, true, 0);
+        bbox.pack_start(&ced.load_target_btn.clone(), true, true, 0);
+        ced.vbox.pack_start(&bbox, false, false, 0);
+        ced.use_as_target_btn.set_no_show_all(true);
+        ced.load_target_btn.set_no_show_all(true);
+
         ced.vbox.show_all();
 
         let events = gdk::EventMask::KEY_PRESS_MASK
@@ -404,10 +806,32 @@ where
             ced_c.set_rgb_and_inform(rgb.into());
         });
 
+        let ced_c = ced.clone();
+        ced.numeric_entry
+            .connect_value_changed(move |rgb| ced_c.set_rgb_and_inform(rgb));
+
+        let ced_c = ced.clone();
+        ced.screen_picker
+            .connect_picked(move |rgb| ced_c.set_rgb_and_inform(*rgb));
+
         let ced_c = ced.clone();
         ced.auto_match_btn
             .connect_clicked(move |_| ced_c.auto_match_samples());
 
+        ced.use_as_target_btn.set_tooltip_text(Some(
+            "Start a new mixture in the mixer with this colour as its target.",
+        ));
+        let ced_c = ced.clone();
+        ced.use_as_target_btn
+            .connect_clicked(move |_| ced_c.use_as_target());
+
+        ced.load_target_btn.set_tooltip_text(Some(
+            "Load the mixer's current target colour into the editor.",
+        ));
+        let ced_c = ced.clone();
+        ced.load_target_btn
+            .connect_clicked(move |_| ced_c.load_target());
+
         let ced_c = ced.clone();
         ced.drawing_area.connect_draw(move |da, cctx| {
             ced_c.draw(da, cctx);
@@ -424,22 +848,25 @@ where
             .connect_activate(move |_| {
                 let cbd = gtk::Clipboard::get(&gdk::SELECTION_CLIPBOARD);
                 if let Some(pixbuf) = cbd.wait_for_image() {
-                    let sample = Sample {
+                    ced_c.add_sample(Sample {
                         pix_buf: pixbuf,
                         position: ced_c.popup_menu_position.get(),
-                    };
-                    ced_c.samples.borrow_mut().push(sample);
-                    if ced_c.auto_match_on_paste_btn.get_active() {
-                        ced_c.auto_match_samples();
-                    } else {
-                        ced_c.drawing_area.queue_draw();
-                    };
-                    ced_c.auto_match_btn.set_sensitive(true);
+                        o_path: None,
+                    });
                 } else {
                     ced_c.inform_user("No image data on clipboard.", None);
                 }
             });
 
+        let ced_c = ced.clone();
+        ced.popup_menu
+            .append_item(
+                "load",
+                "Load Sample from File...",
+                "Load an image file as a sample at this position",
+            )
+            .connect_activate(move |_| ced_c.load_sample_from_file());
+
         let ced_c = ced.clone();
         ced.popup_menu
             .append_item(
@@ -485,8 +912,28 @@ pub type ColourEditor<A> = Rc<ColourEditorCore<A>>;
 
 #[cfg(test)]
 mod tests {
-    //use super::*;
+    use super::*;
 
     #[test]
     fn it_works() {}
+
+    #[test]
+    fn rgb_components() {
+        let rgb = RGB::from([1.0, 0.5, 0.0]);
+        assert_eq!(rgb_to_components(&rgb, 8), [255, 128, 0]);
+        assert_eq!(rgb_to_components(&rgb, 16), [65535, 32768, 0]);
+        assert_eq!(
+            rgb_to_components(&RGB::from([1.5, -0.5, 0.25]), 8),
+            [255, 0, 64]
+        );
+        assert_eq!(
+            components_to_rgb([255, 0, 255], 8),
+            RGB::from([1.0, 0.0, 1.0])
+        );
+        let components = [12345, 54321, 777];
+        assert_eq!(
+            rgb_to_components(&components_to_rgb(components, 16), 16),
+            components
+        );
+    }
 }
@@ -0,0 +1,128 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+//! Optional colour management of the colours that are displayed (requires
+//! the "colour_management" feature and the lcms2 library).  Colours are
+//! assumed to be sRGB and are transformed to the monitor's ICC profile.
+//! Without the feature (or a profile) they are displayed unchanged.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use pw_gix::recollections::{recall, remember};
+
+use crate::colour::RGB;
+
+const DISPLAY_PROFILE_KEY: &str = "colour_management::display_profile";
+
+/// The monitor profile chosen by the user (if any).
+pub fn display_profile_path() -> Option<PathBuf> {
+    match recall(DISPLAY_PROFILE_KEY) {
+        Some(path) if path.len() > 0 => Some(PathBuf::from(path)),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "colour_management")]
+mod transform {
+    use std::cell::{Cell, RefCell};
+    use std::io;
+    use std::path::Path;
+
+    use lcms2::{Intent, PixelFormat, Profile, Transform};
+
+    type DisplayTransform = Transform<[f64; 3], [f64; 3]>;
+
+    thread_local! {
+        static DISPLAY_TRANSFORM: RefCell<Option<DisplayTransform>> = RefCell::new(None);
+        static LOADED: Cell<bool> = Cell::new(false);
+    }
+
+    fn load(path: &Path) -> io::Result<DisplayTransform> {
+        let to_io_error = |err: lcms2::Error| io::Error::new(io::ErrorKind::InvalidData, err);
+        let display_profile = Profile::new_file(path).map_err(to_io_error)?;
+        Transform::new(
+            &Profile::new_srgb(),
+            PixelFormat::RGB_DBL,
+            &display_profile,
+            PixelFormat::RGB_DBL,
+            Intent::Perceptual,
+        )
+        .map_err(to_io_error)
+    }
+
+    pub fn set_profile(o_path: Option<&Path>) -> io::Result<()> {
+        let o_transform = match o_path {
+            Some(path) => Some(load(path)?),
+            None => None,
+        };
+        DISPLAY_TRANSFORM.with(|transform| *transform.borrow_mut() = o_transform);
+        LOADED.with(|loaded| loaded.set(true));
+        Ok(())
+    }
+
+    pub fn apply(rgb: [f64; 3]) -> [f64; 3] {
+        if !LOADED.with(|loaded| loaded.get()) {
+            // A profile that's gone missing since it was chosen is ignored
+            let o_path = super::display_profile_path();
+            if set_profile(o_path.as_ref().map(|path| path.as_path())).is_err() {
+                LOADED.with(|loaded| loaded.set(true));
+            }
+        }
+        DISPLAY_TRANSFORM.with(|transform| match *transform.borrow() {
+            Some(ref transform) => {
+                let mut output = [[0.0; 3]];
+                transform.transform_pixels(&[rgb], &mut output);
+                output[0]
+            }
+            None => rgb,
+        })
+    }
+}
+
+#[cfg(feature = "colour_management")]
+pub fn colour_management_available() -> bool {
+    true
+}
+
+#[cfg(not(feature = "colour_management"))]
+pub fn colour_management_available() -> bool {
+    false
+}
+
+/// Use the monitor profile at the given path (or none) from now on.  The
+/// choice is remembered only if the profile can be loaded.
+#[cfg(feature = "colour_management")]
+pub fn set_display_profile_path(o_path: Option<&Path>) -> io::Result<()> {
+    transform::set_profile(o_path)?;
+    let text = o_path.map(|path| path.to_string_lossy().to_string());
+    remember(DISPLAY_PROFILE_KEY, text.as_deref().unwrap_or(""));
+    Ok(())
+}
+
+#[cfg(not(feature = "colour_management"))]
+pub fn set_display_profile_path(o_path: Option<&Path>) -> io::Result<()> {
+    if o_path.is_some() {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "colour management is not available",
+        ))
+    } else {
+        remember(DISPLAY_PROFILE_KEY, "");
+        Ok(())
+    }
+}
+
+/// The values to send to the display so that the (sRGB) colour is shown
+/// as faithfully as the monitor allows.
+#[cfg(feature = "colour_management")]
+pub fn display_rgb(rgb: &RGB) -> RGB {
+    use crate::colour::CCI;
+
+    let array: [f64; 3] = [rgb[CCI::Red], rgb[CCI::Green], rgb[CCI::Blue]];
+    transform::apply(array).into()
+}
+
+#[cfg(not(feature = "colour_management"))]
+pub fn display_rgb(rgb: &RGB) -> RGB {
+    *rgb
+}
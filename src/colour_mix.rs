@@ -10,20 +10,76 @@ pub struct ColourComponent {
     pub parts: u32,
 }
 
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum MixingModel {
+    // average the gamma encoded sRGB values directly
+    SRGB,
+    // average in linear light and re-encode the result
+    LinearLight,
+}
+
+impl Default for MixingModel {
+    fn default() -> MixingModel {
+        MixingModel::SRGB
+    }
+}
+
+impl MixingModel {
+    fn decode(&self, value: f64) -> f64 {
+        match *self {
+            MixingModel::SRGB => value,
+            MixingModel::LinearLight => srgb_to_linear(value),
+        }
+    }
+
+    fn encode(&self, value: f64) -> f64 {
+        match *self {
+            MixingModel::SRGB => value,
+            MixingModel::LinearLight => linear_to_srgb(value),
+        }
+    }
+}
+
+pub fn srgb_to_linear(value: f64) -> f64 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn linear_to_srgb(value: f64) -> f64 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct ColourMixer {
     rgb_sum: [f64; 3],
     total_parts: u32,
+    model: MixingModel,
 }
 
 impl ColourMixer {
     pub fn new() -> ColourMixer {
+        ColourMixer::with_model(MixingModel::default())
+    }
+
+    pub fn with_model(model: MixingModel) -> ColourMixer {
         ColourMixer {
             rgb_sum: [0.0, 0.0, 0.0],
             total_parts: 0,
+            model: model,
         }
     }
 
+    pub fn model(&self) -> MixingModel {
+        self.model
+    }
+
     pub fn reset(&mut self) {
         self.total_parts = 0;
         self.rgb_sum = [0.0, 0.0, 0.0];
@@ -33,9 +89,9 @@ impl ColourMixer {
         if self.total_parts > 0 {
             let divisor = self.total_parts as f64;
             let array: [f64; 3] = [
-                self.rgb_sum[0] / divisor,
-                self.rgb_sum[1] / divisor,
-                self.rgb_sum[2] / divisor,
+                self.model.encode(self.rgb_sum[0] / divisor),
+                self.model.encode(self.rgb_sum[1] / divisor),
+                self.model.encode(self.rgb_sum[2] / divisor),
             ];
             Some(Colour::from(RGB::from(array)))
         } else {
@@ -45,9 +101,10 @@ impl ColourMixer {
 
     pub fn add(&mut self, colour: &Colour, parts: u32) {
         self.total_parts += parts;
-        self.rgb_sum[0] += colour.rgb()[CCI::Red] * parts as f64;
-        self.rgb_sum[1] += colour.rgb()[CCI::Green] * parts as f64;
-        self.rgb_sum[2] += colour.rgb()[CCI::Blue] * parts as f64;
+        let rgb = colour.rgb();
+        self.rgb_sum[0] += self.model.decode(rgb[CCI::Red]) * parts as f64;
+        self.rgb_sum[1] += self.model.decode(rgb[CCI::Green]) * parts as f64;
+        self.rgb_sum[2] += self.model.decode(rgb[CCI::Blue]) * parts as f64;
     }
 }
 
@@ -72,4 +129,37 @@ mod tests {
         colour_mixer.add(&Colour::from(RGB::RED), 10);
         assert_eq!(colour_mixer.get_colour(), Some(Colour::from(RGB::RED)));
     }
+
+    #[test]
+    fn srgb_linear_round_trip() {
+        for i in 0..=100 {
+            let value = i as f64 / 100.0;
+            assert!((linear_to_srgb(srgb_to_linear(value)) - value).abs() < 1.0e-12);
+        }
+    }
+
+    #[test]
+    fn paint_colour_mix_models_compared() {
+        for model in [MixingModel::SRGB, MixingModel::LinearLight].iter() {
+            let mut colour_mixer = ColourMixer::with_model(*model);
+            colour_mixer.add(&Colour::from(RGB::RED), 3);
+            colour_mixer.add(&Colour::from(RGB::RED), 7);
+            assert_eq!(colour_mixer.get_colour(), Some(Colour::from(RGB::RED)));
+        }
+        let components = vec![(Colour::from(RGB::BLACK), 1), (Colour::from(RGB::WHITE), 1)];
+        let mut srgb_mixer = ColourMixer::with_model(MixingModel::SRGB);
+        let mut linear_mixer = ColourMixer::with_model(MixingModel::LinearLight);
+        for (colour, parts) in components.iter() {
+            srgb_mixer.add(colour, *parts);
+            linear_mixer.add(colour, *parts);
+        }
+        let srgb_mix = srgb_mixer.get_colour().unwrap().rgb();
+        let linear_mix = linear_mixer.get_colour().unwrap().rgb();
+        assert!((srgb_mix[CCI::Red] - 0.5).abs() < 1.0e-12);
+        assert!((linear_mix[CCI::Red] - linear_to_srgb(0.5)).abs() < 1.0e-12);
+        // mixing in linear light does not skew mixtures towards dark
+        assert!(linear_mix[CCI::Red] > srgb_mix[CCI::Red]);
+        assert!(linear_mix[CCI::Green] > srgb_mix[CCI::Green]);
+        assert!(linear_mix[CCI::Blue] > srgb_mix[CCI::Blue]);
+    }
 }
@@ -0,0 +1,410 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+// Descriptive (rather than exact) names for colours e.g. "Deep Warm Olive"
+// to be used as a starting point when naming mixtures.
+
+use crate::colour::*;
+
+pub const GREY_CHROMA_THRESHOLD: f64 = 0.05;
+pub const MUTED_CHROMA_THRESHOLD: f64 = 0.25;
+pub const VIVID_CHROMA_THRESHOLD: f64 = 0.75;
+
+// Upper hue angle (degrees from red) of each band and its name
+static HUE_BANDS: &[(f64, &str)] = &[
+    (15.0, "Red"),
+    (45.0, "Orange"),
+    (70.0, "Yellow"),
+    (95.0, "Chartreuse"),
+    (150.0, "Green"),
+    (175.0, "Teal"),
+    (200.0, "Cyan"),
+    (225.0, "Azure"),
+    (255.0, "Blue"),
+    (285.0, "Violet"),
+    (315.0, "Purple"),
+    (345.0, "Magenta"),
+    (360.0, "Red"),
+];
+
+// Dark versions of some hues have names of their own
+static DARK_HUE_NAMES: &[(&str, &str)] = &[
+    ("Red", "Maroon"),
+    ("Orange", "Brown"),
+    ("Yellow", "Olive"),
+    ("Chartreuse", "Olive"),
+    ("Blue", "Navy"),
+];
+
+// Greys from darkest to lightest with their upper value
+static GREY_NAMES: &[(f64, &str)] = &[
+    (0.1, "Black"),
+    (0.3, "Charcoal"),
+    (0.45, "Dark Grey"),
+    (0.7, "Grey"),
+    (0.9, "Light Grey"),
+    (1.0, "White"),
+];
+
+// Value qualifiers from darkest to lightest with their upper value
+static VALUE_QUALIFIERS: &[(f64, &str)] = &[
+    (0.2, "Very Dark"),
+    (0.4, "Deep"),
+    (0.6, ""),
+    (0.8, "Light"),
+    (1.0, "Pale"),
+];
+
+fn normalised_hue_degrees(colour: &Colour) -> Option<f64> {
+    let degrees = colour.hue_angle()?.radians().to_degrees();
+    Some(if degrees < 0.0 {
+        degrees + 360.0
+    } else {
+        degrees
+    })
+}
+
+// Lightness relative to the hue's most chromatic colour (which is 0.5)
+// so that pure hues of differing value are treated alike
+fn relative_lightness(colour: &Colour) -> f64 {
+    let value = colour.value();
+    let mcv = colour.max_chroma_rgb().value();
+    if value <= mcv {
+        0.5 * value / mcv
+    } else {
+        0.5 + 0.5 * (value - mcv) / (1.0 - mcv)
+    }
+}
+
+fn hue_band(degrees: f64) -> (f64, f64, &'static str) {
+    let mut lower = 0.0;
+    for (upper, name) in HUE_BANDS.iter() {
+        if degrees < *upper {
+            return (lower, *upper, name);
+        }
+        lower = *upper;
+    }
+    (345.0, 360.0, "Red")
+}
+
+fn qualifier_for(value: f64, table: &[(f64, &'static str)]) -> &'static str {
+    for (upper, name) in table.iter() {
+        if value < *upper {
+            return name;
+        }
+    }
+    table[table.len() - 1].1
+}
+
+// Hues that aren't intrinsically warm or cool are described by which
+// way they lean (warm being towards red/yellow)
+fn temperature_qualifier(degrees: f64, band_name: &str) -> &'static str {
+    match band_name {
+        "Red" | "Orange" | "Yellow" | "Cyan" | "Azure" | "Blue" => "",
+        _ => {
+            let (lower, upper, _) = hue_band(degrees);
+            let toward_lower = degrees < (lower + upper) / 2.0;
+            // Bands below cyan get warmer towards their lower edge
+            if (degrees < 180.0) == toward_lower {
+                "Warm"
+            } else {
+                "Cool"
+            }
+        }
+    }
+}
+
+pub fn suggest_colour_name(colour: &Colour) -> String {
+    let chroma = colour.chroma();
+    let degrees = match normalised_hue_degrees(colour) {
+        Some(degrees) if chroma >= GREY_CHROMA_THRESHOLD => degrees,
+        _ => return qualifier_for(colour.value(), GREY_NAMES).to_string(),
+    };
+    let lightness = relative_lightness(colour);
+    let (_, _, band_name) = hue_band(degrees);
+    let mut words: Vec<&str> = vec![];
+    let mut hue_name = band_name;
+    let value_qualifier = qualifier_for(lightness, VALUE_QUALIFIERS);
+    if lightness < 0.4 {
+        if let Some((_, dark_name)) = DARK_HUE_NAMES.iter().find(|(n, _)| *n == band_name) {
+            hue_name = dark_name;
+            if lightness < 0.2 {
+                words.push(value_qualifier);
+            }
+        } else {
+            words.push(value_qualifier);
+        }
+    } else {
+        words.push(value_qualifier);
+    }
+    if chroma < MUTED_CHROMA_THRESHOLD {
+        words.push("Muted");
+    } else if chroma > VIVID_CHROMA_THRESHOLD {
+        words.push("Vivid");
+    }
+    words.push(temperature_qualifier(degrees, band_name));
+    words.push(hue_name);
+    words
+        .iter()
+        .filter(|word| word.len() > 0)
+        .cloned()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+// The CSS4 (and X11) named colours e.g. for starting a colour from
+// "dark olive green" and tweaking it from there.  NB: CSS spells the names
+// without spaces and in lower case.
+static CSS_COLOURS: &[(&str, &str)] = &[
+    ("aliceblue", "#F0F8FF"),
+    ("antiquewhite", "#FAEBD7"),
+    ("aqua", "#00FFFF"),
+    ("aquamarine", "#7FFFD4"),
+    ("azure", "#F0FFFF"),
+    ("beige", "#F5F5DC"),
+    ("bisque", "#FFE4C4"),
+    ("black", "#000000"),
+    ("blanchedalmond", "#FFEBCD"),
+    ("blue", "#0000FF"),
+    ("blueviolet", "#8A2BE2"),
+    ("brown", "#A52A2A"),
+    ("burlywood", "#DEB887"),
+    ("cadetblue", "#5F9EA0"),
+    ("chartreuse", "#7FFF00"),
+    ("chocolate", "#D2691E"),
+    ("coral", "#FF7F50"),
+    ("cornflowerblue", "#6495ED"),
+    ("cornsilk", "#FFF8DC"),
+    ("crimson", "#DC143C"),
+    ("cyan", "#00FFFF"),
+    ("darkblue", "#00008B"),
+    ("darkcyan", "#008B8B"),
+    ("darkgoldenrod", "#B8860B"),
+    ("darkgray", "#A9A9A9"),
+    ("darkgreen", "#006400"),
+    ("darkgrey", "#A9A9A9"),
+    ("darkkhaki", "#BDB76B"),
+    ("darkmagenta", "#8B008B"),
+    ("darkolivegreen", "#556B2F"),
+    ("darkorange", "#FF8C00"),
+    ("darkorchid", "#9932CC"),
+    ("darkred", "#8B0000"),
+    ("darksalmon", "#E9967A"),
+    ("darkseagreen", "#8FBC8F"),
+    ("darkslateblue", "#483D8B"),
+    ("darkslategray", "#2F4F4F"),
+    ("darkslategrey", "#2F4F4F"),
+    ("darkturquoise", "#00CED1"),
+    ("darkviolet", "#9400D3"),
+    ("deeppink", "#FF1493"),
+    ("deepskyblue", "#00BFFF"),
+    ("dimgray", "#696969"),
+    ("dimgrey", "#696969"),
+    ("dodgerblue", "#1E90FF"),
+    ("firebrick", "#B22222"),
+    ("floralwhite", "#FFFAF0"),
+    ("forestgreen", "#228B22"),
+    ("fuchsia", "#FF00FF"),
+    ("gainsboro", "#DCDCDC"),
+    ("ghostwhite", "#F8F8FF"),
+    ("gold", "#FFD700"),
+    ("goldenrod", "#DAA520"),
+    ("gray", "#808080"),
+    ("green", "#008000"),
+    ("greenyellow", "#ADFF2F"),
+    ("grey", "#808080"),
+    ("honeydew", "#F0FFF0"),
+    ("hotpink", "#FF69B4"),
+    ("indianred", "#CD5C5C"),
+    ("indigo", "#4B0082"),
+    ("ivory", "#FFFFF0"),
+    ("khaki", "#F0E68C"),
+    ("lavender", "#E6E6FA"),
+    ("lavenderblush", "#FFF0F5"),
+    ("lawngreen", "#7CFC00"),
+    ("lemonchiffon", "#FFFACD"),
+    ("lightblue", "#ADD8E6"),
+    ("lightcoral", "#F08080"),
+    ("lightcyan", "#E0FFFF"),
+    ("lightgoldenrodyellow", "#FAFAD2"),
+    ("lightgray", "#D3D3D3"),
+    ("lightgreen", "#90EE90"),
+    ("lightgrey", "#D3D3D3"),
+    ("lightpink", "#FFB6C1"),
+    ("lightsalmon", "#FFA07A"),
+    ("lightseagreen", "#20B2AA"),
+    ("lightskyblue", "#87CEFA"),
+    ("lightslategray", "#778899"),
+    ("lightslategrey", "#778899"),
+    ("lightsteelblue", "#B0C4DE"),
+    ("lightyellow", "#FFFFE0"),
+    ("lime", "#00FF00"),
+    ("limegreen", "#32CD32"),
+    ("linen", "#FAF0E6"),
+    ("magenta", "#FF00FF"),
+    ("maroon", "#800000"),
+    ("mediumaquamarine", "#66CDAA"),
+    ("mediumblue", "#0000CD"),
+    ("mediumorchid", "#BA55D3"),
+    ("mediumpurple", "#9370DB"),
+    ("mediumseagreen", "#3CB371"),
+    ("mediumslateblue", "#7B68EE"),
+    ("mediumspringgreen", "#00FA9A"),
+    ("mediumturquoise", "#48D1CC"),
+    ("mediumvioletred", "#C71585"),
+    ("midnightblue", "#191970"),
+    ("mintcream", "#F5FFFA"),
+    ("mistyrose", "#FFE4E1"),
+    ("moccasin", "#FFE4B5"),
+    ("navajowhite", "#FFDEAD"),
+    ("navy", "#000080"),
+    ("oldlace", "#FDF5E6"),
+    ("olive", "#808000"),
+    ("olivedrab", "#6B8E23"),
+    ("orange", "#FFA500"),
+    ("orangered", "#FF4500"),
+    ("orchid", "#DA70D6"),
+    ("palegoldenrod", "#EEE8AA"),
+    ("palegreen", "#98FB98"),
+    ("paleturquoise", "#AFEEEE"),
+    ("palevioletred", "#DB7093"),
+    ("papayawhip", "#FFEFD5"),
+    ("peachpuff", "#FFDAB9"),
+    ("peru", "#CD853F"),
+    ("pink", "#FFC0CB"),
+    ("plum", "#DDA0DD"),
+    ("powderblue", "#B0E0E6"),
+    ("purple", "#800080"),
+    ("rebeccapurple", "#663399"),
+    ("red", "#FF0000"),
+    ("rosybrown", "#BC8F8F"),
+    ("royalblue", "#4169E1"),
+    ("saddlebrown", "#8B4513"),
+    ("salmon", "#FA8072"),
+    ("sandybrown", "#F4A460"),
+    ("seagreen", "#2E8B57"),
+    ("seashell", "#FFF5EE"),
+    ("sienna", "#A0522D"),
+    ("silver", "#C0C0C0"),
+    ("skyblue", "#87CEEB"),
+    ("slateblue", "#6A5ACD"),
+    ("slategray", "#708090"),
+    ("slategrey", "#708090"),
+    ("snow", "#FFFAFA"),
+    ("springgreen", "#00FF7F"),
+    ("steelblue", "#4682B4"),
+    ("tan", "#D2B48C"),
+    ("teal", "#008080"),
+    ("thistle", "#D8BFD8"),
+    ("tomato", "#FF6347"),
+    ("turquoise", "#40E0D0"),
+    ("violet", "#EE82EE"),
+    ("wheat", "#F5DEB3"),
+    ("white", "#FFFFFF"),
+    ("whitesmoke", "#F5F5F5"),
+    ("yellow", "#FFFF00"),
+    ("yellowgreen", "#9ACD32"),
+];
+
+// Lower case without spaces or underscores (so that "Dark Olive Green"
+// and "dark_olive_green" find "darkolivegreen")
+pub fn normalised_css_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| !c.is_whitespace() && *c != '_' && *c != '-')
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+pub fn css_colour_names() -> Vec<&'static str> {
+    CSS_COLOURS.iter().map(|(name, _)| *name).collect()
+}
+
+pub fn css_colour_rgb(name: &str) -> Option<RGB> {
+    let name = normalised_css_name(name);
+    CSS_COLOURS
+        .iter()
+        .find(|(css_name, _)| *css_name == name)
+        .and_then(|(_, hex)| rgb_from_css_hex(hex))
+}
+
+// The named colours whose names contain "text" (in name order)
+pub fn matching_css_colours(text: &str) -> Vec<(&'static str, RGB)> {
+    let text = normalised_css_name(text);
+    CSS_COLOURS
+        .iter()
+        .filter(|(name, _)| name.contains(&text))
+        .filter_map(|(name, hex)| rgb_from_css_hex(hex).map(|rgb| (*name, rgb)))
+        .collect()
+}
+
+// Add a record of the named colour that a colour was started from to
+// "notes" (unless it's already there)
+pub fn notes_with_css_colour(notes: &str, name: &str) -> String {
+    let record = format!("Based on CSS colour \"{}\"", normalised_css_name(name));
+    if notes.contains(&record) {
+        notes.to_string()
+    } else if notes.trim().len() == 0 {
+        record
+    } else {
+        format!("{}; {}", notes.trim(), record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name_for(rgb: [f64; 3]) -> String {
+        suggest_colour_name(&Colour::from(RGB::from(rgb)))
+    }
+
+    #[test]
+    fn greys_named_by_value() {
+        assert_eq!(name_for([0.0, 0.0, 0.0]), "Black");
+        assert_eq!(name_for([0.5, 0.5, 0.5]), "Grey");
+        assert_eq!(name_for([1.0, 1.0, 1.0]), "White");
+    }
+
+    #[test]
+    fn hues_named_by_band() {
+        assert_eq!(name_for([1.0, 0.0, 0.0]), "Vivid Red");
+        assert_eq!(name_for([0.0, 0.0, 1.0]), "Vivid Blue");
+        assert!(name_for([0.3, 0.0, 0.0]).ends_with("Maroon"));
+        assert!(name_for([0.0, 0.5, 0.0]).ends_with("Green"));
+        assert!(name_for([0.4, 0.4, 0.1]).ends_with("Olive"));
+    }
+
+    #[test]
+    fn temperature_follows_lean() {
+        assert_eq!(temperature_qualifier(100.0, "Green"), "Warm");
+        assert_eq!(temperature_qualifier(140.0, "Green"), "Cool");
+        assert_eq!(temperature_qualifier(290.0, "Purple"), "Cool");
+        assert_eq!(temperature_qualifier(310.0, "Purple"), "Warm");
+        assert_eq!(temperature_qualifier(100.0, "Blue"), "");
+    }
+
+    #[test]
+    fn css_colour_lookup() {
+        assert_eq!(css_colour_names().len(), 148);
+        assert_eq!(
+            css_colour_rgb("Dark Olive Green").map(|rgb| rgb.into_css_hex()),
+            Some("#556B2F".to_string())
+        );
+        assert_eq!(
+            css_colour_rgb("rebecca_purple"),
+            css_colour_rgb("rebeccapurple")
+        );
+        assert_eq!(css_colour_rgb("not a colour"), None);
+        let olives: Vec<&str> = matching_css_colours("OLIVE")
+            .iter()
+            .map(|(name, _)| *name)
+            .collect();
+        assert_eq!(olives, vec!["darkolivegreen", "olive", "olivedrab"]);
+        let notes = notes_with_css_colour("", "dark olive green");
+        assert_eq!(notes, "Based on CSS colour \"darkolivegreen\"");
+        assert_eq!(notes_with_css_colour(&notes, "darkolivegreen"), notes);
+        assert_eq!(
+            notes_with_css_colour("Tank camo ", "olive"),
+            "Tank camo; Based on CSS colour \"olive\""
+        );
+    }
+}
@@ -0,0 +1,249 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+// Round trip known colours through the colour conversions and the mixer
+// to check that the arithmetic behaves on this platform (e.g. after upgrades).
+
+use std::rc::Rc;
+
+use pw_gix::{
+    glib::StaticType,
+    gtk::{self, prelude::*},
+    wrapper::*,
+};
+
+use crate::app_name;
+use crate::colour::*;
+use crate::colour_difference::{lab_to_rgb, rgb_to_lab};
+use crate::colour_mix::*;
+
+// Published CIE L*a*b* (D65) values for the sRGB primaries and white
+const LAB_REFERENCES: &[([f64; 3], [f64; 3])] = &[
+    ([1.0, 1.0, 1.0], [100.0, 0.0, 0.0]),
+    ([1.0, 0.0, 0.0], [53.24, 80.09, 67.20]),
+    ([0.0, 1.0, 0.0], [87.73, -86.18, 83.18]),
+    ([0.0, 0.0, 1.0], [32.30, 79.19, -107.86]),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiagnosticCheck {
+    pub name: &'static str,
+    pub max_error: f64,
+    pub tolerance: f64,
+}
+
+impl DiagnosticCheck {
+    pub fn passed(&self) -> bool {
+        self.max_error <= self.tolerance
+    }
+}
+
+// Primaries, secondaries, greys and a few in between
+pub fn reference_rgbs() -> Vec<RGB> {
+    let mut rgbs = vec![
+        RGB::RED,
+        RGB::GREEN,
+        RGB::BLUE,
+        RGB::CYAN,
+        RGB::MAGENTA,
+        RGB::YELLOW,
+        RGB::WHITE,
+        RGB::BLACK,
+    ];
+    for array in [
+        [0.5, 0.5, 0.5],
+        [0.2, 0.4, 0.6],
+        [0.8, 0.3, 0.1],
+        [0.1, 0.6, 0.3],
+        [0.9, 0.85, 0.7],
+    ]
+    .iter()
+    {
+        rgbs.push(RGB::from(*array));
+    }
+    rgbs
+}
+
+fn max_channel_error(rgb1: &RGB, rgb2: &RGB) -> f64 {
+    [CCI::Red, CCI::Green, CCI::Blue]
+        .iter()
+        .map(|cci| (rgb1[*cci] - rgb2[*cci]).abs())
+        .fold(0.0, f64::max)
+}
+
+fn max_error<I: Iterator<Item = f64>>(errors: I) -> f64 {
+    errors.fold(0.0, f64::max)
+}
+
+fn mixed_rgb(model: MixingModel, components: &[(RGB, u32)]) -> Option<RGB> {
+    let mut colour_mixer = ColourMixer::with_model(model);
+    for (rgb, parts) in components.iter() {
+        colour_mixer.add(&Colour::from(*rgb), *parts);
+    }
+    colour_mixer.get_colour().map(|colour| colour.rgb())
+}
+
+pub fn run_diagnostics() -> Vec<DiagnosticCheck> {
+    let rgbs = reference_rgbs();
+    let pairs: Vec<(RGB, RGB)> = rgbs
+        .iter()
+        .zip(rgbs.iter().rev())
+        .map(|(rgb1, rgb2)| (*rgb1, *rgb2))
+        .collect();
+    let mut checks = vec![];
+
+    checks.push(DiagnosticCheck {
+        name: "RGB \u{2192} HCV \u{2192} RGB",
+        max_error: max_error(rgbs.iter().map(|rgb| {
+            let (hue, chroma, value) = hexcone_hcv(rgb);
+            max_channel_error(rgb, &hexcone_rgb(hue, chroma, value))
+        })),
+        tolerance: 1.0e-9,
+    });
+    // the conversion matrices are only given to four decimal places
+    checks.push(DiagnosticCheck {
+        name: "RGB \u{2192} Lab \u{2192} RGB",
+        max_error: max_error(
+            rgbs.iter()
+                .map(|rgb| max_channel_error(rgb, &lab_to_rgb(&rgb_to_lab(rgb)))),
+        ),
+        tolerance: 1.0e-3,
+    });
+    checks.push(DiagnosticCheck {
+        name: "Lab reference values",
+        max_error: max_error(LAB_REFERENCES.iter().map(|(array, expected)| {
+            let lab = rgb_to_lab(&RGB::from(*array));
+            max_error(lab.iter().zip(expected.iter()).map(|(a, b)| (a - b).abs()))
+        })),
+        tolerance: 0.1,
+    });
+    checks.push(DiagnosticCheck {
+        name: "Mixing a colour with itself",
+        max_error: max_error(rgbs.iter().flat_map(|rgb| {
+            [MixingModel::SRGB, MixingModel::LinearLight]
+                .iter()
+                .map(|model| match mixed_rgb(*model, &[(*rgb, 3), (*rgb, 7)]) {
+                    Some(mix) => max_channel_error(rgb, &mix),
+                    None => 1.0,
+                })
+                .collect::<Vec<f64>>()
+        })),
+        tolerance: 1.0e-9,
+    });
+    checks.push(DiagnosticCheck {
+        name: "Mixing equal parts (sRGB)",
+        max_error: max_error(pairs.iter().map(|(rgb1, rgb2)| {
+            match mixed_rgb(MixingModel::SRGB, &[(*rgb1, 1), (*rgb2, 1)]) {
+                Some(mix) => max_channel_error(&lerp_rgb(rgb1, rgb2, 0.5), &mix),
+                None => 1.0,
+            }
+        })),
+        tolerance: 1.0e-9,
+    });
+    checks.push(DiagnosticCheck {
+        name: "Mixing equal parts (linear light)",
+        max_error: max_error(pairs.iter().map(|(rgb1, rgb2)| {
+            let mean = |cci: CCI| {
+                linear_to_srgb((srgb_to_linear(rgb1[cci]) + srgb_to_linear(rgb2[cci])) / 2.0)
+            };
+            let expected = RGB::from([mean(CCI::Red), mean(CCI::Green), mean(CCI::Blue)]);
+            match mixed_rgb(MixingModel::LinearLight, &[(*rgb1, 1), (*rgb2, 1)]) {
+                Some(mix) => max_channel_error(&expected, &mix),
+                None => 1.0,
+            }
+        })),
+        tolerance: 1.0e-9,
+    });
+    checks
+}
+
+pub fn diagnostics_summary(checks: &[DiagnosticCheck]) -> String {
+    let failed = checks.iter().filter(|check| !check.passed()).count();
+    if failed > 0 {
+        format!("{} of {} checks FAILED.", failed, checks.len())
+    } else {
+        format!("All {} checks passed.", checks.len())
+    }
+}
+
+// Run the checks and show the results (which are also returned)
+pub fn show_diagnostics<W: WidgetWrapper>(caller: &Rc<W>) -> Vec<DiagnosticCheck> {
+    let checks = run_diagnostics();
+    let title = format!("{}: Colour Diagnostics", app_name());
+    let dialog = caller.new_dialog_with_buttons(
+        Some(&title),
+        gtk::DialogFlags::DESTROY_WITH_PARENT,
+        &[("Close", gtk::ResponseType::Close)],
+    );
+    dialog.set_size_from_recollections("colour_diagnostics_dialog", (450, 250));
+
+    let list_store = gtk::ListStore::new(&[
+        String::static_type(),
+        String::static_type(),
+        String::static_type(),
+        String::static_type(),
+    ]);
+    for check in checks.iter() {
+        let result = if check.passed() { "OK" } else { "FAILED" };
+        list_store.insert_with_values(
+            None,
+            &[0, 1, 2, 3],
+            &[
+                &check.name,
+                &format!("{:.3e}", check.max_error),
+                &format!("{:.0e}", check.tolerance),
+                &result,
+            ],
+        );
+    }
+    let view = gtk::TreeView::with_model(&list_store);
+    view.set_headers_visible(true);
+    view.get_selection().set_mode(gtk::SelectionMode::None);
+    for (col_id, title) in ["Check", "Max Error", "Tolerance", ""].iter().enumerate() {
+        let col = gtk::TreeViewColumn::new();
+        col.set_title(title);
+        col.set_resizable(true);
+        let cell = gtk::CellRendererText::new();
+        col.pack_start(&cell, true);
+        col.add_attribute(&cell, "text", col_id as i32);
+        view.append_column(&col);
+    }
+    let adj: Option<&gtk::Adjustment> = None;
+    let scrolled_window = gtk::ScrolledWindow::new(adj, adj);
+    scrolled_window.add(&view);
+
+    let summary = diagnostics_summary(&checks);
+    let content_area = dialog.get_content_area();
+    content_area.pack_start(&gtk::Label::new(Some(&summary)), false, false, 0);
+    content_area.pack_start(&scrolled_window, true, true, 0);
+    content_area.show_all();
+    dialog.run();
+    unsafe { dialog.destroy() };
+    checks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diagnostics_pass() {
+        let checks = run_diagnostics();
+        for check in checks.iter() {
+            assert!(check.passed(), "{:?}", check);
+        }
+        assert_eq!(
+            diagnostics_summary(&checks),
+            format!("All {} checks passed.", checks.len())
+        );
+    }
+
+    #[test]
+    fn failures_are_reported() {
+        let checks = vec![DiagnosticCheck {
+            name: "broken",
+            max_error: 0.5,
+            tolerance: 0.1,
+        }];
+        assert_eq!(diagnostics_summary(&checks), "1 of 1 checks FAILED.");
+    }
+}
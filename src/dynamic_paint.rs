@@ -0,0 +1,572 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+// Paints whose characteristics are defined at run time by a schema (read
+// from a configuration file) rather than compiled in e.g.
+//
+//     PaintType: WoodStain
+//     Characteristic: sheen, Sheen, Sh.
+//         M: Matt
+//         S: Satin
+//         G: Gloss
+//
+// NB: the schema must be installed before any of these paints are read or
+// displayed and it shouldn't be changed thereafter.
+
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use regex::Regex;
+use serde_derive::*;
+
+use pw_gix::{
+    glib,
+    gtk::{self, prelude::*},
+    gtkx::tree_view_column::*,
+};
+
+use crate::basic_paint::*;
+use crate::characteristics::CharacteristicError;
+use crate::error::*;
+use crate::mixed_paint::mixer::*;
+use crate::model_paint::ModelPaintAttributes;
+use crate::paint_types::PaintTypeBundle;
+
+// Limited by the number of characteristics columns in the paint list stores
+pub const MAX_DYNAMIC_CHARACTERISTICS: usize = 5;
+// Values are stored as u8 indices starting at 1 (0 means unspecified)
+pub const MAX_CHARACTERISTIC_VALUES: usize = 254;
+
+#[derive(Debug, Clone)]
+pub struct CharacteristicSchema {
+    pub key: String,
+    pub label: String,
+    pub column_title: String,
+    // (abbreviation, description) pairs
+    pub values: Vec<(String, String)>,
+    value_re: Regex,
+}
+
+impl CharacteristicSchema {
+    pub fn new(key: &str, label: &str, column_title: &str) -> CharacteristicSchema {
+        let pattern = format!(r#"\b{}\s*=\s*"(?P<value>[^"]*)""#, regex::escape(key));
+        CharacteristicSchema {
+            key: key.to_string(),
+            label: label.to_string(),
+            column_title: column_title.to_string(),
+            values: vec![],
+            value_re: Regex::new(&pattern).unwrap(),
+        }
+    }
+
+    // Indices start at 1 (0 means unspecified)
+    pub fn index_of(&self, text: &str) -> Option<u8> {
+        self.values
+            .iter()
+            .position(|(abbrev, description)| abbrev == text || description == text)
+            .map(|index| index as u8 + 1)
+    }
+
+    pub fn value(&self, index: u8) -> Option<&(String, String)> {
+        if index == 0 {
+            None
+        } else {
+            self.values.get(index as usize - 1)
+        }
+    }
+
+    pub fn prompt(&self) -> String {
+        format!("{}:", self.label)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CharacteristicsSchema {
+    pub paint_type: String,
+    pub characteristics: Vec<CharacteristicSchema>,
+}
+
+const DEFAULT_PAINT_TYPE: &str = "DynamicPaint";
+
+impl Default for CharacteristicsSchema {
+    fn default() -> CharacteristicsSchema {
+        CharacteristicsSchema {
+            paint_type: DEFAULT_PAINT_TYPE.to_string(),
+            characteristics: vec![],
+        }
+    }
+}
+
+impl CharacteristicsSchema {
+    pub fn from_file(path: &Path) -> io::Result<CharacteristicsSchema> {
+        let text = fs::read_to_string(path)?;
+        CharacteristicsSchema::from_str(&text)
+            .map_err(|msg| io::Error::new(io::ErrorKind::InvalidData, msg))
+    }
+}
+
+impl FromStr for CharacteristicsSchema {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<CharacteristicsSchema, String> {
+        let mut paint_type: Option<String> = None;
+        let mut characteristics: Vec<CharacteristicSchema> = vec![];
+        for (index, line) in string.lines().enumerate() {
+            let line_number = index + 1;
+            let text = line.trim();
+            if text.len() == 0 || text.starts_with('#') {
+                continue;
+            }
+            if let Some(tail) = text.strip_prefix("PaintType:") {
+                paint_type = Some(tail.trim().to_string());
+            } else if let Some(tail) = text.strip_prefix("Characteristic:") {
+                let fields: Vec<&str> = tail.split(',').map(|field| field.trim()).collect();
+                if fields.len() != 3 || fields.iter().any(|field| field.len() == 0) {
+                    return Err(format!(
+                        "Line {}: expected \"Characteristic: key, label, column title\"",
+                        line_number
+                    ));
+                }
+                if !fields[0].chars().all(|c| c.is_alphanumeric() || c == '_') {
+                    return Err(format!(
+                        "Line {}: {}: malformed key",
+                        line_number, fields[0]
+                    ));
+                }
+                if characteristics.iter().any(|c| c.key == fields[0]) {
+                    return Err(format!(
+                        "Line {}: {}: duplicate key",
+                        line_number, fields[0]
+                    ));
+                }
+                if characteristics.len() == MAX_DYNAMIC_CHARACTERISTICS {
+                    return Err(format!(
+                        "Line {}: no more than {} characteristics allowed",
+                        line_number, MAX_DYNAMIC_CHARACTERISTICS
+                    ));
+                }
+                characteristics.push(CharacteristicSchema::new(fields[0], fields[1], fields[2]));
+            } else if line.starts_with(char::is_whitespace) {
+                let characteristic = match characteristics.last_mut() {
+                    Some(characteristic) => characteristic,
+                    None => {
+                        return Err(format!(
+                            "Line {}: value before any characteristic",
+                            line_number
+                        ))
+                    }
+                };
+                let mut parts = text.splitn(2, ':').map(|part| part.trim());
+                match (parts.next(), parts.next()) {
+                    (Some(abbrev), Some(description))
+                        if abbrev.len() > 0 && description.len() > 0 && !abbrev.contains('"') =>
+                    {
+                        if characteristic.values.len() == MAX_CHARACTERISTIC_VALUES {
+                            return Err(format!(
+                                "Line {}: no more than {} values allowed",
+                                line_number, MAX_CHARACTERISTIC_VALUES
+                            ));
+                        }
+                        if characteristic.index_of(abbrev).is_some() {
+                            return Err(format!(
+                                "Line {}: {}: duplicate value",
+                                line_number, abbrev
+                            ));
+                        }
+                        characteristic
+                            .values
+                            .push((abbrev.to_string(), description.to_string()));
+                    }
+                    _ => {
+                        return Err(format!(
+                            "Line {}: expected \"abbreviation: description\"",
+                            line_number
+                        ))
+                    }
+                }
+            } else {
+                return Err(format!("Line {}: {}: not understood", line_number, text));
+            }
+        }
+        let paint_type = match paint_type {
+            Some(paint_type) if paint_type.chars().all(|c| c.is_alphanumeric()) => paint_type,
+            Some(paint_type) => return Err(format!("{}: malformed paint type", paint_type)),
+            None => return Err("No \"PaintType:\" given".to_string()),
+        };
+        if let Some(characteristic) = characteristics.iter().find(|c| c.values.len() == 0) {
+            return Err(format!("{}: has no values", characteristic.key));
+        }
+        Ok(CharacteristicsSchema {
+            paint_type,
+            characteristics,
+        })
+    }
+}
+
+// GTK objects aren't thread safe so (like the paint type registry) each
+// thread has its own schema
+thread_local! {
+    static CHARACTERISTICS_SCHEMA: RefCell<Rc<CharacteristicsSchema>> =
+        RefCell::new(Rc::new(CharacteristicsSchema::default()));
+    // The installed schema's paint type (with the static lifetime that
+    // CharacteristicsInterface::paint_types() requires)
+    static INSTALLED_PAINT_TYPE: Cell<&'static str> = Cell::new(DEFAULT_PAINT_TYPE);
+}
+
+pub fn characteristics_schema() -> Rc<CharacteristicsSchema> {
+    CHARACTERISTICS_SCHEMA.with(|schema| schema.borrow().clone())
+}
+
+pub fn install_characteristics_schema(schema: CharacteristicsSchema) {
+    INSTALLED_PAINT_TYPE.with(|paint_type| {
+        // only leaked when it changes (which should be at most once)
+        if paint_type.get() != schema.paint_type {
+            paint_type.set(Box::leak(schema.paint_type.clone().into_boxed_str()));
+        }
+    });
+    CHARACTERISTICS_SCHEMA.with(|current| *current.borrow_mut() = Rc::new(schema))
+}
+
+pub fn installed_paint_type() -> &'static str {
+    INSTALLED_PAINT_TYPE.with(|paint_type| paint_type.get())
+}
+
+pub fn load_characteristics_schema(path: &Path) -> io::Result<()> {
+    install_characteristics_schema(CharacteristicsSchema::from_file(path)?);
+    Ok(())
+}
+
+// The index (from 1) of each characteristic's value in the schema
+#[derive(Debug, PartialEq, Hash, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct DynamicCharacteristics {
+    values: [u8; MAX_DYNAMIC_CHARACTERISTICS],
+}
+
+impl DynamicCharacteristics {
+    // The (key, abbreviation) of each specified characteristic
+    pub fn values(&self) -> Vec<(String, String)> {
+        let schema = characteristics_schema();
+        schema
+            .characteristics
+            .iter()
+            .zip(self.values.iter())
+            .filter_map(|(characteristic, index)| {
+                characteristic
+                    .value(*index)
+                    .map(|(abbrev, _)| (characteristic.key.clone(), abbrev.clone()))
+            })
+            .collect()
+    }
+
+    pub fn value_of(&self, key: &str) -> Option<String> {
+        self.values()
+            .into_iter()
+            .find(|(v_key, _)| v_key == key)
+            .map(|(_, abbrev)| abbrev)
+    }
+}
+
+impl CharacteristicsInterface for DynamicCharacteristics {
+    type Entry = DynamicCharacteristicsEntryCore;
+
+    fn tv_row_len() -> usize {
+        characteristics_schema().characteristics.len()
+    }
+
+    fn tv_columns(start_col_id: i32) -> Vec<gtk::TreeViewColumn> {
+        let cfw = 30;
+        characteristics_schema()
+            .characteristics
+            .iter()
+            .enumerate()
+            .map(|(i, characteristic)| {
+                simple_text_column(
+                    &characteristic.column_title,
+                    start_col_id + i as i32,
+                    start_col_id + i as i32,
+                    6,
+                    7,
+                    cfw,
+                    false,
+                )
+            })
+            .collect()
+    }
+
+    fn from_floats(floats: &Vec<f64>) -> Self {
+        let schema = characteristics_schema();
+        let mut values = [0_u8; MAX_DYNAMIC_CHARACTERISTICS];
+        for (i, characteristic) in schema.characteristics.iter().enumerate() {
+            let index = floats[i].round().max(0.0) as usize;
+            values[i] = index.min(characteristic.values.len()) as u8;
+        }
+        DynamicCharacteristics { values }
+    }
+
+    fn tv_rows(&self) -> Vec<glib::Value> {
+        let schema = characteristics_schema();
+        schema
+            .characteristics
+            .iter()
+            .zip(self.values.iter())
+            .map(
+                |(characteristic, index)| match characteristic.value(*index) {
+                    Some((abbrev, _)) => abbrev.to_value(),
+                    None => "".to_value(),
+                },
+            )
+            .collect()
+    }
+
+    fn gui_display_widget(&self) -> gtk::Box {
+        let vbox = gtk::Box::new(gtk::Orientation::Vertical, 1);
+        let schema = characteristics_schema();
+        for (characteristic, index) in schema.characteristics.iter().zip(self.values.iter()) {
+            if let Some((_, description)) = characteristic.value(*index) {
+                let text = format!("{} {}", characteristic.prompt(), description);
+                let label = gtk::Label::new(Some(text.as_str()));
+                vbox.pack_start(&label, false, false, 1);
+            }
+        }
+        vbox.show_all();
+        vbox
+    }
+
+    fn to_floats(&self) -> Vec<f64> {
+        let len = characteristics_schema().characteristics.len();
+        self.values[..len]
+            .iter()
+            .map(|index| *index as f64)
+            .collect()
+    }
+
+    fn from_str(
+        string: &str,
+    ) -> Result<DynamicCharacteristics, PaintError<DynamicCharacteristics>> {
+        let schema = characteristics_schema();
+        let mut values = [0_u8; MAX_DYNAMIC_CHARACTERISTICS];
+        for (i, characteristic) in schema.characteristics.iter().enumerate() {
+            let captures = characteristic
+                .value_re
+                .captures(string)
+                .ok_or(CharacteristicError::new(string))?;
+            let text = captures.name("value").map_or("", |m| m.as_str());
+            values[i] = characteristic
+                .index_of(text)
+                .ok_or(CharacteristicError::new(text))?;
+        }
+        Ok(DynamicCharacteristics { values })
+    }
+
+    fn paint_types() -> Vec<&'static str> {
+        vec![installed_paint_type()]
+    }
+}
+
+impl fmt::Display for DynamicCharacteristics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let items: Vec<String> = self
+            .values()
+            .iter()
+            .map(|(key, abbrev)| format!("{}=\"{}\"", key, abbrev))
+            .collect();
+        write!(f, "{}", items.join(", "))
+    }
+}
+
+pub struct DynamicCharacteristicsEntryCore {
+    grid: gtk::Grid,
+    combo_box_texts: Vec<gtk::ComboBoxText>,
+    changed_callbacks: RefCell<Vec<Box<dyn Fn()>>>,
+}
+
+impl DynamicCharacteristicsEntryCore {
+    fn inform_changed(&self) {
+        for callback in self.changed_callbacks.borrow().iter() {
+            callback();
+        }
+    }
+}
+
+impl CharacteristicsEntryInterface<DynamicCharacteristics> for DynamicCharacteristicsEntryCore {
+    fn create() -> Rc<DynamicCharacteristicsEntryCore> {
+        let schema = characteristics_schema();
+        let mut combo_box_texts: Vec<gtk::ComboBoxText> = vec![];
+        for characteristic in schema.characteristics.iter() {
+            let combo_box_text = gtk::ComboBoxText::new();
+            for (_, description) in characteristic.values.iter() {
+                combo_box_text.append_text(description);
+            }
+            combo_box_text.set_active(Some(0));
+            combo_box_text.set_hexpand(true);
+            combo_box_texts.push(combo_box_text);
+        }
+        let cei = Rc::new(DynamicCharacteristicsEntryCore {
+            grid: gtk::Grid::new(),
+            combo_box_texts,
+            changed_callbacks: RefCell::new(Vec::new()),
+        });
+        for (row, characteristic) in schema.characteristics.iter().enumerate() {
+            let combo_box_text = &cei.combo_box_texts[row];
+            let cei_c = cei.clone();
+            combo_box_text.connect_changed(move |_| cei_c.inform_changed());
+            let label = gtk::Label::new(Some(characteristic.prompt().as_str()));
+            label.set_halign(gtk::Align::End);
+            cei.grid.attach(&label, 0, row as i32, 1, 1);
+            cei.grid
+                .attach_next_to(combo_box_text, Some(&label), gtk::PositionType::Right, 1, 1);
+        }
+
+        cei.grid.show_all();
+        cei
+    }
+
+    fn pwo(&self) -> gtk::Grid {
+        self.grid.clone()
+    }
+
+    fn get_characteristics(&self) -> Option<DynamicCharacteristics> {
+        let mut values = [0_u8; MAX_DYNAMIC_CHARACTERISTICS];
+        for (i, combo_box_text) in self.combo_box_texts.iter().enumerate() {
+            values[i] = combo_box_text.get_active()? as u8 + 1;
+        }
+        Some(DynamicCharacteristics { values })
+    }
+
+    fn set_characteristics(&self, o_characteristics: Option<&DynamicCharacteristics>) {
+        for (i, combo_box_text) in self.combo_box_texts.iter().enumerate() {
+            match o_characteristics {
+                Some(characteristics) if characteristics.values[i] > 0 => {
+                    combo_box_text.set_active(Some(characteristics.values[i] as u32 - 1))
+                }
+                _ => combo_box_text.set_active(None),
+            }
+        }
+    }
+
+    fn connect_changed<F: 'static + Fn()>(&self, callback: F) {
+        self.changed_callbacks.borrow_mut().push(Box::new(callback))
+    }
+}
+
+pub struct DynamicPaintMixerConfig;
+
+impl MixerConfig for DynamicPaintMixerConfig {
+    fn mixing_mode() -> MixingMode {
+        MixingMode::MatchTarget
+    }
+
+    fn recollection_name_for(item_name: &str) -> String {
+        format!("dynamic_paint_mixer::{}", item_name)
+    }
+}
+
+// For registration with paint_types::register_paint_type() once the
+// schema has been installed
+pub struct DynamicPaintType;
+
+impl PaintTypeBundle for DynamicPaintType {
+    type Attributes = ModelPaintAttributes;
+    type Characteristics = DynamicCharacteristics;
+    type MixerConfig = DynamicPaintMixerConfig;
+
+    fn type_id() -> &'static str {
+        "dynamic_paint"
+    }
+
+    fn type_label() -> String {
+        characteristics_schema().paint_type.to_string()
+    }
+
+    fn type_description() -> String {
+        let labels: Vec<String> = characteristics_schema()
+            .characteristics
+            .iter()
+            .map(|characteristic| characteristic.label.to_lowercase())
+            .collect();
+        format!("Paints with {} (defined at run time)", labels.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WOOD_STAIN_SCHEMA: &str = "# Stains for furniture
+PaintType: WoodStain
+Characteristic: sheen, Sheen, Sh.
+    M: Matt
+    S: Satin
+    G: Gloss
+Characteristic: base, Base, Ba.
+    W: Water
+    O: Oil
+";
+
+    #[test]
+    fn dynamic_characteristics_driven_by_schema() {
+        let schema = CharacteristicsSchema::from_str(WOOD_STAIN_SCHEMA).unwrap();
+        assert_eq!(schema.paint_type, "WoodStain");
+        assert_eq!(schema.characteristics.len(), 2);
+        assert_eq!(schema.characteristics[0].index_of("Satin"), Some(2));
+        install_characteristics_schema(schema);
+        assert_eq!(DynamicCharacteristics::tv_row_len(), 2);
+        assert_eq!(DynamicCharacteristics::paint_types(), vec!["WoodStain"]);
+
+        let characteristics = DynamicCharacteristics::from_str(r#"base="O", sheen="S""#).unwrap();
+        assert_eq!(characteristics.value_of("sheen"), Some("S".to_string()));
+        assert_eq!(characteristics.to_string(), r#"sheen="S", base="O""#);
+        assert_eq!(characteristics.to_floats(), vec![2.0, 2.0]);
+        assert_eq!(
+            DynamicCharacteristics::from_floats(&characteristics.to_floats()),
+            characteristics
+        );
+        assert!(DynamicCharacteristics::from_str(r#"sheen="S""#).is_err());
+        assert!(DynamicCharacteristics::from_str(r#"sheen="X", base="O""#).is_err());
+
+        let spec = BasicPaintSpec::<DynamicCharacteristics>::from_str(
+            r#"WoodStain(name="Oak", rgb=RGB16(red=0x8000, green=0x6000, blue=0x2000), sheen="G", base="W", notes="")"#,
+        )
+        .unwrap();
+        assert_eq!(spec.characteristics.value_of("base"), Some("W".to_string()));
+        install_characteristics_schema(CharacteristicsSchema::default());
+    }
+
+    #[test]
+    fn malformed_schemas() {
+        assert!(CharacteristicsSchema::from_str("Characteristic: a, A, A.\n  X: Ex\n").is_err());
+        assert!(CharacteristicsSchema::from_str("PaintType: P\n  X: Ex\n").is_err());
+        assert!(CharacteristicsSchema::from_str("PaintType: P\nCharacteristic: a, A\n").is_err());
+        assert!(
+            CharacteristicsSchema::from_str("PaintType: P\nCharacteristic: a, A, A.\n").is_err()
+        );
+        assert!(CharacteristicsSchema::from_str(
+            "PaintType: P\nCharacteristic: a, A, A.\n  X: Ex\nCharacteristic: a, B, B.\n  Y: Why\n"
+        )
+        .is_err());
+        assert!(CharacteristicsSchema::from_str(
+            "PaintType: P\nCharacteristic: a, A, A.\n  X: Ex\n"
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn too_many_schema_values() {
+        let mut text = "PaintType: P\nCharacteristic: a, A, A.\n".to_string();
+        for i in 0..MAX_CHARACTERISTIC_VALUES {
+            text += &format!("  V{}: Value {}\n", i, i);
+        }
+        let schema = CharacteristicsSchema::from_str(&text).unwrap();
+        assert_eq!(
+            schema.characteristics[0].index_of(&format!("V{}", MAX_CHARACTERISTIC_VALUES - 1)),
+            Some(MAX_CHARACTERISTIC_VALUES as u8)
+        );
+        text += "  Extra: One too many\n";
+        let err = CharacteristicsSchema::from_str(&text).unwrap_err();
+        assert!(err.starts_with(&format!("Line {}:", MAX_CHARACTERISTIC_VALUES + 3)));
+    }
+}
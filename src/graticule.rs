@@ -1,16 +1,24 @@
 // Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
 
 use std::cell::{Cell, RefCell};
+use std::io;
+use std::path::Path;
 use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 use pw_gix::{
     cairo, gdk,
     glib::signal::SignalHandlerId,
     gtk::{self, prelude::*},
+    gtkx::dialog::*,
+    gtkx::menu::*,
+    recollections::{recall, remember},
+    wrapper::*,
 };
 
 use normalised_angles::Degrees;
 
+use crate::app_name;
 use crate::cairox::*;
 
 use crate::colour::*;
@@ -55,7 +63,397 @@ impl CurrentTargetShape {
     }
 }
 
+// IMAGE EXPORT
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum WheelImageFormat {
+    PNG,
+    SVG,
+}
+
+impl WheelImageFormat {
+    pub fn for_path(path: &Path) -> Option<WheelImageFormat> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        match extension.as_str() {
+            "png" => Some(WheelImageFormat::PNG),
+            "svg" => Some(WheelImageFormat::SVG),
+            _ => None,
+        }
+    }
+}
+
+const WHEEL_IMAGE_SIZE_RECOLLECTION: &str = "graticule::export_image_size";
+const WHEEL_IMAGE_FILE_RECOLLECTION: &str = "graticule::last_export_image_file";
+const DEFAULT_WHEEL_IMAGE_SIZE: i32 = 1024;
+
+fn ask_wheel_image_size<W: WidgetWrapper>(caller: &W) -> Option<i32> {
+    let title = format!("{}: Image Size", app_name());
+    let dialog = caller.new_dialog_with_buttons(
+        Some(&title),
+        gtk::DialogFlags::DESTROY_WITH_PARENT,
+        CANCEL_OK_BUTTONS,
+    );
+    let size_spin = gtk::SpinButton::with_range(64.0, 8192.0, 64.0);
+    let size = recall(WHEEL_IMAGE_SIZE_RECOLLECTION)
+        .and_then(|text| text.parse::<i32>().ok())
+        .unwrap_or(DEFAULT_WHEEL_IMAGE_SIZE);
+    size_spin.set_value(size as f64);
+    let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 2);
+    hbox.pack_start(
+        &gtk::Label::new(Some("Width and height (pixels):")),
+        false,
+        false,
+        0,
+    );
+    hbox.pack_start(&size_spin, true, true, 0);
+    let content_area = dialog.get_content_area();
+    content_area.pack_start(&hbox, false, false, 0);
+    content_area.show_all();
+    let mut o_size: Option<i32> = None;
+    if gtk::ResponseType::from(dialog.run()) == gtk::ResponseType::Ok {
+        let size = size_spin.get_value_as_int();
+        remember(WHEEL_IMAGE_SIZE_RECOLLECTION, &size.to_string());
+        o_size = Some(size);
+    }
+    unsafe { dialog.destroy() };
+    o_size
+}
+
+// Ask where (and how big) to save an image of the wheel and save it
+pub fn export_wheel_image<W: WidgetWrapper>(caller: &W, graticule: &GraticuleCore) {
+    let o_last_file = recall(WHEEL_IMAGE_FILE_RECOLLECTION);
+    let last_file = if let Some(ref text) = o_last_file {
+        Some(text.as_str())
+    } else {
+        None
+    };
+    if let Some(path) = caller.ask_file_path(Some("Image File (.png or .svg):"), last_file, false) {
+        if let Some(size) = ask_wheel_image_size(caller) {
+            match graticule.render_to_file(&path, size) {
+                Ok(()) => remember(WHEEL_IMAGE_FILE_RECOLLECTION, &path.to_string_lossy()),
+                Err(err) => caller.report_error("Failed to export image", &err),
+            }
+        }
+    }
+}
+
+// COLOUR VISION DEFICIENCY SIMULATION
+const NORMAL_VISION_MENU_KEY: &str = "normal_vision";
+
+// One item per deficiency (plus one to turn simulation off) that passes
+// the chosen simulation to "callback"
+pub fn append_vision_deficiency_items<F>(menu: &WrappedMenu, callback: F)
+where
+    F: Fn(Option<VisionDeficiency>) + 'static,
+{
+    let callback = Rc::new(callback);
+    let callback_c = callback.clone();
+    menu.append_item(
+        NORMAL_VISION_MENU_KEY,
+        "Normal Vision",
+        "Show the colours as they are (i.e. stop simulating a vision deficiency)",
+    )
+    .connect_activate(move |_| callback_c(None));
+    for deficiency in VisionDeficiency::values() {
+        let callback_c = callback.clone();
+        menu.append_item(
+            &deficiency.menu_key(),
+            deficiency.label(),
+            deficiency.tooltip_text(),
+        )
+        .connect_activate(move |_| callback_c(Some(deficiency)));
+    }
+}
+
+// The item for the current simulation is disabled
+pub fn update_vision_deficiency_items(menu: &WrappedMenu, current: Option<VisionDeficiency>) {
+    menu.set_sensitivities(current.is_some(), &[NORMAL_VISION_MENU_KEY]);
+    for deficiency in VisionDeficiency::values() {
+        menu.set_sensitivities(
+            current != Some(deficiency),
+            &[deficiency.menu_key().as_str()],
+        );
+    }
+}
+
+pub fn set_vision_deficiency_items_visibility(menu: &WrappedMenu, visible: bool) {
+    menu.set_visibilities(visible, &[NORMAL_VISION_MENU_KEY]);
+    for deficiency in VisionDeficiency::values() {
+        menu.set_visibilities(visible, &[deficiency.menu_key().as_str()]);
+    }
+}
+
+// So that users don't mistake the simulated colours for the real ones
+pub fn draw_vision_deficiency_note(
+    cairo_context: &cairo::Context,
+    o_deficiency: Option<VisionDeficiency>,
+    position: Point,
+) {
+    if let Some(deficiency) = o_deficiency {
+        let text = deficiency.label().replace("Simulate", "Simulating");
+        cairo_context.set_font_size(LEGEND_FONT_SIZE);
+        let width = text.len() as f64 * LEGEND_FONT_SIZE * 0.6 + LEGEND_MARGIN * 2.0;
+        let height = LEGEND_FONT_SIZE + LEGEND_MARGIN * 2.0;
+        cairo_context.set_source_rgba(0.0, 0.0, 0.0, 0.6);
+        cairo_context.rectangle(position.0, position.1, width, height);
+        cairo_context.fill();
+        cairo_context.set_source_rgb(1.0, 1.0, 1.0);
+        cairo_context.move_to(
+            position.0 + LEGEND_MARGIN,
+            position.1 + LEGEND_MARGIN + LEGEND_FONT_SIZE * 0.8,
+        );
+        cairo_context.show_text(&text);
+    }
+}
+
+// KEYBOARD FOCUS
+const FOCUS_RING_RADIUS: f64 = 0.05;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FocusMove {
+    Next,
+    Previous,
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl FocusMove {
+    pub fn from_key(key: &gdk::keys::Key) -> Option<FocusMove> {
+        use gdk::keys::constants;
+        if *key == constants::Tab {
+            Some(FocusMove::Next)
+        } else if *key == constants::ISO_Left_Tab {
+            Some(FocusMove::Previous)
+        } else if *key == constants::Left || *key == constants::KP_Left {
+            Some(FocusMove::Left)
+        } else if *key == constants::Right || *key == constants::KP_Right {
+            Some(FocusMove::Right)
+        } else if *key == constants::Up || *key == constants::KP_Up {
+            Some(FocusMove::Up)
+        } else if *key == constants::Down || *key == constants::KP_Down {
+            Some(FocusMove::Down)
+        } else {
+            None
+        }
+    }
+
+    fn direction(self) -> Option<Point> {
+        match self {
+            FocusMove::Left => Some(Point(-1.0, 0.0)),
+            FocusMove::Right => Some(Point(1.0, 0.0)),
+            FocusMove::Up => Some(Point(0.0, -1.0)),
+            FocusMove::Down => Some(Point(0.0, 1.0)),
+            _ => None,
+        }
+    }
+}
+
+pub fn is_activate_key(event: &gdk::EventKey) -> bool {
+    let key = event.get_keyval();
+    key == gdk::keys::constants::Return
+        || key == gdk::keys::constants::KP_Enter
+        || key == gdk::keys::constants::space
+}
+
+// The Menu key or (as is the convention) Shift+F10
+pub fn is_menu_key(event: &gdk::EventKey) -> bool {
+    let key = event.get_keyval();
+    key == gdk::keys::constants::Menu
+        || (key == gdk::keys::constants::F10
+            && event.get_state().contains(gdk::ModifierType::SHIFT_MASK))
+}
+
+// KEYBOARD ZOOM
+const ZOOM_KEY_FACTOR: f64 = 1.25;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ZoomKey {
+    In,
+    Out,
+    Reset,
+}
+
+impl ZoomKey {
+    pub fn from_key(key: &gdk::keys::Key) -> Option<ZoomKey> {
+        use gdk::keys::constants;
+        if *key == constants::plus || *key == constants::equal || *key == constants::KP_Add {
+            Some(ZoomKey::In)
+        } else if *key == constants::minus || *key == constants::KP_Subtract {
+            Some(ZoomKey::Out)
+        } else if *key == constants::_0 || *key == constants::KP_0 {
+            Some(ZoomKey::Reset)
+        } else {
+            None
+        }
+    }
+}
+
+// Tab order goes clockwise around the wheel (starting from red) and
+// outwards when shapes share a hue
+fn tab_order(points: &[Point]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..points.len()).collect();
+    let key = |point: &Point| {
+        let angle = point.y().atan2(point.x());
+        let angle = if angle < 0.0 {
+            angle + 2.0 * std::f64::consts::PI
+        } else {
+            angle
+        };
+        (angle, point.hypot())
+    };
+    order.sort_by(|a, b| {
+        key(&points[*a])
+            .partial_cmp(&key(&points[*b]))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    order
+}
+
+// The index of the point that the focus should move to.  Tabbing past
+// either end of the tab order yields None so that the focus can leave
+// the wheel but arrow keys leave the focus where it is when there is
+// nothing further in the requested direction.
+pub fn next_focus_index(
+    points: &[Point],
+    current: Option<usize>,
+    focus_move: FocusMove,
+) -> Option<usize> {
+    let order = tab_order(points);
+    let current = match current {
+        Some(index) if index < points.len() => index,
+        _ => {
+            return match focus_move {
+                FocusMove::Previous => order.last().cloned(),
+                _ => order.first().cloned(),
+            }
+        }
+    };
+    if let Some(direction) = focus_move.direction() {
+        let from = points[current];
+        let mut best: Option<(usize, f64)> = None;
+        for (index, point) in points.iter().enumerate() {
+            let delta = *point - from;
+            let along = delta.x() * direction.x() + delta.y() * direction.y();
+            if index == current || along <= 0.0 {
+                continue;
+            }
+            let across = (delta.x() * direction.y() - delta.y() * direction.x()).abs();
+            let cost = along + 2.0 * across;
+            if best.map_or(true, |(_, best_cost)| cost < best_cost) {
+                best = Some((index, cost));
+            }
+        }
+        Some(best.map_or(current, |(index, _)| index))
+    } else {
+        let posn = order.iter().position(|index| *index == current)?;
+        match focus_move {
+            FocusMove::Previous if posn > 0 => Some(order[posn - 1]),
+            FocusMove::Next if posn + 1 < order.len() => Some(order[posn + 1]),
+            _ => None,
+        }
+    }
+}
+
+// TOOLTIP THROTTLING
+// GTK asks for tooltips on every pointer movement so the (expensive)
+// hit test is only redone when the pointer has moved appreciably or,
+// while it keeps moving, at most once every TOOLTIP_THROTTLE_MILLIS
+// provided it's still within TOOLTIP_THROTTLE_PIXELS of the last test.
+const TOOLTIP_THROTTLE_MILLIS: u64 = 50;
+const TOOLTIP_THROTTLE_PIXELS: f64 = 6.0;
+const TOOLTIP_STILL_PIXELS: f64 = 2.0;
+
+struct TooltipQuery {
+    raw_xy: Point,
+    when: Instant,
+    text: Option<String>,
+}
+
+impl TooltipQuery {
+    fn is_reusable_for(&self, raw_xy: Point) -> bool {
+        let moved = (self.raw_xy - raw_xy).hypot();
+        moved < TOOLTIP_STILL_PIXELS
+            || (moved < TOOLTIP_THROTTLE_PIXELS
+                && self.when.elapsed() < Duration::from_millis(TOOLTIP_THROTTLE_MILLIS))
+    }
+}
+
+// GRATICULE STYLE
+// How big shapes are drawn: "shape_scale" multiplies their nominal size
+// (which is relative to the wheel's size), "min_shape_pixels" is the
+// smallest side length (in pixels) they'll be drawn with when the wheel
+// is small and "emphasis_scale" enlarges the chosen and focused shapes.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct GraticuleStyle {
+    pub shape_scale: f64,
+    pub min_shape_pixels: f64,
+    pub emphasis_scale: f64,
+}
+
+impl Default for GraticuleStyle {
+    fn default() -> GraticuleStyle {
+        GraticuleStyle {
+            shape_scale: 1.0,
+            min_shape_pixels: 6.0,
+            emphasis_scale: 1.5,
+        }
+    }
+}
+
+fn recall_f64(key: &str, default: f64) -> f64 {
+    recall(key)
+        .and_then(|text| text.parse::<f64>().ok())
+        .unwrap_or(default)
+}
+
+impl GraticuleStyle {
+    pub fn recall() -> GraticuleStyle {
+        let default = GraticuleStyle::default();
+        GraticuleStyle {
+            shape_scale: recall_f64("graticule::shape_scale", default.shape_scale),
+            min_shape_pixels: recall_f64("graticule::min_shape_pixels", default.min_shape_pixels),
+            emphasis_scale: recall_f64("graticule::emphasis_scale", default.emphasis_scale),
+        }
+    }
+
+    pub fn remember(&self) {
+        remember("graticule::shape_scale", &self.shape_scale.to_string());
+        remember(
+            "graticule::min_shape_pixels",
+            &self.min_shape_pixels.to_string(),
+        );
+        remember(
+            "graticule::emphasis_scale",
+            &self.emphasis_scale.to_string(),
+        );
+    }
+
+    // The drawn size of a shape dimension whose nominal size (in pixels)
+    // is "nominal" and which is "fraction" of the shape's side length
+    pub fn shape_size(&self, nominal: f64, fraction: f64, emphasised: bool) -> f64 {
+        let size = (nominal * self.shape_scale).max(self.min_shape_pixels * fraction);
+        if emphasised {
+            size * self.emphasis_scale
+        } else {
+            size
+        }
+    }
+}
+
+// LEGEND
+// Explains the wheel's marker shapes in its bottom left corner
+const LEGEND_MARGIN: f64 = 4.0;
+const LEGEND_FONT_SIZE: f64 = 11.0;
+const LEGEND_ROW_HEIGHT: f64 = 16.0;
+const LEGEND_MARKER_SIDE: f64 = 9.0;
+const SHOW_LEGEND_RECOLLECTION: &str = "graticule::show_legend";
+
 // GRATICULE
+const OVERVIEW_MARGIN: f64 = 4.0;
+// Pointer movement (in pixels) between press and release still counted as a click
+const CLICK_SLOP: f64 = 4.0;
+
 pub struct GraticuleCore {
     drawing_area: gtk::DrawingArea,
     attr: ScalarAttribute,
@@ -68,7 +466,16 @@ pub struct GraticuleCore {
     current_target: RefCell<Option<CurrentTargetShape>>,
     last_xy: Cell<Point>,
     motion_enabled: Cell<bool>,
+    press_xy: Cell<Option<Point>>,
     draw_callbacks: RefCell<Vec<Box<dyn Fn(&GraticuleCore, &cairo::Context)>>>,
+    clicked_callbacks: RefCell<Vec<Box<dyn Fn(&GraticuleCore, Point) -> bool>>>,
+    focused_xy: Cell<Option<Point>>,
+    chosen_xy: Cell<Option<Point>>,
+    style: Cell<GraticuleStyle>,
+    legend_entries: RefCell<Vec<(ShapeType, String)>>,
+    show_legend: Cell<bool>,
+    vision_deficiency: Cell<Option<VisionDeficiency>>,
+    last_tooltip_query: RefCell<Option<TooltipQuery>>,
 }
 
 impl GeometryInterface for GraticuleCore {
@@ -83,6 +490,18 @@ impl GeometryInterface for GraticuleCore {
     fn scaled(&self, value: f64) -> f64 {
         value * self.scaled_one.get()
     }
+
+    fn reverse_scaled(&self, length: f64) -> f64 {
+        length / self.radius.get()
+    }
+
+    fn shape_scaled(&self, value: f64, xy: Point) -> f64 {
+        let is_at = |o_point: Option<Point>| o_point.map_or(false, |p| (p - xy).hypot() < 1.0e-9);
+        let emphasised = is_at(self.chosen_xy.get()) || is_at(self.focused_xy.get());
+        self.style
+            .get()
+            .shape_size(self.scaled(value), value / SHAPE_SIDE, emphasised)
+    }
 }
 
 pub trait GraticuleInterface {
@@ -98,6 +517,96 @@ impl GraticuleCore {
         self.drawing_area.clone()
     }
 
+    pub fn style(&self) -> GraticuleStyle {
+        self.style.get()
+    }
+
+    pub fn set_style(&self, style: GraticuleStyle) {
+        self.style.set(style);
+        self.queue_draw();
+    }
+
+    // The wheel's owner describes the shapes that it draws
+    pub fn set_legend_entries(&self, entries: &[(ShapeType, &str)]) {
+        *self.legend_entries.borrow_mut() = entries
+            .iter()
+            .map(|(shape_type, text)| (*shape_type, text.to_string()))
+            .collect();
+        self.queue_draw();
+    }
+
+    pub fn legend_is_shown(&self) -> bool {
+        self.show_legend.get()
+    }
+
+    pub fn set_show_legend(&self, show_legend: bool) {
+        self.show_legend.set(show_legend);
+        remember(SHOW_LEGEND_RECOLLECTION, &show_legend.to_string());
+        self.queue_draw();
+    }
+
+    pub fn vision_deficiency(&self) -> Option<VisionDeficiency> {
+        self.vision_deficiency.get()
+    }
+
+    // NB: only the on screen view is simulated (not exported images)
+    pub fn set_vision_deficiency(&self, o_deficiency: Option<VisionDeficiency>) {
+        self.vision_deficiency.set(o_deficiency);
+        self.queue_draw();
+    }
+
+    fn legend_rows(&self) -> Vec<(Option<ShapeType>, String)> {
+        let mut rows: Vec<(Option<ShapeType>, String)> = self
+            .legend_entries
+            .borrow()
+            .iter()
+            .map(|(shape_type, text)| (Some(*shape_type), text.clone()))
+            .collect();
+        rows.push((Some(ShapeType::BackSight), "Current Target".to_string()));
+        rows.push((None, format!("Radius: {}", self.attr)));
+        rows
+    }
+
+    fn draw_legend(&self, cairo_context: &cairo::Context) {
+        let rows = self.legend_rows();
+        let dh = self.raw_centre.get().1 * 2.0;
+        let max_chars = rows.iter().map(|(_, text)| text.len()).max().unwrap_or(0);
+        // cairo's toy text API is good enough for an estimate of the width
+        let width = LEGEND_ROW_HEIGHT + max_chars as f64 * LEGEND_FONT_SIZE * 0.6 + LEGEND_MARGIN;
+        let height = rows.len() as f64 * LEGEND_ROW_HEIGHT + LEGEND_MARGIN;
+        let corner = Point(LEGEND_MARGIN, dh - height - LEGEND_MARGIN);
+        cairo_context.set_source_rgba(0.0, 0.0, 0.0, 0.6);
+        cairo_context.rectangle(corner.0, corner.1, width, height);
+        cairo_context.fill();
+        cairo_context.set_font_size(LEGEND_FONT_SIZE);
+        cairo_context.set_line_width(1.0);
+        for (i, (o_shape_type, text)) in rows.iter().enumerate() {
+            let row_centre = corner.1 + LEGEND_MARGIN / 2.0 + (i as f64 + 0.5) * LEGEND_ROW_HEIGHT;
+            let marker_point = Point(corner.0 + LEGEND_ROW_HEIGHT / 2.0 + 2.0, row_centre);
+            if let Some(shape_type) = o_shape_type {
+                draw_shape_at(
+                    cairo_context,
+                    *shape_type,
+                    marker_point,
+                    LEGEND_MARKER_SIDE,
+                    &RGB::from([0.8, 0.8, 0.8]),
+                );
+            }
+            cairo_context.set_source_rgb(1.0, 1.0, 1.0);
+            cairo_context.move_to(
+                corner.0 + LEGEND_ROW_HEIGHT + 2.0,
+                row_centre + LEGEND_FONT_SIZE / 3.0,
+            );
+            cairo_context.show_text(text);
+        }
+    }
+
+    // The shape at "xy" (if any) is drawn emphasised
+    pub fn set_chosen_xy(&self, o_xy: Option<Point>) {
+        self.chosen_xy.set(o_xy);
+        self.queue_draw();
+    }
+
     fn update_drawing_area(&self) {
         let dw = self.drawing_area.get_allocated_width() as f64;
         let dh = self.drawing_area.get_allocated_height() as f64;
@@ -122,6 +631,74 @@ impl GraticuleCore {
         self.radius.set(self.zoom.get() * self.scaled_one.get());
     }
 
+    fn overview_is_visible(&self) -> bool {
+        let offset = self.offset.get();
+        self.zoom.get() > 1.0 || offset.0 != 0.0 || offset.1 != 0.0
+    }
+
+    // Returns the bounding square (top left corner and side length) of the
+    // overview inset along with the centre and radius of its wheel
+    fn overview_geometry(&self) -> (Point, f64, Point, f64) {
+        let dw = self.drawing_area.get_allocated_width() as f64;
+        let dh = self.drawing_area.get_allocated_height() as f64;
+        let side = dw.min(dh) / 4.0;
+        let corner = Point(dw - side - OVERVIEW_MARGIN, OVERVIEW_MARGIN);
+        (corner, side, corner + Point(side, side) / 2.0, side / 2.2)
+    }
+
+    fn overview_contains(&self, point: Point) -> bool {
+        let (corner, side, _, _) = self.overview_geometry();
+        point.0 >= corner.0
+            && point.0 <= corner.0 + side
+            && point.1 >= corner.1
+            && point.1 <= corner.1 + side
+    }
+
+    fn centre_view_on_overview_point(&self, point: Point) {
+        let (_, _, o_centre, o_radius) = self.overview_geometry();
+        let wheel_point = (point - o_centre) / o_radius;
+        self.offset.set(wheel_point * -self.radius.get());
+        self.centre.set(self.raw_centre.get() + self.offset.get());
+    }
+
+    fn draw_overview(&self, cairo_context: &cairo::Context) {
+        let (corner, side, o_centre, o_radius) = self.overview_geometry();
+        cairo_context.save();
+        cairo_context.rectangle(corner.0, corner.1, side, side);
+        cairo_context.clip();
+        cairo_context.set_source_rgb(0.4, 0.4, 0.4);
+        cairo_context.paint();
+        cairo_context.set_line_width(1.0);
+        cairo_context.set_source_rgb(0.75, 0.75, 0.75);
+        cairo_context.draw_circle(o_centre, o_radius, false);
+        for i in 0..6 {
+            let angle = Degrees::DEG_60 * i;
+            let rgb = Hue::from(angle).max_chroma_rgb();
+            let g_angle: normalised_angles::Angle<f64> = angle.into();
+            cairo_context.set_source_colour_rgb(&rgb);
+            let eol = o_centre + Point::from((g_angle, 1.0)) * o_radius;
+            cairo_context.draw_line(o_centre, eol);
+        }
+        // the part of the wheel currently visible in the main view
+        let dw = self.drawing_area.get_allocated_width() as f64;
+        let dh = self.drawing_area.get_allocated_height() as f64;
+        let top_left = o_centre + self.reverse_transform(Point(0.0, 0.0)) * o_radius;
+        let bottom_right = o_centre + self.reverse_transform(Point(dw, dh)) * o_radius;
+        cairo_context.set_source_rgb(1.0, 1.0, 1.0);
+        cairo_context.rectangle(
+            top_left.0,
+            top_left.1,
+            bottom_right.0 - top_left.0,
+            bottom_right.1 - top_left.1,
+        );
+        cairo_context.stroke();
+        cairo_context.restore();
+        cairo_context.set_source_rgb(0.0, 0.0, 0.0);
+        cairo_context.set_line_width(1.0);
+        cairo_context.rectangle(corner.0, corner.1, side, side);
+        cairo_context.stroke();
+    }
+
     fn decr_zoom(&self) {
         let new_zoom = self.zoom.get() - 0.025;
         self.set_zoom(new_zoom)
@@ -132,7 +709,41 @@ impl GraticuleCore {
         self.set_zoom(new_zoom)
     }
 
+    // Keyboard zooming takes bigger steps than the scroll wheel
+    fn zoom_by_key(&self, zoom_key: ZoomKey) {
+        match zoom_key {
+            ZoomKey::In => self.set_zoom(self.zoom.get() * ZOOM_KEY_FACTOR),
+            ZoomKey::Out => self.set_zoom(self.zoom.get() / ZOOM_KEY_FACTOR),
+            ZoomKey::Reset => {
+                self.set_zoom(1.0);
+                self.offset.set(Point(0.0, 0.0));
+                self.centre.set(self.raw_centre.get());
+            }
+        }
+    }
+
     fn draw(&self, cairo_context: &cairo::Context) {
+        // what's under the pointer may have changed
+        self.forget_tooltip();
+        let o_deficiency = self.vision_deficiency.get();
+        with_vision_deficiency(o_deficiency, || {
+            self.draw_wheel(cairo_context);
+            if self.drawing_area.has_focus() {
+                self.draw_focus_ring(cairo_context);
+            }
+            if self.overview_is_visible() {
+                self.draw_overview(cairo_context);
+            }
+        });
+        draw_vision_deficiency_note(
+            cairo_context,
+            o_deficiency,
+            Point(LEGEND_MARGIN, LEGEND_MARGIN),
+        );
+    }
+
+    // Everything except the interactive decorations (focus ring and overview)
+    fn draw_wheel(&self, cairo_context: &cairo::Context) {
         cairo_context.set_source_rgb(0.5, 0.5, 0.5);
         cairo_context.paint();
 
@@ -149,7 +760,7 @@ impl GraticuleCore {
             let hue = Hue::from(angle);
             let g_angle: normalised_angles::Angle<f64> = angle.into();
             let rgb = hue.max_chroma_rgb();
-            cairo_context.set_source_rgb(rgb[CCI::Red], rgb[CCI::Green], rgb[CCI::Blue]);
+            cairo_context.set_source_colour_rgb(&rgb);
             let eol = self.transform(Point::from((g_angle, 1.0)));
             cairo_context.draw_line(self.centre.get(), eol);
             cairo_context.stroke();
@@ -161,16 +772,192 @@ impl GraticuleCore {
         if let Some(ref current_target) = *self.current_target.borrow() {
             current_target.draw(self, cairo_context);
         }
+        if self.show_legend.get() {
+            self.draw_legend(cairo_context);
+        }
+    }
+
+    // Draw the whole (unzoomed) wheel as it would appear in a square
+    // window of the given size without disturbing the on screen view
+    fn draw_wheel_at_size(&self, cairo_context: &cairo::Context, size: f64) {
+        let saved = (
+            self.raw_centre.get(),
+            self.offset.get(),
+            self.scaled_one.get(),
+            self.zoom.get(),
+        );
+        self.raw_centre.set(Point(size, size) / 2.0);
+        self.offset.set(Point(0.0, 0.0));
+        self.centre.set(self.raw_centre.get());
+        self.scaled_one.set(size / 2.2);
+        self.zoom.set(1.0);
+        self.radius.set(self.scaled_one.get());
+        self.draw_wheel(cairo_context);
+        let (raw_centre, offset, scaled_one, zoom) = saved;
+        self.raw_centre.set(raw_centre);
+        self.offset.set(offset);
+        self.centre.set(raw_centre + offset);
+        self.scaled_one.set(scaled_one);
+        self.zoom.set(zoom);
+        self.radius.set(zoom * scaled_one);
+    }
+
+    // The format is chosen by the file's extension
+    pub fn render_to_file(&self, path: &Path, size: i32) -> io::Result<()> {
+        match WheelImageFormat::for_path(path) {
+            Some(WheelImageFormat::PNG) => self.render_to_png(path, size),
+            Some(WheelImageFormat::SVG) => self.render_to_svg(path, size),
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{:?}: expected a \".png\" or \".svg\" file", path),
+            )),
+        }
+    }
+
+    fn render_to_png(&self, path: &Path, size: i32) -> io::Result<()> {
+        let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, size, size)
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, format!("{:?}", err)))?;
+        let cairo_context = cairo::Context::new(&surface);
+        self.draw_wheel_at_size(&cairo_context, size as f64);
+        drop(cairo_context);
+        let pixbuf = gdk::pixbuf_get_from_surface(&surface, 0, 0, size, size)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "failed to convert the image"))?;
+        pixbuf
+            .savev(path, "png", &[])
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))
+    }
+
+    #[cfg(feature = "svg_export")]
+    fn render_to_svg(&self, path: &Path, size: i32) -> io::Result<()> {
+        let surface = cairo::SvgSurface::new(size as f64, size as f64, Some(path))
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, format!("{:?}", err)))?;
+        let cairo_context = cairo::Context::new(&surface);
+        self.draw_wheel_at_size(&cairo_context, size as f64);
+        drop(cairo_context);
+        surface.finish();
+        Ok(())
+    }
+
+    #[cfg(not(feature = "svg_export"))]
+    fn render_to_svg(&self, path: &Path, _size: i32) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("{:?}: SVG export requires the \"svg_export\" feature", path),
+        ))
+    }
+
+    fn draw_focus_ring(&self, cairo_context: &cairo::Context) {
+        if let Some(xy) = self.focused_xy.get() {
+            let point = self.transform(xy);
+            let radius = self.scaled(FOCUS_RING_RADIUS);
+            cairo_context.set_line_width(3.0);
+            cairo_context.set_source_rgb(1.0, 1.0, 1.0);
+            cairo_context.draw_circle(point, radius, false);
+            cairo_context.set_line_width(1.5);
+            cairo_context.set_dash(&[4.0, 2.0], 0.0);
+            cairo_context.set_source_rgb(0.0, 0.0, 0.0);
+            cairo_context.draw_circle(point, radius, false);
+            cairo_context.set_dash(&[], 0.0);
+        }
+    }
+
+    // Which (if any) of the points currently has the keyboard focus
+    pub fn focused_index(&self, points: &[Point]) -> Option<usize> {
+        let xy = self.focused_xy.get()?;
+        points
+            .iter()
+            .position(|point| (*point - xy).hypot() < 1.0e-9)
+    }
+
+    pub fn move_focus(&self, points: &[Point], focus_move: FocusMove) -> Option<usize> {
+        let current = self.focused_index(points);
+        let o_index = next_focus_index(points, current, focus_move);
+        self.focused_xy.set(o_index.map(|index| points[index]));
+        self.queue_draw();
+        o_index
+    }
+
+    pub fn clear_focus(&self) {
+        self.focused_xy.set(None);
+        self.queue_draw();
+    }
+
+    // Pop up a menu next to the focused shape (or in the middle if there isn't one)
+    pub fn popup_menu_at_focus(&self, menu: &gtk::Menu) {
+        if let Some(window) = self.drawing_area.get_window() {
+            let point = match self.focused_xy.get() {
+                Some(xy) => self.transform(xy),
+                None => self.raw_centre.get(),
+            };
+            let rectangle = gdk::Rectangle {
+                x: point.x() as i32,
+                y: point.y() as i32,
+                width: 1,
+                height: 1,
+            };
+            menu.popup_at_rect(
+                &window,
+                &rectangle,
+                gdk::Gravity::SouthEast,
+                gdk::Gravity::NorthWest,
+                None,
+            );
+        }
     }
 
     pub fn queue_draw(&self) {
         self.drawing_area.queue_draw()
     }
 
+    // The tooltip text for the shape (if any) at "raw_xy" with the hit
+    // test ("text_at") throttled as described above.  Anything that
+    // changes what a tooltip would say without causing the wheel to be
+    // redrawn should call forget_tooltip().
+    pub fn throttled_tooltip_text<F: Fn(Point) -> Option<String>>(
+        &self,
+        raw_xy: Point,
+        text_at: F,
+    ) -> Option<String> {
+        if let Some(ref query) = *self.last_tooltip_query.borrow() {
+            if query.is_reusable_for(raw_xy) {
+                return query.text.clone();
+            }
+        }
+        let text = text_at(raw_xy);
+        *self.last_tooltip_query.borrow_mut() = Some(TooltipQuery {
+            raw_xy,
+            when: Instant::now(),
+            text: text.clone(),
+        });
+        text
+    }
+
+    pub fn forget_tooltip(&self) {
+        *self.last_tooltip_query.borrow_mut() = None;
+    }
+
     pub fn connect_draw<F: 'static + Fn(&GraticuleCore, &cairo::Context)>(&self, callback: F) {
         self.draw_callbacks.borrow_mut().push(Box::new(callback))
     }
 
+    // A click is a (left button) press and release without dragging the
+    // view.  The callbacks are given the drawing area coordinates of the
+    // click and return whether they've changed what's drawn.
+    pub fn connect_clicked<F: 'static + Fn(&GraticuleCore, Point) -> bool>(&self, callback: F) {
+        self.clicked_callbacks.borrow_mut().push(Box::new(callback))
+    }
+
+    fn inform_clicked(&self, raw_xy: Point) {
+        let mut redraw = false;
+        for callback in self.clicked_callbacks.borrow().iter() {
+            redraw |= callback(self, raw_xy);
+        }
+        if redraw {
+            self.forget_tooltip();
+            self.queue_draw();
+        }
+    }
+
     pub fn set_current_target_colour(&self, o_colour: Option<&Colour>) {
         if let Some(colour) = o_colour {
             *self.current_target.borrow_mut() =
@@ -197,6 +984,15 @@ impl GraticuleCore {
     ) -> SignalHandlerId {
         self.drawing_area.connect_button_press_event(f)
     }
+
+    pub fn connect_key_press_event<
+        F: Fn(&gtk::DrawingArea, &gdk::EventKey) -> Inhibit + 'static,
+    >(
+        &self,
+        f: F,
+    ) -> SignalHandlerId {
+        self.drawing_area.connect_key_press_event(f)
+    }
 }
 
 impl GraticuleInterface for Rc<GraticuleCore> {
@@ -204,7 +1000,10 @@ impl GraticuleInterface for Rc<GraticuleCore> {
         let drawing_area = gtk::DrawingArea::new();
         drawing_area.set_size_request(300, 300);
         drawing_area.set_has_tooltip(true);
+        drawing_area.set_can_focus(true);
         let events = gdk::EventMask::SCROLL_MASK
+            | gdk::EventMask::KEY_PRESS_MASK
+            | gdk::EventMask::FOCUS_CHANGE_MASK
             | gdk::EventMask::BUTTON_PRESS_MASK
             | gdk::EventMask::BUTTON_MOTION_MASK
             | gdk::EventMask::LEAVE_NOTIFY_MASK
@@ -222,7 +1021,16 @@ impl GraticuleInterface for Rc<GraticuleCore> {
             current_target: RefCell::new(None),
             motion_enabled: Cell::new(false),
             last_xy: Cell::new(Point(0.0, 0.0)),
+            press_xy: Cell::new(None),
             draw_callbacks: RefCell::new(Vec::new()),
+            clicked_callbacks: RefCell::new(Vec::new()),
+            focused_xy: Cell::new(None),
+            chosen_xy: Cell::new(None),
+            style: Cell::new(GraticuleStyle::recall()),
+            legend_entries: RefCell::new(Vec::new()),
+            show_legend: Cell::new(recall(SHOW_LEGEND_RECOLLECTION) == Some("true".to_string())),
+            vision_deficiency: Cell::new(None),
+            last_tooltip_query: RefCell::new(None),
         });
         graticule.update_drawing_area();
         let graticule_c = graticule.clone();
@@ -238,18 +1046,18 @@ impl GraticuleInterface for Rc<GraticuleCore> {
         let graticule_c = graticule.clone();
         graticule
             .drawing_area
-            .connect_scroll_event(move |da, scroll_event| {
+            .connect_scroll_event(move |_, scroll_event| {
                 if let Some(device) = scroll_event.get_device() {
                     if device.get_source() == gdk::InputSource::Mouse {
                         match scroll_event.get_direction() {
                             gdk::ScrollDirection::Up => {
                                 graticule_c.decr_zoom();
-                                da.queue_draw();
+                                graticule_c.queue_draw();
                                 return Inhibit(true);
                             }
                             gdk::ScrollDirection::Down => {
                                 graticule_c.incr_zoom();
-                                da.queue_draw();
+                                graticule_c.queue_draw();
                                 return Inhibit(true);
                             }
                             _ => return Inhibit(false),
@@ -262,11 +1070,33 @@ impl GraticuleInterface for Rc<GraticuleCore> {
         let graticule_c = graticule.clone();
         graticule
             .drawing_area
-            .connect_button_press_event(move |_, event| {
+            .connect_key_press_event(move |_, event| {
+                if let Some(zoom_key) = ZoomKey::from_key(&event.get_keyval()) {
+                    graticule_c.zoom_by_key(zoom_key);
+                    graticule_c.queue_draw();
+                    return Inhibit(true);
+                }
+                Inhibit(false)
+            });
+
+        let graticule_c = graticule.clone();
+        graticule
+            .drawing_area
+            .connect_button_press_event(move |da, event| {
+                if !da.has_focus() {
+                    da.grab_focus();
+                }
                 if event.get_event_type() == gdk::EventType::ButtonPress {
                     if event.get_button() == 1 {
                         let point = Point::from(event.get_position());
+                        if graticule_c.overview_is_visible() && graticule_c.overview_contains(point)
+                        {
+                            graticule_c.centre_view_on_overview_point(point);
+                            graticule_c.queue_draw();
+                            return Inhibit(true);
+                        }
                         graticule_c.last_xy.set(point);
+                        graticule_c.press_xy.set(Some(point));
                         graticule_c.motion_enabled.set(true);
                         return Inhibit(true);
                     }
@@ -276,14 +1106,14 @@ impl GraticuleInterface for Rc<GraticuleCore> {
         let graticule_c = graticule.clone();
         graticule
             .drawing_area
-            .connect_motion_notify_event(move |da, event| {
+            .connect_motion_notify_event(move |_, event| {
                 if graticule_c.motion_enabled.get() {
                     let (x, y) = event.get_position();
                     let this_xy = Point(x, y);
                     let delta_xy = this_xy - graticule_c.last_xy.get();
                     graticule_c.last_xy.set(this_xy);
                     graticule_c.shift_offset(delta_xy);
-                    da.queue_draw();
+                    graticule_c.queue_draw();
                     Inhibit(true)
                 } else {
                     Inhibit(false)
@@ -296,11 +1126,25 @@ impl GraticuleInterface for Rc<GraticuleCore> {
                 if event.get_event_type() == gdk::EventType::ButtonRelease {
                     if event.get_button() == 1 {
                         graticule_c.motion_enabled.set(false);
+                        if let Some(press_xy) = graticule_c.press_xy.take() {
+                            let point = Point::from(event.get_position());
+                            if (point - press_xy).hypot() < CLICK_SLOP {
+                                graticule_c.inform_clicked(point);
+                            }
+                        }
                         return Inhibit(true);
                     }
                 }
                 Inhibit(false)
             });
+        graticule.drawing_area.connect_focus_in_event(|da, _| {
+            da.queue_draw();
+            Inhibit(false)
+        });
+        graticule.drawing_area.connect_focus_out_event(|da, _| {
+            da.queue_draw();
+            Inhibit(false)
+        });
         let graticule_c = graticule.clone();
         graticule
             .drawing_area
@@ -316,8 +1160,91 @@ pub type Graticule = Rc<GraticuleCore>;
 
 #[cfg(test)]
 mod tests {
-    //use super::*;
+    use super::*;
+
+    #[test]
+    fn focus_moves() {
+        let points = [
+            Point(0.0, 0.5),
+            Point(0.5, 0.0),
+            Point(-0.5, 0.0),
+            Point(0.8, 0.1),
+        ];
+        assert_eq!(next_focus_index(&points, None, FocusMove::Next), Some(1));
+        assert_eq!(next_focus_index(&points, Some(1), FocusMove::Next), Some(3));
+        assert_eq!(next_focus_index(&points, Some(2), FocusMove::Next), None);
+        assert_eq!(
+            next_focus_index(&points, None, FocusMove::Previous),
+            Some(2)
+        );
+        assert_eq!(
+            next_focus_index(&points, Some(1), FocusMove::Previous),
+            None
+        );
+        assert_eq!(
+            next_focus_index(&points, Some(1), FocusMove::Right),
+            Some(3)
+        );
+        assert_eq!(next_focus_index(&points, Some(1), FocusMove::Left), Some(2));
+        assert_eq!(next_focus_index(&points, Some(2), FocusMove::Left), Some(2));
+        assert_eq!(next_focus_index(&points, Some(1), FocusMove::Down), Some(3));
+        assert_eq!(next_focus_index(&[], None, FocusMove::Next), None);
+    }
 
     #[test]
-    fn it_works() {}
+    fn wheel_image_formats() {
+        assert_eq!(
+            WheelImageFormat::for_path(Path::new("wheel.png")),
+            Some(WheelImageFormat::PNG)
+        );
+        assert_eq!(
+            WheelImageFormat::for_path(Path::new("/tmp/Wheel.SVG")),
+            Some(WheelImageFormat::SVG)
+        );
+        assert_eq!(WheelImageFormat::for_path(Path::new("wheel.jpg")), None);
+        assert_eq!(WheelImageFormat::for_path(Path::new("wheel")), None);
+    }
+
+    #[test]
+    fn zoom_keys() {
+        use gdk::keys::constants;
+        assert_eq!(ZoomKey::from_key(&constants::plus), Some(ZoomKey::In));
+        assert_eq!(ZoomKey::from_key(&constants::KP_Add), Some(ZoomKey::In));
+        assert_eq!(ZoomKey::from_key(&constants::minus), Some(ZoomKey::Out));
+        assert_eq!(ZoomKey::from_key(&constants::KP_0), Some(ZoomKey::Reset));
+        assert_eq!(ZoomKey::from_key(&constants::Left), None);
+    }
+
+    #[test]
+    fn shape_sizes() {
+        let style = GraticuleStyle::default();
+        assert_eq!(style.shape_size(20.0, 1.0, false), 20.0);
+        assert_eq!(style.shape_size(20.0, 1.0, true), 30.0);
+        // small wheels don't shrink shapes below the minimum
+        assert_eq!(style.shape_size(2.0, 1.0, false), 6.0);
+        assert_eq!(style.shape_size(1.0, 0.5, false), 3.0);
+        let style = GraticuleStyle {
+            shape_scale: 2.0,
+            ..GraticuleStyle::default()
+        };
+        assert_eq!(style.shape_size(20.0, 1.0, false), 40.0);
+    }
+
+    #[test]
+    fn tooltip_query_reuse() {
+        let query = TooltipQuery {
+            raw_xy: Point(10.0, 10.0),
+            when: Instant::now(),
+            text: None,
+        };
+        assert!(query.is_reusable_for(Point(11.0, 10.0)));
+        assert!(query.is_reusable_for(Point(14.0, 10.0)));
+        assert!(!query.is_reusable_for(Point(30.0, 10.0)));
+        let stale = TooltipQuery {
+            when: Instant::now() - Duration::from_millis(TOOLTIP_THROTTLE_MILLIS * 2),
+            ..query
+        };
+        assert!(stale.is_reusable_for(Point(11.0, 10.0)));
+        assert!(!stale.is_reusable_for(Point(14.0, 10.0)));
+    }
 }
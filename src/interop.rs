@@ -0,0 +1,392 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+// Reading the swatch libraries of other (digital) colour programs i.e.
+// Adobe Swatch Exchange (.ase) and Photoshop (.aco) palette files so that
+// they can be used to seed paint collections.
+// NB: both formats are big endian throughout.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::basic_paint::*;
+use crate::colln_paint::editor::unique_paint_name;
+use crate::colln_paint::legacy::assumed_characteristics;
+use crate::colour::*;
+use crate::colour_difference::lab_to_rgb;
+use crate::error::*;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SwatchFileFormat {
+    ASE,
+    ACO,
+}
+
+impl SwatchFileFormat {
+    pub fn for_path(path: &Path) -> Option<SwatchFileFormat> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        match extension.as_str() {
+            "ase" => Some(SwatchFileFormat::ASE),
+            "aco" => Some(SwatchFileFormat::ACO),
+            _ => None,
+        }
+    }
+}
+
+struct BigEndianReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> BigEndianReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, offset: 0 }
+    }
+
+    fn at_end(&self) -> bool {
+        self.offset >= self.bytes.len()
+    }
+
+    fn remaining(&self) -> usize {
+        self.bytes.len().saturating_sub(self.offset)
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        if self.offset + n > self.bytes.len() {
+            return Err(format!("unexpected end of data at byte {}", self.offset));
+        }
+        let slice = &self.bytes[self.offset..self.offset + n];
+        self.offset += n;
+        Ok(slice)
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn f32(&mut self) -> Result<f32, String> {
+        Ok(f32::from_bits(self.u32()?))
+    }
+
+    // "n_units" UTF-16 code units (the last of which may be a terminating null)
+    fn utf16(&mut self, n_units: usize) -> Result<String, String> {
+        // a corrupt length mustn't be trusted for the allocation
+        match n_units.checked_mul(2) {
+            Some(n_bytes) if n_bytes <= self.remaining() => (),
+            _ => {
+                return Err(format!(
+                    "name length {} too long at byte {}",
+                    n_units, self.offset
+                ))
+            }
+        }
+        let mut units: Vec<u16> = Vec::with_capacity(n_units);
+        for _ in 0..n_units {
+            units.push(self.u16()?);
+        }
+        while units.last() == Some(&0) {
+            units.pop();
+        }
+        String::from_utf16(&units).map_err(|_| format!("bad name before byte {}", self.offset))
+    }
+}
+
+fn unit(value: f64) -> f64 {
+    value.max(0.0).min(1.0)
+}
+
+// NB: naive (i.e. no colour profile) conversion so only approximate
+fn cmyk_to_rgb(c: f64, m: f64, y: f64, k: f64) -> RGB {
+    RGB::from([
+        unit((1.0 - c) * (1.0 - k)),
+        unit((1.0 - m) * (1.0 - k)),
+        unit((1.0 - y) * (1.0 - k)),
+    ])
+}
+
+// "hue" in degrees and the rest in the range 0.0 to 1.0
+fn hsb_to_rgb(hue: f64, saturation: f64, brightness: f64) -> RGB {
+    let sector = (hue.rem_euclid(360.0)) / 60.0;
+    let chroma = brightness * saturation;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u8 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = brightness - chroma;
+    RGB::from([unit(r + m), unit(g + m), unit(b + m)])
+}
+
+const ASE_SIGNATURE: &[u8] = b"ASEF";
+const ASE_GROUP_START: u16 = 0xC001;
+const ASE_GROUP_END: u16 = 0xC002;
+const ASE_COLOUR_ENTRY: u16 = 0x0001;
+
+// The named colours in an Adobe Swatch Exchange file (group structure is
+// ignored) and a warning for each entry that couldn't be used
+pub fn read_ase(bytes: &[u8]) -> Result<(Vec<(String, RGB)>, Vec<String>), String> {
+    let mut reader = BigEndianReader::new(bytes);
+    if reader.take(4)? != ASE_SIGNATURE {
+        return Err("not an Adobe Swatch Exchange file".to_string());
+    }
+    let _version = (reader.u16()?, reader.u16()?);
+    let n_blocks = reader.u32()?;
+    let mut swatches: Vec<(String, RGB)> = vec![];
+    let mut warnings: Vec<String> = vec![];
+    for _ in 0..n_blocks {
+        let block_type = reader.u16()?;
+        let block_len = reader.u32()? as usize;
+        let block = reader.take(block_len)?;
+        match block_type {
+            ASE_COLOUR_ENTRY => {
+                let mut block_reader = BigEndianReader::new(block);
+                let name_len = block_reader.u16()? as usize;
+                let name = block_reader.utf16(name_len)?;
+                let model = block_reader.take(4)?;
+                let mut values = |n: usize| -> Result<Vec<f64>, String> {
+                    (0..n)
+                        .map(|_| block_reader.f32().map(|v| v as f64))
+                        .collect()
+                };
+                let rgb = match model {
+                    b"RGB " => {
+                        let v = values(3)?;
+                        RGB::from([unit(v[0]), unit(v[1]), unit(v[2])])
+                    }
+                    b"CMYK" => {
+                        let v = values(4)?;
+                        cmyk_to_rgb(v[0], v[1], v[2], v[3])
+                    }
+                    // L is stored as a fraction
+                    b"LAB " => {
+                        let v = values(3)?;
+                        lab_to_rgb(&[v[0] * 100.0, v[1], v[2]])
+                    }
+                    b"Gray" => {
+                        let v = values(1)?;
+                        RGB::from([unit(v[0]), unit(v[0]), unit(v[0])])
+                    }
+                    _ => {
+                        warnings.push(format!(
+                            "{}: colour model \"{}\" not supported (skipped)",
+                            name,
+                            String::from_utf8_lossy(model)
+                        ));
+                        continue;
+                    }
+                };
+                swatches.push((name, rgb));
+            }
+            ASE_GROUP_START | ASE_GROUP_END => (),
+            _ => warnings.push(format!("block type {:#06X} not understood", block_type)),
+        }
+    }
+    Ok((swatches, warnings))
+}
+
+const ACO_RGB: u16 = 0;
+const ACO_HSB: u16 = 1;
+const ACO_CMYK: u16 = 2;
+const ACO_LAB: u16 = 7;
+const ACO_GREYSCALE: u16 = 8;
+
+fn aco_rgb(space: u16, w: [u16; 4]) -> Option<RGB> {
+    let frac = |value: u16| value as f64 / 65535.0;
+    match space {
+        ACO_RGB => Some(RGB::from([frac(w[0]), frac(w[1]), frac(w[2])])),
+        ACO_HSB => Some(hsb_to_rgb(frac(w[0]) * 360.0, frac(w[1]), frac(w[2]))),
+        // 0 means 100% ink
+        ACO_CMYK => Some(cmyk_to_rgb(
+            1.0 - frac(w[0]),
+            1.0 - frac(w[1]),
+            1.0 - frac(w[2]),
+            1.0 - frac(w[3]),
+        )),
+        ACO_LAB => Some(lab_to_rgb(&[
+            w[0] as f64 / 100.0,
+            w[1] as i16 as f64 / 100.0,
+            w[2] as i16 as f64 / 100.0,
+        ])),
+        // 10000 is black
+        ACO_GREYSCALE => {
+            let value = unit(1.0 - w[0] as f64 / 10000.0);
+            Some(RGB::from([value, value, value]))
+        }
+        _ => None,
+    }
+}
+
+// The colours in a Photoshop swatch file.  Version 1 files don't have
+// names so the colours are numbered (and the names from the version 2
+// section that usually follows are used if it's present).
+pub fn read_aco(bytes: &[u8]) -> Result<(Vec<(String, RGB)>, Vec<String>), String> {
+    let mut reader = BigEndianReader::new(bytes);
+    let mut swatches: Vec<(String, RGB)> = vec![];
+    let mut warnings: Vec<String> = vec![];
+    while !reader.at_end() {
+        let version = reader.u16()?;
+        if version != 1 && version != 2 {
+            return Err(format!("swatch file version {} not supported", version));
+        }
+        let count = reader.u16()? as usize;
+        let mut section: Vec<(String, RGB)> = vec![];
+        let mut section_warnings: Vec<String> = vec![];
+        for index in 0..count {
+            let space = reader.u16()?;
+            let w = [reader.u16()?, reader.u16()?, reader.u16()?, reader.u16()?];
+            let name = if version == 2 {
+                let name_len = reader.u32()? as usize;
+                reader.utf16(name_len)?
+            } else {
+                format!("Colour {}", index + 1)
+            };
+            match aco_rgb(space, w) {
+                Some(rgb) => section.push((name, rgb)),
+                None => section_warnings.push(format!(
+                    "{}: colour space {} not supported (skipped)",
+                    name, space
+                )),
+            }
+        }
+        swatches = section;
+        warnings = section_warnings;
+    }
+    Ok((swatches, warnings))
+}
+
+// Paint specifications (with assumed characteristics) for the colours in
+// a swatch file and a warning for anything skipped or renamed
+pub fn swatch_paint_specs<C: CharacteristicsInterface>(
+    path: &Path,
+) -> Result<(Vec<BasicPaintSpec<C>>, Vec<String>), PaintError<C>> {
+    let format = SwatchFileFormat::for_path(path).ok_or(PaintError::from(
+        PaintErrorType::MalformedText(format!("{:?}: not a .ase or .aco file", path)),
+    ))?;
+    let bytes = fs::read(path)?;
+    let result = match format {
+        SwatchFileFormat::ASE => read_ase(&bytes),
+        SwatchFileFormat::ACO => read_aco(&bytes),
+    };
+    let (swatches, mut warnings) =
+        result.map_err(|msg| PaintError::from(PaintErrorType::MalformedText(msg)))?;
+    let characteristics = assumed_characteristics::<C>()?;
+    let mut names: HashSet<String> = HashSet::new();
+    let mut paint_specs: Vec<BasicPaintSpec<C>> = vec![];
+    for (name, rgb) in swatches.into_iter() {
+        let name = if name.trim().len() == 0 {
+            unique_paint_name("Unnamed", |name| names.contains(name))
+        } else if names.contains(name.trim()) {
+            let new_name = unique_paint_name(name.trim(), |name| names.contains(name));
+            warnings.push(format!("{}: duplicate name (renamed {})", name, new_name));
+            new_name
+        } else {
+            name.trim().to_string()
+        };
+        names.insert(name.clone());
+        paint_specs.push(BasicPaintSpec::<C> {
+            rgb,
+            name,
+            notes: String::new(),
+            characteristics,
+        });
+    }
+    paint_specs.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok((paint_specs, warnings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_paint::*;
+
+    fn utf16_be(text: &str) -> Vec<u8> {
+        let mut bytes = vec![];
+        for unit in text.encode_utf16().chain(std::iter::once(0)) {
+            bytes.extend_from_slice(&unit.to_be_bytes());
+        }
+        bytes
+    }
+
+    fn ase_colour_block(name: &str, model: &[u8], values: &[f32]) -> Vec<u8> {
+        let mut block = vec![];
+        block.extend_from_slice(&((name.encode_utf16().count() + 1) as u16).to_be_bytes());
+        block.extend(utf16_be(name));
+        block.extend_from_slice(model);
+        for value in values.iter() {
+            block.extend_from_slice(&value.to_be_bytes());
+        }
+        block.extend_from_slice(&2_u16.to_be_bytes());
+        let mut bytes = ASE_COLOUR_ENTRY.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&(block.len() as u32).to_be_bytes());
+        bytes.extend(block);
+        bytes
+    }
+
+    #[test]
+    fn ase_swatches() {
+        let mut bytes = b"ASEF".to_vec();
+        bytes.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 3]);
+        bytes.extend(ase_colour_block("Red", b"RGB ", &[1.0, 0.0, 0.0]));
+        bytes.extend(ase_colour_block("Black", b"CMYK", &[0.0, 0.0, 0.0, 1.0]));
+        bytes.extend(ase_colour_block("Odd", b"XYZ ", &[0.0, 0.0, 0.0]));
+        let (swatches, warnings) = read_ase(&bytes).unwrap();
+        assert_eq!(swatches.len(), 2);
+        assert_eq!(swatches[0], ("Red".to_string(), RGB::RED));
+        assert_eq!(swatches[1], ("Black".to_string(), RGB::BLACK));
+        assert_eq!(warnings.len(), 1);
+        assert!(read_ase(b"ASEX").is_err());
+        assert!(read_ase(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn aco_swatches() {
+        let mut bytes = vec![0, 1, 0, 1];
+        bytes.extend_from_slice(&[0, 0, 0xFF, 0xFF, 0, 0, 0, 0, 0, 0]);
+        bytes.extend_from_slice(&[0, 2, 0, 1]);
+        bytes.extend_from_slice(&[0, 0, 0xFF, 0xFF, 0, 0, 0, 0, 0, 0]);
+        bytes.extend_from_slice(&4_u32.to_be_bytes());
+        bytes.extend(utf16_be("Red"));
+        let (swatches, warnings) = read_aco(&bytes).unwrap();
+        assert_eq!(swatches, vec![("Red".to_string(), RGB::RED)]);
+        assert_eq!(warnings.len(), 0);
+        let (swatches, _) = read_aco(&bytes[..14]).unwrap();
+        assert_eq!(swatches, vec![("Colour 1".to_string(), RGB::RED)]);
+    }
+
+    #[test]
+    fn aco_oversized_name_length() {
+        let mut bytes = vec![0, 2, 0, 1];
+        bytes.extend_from_slice(&[0, 0, 0xFF, 0xFF, 0, 0, 0, 0, 0, 0]);
+        bytes.extend_from_slice(&u32::MAX.to_be_bytes());
+        bytes.extend(utf16_be("Red"));
+        let err = read_aco(&bytes).unwrap_err();
+        assert!(err.contains("too long"));
+    }
+
+    #[test]
+    fn swatch_files_to_paint_specs() {
+        let mut bytes = b"ASEF".to_vec();
+        bytes.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 2]);
+        bytes.extend(ase_colour_block("Grey", b"Gray", &[0.5]));
+        bytes.extend(ase_colour_block("Grey", b"RGB ", &[0.5, 0.5, 0.5]));
+        let path = std::env::temp_dir().join(format!("epaint_interop_{}.ase", std::process::id()));
+        std::fs::write(&path, &bytes).unwrap();
+        let result = swatch_paint_specs::<ModelPaintCharacteristics>(&path);
+        std::fs::remove_file(&path).unwrap();
+        let (specs, warnings) = result.unwrap();
+        let names: Vec<&str> = specs.iter().map(|spec| spec.name.as_str()).collect();
+        assert_eq!(names, vec!["Grey", "Grey (2)"]);
+        assert_eq!(warnings.len(), 1);
+        assert!(swatch_paint_specs::<ModelPaintCharacteristics>(Path::new("x.gpl")).is_err());
+    }
+}
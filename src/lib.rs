@@ -30,6 +30,8 @@ pub mod colour {
 
     use normalised_angles::Degrees;
 
+    use crate::colour_difference::{lab_to_rgb, rgb_to_lab};
+
     pub use colour_math::{
         urgb::{URGBError, RGB16, RGB8},
         ColourInterface, HueConstants, RGBConstants, ScalarAttribute, CCI,
@@ -42,10 +44,63 @@ pub mod colour {
     pub type RGBManipulator = colour_math::manipulator::ColourManipulator<f64>;
     pub type ColourManipulatorBuilder = colour_math::manipulator::ColourManipulatorBuilder<f64>;
 
+    // The RGBs derived from a colour for list rows and drawing are computed
+    // once (when the colour is created) rather than every time they're used
+    #[derive(Debug, Clone, Copy)]
+    struct DerivedRGBs {
+        max_chroma: RGB,
+        best_foreground: RGB,
+        monochrome: RGB,
+        warmth: RGB,
+    }
+
+    impl DerivedRGBs {
+        fn new(rgb: &RGB, hue: Option<Hue>) -> Self {
+            Self {
+                max_chroma: match hue {
+                    Some(hue) => hue.max_chroma_rgb(),
+                    None => *rgb,
+                },
+                best_foreground: rgb.best_foreground_rgb(),
+                monochrome: rgb.monochrome_rgb(),
+                warmth: rgb.warmth_rgb(),
+            }
+        }
+    }
+
+    // NB: only the RGB and hue are serialized (as they always have been)
+    #[derive(Serialize, Deserialize)]
+    struct ColourData {
+        rgb: RGB,
+        hue: Option<Hue>,
+    }
+
     #[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+    #[serde(from = "ColourData", into = "ColourData")]
     pub struct Colour {
         rgb: RGB,
         hue: Option<Hue>,
+        derived: DerivedRGBs,
+    }
+
+    impl From<ColourData> for Colour {
+        fn from(data: ColourData) -> Self {
+            let derived = DerivedRGBs::new(&data.rgb, data.hue);
+            Self {
+                rgb: data.rgb,
+                hue: data.hue,
+                derived,
+            }
+        }
+    }
+
+    impl From<Colour> for ColourData {
+        fn from(colour: Colour) -> Self {
+            Self {
+                rgb: colour.rgb,
+                hue: colour.hue,
+            }
+        }
     }
 
     impl PartialEq for Colour {
@@ -88,7 +143,8 @@ pub mod colour {
             } else {
                 None
             };
-            Self { rgb, hue }
+            let derived = DerivedRGBs::new(&rgb, hue);
+            Self { rgb, hue, derived }
         }
     }
 
@@ -134,11 +190,7 @@ pub mod colour {
         }
 
         fn max_chroma_rgb(&self) -> RGB {
-            if let Some(hue) = self.hue {
-                hue.max_chroma_rgb()
-            } else {
-                self.rgb()
-            }
+            self.derived.max_chroma
         }
 
         fn warmth(&self) -> f64 {
@@ -146,15 +198,21 @@ pub mod colour {
         }
 
         fn best_foreground_rgb(&self) -> RGB {
-            self.rgb.best_foreground_rgb()
+            self.derived.best_foreground
         }
 
         fn monochrome_rgb(&self) -> RGB {
-            self.rgb.monochrome_rgb()
+            self.derived.monochrome
         }
 
         fn warmth_rgb(&self) -> RGB {
-            self.rgb.warmth_rgb()
+            self.derived.warmth
+        }
+    }
+
+    impl Colour {
+        pub fn colour_temperature(&self) -> Option<f64> {
+            colour_temperature(&self.rgb)
         }
     }
 
@@ -172,6 +230,377 @@ pub mod colour {
             }
         }
     }
+
+    // Toolkit independent representations for consumers that don't use GDK
+    pub trait PlainConvert {
+        fn into_u8_rgba(&self) -> (u8, u8, u8, u8);
+        fn into_css_hex(&self) -> String;
+        fn into_css_hex_with_alpha(&self, alpha: f64) -> String;
+    }
+
+    fn unit_to_u8(value: f64) -> u8 {
+        (value.max(0.0).min(1.0) * 255.0).round() as u8
+    }
+
+    impl PlainConvert for RGB {
+        fn into_u8_rgba(&self) -> (u8, u8, u8, u8) {
+            (
+                unit_to_u8(self[CCI::Red]),
+                unit_to_u8(self[CCI::Green]),
+                unit_to_u8(self[CCI::Blue]),
+                255,
+            )
+        }
+
+        // e.g. "#FF8000"
+        fn into_css_hex(&self) -> String {
+            let (red, green, blue, _) = self.into_u8_rgba();
+            format!("#{:02X}{:02X}{:02X}", red, green, blue)
+        }
+
+        // e.g. "#FF800080"
+        fn into_css_hex_with_alpha(&self, alpha: f64) -> String {
+            format!("{}{:02X}", self.into_css_hex(), unit_to_u8(alpha))
+        }
+    }
+
+    impl PlainConvert for Colour {
+        fn into_u8_rgba(&self) -> (u8, u8, u8, u8) {
+            self.rgb.into_u8_rgba()
+        }
+
+        fn into_css_hex(&self) -> String {
+            self.rgb.into_css_hex()
+        }
+
+        fn into_css_hex_with_alpha(&self, alpha: f64) -> String {
+            self.rgb.into_css_hex_with_alpha(alpha)
+        }
+    }
+
+    // Accepts "#RGB", "#RRGGBB" and "#RRGGBBAA" (the alpha is ignored)
+    pub fn rgb_from_css_hex(text: &str) -> Option<RGB> {
+        let digits = text.trim().strip_prefix('#')?;
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let component = |index: usize, width: usize| -> f64 {
+            let value = u8::from_str_radix(&digits[index * width..(index + 1) * width], 16)
+                .unwrap_or_else(|_| panic!("File: {:?} Line: {:?}", file!(), line!()));
+            if width == 1 {
+                (value * 17) as f64 / 255.0
+            } else {
+                value as f64 / 255.0
+            }
+        };
+        let width = match digits.len() {
+            3 => 1,
+            6 | 8 => 2,
+            _ => return None,
+        };
+        Some(RGB::from([
+            component(0, width),
+            component(1, width),
+            component(2, width),
+        ]))
+    }
+
+    // How the interpolation parameter progresses over time
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Easing {
+        Linear,
+        EaseIn,
+        EaseOut,
+        EaseInOut,
+    }
+
+    impl Easing {
+        pub fn apply(&self, t: f64) -> f64 {
+            let t = t.max(0.0).min(1.0);
+            match *self {
+                Easing::Linear => t,
+                Easing::EaseIn => t * t,
+                Easing::EaseOut => t * (2.0 - t),
+                Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum InterpolationSpace {
+        RGB,
+        HCV,
+        Lab,
+    }
+
+    fn lerp(a: f64, b: f64, t: f64) -> f64 {
+        a + (b - a) * t
+    }
+
+    pub fn lerp_rgb(from: &RGB, to: &RGB, t: f64) -> RGB {
+        RGB::from([
+            lerp(from[CCI::Red], to[CCI::Red], t),
+            lerp(from[CCI::Green], to[CCI::Green], t),
+            lerp(from[CCI::Blue], to[CCI::Blue], t),
+        ])
+    }
+
+    // Hue (degrees, None for greys), chroma and value in the hexcone model
+    pub fn hexcone_hcv(rgb: &RGB) -> (Option<f64>, f64, f64) {
+        let (red, green, blue) = (rgb[CCI::Red], rgb[CCI::Green], rgb[CCI::Blue]);
+        let max = red.max(green).max(blue);
+        let chroma = max - red.min(green).min(blue);
+        if chroma <= 0.0 {
+            return (None, 0.0, max);
+        }
+        let sector = if max == red {
+            ((green - blue) / chroma).rem_euclid(6.0)
+        } else if max == green {
+            (blue - red) / chroma + 2.0
+        } else {
+            (red - green) / chroma + 4.0
+        };
+        (Some(sector * 60.0), chroma, max)
+    }
+
+    pub fn hexcone_rgb(hue: Option<f64>, chroma: f64, value: f64) -> RGB {
+        let grey = value - chroma;
+        let hue = match hue {
+            Some(hue) => hue.rem_euclid(360.0),
+            None => return RGB::from([value, value, value]),
+        };
+        let sector = hue / 60.0;
+        let x = chroma * (1.0 - (sector.rem_euclid(2.0) - 1.0).abs());
+        let (red, green, blue) = match sector as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        RGB::from([red + grey, green + grey, blue + grey])
+    }
+
+    // Hue takes the short way around the wheel (and a grey end takes
+    // the hue of the other end so that there's no swing through other hues)
+    pub fn lerp_hcv(from: &RGB, to: &RGB, t: f64) -> RGB {
+        let (from_hue, from_chroma, from_value) = hexcone_hcv(from);
+        let (to_hue, to_chroma, to_value) = hexcone_hcv(to);
+        let hue = match (from_hue, to_hue) {
+            (Some(from_hue), Some(to_hue)) => {
+                let mut delta = (to_hue - from_hue).rem_euclid(360.0);
+                if delta > 180.0 {
+                    delta -= 360.0;
+                }
+                Some(from_hue + delta * t)
+            }
+            (Some(hue), None) | (None, Some(hue)) => Some(hue),
+            (None, None) => None,
+        };
+        hexcone_rgb(
+            hue,
+            lerp(from_chroma, to_chroma, t),
+            lerp(from_value, to_value, t),
+        )
+    }
+
+    pub fn lerp_lab(from: &RGB, to: &RGB, t: f64) -> RGB {
+        let from_lab = rgb_to_lab(from);
+        let to_lab = rgb_to_lab(to);
+        lab_to_rgb(&[
+            lerp(from_lab[0], to_lab[0], t),
+            lerp(from_lab[1], to_lab[1], t),
+            lerp(from_lab[2], to_lab[2], t),
+        ])
+    }
+
+    pub fn interpolate(
+        from: &RGB,
+        to: &RGB,
+        t: f64,
+        space: InterpolationSpace,
+        easing: Easing,
+    ) -> RGB {
+        let t = easing.apply(t);
+        match space {
+            InterpolationSpace::RGB => lerp_rgb(from, to, t),
+            InterpolationSpace::HCV => lerp_hcv(from, to, t),
+            InterpolationSpace::Lab => lerp_lab(from, to, t),
+        }
+    }
+
+    // The range of colour temperatures (in Kelvin) that are reported
+    pub const MIN_COLOUR_TEMPERATURE: f64 = 1000.0;
+    pub const MAX_COLOUR_TEMPERATURE: f64 = 20000.0;
+
+    // The correlated colour temperature (by McCamy's approximation) of
+    // the light that "rgb" would be if it were a light source (None for black).
+    // NB: only meaningful for colours near the black body locus (i.e. the
+    // whites, creams and pale blues that lighting is described by)
+    pub fn colour_temperature(rgb: &RGB) -> Option<f64> {
+        use crate::colour_mix::srgb_to_linear;
+        let r = srgb_to_linear(rgb[CCI::Red]);
+        let g = srgb_to_linear(rgb[CCI::Green]);
+        let b = srgb_to_linear(rgb[CCI::Blue]);
+        let x = 0.4124 * r + 0.3576 * g + 0.1805 * b;
+        let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+        let z = 0.0193 * r + 0.1192 * g + 0.9505 * b;
+        let sum = x + y + z;
+        if sum <= 0.0 {
+            return None;
+        }
+        let n = (x / sum - 0.3320) / (0.1858 - y / sum);
+        let cct = 449.0 * n.powi(3) + 3525.0 * n.powi(2) + 6823.3 * n + 5520.33;
+        Some(cct.max(MIN_COLOUR_TEMPERATURE).min(MAX_COLOUR_TEMPERATURE))
+    }
+
+    // The (approximate and normalised) colour of a black body radiator at
+    // the given temperature (Kelvin) e.g. for drawing temperature scales
+    pub fn colour_temperature_rgb(kelvin: f64) -> RGB {
+        let t = kelvin
+            .max(MIN_COLOUR_TEMPERATURE)
+            .min(MAX_COLOUR_TEMPERATURE)
+            / 100.0;
+        let unit = |value: f64| (value / 255.0).max(0.0).min(1.0);
+        let red = if t <= 66.0 {
+            1.0
+        } else {
+            unit(329.698_727_446 * (t - 60.0).powf(-0.133_204_759_2))
+        };
+        let green = if t <= 66.0 {
+            unit(99.470_802_586_1 * t.ln() - 161.119_568_166_1)
+        } else {
+            unit(288.122_169_528_3 * (t - 60.0).powf(-0.075_514_849_2))
+        };
+        let blue = if t >= 66.0 {
+            1.0
+        } else if t <= 19.0 {
+            0.0
+        } else {
+            unit(138.517_731_223_1 * (t - 10.0).ln() - 305.044_792_730_7)
+        };
+        RGB::from([red, green, blue])
+    }
+
+    // Dichromatic colour vision deficiencies simulated (at full severity)
+    // by the linear RGB transforms of Machado, Oliveira and Fernandes (2009)
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum VisionDeficiency {
+        Protanopia,
+        Deuteranopia,
+        Tritanopia,
+    }
+
+    static VISION_DEFICIENCY_VALUES: &[VisionDeficiency] = &[
+        VisionDeficiency::Protanopia,
+        VisionDeficiency::Deuteranopia,
+        VisionDeficiency::Tritanopia,
+    ];
+
+    impl VisionDeficiency {
+        pub fn values() -> Vec<VisionDeficiency> {
+            VISION_DEFICIENCY_VALUES.to_vec()
+        }
+
+        pub fn name(&self) -> &'static str {
+            match *self {
+                VisionDeficiency::Protanopia => "protanopia",
+                VisionDeficiency::Deuteranopia => "deuteranopia",
+                VisionDeficiency::Tritanopia => "tritanopia",
+            }
+        }
+
+        pub fn menu_key(&self) -> String {
+            format!("simulate_{}", self.name())
+        }
+
+        pub fn label(&self) -> &'static str {
+            match *self {
+                VisionDeficiency::Protanopia => "Simulate Protanopia",
+                VisionDeficiency::Deuteranopia => "Simulate Deuteranopia",
+                VisionDeficiency::Tritanopia => "Simulate Tritanopia",
+            }
+        }
+
+        pub fn tooltip_text(&self) -> &'static str {
+            match *self {
+                VisionDeficiency::Protanopia => {
+                    "Show the colours as seen without red sensitive cones"
+                }
+                VisionDeficiency::Deuteranopia => {
+                    "Show the colours as seen without green sensitive cones"
+                }
+                VisionDeficiency::Tritanopia => {
+                    "Show the colours as seen without blue sensitive cones"
+                }
+            }
+        }
+
+        fn matrix(&self) -> [[f64; 3]; 3] {
+            match *self {
+                VisionDeficiency::Protanopia => [
+                    [0.152286, 1.052583, -0.204868],
+                    [0.114503, 0.786281, 0.099216],
+                    [-0.003882, -0.048116, 1.051998],
+                ],
+                VisionDeficiency::Deuteranopia => [
+                    [0.367322, 0.860646, -0.227968],
+                    [0.280085, 0.672501, 0.047413],
+                    [-0.011820, 0.042940, 0.968881],
+                ],
+                VisionDeficiency::Tritanopia => [
+                    [1.255528, -0.076749, -0.178779],
+                    [-0.078411, 0.930809, 0.147602],
+                    [0.004733, 0.691367, 0.303900],
+                ],
+            }
+        }
+
+        // How "rgb" (sRGB) looks to someone with this deficiency
+        pub fn simulated_rgb(&self, rgb: &RGB) -> RGB {
+            use crate::colour_mix::{linear_to_srgb, srgb_to_linear};
+
+            let linear = [
+                srgb_to_linear(rgb[CCI::Red]),
+                srgb_to_linear(rgb[CCI::Green]),
+                srgb_to_linear(rgb[CCI::Blue]),
+            ];
+            let mut array = [0.0_f64; 3];
+            for (value, row) in array.iter_mut().zip(self.matrix().iter()) {
+                let sum: f64 = row.iter().zip(linear.iter()).map(|(m, c)| m * c).sum();
+                *value = linear_to_srgb(sum.max(0.0).min(1.0));
+            }
+            RGB::from(array)
+        }
+
+        // NB: "None" (i.e. normal vision) leaves the RGB alone
+        pub fn simulated_rgb_for(o_deficiency: Option<VisionDeficiency>, rgb: &RGB) -> RGB {
+            match o_deficiency {
+                Some(deficiency) => deficiency.simulated_rgb(rgb),
+                None => *rgb,
+            }
+        }
+    }
+
+    impl std::str::FromStr for VisionDeficiency {
+        type Err = String;
+
+        fn from_str(string: &str) -> Result<VisionDeficiency, String> {
+            for deficiency in VISION_DEFICIENCY_VALUES.iter() {
+                if deficiency.name() == string {
+                    return Ok(*deficiency);
+                }
+            }
+            Err(format!("{}: unknown vision deficiency", string))
+        }
+    }
+
+    impl std::fmt::Display for VisionDeficiency {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{}", self.name())
+        }
+    }
 }
 
 pub mod error {
@@ -179,6 +608,7 @@ pub mod error {
     use std::error::Error;
     use std::fmt;
     use std::io;
+    use std::str::FromStr;
 
     use regex;
 
@@ -198,6 +628,102 @@ pub mod error {
         UserCancelled,
         BeingUsedBy(Vec<MixedPaint<C>>),
         PartOfCurrentMixture,
+        WrongPaintType(String),
+        ModelInconsistency(String),
+    }
+
+    /// Stable, machine-readable identification of the kind of a `PaintError`.
+    ///
+    /// Unlike the (human oriented and possibly changing) error messages, the
+    /// code strings returned by `as_str()` are guaranteed not to change once
+    /// published so that embedding applications may safely localize or branch
+    /// on them.  New codes may be added in future releases (so matches should
+    /// include a catch all arm) but existing codes will never be renamed,
+    /// reused or removed.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub enum PaintErrorCode {
+        AlreadyExists,
+        MalformedText,
+        NotFound,
+        IOError,
+        NoSubstantiveComponents,
+        NoCollectionId,
+        UserCancelled,
+        BeingUsedBy,
+        PartOfCurrentMixture,
+        WrongPaintType,
+        ModelInconsistency,
+    }
+
+    impl PaintErrorCode {
+        pub fn values() -> Vec<PaintErrorCode> {
+            vec![
+                PaintErrorCode::AlreadyExists,
+                PaintErrorCode::MalformedText,
+                PaintErrorCode::NotFound,
+                PaintErrorCode::IOError,
+                PaintErrorCode::NoSubstantiveComponents,
+                PaintErrorCode::NoCollectionId,
+                PaintErrorCode::UserCancelled,
+                PaintErrorCode::BeingUsedBy,
+                PaintErrorCode::PartOfCurrentMixture,
+                PaintErrorCode::WrongPaintType,
+                PaintErrorCode::ModelInconsistency,
+            ]
+        }
+
+        pub fn as_str(&self) -> &'static str {
+            match *self {
+                PaintErrorCode::AlreadyExists => "EPAINT_ALREADY_EXISTS",
+                PaintErrorCode::MalformedText => "EPAINT_MALFORMED_TEXT",
+                PaintErrorCode::NotFound => "EPAINT_NOT_FOUND",
+                PaintErrorCode::IOError => "EPAINT_IO_ERROR",
+                PaintErrorCode::NoSubstantiveComponents => "EPAINT_NO_SUBSTANTIVE_COMPONENTS",
+                PaintErrorCode::NoCollectionId => "EPAINT_NO_COLLECTION_ID",
+                PaintErrorCode::UserCancelled => "EPAINT_USER_CANCELLED",
+                PaintErrorCode::BeingUsedBy => "EPAINT_BEING_USED_BY",
+                PaintErrorCode::PartOfCurrentMixture => "EPAINT_PART_OF_CURRENT_MIXTURE",
+                PaintErrorCode::WrongPaintType => "EPAINT_WRONG_PAINT_TYPE",
+                PaintErrorCode::ModelInconsistency => "EPAINT_MODEL_INCONSISTENCY",
+            }
+        }
+    }
+
+    impl fmt::Display for PaintErrorCode {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.as_str())
+        }
+    }
+
+    impl FromStr for PaintErrorCode {
+        type Err = String;
+
+        fn from_str(string: &str) -> Result<PaintErrorCode, String> {
+            for code in PaintErrorCode::values() {
+                if code.as_str() == string {
+                    return Ok(code);
+                }
+            }
+            Err(format!("{}: unknown paint error code", string))
+        }
+    }
+
+    impl<C: CharacteristicsInterface> PaintErrorType<C> {
+        pub fn error_code(&self) -> PaintErrorCode {
+            match *self {
+                PaintErrorType::AlreadyExists(_) => PaintErrorCode::AlreadyExists,
+                PaintErrorType::MalformedText(_) => PaintErrorCode::MalformedText,
+                PaintErrorType::NotFound(_) => PaintErrorCode::NotFound,
+                PaintErrorType::IOError(_) => PaintErrorCode::IOError,
+                PaintErrorType::NoSubstantiveComponents => PaintErrorCode::NoSubstantiveComponents,
+                PaintErrorType::NoCollectionId => PaintErrorCode::NoCollectionId,
+                PaintErrorType::UserCancelled => PaintErrorCode::UserCancelled,
+                PaintErrorType::BeingUsedBy(_) => PaintErrorCode::BeingUsedBy,
+                PaintErrorType::PartOfCurrentMixture => PaintErrorCode::PartOfCurrentMixture,
+                PaintErrorType::WrongPaintType(_) => PaintErrorCode::WrongPaintType,
+                PaintErrorType::ModelInconsistency(_) => PaintErrorCode::ModelInconsistency,
+            }
+        }
     }
 
     #[derive(Debug)]
@@ -210,6 +736,16 @@ pub mod error {
         pub fn error_type(&self) -> &PaintErrorType<C> {
             &self.error_type
         }
+
+        /// The stable code for this error (see `PaintErrorCode`).
+        pub fn error_code(&self) -> PaintErrorCode {
+            self.error_type.error_code()
+        }
+
+        /// The human oriented description of this error.
+        pub fn message(&self) -> &str {
+            &self.msg
+        }
     }
 
     impl<C: CharacteristicsInterface> From<PaintErrorType<C>> for PaintError<C> {
@@ -234,6 +770,12 @@ pub mod error {
                 PaintErrorType::PartOfCurrentMixture => {
                     "Is being used as a component of the current mixture.".to_string()
                 }
+                PaintErrorType::WrongPaintType(ref text) => {
+                    format!("{}: is the wrong type of paint.", text)
+                }
+                PaintErrorType::ModelInconsistency(ref text) => {
+                    format!("{}: display out of step with the paints.", text)
+                }
             };
             PaintError { error_type, msg }
         }
@@ -289,12 +831,17 @@ pub mod error {
 }
 
 pub mod dialogue {
-    use std::cell::RefCell;
+    use std::cell::{Cell, RefCell};
     use std::rc::Rc;
 
     use pw_gix::{
+        gdk,
         glib::signal::SignalHandlerId,
-        gtk::{self, prelude::GtkWindowExtManual, DialogExt, GtkWindowExt, WidgetExt},
+        gtk::{
+            self, prelude::GtkWindowExtManual, DialogExt, GtkMenuItemExt, GtkWindowExt, WidgetExt,
+        },
+        gtkx::menu::WrappedMenu,
+        recollections::{recall, remember},
         wrapper::{parent_none, WidgetWrapper},
     };
 
@@ -310,8 +857,11 @@ pub mod dialogue {
         pub callback: Box<dyn Fn()>,
     }
 
+    // NB: "recollection_key" identifies the kind of dialog for remembering
+    // which monitor the user prefers it to be on.
     pub fn new_display_dialog<W>(
         title: &str,
+        recollection_key: &str,
         caller: &Rc<W>,
         buttons: &[(&str, gtk::ResponseType)],
     ) -> gtk::Dialog
@@ -330,10 +880,222 @@ pub mod dialogue {
             if let Some(ref icon) = tlw.get_icon() {
                 dialog.set_icon(Some(icon));
             }
+            // Cascade successive dialogs so that they don't hide each other
+            let (dx, dy) = cascade_offset(NEXT_CASCADE_INDEX.with(|index| {
+                let next = index.get();
+                index.set(next + 1);
+                next
+            }));
+            // Use the caller's monitor unless the user moved this kind of
+            // dialog to another (still connected) monitor last time
+            let monitors = monitors();
+            let tlw_posn = tlw.get_position();
+            let caller_index = area_index_at(
+                &monitors.iter().map(|m| m.geometry).collect::<Vec<_>>(),
+                tlw_posn,
+            );
+            let preferred_index = recall(&monitor_recollection_key(recollection_key))
+                .and_then(|name| monitors.iter().position(|m| m.name == name));
+            let placement = match (preferred_index, caller_index) {
+                (Some(preferred), caller) if Some(preferred) != caller => {
+                    let (x, y, _, _) = monitors[preferred].work_area;
+                    Some(((x, y), preferred))
+                }
+                (_, Some(caller)) => Some((tlw_posn, caller)),
+                _ => None,
+            };
+            match placement {
+                Some(((x, y), index)) => {
+                    let (x, y) =
+                        clamp_to_area((x + dx, y + dy), monitors[index].work_area, CASCADE_STEP);
+                    dialog.move_(x, y);
+                }
+                None => dialog.move_(tlw_posn.0 + dx, tlw_posn.1 + dy),
+            }
         };
+        remembering_monitor(dialog, recollection_key)
+    }
+
+    fn monitor_recollection_key(recollection_key: &str) -> String {
+        format!("{}::monitor", recollection_key)
+    }
+
+    // Record which monitor the dialog was on when it was closed
+    fn remembering_monitor(dialog: gtk::Dialog, recollection_key: &str) -> gtk::Dialog {
+        let key = monitor_recollection_key(recollection_key);
+        dialog.connect_hide(move |dialog| {
+            let monitors = monitors();
+            let areas: Vec<(i32, i32, i32, i32)> = monitors.iter().map(|m| m.geometry).collect();
+            if let Some(index) = area_index_at(&areas, dialog.get_position()) {
+                remember(&key, &monitors[index].name);
+            }
+        });
         dialog
     }
 
+    pub const CASCADE_STEP: i32 = 30;
+    pub const CASCADE_LENGTH: usize = 10;
+
+    thread_local! {
+        static NEXT_CASCADE_INDEX: Cell<usize> = Cell::new(0);
+    }
+
+    pub fn cascade_offset(index: usize) -> (i32, i32) {
+        let step = CASCADE_STEP * (1 + (index % CASCADE_LENGTH) as i32);
+        (step, step)
+    }
+
+    // Top left corners for "n" items of the given size laid out in rows
+    // within the area (x, y, width, height) starting again when it's full.
+    pub fn tile_positions(
+        n: usize,
+        area: (i32, i32, i32, i32),
+        item_size: (i32, i32),
+    ) -> Vec<(i32, i32)> {
+        let (x, y, width, height) = area;
+        let (item_width, item_height) = (item_size.0.max(1), item_size.1.max(1));
+        let cols = (width / item_width).max(1) as usize;
+        let rows = (height / item_height).max(1) as usize;
+        (0..n)
+            .map(|i| {
+                let cell = i % (cols * rows);
+                (
+                    x + (cell % cols) as i32 * item_width,
+                    y + (cell / cols) as i32 * item_height,
+                )
+            })
+            .collect()
+    }
+
+    struct MonitorInfo {
+        // NB: identical monitors will share a name (the first is used)
+        name: String,
+        geometry: (i32, i32, i32, i32),
+        work_area: (i32, i32, i32, i32),
+    }
+
+    fn monitors() -> Vec<MonitorInfo> {
+        let mut monitors = vec![];
+        if let Some(display) = gdk::Display::get_default() {
+            for index in 0..display.get_n_monitors() {
+                if let Some(monitor) = display.get_monitor(index) {
+                    let name = match monitor.get_model() {
+                        Some(model) => model.to_string(),
+                        None => format!("Monitor {}", index),
+                    };
+                    let geometry = monitor.get_geometry();
+                    let work_area = monitor.get_workarea();
+                    monitors.push(MonitorInfo {
+                        name,
+                        geometry: (geometry.x, geometry.y, geometry.width, geometry.height),
+                        work_area: (work_area.x, work_area.y, work_area.width, work_area.height),
+                    });
+                }
+            }
+        }
+        monitors
+    }
+
+    // The index of the first area (x, y, width, height) containing "point"
+    pub fn area_index_at(areas: &[(i32, i32, i32, i32)], point: (i32, i32)) -> Option<usize> {
+        areas.iter().position(|(x, y, width, height)| {
+            point.0 >= *x && point.0 < x + width && point.1 >= *y && point.1 < y + height
+        })
+    }
+
+    // Keep a window's top left corner at least "margin" inside the area's
+    // right and bottom edges (so that it can still be grabbed)
+    pub fn clamp_to_area(point: (i32, i32), area: (i32, i32, i32, i32), margin: i32) -> (i32, i32) {
+        let (x, y, width, height) = area;
+        (
+            point.0.min(x + (width - margin).max(0)).max(x),
+            point.1.min(y + (height - margin).max(0)).max(y),
+        )
+    }
+
+    fn work_area() -> (i32, i32, i32, i32) {
+        if let Some(display) = gdk::Display::get_default() {
+            if let Some(monitor) = display
+                .get_primary_monitor()
+                .or_else(|| display.get_monitor(0))
+            {
+                let rect = monitor.get_workarea();
+                return (rect.x, rect.y, rect.width, rect.height);
+            }
+        }
+        (0, 0, 1024, 768)
+    }
+
+    pub fn cascade_dialogs(dialogs: &[gtk::Dialog]) {
+        let (x, y, _, _) = work_area();
+        for (index, dialog) in dialogs.iter().enumerate() {
+            let (dx, dy) = cascade_offset(index);
+            dialog.move_(x + dx, y + dy);
+            dialog.present();
+        }
+    }
+
+    pub fn tile_dialogs(dialogs: &[gtk::Dialog]) {
+        let item_size = dialogs.iter().fold((0, 0), |size, dialog| {
+            let (width, height) = dialog.get_size();
+            (size.0.max(width), size.1.max(height))
+        });
+        let positions = tile_positions(dialogs.len(), work_area(), item_size);
+        for (dialog, (x, y)) in dialogs.iter().zip(positions.iter()) {
+            dialog.move_(*x, *y);
+            dialog.present();
+        }
+    }
+
+    pub const DISPLAY_DIALOGS_MENU_KEYS: [&str; 3] =
+        ["cascade_dialogs", "tile_dialogs", "close_dialogs"];
+
+    // Widgets that keep track of the display dialogs that they've opened
+    pub trait DisplayDialogHost {
+        fn display_dialogs(&self) -> Vec<gtk::Dialog>;
+
+        fn cascade_display_dialogs(&self) {
+            cascade_dialogs(&self.display_dialogs())
+        }
+
+        fn tile_display_dialogs(&self) {
+            tile_dialogs(&self.display_dialogs())
+        }
+
+        fn close_display_dialogs(&self) {
+            for dialog in self.display_dialogs().iter() {
+                dialog.close();
+            }
+        }
+    }
+
+    pub fn append_display_dialogs_items<H>(menu: &WrappedMenu, host: &Rc<H>)
+    where
+        H: DisplayDialogHost + 'static,
+    {
+        let host_c = host.clone();
+        menu.append_item(
+            DISPLAY_DIALOGS_MENU_KEYS[0],
+            "Cascade Info Dialogs",
+            "Arrange the open paint information dialogs in a cascade",
+        )
+        .connect_activate(move |_| host_c.cascade_display_dialogs());
+        let host_c = host.clone();
+        menu.append_item(
+            DISPLAY_DIALOGS_MENU_KEYS[1],
+            "Tile Info Dialogs",
+            "Arrange the open paint information dialogs side by side",
+        )
+        .connect_activate(move |_| host_c.tile_display_dialogs());
+        let host_c = host.clone();
+        menu.append_item(
+            DISPLAY_DIALOGS_MENU_KEYS[2],
+            "Close All Info Dialogs",
+            "Close all of the open paint information dialogs",
+        )
+        .connect_activate(move |_| host_c.close_display_dialogs());
+    }
+
     pub trait DialogWrapper {
         fn dialog(&self) -> gtk::Dialog;
 
@@ -437,18 +1199,36 @@ pub mod dialogue {
 
 pub mod art_paint;
 pub mod basic_paint;
+pub mod blend_preview;
 pub mod cairox;
 pub mod characteristics;
 pub mod colln_paint;
+pub mod colour_difference;
 pub mod colour_edit;
+pub mod colour_management;
 pub mod colour_mix;
+pub mod colour_names;
+pub mod diagnostics;
+pub mod dynamic_paint;
 pub mod graticule;
 pub mod icons;
+pub mod interop;
 pub mod mixed_paint;
 pub mod model_paint;
+pub mod paint_types;
+pub mod palette_export;
+pub mod recent_files;
+pub mod screen_picker;
 pub mod series_paint;
 pub mod shape;
+pub mod spell_check;
 pub mod standards;
+pub mod temperature_cad;
+pub mod text_search;
+pub mod value_ramp;
+pub mod vision_check;
+pub mod wheel_pages;
+pub mod wheel_view;
 
 use std::env;
 
@@ -462,8 +1242,190 @@ pub fn app_name() -> String {
 
 #[cfg(test)]
 mod tests {
+    use super::dialogue::*;
+
     #[test]
     fn it_works() {
         assert_eq!(2 + 2, 4);
     }
+
+    #[test]
+    fn colour_plain_conversions() {
+        use super::colour::*;
+
+        let rgb = RGB::from([1.0, 0.5, 0.0]);
+        assert_eq!(rgb.into_u8_rgba(), (255, 128, 0, 255));
+        assert_eq!(rgb.into_css_hex(), "#FF8000");
+        assert_eq!(rgb.into_css_hex_with_alpha(0.5), "#FF800080");
+        assert_eq!(Colour::from(RGB::BLACK).into_css_hex(), "#000000");
+        assert_eq!(rgb_from_css_hex("#FFF"), Some(RGB::WHITE));
+        assert_eq!(rgb_from_css_hex("#00000080"), Some(RGB::BLACK));
+        let round_trip = rgb_from_css_hex(&rgb.into_css_hex()).unwrap();
+        assert_eq!(round_trip.into_css_hex(), "#FF8000");
+        assert_eq!(rgb_from_css_hex("FF8000"), None);
+        assert_eq!(rgb_from_css_hex("#GG8000"), None);
+        assert_eq!(rgb_from_css_hex("#FF80"), None);
+    }
+
+    #[test]
+    fn colour_derived_rgbs() {
+        use super::colour::*;
+
+        for rgb in [RGB::from([0.8, 0.3, 0.1]), RGB::from([0.5, 0.5, 0.5])].iter() {
+            let colour = Colour::from(*rgb);
+            assert_eq!(colour.best_foreground_rgb(), rgb.best_foreground_rgb());
+            assert_eq!(colour.monochrome_rgb(), rgb.monochrome_rgb());
+            assert_eq!(colour.warmth_rgb(), rgb.warmth_rgb());
+            let text = serde_json::to_string(&colour).unwrap();
+            assert!(!text.contains("derived"));
+            let copy: Colour = serde_json::from_str(&text).unwrap();
+            assert_eq!(copy.max_chroma_rgb(), colour.max_chroma_rgb());
+            assert_eq!(copy.warmth_rgb(), colour.warmth_rgb());
+        }
+        assert_eq!(
+            Colour::from(RGB::from([0.5, 0.5, 0.5])).max_chroma_rgb(),
+            RGB::from([0.5, 0.5, 0.5])
+        );
+    }
+
+    #[test]
+    fn colour_interpolation() {
+        use super::colour::*;
+
+        let red = RGB::from([1.0, 0.0, 0.0]);
+        let magenta = RGB::from([1.0, 0.0, 1.0]);
+        // the short way from red (0) to magenta (300) is through 330 not 150
+        let halfway = lerp_hcv(&red, &magenta, 0.5);
+        assert_eq!(halfway.into_css_hex(), "#FF0080");
+        let (hue, _, _) = hexcone_hcv(&halfway);
+        assert!((hue.unwrap() - 330.0).abs() < 0.5);
+        let (hue, _, _) = hexcone_hcv(&lerp_hcv(&magenta, &red, 0.5));
+        assert!((hue.unwrap() - 330.0).abs() < 0.5);
+        for rgb in [red, magenta, RGB::from([0.2, 0.6, 0.4])].iter() {
+            let (hue, chroma, value) = hexcone_hcv(rgb);
+            assert_eq!(
+                hexcone_rgb(hue, chroma, value).into_css_hex(),
+                rgb.into_css_hex()
+            );
+        }
+        let grey = RGB::from([0.5, 0.5, 0.5]);
+        let (hue, _, _) = hexcone_hcv(&lerp_hcv(&grey, &red, 0.5));
+        assert!(hue.unwrap().abs() < 0.5);
+        for space in [
+            InterpolationSpace::RGB,
+            InterpolationSpace::HCV,
+            InterpolationSpace::Lab,
+        ]
+        .iter()
+        {
+            let start = interpolate(&red, &magenta, 0.0, *space, Easing::EaseInOut);
+            let end = interpolate(&red, &magenta, 1.0, *space, Easing::EaseInOut);
+            assert_eq!(start.into_css_hex(), red.into_css_hex());
+            assert_eq!(end.into_css_hex(), magenta.into_css_hex());
+        }
+        assert_eq!(Easing::EaseIn.apply(0.5), 0.25);
+        assert_eq!(Easing::EaseOut.apply(2.0), 1.0);
+    }
+
+    #[test]
+    fn colour_vision_deficiency_simulation() {
+        use super::colour::*;
+        use std::str::FromStr;
+
+        let distance = |a: &RGB, b: &RGB| {
+            [CCI::Red, CCI::Green, CCI::Blue]
+                .iter()
+                .map(|cci| (a[*cci] - b[*cci]).abs())
+                .fold(0.0, f64::max)
+        };
+        let grey = RGB::from([0.5, 0.5, 0.5]);
+        for deficiency in VisionDeficiency::values() {
+            assert_eq!(
+                VisionDeficiency::from_str(deficiency.name()),
+                Ok(deficiency)
+            );
+            // greys look the same to everyone
+            assert!(distance(&deficiency.simulated_rgb(&grey), &grey) < 1.0e-3);
+        }
+        let red = RGB::from([0.8, 0.2, 0.1]);
+        let green = RGB::from([0.3, 0.6, 0.1]);
+        for deficiency in [VisionDeficiency::Protanopia, VisionDeficiency::Deuteranopia].iter() {
+            assert!(
+                distance(
+                    &deficiency.simulated_rgb(&red),
+                    &deficiency.simulated_rgb(&green)
+                ) < distance(&red, &green)
+            );
+        }
+        assert_eq!(VisionDeficiency::simulated_rgb_for(None, &red), red);
+        assert!(VisionDeficiency::from_str("achromatopsia").is_err());
+    }
+
+    #[test]
+    fn colour_temperatures() {
+        use super::colour::*;
+
+        assert_eq!(colour_temperature(&RGB::BLACK), None);
+        // sRGB white is D65
+        let white = colour_temperature(&RGB::WHITE).unwrap();
+        assert!((white - 6504.0).abs() < 50.0);
+        let warm = colour_temperature(&RGB::from([1.0, 0.8, 0.6])).unwrap();
+        let cool = colour_temperature(&RGB::from([0.7, 0.8, 1.0])).unwrap();
+        assert!(warm < white && white < cool);
+        for rgb in [RGB::RED, RGB::GREEN, RGB::BLUE].iter() {
+            let kelvin = colour_temperature(rgb).unwrap();
+            assert!(kelvin >= MIN_COLOUR_TEMPERATURE && kelvin <= MAX_COLOUR_TEMPERATURE);
+        }
+        // and the black body scale goes from red(ish) to blue(ish)
+        let low = colour_temperature_rgb(MIN_COLOUR_TEMPERATURE);
+        let high = colour_temperature_rgb(MAX_COLOUR_TEMPERATURE);
+        assert!(low[CCI::Red] > low[CCI::Blue]);
+        assert!(high[CCI::Blue] > high[CCI::Red]);
+        assert_eq!(colour_temperature_rgb(6600.0), RGB::WHITE);
+    }
+
+    #[test]
+    fn error_codes_are_stable() {
+        use super::art_paint::ArtPaintCharacteristics;
+        use super::error::*;
+        use std::str::FromStr;
+
+        for code in PaintErrorCode::values() {
+            assert_eq!(PaintErrorCode::from_str(code.as_str()), Ok(code));
+        }
+        assert!(PaintErrorCode::from_str("EPAINT_NONSENSE").is_err());
+        assert_eq!(PaintErrorCode::NotFound.to_string(), "EPAINT_NOT_FOUND");
+        let error: PaintError<ArtPaintCharacteristics> =
+            PaintErrorType::NotFound("Blue".to_string()).into();
+        assert_eq!(error.error_code(), PaintErrorCode::NotFound);
+        assert_eq!(error.message(), "Blue: not found.");
+        let error: PaintError<ArtPaintCharacteristics> =
+            std::io::Error::new(std::io::ErrorKind::Other, "oops").into();
+        assert_eq!(error.error_code().as_str(), "EPAINT_IO_ERROR");
+    }
+
+    #[test]
+    fn dialogue_cascade_and_tile_positions() {
+        assert_eq!(cascade_offset(0), (CASCADE_STEP, CASCADE_STEP));
+        assert_eq!(cascade_offset(CASCADE_LENGTH), cascade_offset(0));
+        assert_ne!(cascade_offset(1), cascade_offset(0));
+        let positions = tile_positions(5, (10, 20, 300, 200), (100, 100));
+        assert_eq!(
+            positions,
+            vec![(10, 20), (110, 20), (210, 20), (10, 120), (110, 120)]
+        );
+        let positions = tile_positions(7, (0, 0, 300, 200), (100, 100));
+        assert_eq!(positions[6], (0, 0));
+    }
+
+    #[test]
+    fn dialogue_monitor_placement() {
+        let areas = vec![(0, 0, 1920, 1080), (1920, 0, 1280, 1024)];
+        assert_eq!(area_index_at(&areas, (10, 10)), Some(0));
+        assert_eq!(area_index_at(&areas, (1920, 500)), Some(1));
+        assert_eq!(area_index_at(&areas, (3200, 500)), None);
+        assert_eq!(clamp_to_area((100, 100), areas[1], 30), (1920, 100));
+        assert_eq!(clamp_to_area((3190, 1020), areas[1], 30), (3170, 994));
+        assert_eq!(clamp_to_area((500, 500), areas[0], 30), (500, 500));
+    }
 }
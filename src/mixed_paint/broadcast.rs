@@ -0,0 +1,90 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::colour::*;
+
+// The single point through which a mixer's target colour is distributed so
+// that every wheel, CAD, dialog and component box shows the same target.
+// Receivers connected after a target has been set are given it immediately.
+pub struct TargetBroadcastCore {
+    current_target: RefCell<Option<Colour>>,
+    receivers: RefCell<Vec<Box<dyn Fn(Option<&Colour>)>>>,
+}
+
+pub type TargetBroadcast = Rc<TargetBroadcastCore>;
+
+impl TargetBroadcastCore {
+    pub fn new() -> TargetBroadcast {
+        Rc::new(TargetBroadcastCore {
+            current_target: RefCell::new(None),
+            receivers: RefCell::new(Vec::new()),
+        })
+    }
+
+    pub fn current_target(&self) -> Option<Colour> {
+        self.current_target.borrow().clone()
+    }
+
+    pub fn connect<F: 'static + Fn(Option<&Colour>)>(&self, receiver: F) {
+        receiver(self.current_target.borrow().as_ref());
+        self.receivers.borrow_mut().push(Box::new(receiver))
+    }
+
+    pub fn broadcast(&self, o_colour: Option<&Colour>) {
+        *self.current_target.borrow_mut() = o_colour.cloned();
+        for receiver in self.receivers.borrow().iter() {
+            receiver(o_colour);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.receivers.borrow().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recorder(broadcast: &TargetBroadcast) -> Rc<RefCell<Vec<Option<RGB>>>> {
+        let received: Rc<RefCell<Vec<Option<RGB>>>> = Rc::new(RefCell::new(Vec::new()));
+        let received_c = received.clone();
+        broadcast.connect(move |o_colour| {
+            received_c
+                .borrow_mut()
+                .push(o_colour.map(|colour| colour.rgb()))
+        });
+        received
+    }
+
+    #[test]
+    fn all_receivers_see_every_target() {
+        let broadcast = TargetBroadcastCore::new();
+        let receivers: Vec<_> = (0..4).map(|_| recorder(&broadcast)).collect();
+        assert_eq!(broadcast.len(), 4);
+        broadcast.broadcast(Some(&Colour::from(RGB::RED)));
+        broadcast.broadcast(None);
+        broadcast.broadcast(Some(&Colour::from(RGB::BLUE)));
+        for received in receivers.iter() {
+            assert_eq!(
+                *received.borrow(),
+                vec![None, Some(RGB::RED), None, Some(RGB::BLUE)]
+            );
+        }
+    }
+
+    #[test]
+    fn late_receivers_are_consistent() {
+        let broadcast = TargetBroadcastCore::new();
+        let early = recorder(&broadcast);
+        broadcast.broadcast(Some(&Colour::from(RGB::GREEN)));
+        let late = recorder(&broadcast);
+        assert_eq!(early.borrow().last(), late.borrow().last());
+        assert_eq!(
+            broadcast.current_target().map(|colour| colour.rgb()),
+            Some(RGB::GREEN)
+        );
+    }
+}
@@ -0,0 +1,417 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+// Trial recipes for the current target kept so that they can be compared
+// (and switched between) before the best of them is accepted.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use pw_gix::{
+    gdk,
+    glib::StaticType,
+    gtk::{self, prelude::*},
+    wrapper::*,
+};
+
+use crate::basic_paint::*;
+use crate::colour::*;
+use crate::colour_difference::*;
+use crate::series_paint::*;
+
+use super::*;
+
+#[derive(Debug, Clone)]
+pub struct Candidate<C: CharacteristicsInterface> {
+    pub sp_components: Vec<(SeriesPaint<C>, u32)>,
+    pub mp_components: Vec<(MixedPaint<C>, u32)>,
+    pub colour: Colour,
+    // Distance from the target when the candidate was taken
+    pub delta_e: Option<f64>,
+}
+
+impl<C: CharacteristicsInterface> Candidate<C> {
+    pub fn new(
+        sp_components: Vec<(SeriesPaint<C>, u32)>,
+        mp_components: Vec<(MixedPaint<C>, u32)>,
+        colour: &Colour,
+        o_target: Option<&Colour>,
+        metric: DeltaEMetric,
+    ) -> Candidate<C> {
+        Candidate {
+            sp_components,
+            mp_components,
+            colour: *colour,
+            delta_e: o_target.map(|target| metric.delta_e(&target.rgb(), &colour.rgb())),
+        }
+    }
+
+    pub fn recipe(&self) -> String {
+        let mut parts: Vec<String> = self
+            .sp_components
+            .iter()
+            .map(|(paint, parts)| format!("{} x {}", parts, paint.name()))
+            .collect();
+        parts.extend(
+            self.mp_components
+                .iter()
+                .map(|(paint, parts)| format!("{} x {}", parts, paint.name())),
+        );
+        parts.join(", ")
+    }
+
+    fn same_recipe(&self, other: &Candidate<C>) -> bool {
+        self.sp_components == other.sp_components && self.mp_components == other.mp_components
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CandidateSet<C: CharacteristicsInterface> {
+    candidates: Vec<Candidate<C>>,
+}
+
+impl<C: CharacteristicsInterface> Default for CandidateSet<C> {
+    fn default() -> CandidateSet<C> {
+        CandidateSet { candidates: vec![] }
+    }
+}
+
+impl<C: CharacteristicsInterface> CandidateSet<C> {
+    pub fn len(&self) -> usize {
+        self.candidates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.candidates.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Candidate<C>> {
+        self.candidates.get(index)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Candidate<C>> {
+        self.candidates.iter()
+    }
+
+    // Returns the index of the candidate (a recipe that's already present
+    // isn't added again)
+    pub fn add(&mut self, candidate: Candidate<C>) -> usize {
+        if let Some(index) = self
+            .candidates
+            .iter()
+            .position(|c| c.same_recipe(&candidate))
+        {
+            index
+        } else {
+            self.candidates.push(candidate);
+            self.candidates.len() - 1
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<Candidate<C>> {
+        if index < self.candidates.len() {
+            Some(self.candidates.remove(index))
+        } else {
+            None
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.candidates.clear();
+    }
+
+    // The candidate closest to the target
+    pub fn best_index(&self) -> Option<usize> {
+        self.candidates
+            .iter()
+            .enumerate()
+            .filter_map(|(index, c)| c.delta_e.map(|d| (index, d)))
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(index, _)| index)
+    }
+}
+
+const CL_INDEX: u32 = 0;
+const CL_RGB: u32 = 1;
+const CL_LABEL: u32 = 2;
+const CL_DELTA_E: u32 = 3;
+const CL_RECIPE: u32 = 4;
+
+fn text_column(title: &str, col_id: u32) -> gtk::TreeViewColumn {
+    let col = gtk::TreeViewColumn::new();
+    col.set_title(title);
+    col.set_resizable(true);
+    let cell = gtk::CellRendererText::new();
+    col.pack_start(&cell, true);
+    col.add_attribute(&cell, "text", col_id as i32);
+    col
+}
+
+#[derive(PWO, Wrapper)]
+pub struct CandidateSetViewCore<C: CharacteristicsInterface + 'static> {
+    vbox: gtk::Box,
+    list_store: gtk::ListStore,
+    view: gtk::TreeView,
+    candidate_set: RefCell<CandidateSet<C>>,
+    snapshot_btn: gtk::Button,
+    use_btn: gtk::Button,
+    remove_btn: gtk::Button,
+    accept_best_btn: gtk::Button,
+    snapshot_callbacks: RefCell<Vec<Box<dyn Fn()>>>,
+    use_candidate_callbacks: RefCell<Vec<Box<dyn Fn(&Candidate<C>)>>>,
+    accept_candidate_callbacks: RefCell<Vec<Box<dyn Fn(&Candidate<C>)>>>,
+}
+
+impl<C: CharacteristicsInterface + 'static> CandidateSetViewCore<C> {
+    pub fn len(&self) -> usize {
+        self.candidate_set.borrow().len()
+    }
+
+    pub fn add_candidate(&self, candidate: Candidate<C>) {
+        let index = self.candidate_set.borrow_mut().add(candidate);
+        self.refill();
+        if let Some(iter) = self.list_store.iter_nth_child(None, index as i32) {
+            self.view.get_selection().select_iter(&iter);
+        }
+    }
+
+    pub fn clear(&self) {
+        self.candidate_set.borrow_mut().clear();
+        self.refill();
+    }
+
+    pub fn set_snapshot_sensitive(&self, sensitive: bool) {
+        self.snapshot_btn.set_sensitive(sensitive);
+    }
+
+    fn refill(&self) {
+        self.list_store.clear();
+        let candidate_set = self.candidate_set.borrow();
+        let o_best = candidate_set.best_index();
+        for (index, candidate) in candidate_set.iter().enumerate() {
+            let label = if Some(index) == o_best {
+                format!("#{} (best)", index + 1)
+            } else {
+                format!("#{}", index + 1)
+            };
+            let delta_e = match candidate.delta_e {
+                Some(delta_e) => format!("{:.2}", delta_e),
+                None => "-".to_string(),
+            };
+            self.list_store.insert_with_values(
+                None,
+                &[CL_INDEX, CL_RGB, CL_LABEL, CL_DELTA_E, CL_RECIPE],
+                &[
+                    &(index as u32),
+                    &candidate.colour.rgb().into_gdk_rgba(),
+                    &label,
+                    &delta_e,
+                    &candidate.recipe(),
+                ],
+            );
+        }
+        self.accept_best_btn.set_sensitive(o_best.is_some());
+        self.update_selection_sensitivities();
+    }
+
+    fn selected_index(&self) -> Option<usize> {
+        let (model, iter) = self.view.get_selection().get_selected()?;
+        model
+            .get_value(&iter, CL_INDEX as i32)
+            .get::<u32>()
+            .ok()
+            .and_then(|index| index)
+            .map(|index| index as usize)
+    }
+
+    fn update_selection_sensitivities(&self) {
+        let selected = self.selected_index().is_some();
+        self.use_btn.set_sensitive(selected);
+        self.remove_btn.set_sensitive(selected);
+    }
+
+    fn use_selected(&self) {
+        if let Some(index) = self.selected_index() {
+            let o_candidate = self.candidate_set.borrow().get(index).cloned();
+            if let Some(candidate) = o_candidate {
+                for callback in self.use_candidate_callbacks.borrow().iter() {
+                    callback(&candidate)
+                }
+            }
+        }
+    }
+
+    fn remove_selected(&self) {
+        if let Some(index) = self.selected_index() {
+            self.candidate_set.borrow_mut().remove(index);
+            self.refill();
+        }
+    }
+
+    fn accept_best(&self) {
+        let o_candidate = {
+            let candidate_set = self.candidate_set.borrow();
+            candidate_set
+                .best_index()
+                .and_then(|index| candidate_set.get(index).cloned())
+        };
+        if let Some(candidate) = o_candidate {
+            for callback in self.accept_candidate_callbacks.borrow().iter() {
+                callback(&candidate)
+            }
+        }
+    }
+
+    // The receiver should respond by adding a candidate for the current mixture
+    pub fn connect_snapshot_requested<F: 'static + Fn()>(&self, callback: F) {
+        self.snapshot_callbacks
+            .borrow_mut()
+            .push(Box::new(callback))
+    }
+
+    pub fn connect_use_candidate<F: 'static + Fn(&Candidate<C>)>(&self, callback: F) {
+        self.use_candidate_callbacks
+            .borrow_mut()
+            .push(Box::new(callback))
+    }
+
+    pub fn connect_accept_candidate<F: 'static + Fn(&Candidate<C>)>(&self, callback: F) {
+        self.accept_candidate_callbacks
+            .borrow_mut()
+            .push(Box::new(callback))
+    }
+}
+
+pub type CandidateSetView<C> = Rc<CandidateSetViewCore<C>>;
+
+pub trait CandidateSetViewInterface<C: CharacteristicsInterface + 'static> {
+    fn create() -> CandidateSetView<C>;
+}
+
+impl<C: CharacteristicsInterface + 'static> CandidateSetViewInterface<C> for CandidateSetView<C> {
+    fn create() -> CandidateSetView<C> {
+        let list_store = gtk::ListStore::new(&[
+            u32::static_type(),
+            gdk::RGBA::static_type(),
+            String::static_type(),
+            String::static_type(),
+            String::static_type(),
+        ]);
+        let view = gtk::TreeView::with_model(&list_store);
+        view.set_headers_visible(true);
+        view.set_tooltip_column(CL_RECIPE as i32);
+        view.get_selection().set_mode(gtk::SelectionMode::Single);
+        view.append_column(&swatch_tv_column(CL_RGB as i32));
+        view.append_column(&text_column("Candidate", CL_LABEL));
+        view.append_column(&text_column("ΔE", CL_DELTA_E));
+        view.append_column(&text_column("Recipe", CL_RECIPE));
+
+        let csv = Rc::new(CandidateSetViewCore::<C> {
+            vbox: gtk::Box::new(gtk::Orientation::Vertical, 0),
+            list_store,
+            view,
+            candidate_set: RefCell::new(CandidateSet::default()),
+            snapshot_btn: gtk::Button::with_label("Snapshot"),
+            use_btn: gtk::Button::with_label("Use"),
+            remove_btn: gtk::Button::with_label("Remove"),
+            accept_best_btn: gtk::Button::with_label("Accept Best"),
+            snapshot_callbacks: RefCell::new(Vec::new()),
+            use_candidate_callbacks: RefCell::new(Vec::new()),
+            accept_candidate_callbacks: RefCell::new(Vec::new()),
+        });
+
+        csv.snapshot_btn.set_tooltip_text(Some(
+            "Keep the current parts as a candidate recipe for this target",
+        ));
+        csv.use_btn
+            .set_tooltip_text(Some("Put the selected candidate's parts back in the mixer"));
+        csv.remove_btn
+            .set_tooltip_text(Some("Discard the selected candidate"));
+        csv.accept_best_btn.set_tooltip_text(Some(
+            "Accept the candidate closest to the target as the new mixture",
+        ));
+
+        let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 2);
+        hbox.pack_start(&csv.snapshot_btn, true, true, 0);
+        hbox.pack_start(&csv.use_btn, true, true, 0);
+        hbox.pack_start(&csv.remove_btn, true, true, 0);
+        hbox.pack_start(&csv.accept_best_btn, true, true, 0);
+        csv.vbox.pack_start(&hbox, false, false, 0);
+        let adj: Option<&gtk::Adjustment> = None;
+        let scrolled_window = gtk::ScrolledWindow::new(adj, adj);
+        scrolled_window.set_size_request(-1, 80);
+        scrolled_window.add(&csv.view);
+        csv.vbox.pack_start(&scrolled_window, true, true, 0);
+        csv.vbox.show_all();
+
+        let csv_c = csv.clone();
+        csv.snapshot_btn.connect_clicked(move |_| {
+            for callback in csv_c.snapshot_callbacks.borrow().iter() {
+                callback()
+            }
+        });
+        let csv_c = csv.clone();
+        csv.use_btn.connect_clicked(move |_| csv_c.use_selected());
+        let csv_c = csv.clone();
+        csv.view
+            .connect_row_activated(move |_, _, _| csv_c.use_selected());
+        let csv_c = csv.clone();
+        csv.remove_btn
+            .connect_clicked(move |_| csv_c.remove_selected());
+        let csv_c = csv.clone();
+        csv.accept_best_btn
+            .connect_clicked(move |_| csv_c.accept_best());
+        let csv_c = csv.clone();
+        csv.view
+            .get_selection()
+            .connect_changed(move |_| csv_c.update_selection_sensitivities());
+
+        csv.refill();
+
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::art_paint::*;
+    use crate::colln_paint::collection::*;
+    use std::str::FromStr;
+
+    fn series_paints() -> Vec<ArtSeriesPaint> {
+        let text = "Manufacturer: Imaginary\nSeries: Series\n\
+             ArtPaint(name=\"Black\", rgb=#000000, transparency=\"O\", permanence=\"A\", notes=\"\")\n\
+             ArtPaint(name=\"White\", rgb=#FFFFFF, transparency=\"O\", permanence=\"A\", notes=\"\")\n";
+        ArtPaintSeries::from_spec(&ArtPaintSeriesSpec::from_str(text).unwrap()).get_paints()
+    }
+
+    #[test]
+    fn candidate_set_keeps_distinct_recipes() {
+        let paints = series_paints();
+        let target = Colour::from(RGB::from([0.7, 0.7, 0.7]));
+        let candidate = |black: u32, white: u32, rgb: f64| {
+            Candidate::<ArtPaintCharacteristics>::new(
+                vec![(paints[0].clone(), black), (paints[1].clone(), white)],
+                vec![],
+                &Colour::from(RGB::from([rgb, rgb, rgb])),
+                Some(&target),
+            )
+        };
+        let mut candidate_set = CandidateSet::default();
+        assert_eq!(candidate_set.best_index(), None);
+        assert_eq!(candidate_set.add(candidate(1, 1, 0.5)), 0);
+        assert_eq!(candidate_set.add(candidate(1, 3, 0.75)), 1);
+        assert_eq!(candidate_set.add(candidate(1, 1, 0.5)), 0);
+        assert_eq!(candidate_set.len(), 2);
+        assert_eq!(candidate_set.best_index(), Some(1));
+        assert_eq!(
+            candidate_set.get(1).unwrap().recipe(),
+            "1 x Black, 3 x White"
+        );
+        assert!(candidate_set.remove(1).is_some());
+        assert!(candidate_set.remove(1).is_none());
+        assert_eq!(candidate_set.best_index(), Some(0));
+        candidate_set.clear();
+        assert!(candidate_set.is_empty());
+    }
+}
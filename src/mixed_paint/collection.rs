@@ -2,7 +2,9 @@
 
 use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
+use std::io;
 use std::marker::PhantomData;
+use std::path::Path;
 use std::rc::Rc;
 
 use num::Integer;
@@ -11,48 +13,80 @@ use gdk;
 use gtk;
 use gtk::prelude::*;
 
+use pw_gix::gtkx::dialog::*;
 use pw_gix::gtkx::list_store::*;
 use pw_gix::gtkx::menu::*;
 use pw_gix::gtkx::tree_view_column::*;
 use pw_gix::wrapper::*;
 
+use crate::app_name;
 use crate::basic_paint::*;
+use crate::colln_paint::collection::PaintUsage;
+use crate::colln_paint::editor::unique_paint_name;
 use crate::colour::*;
+use crate::colour_mix::*;
 use crate::dialogue::*;
 use crate::error::*;
+use crate::palette_export::*;
+use crate::text_search::*;
+use crate::value_ramp::*;
 
 use super::components::*;
 use super::display::*;
+use super::naming::*;
 use super::target::TargetColourInterface;
 use super::*;
 
 pub struct MixedPaintFactoryCore<C: CharacteristicsInterface> {
     last_mixture_id: Cell<u32>,
     paints: RefCell<Vec<MixedPaint<C>>>,
+    mixing_model: MixingModel,
+    naming_policy: RefCell<MixedPaintNamingPolicy>,
 }
 
 impl<C: CharacteristicsInterface> MixedPaintFactoryCore<C> {
-    fn find_name(&self, name: &str) -> Result<usize, usize> {
-        let result = self
-            .paints
+    // NB: the paints are kept in the order they were made (not by name)
+    fn find_name(&self, name: &str) -> Option<usize> {
+        self.paints
             .borrow()
-            .binary_search_by_key(&name.to_string(), |paint| paint.name());
-        result
+            .iter()
+            .position(|paint| paint.name() == name)
     }
 
     pub fn next_mixture_id(&self) -> u32 {
         self.last_mixture_id.get() + 1
     }
 
+    pub fn naming_policy(&self) -> MixedPaintNamingPolicy {
+        self.naming_policy.borrow().clone()
+    }
+
+    pub fn set_naming_policy(&self, naming_policy: MixedPaintNamingPolicy) {
+        *self.naming_policy.borrow_mut() = naming_policy;
+    }
+
+    // The name the next mixture will get (made unique if the policy
+    // yields one that's in use)
+    pub fn next_name(&self) -> String {
+        let name = self.naming_policy.borrow().name_for(self.next_mixture_id());
+        if self.has_paint_named(&name) {
+            unique_paint_name(&name, |name| self.has_paint_named(name))
+        } else {
+            name
+        }
+    }
+
     pub fn len(&self) -> usize {
         self.paints.borrow().len()
     }
 
+    pub fn mixing_model(&self) -> MixingModel {
+        self.mixing_model
+    }
+
     pub fn get_paint(&self, name: &str) -> Option<MixedPaint<C>> {
-        match self.find_name(name) {
-            Ok(index) => Some(self.paints.borrow()[index].clone()),
-            Err(_) => None,
-        }
+        self.find_name(name)
+            .map(|index| self.paints.borrow()[index].clone())
     }
 
     pub fn get_paints(&self) -> Vec<MixedPaint<C>> {
@@ -60,86 +94,188 @@ impl<C: CharacteristicsInterface> MixedPaintFactoryCore<C> {
     }
 
     pub fn has_paint_named(&self, name: &str) -> bool {
-        self.find_name(name).is_ok()
+        self.find_name(name).is_some()
     }
 
-    pub fn add_paint(
+    // The colour, characteristics and (reduced) components of a recipe
+    fn mix(
         &self,
-        notes: &str,
         sp_components: Vec<(SeriesPaint<C>, u32)>,
         mp_components: Vec<(MixedPaint<C>, u32)>,
-        matched_colour: Option<Colour>,
-    ) -> Result<MixedPaint<C>, PaintError<C>> {
+    ) -> Result<(Colour, C, Vec<PaintComponent<C>>), PaintError<C>> {
         let mut parts: Vec<u32> = sp_components.iter().map(|c| c.1).collect();
         parts.extend(mp_components.iter().map(|c| c.1));
         let gcd: u32 = parts.iter().fold(0, |gcd, p| gcd.gcd(&p));
         if gcd == 0 {
             return Err(PaintErrorType::NoSubstantiveComponents.into());
         }
-        let mut total_parts: u32 = parts.iter().sum();
-        total_parts /= gcd;
-        let mut new_rgb_array: [f64; 3] = [0.0, 0.0, 0.0];
         let mut p_components: Vec<PaintComponent<C>> = Vec::new();
-        let mut new_c_floats = vec![0.0_f64; C::tv_row_len()];
-        for (series_paint, mut parts) in sp_components {
+        for (series_paint, parts) in sp_components {
             if parts > 0 {
-                parts /= gcd;
-                let weight: f64 = parts as f64 / total_parts as f64;
-                let rgb = series_paint.rgb();
-                new_rgb_array[0] += rgb[CCI::Red] * weight;
-                new_rgb_array[1] += rgb[CCI::Green] * weight;
-                new_rgb_array[2] += rgb[CCI::Blue] * weight;
-                let floats = series_paint.characteristics().to_floats();
-                for (i, val) in new_c_floats.iter_mut().enumerate() {
-                    *val = *val + floats[i] * weight;
-                }
                 let paint = Paint::Series(series_paint);
-                p_components.push(PaintComponent { parts, paint });
+                p_components.push(PaintComponent {
+                    parts: parts / gcd,
+                    paint,
+                });
             }
         }
-        for (mixed_paint, mut parts) in mp_components {
+        for (mixed_paint, parts) in mp_components {
             if parts > 0 {
-                parts /= gcd;
-                let weight: f64 = parts as f64 / total_parts as f64;
-                let rgb = mixed_paint.rgb();
-                new_rgb_array[0] += rgb[CCI::Red] * weight;
-                new_rgb_array[1] += rgb[CCI::Green] * weight;
-                new_rgb_array[2] += rgb[CCI::Blue] * weight;
-                let floats = mixed_paint.characteristics().to_floats();
-                for (i, val) in new_c_floats.iter_mut().enumerate() {
-                    *val = *val + floats[i] * weight;
-                }
                 let paint = Paint::Mixed(mixed_paint);
-                p_components.push(PaintComponent { parts, paint });
+                p_components.push(PaintComponent {
+                    parts: parts / gcd,
+                    paint,
+                });
             }
         }
-        let name_num = self.last_mixture_id.get() + 1;
+        let colour = mixture_colour(&p_components, self.mixing_model)
+            .unwrap_or_else(|| panic!("File: {:?} Line: {:?}", file!(), line!()));
+        let characteristics = mixture_characteristics(&p_components);
+        Ok((colour, characteristics, p_components))
+    }
+
+    pub fn add_paint(
+        &self,
+        notes: &str,
+        sp_components: Vec<(SeriesPaint<C>, u32)>,
+        mp_components: Vec<(MixedPaint<C>, u32)>,
+        matched_colour: Option<Colour>,
+    ) -> Result<MixedPaint<C>, PaintError<C>> {
+        self.add_paint_with_provenance(notes, sp_components, mp_components, matched_colour, None)
+    }
+
+    // NB: the provenance is ignored if there's no matched colour
+    pub fn add_paint_with_provenance(
+        &self,
+        notes: &str,
+        sp_components: Vec<(SeriesPaint<C>, u32)>,
+        mp_components: Vec<(MixedPaint<C>, u32)>,
+        matched_colour: Option<Colour>,
+        o_provenance: Option<TargetProvenance>,
+    ) -> Result<MixedPaint<C>, PaintError<C>> {
+        let (colour, characteristics, p_components) = self.mix(sp_components, mp_components)?;
+        let name = self.next_name();
         let target_colour = if let Some(ref colour) = matched_colour {
-            let name = format!("Target #{:03}", name_num);
-            Some(TargetColour::create(colour, &name, notes))
+            Some(TargetColour::create_with_provenance(
+                colour,
+                &target_name_for(&name),
+                notes,
+                o_provenance,
+            ))
         } else {
             None
         };
-        self.last_mixture_id.set(name_num);
-        let new_rgb: RGB = new_rgb_array.into();
+        self.last_mixture_id.set(self.next_mixture_id());
         let mixed_paint = Rc::new(MixedPaintCore::<C> {
-            colour: Colour::from(new_rgb),
-            name: format!("Mix #{:03}", name_num),
+            colour: Cell::new(colour),
+            name: name,
             notes: RefCell::new(notes.to_string()),
-            characteristics: C::from_floats(&new_c_floats),
+            characteristics: Cell::new(characteristics),
             target_colour: target_colour,
-            components: Rc::new(p_components),
+            components: RefCell::new(Rc::new(p_components)),
+            recipe_history: vec![],
         });
         self.paints.borrow_mut().push(mixed_paint.clone());
         Ok(mixed_paint)
     }
 
+    // Replace the recipe of a mixture (that no other mixture uses) keeping
+    // its name and recording its old recipe in its history.
+    pub fn replace_recipe(
+        &self,
+        paint: &MixedPaint<C>,
+        notes: &str,
+        sp_components: Vec<(SeriesPaint<C>, u32)>,
+        mp_components: Vec<(MixedPaint<C>, u32)>,
+        matched_colour: Option<Colour>,
+    ) -> Result<MixedPaint<C>, PaintError<C>> {
+        let mut users = self.mixed_paints_using(&Paint::Mixed(paint.clone()));
+        if mp_components
+            .iter()
+            .any(|(mp, parts)| *parts > 0 && mp == paint)
+        {
+            users.push(paint.clone());
+        }
+        if users.len() > 0 {
+            return Err(PaintErrorType::BeingUsedBy(users).into());
+        }
+        let index = self
+            .find_name(&paint.name())
+            .ok_or(PaintError::from(PaintErrorType::NotFound(paint.name())))?;
+        let (colour, characteristics, p_components) = self.mix(sp_components, mp_components)?;
+        let target_colour = if let Some(ref colour) = matched_colour {
+            let name = match paint.target_colour() {
+                Some(target_colour) => target_colour.name(),
+                None => target_name_for(&paint.name()),
+            };
+            Some(TargetColour::create_with_provenance(
+                colour,
+                &name,
+                notes,
+                paint.target_provenance(),
+            ))
+        } else {
+            None
+        };
+        let mut recipe_history = paint.recipe_history.clone();
+        recipe_history.push(RecipeRevision {
+            colour: paint.colour(),
+            components: paint.components(),
+            replaced: Local::now(),
+        });
+        let mixed_paint = Rc::new(MixedPaintCore::<C> {
+            colour: Cell::new(colour),
+            name: paint.name(),
+            notes: RefCell::new(notes.to_string()),
+            characteristics: Cell::new(characteristics),
+            target_colour: target_colour,
+            components: RefCell::new(Rc::new(p_components)),
+            recipe_history: recipe_history,
+        });
+        self.paints.borrow_mut()[index] = mixed_paint.clone();
+        Ok(mixed_paint)
+    }
+
+    // Mixtures used by other mixtures can't be renamed as the users hold
+    // (and display) the original.
+    pub fn rename_paint(
+        &self,
+        paint: &MixedPaint<C>,
+        new_name: &str,
+    ) -> Result<MixedPaint<C>, PaintError<C>> {
+        let new_name = new_name.trim();
+        if new_name.len() == 0 {
+            return Err(PaintErrorType::MalformedText(new_name.to_string()).into());
+        }
+        if self.has_paint_named(new_name) {
+            return Err(PaintErrorType::AlreadyExists(new_name.to_string()).into());
+        }
+        let users = self.mixed_paints_using(&Paint::Mixed(paint.clone()));
+        if users.len() > 0 {
+            return Err(PaintErrorType::BeingUsedBy(users).into());
+        }
+        let index = self
+            .find_name(&paint.name())
+            .ok_or(PaintError::from(PaintErrorType::NotFound(paint.name())))?;
+        let mixed_paint = Rc::new(MixedPaintCore::<C> {
+            colour: Cell::new(paint.colour()),
+            name: new_name.to_string(),
+            notes: RefCell::new(paint.notes()),
+            characteristics: Cell::new(paint.characteristics()),
+            target_colour: paint.target_colour(),
+            components: RefCell::new(paint.components()),
+            recipe_history: paint.recipe_history.clone(),
+        });
+        self.paints.borrow_mut()[index] = mixed_paint.clone();
+        Ok(mixed_paint)
+    }
+
     pub fn remove_paint(&self, paint: &MixedPaint<C>) -> Result<(), PaintError<C>> {
         let users = self.mixed_paints_using(&Paint::Mixed(paint.clone()));
         if users.len() > 0 {
             return Err(PaintErrorType::BeingUsedBy(users).into());
         }
-        if let Ok(index) = self.find_name(&paint.name()) {
+        if let Some(index) = self.find_name(&paint.name()) {
             let old_paint = self.paints.borrow_mut().remove(index);
             if old_paint != *paint {
                 panic!("File: {} Line: {}", file!(), line!())
@@ -150,6 +286,112 @@ impl<C: CharacteristicsInterface> MixedPaintFactoryCore<C> {
         Ok(())
     }
 
+    // Bring the mixtures into line with the current versions of the series
+    // paints they use returning those whose colour or characteristics
+    // changed.  Mixtures are done in the order they were made so that
+    // those used as components are up to date before their users are.
+    pub fn rename_paint(
+        &self,
+        paint: &MixedPaint<C>,
+        new_name: &str,
+    ) -> Result<MixedPaint<C>, PaintError<C>> {
+        if self.components.is_being_used(paint) {
+            return Err(PaintErrorType::PartOfCurrentMixture.into());
+        };
+        let (_, iter) = self
+            .find_row_named(&paint.name())
+            .ok_or(PaintError::from(PaintErrorType::NotFound(paint.name())))?;
+        let new_paint = self.factory.rename_paint(paint, new_name)?;
+        self.components.remove_paint(paint);
+        // NB: closing a dialog removes it from the map so don't hold a borrow
+        let stale_dialogs: Vec<MixedPaintDisplayDialog<A, C>> = self
+            .mixed_paint_dialogs
+            .borrow()
+            .values()
+            .filter(|dialog| dialog.paint() == *paint)
+            .cloned()
+            .collect();
+        for dialog in stale_dialogs.iter() {
+            dialog.close();
+        }
+        for (col, value) in new_paint.tv_rows().iter().enumerate() {
+            self.list_store.set_value(&iter, col as u32, value);
+        }
+        self.inform_paint_renamed(paint, &new_paint);
+        Ok(new_paint)
+    }
+
+    pub fn connect_paint_renamed<F: 'static + Fn(&MixedPaint<C>, &MixedPaint<C>)>(
+        &self,
+        callback: F,
+    ) {
+        self.paint_renamed_callbacks
+            .borrow_mut()
+            .push(Box::new(callback))
+    }
+
+    fn inform_paint_renamed(&self, old_paint: &MixedPaint<C>, new_paint: &MixedPaint<C>) {
+        for callback in self.paint_renamed_callbacks.borrow().iter() {
+            callback(old_paint, new_paint);
+        }
+    }
+
+    fn ask_new_name(&self, paint: &MixedPaint<C>) -> Option<String> {
+        let title = format!("Rename \"{}\"", paint.name());
+        let dialog = self.new_dialog_with_buttons(
+            Some(&title),
+            gtk::DialogFlags::DESTROY_WITH_PARENT,
+            CANCEL_OK_BUTTONS,
+        );
+        dialog.set_default_response(gtk::ResponseType::Ok);
+        let entry = gtk::Entry::new();
+        entry.set_activates_default(true);
+        entry.set_text(&paint.name());
+        let content_area = dialog.get_content_area();
+        content_area.pack_start(&entry, false, false, 0);
+        content_area.show_all();
+        let mut o_text: Option<String> = None;
+        if gtk::ResponseType::from(dialog.run()) == gtk::ResponseType::Ok {
+            let text = entry.get_text().trim().to_string();
+            if text.len() > 0 && text != paint.name() {
+                o_text = Some(text);
+            }
+        }
+        unsafe { dialog.destroy() };
+        o_text
+    }
+
+    pub fn refresh_derived_values(&self, current: &[SeriesPaint<C>]) -> Vec<MixedPaint<C>> {
+        let mut changed: Vec<MixedPaint<C>> = Vec::new();
+        for mixed_paint in self.paints.borrow().iter() {
+            let mut is_changed = mixed_paint.update_series_components(current);
+            is_changed |= mixed_paint
+                .components()
+                .iter()
+                .any(|component| match component.paint {
+                    Paint::Mixed(ref paint) => changed.contains(paint),
+                    Paint::Series(_) => false,
+                });
+            if is_changed {
+                mixed_paint.recompute_colour(self.mixing_model);
+                mixed_paint.recompute_characteristics();
+                changed.push(mixed_paint.clone());
+            }
+        }
+        changed
+    }
+
+    // In the order that they were mixed
+    pub fn write_gpl_palette(&self, path: &Path, palette_name: &str) -> io::Result<()> {
+        let entries: Vec<(String, RGB)> = self
+            .paints
+            .borrow()
+            .iter()
+            .map(|paint| (paint.name(), paint.rgb()))
+            .collect();
+        write_gpl_palette(path, palette_name, &entries)
+    }
+
     pub fn series_paints_used(&self) -> Vec<SeriesPaint<C>> {
         let mut spu: Vec<SeriesPaint<C>> = Vec::new();
         for mixed_paint in self.paints.borrow().iter() {
@@ -164,6 +406,40 @@ impl<C: CharacteristicsInterface> MixedPaintFactoryCore<C> {
         spu
     }
 
+    // The number of mixtures that have "paint" as a (direct) component and
+    // the total parts of it that they contain
+    pub fn usage_of_series_paint(&self, paint: &SeriesPaint<C>) -> PaintUsage {
+        let mut usage = PaintUsage::default();
+        for mixed_paint in self.paints.borrow().iter() {
+            let parts: u32 = mixed_paint
+                .components()
+                .iter()
+                .filter(|component| match component.paint {
+                    Paint::Series(ref series_paint) => series_paint == paint,
+                    Paint::Mixed(_) => false,
+                })
+                .map(|component| component.parts)
+                .sum();
+            if parts > 0 {
+                usage.mixture_count += 1;
+                usage.total_parts += parts;
+            }
+        }
+        usage
+    }
+
+    // Sorted by paint
+    pub fn series_paint_usage(&self) -> Vec<(SeriesPaint<C>, PaintUsage)> {
+        self.series_paints_used()
+            .into_iter()
+            .map(|paint| {
+                let usage = self.usage_of_series_paint(&paint);
+                (paint, usage)
+            })
+            .filter(|(_, usage)| usage.mixture_count > 0)
+            .collect()
+    }
+
     pub fn mixed_paints_using(&self, paint: &Paint<C>) -> Vec<MixedPaint<C>> {
         self.paints
             .borrow()
@@ -186,19 +462,21 @@ impl<C: CharacteristicsInterface> MixedPaintFactoryCore<C> {
 pub type MixedPaintFactory<C> = Rc<MixedPaintFactoryCore<C>>;
 
 pub trait MixedPaintFactoryInterface<C: CharacteristicsInterface> {
-    fn create() -> MixedPaintFactory<C>;
+    fn create(mixing_model: MixingModel) -> MixedPaintFactory<C>;
 }
 
 impl<C> MixedPaintFactoryInterface<C> for MixedPaintFactory<C>
 where
     C: CharacteristicsInterface,
 {
-    fn create() -> MixedPaintFactory<C> {
+    fn create(mixing_model: MixingModel) -> MixedPaintFactory<C> {
         let last_mixture_id: Cell<u32> = Cell::new(0);
         let paints: RefCell<Vec<MixedPaint<C>>> = RefCell::new(Vec::new());
         Rc::new(MixedPaintFactoryCore::<C> {
             last_mixture_id,
             paints,
+            mixing_model,
+            naming_policy: RefCell::new(MixedPaintNamingPolicy::default()),
         })
     }
 }
@@ -223,10 +501,46 @@ where
     current_target: RefCell<Option<Colour>>,
     add_paint_callbacks: RefCell<Vec<Box<dyn Fn(&MixedPaint<C>)>>>,
     remove_paint_callbacks: RefCell<Vec<Box<dyn Fn(&MixedPaint<C>)>>>,
+    edit_recipe_callbacks: RefCell<Vec<Box<dyn Fn(&MixedPaint<C>)>>>,
+    add_favourite_callbacks: RefCell<Vec<Box<dyn Fn(&MixedPaint<C>)>>>,
+    paint_renamed_callbacks: RefCell<Vec<Box<dyn Fn(&MixedPaint<C>, &MixedPaint<C>)>>>,
     mixed_paint_dialogs: RefCell<HashMap<u32, MixedPaintDisplayDialog<A, C>>>,
+    notes_column: RefCell<Option<gtk::TreeViewColumn>>,
+    read_only: Cell<bool>,
     spec: PhantomData<A>,
 }
 
+impl<A, C> DisplayDialogHost for MixedPaintCollectionWidgetCore<A, C>
+where
+    A: ColourAttributesInterface + 'static,
+    C: CharacteristicsInterface + 'static,
+{
+    fn display_dialogs(&self) -> Vec<gtk::Dialog> {
+        let mut dialogs: Vec<gtk::Dialog> = self
+            .mixed_paint_dialogs
+            .borrow()
+            .values()
+            .map(|dialog| dialog.dialog())
+            .collect();
+        dialogs.extend(self.components.display_dialogs());
+        dialogs
+    }
+}
+
+impl<A, C> PaintListRows<C, MixedPaint<C>> for MixedPaintCollectionWidgetCore<A, C>
+where
+    A: ColourAttributesInterface + 'static,
+    C: CharacteristicsInterface + 'static,
+{
+    fn rows_list_store(&self) -> &gtk::ListStore {
+        &self.list_store
+    }
+
+    fn rows_paints(&self) -> Vec<MixedPaint<C>> {
+        self.factory.get_paints()
+    }
+}
+
 impl<A, C> MixedPaintCollectionWidgetCore<A, C>
 where
     A: ColourAttributesInterface + 'static,
@@ -236,27 +550,50 @@ where
         self.factory.next_mixture_id()
     }
 
-    fn get_mixed_paint_at(&self, posn: (f64, f64)) -> Option<MixedPaint<C>> {
+    pub fn next_name(&self) -> String {
+        self.factory.next_name()
+    }
+
+    pub fn set_naming_policy(&self, naming_policy: MixedPaintNamingPolicy) {
+        self.factory.set_naming_policy(naming_policy)
+    }
+
+    fn try_get_mixed_paint_at(&self, posn: (f64, f64)) -> PaintResult<Option<MixedPaint<C>>, C> {
         let x = posn.0 as i32;
         let y = posn.1 as i32;
         if let Some(location) = self.view.get_path_at_pos(x, y) {
             if let Some(path) = location.0 {
                 if let Some(iter) = self.list_store.get_iter(&path) {
-                    let name: String = self
-                        .list_store
-                        .get_value(&iter, MP_NAME)
-                        .get()
-                        .unwrap()
-                        .unwrap_or_else(|| panic!("File: {:?} Line: {:?}", file!(), line!()));
+                    let name = self.list_store.row_string(&iter, MP_NAME)?;
                     let paint = self
                         .factory
                         .get_paint(&name)
-                        .unwrap_or_else(|| panic!("File: {:?} Line: {:?}", file!(), line!()));
-                    return Some(paint);
+                        .ok_or(PaintError::from(PaintErrorType::ModelInconsistency(name)))?;
+                    return Ok(Some(paint));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn get_mixed_paint_at(&self, posn: (f64, f64)) -> Option<MixedPaint<C>> {
+        reported_row_value(self, self.try_get_mixed_paint_at(posn))
+    }
+
+    pub fn get_selected_paints(&self) -> Vec<MixedPaint<C>> {
+        let mut paints = vec![];
+        let (paths, _) = self.view.get_selection().get_selected_rows();
+        for path in paths.iter() {
+            if let Some(iter) = self.list_store.get_iter(path) {
+                let r_name = self.list_store.row_string(&iter, MP_NAME).map(Some);
+                if let Some(name) = reported_row_value(self, r_name) {
+                    if let Some(paint) = self.factory.get_paint(&name) {
+                        paints.push(paint);
+                    }
                 }
             }
         }
-        None
+        paints
     }
 
     pub fn connect_add_paint<F: 'static + Fn(&MixedPaint<C>)>(&self, callback: F) {
@@ -302,10 +639,24 @@ where
         mp_components: Vec<(MixedPaint<C>, u32)>,
         matched_colour: Option<Colour>,
     ) -> Result<MixedPaint<C>, PaintError<C>> {
-        match self
-            .factory
-            .add_paint(notes, sp_components, mp_components, matched_colour)
-        {
+        self.add_paint_with_provenance(notes, sp_components, mp_components, matched_colour, None)
+    }
+
+    pub fn add_paint_with_provenance(
+        &self,
+        notes: &str,
+        sp_components: Vec<(SeriesPaint<C>, u32)>,
+        mp_components: Vec<(MixedPaint<C>, u32)>,
+        matched_colour: Option<Colour>,
+        o_provenance: Option<TargetProvenance>,
+    ) -> Result<MixedPaint<C>, PaintError<C>> {
+        match self.factory.add_paint_with_provenance(
+            notes,
+            sp_components,
+            mp_components,
+            matched_colour,
+            o_provenance,
+        ) {
             Ok(mixed_paint) => {
                 self.list_store.append_row(&mixed_paint.tv_rows());
                 Ok(mixed_paint)
@@ -314,24 +665,17 @@ where
         }
     }
 
-    fn find_paint_named(&self, name: &str) -> Option<(i32, gtk::TreeIter)> {
-        self.list_store.find_row_where(|list_store, iter| {
-            list_store.get_value(iter, 0).get().unwrap() == Some(name)
-        })
-    }
-
-    fn set_notes_for_paint_at(&self, iter: &gtk::TreeIter, new_notes: &str) {
-        let o_paint_name: Option<String> = self.list_store.get_value(iter, MP_NAME).get().unwrap();
-        if let Some(ref paint_name) = o_paint_name {
-            if let Some(paint) = self.factory.get_paint(paint_name) {
-                paint.set_notes(new_notes);
-                self.list_store
-                    .set_value(iter, MP_NOTES as u32, &new_notes.into());
-            } else {
-                panic!("File: {} Line: {}", file!(), line!())
-            }
+    fn set_notes_for_paint_at(
+        &self,
+        iter: &gtk::TreeIter,
+        new_notes: &str,
+    ) -> Result<(), PaintError<C>> {
+        let paint_name = self.list_store.row_string(iter, MP_NAME)?;
+        if let Some(paint) = self.factory.get_paint(&paint_name) {
+            paint.set_notes(new_notes);
+            self.update_row_for(&paint)
         } else {
-            panic!("File: {} Line: {}", file!(), line!())
+            Err(PaintErrorType::NotFound(paint_name).into())
         }
     }
 
@@ -339,14 +683,93 @@ where
         if self.components.is_being_used(paint) {
             return Err(PaintErrorType::PartOfCurrentMixture.into());
         };
+        if !self.has_row_for(paint) {
+            return Err(PaintErrorType::NotFound(paint.name()).into());
+        };
         self.factory.remove_paint(paint)?;
         self.components.remove_paint(paint);
-        if let Some((_, iter)) = self.find_paint_named(&paint.name()) {
-            self.list_store.remove(&iter);
-        } else {
-            panic!("File: {} Line: {}", file!(), line!())
-        };
-        Ok(())
+        self.remove_row_for(paint)
+    }
+
+    pub fn replace_recipe(
+        &self,
+        paint: &MixedPaint<C>,
+        notes: &str,
+        sp_components: Vec<(SeriesPaint<C>, u32)>,
+        mp_components: Vec<(MixedPaint<C>, u32)>,
+        matched_colour: Option<Colour>,
+    ) -> Result<MixedPaint<C>, PaintError<C>> {
+        let new_paint = self.factory.replace_recipe(
+            paint,
+            notes,
+            sp_components,
+            mp_components,
+            matched_colour,
+        )?;
+        self.components.remove_paint(paint);
+        // NB: closing a dialog removes it from the map so don't hold a borrow
+        let stale_dialogs: Vec<MixedPaintDisplayDialog<A, C>> = self
+            .mixed_paint_dialogs
+            .borrow()
+            .values()
+            .filter(|dialog| dialog.paint() == *paint)
+            .cloned()
+            .collect();
+        for dialog in stale_dialogs.iter() {
+            dialog.close();
+        }
+        self.update_row_for(&new_paint)?;
+        Ok(new_paint)
+    }
+
+    pub fn refresh_derived_values(&self, current: &[SeriesPaint<C>]) -> Vec<MixedPaint<C>> {
+        let changed = self.factory.refresh_derived_values(current);
+        for paint in changed.iter() {
+            // NB: closing a dialog removes it from the map so don't hold a borrow
+            let stale_dialogs: Vec<MixedPaintDisplayDialog<A, C>> = self
+                .mixed_paint_dialogs
+                .borrow()
+                .values()
+                .filter(|dialog| dialog.paint() == *paint)
+                .cloned()
+                .collect();
+            for dialog in stale_dialogs.iter() {
+                dialog.close();
+            }
+            if let Err(err) = self.update_row_for(paint) {
+                self.report_error("Failed to update mixture", &err);
+            }
+        }
+        changed
+    }
+
+    pub fn mixed_paints_using_mixed_paint(&self, paint: &MixedPaint<C>) -> Vec<MixedPaint<C>> {
+        self.factory
+            .mixed_paints_using(&Paint::Mixed(paint.clone()))
+    }
+
+    pub fn connect_edit_recipe<F: 'static + Fn(&MixedPaint<C>)>(&self, callback: F) {
+        self.edit_recipe_callbacks
+            .borrow_mut()
+            .push(Box::new(callback))
+    }
+
+    fn inform_edit_recipe(&self, paint: &MixedPaint<C>) {
+        for callback in self.edit_recipe_callbacks.borrow().iter() {
+            callback(&paint);
+        }
+    }
+
+    pub fn connect_add_favourite<F: 'static + Fn(&MixedPaint<C>)>(&self, callback: F) {
+        self.add_favourite_callbacks
+            .borrow_mut()
+            .push(Box::new(callback))
+    }
+
+    fn inform_add_favourite(&self, paint: &MixedPaint<C>) {
+        for callback in self.add_favourite_callbacks.borrow().iter() {
+            callback(&paint);
+        }
     }
 
     pub fn series_paints_used(&self) -> Vec<SeriesPaint<C>> {
@@ -357,11 +780,41 @@ where
         self.factory.get_paints()
     }
 
+    pub fn write_gpl_palette(&self, path: &Path) -> io::Result<()> {
+        self.factory
+            .write_gpl_palette(path, &format!("{} Mixtures", app_name()))
+    }
+
+    pub fn usage_of_series_paint(&self, paint: &SeriesPaint<C>) -> PaintUsage {
+        self.factory.usage_of_series_paint(paint)
+    }
+
+    pub fn series_paint_usage(&self) -> Vec<(SeriesPaint<C>, PaintUsage)> {
+        self.factory.series_paint_usage()
+    }
+
     pub fn mixed_paints_using_series_paint(&self, paint: &SeriesPaint<C>) -> Vec<MixedPaint<C>> {
         self.factory.mixed_paints_using_series_paint(paint)
     }
 
     // Components interface
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.get()
+    }
+
+    // Lock the collection (e.g. a finished project's mixtures kept for
+    // reference) against additions, deletions, renaming and notes editing
+    pub fn set_read_only(&self, read_only: bool) {
+        self.read_only.set(read_only);
+        if let Some(ref notes_column) = *self.notes_column.borrow() {
+            for cell in notes_column.get_cells().iter() {
+                if let Ok(text_cell) = cell.clone().downcast::<gtk::CellRendererText>() {
+                    text_cell.set_property_editable(!read_only);
+                }
+            }
+        }
+    }
+
     pub fn components(&self) -> MixedPaintComponentBox<A, C> {
         self.components.clone()
     }
@@ -374,7 +827,10 @@ where
     A: ColourAttributesInterface + 'static,
     C: CharacteristicsInterface + 'static,
 {
-    fn create(mixing_mode: MixingMode) -> MixedPaintCollectionWidget<A, C>;
+    fn create(
+        mixing_mode: MixingMode,
+        mixing_model: MixingModel,
+    ) -> MixedPaintCollectionWidget<A, C>;
 }
 
 impl<A, C> MixedPaintCollectionWidgetInterface<A, C> for MixedPaintCollectionWidget<A, C>
@@ -382,12 +838,16 @@ where
     A: ColourAttributesInterface + 'static,
     C: CharacteristicsInterface + 'static,
 {
-    fn create(mixing_mode: MixingMode) -> MixedPaintCollectionWidget<A, C> {
+    fn create(
+        mixing_mode: MixingMode,
+        mixing_model: MixingModel,
+    ) -> MixedPaintCollectionWidget<A, C> {
         let len = MixedPaint::<C>::tv_row_len();
         let list_store = gtk::ListStore::new(&MIXED_PAINT_ROW_SPEC[0..len]);
         let view = gtk::TreeView::with_model(&list_store.clone());
         view.set_headers_visible(true);
-        view.get_selection().set_mode(gtk::SelectionMode::Single);
+        view.get_selection().set_mode(gtk::SelectionMode::Multiple);
+        set_normalised_search(&view, &[MP_NAME, MP_NOTES], SearchNormaliser::default());
 
         let adj: Option<&gtk::Adjustment> = None;
         let mspl = Rc::new(MixedPaintCollectionWidgetCore::<A, C> {
@@ -395,17 +855,23 @@ where
             scrolled_window: gtk::ScrolledWindow::new(adj, adj),
             list_store: list_store,
             popup_menu: WrappedMenu::new(&vec![]),
-            factory: MixedPaintFactory::create(),
+            factory: MixedPaintFactory::create(mixing_model),
             components: MixedPaintComponentBox::<A, C>::create_with(4, true),
             view: view,
             chosen_paint: RefCell::new(None),
             current_target: RefCell::new(None),
             add_paint_callbacks: RefCell::new(Vec::new()),
             remove_paint_callbacks: RefCell::new(Vec::new()),
+            edit_recipe_callbacks: RefCell::new(Vec::new()),
+            add_favourite_callbacks: RefCell::new(Vec::new()),
+            paint_renamed_callbacks: RefCell::new(Vec::new()),
             mixed_paint_dialogs: RefCell::new(HashMap::new()),
+            notes_column: RefCell::new(None),
+            read_only: Cell::new(false),
             spec: PhantomData,
         });
 
+        mspl.view.append_column(&swatch_tv_column(MP_RGB));
         mspl.view.append_column(&simple_text_column(
             "Name", MP_NAME, MP_NAME, MP_RGB, MP_RGB_FG, -1, true,
         ));
@@ -430,14 +896,18 @@ where
             -1,
             true,
             move |_, tree_path, new_notes| {
+                if mspl_c.is_read_only() {
+                    return;
+                }
                 if let Some(ref iter) = mspl_c.list_store.get_iter(&tree_path) {
-                    mspl_c.set_notes_for_paint_at(iter, new_notes);
-                } else {
-                    panic!("File: {} Line: {}", file!(), line!())
+                    if let Err(err) = mspl_c.set_notes_for_paint_at(iter, new_notes) {
+                        mspl_c.report_error("Failed to set notes", &err);
+                    }
                 }
             },
         );
         mspl.view.append_column(&notes_col);
+        *mspl.notes_column.borrow_mut() = Some(notes_col);
         for col in A::tv_columns() {
             mspl.view.append_column(&col);
         }
@@ -507,6 +977,56 @@ where
                 }
             });
 
+        let mspl_c = mspl.clone();
+        mspl.popup_menu
+            .append_item(
+                "edit_recipe",
+                "Edit Recipe",
+                "Reopen this mixture's components in the mixing area to change its recipe",
+            )
+            .connect_activate(move |_| {
+                if let Some(ref paint) = *mspl_c.chosen_paint.borrow() {
+                    mspl_c.inform_edit_recipe(paint);
+                } else {
+                    panic!("File: {:?} Line: {:?} SHOULDN'T GET HERE", file!(), line!())
+                }
+            });
+
+        let mspl_c = mspl.clone();
+        mspl.popup_menu
+            .append_item(
+                "add_favourite",
+                "Add to Favourites",
+                "Bookmark this mixture's recipe (with tags) for use in later sessions",
+            )
+            .connect_activate(move |_| {
+                if let Some(ref paint) = *mspl_c.chosen_paint.borrow() {
+                    mspl_c.inform_add_favourite(paint);
+                } else {
+                    panic!("File: {:?} Line: {:?} SHOULDN'T GET HERE", file!(), line!())
+                }
+            });
+
+        let mspl_c = mspl.clone();
+        mspl.popup_menu
+            .append_item(
+                "rename",
+                "Rename",
+                "Give this mixture a different (unique) name",
+            )
+            .connect_activate(move |_| {
+                let o_paint = mspl_c.chosen_paint.borrow().clone();
+                if let Some(ref paint) = o_paint {
+                    if let Some(new_name) = mspl_c.ask_new_name(paint) {
+                        if let Err(err) = mspl_c.rename_paint(paint, &new_name) {
+                            mspl_c.report_error("Failed to rename mixture", &err);
+                        }
+                    }
+                } else {
+                    panic!("File: {:?} Line: {:?} SHOULDN'T GET HERE", file!(), line!())
+                }
+            });
+
         let mspl_c = mspl.clone();
         mspl.popup_menu
             .append_item(
@@ -522,6 +1042,35 @@ where
                 }
             });
 
+        let mspl_c = mspl.clone();
+        mspl.popup_menu
+            .append_item(
+                "audit_ramp",
+                "Audit Value Ramp",
+                "Report the value steps between the selected paints from darkest to lightest",
+            )
+            .connect_activate(move |_| {
+                let members: Vec<(String, RGB)> = mspl_c
+                    .get_selected_paints()
+                    .iter()
+                    .map(|paint| (paint.name(), paint.rgb()))
+                    .collect();
+                show_value_ramp_audit(&members, &mspl_c);
+            });
+
+        let mspl_c = mspl.clone();
+        mspl.popup_menu
+            .append_item(
+                "export_palette",
+                "Export GIMP Palette...",
+                "Save the mixed paints as a GIMP/Inkscape palette (.gpl) file",
+            )
+            .connect_activate(move |_| {
+                export_gpl_palette(&*mspl_c, |path| mspl_c.write_gpl_palette(path))
+            });
+
+        append_display_dialogs_items(&mspl.popup_menu, &mspl);
+
         let mspl_c = mspl.clone();
         mspl.view.connect_button_press_event(move |_, event| {
             if event.get_event_type() == gdk::EventType::ButtonPress {
@@ -530,15 +1079,40 @@ where
                     mspl_c
                         .popup_menu
                         .set_sensitivities(o_paint.is_some(), &["info"]);
-                    mspl_c
-                        .popup_menu
-                        .set_sensitivities(o_paint.is_some(), &["add", "delete"]);
+                    mspl_c.popup_menu.set_sensitivities(
+                        o_paint.is_some(),
+                        &["add", "rename", "delete", "edit_recipe", "add_favourite"],
+                    );
+                    if mspl_c.is_read_only() {
+                        mspl_c
+                            .popup_menu
+                            .set_sensitivities(false, &["add", "rename", "delete", "edit_recipe"]);
+                    }
                     let have_listeners = mspl_c.components().has_listeners();
                     mspl_c.popup_menu.set_visibilities(have_listeners, &["add"]);
                     let have_listeners = mspl_c.remove_paint_callbacks.borrow().len() > 0;
                     mspl_c
                         .popup_menu
                         .set_visibilities(have_listeners, &["delete"]);
+                    let have_listeners = mspl_c.edit_recipe_callbacks.borrow().len() > 0;
+                    mspl_c
+                        .popup_menu
+                        .set_visibilities(have_listeners, &["edit_recipe"]);
+                    let have_listeners = mspl_c.add_favourite_callbacks.borrow().len() > 0;
+                    mspl_c
+                        .popup_menu
+                        .set_visibilities(have_listeners, &["add_favourite"]);
+                    let have_ramp = mspl_c.get_selected_paints().len() > 1;
+                    mspl_c
+                        .popup_menu
+                        .set_sensitivities(have_ramp, &["audit_ramp"]);
+                    mspl_c
+                        .popup_menu
+                        .set_sensitivities(mspl_c.factory.len() > 0, &["export_palette"]);
+                    let has_dialogs = mspl_c.display_dialogs().len() > 0;
+                    mspl_c
+                        .popup_menu
+                        .set_sensitivities(has_dialogs, &DISPLAY_DIALOGS_MENU_KEYS);
                     *mspl_c.chosen_paint.borrow_mut() = o_paint;
                     mspl_c.popup_menu.popup_at_event(event);
                     return Inhibit(true);
@@ -556,5 +1130,126 @@ where
 
 #[cfg(test)]
 mod tests {
-    //use super::*;
+    use super::*;
+    use crate::art_paint::*;
+    use crate::colln_paint::collection::*;
+    use std::str::FromStr;
+
+    fn series(black_rgb: &str) -> ArtPaintSeries {
+        let text = format!(
+            "Manufacturer: Imaginary\nSeries: Series\n\
+             ArtPaint(name=\"Black\", rgb={}, transparency=\"O\", permanence=\"A\", notes=\"\")\n\
+             ArtPaint(name=\"White\", rgb=#FFFFFF, transparency=\"O\", permanence=\"A\", notes=\"\")\n",
+            black_rgb
+        );
+        ArtPaintSeries::from_spec(&ArtPaintSeriesSpec::from_str(&text).unwrap())
+    }
+
+    #[test]
+    fn derived_values_refreshed() {
+        let factory = MixedPaintFactory::<ArtPaintCharacteristics>::create(MixingModel::SRGB);
+        let paints = series("#000000").get_paints();
+        let grey = factory
+            .add_paint(
+                "",
+                vec![(paints[0].clone(), 2), (paints[1].clone(), 2)],
+                vec![],
+                None,
+            )
+            .unwrap();
+        let tint = factory
+            .add_paint(
+                "",
+                vec![(paints[1].clone(), 1)],
+                vec![(grey.clone(), 1)],
+                None,
+            )
+            .unwrap();
+        assert!(factory.refresh_derived_values(&paints).is_empty());
+        let old_grey_colour = grey.colour();
+        let old_tint_colour = tint.colour();
+
+        let corrected = series("#202020").get_paints();
+        let changed = factory.refresh_derived_values(&corrected);
+        assert_eq!(changed, vec![grey.clone(), tint.clone()]);
+        assert!(grey.colour() != old_grey_colour);
+        assert!(tint.colour() != old_tint_colour);
+        assert_eq!(
+            grey.colour(),
+            mixture_colour(&grey.components(), MixingModel::SRGB).unwrap()
+        );
+        assert!(factory.refresh_derived_values(&corrected).is_empty());
+    }
+
+    #[test]
+    fn naming_and_renaming() {
+        let factory = MixedPaintFactory::<ArtPaintCharacteristics>::create(MixingModel::SRGB);
+        let paints = series("#000000").get_paints();
+        let grey = factory
+            .add_paint(
+                "",
+                vec![(paints[0].clone(), 1), (paints[1].clone(), 1)],
+                vec![],
+                None,
+            )
+            .unwrap();
+        assert_eq!(grey.name(), "Mix #001");
+        factory.set_naming_policy(MixedPaintNamingPolicy::numbered("Batch ", 0));
+        assert_eq!(factory.next_name(), "Batch 2");
+        let tint = factory
+            .add_paint(
+                "",
+                vec![(paints[1].clone(), 1)],
+                vec![(grey.clone(), 1)],
+                None,
+            )
+            .unwrap();
+        assert_eq!(tint.name(), "Batch 2");
+        assert!(factory.rename_paint(&grey, "Grey").is_err());
+        assert!(factory.rename_paint(&tint, "Mix #001").is_err());
+        let renamed = factory.rename_paint(&tint, "Tint").unwrap();
+        assert_eq!(renamed.name(), "Tint");
+        assert!(factory.get_paint("Batch 2").is_none());
+        assert_eq!(factory.get_paint("Tint").unwrap().colour(), tint.colour());
+        factory.set_naming_policy(MixedPaintNamingPolicy::custom(|_| "Tint".to_string()));
+        assert_eq!(factory.next_name(), "Tint (2)");
+    }
+
+    #[test]
+    fn series_paint_usage_counted() {
+        let factory = MixedPaintFactory::<ArtPaintCharacteristics>::create(MixingModel::SRGB);
+        let paints = series("#000000").get_paints();
+        let grey = factory
+            .add_paint(
+                "",
+                vec![(paints[0].clone(), 3), (paints[1].clone(), 1)],
+                vec![],
+                None,
+            )
+            .unwrap();
+        factory
+            .add_paint(
+                "",
+                vec![(paints[1].clone(), 2)],
+                vec![(grey.clone(), 1)],
+                None,
+            )
+            .unwrap();
+        assert_eq!(
+            factory.usage_of_series_paint(&paints[0]),
+            PaintUsage {
+                mixture_count: 1,
+                total_parts: 3
+            }
+        );
+        let usage = factory.series_paint_usage();
+        assert_eq!(usage.len(), 2);
+        assert!(usage.contains(&(
+            paints[1].clone(),
+            PaintUsage {
+                mixture_count: 2,
+                total_parts: 3
+            }
+        )));
+    }
 }
@@ -1,7 +1,10 @@
 // Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
 
 use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
+use std::fmt;
 use std::rc::Rc;
+use std::str::FromStr;
 
 use num::Integer;
 
@@ -9,6 +12,7 @@ use pw_gix::{
     gdk,
     gtk::{self, prelude::*},
     gtkx::menu::*,
+    recollections::{recall, remember},
     wrapper::*,
 };
 
@@ -16,7 +20,181 @@ use colour_math_gtk::coloured::*;
 
 use crate::basic_paint::*;
 use crate::colour::*;
-use crate::dialogue::PaintDisplayWithCurrentTarget;
+use crate::dialogue::{DisplayDialogHost, PaintDisplayWithCurrentTarget};
+
+use super::measures::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComponentSortOrder {
+    Manual,
+    Name,
+    Hue,
+    Value,
+    PartsDescending,
+}
+
+impl Default for ComponentSortOrder {
+    fn default() -> ComponentSortOrder {
+        ComponentSortOrder::Manual
+    }
+}
+
+static COMPONENT_SORT_ORDER_VALUES: &[ComponentSortOrder] = &[
+    ComponentSortOrder::Manual,
+    ComponentSortOrder::Name,
+    ComponentSortOrder::Hue,
+    ComponentSortOrder::Value,
+    ComponentSortOrder::PartsDescending,
+];
+
+impl ComponentSortOrder {
+    pub fn values() -> Vec<ComponentSortOrder> {
+        COMPONENT_SORT_ORDER_VALUES.to_vec()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ComponentSortOrder::Manual => "manual",
+            ComponentSortOrder::Name => "name",
+            ComponentSortOrder::Hue => "hue",
+            ComponentSortOrder::Value => "value",
+            ComponentSortOrder::PartsDescending => "parts_descending",
+        }
+    }
+
+    pub fn menu_key(&self) -> String {
+        format!("sort_{}", self.name())
+    }
+
+    pub fn label(&self) -> &'static str {
+        match *self {
+            ComponentSortOrder::Manual => "Manual Order",
+            ComponentSortOrder::Name => "Sort by Name",
+            ComponentSortOrder::Hue => "Sort by Hue",
+            ComponentSortOrder::Value => "Sort by Value",
+            ComponentSortOrder::PartsDescending => "Sort by Parts",
+        }
+    }
+
+    pub fn tooltip_text(&self) -> &'static str {
+        match *self {
+            ComponentSortOrder::Manual => {
+                "Show the paints in the order they were added and allow them to be dragged into a new order"
+            }
+            ComponentSortOrder::Name => "Show the paints in alphabetical order of their names",
+            ComponentSortOrder::Hue => "Show the paints in order of hue with greys last",
+            ComponentSortOrder::Value => "Show the paints in order from darkest to lightest",
+            ComponentSortOrder::PartsDescending => {
+                "Show the paints with the most parts in the mixture first"
+            }
+        }
+    }
+
+    pub fn recall(key: &str) -> ComponentSortOrder {
+        if let Some(text) = recall(key) {
+            if let Ok(sort_order) = ComponentSortOrder::from_str(&text) {
+                return sort_order;
+            }
+        };
+        ComponentSortOrder::default()
+    }
+
+    pub fn remember(&self, key: &str) {
+        remember(key, self.name())
+    }
+
+    fn compare<C, P>(&self, a: &(P, u32), b: &(P, u32)) -> Ordering
+    where
+        C: CharacteristicsInterface,
+        P: BasicPaintInterface<C>,
+    {
+        match *self {
+            ComponentSortOrder::Manual => Ordering::Equal,
+            ComponentSortOrder::Name => a.0.name().to_lowercase().cmp(&b.0.name().to_lowercase()),
+            ComponentSortOrder::Hue => match (a.0.colour().hue(), b.0.colour().hue()) {
+                (Some(a_hue), Some(b_hue)) => a_hue
+                    .angle()
+                    .radians()
+                    .partial_cmp(&b_hue.angle().radians())
+                    .unwrap_or(Ordering::Equal),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            },
+            ComponentSortOrder::Value => {
+                a.0.colour()
+                    .value()
+                    .partial_cmp(&b.0.colour().value())
+                    .unwrap_or(Ordering::Equal)
+            }
+            ComponentSortOrder::PartsDescending => b.1.cmp(&a.1),
+        }
+    }
+}
+
+impl FromStr for ComponentSortOrder {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<ComponentSortOrder, String> {
+        for sort_order in COMPONENT_SORT_ORDER_VALUES.iter() {
+            if sort_order.name() == string {
+                return Ok(*sort_order);
+            }
+        }
+        Err(format!("{}: unknown component sort order", string))
+    }
+}
+
+impl fmt::Display for ComponentSortOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// Stable sort of paints (in manual order) with their parts
+pub fn sort_components<C, P>(components: &mut Vec<(P, u32)>, sort_order: ComponentSortOrder)
+where
+    C: CharacteristicsInterface,
+    P: BasicPaintInterface<C>,
+{
+    components.sort_by(|a, b| sort_order.compare::<C, P>(a, b));
+}
+
+// (menu key, label, tooltip, steps)
+const QUICK_ADJUSTMENTS: &[(&str, &str, &str, i32)] = &[
+    (
+        "plus_1",
+        "+1",
+        "Increase this paint's contribution by one step",
+        1,
+    ),
+    (
+        "minus_1",
+        "-1",
+        "Decrease this paint's contribution by one step",
+        -1,
+    ),
+    (
+        "plus_10",
+        "+10",
+        "Increase this paint's contribution by ten steps",
+        10,
+    ),
+    (
+        "minus_10",
+        "-10",
+        "Decrease this paint's contribution by ten steps",
+        -10,
+    ),
+];
+
+fn component_drag_targets() -> Vec<gtk::TargetEntry> {
+    vec![gtk::TargetEntry::new(
+        "epaint/paint-component",
+        gtk::TargetFlags::SAME_APP,
+        0,
+    )]
+}
 
 pub trait PaintPartsSpinButtonInterface<A, C, P, D>
 where
@@ -46,6 +224,7 @@ where
     label: gtk::Label,
     popup_menu: WrappedMenu,
     paint: P,
+    quantity_unit: Cell<QuantityUnit>,
     current_target: RefCell<Option<Colour>>,
     dialog: RefCell<Option<D>>,
     parts_changed_callbacks: RefCell<Vec<Box<dyn Fn(u32)>>>,
@@ -89,6 +268,7 @@ where
             label: gtk::Label::new(Some(label_text.as_str())),
             popup_menu: WrappedMenu::new(&vec![]),
             paint: paint.clone(),
+            quantity_unit: Cell::new(QuantityUnit::default()),
             current_target: RefCell::new(None),
             dialog: RefCell::new(None),
             parts_changed_callbacks: parts_changed_callbacks,
@@ -143,6 +323,13 @@ where
             .connect_activate(move |_| {
                 spin_button_c.inform_remove_me();
             });
+        for (key, label, tooltip, steps) in QUICK_ADJUSTMENTS.iter() {
+            let spin_button_c = spin_button.clone();
+            spin_button
+                .popup_menu
+                .append_item(key, label, tooltip)
+                .connect_activate(move |_| spin_button_c.adjust_parts(*steps));
+        }
         //
         let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 1);
         hbox.pack_start(&spin_button.label.clone(), true, true, 0);
@@ -163,6 +350,7 @@ where
                         spin_button_c
                             .popup_menu
                             .set_sensitivities(spin_button_c.get_parts() == 0, &["remove"]);
+                        spin_button_c.update_quick_adjustment_menu();
                         spin_button_c.popup_menu.popup_at_event(event);
                         return Inhibit(true);
                     }
@@ -192,12 +380,23 @@ where
         self.paint.clone()
     }
 
+    fn get_quantity(&self) -> f64 {
+        self.entry.get_value()
+    }
+
+    // NB: measured quantities are converted to (hundredths of a ml) parts
     fn get_parts(&self) -> u32 {
-        self.entry.get_value_as_int() as u32
+        self.quantity_unit
+            .get()
+            .parts(self.get_quantity(), self.paint.density())
     }
 
     fn set_parts(&self, parts: u32) {
-        self.entry.set_value(parts as f64)
+        let quantity = self
+            .quantity_unit
+            .get()
+            .quantity_for_parts(parts, self.paint.density());
+        self.entry.set_value(quantity)
     }
 
     fn divide_parts(&self, divisor: u32) {
@@ -205,14 +404,65 @@ where
         self.entry.set_value(parts as f64);
     }
 
+    // NB: steps are in the current unit's increments (1 part or 0.1 ml/g)
+    fn adjust_parts(&self, steps: i32) {
+        let upper = self.entry.get_adjustment().get_upper();
+        let quantity =
+            self.quantity_unit
+                .get()
+                .adjusted_quantity(self.get_quantity(), steps, upper);
+        self.entry.set_value(quantity)
+    }
+
+    fn update_quick_adjustment_menu(&self) {
+        let sensitive = self.entry.get_sensitive();
+        let quantity = self.get_quantity();
+        let upper = self.entry.get_adjustment().get_upper();
+        self.popup_menu
+            .set_sensitivities(sensitive && quantity < upper, &["plus_1", "plus_10"]);
+        self.popup_menu
+            .set_sensitivities(sensitive && quantity > 0.0, &["minus_1", "minus_10"]);
+    }
+
     fn get_paint_component(&self) -> (P, u32) {
-        (self.paint.clone(), self.entry.get_value_as_int() as u32)
+        (self.paint.clone(), self.get_parts())
+    }
+
+    // The current quantity is converted to the new unit
+    fn set_quantity_unit(&self, unit: QuantityUnit) {
+        let density = self.paint.density();
+        let volume = self
+            .quantity_unit
+            .get()
+            .volume(self.get_quantity(), density);
+        self.quantity_unit.set(unit);
+        let (step, page) = unit.increments();
+        self.entry.set_digits(unit.digits());
+        self.entry.set_increments(step, page);
+        self.entry
+            .set_value(unit.quantity_for_volume(volume, density));
     }
 
     fn set_sensitive(&self, sensitive: bool) {
         self.entry.set_sensitive(sensitive)
     }
 
+    fn set_draggable(&self, draggable: bool) {
+        if draggable {
+            let targets = component_drag_targets();
+            self.event_box.drag_source_set(
+                gdk::ModifierType::BUTTON1_MASK,
+                &targets,
+                gdk::DragAction::MOVE,
+            );
+            self.event_box
+                .drag_dest_set(gtk::DestDefaults::ALL, &targets, gdk::DragAction::MOVE);
+        } else {
+            self.event_box.drag_source_unset();
+            self.event_box.drag_dest_unset();
+        }
+    }
+
     fn connect_parts_changed<F: 'static + Fn(u32)>(&self, callback: F) {
         self.parts_changed_callbacks
             .borrow_mut()
@@ -220,7 +470,7 @@ where
     }
 
     fn inform_parts_changed(&self) {
-        let parts = self.entry.get_value_as_int() as u32;
+        let parts = self.get_parts();
         for callback in self.parts_changed_callbacks.borrow().iter() {
             callback(parts);
         }
@@ -238,6 +488,14 @@ where
         }
     }
 
+    fn display_dialog(&self) -> Option<gtk::Dialog> {
+        if let Some(ref dialog) = *self.dialog.borrow() {
+            Some(dialog.dialog())
+        } else {
+            None
+        }
+    }
+
     fn set_current_target(&self, new_current_target: Option<&Colour>) {
         if let Some(ref dialog) = *self.dialog.borrow() {
             dialog.set_current_target(new_current_target);
@@ -280,6 +538,12 @@ where
 {
     vbox: gtk::Box,
     spin_buttons: RefCell<Vec<PaintPartsSpinButton<A, C, P, D>>>,
+    manual_order: RefCell<Vec<P>>,
+    sort_order: Cell<ComponentSortOrder>,
+    sort_order_recollection_name: RefCell<Option<String>>,
+    quantity_unit: Cell<QuantityUnit>,
+    quantity_unit_recollection_name: RefCell<Option<String>>,
+    dragged_paint: RefCell<Option<P>>,
     h_boxes: RefCell<Vec<gtk::Box>>,
     count: Cell<u32>,
     n_cols: Cell<u32>,
@@ -288,6 +552,7 @@ where
     current_target: RefCell<Option<Colour>>,
     contributions_changed_callbacks: RefCell<Vec<Box<dyn Fn()>>>,
     removal_requested_callbacks: RefCell<Vec<Box<dyn Fn(&P)>>>,
+    quantity_unit_changed_callbacks: RefCell<Vec<Box<dyn Fn(QuantityUnit)>>>,
 }
 
 impl<A, C, P, D> PaintComponentsBoxCore<A, C, P, D>
@@ -309,6 +574,10 @@ where
         self.contributions_changed_callbacks.borrow().len() > 0
     }
 
+    pub fn has_paint(&self, paint: &P) -> bool {
+        self.find_paint_index(paint).is_ok()
+    }
+
     pub fn is_being_used(&self, paint: &P) -> bool {
         if let Ok(index) = self.find_paint_index(paint) {
             return self.spin_buttons.borrow()[index].get_parts() > 0;
@@ -377,16 +646,170 @@ where
         }
         self.h_boxes.borrow_mut().clear();
         self.count.set(0);
-        for spin_button in self.spin_buttons.borrow().iter() {
+        for spin_button in self.ordered_spin_buttons().iter() {
             self.pack_append(spin_button);
         }
         self.vbox.show_all();
     }
 
+    fn ordered_spin_buttons(&self) -> Vec<PaintPartsSpinButton<A, C, P, D>> {
+        let mut components: Vec<(P, u32)> = self
+            .manual_order
+            .borrow()
+            .iter()
+            .map(|paint| {
+                if let Ok(index) = self.find_paint_index(paint) {
+                    (paint.clone(), self.spin_buttons.borrow()[index].get_parts())
+                } else {
+                    panic!("File: {:?} Line: {:?}", file!(), line!())
+                }
+            })
+            .collect();
+        sort_components::<C, P>(&mut components, self.sort_order.get());
+        let spin_buttons = self.spin_buttons.borrow();
+        components
+            .iter()
+            .map(|(paint, _)| {
+                if let Ok(index) = self.find_paint_index(paint) {
+                    spin_buttons[index].clone()
+                } else {
+                    panic!("File: {:?} Line: {:?}", file!(), line!())
+                }
+            })
+            .collect()
+    }
+
+    // Only repack if necessary so that we don't disturb the user's editing
+    fn repack_if_out_of_order(&self) {
+        let ordered = self.ordered_spin_buttons();
+        let packed: Vec<gtk::Widget> = self
+            .h_boxes
+            .borrow()
+            .iter()
+            .flat_map(|hbox| hbox.get_children())
+            .collect();
+        let in_order = packed.len() == ordered.len()
+            && packed
+                .iter()
+                .zip(ordered.iter())
+                .all(|(widget, spin_button)| *widget == spin_button.pwo().upcast::<gtk::Widget>());
+        if !in_order {
+            self.repack_all();
+        }
+    }
+
+    pub fn sort_order(&self) -> ComponentSortOrder {
+        self.sort_order.get()
+    }
+
+    pub fn set_sort_order(&self, sort_order: ComponentSortOrder) {
+        self.sort_order.set(sort_order);
+        for spin_button in self.spin_buttons.borrow().iter() {
+            self.update_sort_order_menu(spin_button);
+            spin_button.set_draggable(sort_order == ComponentSortOrder::Manual);
+        }
+        if let Some(ref name) = *self.sort_order_recollection_name.borrow() {
+            sort_order.remember(name);
+        }
+        self.repack_all();
+    }
+
+    // Recall the sort order from (and remember future changes in) the named recollection
+    pub fn set_sort_order_recollection_name(&self, name: &str) {
+        *self.sort_order_recollection_name.borrow_mut() = Some(name.to_string());
+        self.set_sort_order(ComponentSortOrder::recall(name));
+    }
+
+    fn update_sort_order_menu(&self, spin_button: &PaintPartsSpinButton<A, C, P, D>) {
+        for sort_order in ComponentSortOrder::values() {
+            spin_button.popup_menu.set_sensitivities(
+                sort_order != self.sort_order.get(),
+                &[sort_order.menu_key().as_str()],
+            );
+        }
+    }
+
+    pub fn quantity_unit(&self) -> QuantityUnit {
+        self.quantity_unit.get()
+    }
+
+    pub fn set_quantity_unit(&self, unit: QuantityUnit) {
+        if unit == self.quantity_unit.get() {
+            return;
+        }
+        self.quantity_unit.set(unit);
+        self.supress_change_notification.set(true);
+        for spin_button in self.spin_buttons.borrow().iter() {
+            spin_button.set_quantity_unit(unit);
+            self.update_quantity_unit_menu(spin_button);
+        }
+        self.supress_change_notification.set(false);
+        if let Some(ref name) = *self.quantity_unit_recollection_name.borrow() {
+            unit.remember(name);
+        }
+        self.repack_if_out_of_order();
+        self.inform_contributions_changed();
+        for callback in self.quantity_unit_changed_callbacks.borrow().iter() {
+            callback(unit);
+        }
+    }
+
+    // Recall the unit from (and remember future changes in) the named recollection
+    pub fn set_quantity_unit_recollection_name(&self, name: &str) {
+        *self.quantity_unit_recollection_name.borrow_mut() = Some(name.to_string());
+        self.set_quantity_unit(QuantityUnit::recall(name));
+    }
+
+    pub fn connect_quantity_unit_changed<F: 'static + Fn(QuantityUnit)>(&self, callback: F) {
+        self.quantity_unit_changed_callbacks
+            .borrow_mut()
+            .push(Box::new(callback))
+    }
+
+    fn update_quantity_unit_menu(&self, spin_button: &PaintPartsSpinButton<A, C, P, D>) {
+        for unit in QuantityUnit::values() {
+            spin_button.popup_menu.set_sensitivities(
+                unit != self.quantity_unit.get(),
+                &[unit.menu_key().as_str()],
+            );
+        }
+    }
+
+    // The quantities (in the current unit) of the paints in use
+    pub fn get_paint_quantities(&self) -> Vec<(P, f64)> {
+        self.ordered_spin_buttons()
+            .iter()
+            .filter(|s| s.get_parts() > 0)
+            .map(|s| (s.paint(), s.get_quantity()))
+            .collect()
+    }
+
+    fn move_dragged_paint_to(&self, target: &P) {
+        let o_dragged = self.dragged_paint.borrow_mut().take();
+        if let Some(dragged) = o_dragged {
+            if dragged == *target {
+                return;
+            }
+            let mut manual_order = self.manual_order.borrow_mut();
+            if let Some(from) = manual_order.iter().position(|paint| *paint == dragged) {
+                manual_order.remove(from);
+                if let Some(to) = manual_order.iter().position(|paint| paint == target) {
+                    // the dragged paint takes the target's place
+                    let to = if from <= to { to + 1 } else { to };
+                    manual_order.insert(to, dragged);
+                } else {
+                    panic!("File: {:?} Line: {:?}", file!(), line!())
+                }
+            }
+        }
+        self.repack_all();
+    }
+
     pub fn remove_paint(&self, paint: &P) {
         let r_index = self.find_paint_index(paint);
         if let Ok(index) = r_index {
             let spin_button = self.spin_buttons.borrow_mut().remove(index);
+            self.manual_order.borrow_mut().retain(|p| p != paint);
             spin_button.close_dialog();
             self.repack_all();
             if spin_button.get_parts() > 0 {
@@ -407,20 +830,50 @@ where
             }
         }
         *self.spin_buttons.borrow_mut() = keepers;
+        self.manual_order
+            .borrow_mut()
+            .retain(|paint| !removed_paints.contains(paint));
         self.repack_all();
         removed_paints
     }
 
+    // Adjust a paint's contribution by a number of steps (clamped to the
+    // spin button's range) and return its new parts (None if not present)
+    pub fn adjust_parts(&self, paint: &P, delta: i32) -> Option<u32> {
+        let index = self.find_paint_index(paint).ok()?;
+        let spin_button = self.spin_buttons.borrow()[index].clone();
+        spin_button.adjust_parts(delta);
+        Some(spin_button.get_parts())
+    }
+
     pub fn reset_all_parts_to_zero(&self) {
         self.supress_change_notification.set(true);
         for spin_button in self.spin_buttons.borrow().iter() {
             spin_button.set_parts(0);
         }
         self.supress_change_notification.set(false);
+        self.repack_if_out_of_order();
+        self.inform_contributions_changed();
+    }
+
+    // Set the parts of paints already in the box (others are ignored)
+    pub fn set_parts_for(&self, components: &[(P, u32)]) {
+        self.supress_change_notification.set(true);
+        for (paint, parts) in components.iter() {
+            if let Ok(index) = self.find_paint_index(paint) {
+                self.spin_buttons.borrow()[index].set_parts(*parts);
+            }
+        }
+        self.supress_change_notification.set(false);
+        self.repack_if_out_of_order();
         self.inform_contributions_changed();
     }
 
+    // Measured quantities aren't simplified (so the gcd is always 1)
     pub fn get_gcd(&self) -> u32 {
+        if self.quantity_unit.get().is_measured() {
+            return 1;
+        }
         self.spin_buttons
             .borrow()
             .iter()
@@ -428,15 +881,24 @@ where
     }
 
     pub fn divide_all_parts_by(&self, gcd: u32) {
-        if gcd > 1 {
+        if gcd > 1 && !self.quantity_unit.get().is_measured() {
             self.supress_change_notification.set(true);
             for spin_button in self.spin_buttons.borrow().iter() {
                 spin_button.divide_parts(gcd);
             }
             self.supress_change_notification.set(false);
+            self.repack_if_out_of_order();
         }
     }
 
+    pub fn get_paints(&self) -> Vec<P> {
+        self.spin_buttons
+            .borrow()
+            .iter()
+            .map(|s| s.paint())
+            .collect()
+    }
+
     pub fn get_paint_components(&self) -> Vec<(P, u32)> {
         self.spin_buttons
             .borrow()
@@ -466,6 +928,22 @@ where
     }
 }
 
+impl<A, C, P, D> DisplayDialogHost for PaintComponentsBoxCore<A, C, P, D>
+where
+    C: CharacteristicsInterface + 'static,
+    A: ColourAttributesInterface + 'static,
+    P: BasicPaintInterface<C> + 'static,
+    D: PaintDisplayWithCurrentTarget<A, C, P> + 'static,
+{
+    fn display_dialogs(&self) -> Vec<gtk::Dialog> {
+        self.spin_buttons
+            .borrow()
+            .iter()
+            .filter_map(|spin_button| spin_button.display_dialog())
+            .collect()
+    }
+}
+
 pub type PaintComponentsBox<A, C, P, D> = Rc<PaintComponentsBoxCore<A, C, P, D>>;
 
 impl<A, C, P, D> PaintComponentsBoxInterface<A, C, P, D> for PaintComponentsBox<A, C, P, D>
@@ -479,6 +957,12 @@ where
         let pcb_core = PaintComponentsBoxCore::<A, C, P, D> {
             vbox: gtk::Box::new(gtk::Orientation::Vertical, 1),
             spin_buttons: RefCell::new(Vec::new()),
+            manual_order: RefCell::new(Vec::new()),
+            sort_order: Cell::new(ComponentSortOrder::default()),
+            sort_order_recollection_name: RefCell::new(None),
+            quantity_unit: Cell::new(QuantityUnit::default()),
+            quantity_unit_recollection_name: RefCell::new(None),
+            dragged_paint: RefCell::new(None),
             h_boxes: RefCell::new(Vec::new()),
             count: Cell::new(0),
             n_cols: Cell::new(n_cols),
@@ -487,6 +971,7 @@ where
             current_target: RefCell::new(None),
             contributions_changed_callbacks: RefCell::new(Vec::new()),
             removal_requested_callbacks: RefCell::new(Vec::new()),
+            quantity_unit_changed_callbacks: RefCell::new(Vec::new()),
         };
         Rc::new(pcb_core)
     }
@@ -507,12 +992,65 @@ where
             );
             let spin_button_c = spin_button.clone();
             self.spin_buttons.borrow_mut().insert(index, spin_button_c);
+            self.manual_order.borrow_mut().push(paint.clone());
             let self_c = self.clone();
-            spin_button.connect_parts_changed(move |_| self_c.inform_contributions_changed());
+            spin_button.connect_parts_changed(move |_| {
+                if self_c.sort_order.get() == ComponentSortOrder::PartsDescending
+                    && !self_c.supress_change_notification.get()
+                {
+                    self_c.repack_if_out_of_order();
+                }
+                self_c.inform_contributions_changed()
+            });
             let self_c = self.clone();
             spin_button.connect_remove_me(move |sb| self_c.inform_removal_requested(&sb.paint()));
-            self.pack_append(&spin_button);
-            self.vbox.show_all();
+            for sort_order in ComponentSortOrder::values() {
+                let self_c = self.clone();
+                spin_button
+                    .popup_menu
+                    .append_item(
+                        &sort_order.menu_key(),
+                        sort_order.label(),
+                        sort_order.tooltip_text(),
+                    )
+                    .connect_activate(move |_| self_c.set_sort_order(sort_order));
+            }
+            self.update_sort_order_menu(&spin_button);
+            for unit in QuantityUnit::values() {
+                let self_c = self.clone();
+                spin_button
+                    .popup_menu
+                    .append_item(&unit.menu_key(), unit.label(), unit.tooltip_text())
+                    .connect_activate(move |_| self_c.set_quantity_unit(unit));
+            }
+            spin_button.set_quantity_unit(self.quantity_unit.get());
+            self.update_quantity_unit_menu(&spin_button);
+            // Manual reordering by drag and drop
+            let self_c = self.clone();
+            let paint_c = paint.clone();
+            spin_button.event_box.connect_drag_begin(move |_, _| {
+                *self_c.dragged_paint.borrow_mut() = Some(paint_c.clone());
+            });
+            let paint_name = paint.name();
+            spin_button
+                .event_box
+                .connect_drag_data_get(move |_, _, selection_data, _, _| {
+                    selection_data.set_text(&paint_name);
+                });
+            let self_c = self.clone();
+            let paint_c = paint.clone();
+            spin_button
+                .event_box
+                .connect_drag_data_received(move |_, _, _, _, _, _, _| {
+                    self_c.move_dragged_paint_to(&paint_c)
+                });
+            spin_button.set_draggable(self.sort_order.get() == ComponentSortOrder::Manual);
+            if self.sort_order.get() == ComponentSortOrder::Manual {
+                self.pack_append(&spin_button);
+                self.vbox.show_all();
+            } else {
+                self.repack_all();
+            }
         }
     }
 
@@ -557,3 +1095,53 @@ where
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::art_paint::*;
+
+    #[test]
+    fn component_sort_order_names() {
+        for sort_order in ComponentSortOrder::values() {
+            assert_eq!(
+                ComponentSortOrder::from_str(sort_order.name()),
+                Ok(sort_order)
+            );
+        }
+        assert!(ComponentSortOrder::from_str("sideways").is_err());
+    }
+
+    #[test]
+    fn sort_components_orders() {
+        let series = create_ideal_art_paint_series();
+        let names = ["White", "Red", "Black", "Blue"];
+        let mut components: Vec<(ArtSeriesPaint, u32)> = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (series.get_paint(name).unwrap(), i as u32))
+            .collect();
+        let sorted_names = |components: &Vec<(ArtSeriesPaint, u32)>| -> Vec<String> {
+            components.iter().map(|c| c.0.name()).collect()
+        };
+        sort_components(&mut components, ComponentSortOrder::Manual);
+        assert_eq!(sorted_names(&components), names.to_vec());
+        sort_components(&mut components, ComponentSortOrder::Name);
+        assert_eq!(
+            sorted_names(&components),
+            vec!["Black", "Blue", "Red", "White"]
+        );
+        sort_components(&mut components, ComponentSortOrder::PartsDescending);
+        assert_eq!(
+            sorted_names(&components),
+            vec!["Blue", "Black", "Red", "White"]
+        );
+        sort_components(&mut components, ComponentSortOrder::Value);
+        assert_eq!(sorted_names(&components)[0], "Black");
+        assert_eq!(sorted_names(&components)[3], "White");
+        sort_components(&mut components, ComponentSortOrder::Hue);
+        let last_two = &sorted_names(&components)[2..];
+        assert!(last_two.contains(&"Black".to_string()));
+        assert!(last_two.contains(&"White".to_string()));
+    }
+}
@@ -0,0 +1,171 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+use crate::basic_paint::*;
+use crate::colour::*;
+use crate::colour_difference::*;
+use crate::series_paint::*;
+
+use super::*;
+
+pub fn csv_field(text: &str) -> String {
+    if text.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", text.replace("\"", "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+fn csv_line(fields: &[String]) -> String {
+    let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+    fields.join(",") + "\n"
+}
+
+fn hue_degrees(colour: &Colour) -> String {
+    if let Some(hue) = colour.hue() {
+        format!("{:.1}", hue.angle().radians().to_degrees())
+    } else {
+        "".to_string()
+    }
+}
+
+fn float_field(value: f64) -> String {
+    format!("{:.4}", value)
+}
+
+// One row per mixture with its colour, its deltas from its target (if
+// any) and the parts of each of the given series paints that it uses.
+pub fn mixtures_csv<C>(mixed_paints: &[MixedPaint<C>], series_paints: &[SeriesPaint<C>]) -> String
+where
+    C: CharacteristicsInterface,
+{
+    mixtures_csv_with(mixed_paints, series_paints, DisplayNamePolicy::Name)
+}
+
+// As above but with the series paints identified according to "policy"
+pub fn mixtures_csv_with<C>(
+    mixed_paints: &[MixedPaint<C>],
+    series_paints: &[SeriesPaint<C>],
+    policy: DisplayNamePolicy,
+) -> String
+where
+    C: CharacteristicsInterface,
+{
+    let mut header: Vec<String> = [
+        "Name",
+        "Notes",
+        "Red",
+        "Green",
+        "Blue",
+        "Hue",
+        "Chroma",
+        "Value",
+        "Target",
+        "Delta E",
+        "Delta Chroma",
+        "Delta Value",
+    ]
+    .iter()
+    .map(|title| title.to_string())
+    .collect();
+    for series_paint in series_paints.iter() {
+        header.push(format!(
+            "{} ({})",
+            series_paint.display_name(policy),
+            series_paint.colln_id().colln_name()
+        ));
+    }
+    let mut text = csv_line(&header);
+    for mixed_paint in mixed_paints.iter() {
+        let colour = mixed_paint.colour();
+        let rgb = colour.rgb();
+        let mut fields = vec![
+            mixed_paint.name(),
+            mixed_paint.notes(),
+            float_field(rgb[CCI::Red]),
+            float_field(rgb[CCI::Green]),
+            float_field(rgb[CCI::Blue]),
+            hue_degrees(&colour),
+            float_field(colour.chroma()),
+            float_field(colour.value()),
+        ];
+        if let Some(target) = mixed_paint.matched_colour() {
+            fields.push(rgb8_string(&target.rgb()));
+            fields.push(format!("{:.2}", delta_e(&target.rgb(), &rgb)));
+            fields.push(float_field(colour.chroma() - target.chroma()));
+            fields.push(float_field(colour.value() - target.value()));
+        } else {
+            fields.extend(vec!["".to_string(); 4]);
+        }
+        let series_parts = mixed_paint.series_parts();
+        for series_paint in series_paints.iter() {
+            let parts = series_parts
+                .iter()
+                .find(|sp| sp.0 == *series_paint)
+                .map(|sp| sp.1)
+                .unwrap_or(0.0);
+            fields.push(format!("{}", (parts * 1000.0).round() / 1000.0));
+        }
+        text += &csv_line(&fields);
+    }
+    text
+}
+
+fn rgb8_string(rgb: &RGB) -> String {
+    format!(
+        "#{:02X}{:02X}{:02X}",
+        (rgb[CCI::Red] * 255.0).round() as u8,
+        (rgb[CCI::Green] * 255.0).round() as u8,
+        (rgb[CCI::Blue] * 255.0).round() as u8
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::art_paint::*;
+    use crate::colour_mix::MixingModel;
+    use crate::mixed_paint::collection::*;
+
+    #[test]
+    fn csv_field_quoting() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a, b"), "\"a, b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn mixtures_csv_rows_and_parts() {
+        let series = create_ideal_art_paint_series();
+        let red = series.get_paint("Red").unwrap();
+        let white = series.get_paint("White").unwrap();
+        let blue = series.get_paint("Blue").unwrap();
+        let factory = MixedPaintFactory::<ArtPaintCharacteristics>::create(MixingModel::SRGB);
+        let pink = factory
+            .add_paint(
+                "Pink, light",
+                vec![(red.clone(), 1), (white.clone(), 2)],
+                vec![],
+                None,
+            )
+            .unwrap();
+        let purple = factory
+            .add_paint(
+                "Purple",
+                vec![(blue.clone(), 1)],
+                vec![(pink.clone(), 3)],
+                Some(Colour::from(RGB::MAGENTA)),
+            )
+            .unwrap();
+        let series_paints = vec![blue, red, white];
+        let text = mixtures_csv(&[pink, purple], &series_paints);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].ends_with(
+            "Blue (Ideal Paint Colours Series),Red (Ideal Paint Colours Series),White (Ideal Paint Colours Series)"
+        ));
+        assert!(lines[1].contains("\"Pink, light\""));
+        assert!(lines[1].ends_with(",,,,0,1,2"));
+        assert!(lines[2].contains("#FF00FF"));
+        assert!(lines[2].ends_with(",1,1,2"));
+    }
+}
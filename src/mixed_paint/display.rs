@@ -16,7 +16,9 @@ use colour_math_gtk::coloured::*;
 
 use crate::basic_paint::*;
 use crate::colour::*;
+use crate::colour_difference::DeltaEMetric;
 use crate::dialogue::*;
+use crate::error::*;
 use crate::series_paint::*;
 
 use super::*;
@@ -29,6 +31,7 @@ where
     dialog: gtk::Dialog,
     paint: MixedPaint<C>,
     current_target_label: gtk::Label,
+    delta_e_label: gtk::Label,
     cads: Rc<A>,
     components_view: PaintComponentListView<A, C>,
     id_no: u32,
@@ -72,7 +75,7 @@ where
         caller: &Rc<W>,
         button_specs: Vec<PaintDisplayButtonSpec>,
     ) -> MixedPaintDisplayDialog<A, C> {
-        let dialog = new_display_dialog(&paint.name(), caller, &[]);
+        let dialog = new_display_dialog(&paint.name(), "mixed_paint_display", caller, &[]);
         dialog.set_size_from_recollections("mixed_paint_display", (60, 330));
         let content_area = dialog.get_content_area();
         let vbox = gtk::Box::new(gtk::Orientation::Vertical, 0);
@@ -95,6 +98,13 @@ where
             vbox.pack_start(&matched_colour_label.clone(), true, true, 0);
             cads.set_target_colour(Some(&matched_colour.clone()));
         }
+        if let Some(provenance) = paint.target_provenance() {
+            let provenance_label = gtk::Label::new(Some(provenance.description().as_str()));
+            provenance_label.set_line_wrap(true);
+            vbox.pack_start(&provenance_label, false, false, 0);
+        }
+        let delta_e_label = gtk::Label::new(None);
+        vbox.pack_start(&delta_e_label.clone(), false, false, 0);
         //
         content_area.pack_start(&vbox, false, true, 0);
         content_area.pack_start(&cads.pwo(), true, true, 1);
@@ -103,6 +113,25 @@ where
         let components_view =
             PaintComponentListView::<A, C>::create(&paint.components(), current_target);
         content_area.pack_start(&components_view.pwo(), true, true, 0);
+        if paint.recipe_history().len() > 0 {
+            let history: Vec<String> = paint
+                .recipe_history()
+                .iter()
+                .map(|revision| revision.description())
+                .collect();
+            let label =
+                gtk::Label::new(Some(&format!("Previous Recipes:\n{}", history.join("\n"))));
+            label.set_xalign(0.0);
+            label.set_line_wrap(true);
+            content_area.pack_start(&label, false, false, 0);
+        }
+        let scale_btn = gtk::Button::with_label("Scale Recipe...");
+        scale_btn.set_tooltip_text(Some(
+            "Work out how much of each component is needed for a given amount of this paint",
+        ));
+        let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+        hbox.pack_end(&scale_btn, false, false, 0);
+        content_area.pack_start(&hbox, false, false, 0);
         content_area.show_all();
         for (response_id, spec) in button_specs.iter().enumerate() {
             let button = dialog.add_button(
@@ -122,6 +151,7 @@ where
             dialog: dialog,
             paint: paint.clone(),
             current_target_label: current_target_label,
+            delta_e_label: delta_e_label,
             cads: cads,
             components_view: components_view,
             id_no: get_id_for_dialog(),
@@ -129,6 +159,10 @@ where
         });
         spd_dialog.set_current_target(current_target);
         let spd_dialog_c = spd_dialog.clone();
+        scale_btn.connect_clicked(move |_| {
+            recipe::scale_recipe_dialog(&spd_dialog_c.paint, &spd_dialog_c.dialog)
+        });
+        let spd_dialog_c = spd_dialog.clone();
         spd_dialog
             .dialog
             .connect_destroy(move |_| spd_dialog_c.inform_destroyed());
@@ -157,6 +191,35 @@ where
             }
             self.components_view.set_target_colour(None);
         };
+        self.update_delta_e_label(new_current_target);
+    }
+}
+
+impl<A, C> MixedPaintDisplayDialogCore<A, C>
+where
+    A: ColourAttributesInterface + 'static,
+    C: CharacteristicsInterface + 'static,
+{
+    // Measured from the current target if there is one and otherwise from
+    // the colour the paint was mixed to match
+    fn update_delta_e_label(&self, current_target: Option<&Colour>) {
+        let metric = DeltaEMetric::recall();
+        let text = if let Some(colour) = current_target {
+            format!(
+                "\u{394}E ({}) from Current Target: {:.2}",
+                metric,
+                self.paint.delta_e_to(colour, metric)
+            )
+        } else if let Some(matched_colour) = self.paint.matched_colour() {
+            format!(
+                "\u{394}E ({}) from Matched Colour: {:.2}",
+                metric,
+                self.paint.delta_e_to(&matched_colour, metric)
+            )
+        } else {
+            String::new()
+        };
+        self.delta_e_label.set_label(&text);
     }
 }
 
@@ -180,9 +243,10 @@ pub const PC_CHARS_0: i32 = 16;
 pub const PC_CHARS_1: i32 = 17;
 pub const PC_CHARS_2: i32 = 18;
 pub const PC_CHARS_3: i32 = 19;
+pub const PC_CHARS_4: i32 = 20;
 
 lazy_static! {
-    pub static ref PAINT_COMPONENTS_ROW_SPEC: [glib::Type; 20] =
+    pub static ref PAINT_COMPONENTS_ROW_SPEC: [glib::Type; 21] =
         [
             glib::Type::String,          // 0 Name
             glib::Type::String,          // 1 Notes
@@ -204,6 +268,7 @@ lazy_static! {
             glib::Type::String,          // 17 Characteristic #2
             glib::Type::String,          // 18 Characteristic #3
             glib::Type::String,          // 19 Characteristic #4
+            glib::Type::String,          // 20 Characteristic #5
         ];
 }
 
@@ -280,31 +345,27 @@ where
     A: ColourAttributesInterface + 'static,
     C: CharacteristicsInterface + 'static,
 {
-    fn get_paint_at(&self, posn: (f64, f64)) -> Option<Paint<C>> {
+    fn try_get_paint_at(&self, posn: (f64, f64)) -> PaintResult<Option<Paint<C>>, C> {
         let x = posn.0 as i32;
         let y = posn.1 as i32;
         if let Some(location) = self.view.get_path_at_pos(x, y) {
             if let Some(path) = location.0 {
                 if let Some(iter) = self.list_store.get_iter(&path) {
-                    let index: u32 = self
-                        .list_store
-                        .get_value(&iter, 14)
-                        .get()
-                        .unwrap()
-                        .unwrap_or_else(|| panic!("File: {:?} Line: {:?}", file!(), line!()));
+                    let index = self.list_store.row_u32(&iter, 14)?;
                     if index as usize >= self.components.len() {
-                        panic!(
-                            "File: {:?} Line: {:?} outside array bounds",
-                            file!(),
-                            line!()
-                        )
+                        let text = format!("component #{}", index);
+                        return Err(PaintErrorType::ModelInconsistency(text).into());
                     };
                     let paint = self.components[index as usize].paint.clone();
-                    return Some(paint);
+                    return Ok(Some(paint));
                 }
             }
         }
-        None
+        Ok(None)
+    }
+
+    fn get_paint_at(&self, posn: (f64, f64)) -> Option<Paint<C>> {
+        reported_row_value(self, self.try_get_paint_at(posn))
     }
 
     pub fn set_target_colour(&self, ocolour: Option<&Colour>) {
@@ -385,6 +446,7 @@ where
         pclv.view.append_column(&simple_text_column(
             "Parts", PC_PARTS, PC_PARTS, -1, -1, -1, true,
         ));
+        pclv.view.append_column(&swatch_tv_column(PC_RGB));
         pclv.view.append_column(&simple_text_column(
             "Name", PC_NAME, PC_NAME, PC_RGB, PC_RGB_FG, -1, true,
         ));
@@ -0,0 +1,488 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::rc::Rc;
+
+use chrono::prelude::*;
+use num::Integer;
+use serde_derive::*;
+
+use pw_gix::{
+    gdk,
+    glib::StaticType,
+    gtk::{self, prelude::*},
+    gtkx::dialog::*,
+    wrapper::*,
+};
+
+use crate::app_name;
+use crate::basic_paint::*;
+use crate::colour::*;
+use crate::colour_difference::*;
+use crate::series_paint::*;
+
+use super::*;
+
+// A series paint identified by name so that it can be found again in a
+// later session (if its series is loaded)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FavouriteComponent {
+    pub manufacturer: String,
+    pub series: String,
+    pub paint: String,
+    pub parts: u32,
+}
+
+impl FavouriteComponent {
+    pub fn description(&self) -> String {
+        format!(
+            "{}: {} ({} {})",
+            self.parts, self.paint, self.manufacturer, self.series
+        )
+    }
+
+    fn is_for<C: CharacteristicsInterface>(&self, paint: &SeriesPaint<C>) -> bool {
+        let colln_id = paint.colln_id();
+        paint.name() == self.paint
+            && colln_id.colln_name() == self.series
+            && colln_id.colln_owner() == self.manufacturer
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FavouriteMix {
+    pub name: String,
+    pub notes: String,
+    pub tags: Vec<String>,
+    pub rgb: String,
+    pub target_rgb: Option<String>,
+    pub added: String,
+    pub components: Vec<FavouriteComponent>,
+}
+
+// Mixed paint components are expanded into their series paints (with the
+// parts scaled to whole numbers)
+fn whole_series_parts<C: CharacteristicsInterface>(
+    mixed_paint: &MixedPaint<C>,
+) -> Vec<(SeriesPaint<C>, u32)> {
+    if mixed_paint.components().iter().all(|c| c.paint.is_series()) {
+        return mixed_paint
+            .components()
+            .iter()
+            .filter_map(|component| match component.paint {
+                Paint::Series(ref series_paint) => Some((series_paint.clone(), component.parts)),
+                Paint::Mixed(_) => None,
+            })
+            .collect();
+    }
+    let series_parts = mixed_paint.series_parts();
+    let total: f64 = series_parts.iter().map(|sp| sp.1).sum();
+    let mut whole_parts: Vec<(SeriesPaint<C>, u32)> = series_parts
+        .iter()
+        .map(|(paint, parts)| {
+            (
+                paint.clone(),
+                ((parts * 1000.0 / total).round() as u32).max(1),
+            )
+        })
+        .collect();
+    let gcd = whole_parts.iter().fold(0, |gcd, sp| gcd.gcd(&sp.1));
+    if gcd > 1 {
+        for sp in whole_parts.iter_mut() {
+            sp.1 /= gcd;
+        }
+    }
+    whole_parts
+}
+
+impl FavouriteMix {
+    pub fn from_mixed_paint<C: CharacteristicsInterface>(
+        mixed_paint: &MixedPaint<C>,
+        name: &str,
+        tags: &[String],
+    ) -> FavouriteMix {
+        let components = whole_series_parts(mixed_paint)
+            .iter()
+            .map(|(paint, parts)| {
+                let colln_id = paint.colln_id();
+                FavouriteComponent {
+                    manufacturer: colln_id.colln_owner(),
+                    series: colln_id.colln_name(),
+                    paint: paint.name(),
+                    parts: *parts,
+                }
+            })
+            .collect();
+        FavouriteMix {
+            name: name.to_string(),
+            notes: mixed_paint.notes(),
+            tags: tags.to_vec(),
+            rgb: mixed_paint.rgb().into_css_hex(),
+            target_rgb: mixed_paint
+                .matched_colour()
+                .map(|colour| colour.rgb().into_css_hex()),
+            added: Local::today().naive_local().format("%Y-%m-%d").to_string(),
+            components,
+        }
+    }
+
+    pub fn rgb(&self) -> RGB {
+        rgb_from_css_hex(&self.rgb).unwrap_or(RGB::BLACK)
+    }
+
+    pub fn target_rgb(&self) -> Option<RGB> {
+        self.target_rgb
+            .as_ref()
+            .and_then(|text| rgb_from_css_hex(text))
+    }
+
+    pub fn tooltip_text(&self) -> String {
+        let lines: Vec<String> = self
+            .components
+            .iter()
+            .map(|component| component.description())
+            .collect();
+        format!("{}\n{}", self.notes, lines.join("\n"))
+    }
+
+    // Case insensitive search of the tags (and name) for each of the
+    // space separated words in "text"
+    pub fn matches_text(&self, text: &str) -> bool {
+        let tags: Vec<String> = self.tags.iter().map(|tag| tag.to_lowercase()).collect();
+        let name = self.name.to_lowercase();
+        text.split_whitespace().all(|word| {
+            let word = word.to_lowercase();
+            name.contains(&word) || tags.iter().any(|tag| tag.contains(&word))
+        })
+    }
+
+    // The series paints (and parts) needed to remake this mix or the
+    // descriptions of those components whose series isn't loaded
+    pub fn resolve<C: CharacteristicsInterface>(
+        &self,
+        available: &[SeriesPaint<C>],
+    ) -> Result<Vec<(SeriesPaint<C>, u32)>, Vec<String>> {
+        let mut resolved: Vec<(SeriesPaint<C>, u32)> = vec![];
+        let mut missing: Vec<String> = vec![];
+        for component in self.components.iter() {
+            match available.iter().find(|paint| component.is_for(paint)) {
+                Some(paint) => resolved.push((paint.clone(), component.parts)),
+                None => missing.push(component.description()),
+            }
+        }
+        if missing.len() > 0 {
+            Err(missing)
+        } else {
+            Ok(resolved)
+        }
+    }
+}
+
+pub fn parse_tags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = vec![];
+    for tag in text.split(',').map(|tag| tag.trim()) {
+        if tag.len() > 0 && !tags.iter().any(|t| t == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+// The indices of the favourites whose tags match "text" and (if given)
+// whose colour is within "max_delta_e" of "o_rgb" (nearest first)
+pub fn search_favourites(
+    favourites: &[FavouriteMix],
+    text: &str,
+    o_rgb: Option<&RGB>,
+    max_delta_e: f64,
+) -> Vec<usize> {
+    let indices: Vec<usize> = (0..favourites.len())
+        .filter(|index| favourites[*index].matches_text(text))
+        .collect();
+    if let Some(rgb) = o_rgb {
+        nearest_by_delta_e(&indices, rgb, max_delta_e, |index| favourites[*index].rgb())
+            .iter()
+            .map(|(index, _)| *index)
+            .collect()
+    } else {
+        indices
+    }
+}
+
+pub fn read_favourites(path: &Path) -> io::Result<Vec<FavouriteMix>> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let mut file = File::open(path)?;
+    let mut text = String::new();
+    file.read_to_string(&mut text)?;
+    serde_json::from_str(&text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+pub fn write_favourites(path: &Path, favourites: &[FavouriteMix]) -> io::Result<()> {
+    let text = serde_json::to_string_pretty(favourites)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    let mut file = File::create(path)?;
+    file.write_all(text.as_bytes())
+}
+
+// Ask for the name and tags for a new favourite
+pub fn ask_favourite_details<W: WidgetWrapper>(
+    default_name: &str,
+    caller: &W,
+) -> Option<(String, Vec<String>)> {
+    let title = format!("{}: Add to Favourite Mixes", app_name());
+    let dialog = caller.new_dialog_with_buttons(
+        Some(&title),
+        gtk::DialogFlags::DESTROY_WITH_PARENT,
+        CANCEL_OK_BUTTONS,
+    );
+    let name_entry = gtk::Entry::new();
+    name_entry.set_text(default_name);
+    let tags_entry = gtk::Entry::new();
+    tags_entry.set_tooltip_text(Some("Comma separated e.g. skin, highlight, Project X"));
+    let grid = gtk::Grid::new();
+    grid.set_row_spacing(2);
+    grid.set_column_spacing(4);
+    grid.attach(&gtk::Label::new(Some("Name:")), 0, 0, 1, 1);
+    grid.attach(&name_entry, 1, 0, 1, 1);
+    grid.attach(&gtk::Label::new(Some("Tags:")), 0, 1, 1, 1);
+    grid.attach(&tags_entry, 1, 1, 1, 1);
+    name_entry.set_hexpand(true);
+    let content_area = dialog.get_content_area();
+    content_area.pack_start(&grid, true, true, 0);
+    content_area.show_all();
+    let mut o_details: Option<(String, Vec<String>)> = None;
+    if gtk::ResponseType::from(dialog.run()) == gtk::ResponseType::Ok {
+        let name = name_entry.get_text().trim().to_string();
+        if name.len() > 0 {
+            o_details = Some((name, parse_tags(&tags_entry.get_text())));
+        }
+    }
+    unsafe { dialog.destroy() };
+    o_details
+}
+
+const FM_INDEX: u32 = 0;
+const FM_RGB: u32 = 1;
+const FM_NAME: u32 = 2;
+const FM_TAGS: u32 = 3;
+const FM_NOTES: u32 = 4;
+const FM_TOOLTIP: u32 = 5;
+
+fn text_column(title: &str, col_id: u32) -> gtk::TreeViewColumn {
+    let col = gtk::TreeViewColumn::new();
+    col.set_title(title);
+    col.set_resizable(true);
+    let cell = gtk::CellRendererText::new();
+    col.pack_start(&cell, true);
+    col.add_attribute(&cell, "text", col_id as i32);
+    col
+}
+
+// Browse (and prune) the favourites.  Returns the chosen favourite (if
+// any) and whether the favourites were changed.
+pub fn browse_favourites<W: WidgetWrapper>(
+    favourites: &mut Vec<FavouriteMix>,
+    o_target: Option<&RGB>,
+    caller: &W,
+) -> (Option<FavouriteMix>, bool) {
+    let title = format!("{}: Favourite Mixes", app_name());
+    let dialog = caller.new_dialog_with_buttons(
+        Some(&title),
+        gtk::DialogFlags::DESTROY_WITH_PARENT,
+        &[
+            ("Close", gtk::ResponseType::Cancel),
+            ("Use", gtk::ResponseType::Ok),
+        ],
+    );
+    dialog.set_size_from_recollections("favourite_mixes_dialog", (480, 360));
+    let delete_btn = dialog.add_button("Delete", gtk::ResponseType::Other(0));
+    delete_btn.set_tooltip_text(Some("Remove the selected mix from the favourites"));
+    let search_entry = gtk::SearchEntry::new();
+    search_entry.set_tooltip_text(Some("Show only favourites with matching tags or names"));
+    let near_target_btn = gtk::CheckButton::with_label("Near Target (ΔE):");
+    near_target_btn.set_tooltip_text(Some(
+        "Show only favourites close to the current target (nearest first)",
+    ));
+    near_target_btn.set_sensitive(o_target.is_some());
+    let delta_e_spin = gtk::SpinButton::with_range(1.0, 50.0, 1.0);
+    delta_e_spin.set_value(10.0);
+    let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 2);
+    hbox.pack_start(&gtk::Label::new(Some("Tags:")), false, false, 0);
+    hbox.pack_start(&search_entry, true, true, 0);
+    hbox.pack_start(&near_target_btn, false, false, 0);
+    hbox.pack_start(&delta_e_spin, false, false, 0);
+
+    let list_store = gtk::ListStore::new(&[
+        u32::static_type(),
+        gdk::RGBA::static_type(),
+        String::static_type(),
+        String::static_type(),
+        String::static_type(),
+        String::static_type(),
+    ]);
+    let view = gtk::TreeView::with_model(&list_store);
+    view.set_headers_visible(true);
+    view.set_tooltip_column(FM_TOOLTIP as i32);
+    view.get_selection().set_mode(gtk::SelectionMode::Single);
+    view.append_column(&swatch_tv_column(FM_RGB as i32));
+    view.append_column(&text_column("Name", FM_NAME));
+    view.append_column(&text_column("Tags", FM_TAGS));
+    view.append_column(&text_column("Notes", FM_NOTES));
+    let adj: Option<&gtk::Adjustment> = None;
+    let scrolled_window = gtk::ScrolledWindow::new(adj, adj);
+    scrolled_window.add(&view);
+
+    let shown = Rc::new(RefCell::new(favourites.clone()));
+    let o_target = o_target.cloned();
+    let refill = {
+        let shown = shown.clone();
+        let list_store = list_store.clone();
+        let search_entry = search_entry.clone();
+        let near_target_btn = near_target_btn.clone();
+        let delta_e_spin = delta_e_spin.clone();
+        Rc::new(move || {
+            list_store.clear();
+            let o_rgb = if near_target_btn.get_active() {
+                o_target.as_ref()
+            } else {
+                None
+            };
+            let shown = shown.borrow();
+            for index in search_favourites(
+                &shown,
+                &search_entry.get_text(),
+                o_rgb,
+                delta_e_spin.get_value(),
+            ) {
+                let favourite = &shown[index];
+                list_store.insert_with_values(
+                    None,
+                    &[FM_INDEX, FM_RGB, FM_NAME, FM_TAGS, FM_NOTES, FM_TOOLTIP],
+                    &[
+                        &(index as u32),
+                        &favourite.rgb().into_gdk_rgba(),
+                        &favourite.name,
+                        &favourite.tags.join(", "),
+                        &favourite.notes,
+                        &favourite.tooltip_text(),
+                    ],
+                );
+            }
+        })
+    };
+    refill();
+    let refill_c = refill.clone();
+    search_entry.connect_search_changed(move |_| refill_c());
+    let refill_c = refill.clone();
+    near_target_btn.connect_toggled(move |_| refill_c());
+    let refill_c = refill.clone();
+    delta_e_spin.connect_value_changed(move |_| refill_c());
+
+    let content_area = dialog.get_content_area();
+    content_area.pack_start(&hbox, false, false, 0);
+    content_area.pack_start(&scrolled_window, true, true, 0);
+    content_area.show_all();
+
+    let selected_index = || -> Option<usize> {
+        let (model, iter) = view.get_selection().get_selected()?;
+        model
+            .get_value(&iter, FM_INDEX as i32)
+            .get::<u32>()
+            .ok()
+            .and_then(|index| index)
+            .map(|index| index as usize)
+    };
+    let mut changed = false;
+    let mut o_chosen: Option<FavouriteMix> = None;
+    loop {
+        match gtk::ResponseType::from(dialog.run()) {
+            gtk::ResponseType::Other(0) => {
+                if let Some(index) = selected_index() {
+                    let name = shown.borrow()[index].name.clone();
+                    let question = format!("Remove \"{}\" from the favourite mixes?", name);
+                    if caller.ask_confirm_action(&question, None) {
+                        shown.borrow_mut().remove(index);
+                        changed = true;
+                        refill();
+                    }
+                }
+            }
+            gtk::ResponseType::Ok => {
+                if let Some(index) = selected_index() {
+                    o_chosen = Some(shown.borrow()[index].clone());
+                    break;
+                }
+            }
+            _ => break,
+        }
+    }
+    unsafe { dialog.destroy() };
+    if changed {
+        *favourites = shown.borrow().clone();
+    }
+    (o_chosen, changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn favourite(name: &str, rgb: &str, tags: &[&str]) -> FavouriteMix {
+        FavouriteMix {
+            name: name.to_string(),
+            notes: String::new(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            rgb: rgb.to_string(),
+            target_rgb: None,
+            added: "2017-12-25".to_string(),
+            components: vec![FavouriteComponent {
+                manufacturer: "Imaginary".to_string(),
+                series: "Series".to_string(),
+                paint: "Black".to_string(),
+                parts: 1,
+            }],
+        }
+    }
+
+    #[test]
+    fn favourites_search_by_tag_and_colour() {
+        let favourites = vec![
+            favourite("Flesh", "#E0B090", &["skin", "Project X"]),
+            favourite("Shadow", "#302020", &["skin", "shade"]),
+            favourite("Sky", "#80A0FF", &["background"]),
+        ];
+        assert_eq!(
+            search_favourites(&favourites, "", None, 10.0),
+            vec![0, 1, 2]
+        );
+        assert_eq!(
+            search_favourites(&favourites, "SKIN", None, 10.0),
+            vec![0, 1]
+        );
+        assert_eq!(
+            search_favourites(&favourites, "skin project", None, 10.0),
+            vec![0]
+        );
+        assert_eq!(search_favourites(&favourites, "sky", None, 10.0), vec![2]);
+        let near_shadow = rgb_from_css_hex("#352222").unwrap();
+        assert_eq!(
+            search_favourites(&favourites, "skin", Some(&near_shadow), 10.0),
+            vec![1]
+        );
+        assert_eq!(parse_tags(" skin, shade,,skin "), vec!["skin", "shade"]);
+    }
+
+    #[test]
+    fn favourites_serialise() {
+        let favourites = vec![favourite("Sky", "#80A0FF", &["background"])];
+        let text = serde_json::to_string(&favourites).unwrap();
+        let read: Vec<FavouriteMix> = serde_json::from_str(&text).unwrap();
+        assert_eq!(read, favourites);
+        assert_eq!(read[0].rgb().into_css_hex(), "#80A0FF");
+    }
+}
@@ -1,8 +1,10 @@
 // Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
 
 use std;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
+use std::io;
+use std::path::Path;
 use std::rc::Rc;
 
 use gdk;
@@ -11,6 +13,7 @@ use gtk::prelude::*;
 
 use crate::cairox::*;
 use pw_gix::gtkx::menu::*;
+use pw_gix::recollections::{recall, remember};
 use pw_gix::wrapper::*;
 
 use crate::basic_paint::*;
@@ -24,6 +27,9 @@ use super::display::*;
 use super::target::*;
 use super::*;
 
+const SHOW_GAMUT_RECOLLECTION: &str = "mixer_hue_wheel::show_gamut";
+const GAMUT_ALPHA: f64 = 0.15;
+
 // CHOSEN_ITEM
 #[derive(Debug)]
 pub enum ChosenItem<C: CharacteristicsInterface> {
@@ -191,6 +197,30 @@ where
     add_mixed_paint_callbacks: RefCell<Vec<Box<dyn Fn(&MixedPaint<C>)>>>,
     series_paint_dialogs: RefCell<HashMap<u32, SeriesPaintDisplayDialog<A, C>>>,
     mixed_paint_dialogs: RefCell<HashMap<u32, MixedPaintDisplayDialog<A, C>>>,
+    display_name_policy: Cell<DisplayNamePolicy>,
+    show_gamut: Cell<bool>,
+}
+
+impl<A, C> DisplayDialogHost for MixerHueAttrWheelCore<A, C>
+where
+    C: CharacteristicsInterface + 'static,
+    A: ColourAttributesInterface + 'static,
+{
+    fn display_dialogs(&self) -> Vec<gtk::Dialog> {
+        let mut dialogs: Vec<gtk::Dialog> = self
+            .series_paint_dialogs
+            .borrow()
+            .values()
+            .map(|dialog| dialog.dialog())
+            .collect();
+        dialogs.extend(
+            self.mixed_paint_dialogs
+                .borrow()
+                .values()
+                .map(|dialog| dialog.dialog()),
+        );
+        dialogs
+    }
 }
 
 impl<A, C> PackableWidgetObject for MixerHueAttrWheelCore<A, C>
@@ -232,9 +262,94 @@ where
             add_mixed_paint_callbacks: RefCell::new(Vec::new()),
             series_paint_dialogs: RefCell::new(HashMap::new()),
             mixed_paint_dialogs: RefCell::new(HashMap::new()),
+            display_name_policy: Cell::new(DisplayNamePolicy::default()),
+            show_gamut: Cell::new(recall(SHOW_GAMUT_RECOLLECTION) == Some("true".to_string())),
         });
+        wheel.graticule.set_legend_entries(&[
+            (ShapeType::Square, "Series Paint"),
+            (ShapeType::Diamond, "Mixed Paint"),
+            (ShapeType::Circle, "Target"),
+        ]);
 
         let wheel_c = wheel.clone();
+        let show_chosen_item_info: Rc<dyn Fn()> = Rc::new(move || {
+            let target_colour = wheel_c.graticule.current_target_colour().clone();
+            let target = if let Some(ref colour) = target_colour {
+                Some(colour)
+            } else {
+                None
+            };
+            match *wheel_c.chosen_item.borrow() {
+                ChosenItem::SeriesPaint(ref paint) => {
+                    let have_listeners = wheel_c.add_series_paint_callbacks.borrow().len() > 0;
+                    if have_listeners {
+                        let wheel_c_c = wheel_c.clone();
+                        let paint_c = paint.clone();
+                        let spec = PaintDisplayButtonSpec {
+                            label: "Add".to_string(),
+                            tooltip_text: "Add this paint to the paint mixing area.".to_string(),
+                            callback: Box::new(move || wheel_c_c.inform_add_series_paint(&paint_c)),
+                        };
+                        let dialog = SeriesPaintDisplayDialog::<A, C>::create(
+                            &paint,
+                            target,
+                            &wheel_c,
+                            vec![spec],
+                        );
+                        let wheel_c_c = wheel_c.clone();
+                        dialog.connect_destroyed(move |id| {
+                            wheel_c_c.series_paint_dialogs.borrow_mut().remove(&id);
+                        });
+                        wheel_c
+                            .series_paint_dialogs
+                            .borrow_mut()
+                            .insert(dialog.id_no(), dialog.clone());
+                        dialog.show();
+                    } else {
+                        SeriesPaintDisplayDialog::<A, C>::create(&paint, target, &wheel_c, vec![])
+                            .show();
+                    }
+                }
+                ChosenItem::MixedPaint(ref paint) => {
+                    let have_listeners = wheel_c.add_mixed_paint_callbacks.borrow().len() > 0;
+                    if have_listeners {
+                        let wheel_c_c = wheel_c.clone();
+                        let paint_c = paint.clone();
+                        let spec = PaintDisplayButtonSpec {
+                            label: "Add".to_string(),
+                            tooltip_text: "Add this paint to the paint mixing area.".to_string(),
+                            callback: Box::new(move || wheel_c_c.inform_add_mixed_paint(&paint_c)),
+                        };
+                        let dialog = MixedPaintDisplayDialog::<A, C>::create(
+                            &paint,
+                            target,
+                            &wheel_c,
+                            vec![spec],
+                        );
+                        let wheel_c_c = wheel_c.clone();
+                        dialog.connect_destroyed(move |id| {
+                            wheel_c_c.mixed_paint_dialogs.borrow_mut().remove(&id);
+                        });
+                        wheel_c
+                            .mixed_paint_dialogs
+                            .borrow_mut()
+                            .insert(dialog.id_no(), dialog.clone());
+                        dialog.show();
+                    } else {
+                        MixedPaintDisplayDialog::<A, C>::create(&paint, None, &wheel_c, vec![])
+                            .show();
+                    }
+                }
+                ChosenItem::TargetColour(ref colour) => {
+                    let dialog = TargetColourDisplayDialog::<A>::create(&colour, &wheel_c);
+                    dialog.show();
+                }
+                ChosenItem::None => {
+                    panic!("File: {:?} Line: {:?} SHOULDN'T GET HERE", file!(), line!())
+                }
+            }
+        });
+        let show_chosen_item_info_c = show_chosen_item_info.clone();
         wheel
             .popup_menu
             .append_item(
@@ -242,94 +357,7 @@ where
                 "Paint Information",
                 "Display this paint's information",
             )
-            .connect_activate(move |_| {
-                let target_colour = wheel_c.graticule.current_target_colour().clone();
-                let target = if let Some(ref colour) = target_colour {
-                    Some(colour)
-                } else {
-                    None
-                };
-                match *wheel_c.chosen_item.borrow() {
-                    ChosenItem::SeriesPaint(ref paint) => {
-                        let have_listeners = wheel_c.add_series_paint_callbacks.borrow().len() > 0;
-                        if have_listeners {
-                            let wheel_c_c = wheel_c.clone();
-                            let paint_c = paint.clone();
-                            let spec = PaintDisplayButtonSpec {
-                                label: "Add".to_string(),
-                                tooltip_text: "Add this paint to the paint mixing area."
-                                    .to_string(),
-                                callback: Box::new(move || {
-                                    wheel_c_c.inform_add_series_paint(&paint_c)
-                                }),
-                            };
-                            let dialog = SeriesPaintDisplayDialog::<A, C>::create(
-                                &paint,
-                                target,
-                                &wheel_c,
-                                vec![spec],
-                            );
-                            let wheel_c_c = wheel_c.clone();
-                            dialog.connect_destroyed(move |id| {
-                                wheel_c_c.series_paint_dialogs.borrow_mut().remove(&id);
-                            });
-                            wheel_c
-                                .series_paint_dialogs
-                                .borrow_mut()
-                                .insert(dialog.id_no(), dialog.clone());
-                            dialog.show();
-                        } else {
-                            SeriesPaintDisplayDialog::<A, C>::create(
-                                &paint,
-                                target,
-                                &wheel_c,
-                                vec![],
-                            )
-                            .show();
-                        }
-                    }
-                    ChosenItem::MixedPaint(ref paint) => {
-                        let have_listeners = wheel_c.add_mixed_paint_callbacks.borrow().len() > 0;
-                        if have_listeners {
-                            let wheel_c_c = wheel_c.clone();
-                            let paint_c = paint.clone();
-                            let spec = PaintDisplayButtonSpec {
-                                label: "Add".to_string(),
-                                tooltip_text: "Add this paint to the paint mixing area."
-                                    .to_string(),
-                                callback: Box::new(move || {
-                                    wheel_c_c.inform_add_mixed_paint(&paint_c)
-                                }),
-                            };
-                            let dialog = MixedPaintDisplayDialog::<A, C>::create(
-                                &paint,
-                                target,
-                                &wheel_c,
-                                vec![spec],
-                            );
-                            let wheel_c_c = wheel_c.clone();
-                            dialog.connect_destroyed(move |id| {
-                                wheel_c_c.mixed_paint_dialogs.borrow_mut().remove(&id);
-                            });
-                            wheel_c
-                                .mixed_paint_dialogs
-                                .borrow_mut()
-                                .insert(dialog.id_no(), dialog.clone());
-                            dialog.show();
-                        } else {
-                            MixedPaintDisplayDialog::<A, C>::create(&paint, None, &wheel_c, vec![])
-                                .show();
-                        }
-                    }
-                    ChosenItem::TargetColour(ref colour) => {
-                        let dialog = TargetColourDisplayDialog::<A>::create(&colour, &wheel_c);
-                        dialog.show();
-                    }
-                    ChosenItem::None => {
-                        panic!("File: {:?} Line: {:?} SHOULDN'T GET HERE", file!(), line!())
-                    }
-                }
-            });
+            .connect_activate(move |_| show_chosen_item_info_c());
 
         let wheel_c = wheel.clone();
         wheel
@@ -345,6 +373,60 @@ where
                 }
             });
 
+        let wheel_c = wheel.clone();
+        wheel
+            .popup_menu
+            .append_item(
+                "legend",
+                "Show/Hide Legend",
+                "Toggle the explanation of the wheel's markers",
+            )
+            .connect_activate(move |_| {
+                let show_legend = !wheel_c.graticule.legend_is_shown();
+                wheel_c.graticule.set_show_legend(show_legend);
+            });
+
+        let wheel_c = wheel.clone();
+        wheel
+            .popup_menu
+            .append_item(
+                "gamut",
+                "Show/Hide Reachable Gamut",
+                "Toggle shading the area that can be reached by mixing the paints in the mixing area (green if it includes the target and red if it doesn't)",
+            )
+            .connect_activate(move |_| {
+                let show_gamut = !wheel_c.gamut_is_shown();
+                wheel_c.set_show_gamut(show_gamut);
+            });
+
+        let wheel_c = wheel.clone();
+        wheel
+            .popup_menu
+            .append_item(
+                "cluster",
+                "Cluster/Uncluster Overlaps",
+                "Toggle drawing overlapping markers as one marker showing their number (click it to see them)",
+            )
+            .connect_activate(move |_| {
+                let clustering = !wheel_c.series_paints.is_clustering();
+                wheel_c.set_clustering(clustering);
+            });
+
+        let wheel_c = wheel.clone();
+        wheel
+            .popup_menu
+            .append_item(
+                "export_image",
+                "Export Image...",
+                "Save an image (PNG or SVG) of this wheel",
+            )
+            .connect_activate(move |_| wheel_c.export_image());
+
+        let wheel_c = wheel.clone();
+        append_vision_deficiency_items(&wheel.popup_menu, move |o_deficiency| {
+            wheel_c.set_vision_deficiency(o_deficiency)
+        });
+
         let wheel_c = wheel.clone();
         wheel
             .graticule
@@ -353,29 +435,8 @@ where
                 if event.get_event_type() == gdk::EventType::ButtonPress {
                     if event.get_button() == 3 {
                         let chosen_item = wheel_c.get_item_at(Point::from(event.get_position()));
-                        wheel_c
-                            .popup_menu
-                            .set_sensitivities(!chosen_item.is_none(), &["info"]);
-                        let have_series_listeners =
-                            wheel_c.add_series_paint_callbacks.borrow().len() > 0;
-                        let have_mixed_listeners =
-                            wheel_c.add_mixed_paint_callbacks.borrow().len() > 0;
-                        wheel_c.popup_menu.set_visibilities(
-                            have_series_listeners || have_mixed_listeners,
-                            &["add"],
-                        );
-                        if chosen_item.is_series_paint() {
-                            wheel_c
-                                .popup_menu
-                                .set_sensitivities(have_series_listeners, &["add"]);
-                        } else if chosen_item.is_mixed_paint() {
-                            wheel_c
-                                .popup_menu
-                                .set_sensitivities(have_mixed_listeners, &["add"]);
-                        } else {
-                            wheel_c.popup_menu.set_sensitivities(false, &["add"]);
-                        };
                         *wheel_c.chosen_item.borrow_mut() = chosen_item;
+                        wheel_c.update_popup_sensitivities();
                         wheel_c.popup_menu.popup_at_event(event);
                         return Inhibit(true);
                     }
@@ -383,6 +444,36 @@ where
                 Inhibit(false)
             });
 
+        let wheel_c = wheel.clone();
+        wheel.graticule.connect_key_press_event(move |_, event| {
+            let items = wheel_c.focusable_items();
+            let points: Vec<Point> = items.iter().map(|(_, xy)| *xy).collect();
+            if let Some(focus_move) = FocusMove::from_key(&event.get_keyval()) {
+                let moved = wheel_c.graticule.move_focus(&points, focus_move);
+                return Inhibit(moved.is_some());
+            }
+            let focused_item = wheel_c
+                .graticule
+                .focused_index(&points)
+                .and_then(|index| items.into_iter().nth(index))
+                .map(|(item, _)| item);
+            if is_activate_key(event) {
+                if let Some(item) = focused_item {
+                    *wheel_c.chosen_item.borrow_mut() = item;
+                    show_chosen_item_info();
+                    return Inhibit(true);
+                }
+            } else if is_menu_key(event) {
+                *wheel_c.chosen_item.borrow_mut() = focused_item.unwrap_or(ChosenItem::None);
+                wheel_c.update_popup_sensitivities();
+                wheel_c
+                    .graticule
+                    .popup_menu_at_focus(&wheel_c.popup_menu.pwo());
+                return Inhibit(true);
+            }
+            Inhibit(false)
+        });
+
         let wheel_c = wheel.clone();
         wheel
             .graticule
@@ -392,20 +483,26 @@ where
                 //let rectangle = gtk::Rectangle{x: x, y: y, width: 10, height: -10};
                 //println!("Rectangle: {:?}", rectangle);
                 //tooltip.set_tip_area(&rectangle);
-                match wheel_c.get_item_at(Point(x as f64, y as f64)) {
-                    ChosenItem::SeriesPaint(paint) => {
-                        tooltip.set_text(Some(paint.tooltip_text().as_str()));
-                        true
-                    }
-                    ChosenItem::MixedPaint(paint) => {
-                        tooltip.set_text(Some(paint.tooltip_text().as_str()));
-                        true
-                    }
-                    ChosenItem::TargetColour(colour) => {
-                        tooltip.set_text(Some(colour.tooltip_text().as_str()));
+                let o_text =
+                    wheel_c
+                        .graticule
+                        .throttled_tooltip_text(Point(x as f64, y as f64), |raw_xy| match wheel_c
+                            .get_item_at(raw_xy)
+                        {
+                            ChosenItem::SeriesPaint(paint) => {
+                                let policy = wheel_c.display_name_policy.get();
+                                Some(paint.tooltip_text_for(policy))
+                            }
+                            ChosenItem::MixedPaint(paint) => Some(paint.tooltip_text()),
+                            ChosenItem::TargetColour(colour) => Some(colour.tooltip_text()),
+                            ChosenItem::None => None,
+                        });
+                match o_text {
+                    Some(text) => {
+                        tooltip.set_text(Some(text.as_str()));
                         true
                     }
-                    ChosenItem::None => false,
+                    None => false,
                 }
             });
 
@@ -413,12 +510,24 @@ where
         wheel
             .graticule
             .connect_draw(move |graticule, cairo_context| {
+                if wheel_c.gamut_is_shown() {
+                    wheel_c.draw_gamut(graticule, cairo_context);
+                }
                 cairo_context.set_line_width(2.0);
                 wheel_c.series_paints.draw(graticule, cairo_context);
                 wheel_c.mixed_paints.draw(graticule, cairo_context);
                 wheel_c.target_colours.draw(graticule, cairo_context);
             });
 
+        let wheel_c = wheel.clone();
+        wheel.graticule.connect_clicked(move |graticule, raw_xy| {
+            let point = graticule.reverse_transform(raw_xy);
+            // NB: non short circuit so that every list gets the click
+            wheel_c.series_paints.expand_cluster_at(graticule, point)
+                | wheel_c.mixed_paints.expand_cluster_at(graticule, point)
+                | wheel_c.target_colours.expand_cluster_at(graticule, point)
+        });
+
         wheel
     }
 }
@@ -464,23 +573,141 @@ where
         self.graticule.attr()
     }
 
+    pub fn gamut_is_shown(&self) -> bool {
+        self.show_gamut.get()
+    }
+
+    pub fn set_show_gamut(&self, show_gamut: bool) {
+        self.show_gamut.set(show_gamut);
+        remember(SHOW_GAMUT_RECOLLECTION, &show_gamut.to_string());
+        self.graticule.queue_draw();
+    }
+
+    // The convex hull (on this wheel) of the paints in the mixing area.
+    // Mixtures of the paints can't be outside it.
+    pub fn gamut_hull(&self) -> Vec<Point> {
+        let attr = self.attr();
+        let mut points: Vec<Point> = self
+            .series_paints
+            .coloured_items_xy()
+            .iter()
+            .map(|(paint, _)| colour_gamut_xy(&paint.colour(), attr))
+            .collect();
+        points.extend(
+            self.mixed_paints
+                .coloured_items_xy()
+                .iter()
+                .map(|(paint, _)| colour_gamut_xy(&paint.colour(), attr)),
+        );
+        convex_hull(&points)
+    }
+
+    // Whether the current target (if any) is inside the gamut hull
+    pub fn target_is_reachable(&self) -> Option<bool> {
+        let colour = self.graticule.current_target_colour()?;
+        let point = colour_gamut_xy(&colour, self.attr());
+        Some(hull_contains(&self.gamut_hull(), point))
+    }
+
+    fn draw_gamut(&self, graticule: &GraticuleCore, cairo_context: &cairo::Context) {
+        let hull = self.gamut_hull();
+        if hull.len() < 2 {
+            return;
+        }
+        let rgb = match self.target_is_reachable() {
+            Some(true) => RGB::GREEN,
+            Some(false) => RGB::RED,
+            None => RGB::BLACK,
+        };
+        cairo_context.move_to_point(graticule.transform(hull[0]));
+        for point in hull[1..].iter() {
+            cairo_context.line_to_point(graticule.transform(*point));
+        }
+        cairo_context.close_path();
+        cairo_context.set_source_colour_rgba(&rgb, GAMUT_ALPHA);
+        cairo_context.fill_preserve();
+        cairo_context.set_source_colour_rgba(&rgb, GAMUT_ALPHA * 3.0);
+        cairo_context.set_line_width(1.0);
+        cairo_context.stroke();
+    }
+
+    // Draw overlapping markers as one marker with a count (expanded by clicking it)
+    pub fn set_clustering(&self, clustering: bool) {
+        self.series_paints.set_clustering(clustering);
+        self.mixed_paints.set_clustering(clustering);
+        self.target_colours.set_clustering(clustering);
+        self.graticule.forget_tooltip();
+        self.graticule.queue_draw();
+    }
+
+    // The whole wheel at "size" x "size" pixels as PNG or SVG (by extension)
+    pub fn render_to_file(&self, path: &Path, size: i32) -> io::Result<()> {
+        self.graticule.render_to_file(path, size)
+    }
+
+    pub fn export_image(&self) {
+        export_wheel_image(self, &self.graticule)
+    }
+
+    pub fn vision_deficiency(&self) -> Option<VisionDeficiency> {
+        self.graticule.vision_deficiency()
+    }
+
+    pub fn set_vision_deficiency(&self, o_deficiency: Option<VisionDeficiency>) {
+        self.graticule.set_vision_deficiency(o_deficiency)
+    }
+
+    // Series paints rejected by "filter" are hidden or dimmed
+    pub fn set_series_paint_filter(&self, filter: Option<ColouredItemFilter<SeriesPaint<C>>>) {
+        self.series_paints.set_filter(filter);
+        self.graticule.forget_tooltip();
+        self.graticule.queue_draw();
+    }
+
+    pub fn set_mixed_paint_filter(&self, filter: Option<ColouredItemFilter<MixedPaint<C>>>) {
+        self.mixed_paints.set_filter(filter);
+        self.graticule.forget_tooltip();
+        self.graticule.queue_draw();
+    }
+
+    pub fn set_filtered_display(&self, filtered_display: FilteredItemDisplay) {
+        self.series_paints.set_filtered_display(filtered_display);
+        self.mixed_paints.set_filtered_display(filtered_display);
+        self.graticule.forget_tooltip();
+        self.graticule.queue_draw();
+    }
+
+    pub fn set_display_name_policy(&self, policy: DisplayNamePolicy) {
+        self.display_name_policy.set(policy);
+        self.graticule.forget_tooltip();
+    }
+
     pub fn get_item_at(&self, raw_point: Point) -> ChosenItem<C> {
         let point = self.graticule.reverse_transform(raw_point);
         let mut min_range = std::f64::MAX;
         let mut chosen_item = ChosenItem::None;
-        if let Some((paint, range)) = self.series_paints.get_coloured_item_at(point) {
+        if let Some((paint, range)) = self
+            .series_paints
+            .get_coloured_item_at(&*self.graticule, point)
+        {
             if range < min_range {
                 min_range = range;
                 chosen_item = ChosenItem::SeriesPaint(paint);
             }
         };
-        if let Some((paint, range)) = self.mixed_paints.get_coloured_item_at(point) {
+        if let Some((paint, range)) = self
+            .mixed_paints
+            .get_coloured_item_at(&*self.graticule, point)
+        {
             if range < min_range {
                 min_range = range;
                 chosen_item = ChosenItem::MixedPaint(paint);
             }
         };
-        if let Some((colour, range)) = self.target_colours.get_coloured_item_at(point) {
+        if let Some((colour, range)) = self
+            .target_colours
+            .get_coloured_item_at(&*self.graticule, point)
+        {
             if range < min_range {
                 chosen_item = ChosenItem::TargetColour(colour);
             }
@@ -488,6 +715,49 @@ where
         chosen_item
     }
 
+    // Everything on the wheel that can be given the keyboard focus
+    fn focusable_items(&self) -> Vec<(ChosenItem<C>, Point)> {
+        let mut items: Vec<(ChosenItem<C>, Point)> = self
+            .series_paints
+            .coloured_items_xy()
+            .into_iter()
+            .map(|(paint, xy)| (ChosenItem::SeriesPaint(paint), xy))
+            .collect();
+        items.extend(
+            self.mixed_paints
+                .coloured_items_xy()
+                .into_iter()
+                .map(|(paint, xy)| (ChosenItem::MixedPaint(paint), xy)),
+        );
+        items.extend(
+            self.target_colours
+                .coloured_items_xy()
+                .into_iter()
+                .map(|(colour, xy)| (ChosenItem::TargetColour(colour), xy)),
+        );
+        items
+    }
+
+    fn update_popup_sensitivities(&self) {
+        let chosen_item = self.chosen_item.borrow();
+        self.popup_menu
+            .set_sensitivities(!chosen_item.is_none(), &["info"]);
+        let have_series_listeners = self.add_series_paint_callbacks.borrow().len() > 0;
+        let have_mixed_listeners = self.add_mixed_paint_callbacks.borrow().len() > 0;
+        self.popup_menu
+            .set_visibilities(have_series_listeners || have_mixed_listeners, &["add"]);
+        if chosen_item.is_series_paint() {
+            self.popup_menu
+                .set_sensitivities(have_series_listeners, &["add"]);
+        } else if chosen_item.is_mixed_paint() {
+            self.popup_menu
+                .set_sensitivities(have_mixed_listeners, &["add"]);
+        } else {
+            self.popup_menu.set_sensitivities(false, &["add"]);
+        };
+        update_vision_deficiency_items(&self.popup_menu, self.vision_deficiency());
+    }
+
     pub fn connect_add_series_paint<F: 'static + Fn(&SeriesPaint<C>)>(&self, callback: F) {
         self.add_series_paint_callbacks
             .borrow_mut()
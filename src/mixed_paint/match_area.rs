@@ -1,19 +1,29 @@
 // Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
 
 use std::cell::{Cell, RefCell};
+use std::fmt;
 use std::rc::Rc;
+use std::str::FromStr;
+
+use chrono::prelude::*;
 
 use pw_gix::{
     cairo,
     gdk_pixbuf::Pixbuf,
+    glib,
     gtk::{self, prelude::*},
-    gtkx::menu::*,
+    gtkx::{dialog::*, menu::*},
+    recollections::{recall, remember},
     wrapper::*,
 };
 
 use crate::cairox::*;
 
 use crate::colour::*;
+use crate::colour_difference::*;
+use crate::graticule::{
+    append_vision_deficiency_items, draw_vision_deficiency_note, update_vision_deficiency_items,
+};
 
 use super::*;
 
@@ -22,14 +32,260 @@ struct Sample {
     position: Point,
 }
 
+const ANNOTATION_MARKER_RADIUS: f64 = 5.0;
+const ANNOTATION_FONT_SIZE: f64 = 12.0;
+const TARGET_CHANGE_STEPS: u32 = 12;
+const TARGET_CHANGE_STEP_MILLIS: u32 = 25;
+
+pub const COMPARISON_MODE_RECOLLECTION: &str = "match_area::comparison_mode";
+const COMPARISON_SCALE_RECOLLECTION: &str = "match_area::comparison_scale";
+pub const DEFAULT_COMPARISON_SCALE: f64 = 0.25;
+pub const MIN_COMPARISON_SCALE: f64 = 0.02;
+pub const MAX_COMPARISON_SCALE: f64 = 0.5;
+const COMPARISON_ZOOM_FACTOR: f64 = 1.1;
+const MIN_CHECK_SIDE: f64 = 4.0;
+
+// How the target is shown against the mixed colour
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ComparisonMode {
+    Inset,
+    VerticalSplit,
+    Checkerboard,
+    Spot,
+}
+
+impl Default for ComparisonMode {
+    fn default() -> ComparisonMode {
+        ComparisonMode::Inset
+    }
+}
+
+static COMPARISON_MODE_VALUES: &[ComparisonMode] = &[
+    ComparisonMode::Inset,
+    ComparisonMode::VerticalSplit,
+    ComparisonMode::Checkerboard,
+    ComparisonMode::Spot,
+];
+
+impl ComparisonMode {
+    pub fn values() -> Vec<ComparisonMode> {
+        COMPARISON_MODE_VALUES.to_vec()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ComparisonMode::Inset => "inset",
+            ComparisonMode::VerticalSplit => "vertical_split",
+            ComparisonMode::Checkerboard => "checkerboard",
+            ComparisonMode::Spot => "spot",
+        }
+    }
+
+    pub fn menu_key(&self) -> String {
+        format!("comparison_{}", self.name())
+    }
+
+    pub fn label(&self) -> &'static str {
+        match *self {
+            ComparisonMode::Inset => "Compare with Target Inset",
+            ComparisonMode::VerticalSplit => "Compare Side by Side",
+            ComparisonMode::Checkerboard => "Compare as Checkerboard",
+            ComparisonMode::Spot => "Compare with Target Spot",
+        }
+    }
+
+    pub fn tooltip_text(&self) -> &'static str {
+        match *self {
+            ComparisonMode::Inset => "Show the target as a rectangle in the middle of the mixed colour",
+            ComparisonMode::VerticalSplit => {
+                "Show the mixed colour on the left and the target on the right"
+            }
+            ComparisonMode::Checkerboard => {
+                "Interleave the target and mixed colour as a checkerboard (scroll to resize the squares)"
+            }
+            ComparisonMode::Spot => {
+                "Show the target as a round spot on the mixed colour (scroll to resize it and click to move it)"
+            }
+        }
+    }
+
+    // Whether scrolling changes the size of the target's shapes
+    pub fn is_zoomable(&self) -> bool {
+        match *self {
+            ComparisonMode::Checkerboard | ComparisonMode::Spot => true,
+            _ => false,
+        }
+    }
+
+    pub fn recall() -> ComparisonMode {
+        if let Some(text) = recall(COMPARISON_MODE_RECOLLECTION) {
+            if let Ok(mode) = ComparisonMode::from_str(&text) {
+                return mode;
+            }
+        };
+        ComparisonMode::default()
+    }
+
+    pub fn remember(&self) {
+        remember(COMPARISON_MODE_RECOLLECTION, self.name())
+    }
+}
+
+impl FromStr for ComparisonMode {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<ComparisonMode, String> {
+        for mode in COMPARISON_MODE_VALUES.iter() {
+            if mode.name() == string {
+                return Ok(*mode);
+            }
+        }
+        Err(format!("{}: unknown comparison mode", string))
+    }
+}
+
+impl fmt::Display for ComparisonMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.label())
+    }
+}
+
+const IMAGE_FILE_RECOLLECTION: &str = "match_area::image_file";
+pub const MIN_IMAGE_ZOOM: f64 = 0.01;
+pub const MAX_IMAGE_ZOOM: f64 = 32.0;
+const IMAGE_ZOOM_FACTOR: f64 = 1.25;
+// A click (rather than a drag) samples a square this many pixels across
+const CLICK_SAMPLE_SIDE: f64 = 9.0;
+const CLICK_TOLERANCE: f64 = 3.0;
+
+// How a loaded image is shown in its pane: "origin" is the image pixel
+// at the top left corner of the pane.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImageView {
+    pub origin: Point,
+    pub zoom: f64,
+}
+
+impl ImageView {
+    // All of the image centred in the pane
+    pub fn fitted(image_size: (f64, f64), pane_size: (f64, f64)) -> ImageView {
+        let zoom = (pane_size.0 / image_size.0.max(1.0))
+            .min(pane_size.1 / image_size.1.max(1.0))
+            .max(MIN_IMAGE_ZOOM)
+            .min(MAX_IMAGE_ZOOM);
+        let offset = Point(
+            (pane_size.0 - image_size.0 * zoom) / 2.0,
+            (pane_size.1 - image_size.1 * zoom) / 2.0,
+        );
+        ImageView {
+            origin: Point(0.0, 0.0) - offset / zoom,
+            zoom,
+        }
+    }
+
+    pub fn to_image(&self, pane_point: Point) -> Point {
+        self.origin + pane_point / self.zoom
+    }
+
+    pub fn to_pane(&self, image_point: Point) -> Point {
+        (image_point - self.origin) * self.zoom
+    }
+
+    // "delta" is in pane pixels
+    pub fn panned(&self, delta: Point) -> ImageView {
+        ImageView {
+            origin: self.origin - delta / self.zoom,
+            zoom: self.zoom,
+        }
+    }
+
+    // Zoom in (or out for negative "steps") keeping the image pixel under
+    // "pane_point" where it is
+    pub fn zoomed_about(&self, pane_point: Point, steps: i32) -> ImageView {
+        let zoom = (self.zoom * IMAGE_ZOOM_FACTOR.powi(steps))
+            .max(MIN_IMAGE_ZOOM)
+            .min(MAX_IMAGE_ZOOM);
+        ImageView {
+            origin: self.to_image(pane_point) - pane_point / zoom,
+            zoom,
+        }
+    }
+}
+
+// The (x, y, width, height) of the pixels of an image of "image_size"
+// within the rectangle with corners "a" and "b" (in image coordinates)
+pub fn image_region(a: Point, b: Point, image_size: (i32, i32)) -> Option<(i32, i32, i32, i32)> {
+    let x0 = a.0.min(b.0).floor().max(0.0) as i32;
+    let y0 = a.1.min(b.1).floor().max(0.0) as i32;
+    let x1 = (a.0.max(b.0).ceil() as i32).min(image_size.0);
+    let y1 = (a.1.max(b.1).ceil() as i32).min(image_size.1);
+    if x1 > x0 && y1 > y0 {
+        Some((x0, y0, x1 - x0, y1 - y0))
+    } else {
+        None
+    }
+}
+
+struct LoadedImage {
+    pixbuf: Pixbuf,
+    view: ImageView,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ImageDrag {
+    start: Point,
+    last: Point,
+    selecting: bool,
+}
+
+// The scale after zooming in (or out for negative "steps") on the
+// checkerboard squares or spot
+pub fn zoomed_comparison_scale(scale: f64, steps: i32) -> f64 {
+    (scale * COMPARISON_ZOOM_FACTOR.powi(steps))
+        .max(MIN_COMPARISON_SCALE)
+        .min(MAX_COMPARISON_SCALE)
+}
+
+// A note placed on the match area by the user.  Checkpoints record the
+// mixed colour at the time they were made.
+#[derive(Clone)]
+pub struct Annotation {
+    pub text: String,
+    pub position: Point, // as a fraction of the area's width and height
+    pub colour: Option<Colour>,
+    pub timestamp: DateTime<Local>,
+}
+
+impl Annotation {
+    pub fn report_text(&self) -> String {
+        format!("{}: {}", self.timestamp.format("%X"), self.text)
+    }
+}
+
 #[derive(PWO, Wrapper)]
 pub struct ColourMatchAreaCore {
     drawing_area: gtk::DrawingArea,
     mixed_colour: RefCell<Option<Colour>>,
     target_colour: RefCell<Option<Colour>>,
+    // The colour shown for the target while changing from one to another
+    shown_target_rgb: Rc<RefCell<Option<RGB>>>,
+    target_change_id: Rc<Cell<u32>>,
     popup_menu: WrappedMenu,
     samples: RefCell<Vec<Sample>>,
+    annotations: RefCell<Vec<Annotation>>,
+    samples_changed_callbacks: RefCell<Vec<Box<dyn Fn()>>>,
     popup_menu_position: Cell<Point>,
+    delta_e_metric: Cell<DeltaEMetric>,
+    vision_deficiency: Cell<Option<VisionDeficiency>>,
+    comparison_mode: Cell<ComparisonMode>,
+    // The size of the checkerboard's squares or the spot (as a fraction
+    // of the area's smaller dimension)
+    comparison_scale: Cell<f64>,
+    spot_position: Cell<Point>, // as a fraction of the area's width and height
+    // An image (e.g. a photograph of a physical sample) to take colours from
+    image: RefCell<Option<LoadedImage>>,
+    image_drag: Cell<Option<ImageDrag>>,
+    sampled_colour: RefCell<Option<Colour>>,
     mixing_mode: MixingMode,
 }
 
@@ -39,26 +295,395 @@ impl ColourMatchAreaCore {
     }
 
     fn draw(&self, drawing_area: &gtk::DrawingArea, cairo_context: &cairo::Context) {
-        if let Some(ref colour) = *self.mixed_colour.borrow() {
-            let rgb = colour.rgb();
-            cairo_context.set_source_rgb(rgb[CCI::Red], rgb[CCI::Green], rgb[CCI::Blue]);
+        with_vision_deficiency(self.vision_deficiency.get(), || {
+            if let Some(ref colour) = *self.mixed_colour.borrow() {
+                cairo_context.set_source_colour_rgb(&colour.rgb());
+            } else {
+                cairo_context.set_source_rgb(0.0, 0.0, 0.0);
+            };
+            cairo_context.paint();
+            let o_target_rgb = match *self.shown_target_rgb.borrow() {
+                Some(rgb) => Some(rgb),
+                None => self
+                    .target_colour
+                    .borrow()
+                    .as_ref()
+                    .map(|colour| colour.rgb()),
+            };
+            if let Some(rgb) = o_target_rgb {
+                cairo_context.set_source_colour_rgb(&rgb);
+                self.draw_target(drawing_area, cairo_context);
+            }
+            self.draw_image(cairo_context);
+            for sample in self.samples.borrow().iter() {
+                cairo_context.set_source_pixbuf_at(&sample.pix_buf, sample.position);
+                cairo_context.set_line_width(0.0);
+                cairo_context.paint();
+            }
+        });
+        self.draw_annotations(drawing_area, cairo_context);
+        self.draw_delta_e(drawing_area, cairo_context);
+        draw_vision_deficiency_note(
+            cairo_context,
+            self.vision_deficiency.get(),
+            Point(ANNOTATION_FONT_SIZE / 2.0, ANNOTATION_FONT_SIZE / 2.0),
+        );
+    }
+
+    // The image's pane is the right half of the area
+    fn image_pane(&self) -> (Point, (f64, f64)) {
+        let width = self.drawing_area.get_allocated_width() as f64;
+        let height = self.drawing_area.get_allocated_height() as f64;
+        (Point(width / 2.0, 0.0), (width / 2.0, height))
+    }
+
+    fn point_in_image_pane(&self, position: Point) -> bool {
+        let (pane_origin, (pane_width, pane_height)) = self.image_pane();
+        self.image.borrow().is_some()
+            && position.0 >= pane_origin.0
+            && position.0 < pane_origin.0 + pane_width
+            && position.1 >= pane_origin.1
+            && position.1 < pane_origin.1 + pane_height
+    }
+
+    fn draw_image(&self, cairo_context: &cairo::Context) {
+        let width = self.drawing_area.get_allocated_width() as f64;
+        let height = self.drawing_area.get_allocated_height() as f64;
+        if let Some(ref image) = *self.image.borrow() {
+            let (pane_origin, (pane_width, pane_height)) = self.image_pane();
+            cairo_context.save();
+            cairo_context.rectangle(pane_origin.0, pane_origin.1, pane_width, pane_height);
+            cairo_context.clip();
+            cairo_context.translate(pane_origin.0, pane_origin.1);
+            cairo_context.scale(image.view.zoom, image.view.zoom);
+            cairo_context.set_source_pixbuf_at(&image.pixbuf, Point(0.0, 0.0) - image.view.origin);
+            cairo_context.paint();
+            cairo_context.restore();
+        }
+        // shown in the middle of the mixed colour's half for comparison
+        if let Some(ref colour) = *self.sampled_colour.borrow() {
+            cairo_context.set_source_colour_rgb(&colour.rgb());
+            cairo_context.rectangle(width / 8.0, height / 4.0, width / 4.0, height / 2.0);
+            cairo_context.fill();
+        }
+        if let Some(drag) = self.image_drag.get() {
+            if drag.selecting {
+                cairo_context.set_source_colour_rgb(&RGB::WHITE);
+                cairo_context.set_line_width(1.0);
+                cairo_context.rectangle(
+                    drag.start.0,
+                    drag.start.1,
+                    drag.last.0 - drag.start.0,
+                    drag.last.1 - drag.start.1,
+                );
+                cairo_context.stroke();
+            }
+        }
+    }
+
+    pub fn load_image(&self) {
+        let o_last_file = recall(IMAGE_FILE_RECOLLECTION);
+        let last_file = if let Some(ref text) = o_last_file {
+            Some(text.as_str())
         } else {
-            cairo_context.set_source_rgb(0.0, 0.0, 0.0);
+            None
         };
-        cairo_context.paint();
-        if let Some(ref colour) = *self.target_colour.borrow() {
-            let rgb = colour.rgb();
-            cairo_context.set_source_rgb(rgb[CCI::Red], rgb[CCI::Green], rgb[CCI::Blue]);
-            let width = drawing_area.get_allocated_width() as f64;
+        if let Some(path) = self.ask_file_path(Some("Sample Image File:"), last_file, true) {
+            match Pixbuf::from_file(&path) {
+                Ok(pixbuf) => {
+                    remember(IMAGE_FILE_RECOLLECTION, &path.to_string_lossy());
+                    let (_, pane_size) = self.image_pane();
+                    let image_size = (pixbuf.get_width() as f64, pixbuf.get_height() as f64);
+                    let view =
+                        ImageView::fitted(image_size, (pane_size.0.max(1.0), pane_size.1.max(1.0)));
+                    *self.image.borrow_mut() = Some(LoadedImage { pixbuf, view });
+                    self.drawing_area.queue_draw();
+                }
+                Err(err) => {
+                    let msg = format!("{:?}: Failed to load sample image", path);
+                    self.report_error(&msg, &err);
+                }
+            }
+        }
+    }
+
+    pub fn has_image(&self) -> bool {
+        self.image.borrow().is_some()
+    }
+
+    pub fn remove_image(&self) {
+        *self.image.borrow_mut() = None;
+        self.drawing_area.queue_draw();
+    }
+
+    pub fn fit_image(&self) {
+        let (_, pane_size) = self.image_pane();
+        if let Some(ref mut image) = *self.image.borrow_mut() {
+            let image_size = (
+                image.pixbuf.get_width() as f64,
+                image.pixbuf.get_height() as f64,
+            );
+            image.view = ImageView::fitted(image_size, pane_size);
+        }
+        self.drawing_area.queue_draw();
+    }
+
+    // "delta" is in drawing area pixels
+    fn pan_image(&self, delta: Point) {
+        if let Some(ref mut image) = *self.image.borrow_mut() {
+            image.view = image.view.panned(delta);
+        }
+        self.drawing_area.queue_draw();
+    }
+
+    // "position" is in drawing area coordinates
+    fn zoom_image(&self, position: Point, steps: i32) {
+        let (pane_origin, _) = self.image_pane();
+        if let Some(ref mut image) = *self.image.borrow_mut() {
+            image.view = image.view.zoomed_about(position - pane_origin, steps);
+        }
+        self.drawing_area.queue_draw();
+    }
+
+    // The average colour of the image within the rectangle with corners
+    // "a" and "b" (in drawing area coordinates) becomes the sampled colour.
+    // If they're (nearly) the same point a small square around it is used.
+    fn sample_image_region(&self, a: Point, b: Point) {
+        let (pane_origin, _) = self.image_pane();
+        let o_rgb = if let Some(ref image) = *self.image.borrow() {
+            let (ia, ib) = if (b - a).hypot() < CLICK_TOLERANCE {
+                let centre = image.view.to_image(a - pane_origin);
+                let half = (CLICK_SAMPLE_SIDE / 2.0 / image.view.zoom).max(0.5);
+                (centre - Point(half, half), centre + Point(half, half))
+            } else {
+                (
+                    image.view.to_image(a - pane_origin),
+                    image.view.to_image(b - pane_origin),
+                )
+            };
+            let image_size = (image.pixbuf.get_width(), image.pixbuf.get_height());
+            image_region(ia, ib, image_size)
+                .and_then(|(x, y, width, height)| image.pixbuf.new_subpixbuf(x, y, width, height))
+                .and_then(|region| colour_spread(&pixbuf_rgbs(&region)))
+                .map(|spread| spread.mean)
+        } else {
+            None
+        };
+        if let Some(rgb) = o_rgb {
+            *self.sampled_colour.borrow_mut() = Some(Colour::from(rgb));
+            self.drawing_area.queue_draw();
+            self.inform_samples_changed();
+        }
+    }
+
+    // The colour most recently sampled from the image (if any)
+    pub fn get_sampled_colour(&self) -> Option<Colour> {
+        self.sampled_colour.borrow().clone()
+    }
+
+    pub fn forget_sampled_colour(&self) {
+        *self.sampled_colour.borrow_mut() = None;
+        self.drawing_area.queue_draw();
+        self.inform_samples_changed();
+    }
+
+    fn draw_target(&self, drawing_area: &gtk::DrawingArea, cairo_context: &cairo::Context) {
+        let width = drawing_area.get_allocated_width() as f64;
+        let height = drawing_area.get_allocated_height() as f64;
+        let scaled = self.comparison_scale.get() * width.min(height);
+        match self.comparison_mode.get() {
+            ComparisonMode::Inset => {
+                cairo_context.rectangle(width / 4.0, height / 4.0, width / 2.0, height / 2.0);
+            }
+            ComparisonMode::VerticalSplit => {
+                cairo_context.rectangle(width / 2.0, 0.0, width / 2.0, height);
+            }
+            ComparisonMode::Checkerboard => {
+                let side = (scaled / 2.0).max(MIN_CHECK_SIDE);
+                let n_cols = (width / side).ceil() as usize;
+                let n_rows = (height / side).ceil() as usize;
+                for row in 0..n_rows {
+                    for col in (row % 2..n_cols).step_by(2) {
+                        cairo_context.rectangle(col as f64 * side, row as f64 * side, side, side);
+                    }
+                }
+            }
+            ComparisonMode::Spot => {
+                let position = self.spot_position.get();
+                let centre = Point(position.0 * width, position.1 * height);
+                cairo_context.arc(centre.0, centre.1, scaled, 0.0, 2.0 * std::f64::consts::PI);
+            }
+        }
+        cairo_context.fill();
+    }
+
+    pub fn comparison_mode(&self) -> ComparisonMode {
+        self.comparison_mode.get()
+    }
+
+    pub fn set_comparison_mode(&self, mode: ComparisonMode) {
+        self.comparison_mode.set(mode);
+        mode.remember();
+        self.drawing_area.queue_draw();
+    }
+
+    pub fn comparison_scale(&self) -> f64 {
+        self.comparison_scale.get()
+    }
+
+    pub fn zoom_comparison(&self, steps: i32) {
+        let scale = zoomed_comparison_scale(self.comparison_scale.get(), steps);
+        self.comparison_scale.set(scale);
+        remember(COMPARISON_SCALE_RECOLLECTION, &scale.to_string());
+        self.drawing_area.queue_draw();
+    }
+
+    // "position" is in drawing area coordinates
+    pub fn move_spot(&self, position: Point) {
+        self.spot_position.set(self.fractional_position(position));
+        self.drawing_area.queue_draw();
+    }
+
+    fn draw_delta_e(&self, drawing_area: &gtk::DrawingArea, cairo_context: &cairo::Context) {
+        if let (Some(delta_e), Some(ref colour)) = (self.delta_e(), &*self.mixed_colour.borrow()) {
             let height = drawing_area.get_allocated_height() as f64;
-            cairo_context.rectangle(width / 4.0, height / 4.0, width / 2.0, height / 2.0);
-            cairo_context.fill();
+            let text = format!("\u{394}E ({}) = {:.2}", self.delta_e_metric.get(), delta_e);
+            cairo_context.set_font_size(ANNOTATION_FONT_SIZE);
+            cairo_context.set_source_colour_rgb(&colour.best_foreground_rgb());
+            cairo_context.move_to(
+                ANNOTATION_FONT_SIZE / 2.0,
+                height - ANNOTATION_FONT_SIZE / 2.0,
+            );
+            cairo_context.show_text(&text);
         }
-        for sample in self.samples.borrow().iter() {
-            cairo_context.set_source_pixbuf_at(&sample.pix_buf, sample.position);
-            cairo_context.set_line_width(0.0);
-            cairo_context.paint();
+    }
+
+    pub fn delta_e_metric(&self) -> DeltaEMetric {
+        self.delta_e_metric.get()
+    }
+
+    pub fn set_delta_e_metric(&self, metric: DeltaEMetric) {
+        self.delta_e_metric.set(metric);
+        metric.remember();
+        self.drawing_area.queue_draw();
+    }
+
+    pub fn vision_deficiency(&self) -> Option<VisionDeficiency> {
+        self.vision_deficiency.get()
+    }
+
+    // NB: only the displayed colours are affected (not delta E)
+    pub fn set_vision_deficiency(&self, o_deficiency: Option<VisionDeficiency>) {
+        self.vision_deficiency.set(o_deficiency);
+        self.drawing_area.queue_draw();
+    }
+
+    // The difference between the mixed colour and the target (or the
+    // colour sampled from the image) if both exist
+    pub fn delta_e(&self) -> Option<f64> {
+        let target_colour = self
+            .target_colour
+            .borrow()
+            .clone()
+            .or_else(|| self.sampled_colour.borrow().clone());
+        let mixed_colour = self.mixed_colour.borrow();
+        match (&target_colour, &*mixed_colour) {
+            (Some(target), Some(mixed)) => Some(
+                self.delta_e_metric
+                    .get()
+                    .delta_e(&target.rgb(), &mixed.rgb()),
+            ),
+            _ => None,
+        }
+    }
+
+    fn draw_annotations(&self, drawing_area: &gtk::DrawingArea, cairo_context: &cairo::Context) {
+        let width = drawing_area.get_allocated_width() as f64;
+        let height = drawing_area.get_allocated_height() as f64;
+        let background_rgb = match *self.mixed_colour.borrow() {
+            Some(ref colour) => colour.rgb(),
+            None => RGB::BLACK,
+        };
+        let foreground_rgb = background_rgb.best_foreground_rgb();
+        cairo_context.set_font_size(ANNOTATION_FONT_SIZE);
+        cairo_context.set_line_width(1.0);
+        for annotation in self.annotations.borrow().iter() {
+            let position = Point(
+                annotation.position.0 * width,
+                annotation.position.1 * height,
+            );
+            if let Some(ref colour) = annotation.colour {
+                cairo_context.set_source_colour_rgb(&colour.rgb());
+                cairo_context.draw_circle(position, ANNOTATION_MARKER_RADIUS, true);
+            }
+            cairo_context.set_source_colour_rgb(&foreground_rgb);
+            cairo_context.draw_circle(position, ANNOTATION_MARKER_RADIUS, false);
+            cairo_context.move_to(
+                position.0 + ANNOTATION_MARKER_RADIUS * 2.0,
+                position.1 + ANNOTATION_FONT_SIZE / 3.0,
+            );
+            cairo_context.show_text(&annotation.report_text());
+        }
+    }
+
+    fn fractional_position(&self, position: Point) -> Point {
+        let width = (self.drawing_area.get_allocated_width() as f64).max(1.0);
+        let height = (self.drawing_area.get_allocated_height() as f64).max(1.0);
+        Point(position.0 / width, position.1 / height)
+    }
+
+    pub fn annotations(&self) -> Vec<Annotation> {
+        self.annotations.borrow().clone()
+    }
+
+    // "position" is in drawing area coordinates
+    pub fn add_annotation(&self, text: &str, position: Point, colour: Option<&Colour>) {
+        let annotation = Annotation {
+            text: text.to_string(),
+            position: self.fractional_position(position),
+            colour: colour.cloned(),
+            timestamp: Local::now(),
+        };
+        self.annotations.borrow_mut().push(annotation);
+        self.drawing_area.queue_draw();
+    }
+
+    pub fn add_checkpoint(&self, position: Point) {
+        let o_colour = self.mixed_colour.borrow().clone();
+        self.add_annotation("Checkpoint", position, o_colour.as_ref());
+    }
+
+    pub fn remove_annotations(&self) {
+        self.annotations.borrow_mut().clear();
+        self.drawing_area.queue_draw();
+    }
+
+    fn ask_annotation_text(&self) -> Option<String> {
+        let dialog = self.new_dialog_with_buttons(
+            Some("Add Note"),
+            gtk::DialogFlags::DESTROY_WITH_PARENT,
+            CANCEL_OK_BUTTONS,
+        );
+        dialog.set_default_response(gtk::ResponseType::Ok);
+        let entry = gtk::Entry::new();
+        entry.set_activates_default(true);
+        entry.set_tooltip_text(Some("e.g. \"too green\""));
+        let content_area = dialog.get_content_area();
+        content_area.pack_start(&entry, false, false, 0);
+        content_area.show_all();
+        let mut o_text: Option<String> = None;
+        if gtk::ResponseType::from(dialog.run()) == gtk::ResponseType::Ok {
+            let text = entry.get_text().trim().to_string();
+            if text.len() > 0 {
+                o_text = Some(text);
+            }
         }
+        unsafe { dialog.destroy() };
+        o_text
+    }
+
+    pub fn get_mixed_colour(&self) -> Option<Colour> {
+        self.mixed_colour.borrow().clone()
     }
 
     pub fn get_target_colour(&self) -> Option<Colour> {
@@ -84,6 +709,17 @@ impl ColourMatchAreaCore {
 
     pub fn set_target_colour(&self, colour: Option<&Colour>) {
         assert!(colour.is_none() || self.mixing_mode == MixingMode::MatchTarget);
+        let o_old_rgb = self
+            .target_colour
+            .borrow()
+            .as_ref()
+            .map(|colour| colour.rgb());
+        if let (Some(old_rgb), Some(colour)) = (o_old_rgb, colour) {
+            self.animate_target_change(old_rgb, colour.rgb());
+        } else {
+            self.target_change_id.set(self.target_change_id.get() + 1);
+            *self.shown_target_rgb.borrow_mut() = None;
+        }
         if let Some(colour) = colour {
             *self.target_colour.borrow_mut() = Some(colour.clone())
         } else {
@@ -92,9 +728,63 @@ impl ColourMatchAreaCore {
         self.drawing_area.queue_draw();
     }
 
+    // Glide from the old target to the new one (a newer change cancels it)
+    fn animate_target_change(&self, from: RGB, to: RGB) {
+        let change_id = self.target_change_id.get() + 1;
+        self.target_change_id.set(change_id);
+        let target_change_id = self.target_change_id.clone();
+        let shown_target_rgb = self.shown_target_rgb.clone();
+        let drawing_area = self.drawing_area.clone();
+        let step = Cell::new(0);
+        glib::timeout_add_local(TARGET_CHANGE_STEP_MILLIS, move || {
+            if target_change_id.get() != change_id {
+                return glib::Continue(false);
+            }
+            step.set(step.get() + 1);
+            let done = step.get() >= TARGET_CHANGE_STEPS;
+            *shown_target_rgb.borrow_mut() = if done {
+                None
+            } else {
+                let t = step.get() as f64 / TARGET_CHANGE_STEPS as f64;
+                Some(interpolate(
+                    &from,
+                    &to,
+                    t,
+                    InterpolationSpace::Lab,
+                    Easing::EaseInOut,
+                ))
+            };
+            drawing_area.queue_draw();
+            glib::Continue(!done)
+        });
+        *self.shown_target_rgb.borrow_mut() = Some(from);
+    }
+
     pub fn remove_samples(&self) {
         self.samples.borrow_mut().clear();
         self.drawing_area.queue_draw();
+        self.inform_samples_changed();
+    }
+
+    // The spread of the colours in the pasted samples
+    pub fn sample_spread(&self) -> Option<ColourSpread> {
+        let mut rgbs: Vec<RGB> = Vec::new();
+        for sample in self.samples.borrow().iter() {
+            rgbs.extend(pixbuf_rgbs(&sample.pix_buf));
+        }
+        colour_spread(&rgbs)
+    }
+
+    pub fn connect_samples_changed<F: 'static + Fn()>(&self, callback: F) {
+        self.samples_changed_callbacks
+            .borrow_mut()
+            .push(Box::new(callback))
+    }
+
+    fn inform_samples_changed(&self) {
+        for callback in self.samples_changed_callbacks.borrow().iter() {
+            callback();
+        }
     }
 }
 
@@ -114,16 +804,104 @@ impl ColourMatchAreaInterface for ColourMatchArea {
             drawing_area: gtk::DrawingArea::new(),
             mixed_colour: RefCell::new(None),
             target_colour: RefCell::new(None),
+            shown_target_rgb: Rc::new(RefCell::new(None)),
+            target_change_id: Rc::new(Cell::new(0)),
             popup_menu: WrappedMenu::new(&vec![]),
             samples: RefCell::new(Vec::new()),
+            annotations: RefCell::new(Vec::new()),
+            samples_changed_callbacks: RefCell::new(Vec::new()),
             popup_menu_position: Cell::new(Point(0.0, 0.0)),
+            delta_e_metric: Cell::new(DeltaEMetric::recall()),
+            vision_deficiency: Cell::new(None),
+            comparison_mode: Cell::new(ComparisonMode::recall()),
+            comparison_scale: Cell::new(
+                recall(COMPARISON_SCALE_RECOLLECTION)
+                    .and_then(|text| f64::from_str(&text).ok())
+                    .map_or(DEFAULT_COMPARISON_SCALE, |scale| {
+                        zoomed_comparison_scale(scale, 0)
+                    }),
+            ),
+            spot_position: Cell::new(Point(0.5, 0.5)),
+            image: RefCell::new(None),
+            image_drag: Cell::new(None),
+            sampled_colour: RefCell::new(None),
             mixing_mode: mixing_mode,
         });
 
-        if mixing_mode == MixingMode::MatchSamples {
-            let events = gdk::EventMask::BUTTON_PRESS_MASK | gdk::EventMask::BUTTON_RELEASE_MASK;
-            colour_match_area.drawing_area.add_events(events);
+        let events = gdk::EventMask::BUTTON_PRESS_MASK
+            | gdk::EventMask::BUTTON_RELEASE_MASK
+            | gdk::EventMask::BUTTON1_MOTION_MASK
+            | gdk::EventMask::SCROLL_MASK;
+        colour_match_area.drawing_area.add_events(events);
+
+        let colour_match_area_c = colour_match_area.clone();
+        colour_match_area
+            .popup_menu
+            .append_item("annotate", "Add Note...", "Add a note at this position")
+            .connect_activate(move |_| {
+                if let Some(text) = colour_match_area_c.ask_annotation_text() {
+                    let position = colour_match_area_c.popup_menu_position.get();
+                    colour_match_area_c.add_annotation(&text, position, None);
+                }
+            });
+
+        let colour_match_area_c = colour_match_area.clone();
+        colour_match_area
+            .popup_menu
+            .append_item(
+                "checkpoint",
+                "Checkpoint Mixed Colour",
+                "Mark this position with the current mixed colour and time",
+            )
+            .connect_activate(move |_| {
+                let position = colour_match_area_c.popup_menu_position.get();
+                colour_match_area_c.add_checkpoint(position);
+            });
 
+        let colour_match_area_c = colour_match_area.clone();
+        colour_match_area
+            .popup_menu
+            .append_item(
+                "remove_notes",
+                "Remove Notes",
+                "Remove all notes and checkpoints from the match area",
+            )
+            .connect_activate(move |_| {
+                colour_match_area_c.remove_annotations();
+            });
+
+        for metric in DeltaEMetric::values() {
+            let colour_match_area_c = colour_match_area.clone();
+            colour_match_area
+                .popup_menu
+                .append_item(
+                    &format!("delta_e_{}", metric.name()),
+                    &format!("Measure \u{394}E by {}", metric.label()),
+                    metric.tooltip_text(),
+                )
+                .connect_activate(move |_| {
+                    colour_match_area_c.set_delta_e_metric(metric);
+                });
+        }
+
+        if mixing_mode == MixingMode::MatchTarget {
+            for mode in ComparisonMode::values() {
+                let colour_match_area_c = colour_match_area.clone();
+                colour_match_area
+                    .popup_menu
+                    .append_item(&mode.menu_key(), mode.label(), mode.tooltip_text())
+                    .connect_activate(move |_| {
+                        colour_match_area_c.set_comparison_mode(mode);
+                    });
+            }
+        }
+
+        let colour_match_area_c = colour_match_area.clone();
+        append_vision_deficiency_items(&colour_match_area.popup_menu, move |o_deficiency| {
+            colour_match_area_c.set_vision_deficiency(o_deficiency)
+        });
+
+        if mixing_mode == MixingMode::MatchSamples {
             let colour_match_area_c = colour_match_area.clone();
             colour_match_area
                 .popup_menu
@@ -141,6 +919,7 @@ impl ColourMatchAreaInterface for ColourMatchArea {
                         };
                         colour_match_area_c.samples.borrow_mut().push(sample);
                         colour_match_area_c.drawing_area.queue_draw();
+                        colour_match_area_c.inform_samples_changed();
                     } else {
                         colour_match_area_c.inform_user("No image data on clipboard.", None);
                     }
@@ -160,12 +939,46 @@ impl ColourMatchAreaInterface for ColourMatchArea {
 
             let colour_match_area_c = colour_match_area.clone();
             colour_match_area
-                .drawing_area
-                .connect_button_press_event(move |_, event| {
-                    if event.get_event_type() == gdk::EventType::ButtonPress {
-                        if event.get_button() == 3 {
-                            let position = Point::from(event.get_position());
-                            colour_match_area_c.popup_menu_position.set(position);
+                .popup_menu
+                .append_item(
+                    "load_image",
+                    "Load Image...",
+                    "Load an image (e.g. a photograph of a physical sample) into the right half of the area.  Drag to pan it, scroll to zoom it and click (or control drag over a region) to use its average colour as the target",
+                )
+                .connect_activate(move |_| colour_match_area_c.load_image());
+
+            let colour_match_area_c = colour_match_area.clone();
+            colour_match_area
+                .popup_menu
+                .append_item("fit_image", "Fit Image", "Show all of the loaded image")
+                .connect_activate(move |_| colour_match_area_c.fit_image());
+
+            let colour_match_area_c = colour_match_area.clone();
+            colour_match_area
+                .popup_menu
+                .append_item("remove_image", "Remove Image", "Remove the loaded image")
+                .connect_activate(move |_| colour_match_area_c.remove_image());
+
+            let colour_match_area_c = colour_match_area.clone();
+            colour_match_area
+                .popup_menu
+                .append_item(
+                    "forget_sampled",
+                    "Forget Sampled Colour",
+                    "Stop using the colour sampled from the image as the target",
+                )
+                .connect_activate(move |_| colour_match_area_c.forget_sampled_colour());
+        };
+
+        let colour_match_area_c = colour_match_area.clone();
+        colour_match_area
+            .drawing_area
+            .connect_button_press_event(move |_, event| {
+                if event.get_event_type() == gdk::EventType::ButtonPress {
+                    if event.get_button() == 3 {
+                        let position = Point::from(event.get_position());
+                        colour_match_area_c.popup_menu_position.set(position);
+                        if colour_match_area_c.mixing_mode == MixingMode::MatchSamples {
                             let cbd = gtk::Clipboard::get(&gdk::SELECTION_CLIPBOARD);
                             let pastable = cbd.wait_is_image_available();
                             colour_match_area_c
@@ -175,13 +988,134 @@ impl ColourMatchAreaInterface for ColourMatchArea {
                             colour_match_area_c
                                 .popup_menu
                                 .set_sensitivities(have_samples, &["remove"]);
-                            colour_match_area_c.popup_menu.popup_at_event(event);
-                            return Inhibit(true);
+                            let have_image = colour_match_area_c.has_image();
+                            colour_match_area_c
+                                .popup_menu
+                                .set_sensitivities(have_image, &["fit_image", "remove_image"]);
+                            let have_sampled =
+                                colour_match_area_c.sampled_colour.borrow().is_some();
+                            colour_match_area_c
+                                .popup_menu
+                                .set_sensitivities(have_sampled, &["forget_sampled"]);
                         }
+                        let have_mixed_colour = colour_match_area_c.mixed_colour.borrow().is_some();
+                        colour_match_area_c
+                            .popup_menu
+                            .set_sensitivities(have_mixed_colour, &["checkpoint"]);
+                        let have_notes = colour_match_area_c.annotations.borrow().len() > 0;
+                        colour_match_area_c
+                            .popup_menu
+                            .set_sensitivities(have_notes, &["remove_notes"]);
+                        for metric in DeltaEMetric::values() {
+                            let key = format!("delta_e_{}", metric.name());
+                            let is_current = metric == colour_match_area_c.delta_e_metric.get();
+                            colour_match_area_c
+                                .popup_menu
+                                .set_sensitivities(!is_current, &[key.as_str()]);
+                        }
+                        if colour_match_area_c.mixing_mode == MixingMode::MatchTarget {
+                            let current_mode = colour_match_area_c.comparison_mode.get();
+                            for mode in ComparisonMode::values() {
+                                colour_match_area_c.popup_menu.set_sensitivities(
+                                    mode != current_mode,
+                                    &[mode.menu_key().as_str()],
+                                );
+                            }
+                        }
+                        update_vision_deficiency_items(
+                            &colour_match_area_c.popup_menu,
+                            colour_match_area_c.vision_deficiency.get(),
+                        );
+                        colour_match_area_c.popup_menu.popup_at_event(event);
+                        return Inhibit(true);
+                    } else if event.get_button() == 1
+                        && colour_match_area_c
+                            .point_in_image_pane(Point::from(event.get_position()))
+                    {
+                        let position = Point::from(event.get_position());
+                        let selecting = event.get_state().contains(gdk::ModifierType::CONTROL_MASK);
+                        colour_match_area_c.image_drag.set(Some(ImageDrag {
+                            start: position,
+                            last: position,
+                            selecting,
+                        }));
+                        return Inhibit(true);
+                    } else if event.get_button() == 1
+                        && colour_match_area_c.comparison_mode.get() == ComparisonMode::Spot
+                        && colour_match_area_c.has_target_colour()
+                    {
+                        colour_match_area_c.move_spot(Point::from(event.get_position()));
+                        return Inhibit(true);
                     }
-                    Inhibit(false)
-                });
-        };
+                }
+                Inhibit(false)
+            });
+
+        let colour_match_area_c = colour_match_area.clone();
+        colour_match_area
+            .drawing_area
+            .connect_motion_notify_event(move |_, event| {
+                if let Some(mut drag) = colour_match_area_c.image_drag.get() {
+                    let position = Point::from(event.get_position());
+                    if !drag.selecting {
+                        colour_match_area_c.pan_image(position - drag.last);
+                    }
+                    drag.last = position;
+                    colour_match_area_c.image_drag.set(Some(drag));
+                    colour_match_area_c.drawing_area.queue_draw();
+                    return Inhibit(true);
+                }
+                Inhibit(false)
+            });
+
+        let colour_match_area_c = colour_match_area.clone();
+        colour_match_area
+            .drawing_area
+            .connect_button_release_event(move |_, event| {
+                if event.get_button() == 1 {
+                    if let Some(drag) = colour_match_area_c.image_drag.take() {
+                        // a click is a drag that doesn't go anywhere
+                        if drag.selecting || (drag.last - drag.start).hypot() < CLICK_TOLERANCE {
+                            colour_match_area_c
+                                .sample_image_region(drag.start, Point::from(event.get_position()));
+                        }
+                        colour_match_area_c.drawing_area.queue_draw();
+                        return Inhibit(true);
+                    }
+                }
+                Inhibit(false)
+            });
+
+        let colour_match_area_c = colour_match_area.clone();
+        colour_match_area
+            .drawing_area
+            .connect_scroll_event(move |_, scroll_event| {
+                let position = Point::from(scroll_event.get_position());
+                if colour_match_area_c.point_in_image_pane(position) {
+                    match scroll_event.get_direction() {
+                        gdk::ScrollDirection::Up => colour_match_area_c.zoom_image(position, 1),
+                        gdk::ScrollDirection::Down => colour_match_area_c.zoom_image(position, -1),
+                        _ => return Inhibit(false),
+                    }
+                    return Inhibit(true);
+                }
+                if !colour_match_area_c.comparison_mode.get().is_zoomable()
+                    || !colour_match_area_c.has_target_colour()
+                {
+                    return Inhibit(false);
+                }
+                match scroll_event.get_direction() {
+                    gdk::ScrollDirection::Up => {
+                        colour_match_area_c.zoom_comparison(1);
+                        Inhibit(true)
+                    }
+                    gdk::ScrollDirection::Down => {
+                        colour_match_area_c.zoom_comparison(-1);
+                        Inhibit(true)
+                    }
+                    _ => Inhibit(false),
+                }
+            });
 
         let colour_match_area_c = colour_match_area.clone();
         colour_match_area
@@ -196,10 +1130,59 @@ impl ColourMatchAreaInterface for ColourMatchArea {
 
 #[cfg(test)]
 mod tests {
-    //use super::*;
+    use super::*;
 
     #[test]
     fn paint_mixer_test() {
         //assert!(false)
     }
+
+    #[test]
+    fn comparison_modes() {
+        for mode in ComparisonMode::values() {
+            assert_eq!(ComparisonMode::from_str(mode.name()), Ok(mode));
+        }
+        assert!(ComparisonMode::from_str("mosaic").is_err());
+        assert!(!ComparisonMode::Inset.is_zoomable());
+        assert!(ComparisonMode::Spot.is_zoomable());
+        let scale = zoomed_comparison_scale(DEFAULT_COMPARISON_SCALE, 1);
+        assert!(scale > DEFAULT_COMPARISON_SCALE);
+        assert!((zoomed_comparison_scale(scale, -1) - DEFAULT_COMPARISON_SCALE).abs() < 1.0e-12);
+        assert_eq!(zoomed_comparison_scale(0.4, 100), MAX_COMPARISON_SCALE);
+        assert_eq!(zoomed_comparison_scale(0.4, -100), MIN_COMPARISON_SCALE);
+    }
+
+    #[test]
+    fn image_views() {
+        let view = ImageView::fitted((200.0, 100.0), (100.0, 100.0));
+        assert_eq!(view.zoom, 0.5);
+        // the image is centred vertically
+        assert!((view.to_pane(Point(0.0, 0.0)) - Point(0.0, 25.0)).hypot() < 1.0e-9);
+        let point = Point(30.0, 40.0);
+        assert!((view.to_pane(view.to_image(point)) - point).hypot() < 1.0e-9);
+        let panned = view.panned(Point(10.0, 0.0));
+        assert!(
+            (panned.to_image(Point(10.0, 0.0)) - view.to_image(Point(0.0, 0.0))).hypot() < 1.0e-9
+        );
+        let zoomed = view.zoomed_about(point, 2);
+        assert!(zoomed.zoom > view.zoom);
+        assert!((zoomed.to_image(point) - view.to_image(point)).hypot() < 1.0e-9);
+        assert_eq!(view.zoomed_about(point, 100).zoom, MAX_IMAGE_ZOOM);
+    }
+
+    #[test]
+    fn image_regions() {
+        assert_eq!(
+            image_region(Point(10.2, 5.0), Point(2.5, 8.7), (100, 100)),
+            Some((2, 5, 9, 4))
+        );
+        assert_eq!(
+            image_region(Point(-5.0, -5.0), Point(5.0, 200.0), (10, 10)),
+            Some((0, 0, 5, 10))
+        );
+        assert_eq!(
+            image_region(Point(20.0, 0.0), Point(30.0, 5.0), (10, 10)),
+            None
+        );
+    }
 }
@@ -0,0 +1,242 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+// Measuring paint in real units (ml or g) instead of abstract parts.
+// Colours mix by volume so weights are converted using the paint's density.
+
+use std::fmt;
+use std::str::FromStr;
+
+use pw_gix::recollections::{recall, remember};
+
+// Used for paints whose density hasn't been recorded (i.e. like water)
+pub const DEFAULT_DENSITY: f64 = 1.0;
+
+// Measured quantities are converted to parts in hundredths of a millilitre
+pub const MEASURED_PARTS_PER_ML: f64 = 100.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuantityUnit {
+    Parts,
+    Millilitres,
+    Grams,
+}
+
+impl Default for QuantityUnit {
+    fn default() -> QuantityUnit {
+        QuantityUnit::Parts
+    }
+}
+
+static QUANTITY_UNIT_VALUES: &[QuantityUnit] = &[
+    QuantityUnit::Parts,
+    QuantityUnit::Millilitres,
+    QuantityUnit::Grams,
+];
+
+impl QuantityUnit {
+    pub fn values() -> Vec<QuantityUnit> {
+        QUANTITY_UNIT_VALUES.to_vec()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            QuantityUnit::Parts => "parts",
+            QuantityUnit::Millilitres => "ml",
+            QuantityUnit::Grams => "g",
+        }
+    }
+
+    pub fn menu_key(&self) -> String {
+        format!("unit_{}", self.name())
+    }
+
+    pub fn label(&self) -> &'static str {
+        match *self {
+            QuantityUnit::Parts => "Measure in Parts",
+            QuantityUnit::Millilitres => "Measure in Millilitres",
+            QuantityUnit::Grams => "Measure in Grams",
+        }
+    }
+
+    pub fn tooltip_text(&self) -> &'static str {
+        match *self {
+            QuantityUnit::Parts => "Give each paint's contribution as a whole number of parts",
+            QuantityUnit::Millilitres => {
+                "Give each paint's contribution as a volume in millilitres"
+            }
+            QuantityUnit::Grams => {
+                "Give each paint's contribution as a weight in grams (using the paint's density)"
+            }
+        }
+    }
+
+    pub fn is_measured(&self) -> bool {
+        *self != QuantityUnit::Parts
+    }
+
+    pub fn digits(&self) -> u32 {
+        match *self {
+            QuantityUnit::Parts => 0,
+            _ => 1,
+        }
+    }
+
+    pub fn increments(&self) -> (f64, f64) {
+        match *self {
+            QuantityUnit::Parts => (1.0, 10.0),
+            _ => (0.1, 1.0),
+        }
+    }
+
+    // Move the quantity by a number of (step) increments staying within 0..=max
+    pub fn adjusted_quantity(&self, quantity: f64, steps: i32, max: f64) -> f64 {
+        let (step, _) = self.increments();
+        let scale = 10.0_f64.powi(self.digits() as i32);
+        let quantity = ((quantity + steps as f64 * step) * scale).round() / scale;
+        quantity.max(0.0).min(max)
+    }
+
+    // NB: parts are treated as millilitres when changing units
+    pub fn volume(&self, quantity: f64, density: Option<f64>) -> f64 {
+        match *self {
+            QuantityUnit::Grams => quantity / density.unwrap_or(DEFAULT_DENSITY),
+            _ => quantity,
+        }
+    }
+
+    pub fn quantity_for_volume(&self, volume: f64, density: Option<f64>) -> f64 {
+        match *self {
+            QuantityUnit::Parts => volume.round(),
+            QuantityUnit::Millilitres => volume,
+            QuantityUnit::Grams => volume * density.unwrap_or(DEFAULT_DENSITY),
+        }
+    }
+
+    // Any non zero quantity contributes at least one part
+    pub fn parts(&self, quantity: f64, density: Option<f64>) -> u32 {
+        if quantity <= 0.0 {
+            0
+        } else if *self == QuantityUnit::Parts {
+            quantity.round() as u32
+        } else {
+            let parts = self.volume(quantity, density) * MEASURED_PARTS_PER_ML;
+            (parts.round() as u32).max(1)
+        }
+    }
+
+    pub fn quantity_for_parts(&self, parts: u32, density: Option<f64>) -> f64 {
+        self.quantity_for_volume(parts as f64, density)
+    }
+
+    pub fn recall(key: &str) -> QuantityUnit {
+        if let Some(text) = recall(key) {
+            if let Ok(unit) = QuantityUnit::from_str(&text) {
+                return unit;
+            }
+        };
+        QuantityUnit::default()
+    }
+
+    pub fn remember(&self, key: &str) {
+        remember(key, self.name())
+    }
+}
+
+impl FromStr for QuantityUnit {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<QuantityUnit, String> {
+        for unit in QUANTITY_UNIT_VALUES.iter() {
+            if unit.name() == string {
+                return Ok(*unit);
+            }
+        }
+        Err(format!("{}: unknown quantity unit", string))
+    }
+}
+
+impl fmt::Display for QuantityUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// One line per (named) quantity with the amounts right aligned
+pub fn recipe_text(quantities: &[(String, f64)], unit: QuantityUnit) -> String {
+    let digits = unit.digits() as usize;
+    let amounts: Vec<String> = quantities
+        .iter()
+        .map(|(_, quantity)| format!("{:.*} {}", digits, quantity, unit))
+        .collect();
+    let width = amounts.iter().map(|amount| amount.len()).max().unwrap_or(0);
+    let mut text = String::new();
+    for ((name, _), amount) in quantities.iter().zip(amounts.iter()) {
+        text += &format!("{:>width$}  {}\n", amount, name, width = width);
+    }
+    if unit.is_measured() {
+        let total: f64 = quantities.iter().map(|(_, quantity)| quantity).sum();
+        text += &format!(
+            "{:>width$}  Total\n",
+            format!("{:.*} {}", digits, total, unit),
+            width = width
+        );
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantity_unit_names() {
+        for unit in QuantityUnit::values() {
+            assert_eq!(QuantityUnit::from_str(unit.name()), Ok(unit));
+        }
+        assert!(QuantityUnit::from_str("cups").is_err());
+    }
+
+    #[test]
+    fn quantities_to_parts() {
+        assert_eq!(QuantityUnit::Parts.parts(3.0, None), 3);
+        assert_eq!(QuantityUnit::Millilitres.parts(1.5, None), 150);
+        assert_eq!(QuantityUnit::Millilitres.parts(0.001, None), 1);
+        assert_eq!(QuantityUnit::Millilitres.parts(0.0, None), 0);
+        // 3g of a paint twice as dense as water is 1.5 ml
+        assert_eq!(QuantityUnit::Grams.parts(3.0, Some(2.0)), 150);
+        assert_eq!(QuantityUnit::Grams.parts(3.0, None), 300);
+        let volume = QuantityUnit::Grams.volume(4.0, Some(2.0));
+        assert_eq!(
+            QuantityUnit::Millilitres.quantity_for_volume(volume, Some(2.0)),
+            2.0
+        );
+        assert_eq!(QuantityUnit::Grams.quantity_for_parts(2, Some(1.5)), 3.0);
+    }
+
+    #[test]
+    fn quantity_adjustments() {
+        assert_eq!(QuantityUnit::Parts.adjusted_quantity(3.0, 1, 999.0), 4.0);
+        assert_eq!(QuantityUnit::Parts.adjusted_quantity(3.0, -10, 999.0), 0.0);
+        assert_eq!(
+            QuantityUnit::Parts.adjusted_quantity(995.0, 10, 999.0),
+            999.0
+        );
+        assert_eq!(
+            QuantityUnit::Millilitres.adjusted_quantity(1.5, -1, 999.0),
+            1.4
+        );
+        assert_eq!(QuantityUnit::Grams.adjusted_quantity(0.2, 10, 999.0), 1.2);
+        assert_eq!(QuantityUnit::Grams.adjusted_quantity(0.05, -1, 999.0), 0.0);
+    }
+
+    #[test]
+    fn printable_recipe() {
+        let quantities = vec![("Red".to_string(), 12.5), ("White".to_string(), 2.0)];
+        assert_eq!(
+            recipe_text(&quantities, QuantityUnit::Millilitres),
+            "12.5 ml  Red\n 2.0 ml  White\n14.5 ml  Total\n"
+        );
+        let parts = vec![("Red".to_string(), 3.0)];
+        assert_eq!(recipe_text(&parts, QuantityUnit::Parts), "3 parts  Red\n");
+    }
+}
@@ -1,8 +1,12 @@
 // Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
 
+use std::cell::{Cell, RefCell};
+use std::fs::File;
+use std::io::Write;
 use std::marker::PhantomData;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::str::FromStr;
 
 use num::Integer;
 
@@ -10,28 +14,60 @@ use chrono::prelude::*;
 use xml::escape::*;
 
 use pw_gix::{
+    gdk,
     gdk_pixbuf::Pixbuf,
+    glib,
     gtk::{self, prelude::*},
     gtkx::paned::*,
+    recollections::{recall, remember},
     wrapper::*,
 };
 
 use crate::basic_paint::*;
+use crate::colln_paint::collection::PaintUsage;
 use crate::colour::*;
+use crate::colour_difference::*;
+use crate::colour_edit::TargetColourExchange;
 use crate::colour_mix::*;
+use crate::colour_names::suggest_colour_name;
+use crate::diagnostics::show_diagnostics;
+use crate::dialogue::DisplayDialogHost;
+use crate::error::*;
 use crate::icons::mixtures_print_xpm;
 use crate::series_paint::*;
+use crate::spell_check::enable_spell_checking;
 use crate::standards::*;
+use crate::vision_check::*;
+use crate::wheel_pages::*;
 
+use super::broadcast::*;
+use super::candidates::*;
 use super::collection::*;
 use super::components::*;
+use super::csv_export::*;
+use super::favourites::*;
 use super::hue_wheel::*;
 use super::match_area::*;
+use super::measures::*;
+use super::notes_template::*;
+use super::report::*;
+use super::session::*;
+use super::solver::*;
 use super::target::*;
+use super::target_library::*;
+use super::trainer::*;
 use super::*;
 
 pub trait MixerConfig {
     fn mixing_mode() -> MixingMode;
+
+    fn mixing_model() -> MixingModel {
+        MixingModel::default()
+    }
+
+    fn recollection_name_for(item_name: &str) -> String {
+        format!("paint_mixer::{}", item_name)
+    }
 }
 
 pub trait PaintMixerInterface<A, C, MC>
@@ -58,27 +94,82 @@ where
 {
     vbox: gtk::Box,
     cads: Rc<A>,
+    cads_expander: gtk::Expander,
     colour_match_area: ColourMatchArea,
+    notebook: gtk::Notebook,
     hue_attr_wheels: Vec<MixerHueAttrWheel<A, C>>,
     series_paint_components: SeriesPaintComponentBox<A, C>,
     mixed_paints: MixedPaintCollectionWidget<A, C>,
     notes: gtk::Entry,
     next_name_label: gtk::Label,
     mixed_paint_notes: gtk::Entry,
+    suggested_notes: RefCell<String>,
+    target_name: RefCell<String>,
+    target_broadcast: TargetBroadcast,
+    practice_target: RefCell<Option<PracticeTarget<SeriesPaint<C>>>>,
+    target_provenance: RefCell<Option<TargetProvenance>>,
+    recipe_being_edited: RefCell<Option<MixedPaint<C>>>,
+    practice_difficulty_combo: gtk::ComboBoxText,
+    notes_template_menu: gtk::Menu,
+    display_dialogs_menu: gtk::Menu,
+    display_name_policy_combo: gtk::ComboBoxText,
+    target_tolerance_spin: gtk::SpinButton,
+    sample_spread_label: gtk::Label,
+    sample_spread: Cell<Option<ColourSpread>>,
+    flag_target_reached_btn: gtk::CheckButton,
+    chime_target_reached_btn: gtk::CheckButton,
+    target_reached: Rc<Cell<bool>>,
+    target_reached_callbacks: RefCell<Vec<Box<dyn Fn(&Colour, f64)>>>,
+    report_sections: RefCell<Vec<ReportSectionGenerator<C>>>,
+    favourites_path: PathBuf,
+    target_library_path: PathBuf,
     // Buttons
     new_mixture_btn: gtk::Button,
+    practice_btn: gtk::Button,
+    notes_template_btn: gtk::Button,
     print_report_btn: gtk::Button,
+    vision_check_btn: gtk::Button,
+    diagnostics_btn: gtk::Button,
+    favourites_btn: gtk::Button,
+    target_library_btn: gtk::Button,
+    export_csv_btn: gtk::Button,
+    export_pdf_btn: gtk::Button,
+    save_session_btn: gtk::Button,
+    load_session_btn: gtk::Button,
+    merge_session_btn: gtk::Button,
+    display_dialogs_btn: gtk::Button,
     accept_mixture_btn: gtk::Button,
     reset_parts_btn: gtk::Button,
     remove_unused_btn: gtk::Button,
     simplify_parts_btn: gtk::Button,
+    print_recipe_btn: gtk::Button,
+    suggest_mix_btn: gtk::Button,
+    candidates: CandidateSetView<C>,
+    suggest_max_parts_spin: gtk::SpinButton,
     cancel_btn: gtk::Button,
+    compact_mode_btn: gtk::ToggleButton,
     // Managers
     series_paint_manager: SeriesPaintManager<A, C>,
     o_paint_standards_manager: Option<PaintStandardManager<A, C>>,
+    o_standards_proximity_list: Option<PaintStandardProximityList<A, C>>,
     phantom: PhantomData<MC>,
 }
 
+// The accept button is highlighted while the target is reached (if the
+// user has asked for that)
+fn style_accept_button(
+    accept_mixture_btn: &gtk::Button,
+    target_reached: bool,
+    flag_target_reached_btn: &gtk::CheckButton,
+) {
+    let style_context = accept_mixture_btn.get_style_context();
+    if target_reached && flag_target_reached_btn.get_active() {
+        style_context.add_class("suggested-action");
+    } else {
+        style_context.remove_class("suggested-action");
+    }
+}
+
 impl<A, C, MC> PaintMixerCore<A, C, MC>
 where
     A: ColourAttributesInterface + 'static,
@@ -92,6 +183,46 @@ where
         }
     }
 
+    fn wheel_pages(&self) -> Vec<(ScalarAttribute, gtk::DrawingArea)> {
+        self.hue_attr_wheels
+            .iter()
+            .map(|wheel| (wheel.attr(), wheel.pwo()))
+            .collect()
+    }
+
+    pub fn visible_wheels(&self) -> Vec<ScalarAttribute> {
+        wheel_page_order(&self.notebook, &self.wheel_pages())
+    }
+
+    // Show only the wheels for "attrs" in the given order
+    pub fn set_visible_wheels(&self, attrs: &[ScalarAttribute]) {
+        let attrs = valid_wheel_pages(attrs, &A::scalar_attributes());
+        arrange_wheel_pages(&self.notebook, &self.wheel_pages(), &attrs);
+        self.remember_wheel_pages();
+    }
+
+    fn remember_wheel_pages(&self) {
+        remember_wheel_pages(
+            &MC::recollection_name_for("wheel_pages"),
+            &self.visible_wheels(),
+            &A::scalar_attributes(),
+        );
+    }
+
+    pub fn display_name_policy(&self) -> DisplayNamePolicy {
+        if let Some(id) = self.display_name_policy_combo.get_active_id() {
+            if let Ok(policy) = DisplayNamePolicy::from_str(&id) {
+                return policy;
+            }
+        };
+        DisplayNamePolicy::default()
+    }
+
+    pub fn set_display_name_policy(&self, policy: DisplayNamePolicy) {
+        self.display_name_policy_combo
+            .set_active_id(Some(policy.name()));
+    }
+
     fn has_notes(&self) -> bool {
         self.mixed_paint_notes.get_text().len() > 0
     }
@@ -101,6 +232,18 @@ where
         for wheel in self.hue_attr_wheels.iter() {
             wheel.add_series_paint(paint);
         }
+        self.series_paint_manager.refresh_membership();
+    }
+
+    // The paint is added regardless (as the inventory may be out of date)
+    fn warn_if_out_of_stock(&self, paint: &SeriesPaint<C>) {
+        if paint.is_out_of_stock() {
+            let expln = format!(
+                "The inventory for \"{}\" says that none is owned.",
+                paint.name()
+            );
+            self.warn_user("Out of stock!", Some(&expln));
+        }
     }
 
     fn handle_series_paint_removal_request(&self, paint: &SeriesPaint<C>) {
@@ -111,6 +254,7 @@ where
             for wheel in self.hue_attr_wheels.iter() {
                 wheel.remove_series_paint(paint);
             }
+            self.series_paint_manager.refresh_membership();
         } else {
             let expln = format!(
                 "\"{}\" is being used in one or more mixtures.",
@@ -120,6 +264,18 @@ where
         }
     }
 
+    // Mixtures made with series paints whose details have since been
+    // corrected are brought up to date with the corrected versions
+    pub fn refresh_mixed_paints(&self) {
+        let current = self.series_paint_manager.get_series_paints();
+        for paint in self.mixed_paints.refresh_derived_values(&current).iter() {
+            for wheel in self.hue_attr_wheels.iter() {
+                wheel.remove_mixed_paint(paint);
+                wheel.add_mixed_paint(paint);
+            }
+        }
+    }
+
     fn remove_unused_paints_from_mixing_area(&self) {
         //TODO: implement different policies for what "unused" means
         let series_paints_in_use = self.mixed_paints.series_paints_used();
@@ -132,6 +288,20 @@ where
                 wheel.remove_series_paint(paint);
             }
         }
+        self.series_paint_manager.refresh_membership();
+    }
+
+    fn mixed_paint_renamed(&self, old_paint: &MixedPaint<C>, new_paint: &MixedPaint<C>) {
+        let being_edited = self.recipe_being_edited.borrow().as_ref() == Some(old_paint);
+        if being_edited {
+            *self.recipe_being_edited.borrow_mut() = Some(new_paint.clone());
+            self.next_name_label
+                .set_text(&format!("{} (editing):", new_paint.name()));
+        }
+        for wheel in self.hue_attr_wheels.iter() {
+            wheel.remove_mixed_paint(old_paint);
+            wheel.add_mixed_paint(new_paint);
+        }
     }
 
     fn remove_mixed_paint(&self, paint: &MixedPaint<C>) {
@@ -144,6 +314,7 @@ where
                 for wheel in self.hue_attr_wheels.iter() {
                     wheel.remove_mixed_paint(paint);
                 }
+                self.series_paint_manager.refresh_usage();
             }
         }
     }
@@ -151,25 +322,34 @@ where
     fn set_button_sensitivities(&self) {
         let has_colour = self.series_paint_components.has_contributions()
             || self.mixed_paints.components().has_contributions();
-        self.simplify_parts_btn.set_sensitive(has_colour);
+        let is_measured = self.series_paint_components.quantity_unit().is_measured();
+        self.simplify_parts_btn
+            .set_sensitive(has_colour && !is_measured);
+        self.print_recipe_btn.set_sensitive(has_colour);
+        self.candidates.set_snapshot_sensitive(has_colour);
+        self.suggest_mix_btn
+            .set_sensitive(self.colour_match_area.has_target_colour());
         self.reset_parts_btn.set_sensitive(has_colour);
         if MC::mixing_mode() == MixingMode::MatchSamples {
             self.accept_mixture_btn
-                .set_sensitive(has_colour && self.has_notes());
+                .set_sensitive(has_colour && self.has_notes() && !self.mixed_paints.is_read_only());
         } else if self.colour_match_area.has_target_colour() {
             self.series_paint_components.set_sensitive(true);
             self.mixed_paints.components().set_sensitive(true);
             self.new_mixture_btn.set_sensitive(false);
+            self.practice_btn.set_sensitive(false);
             self.cancel_btn.set_sensitive(true);
             self.accept_mixture_btn
-                .set_sensitive(has_colour && self.has_notes());
+                .set_sensitive(has_colour && self.has_notes() && !self.mixed_paints.is_read_only());
             if let Some(ref paint_standards_manager) = self.o_paint_standards_manager {
                 paint_standards_manager.set_initiate_select_ok(false)
             };
         } else {
             self.series_paint_components.set_sensitive(false);
             self.mixed_paints.components().set_sensitive(false);
-            self.new_mixture_btn.set_sensitive(true);
+            self.new_mixture_btn
+                .set_sensitive(!self.mixed_paints.is_read_only());
+            self.practice_btn.set_sensitive(true);
             self.accept_mixture_btn.set_sensitive(false);
             self.cancel_btn.set_sensitive(false);
             if let Some(ref paint_standards_manager) = self.o_paint_standards_manager {
@@ -179,42 +359,350 @@ where
     }
 
     fn set_target_colour(&self, o_colour: Option<&Colour>) {
-        self.cads.set_target_colour(o_colour);
-        self.colour_match_area.set_target_colour(o_colour);
-        self.series_paint_manager.set_target_colour(o_colour);
-        self.series_paint_components.set_current_target(o_colour);
-        self.mixed_paints.set_target_colour(o_colour);
+        self.target_broadcast.broadcast(o_colour);
+    }
+
+    // The single connect point for anything that needs to show the target
+    pub fn connect_target_changed<F: 'static + Fn(Option<&Colour>)>(&self, callback: F) {
+        self.target_broadcast.connect(callback)
+    }
+
+    fn connect_target_receivers(&self) {
+        let cads = self.cads.clone();
+        self.connect_target_changed(move |o_colour| cads.set_target_colour(o_colour));
+        let colour_match_area = self.colour_match_area.clone();
+        self.connect_target_changed(move |o_colour| colour_match_area.set_target_colour(o_colour));
+        let series_paint_manager = self.series_paint_manager.clone();
+        self.connect_target_changed(move |o_colour| {
+            series_paint_manager.set_target_colour(o_colour)
+        });
+        if let Some(ref paint_standards_manager) = self.o_paint_standards_manager {
+            let paint_standards_manager = paint_standards_manager.clone();
+            self.connect_target_changed(move |o_colour| {
+                paint_standards_manager.set_target_colour(o_colour)
+            });
+        }
+        let series_paint_components = self.series_paint_components.clone();
+        self.connect_target_changed(move |o_colour| {
+            series_paint_components.set_current_target(o_colour)
+        });
+        let mixed_paints = self.mixed_paints.clone();
+        self.connect_target_changed(move |o_colour| mixed_paints.set_target_colour(o_colour));
         for wheel in self.hue_attr_wheels.iter() {
-            wheel.set_target_colour(o_colour);
+            let wheel = wheel.clone();
+            self.connect_target_changed(move |o_colour| wheel.set_target_colour(o_colour));
         }
     }
 
     fn start_new_mixture(&self, o_notes: Option<&str>, o_target_colour: Option<&Colour>) {
+        *self.target_provenance.borrow_mut() = None;
         if let Some(notes) = o_notes {
             self.mixed_paint_notes.set_text(notes);
+            *self.target_name.borrow_mut() = notes.to_string();
         } else {
             self.mixed_paint_notes.set_text("");
+            self.target_name.borrow_mut().clear();
         }
         self.set_target_colour(o_target_colour);
         self.series_paint_components.reset_all_parts_to_zero();
         self.mixed_paints.components().reset_all_parts_to_zero();
-        let name_text = format!("#{:03}:", self.mixed_paints.next_mixture_id());
+        let name_text = format!("{}:", self.mixed_paints.next_name());
         self.next_name_label.set_text(name_text.as_str());
         self.set_button_sensitivities();
     }
 
+    fn practice_difficulty(&self) -> PracticeDifficulty {
+        if let Some(id) = self.practice_difficulty_combo.get_active_id() {
+            if let Ok(difficulty) = PracticeDifficulty::from_str(&id) {
+                return difficulty;
+            }
+        };
+        PracticeDifficulty::default()
+    }
+
+    // Start mixing a random target that can be made from the loaded series
+    fn start_practice_mixture(&self) {
+        let paints = self.series_paint_manager.get_series_paints();
+        let difficulty = self.practice_difficulty();
+        let mut rng = PracticeRng::from_time();
+        match generate_practice_target(&paints, difficulty, MC::mixing_model(), &mut rng) {
+            Some(practice_target) => {
+                let notes = format!("Practice ({})", difficulty.label());
+                self.start_new_mixture(Some(&notes), Some(&practice_target.colour));
+                *self.practice_target.borrow_mut() = Some(practice_target);
+            }
+            None => self.inform_user(
+                "Practice targets need at least two paints.",
+                Some("Load a paint series using the Series Paint Manager."),
+            ),
+        }
+    }
+
+    fn report_practice_score(&self) {
+        let o_practice_target = self.practice_target.borrow_mut().take();
+        if let Some(practice_target) = o_practice_target {
+            if let Some(mixed_colour) = self.colour_match_area.get_mixed_colour() {
+                let score = practice_score(&practice_target.colour, &mixed_colour);
+                let policy = self.display_name_policy();
+                let expln = format!(
+                    "{}\n\nThe target was mixed from:\n{}",
+                    practice_score_comment(score),
+                    practice_target.recipe_text(|paint| paint.display_name(policy))
+                );
+                let msg = format!("Practice Score: {}/100", score);
+                self.inform_user(&msg, Some(&expln));
+            }
+        }
+    }
+
+    // Reopen an accepted mixture's components in the mixing area so that
+    // accepting the mixture replaces its recipe (rather than adding a new
+    // mixture).  Mixtures used by other mixtures can't be edited.
+    fn mixture_in_progress(&self) -> bool {
+        self.colour_match_area.has_target_colour()
+            || self.series_paint_components.has_contributions()
+            || self.mixed_paints.components().has_contributions()
+    }
+
+    fn add_favourite(&self, paint: &MixedPaint<C>) {
+        let default_name = if paint.notes().len() > 0 {
+            paint.notes()
+        } else {
+            paint.name()
+        };
+        if let Some((name, tags)) = ask_favourite_details(&default_name, self) {
+            let favourite = FavouriteMix::from_mixed_paint(paint, &name, &tags);
+            let result = read_favourites(&self.favourites_path).and_then(|mut favourites| {
+                favourites.push(favourite);
+                write_favourites(&self.favourites_path, &favourites)
+            });
+            if let Err(err) = result {
+                self.report_error("Failed to save favourite mix", &PaintError::<C>::from(err));
+            }
+        }
+    }
+
+    fn browse_favourites(&self) {
+        let mut favourites = match read_favourites(&self.favourites_path) {
+            Ok(favourites) => favourites,
+            Err(err) => {
+                self.report_error(
+                    "Failed to read favourite mixes",
+                    &PaintError::<C>::from(err),
+                );
+                return;
+            }
+        };
+        if favourites.len() == 0 {
+            self.inform_user(
+                "There are no favourite mixes.",
+                Some("Use a mixed paint's \"Add to Favourites\" menu item to add one."),
+            );
+            return;
+        }
+        let o_target_rgb = self.colour_match_area.get_target_colour().map(|c| c.rgb());
+        let (o_chosen, changed) = browse_favourites(&mut favourites, o_target_rgb.as_ref(), self);
+        if changed {
+            if let Err(err) = write_favourites(&self.favourites_path, &favourites) {
+                self.report_error(
+                    "Failed to save favourite mixes",
+                    &PaintError::<C>::from(err),
+                );
+            }
+        }
+        if let Some(favourite) = o_chosen {
+            self.start_favourite_mixture(&favourite);
+        }
+    }
+
+    fn manage_target_library(&self) {
+        let mut targets = match read_target_library(&self.target_library_path) {
+            Ok(targets) => targets,
+            Err(err) => {
+                self.report_error("Failed to read target library", &PaintError::<C>::from(err));
+                return;
+            }
+        };
+        let o_current = self.colour_match_area.get_target_colour().map(|colour| {
+            (
+                self.target_name.borrow().clone(),
+                colour,
+                self.target_provenance.borrow().clone(),
+            )
+        });
+        let (o_chosen, changed) = manage_target_library(&mut targets, o_current, self);
+        if changed {
+            if let Err(err) = write_target_library(&self.target_library_path, &targets) {
+                self.report_error("Failed to save target library", &PaintError::<C>::from(err));
+            }
+        }
+        if let Some(target) = o_chosen {
+            if self.mixture_in_progress() {
+                self.inform_user(
+                    "A mixture is already in progress.",
+                    Some("Accept or cancel the current mixture before using a saved target."),
+                );
+                return;
+            }
+            self.start_new_mixture(Some(&target.name), Some(&target.colour()));
+            *self.target_provenance.borrow_mut() = target.provenance.clone();
+        }
+    }
+
+    // Remake a favourite from the currently loaded series
+    // (a target that's already been set is kept)
+    fn start_favourite_mixture(&self, favourite: &FavouriteMix) {
+        if self.series_paint_components.has_contributions()
+            || self.mixed_paints.components().has_contributions()
+        {
+            self.inform_user(
+                "A mixture is already in progress.",
+                Some("Accept or cancel the current mixture before using a favourite."),
+            );
+            return;
+        }
+        match favourite.resolve(&self.series_paint_manager.get_series_paints()) {
+            Ok(sp_parts) => {
+                if self.colour_match_area.has_target_colour() {
+                    if self.mixed_paint_notes.get_text().len() == 0 {
+                        self.mixed_paint_notes.set_text(&favourite.notes);
+                    }
+                } else {
+                    let o_target_colour = if MC::mixing_mode() == MixingMode::MatchTarget {
+                        Some(Colour::from(
+                            favourite.target_rgb().unwrap_or(favourite.rgb()),
+                        ))
+                    } else {
+                        None
+                    };
+                    self.start_new_mixture(Some(&favourite.notes), o_target_colour.as_ref());
+                }
+                for (series_paint, _) in sp_parts.iter() {
+                    if !self.series_paint_components.has_paint(series_paint) {
+                        self.add_series_paint(series_paint);
+                    }
+                }
+                self.series_paint_components.set_parts_for(&sp_parts);
+                self.set_button_sensitivities();
+            }
+            Err(missing) => {
+                let expln = format!(
+                    "Load the series containing these paints and try again:\n{}",
+                    missing.join("\n")
+                );
+                self.warn_user(
+                    &format!("\"{}\" needs paints that aren't loaded.", favourite.name),
+                    Some(&expln),
+                );
+            }
+        }
+    }
+
+    fn edit_mixed_paint_recipe(&self, paint: &MixedPaint<C>) {
+        if self.mixture_in_progress() {
+            self.inform_user(
+                "A mixture is already in progress.",
+                Some("Accept or cancel the current mixture before editing a recipe."),
+            );
+            return;
+        }
+        let users = self.mixed_paints.mixed_paints_using_mixed_paint(paint);
+        if users.len() > 0 {
+            let names: Vec<String> = users.iter().map(|user| user.name()).collect();
+            let expln = format!(
+                "\"{}\" is used by: {}.\nRecipes of mixtures used by other mixtures can't be edited.",
+                paint.name(),
+                names.join(", ")
+            );
+            self.warn_user("Edit recipe aborted!", Some(&expln));
+            return;
+        }
+        let message = format!("Edit the recipe of {}: {}?", paint.name(), paint.notes());
+        if !self.ask_confirm_action(&message, None) {
+            return;
+        }
+        let o_target_colour = if MC::mixing_mode() == MixingMode::MatchTarget {
+            Some(paint.matched_colour().unwrap_or(paint.colour()))
+        } else {
+            paint.matched_colour()
+        };
+        self.start_new_mixture(Some(&paint.notes()), o_target_colour.as_ref());
+        let mut sp_parts: Vec<(SeriesPaint<C>, u32)> = vec![];
+        let mut mp_parts: Vec<(MixedPaint<C>, u32)> = vec![];
+        for component in paint.components().iter() {
+            match component.paint {
+                Paint::Series(ref series_paint) => {
+                    if !self.series_paint_components.has_paint(series_paint) {
+                        self.add_series_paint(series_paint);
+                    }
+                    sp_parts.push((series_paint.clone(), component.parts));
+                }
+                Paint::Mixed(ref mixed_paint) => {
+                    if !self.mixed_paints.components().has_paint(mixed_paint) {
+                        self.mixed_paints.components().add_paint(mixed_paint);
+                    }
+                    mp_parts.push((mixed_paint.clone(), component.parts));
+                }
+            }
+        }
+        self.series_paint_components.set_parts_for(&sp_parts);
+        self.mixed_paints.components().set_parts_for(&mp_parts);
+        *self.recipe_being_edited.borrow_mut() = Some(paint.clone());
+        self.next_name_label
+            .set_text(&format!("{} (editing):", paint.name()));
+        self.set_button_sensitivities();
+    }
+
+    fn accept_edited_recipe(&self, paint: &MixedPaint<C>) {
+        let notes: String = String::from(self.mixed_paint_notes.get_text());
+        let o_matched_colour = self.colour_match_area.get_target_colour();
+        let sp_components = self.series_paint_components.get_paint_components();
+        let mp_components = self.mixed_paints.components().get_paint_components();
+        match self.mixed_paints.replace_recipe(
+            paint,
+            &notes,
+            sp_components,
+            mp_components,
+            o_matched_colour,
+        ) {
+            Ok(new_paint) => {
+                for wheel in self.hue_attr_wheels.iter() {
+                    wheel.remove_mixed_paint(paint);
+                    wheel.add_mixed_paint(&new_paint);
+                }
+                self.series_paint_manager.refresh_usage();
+                self.cancel_current_mixture();
+            }
+            Err(err) => {
+                let message = format!("Error: {}: recipe not changed", paint.name());
+                self.report_error(&message, &err);
+            }
+        }
+    }
+
     fn accept_new_mixture(&self) {
+        let o_edited = self.recipe_being_edited.borrow().clone();
+        if let Some(paint) = o_edited {
+            self.accept_edited_recipe(&paint);
+            return;
+        }
+        self.report_practice_score();
         let notes: String = String::from(self.mixed_paint_notes.get_text());
         let o_matched_colour = self.colour_match_area.get_target_colour();
         let sp_components = self.series_paint_components.get_paint_components();
         let mp_components = self.mixed_paints.components().get_paint_components();
-        if let Ok(mixed_paint) =
-            self.mixed_paints
-                .add_paint(&notes, sp_components, mp_components, o_matched_colour)
-        {
+        let o_provenance = self.target_provenance.borrow().clone();
+        if let Ok(mixed_paint) = self.mixed_paints.add_paint_with_provenance(
+            &notes,
+            sp_components,
+            mp_components,
+            o_matched_colour,
+            o_provenance,
+        ) {
             for wheel in self.hue_attr_wheels.iter() {
                 wheel.add_mixed_paint(&mixed_paint);
             }
+            self.series_paint_manager.refresh_usage();
         } else {
             panic!("File: {:?} Line: {:?}", file!(), line!())
         }
@@ -222,25 +710,172 @@ where
     }
 
     fn update_mixed_colour(&self) {
-        let mut colour_mixer = ColourMixer::new();
+        let mut colour_mixer = ColourMixer::with_model(MC::mixing_model());
         for (colour, parts) in self.series_paint_components.iter_colour_components() {
             colour_mixer.add(&colour, parts)
         }
         for (colour, parts) in self.mixed_paints.components().iter_colour_components() {
             colour_mixer.add(&colour, parts)
         }
-        if let Some(ref colour) = colour_mixer.get_colour() {
-            self.colour_match_area.set_mixed_colour(Some(colour));
-            self.cads.set_colour(Some(colour));
-        } else {
-            self.colour_match_area.set_mixed_colour(None);
-            self.cads.set_colour(None);
+        let o_colour = colour_mixer.get_colour();
+        self.check_target_reached(o_colour.as_ref());
+        self.colour_match_area.set_mixed_colour(o_colour.as_ref());
+        self.cads.set_colour(o_colour.as_ref());
+        if let Some(ref proximity_list) = self.o_standards_proximity_list {
+            proximity_list.set_colour(o_colour.as_ref());
         }
+        self.update_suggested_notes(o_colour.as_ref());
         self.set_button_sensitivities();
     }
 
+    pub fn target_tolerance(&self) -> f64 {
+        self.target_tolerance_spin.get_value()
+    }
+
+    pub fn set_target_tolerance(&self, tolerance: f64) {
+        self.target_tolerance_spin.set_value(tolerance);
+    }
+
+    // Callbacks receive the mixed colour and its difference from the target
+    pub fn connect_target_reached<F: 'static + Fn(&Colour, f64)>(&self, callback: F) {
+        self.target_reached_callbacks
+            .borrow_mut()
+            .push(Box::new(callback))
+    }
+
+    fn inform_target_reached(&self, colour: &Colour, delta_e: f64) {
+        for callback in self.target_reached_callbacks.borrow().iter() {
+            callback(colour, delta_e);
+        }
+    }
+
+    // When matching samples the target is their mean colour and the
+    // tolerance is widened by their spread.
+    fn effective_target(&self) -> Option<(RGB, f64)> {
+        if MC::mixing_mode() == MixingMode::MatchSamples {
+            // a colour sampled from a loaded image takes precedence
+            if let Some(sampled) = self.colour_match_area.get_sampled_colour() {
+                return Some((sampled.rgb(), self.target_tolerance()));
+            }
+            self.sample_spread.get().map(|spread| {
+                (
+                    spread.mean,
+                    effective_tolerance(self.target_tolerance(), Some(&spread)),
+                )
+            })
+        } else {
+            self.colour_match_area
+                .get_target_colour()
+                .map(|target| (target.rgb(), self.target_tolerance()))
+        }
+    }
+
+    fn update_sample_spread(&self) {
+        let o_spread = self.colour_match_area.sample_spread();
+        self.sample_spread.set(o_spread);
+        if let Some(spread) = o_spread {
+            let text = format!("Sample Spread: {}", spread.description());
+            self.sample_spread_label.set_text(&text);
+            let tooltip = format!(
+                "{}\n\nEffective tolerance: ΔE {:.1}",
+                spread.tooltip_text(),
+                effective_tolerance(self.target_tolerance(), Some(&spread))
+            );
+            self.sample_spread_label.set_tooltip_text(Some(&tooltip));
+        } else {
+            self.sample_spread_label
+                .set_text("Sample Spread: no samples");
+            self.sample_spread_label.set_tooltip_text(None);
+        }
+        self.update_mixed_colour();
+    }
+
+    // Only act when the mixed colour first comes within tolerance
+    fn check_target_reached(&self, o_colour: Option<&Colour>) {
+        let o_delta_e = match (self.effective_target(), o_colour) {
+            (Some((target, tolerance)), Some(colour)) => {
+                let metric = self.colour_match_area.delta_e_metric();
+                Some((metric.delta_e(&target, &colour.rgb()), tolerance))
+            }
+            _ => None,
+        };
+        let reached = if let Some((delta_e, tolerance)) = o_delta_e {
+            delta_e <= tolerance
+        } else {
+            false
+        };
+        let was_reached = self.target_reached.replace(reached);
+        if reached && !was_reached {
+            if self.flag_target_reached_btn.get_active() {
+                self.flash_accept_button();
+                if self.chime_target_reached_btn.get_active() {
+                    gdk::beep();
+                }
+            }
+            if let (Some(colour), Some((delta_e, _))) = (o_colour, o_delta_e) {
+                self.inform_target_reached(colour, delta_e);
+            }
+        }
+        self.update_accept_button_style();
+    }
+
+    fn update_accept_button_style(&self) {
+        style_accept_button(
+            &self.accept_mixture_btn,
+            self.target_reached.get(),
+            &self.flag_target_reached_btn,
+        );
+    }
+
+    // The style left when the flashing stops is that for the state at
+    // that time (which may have changed while it was flashing)
+    fn flash_accept_button(&self) {
+        let accept_mixture_btn = self.accept_mixture_btn.clone();
+        let target_reached = self.target_reached.clone();
+        let flag_target_reached_btn = self.flag_target_reached_btn.clone();
+        let flashes = Cell::new(6);
+        glib::timeout_add_local(250, move || {
+            flashes.set(flashes.get() - 1);
+            if flashes.get() == 0 {
+                style_accept_button(
+                    &accept_mixture_btn,
+                    target_reached.get(),
+                    &flag_target_reached_btn,
+                );
+                return glib::Continue(false);
+            }
+            let style_context = accept_mixture_btn.get_style_context();
+            if flashes.get() % 2 == 0 {
+                style_context.add_class("suggested-action");
+            } else {
+                style_context.remove_class("suggested-action");
+            }
+            glib::Continue(true)
+        });
+    }
+
+    // A suggested name fills empty notes (and follows the colour) until
+    // the user replaces it with their own
+    fn update_suggested_notes(&self, o_colour: Option<&Colour>) {
+        if self.recipe_being_edited.borrow().is_some() {
+            return;
+        }
+        let suggestion = o_colour.map(suggest_colour_name).unwrap_or_default();
+        let notes = self.mixed_paint_notes.get_text().to_string();
+        let last_suggestion = self.suggested_notes.replace(suggestion.clone());
+        if notes.is_empty() || notes == last_suggestion {
+            self.mixed_paint_notes.set_text(&suggestion);
+        }
+    }
+
     fn cancel_current_mixture(&self) {
+        *self.practice_target.borrow_mut() = None;
+        *self.target_provenance.borrow_mut() = None;
+        *self.recipe_being_edited.borrow_mut() = None;
         self.mixed_paint_notes.set_text("");
+        self.suggested_notes.borrow_mut().clear();
+        self.candidates.clear();
+        self.target_name.borrow_mut().clear();
         self.set_target_colour(None);
         self.next_name_label.set_text("#00?:");
         self.series_paint_components.reset_all_parts_to_zero();
@@ -248,6 +883,87 @@ where
         self.set_button_sensitivities();
     }
 
+    fn notes_template_fields(&self) -> NotesTemplateFields {
+        NotesTemplateFields {
+            target_name: self.target_name.borrow().clone(),
+            date: Local::now().format("%Y-%m-%d").to_string(),
+            mix_id: format!("#{:03}", self.mixed_paints.next_mixture_id()),
+            colour_name: self
+                .colour_match_area
+                .get_mixed_colour()
+                .map(|colour| suggest_colour_name(&colour))
+                .unwrap_or_default(),
+        }
+    }
+
+    fn apply_notes_template(&self, template: &str) {
+        let notes = expand_notes_template(template, &self.notes_template_fields());
+        self.mixed_paint_notes.set_text(&notes);
+        self.set_button_sensitivities();
+    }
+
+    // Fill in the parts of the series paints in the mixing area that
+    // come closest to the target (mixed paint parts are zeroed)
+    fn suggest_mix(&self) {
+        let target = match self.colour_match_area.get_target_colour() {
+            Some(colour) => colour,
+            None => return,
+        };
+        let paints = self.series_paint_components.get_paints();
+        let colours: Vec<Colour> = paints.iter().map(|paint| paint.colour()).collect();
+        let config = SolverConfig {
+            max_total_parts: self.suggest_max_parts_spin.get_value_as_int() as u32,
+            mixing_model: MC::mixing_model(),
+        };
+        match solve_parts(&colours, &target, &config) {
+            Some(solution) => {
+                let components: Vec<(SeriesPaint<C>, u32)> = paints
+                    .iter()
+                    .cloned()
+                    .zip(solution.parts.iter().cloned())
+                    .collect();
+                self.mixed_paints.components().reset_all_parts_to_zero();
+                self.series_paint_components.set_parts_for(&components);
+            }
+            None => self.inform_user(
+                "There are no paints in the mixing area.",
+                Some("Add the series paints to be used before asking for a suggested mix."),
+            ),
+        }
+    }
+
+    fn snapshot_candidate(&self) {
+        if let Some(colour) = self.colour_match_area.get_mixed_colour() {
+            let candidate = Candidate::new(
+                self.series_paint_components.get_paint_components(),
+                self.mixed_paints.components().get_paint_components(),
+                &colour,
+                self.colour_match_area.get_target_colour().as_ref(),
+                self.colour_match_area.delta_e_metric(),
+            );
+            self.candidates.add_candidate(candidate);
+        }
+    }
+
+    fn use_candidate(&self, candidate: &Candidate<C>) {
+        self.series_paint_components.reset_all_parts_to_zero();
+        self.mixed_paints.components().reset_all_parts_to_zero();
+        self.series_paint_components
+            .set_parts_for(&candidate.sp_components);
+        self.mixed_paints
+            .components()
+            .set_parts_for(&candidate.mp_components);
+    }
+
+    fn accept_candidate(&self, candidate: &Candidate<C>) {
+        if !self.has_notes() {
+            self.inform_user("The mixture needs notes before it can be accepted.", None);
+            return;
+        }
+        self.use_candidate(candidate);
+        self.accept_new_mixture();
+    }
+
     fn simplify_parts(&self) {
         let mut gcd = self.series_paint_components.get_gcd();
         gcd = gcd.gcd(&self.mixed_paints.components().get_gcd());
@@ -255,6 +971,343 @@ where
         self.mixed_paints.components().divide_all_parts_by(gcd);
     }
 
+    fn export_csv(&self) -> Result<(), PaintError<C>> {
+        let o_last_file = recall("paint_mixer::last_csv_export_file");
+        let last_file = if let Some(ref text) = o_last_file {
+            Some(text.as_str())
+        } else {
+            None
+        };
+        if let Some(path) = self.ask_file_path(Some("Export to:"), last_file, false) {
+            let text = mixtures_csv_with(
+                &self.mixed_paints.get_paints(),
+                &self.mixed_paints.series_paints_used(),
+                self.display_name_policy(),
+            );
+            let mut file = File::create(&path)?;
+            file.write_all(text.as_bytes())?;
+            remember("paint_mixer::last_csv_export_file", &path.to_string_lossy());
+        };
+        Ok(())
+    }
+
+    // The report (see pango_markup_chunks()) laid out for a PDF file
+    pub fn report_lines(&self) -> Vec<ReportLine> {
+        let session = self.mix_session();
+        let mut lines = mix_session_report_lines(&session, self.display_name_policy());
+        if let Some(profile) = VisionProfile::recall() {
+            lines.extend(text_report_lines(&format!(
+                "Matched by: {}",
+                profile.summary()
+            )));
+        }
+        let annotations = self.colour_match_area.annotations();
+        if annotations.len() > 0 {
+            lines.push(ReportLine::Heading("Match Notes:".to_string()));
+            for annotation in annotations.iter() {
+                match annotation.colour {
+                    Some(ref colour) => lines.push(ReportLine::Swatches(
+                        vec![colour.rgb()],
+                        annotation.report_text(),
+                    )),
+                    None => lines.extend(text_report_lines(&annotation.report_text())),
+                }
+            }
+        }
+        for chunk in report_section_chunks(&session, &self.report_sections.borrow()).iter() {
+            lines.extend(text_report_lines(&markup_to_text(chunk)));
+        }
+        lines
+    }
+
+    fn export_pdf(&self) -> Result<(), PaintError<C>> {
+        let o_last_file = recall("paint_mixer::last_pdf_export_file");
+        let last_file = if let Some(ref text) = o_last_file {
+            Some(text.as_str())
+        } else {
+            None
+        };
+        if let Some(path) = self.ask_file_path(Some("Export to:"), last_file, false) {
+            write_pdf_report(&path, &self.report_lines())?;
+            remember("paint_mixer::last_pdf_export_file", &path.to_string_lossy());
+        };
+        Ok(())
+    }
+
+    pub fn save_session(&self, path: &Path) -> Result<(), PaintError<C>> {
+        let session = MixingSession::from_mixed_paints(
+            &self.mixed_paints.get_paints(),
+            &self.notes.get_text(),
+        );
+        write_session(path, &session)?;
+        Ok(())
+    }
+
+    // The restored mixtures are added to (and numbered after) any already
+    // present and the number restored is returned.
+    pub fn load_session(&self, path: &Path) -> Result<usize, PaintError<C>> {
+        let session = read_session(path)?;
+        let available = self.series_paint_manager.get_series_paints();
+        let restored = session.restore(
+            &available,
+            |notes, sp_components, mp_components, target, o_provenance| {
+                self.mixed_paints.add_paint_with_provenance(
+                    notes,
+                    sp_components,
+                    mp_components,
+                    target,
+                    o_provenance,
+                )
+            },
+        )?;
+        self.adopt_restored_paints(&restored);
+        if self.notes.get_text().len() == 0 {
+            self.notes.set_text(&session.notes);
+        }
+        Ok(restored.len())
+    }
+
+    // Mixtures from another session (e.g. made on another machine) are
+    // added to those present except for any that duplicate them
+    pub fn merge_session(&self, path: &Path) -> Result<SessionMerge<C>, PaintError<C>> {
+        let session = read_session(path)?;
+        let available = self.series_paint_manager.get_series_paints();
+        let merge = session.merge_into(
+            &available,
+            &self.mixed_paints.get_paints(),
+            |notes, sp_components, mp_components, target, o_provenance| {
+                self.mixed_paints.add_paint_with_provenance(
+                    notes,
+                    sp_components,
+                    mp_components,
+                    target,
+                    o_provenance,
+                )
+            },
+        )?;
+        self.adopt_restored_paints(&merge.added);
+        let notes = self.notes.get_text();
+        if session.notes.len() > 0 && !notes.contains(&session.notes) {
+            if notes.len() == 0 {
+                self.notes.set_text(&session.notes);
+            } else {
+                self.notes
+                    .set_text(&format!("{}\n{}", notes, session.notes));
+            }
+        }
+        Ok(merge)
+    }
+
+    fn adopt_restored_paints(&self, restored: &[MixedPaint<C>]) {
+        for mixed_paint in restored.iter() {
+            for component in mixed_paint.components().iter() {
+                if let Paint::Series(ref series_paint) = component.paint {
+                    if !self.series_paint_components.has_paint(series_paint) {
+                        self.add_series_paint(series_paint);
+                    }
+                }
+            }
+            for wheel in self.hue_attr_wheels.iter() {
+                wheel.add_mixed_paint(mixed_paint);
+            }
+        }
+        self.series_paint_manager.refresh_usage();
+    }
+
+    fn save_session_as(&self) -> Result<(), PaintError<C>> {
+        let o_last_file = recall("paint_mixer::last_session_file");
+        if let Some(path) =
+            self.ask_file_path(Some("Save session as:"), o_last_file.as_deref(), false)
+        {
+            self.save_session(&path)?;
+            remember("paint_mixer::last_session_file", &path.to_string_lossy());
+        };
+        Ok(())
+    }
+
+    fn load_session_from(&self) -> Result<(), PaintError<C>> {
+        let o_last_file = recall("paint_mixer::last_session_file");
+        if let Some(path) =
+            self.ask_file_path(Some("Load session from:"), o_last_file.as_deref(), true)
+        {
+            self.load_session(&path)?;
+            remember("paint_mixer::last_session_file", &path.to_string_lossy());
+        };
+        Ok(())
+    }
+
+    fn merge_session_from(&self) -> Result<(), PaintError<C>> {
+        let o_last_file = recall("paint_mixer::last_session_file");
+        if let Some(path) =
+            self.ask_file_path(Some("Merge session from:"), o_last_file.as_deref(), true)
+        {
+            let merge = self.merge_session(&path)?;
+            let mut msg = format!("{} mixture(s) merged", merge.added.len());
+            if merge.skipped.len() > 0 {
+                msg += &format!(
+                    "\n{} duplicate(s) skipped:\n{}",
+                    merge.skipped.len(),
+                    merge.skipped.join("\n")
+                );
+            }
+            self.inform_user(&msg, None);
+        };
+        Ok(())
+    }
+
+    fn update_vision_check_tooltip(&self) {
+        let text = match VisionProfile::recall() {
+            Some(profile) => format!(
+                "Arrange grey and hue patches to record whose eyes judge matches.\nCurrent: {}",
+                profile.summary()
+            ),
+            None => "Arrange grey and hue patches to record whose eyes judge matches.\n(Reports are annotated with the result.)".to_string(),
+        };
+        self.vision_check_btn.set_tooltip_text(Some(&text));
+    }
+
+    // The buttons that lose their labels in compact mode
+    fn labelled_buttons(&self) -> Vec<(&gtk::Button, &'static str, &'static str)> {
+        vec![
+            (&self.vision_check_btn, "Vision Check", "preferences-color"),
+            (&self.diagnostics_btn, "Diagnostics", "system-run"),
+            (&self.favourites_btn, "Favourites", "starred"),
+            (&self.target_library_btn, "Targets", "bookmark-new"),
+            (&self.export_csv_btn, "Export CSV", "document-save-as"),
+            (&self.export_pdf_btn, "Export PDF", "x-office-document"),
+            (&self.save_session_btn, "Save Session", "document-save"),
+            (&self.load_session_btn, "Load Session", "document-open"),
+            (&self.merge_session_btn, "Merge Session", "list-add"),
+            (
+                &self.display_dialogs_btn,
+                "Info Dialogs",
+                "dialog-information",
+            ),
+            (&self.new_mixture_btn, "New", "document-new"),
+            (&self.practice_btn, "Practice", "media-playlist-shuffle"),
+            (&self.notes_template_btn, "Templates", "insert-text"),
+            (&self.accept_mixture_btn, "Accept", "object-select"),
+            (&self.cancel_btn, "Cancel", "process-stop"),
+            (&self.reset_parts_btn, "Reset", "edit-undo"),
+            (
+                &self.remove_unused_btn,
+                "Remove Unused Paints",
+                "edit-clear",
+            ),
+            (
+                &self.simplify_parts_btn,
+                "Simplify Parts",
+                "view-sort-ascending",
+            ),
+            (&self.print_recipe_btn, "Print Recipe", "document-print"),
+        ]
+    }
+
+    // How often (and how much) each series paint has been used in the mixtures
+    pub fn series_paint_usage(&self) -> Vec<(SeriesPaint<C>, PaintUsage)> {
+        self.mixed_paints.series_paint_usage()
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.mixed_paints.is_read_only()
+    }
+
+    // NB: a mixture that's in progress can still be cancelled
+    pub fn set_read_only(&self, read_only: bool) {
+        self.mixed_paints.set_read_only(read_only);
+        self.set_button_sensitivities();
+    }
+
+    pub fn is_compact_mode(&self) -> bool {
+        self.compact_mode_btn.get_active()
+    }
+
+    pub fn set_compact_mode(&self, compact: bool) {
+        if self.compact_mode_btn.get_active() == compact {
+            self.apply_compact_mode();
+        } else {
+            // the toggled handler will apply it
+            self.compact_mode_btn.set_active(compact);
+        }
+    }
+
+    // Trade labels, attribute displays and wheel tabs for space on
+    // small screens (buttons keep their tooltips)
+    fn apply_compact_mode(&self) {
+        let compact = self.is_compact_mode();
+        self.cads_expander.set_expanded(!compact);
+        self.notebook.set_show_tabs(!compact);
+        for (button, label, icon_name) in self.labelled_buttons() {
+            if compact {
+                let image = gtk::Image::from_icon_name(Some(icon_name), gtk::IconSize::Button);
+                button.set_image(Some(&image));
+                button.set_always_show_image(true);
+                button.set_label("");
+            } else {
+                button.set_image(None::<&gtk::Image>);
+                button.set_label(label);
+            }
+        }
+        remember(
+            &MC::recollection_name_for("compact_mode"),
+            if compact { "true" } else { "false" },
+        );
+    }
+
+    pub fn mix_session(&self) -> MixSession<C> {
+        MixSession {
+            date: Local::now(),
+            notes: self.notes.get_text().to_string(),
+            target_name: self.target_name.borrow().clone(),
+            series_paints: self.mixed_paints.series_paints_used(),
+            mixed_paints: self.mixed_paints.get_paints(),
+        }
+    }
+
+    // Register a generator of extra sections for the end of the report
+    pub fn add_report_section<F: Fn(&MixSession<C>) -> Vec<String> + 'static>(&self, generator: F) {
+        self.report_sections.borrow_mut().push(Box::new(generator))
+    }
+
+    // The current mixture's quantities (in the units being used)
+    fn recipe_markup_chunks(&self) -> Vec<String> {
+        let policy = self.display_name_policy();
+        let mut quantities: Vec<(String, f64)> = self
+            .series_paint_components
+            .get_paint_quantities()
+            .iter()
+            .map(|(paint, quantity)| (paint.display_name(policy), *quantity))
+            .collect();
+        quantities.extend(
+            self.mixed_paints
+                .components()
+                .get_paint_quantities()
+                .iter()
+                .map(|(paint, quantity)| (paint.display_name(policy), *quantity)),
+        );
+        let unit = self.series_paint_components.quantity_unit();
+        let mut text = format!("<b>{}</b> ", escape_str_attribute("Recipe:"));
+        text += &format!(
+            "{}
+",
+            Local::now().format("%X: %A %x")
+        );
+        let notes = self.mixed_paint_notes.get_text();
+        if notes.len() > 0 {
+            text += &format!(
+                "{}
+",
+                escape_str_attribute(&notes)
+            );
+        }
+        text += &format!(
+            "
+<tt>{}</tt>",
+            escape_str_attribute(&recipe_text(&quantities, unit))
+        );
+        vec![text]
+    }
+
     fn pango_markup_chunks(&self) -> Vec<String> {
         let series_paints_used = self.mixed_paints.series_paints_used();
 
@@ -270,15 +1323,26 @@ where
                 text += &format!("\n{}\n", notes);
             }
         };
+        if let Some(profile) = VisionProfile::recall() {
+            text += &format!(
+                "\n<b>{}</b> {}\n",
+                escape_str_attribute("Matched by:"),
+                escape_str_attribute(&profile.summary())
+            );
+        }
         let mut chunks = vec![text];
 
+        let policy = self.display_name_policy();
         let mut text = format!("<b>{}</b>\n\n", escape_str_attribute("Paint Colours:"));
         for series_paint in series_paints_used.iter() {
             text += &format!(
                 "<span background=\"{}\">\t</span> ",
                 series_paint.rgb().pango_string()
             );
-            text += &format!("{}", escape_str_attribute(&series_paint.name()));
+            text += &format!(
+                "{}",
+                escape_str_attribute(&series_paint.display_name(policy))
+            );
             if series_paint.notes().len() > 0 {
                 text += &format!(" {}\n", escape_str_attribute(&series_paint.notes()));
             } else {
@@ -321,6 +1385,9 @@ where
                     matched_colour.max_chroma_rgb().pango_string()
                 );
             };
+            if let Some(provenance) = mixed_paint.target_provenance() {
+                text += &format!("{}\n", escape_str_attribute(&provenance.description()));
+            };
             for component in mixed_paint.components().iter() {
                 text += &format!("{:7}: ", component.parts);
                 text += &format!(
@@ -333,10 +1400,71 @@ where
             text = "".to_string();
         }
 
+        let annotations = self.colour_match_area.annotations();
+        if annotations.len() > 0 {
+            let mut text = format!("<b>{}</b>\n\n", escape_str_attribute("Match Notes:"));
+            for annotation in annotations.iter() {
+                if let Some(ref colour) = annotation.colour {
+                    text += &format!(
+                        "<span background=\"{}\">\t</span> ",
+                        colour.rgb().pango_string()
+                    );
+                }
+                text += &format!("{}\n", escape_str_attribute(&annotation.report_text()));
+            }
+            chunks.push(text);
+        }
+
+        chunks.extend(report_section_chunks(
+            &self.mix_session(),
+            &self.report_sections.borrow(),
+        ));
+
         chunks
     }
 }
 
+impl<A, C, MC> DisplayDialogHost for PaintMixerCore<A, C, MC>
+where
+    A: ColourAttributesInterface + 'static,
+    C: CharacteristicsInterface + 'static,
+    MC: MixerConfig + 'static,
+{
+    fn display_dialogs(&self) -> Vec<gtk::Dialog> {
+        let mut dialogs = self.series_paint_components.display_dialogs();
+        dialogs.extend(self.mixed_paints.display_dialogs());
+        for wheel in self.hue_attr_wheels.iter() {
+            dialogs.extend(wheel.display_dialogs());
+        }
+        dialogs
+    }
+}
+
+impl<A, C, MC> TargetColourExchange for PaintMixerCore<A, C, MC>
+where
+    A: ColourAttributesInterface + 'static,
+    C: CharacteristicsInterface + 'static,
+    MC: MixerConfig + 'static,
+{
+    fn target_colour(&self) -> Option<Colour> {
+        self.colour_match_area.get_target_colour()
+    }
+
+    fn accept_target_colour(&self, colour: &Colour) {
+        if MC::mixing_mode() != MixingMode::MatchTarget {
+            self.inform_user("This mixer does not use target colours.", None);
+            return;
+        }
+        if self.colour_match_area.has_target_colour() {
+            let message = "Abandon the current mixture and start a new one with this target?";
+            if !self.ask_confirm_action(message, None) {
+                return;
+            }
+        }
+        self.start_new_mixture(None, Some(colour));
+    }
+}
+
 pub type PaintMixer<A, C, MC> = Rc<PaintMixerCore<A, C, MC>>;
 
 impl<A, C, MC> PaintMixerInterface<A, C, MC> for PaintMixer<A, C, MC>
@@ -361,29 +1489,81 @@ where
         } else {
             None
         };
+        let o_standards_proximity_list =
+            if let Some(ref paint_standards_manager) = o_paint_standards_manager {
+                Some(PaintStandardProximityList::<A, C>::create(
+                    paint_standards_manager,
+                ))
+            } else {
+                None
+            };
         let paint_mixer = Rc::new(PaintMixerCore::<A, C, MC> {
             vbox: gtk::Box::new(gtk::Orientation::Vertical, 1),
             cads: A::create(),
+            cads_expander: gtk::Expander::new(Some("Colour Attributes")),
+            notebook: gtk::Notebook::new(),
             hue_attr_wheels: view_attr_wheels,
             colour_match_area: ColourMatchArea::create(MC::mixing_mode()),
             series_paint_components: SeriesPaintComponentBox::<A, C>::create_with(4, true),
-            mixed_paints: MixedPaintCollectionWidget::<A, C>::create(MC::mixing_mode()),
+            mixed_paints: MixedPaintCollectionWidget::<A, C>::create(
+                MC::mixing_mode(),
+                MC::mixing_model(),
+            ),
             notes: gtk::Entry::new(),
             next_name_label: gtk::Label::new(Some("#???:")),
             mixed_paint_notes: gtk::Entry::new(),
+            suggested_notes: RefCell::new(String::new()),
+            target_name: RefCell::new(String::new()),
+            target_broadcast: TargetBroadcastCore::new(),
+            practice_target: RefCell::new(None),
+            target_provenance: RefCell::new(None),
+            recipe_being_edited: RefCell::new(None),
+            practice_difficulty_combo: gtk::ComboBoxText::new(),
+            notes_template_menu: gtk::Menu::new(),
+            display_dialogs_menu: gtk::Menu::new(),
+            display_name_policy_combo: gtk::ComboBoxText::new(),
+            target_tolerance_spin: gtk::SpinButton::with_range(0.1, 20.0, 0.1),
+            sample_spread_label: gtk::Label::new(None),
+            sample_spread: Cell::new(None),
+            flag_target_reached_btn: gtk::CheckButton::with_label("Flag When Reached"),
+            chime_target_reached_btn: gtk::CheckButton::with_label("Chime"),
+            target_reached: Rc::new(Cell::new(false)),
+            target_reached_callbacks: RefCell::new(Vec::new()),
+            report_sections: RefCell::new(Vec::new()),
+            favourites_path: series_paint_data_path.with_extension("favourites"),
+            target_library_path: series_paint_data_path.with_extension("targets"),
             // Buttons
             print_report_btn: gtk::Button::new(),
+            vision_check_btn: gtk::Button::with_label("Vision Check"),
+            diagnostics_btn: gtk::Button::with_label("Diagnostics"),
+            favourites_btn: gtk::Button::with_label("Favourites"),
+            target_library_btn: gtk::Button::with_label("Targets"),
+            export_csv_btn: gtk::Button::with_label("Export CSV"),
+            export_pdf_btn: gtk::Button::with_label("Export PDF"),
+            save_session_btn: gtk::Button::with_label("Save Session"),
+            load_session_btn: gtk::Button::with_label("Load Session"),
+            merge_session_btn: gtk::Button::with_label("Merge Session"),
+            display_dialogs_btn: gtk::Button::with_label("Info Dialogs"),
             new_mixture_btn: gtk::Button::with_label("New"),
+            practice_btn: gtk::Button::with_label("Practice"),
+            notes_template_btn: gtk::Button::with_label("Templates"),
             accept_mixture_btn: gtk::Button::with_label("Accept"),
             cancel_btn: gtk::Button::with_label("Cancel"),
             reset_parts_btn: gtk::Button::with_label("Reset"),
             remove_unused_btn: gtk::Button::with_label("Remove Unused Paints"),
             simplify_parts_btn: gtk::Button::with_label("Simplify Parts"),
+            print_recipe_btn: gtk::Button::with_label("Print Recipe"),
+            suggest_mix_btn: gtk::Button::with_label("Suggest Mix"),
+            candidates: CandidateSetView::<C>::create(),
+            suggest_max_parts_spin: gtk::SpinButton::with_range(1.0, 99.0, 1.0),
+            compact_mode_btn: gtk::ToggleButton::with_label("Compact"),
             // Managers
             series_paint_manager: SeriesPaintManager::<A, C>::create(series_paint_data_path),
             o_paint_standards_manager: o_paint_standards_manager,
+            o_standards_proximity_list: o_standards_proximity_list,
             phantom: PhantomData,
         });
+        paint_mixer.connect_target_receivers();
 
         // TODO: Consider redoing this when Toolbar bug fixed.
         //let toolbar = gtk::Toolbar::new();
@@ -395,6 +1575,25 @@ where
         //paint_mixer.vbox.pack_start(&toolbar, false, false, 0);
         let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 0);
         hbox.pack_start(&paint_mixer.print_report_btn.clone(), false, true, 2);
+        hbox.pack_start(&paint_mixer.export_csv_btn.clone(), false, true, 2);
+        hbox.pack_start(&paint_mixer.export_pdf_btn.clone(), false, true, 2);
+        hbox.pack_start(&paint_mixer.save_session_btn.clone(), false, true, 2);
+        hbox.pack_start(&paint_mixer.load_session_btn.clone(), false, true, 2);
+        hbox.pack_start(&paint_mixer.merge_session_btn.clone(), false, true, 2);
+        hbox.pack_start(&paint_mixer.display_dialogs_btn.clone(), false, true, 2);
+        hbox.pack_start(&paint_mixer.vision_check_btn.clone(), false, true, 2);
+        hbox.pack_start(&paint_mixer.diagnostics_btn.clone(), false, true, 2);
+        hbox.pack_start(&paint_mixer.favourites_btn.clone(), false, true, 2);
+        if MC::mixing_mode() == MixingMode::MatchTarget {
+            hbox.pack_start(&paint_mixer.target_library_btn.clone(), false, true, 2);
+        }
+        hbox.pack_start(&paint_mixer.compact_mode_btn.clone(), false, true, 2);
+        hbox.pack_start(
+            &paint_mixer.display_name_policy_combo.clone(),
+            false,
+            true,
+            2,
+        );
         hbox.pack_start(&paint_mixer.series_paint_manager.button(), false, true, 2);
         if let Some(ref paint_standards_manager) = paint_mixer.o_paint_standards_manager {
             hbox.pack_start(&paint_standards_manager.button(), false, true, 2);
@@ -403,28 +1602,45 @@ where
 
         let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 0);
         hbox.pack_start(&gtk::Label::new(Some("Notes:")), false, false, 0);
+        enable_spell_checking(&paint_mixer.notes);
         hbox.pack_start(&paint_mixer.notes.clone(), true, true, 0);
         paint_mixer.vbox.pack_start(&hbox, false, false, 0);
 
         let vbox = gtk::Box::new(gtk::Orientation::Vertical, 1);
         let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 0);
         hbox.pack_start(&paint_mixer.next_name_label.clone(), false, false, 0);
+        enable_spell_checking(&paint_mixer.mixed_paint_notes);
         hbox.pack_start(&paint_mixer.mixed_paint_notes.clone(), true, true, 0);
+        hbox.pack_start(&paint_mixer.notes_template_btn.clone(), false, false, 0);
         vbox.pack_start(&hbox, false, false, 0);
-        vbox.pack_start(&paint_mixer.cads.pwo(), false, false, 0);
+        paint_mixer.cads_expander.add(&paint_mixer.cads.pwo());
+        paint_mixer.cads_expander.set_expanded(true);
+        vbox.pack_start(&paint_mixer.cads_expander, false, false, 0);
         vbox.pack_start(&paint_mixer.colour_match_area.pwo(), true, true, 0);
+        if let Some(ref proximity_list) = paint_mixer.o_standards_proximity_list {
+            let frame = gtk::Frame::new(Some("Nearest Standards"));
+            frame.add(&proximity_list.pwo());
+            vbox.pack_start(&frame, false, true, 0);
+        };
 
-        let notebook = gtk::Notebook::new();
-        for wheel in paint_mixer.hue_attr_wheels.iter() {
-            let label_text = format!("Hue/{} Wheel", wheel.attr().to_string());
-            let label = gtk::Label::new(Some(label_text.as_str()));
-            notebook.append_page(&wheel.pwo(), Some(&label));
-        }
-        notebook.set_scrollable(true);
-        notebook.popup_enable();
+        let visible_wheels = recall_wheel_pages(
+            &MC::recollection_name_for("wheel_pages"),
+            &A::scalar_attributes(),
+        );
+        arrange_wheel_pages(
+            &paint_mixer.notebook,
+            &paint_mixer.wheel_pages(),
+            &visible_wheels,
+        );
+        paint_mixer.notebook.set_scrollable(true);
+        paint_mixer.notebook.popup_enable();
+        let paint_mixer_c = paint_mixer.clone();
+        paint_mixer
+            .notebook
+            .connect_page_reordered(move |_, _, _| paint_mixer_c.remember_wheel_pages());
 
         let hpaned = gtk::Paned::new(gtk::Orientation::Horizontal);
-        hpaned.pack1(&notebook, true, true);
+        hpaned.pack1(&paint_mixer.notebook, true, true);
         hpaned.pack2(&vbox, true, true);
         hpaned.set_position_from_recollections("paint_mixer_horizontal", 200);
         paint_mixer.vbox.pack_start(&hpaned, true, true, 0);
@@ -433,15 +1649,67 @@ where
         paint_mixer.vbox.pack_start(&button_box, false, false, 0);
         if MC::mixing_mode() == MixingMode::MatchTarget {
             button_box.pack_start(&paint_mixer.new_mixture_btn, true, true, 0);
+            button_box.pack_start(&paint_mixer.practice_btn, true, true, 0);
+            button_box.pack_start(&paint_mixer.practice_difficulty_combo, false, false, 0);
             button_box.pack_start(&paint_mixer.accept_mixture_btn, true, true, 0);
             button_box.pack_start(&paint_mixer.cancel_btn, true, true, 0);
         } else {
             button_box.pack_start(&paint_mixer.accept_mixture_btn, true, true, 0);
         };
         button_box.pack_start(&paint_mixer.simplify_parts_btn, true, true, 0);
+        button_box.pack_start(&paint_mixer.print_recipe_btn, true, true, 0);
+        button_box.pack_start(&paint_mixer.suggest_mix_btn, true, true, 0);
+        button_box.pack_start(&paint_mixer.suggest_max_parts_spin, false, false, 0);
+        let candidates_expander = gtk::Expander::new(Some("Candidate Mixes"));
+        candidates_expander.add(&paint_mixer.candidates.pwo());
+        paint_mixer
+            .vbox
+            .pack_start(&candidates_expander, false, false, 0);
         button_box.pack_start(&paint_mixer.reset_parts_btn, true, true, 0);
         button_box.pack_start(&paint_mixer.remove_unused_btn, true, true, 0);
 
+        let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 2);
+        hbox.pack_start(
+            &gtk::Label::new(Some("Target Tolerance (ΔE):")),
+            false,
+            false,
+            0,
+        );
+        hbox.pack_start(&paint_mixer.target_tolerance_spin, false, false, 0);
+        hbox.pack_start(&paint_mixer.flag_target_reached_btn, false, false, 0);
+        hbox.pack_start(&paint_mixer.chime_target_reached_btn, false, false, 0);
+        if MC::mixing_mode() == MixingMode::MatchSamples {
+            hbox.pack_start(&paint_mixer.sample_spread_label, false, false, 4);
+            let paint_mixer_c = paint_mixer.clone();
+            paint_mixer
+                .colour_match_area
+                .connect_samples_changed(move || paint_mixer_c.update_sample_spread());
+            paint_mixer.update_sample_spread();
+        };
+        paint_mixer.vbox.pack_start(&hbox, false, false, 0);
+
+        paint_mixer
+            .series_paint_components
+            .set_sort_order_recollection_name(&MC::recollection_name_for("components_sort_order"));
+        // Both sets of components must be measured in the same units
+        let mixed_paint_components = paint_mixer.mixed_paints.components();
+        paint_mixer
+            .series_paint_components
+            .connect_quantity_unit_changed(move |unit| {
+                mixed_paint_components.set_quantity_unit(unit)
+            });
+        let series_paint_components = paint_mixer.series_paint_components.clone();
+        paint_mixer
+            .mixed_paints
+            .components()
+            .connect_quantity_unit_changed(move |unit| {
+                series_paint_components.set_quantity_unit(unit)
+            });
+        paint_mixer
+            .series_paint_components
+            .set_quantity_unit_recollection_name(&MC::recollection_name_for(
+                "components_quantity_unit",
+            ));
         let frame = gtk::Frame::new(Some("Paints"));
         frame.add(&paint_mixer.series_paint_components.pwo());
         paint_mixer.vbox.pack_start(&frame, true, true, 0);
@@ -456,6 +1724,21 @@ where
         paint_mixer
             .print_report_btn
             .set_tooltip_text(Some("Print a report of the mixtures and paints used"));
+        paint_mixer.update_vision_check_tooltip();
+        let paint_mixer_c = paint_mixer.clone();
+        paint_mixer.vision_check_btn.connect_clicked(move |_| {
+            if let Some(profile) = run_vision_check(&paint_mixer_c) {
+                paint_mixer_c.inform_user("Vision check recorded.", Some(&profile.summary()));
+                paint_mixer_c.update_vision_check_tooltip();
+            }
+        });
+        paint_mixer.diagnostics_btn.set_tooltip_text(Some(
+            "Check the colour conversion and mixing arithmetic against known colours",
+        ));
+        let paint_mixer_c = paint_mixer.clone();
+        paint_mixer.diagnostics_btn.connect_clicked(move |_| {
+            show_diagnostics(&paint_mixer_c);
+        });
         let paint_mixer_c = paint_mixer.clone();
         paint_mixer.print_report_btn.connect_clicked(move |_| {
             if let Err(ref err) =
@@ -465,6 +1748,160 @@ where
             };
         });
 
+        paint_mixer
+            .notes_template_btn
+            .set_tooltip_text(Some("Fill in the mixed paint's notes from a template."));
+        let paint_mixer_c = paint_mixer.clone();
+        paint_mixer
+            .notes_template_btn
+            .connect_clicked(move |button| {
+                let menu = &paint_mixer_c.notes_template_menu;
+                for child in menu.get_children().iter() {
+                    menu.remove(child);
+                }
+                for template in recall_notes_templates() {
+                    let fields = paint_mixer_c.notes_template_fields();
+                    let item =
+                        gtk::MenuItem::with_label(&expand_notes_template(&template, &fields));
+                    item.set_tooltip_text(Some(&template));
+                    let paint_mixer_c_c = paint_mixer_c.clone();
+                    item.connect_activate(move |_| paint_mixer_c_c.apply_notes_template(&template));
+                    menu.append(&item);
+                }
+                menu.append(&gtk::SeparatorMenuItem::new());
+                let item = gtk::MenuItem::with_label("Edit Templates...");
+                let paint_mixer_c_c = paint_mixer_c.clone();
+                item.connect_activate(move |_| edit_notes_templates(&paint_mixer_c_c));
+                menu.append(&item);
+                menu.show_all();
+                menu.popup_at_widget(
+                    button,
+                    gdk::Gravity::SouthWest,
+                    gdk::Gravity::NorthWest,
+                    None,
+                );
+            });
+
+        paint_mixer.display_dialogs_btn.set_tooltip_text(Some(
+            "Arrange or close the mixer's paint information dialogs.",
+        ));
+        let items: [(&str, &str, fn(&PaintMixerCore<A, C, MC>)); 3] = [
+            (
+                "Cascade Info Dialogs",
+                "Arrange the open paint information dialogs in a cascade",
+                |mixer| mixer.cascade_display_dialogs(),
+            ),
+            (
+                "Tile Info Dialogs",
+                "Arrange the open paint information dialogs side by side",
+                |mixer| mixer.tile_display_dialogs(),
+            ),
+            (
+                "Close All Info Dialogs",
+                "Close all of the open paint information dialogs",
+                |mixer| mixer.close_display_dialogs(),
+            ),
+        ];
+        for (label, tooltip_text, action) in items.iter() {
+            let item = gtk::MenuItem::with_label(label);
+            item.set_tooltip_text(Some(tooltip_text));
+            let paint_mixer_c = paint_mixer.clone();
+            let action = *action;
+            item.connect_activate(move |_| action(&paint_mixer_c));
+            paint_mixer.display_dialogs_menu.append(&item);
+        }
+        paint_mixer.display_dialogs_menu.show_all();
+        let paint_mixer_c = paint_mixer.clone();
+        paint_mixer
+            .display_dialogs_btn
+            .connect_clicked(move |button| {
+                let has_dialogs = paint_mixer_c.display_dialogs().len() > 0;
+                for item in paint_mixer_c.display_dialogs_menu.get_children().iter() {
+                    item.set_sensitive(has_dialogs);
+                }
+                paint_mixer_c.display_dialogs_menu.popup_at_widget(
+                    button,
+                    gdk::Gravity::SouthWest,
+                    gdk::Gravity::NorthWest,
+                    None,
+                );
+            });
+
+        for policy in DisplayNamePolicy::values() {
+            paint_mixer
+                .display_name_policy_combo
+                .append(Some(policy.name()), policy.label());
+        }
+        paint_mixer.display_name_policy_combo.set_tooltip_text(Some(
+            "How paints are identified in the wheels' tooltips and in reports",
+        ));
+        let paint_mixer_c = paint_mixer.clone();
+        paint_mixer
+            .display_name_policy_combo
+            .connect_changed(move |_| {
+                let policy = paint_mixer_c.display_name_policy();
+                for wheel in paint_mixer_c.hue_attr_wheels.iter() {
+                    wheel.set_display_name_policy(policy);
+                }
+                policy.remember(&MC::recollection_name_for("display_name_policy"));
+            });
+        paint_mixer.set_display_name_policy(DisplayNamePolicy::recall(&MC::recollection_name_for(
+            "display_name_policy",
+        )));
+
+        paint_mixer.export_csv_btn.set_tooltip_text(Some(
+            "Export the mixtures and the parts of each paint used as CSV for spreadsheets",
+        ));
+        let paint_mixer_c = paint_mixer.clone();
+        paint_mixer.export_csv_btn.connect_clicked(move |_| {
+            if let Err(ref err) = paint_mixer_c.export_csv() {
+                paint_mixer_c.report_error("Failed to export CSV", err);
+            };
+        });
+
+        paint_mixer.export_pdf_btn.set_tooltip_text(Some(
+            "Export the report of the mixtures and paints used (with colour swatches) as a PDF file",
+        ));
+        paint_mixer
+            .export_pdf_btn
+            .set_sensitive(pdf_export_available());
+        let paint_mixer_c = paint_mixer.clone();
+        paint_mixer.export_pdf_btn.connect_clicked(move |_| {
+            if let Err(ref err) = paint_mixer_c.export_pdf() {
+                paint_mixer_c.report_error("Failed to export PDF", err);
+            };
+        });
+
+        paint_mixer.save_session_btn.set_tooltip_text(Some(
+            "Save the mixtures (with their components, targets and notes) so that mixing can be resumed later",
+        ));
+        let paint_mixer_c = paint_mixer.clone();
+        paint_mixer.save_session_btn.connect_clicked(move |_| {
+            if let Err(ref err) = paint_mixer_c.save_session_as() {
+                paint_mixer_c.report_error("Failed to save session", err);
+            };
+        });
+
+        paint_mixer.load_session_btn.set_tooltip_text(Some(
+            "Restore the mixtures from a saved session (their series must be loaded)",
+        ));
+        let paint_mixer_c = paint_mixer.clone();
+        paint_mixer.load_session_btn.connect_clicked(move |_| {
+            if let Err(ref err) = paint_mixer_c.load_session_from() {
+                paint_mixer_c.report_error("Failed to load session", err);
+            };
+        });
+
+        paint_mixer.merge_session_btn.set_tooltip_text(Some(
+            "Add the mixtures from another saved session to these (skipping any duplicates)",
+        ));
+        let paint_mixer_c = paint_mixer.clone();
+        paint_mixer.merge_session_btn.connect_clicked(move |_| {
+            if let Err(ref err) = paint_mixer_c.merge_session_from() {
+                paint_mixer_c.report_error("Failed to merge session", err);
+            };
+        });
+
         if MC::mixing_mode() == MixingMode::MatchTarget {
             paint_mixer
                 .new_mixture_btn
@@ -472,11 +1909,43 @@ where
             let paint_mixer_c = paint_mixer.clone();
             paint_mixer.new_mixture_btn.connect_clicked(move |_| {
                 let dialog = NewTargetColourDialog::<A>::create(&paint_mixer_c);
-                if let Some((ref notes, ref colour)) = dialog.get_new_target() {
-                    paint_mixer_c.start_new_mixture(Some(&notes), Some(&colour))
+                if let Some((ref notes, ref colour, o_provenance)) =
+                    dialog.get_new_target_with_provenance()
+                {
+                    paint_mixer_c.start_new_mixture(Some(&notes), Some(&colour));
+                    *paint_mixer_c.target_provenance.borrow_mut() = o_provenance;
                 }
             });
 
+            paint_mixer.practice_btn.set_tooltip_text(Some(
+                "Start mixing a random target that can be mixed from the loaded paint series.\nThe accepted mixture will be scored against the target.",
+            ));
+            let paint_mixer_c = paint_mixer.clone();
+            paint_mixer
+                .practice_btn
+                .connect_clicked(move |_| paint_mixer_c.start_practice_mixture());
+            for difficulty in PracticeDifficulty::values() {
+                paint_mixer
+                    .practice_difficulty_combo
+                    .append(Some(difficulty.name()), difficulty.label());
+            }
+            paint_mixer
+                .practice_difficulty_combo
+                .set_tooltip_text(Some("How hard practice targets are to mix"));
+            let difficulty =
+                PracticeDifficulty::recall(&MC::recollection_name_for("practice_difficulty"));
+            paint_mixer
+                .practice_difficulty_combo
+                .set_active_id(Some(difficulty.name()));
+            let paint_mixer_c = paint_mixer.clone();
+            paint_mixer
+                .practice_difficulty_combo
+                .connect_changed(move |_| {
+                    paint_mixer_c
+                        .practice_difficulty()
+                        .remember(&MC::recollection_name_for("practice_difficulty"));
+                });
+
             paint_mixer
                 .cancel_btn
                 .set_tooltip_text(Some("Cancel the current mixture."));
@@ -486,6 +1955,64 @@ where
                 .connect_clicked(move |_| paint_mixer_c.cancel_current_mixture());
         };
 
+        paint_mixer.target_tolerance_spin.set_digits(1);
+        paint_mixer.target_tolerance_spin.set_tooltip_text(Some(
+            "The target is reached when the mixed colour is within this colour difference of it",
+        ));
+        let tolerance = recall(&MC::recollection_name_for("target_tolerance"))
+            .and_then(|text| text.parse::<f64>().ok())
+            .unwrap_or(DEFAULT_TARGET_TOLERANCE);
+        paint_mixer.set_target_tolerance(tolerance);
+        let paint_mixer_c = paint_mixer.clone();
+        paint_mixer
+            .target_tolerance_spin
+            .connect_value_changed(move |spin| {
+                remember(
+                    &MC::recollection_name_for("target_tolerance"),
+                    &spin.get_value().to_string(),
+                );
+                if MC::mixing_mode() == MixingMode::MatchSamples {
+                    paint_mixer_c.update_sample_spread();
+                } else {
+                    paint_mixer_c.update_mixed_colour();
+                }
+            });
+        paint_mixer.flag_target_reached_btn.set_tooltip_text(Some(
+            "Highlight the Accept button when the mixed colour reaches the target",
+        ));
+        paint_mixer.chime_target_reached_btn.set_tooltip_text(Some(
+            "Sound the bell when the mixed colour reaches the target",
+        ));
+        for (button, name) in [
+            (&paint_mixer.flag_target_reached_btn, "flag_target_reached"),
+            (
+                &paint_mixer.chime_target_reached_btn,
+                "chime_target_reached",
+            ),
+        ]
+        .iter()
+        {
+            let active = recall(&MC::recollection_name_for(name))
+                .and_then(|text| text.parse::<bool>().ok())
+                .unwrap_or(false);
+            button.set_active(active);
+            let paint_mixer_c = paint_mixer.clone();
+            let name = name.to_string();
+            button.connect_toggled(move |button| {
+                remember(
+                    &MC::recollection_name_for(&name),
+                    &button.get_active().to_string(),
+                );
+                paint_mixer_c
+                    .chime_target_reached_btn
+                    .set_sensitive(paint_mixer_c.flag_target_reached_btn.get_active());
+                paint_mixer_c.update_accept_button_style();
+            });
+        }
+        paint_mixer
+            .chime_target_reached_btn
+            .set_sensitive(paint_mixer.flag_target_reached_btn.get_active());
+
         paint_mixer.accept_mixture_btn.set_tooltip_text(Some(
             "Accept the current mixture and add it to the list of mixed colours.",
         ));
@@ -494,6 +2021,42 @@ where
             .accept_mixture_btn
             .connect_clicked(move |_| paint_mixer_c.accept_new_mixture());
 
+        paint_mixer.suggest_mix_btn.set_tooltip_text(Some(
+            "Set the parts of the series paints in the mixing area to those whose mixture best matches the target.",
+        ));
+        paint_mixer
+            .suggest_max_parts_spin
+            .set_tooltip_text(Some("The maximum total parts for a suggested mix."));
+        let max_parts_key = MC::recollection_name_for("suggest_max_parts");
+        let max_parts = recall(&max_parts_key)
+            .and_then(|text| u32::from_str(&text).ok())
+            .unwrap_or(DEFAULT_MAX_TOTAL_PARTS);
+        paint_mixer
+            .suggest_max_parts_spin
+            .set_value(max_parts as f64);
+        paint_mixer
+            .suggest_max_parts_spin
+            .connect_value_changed(move |spin| {
+                remember(&max_parts_key, &spin.get_value_as_int().to_string());
+            });
+        let paint_mixer_c = paint_mixer.clone();
+        paint_mixer
+            .suggest_mix_btn
+            .connect_clicked(move |_| paint_mixer_c.suggest_mix());
+
+        let paint_mixer_c = paint_mixer.clone();
+        paint_mixer
+            .candidates
+            .connect_snapshot_requested(move || paint_mixer_c.snapshot_candidate());
+        let paint_mixer_c = paint_mixer.clone();
+        paint_mixer
+            .candidates
+            .connect_use_candidate(move |candidate| paint_mixer_c.use_candidate(candidate));
+        let paint_mixer_c = paint_mixer.clone();
+        paint_mixer
+            .candidates
+            .connect_accept_candidate(move |candidate| paint_mixer_c.accept_candidate(candidate));
+
         paint_mixer.simplify_parts_btn.set_tooltip_text(Some(
             "Divide all paints' parts by their greatest common denominator.",
         ));
@@ -502,6 +2065,18 @@ where
             .simplify_parts_btn
             .connect_clicked(move |_| paint_mixer_c.simplify_parts());
 
+        paint_mixer.print_recipe_btn.set_tooltip_text(Some(
+            "Print the current mixture's recipe (in the units being used) for measuring out.",
+        ));
+        let paint_mixer_c = paint_mixer.clone();
+        paint_mixer.print_recipe_btn.connect_clicked(move |_| {
+            if let Err(ref err) =
+                paint_mixer_c.print_markup_chunks(paint_mixer_c.recipe_markup_chunks())
+            {
+                paint_mixer_c.report_error("Failure", err);
+            };
+        });
+
         paint_mixer
             .reset_parts_btn
             .set_tooltip_text(Some("Reset parts of all paints in mixing part to zero."));
@@ -526,6 +2101,17 @@ where
             paint_mixer_c.remove_unused_paints_from_mixing_area();
         });
 
+        paint_mixer.compact_mode_btn.set_tooltip_text(Some(
+            "Use a compact layout (icon buttons, collapsed colour attributes and a single\nwheel without tabs) suited to small screens.",
+        ));
+        let paint_mixer_c = paint_mixer.clone();
+        paint_mixer
+            .compact_mode_btn
+            .connect_toggled(move |_| paint_mixer_c.apply_compact_mode());
+        let compact =
+            recall(&MC::recollection_name_for("compact_mode")) == Some("true".to_string());
+        paint_mixer.set_compact_mode(compact);
+
         let paint_mixer_c = paint_mixer.clone();
         paint_mixer
             .series_paint_components
@@ -559,13 +2145,62 @@ where
         let paint_mixer_c = paint_mixer.clone();
         paint_mixer
             .series_paint_manager
-            .connect_add_paint(move |paint| paint_mixer_c.add_series_paint(paint));
+            .connect_add_paint(move |paint| {
+                paint_mixer_c.add_series_paint(paint);
+                paint_mixer_c.warn_if_out_of_stock(paint);
+            });
+
+        let paint_mixer_c = paint_mixer.clone();
+        paint_mixer
+            .series_paint_manager
+            .connect_collns_changed(move || paint_mixer_c.refresh_mixed_paints());
+
+        let series_paint_components = paint_mixer.series_paint_components.clone();
+        paint_mixer
+            .series_paint_manager
+            .set_membership_test(move |paint| series_paint_components.has_paint(paint));
+
+        let mixed_paints = paint_mixer.mixed_paints.clone();
+        paint_mixer
+            .series_paint_manager
+            .set_usage_source(move |paint| mixed_paints.usage_of_series_paint(paint));
 
         let paint_mixer_c = paint_mixer.clone();
         paint_mixer
             .mixed_paints
             .connect_remove_paint(move |paint| paint_mixer_c.remove_mixed_paint(paint));
 
+        let paint_mixer_c = paint_mixer.clone();
+        paint_mixer
+            .mixed_paints
+            .connect_edit_recipe(move |paint| paint_mixer_c.edit_mixed_paint_recipe(paint));
+
+        let paint_mixer_c = paint_mixer.clone();
+        paint_mixer
+            .mixed_paints
+            .connect_paint_renamed(move |old, new| paint_mixer_c.mixed_paint_renamed(old, new));
+
+        let paint_mixer_c = paint_mixer.clone();
+        paint_mixer
+            .mixed_paints
+            .connect_add_favourite(move |paint| paint_mixer_c.add_favourite(paint));
+
+        paint_mixer.favourites_btn.set_tooltip_text(Some(
+            "Browse the favourite mixes (by tag or closeness to the target) and remake one.",
+        ));
+        let paint_mixer_c = paint_mixer.clone();
+        paint_mixer
+            .favourites_btn
+            .connect_clicked(move |_| paint_mixer_c.browse_favourites());
+
+        paint_mixer.target_library_btn.set_tooltip_text(Some(
+            "Save the current target in (or choose a saved target from) the target colour library.",
+        ));
+        let paint_mixer_c = paint_mixer.clone();
+        paint_mixer
+            .target_library_btn
+            .connect_clicked(move |_| paint_mixer_c.manage_target_library());
+
         if let Some(ref paint_standards_manager) = paint_mixer.o_paint_standards_manager {
             let paint_mixer_c = paint_mixer.clone();
             paint_standards_manager.connect_set_target_from(move |paint| {
@@ -577,6 +2212,12 @@ where
                 };
                 let colour = paint.colour();
                 paint_mixer_c.start_new_mixture(Some(&notes), Some(&colour));
+                let colln_id = paint.colln_id();
+                *paint_mixer_c.target_provenance.borrow_mut() = Some(TargetProvenance::Standard {
+                    standard: colln_id.colln_name(),
+                    sponsor: colln_id.colln_owner(),
+                    paint: paint.name(),
+                });
             });
         };
 
@@ -1,9 +1,11 @@
 // Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::cmp::Ordering;
 use std::rc::Rc;
 
+use chrono::prelude::*;
+
 use pw_gix::{
     gdk,
     glib::{self, StaticType, ToValue},
@@ -12,17 +14,31 @@ use pw_gix::{
 
 use crate::basic_paint::*;
 use crate::colour::*;
+use crate::colour_mix::*;
 use crate::series_paint::*;
 
+pub mod broadcast;
+pub mod candidates;
 pub mod collection;
 pub mod components;
+pub mod csv_export;
 pub mod display;
+pub mod favourites;
 pub mod hue_wheel;
 pub mod match_area;
+pub mod measures;
 pub mod mixer;
+pub mod naming;
+pub mod notes_template;
+pub mod recipe;
+pub mod report;
+pub mod session;
+pub mod solver;
 pub mod target;
+pub mod target_library;
+pub mod trainer;
 
-use self::target::TargetColour;
+use self::target::{TargetColour, TargetProvenance};
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum MixingMode {
@@ -141,6 +157,43 @@ pub struct PaintComponent<C: CharacteristicsInterface> {
     pub parts: u32,
 }
 
+// The colour of a mixture of the components (with parts already reduced)
+pub fn mixture_colour<C: CharacteristicsInterface>(
+    components: &[PaintComponent<C>],
+    mixing_model: MixingModel,
+) -> Option<Colour> {
+    let mut colour_mixer = ColourMixer::with_model(mixing_model);
+    for component in components.iter() {
+        colour_mixer.add(&component.paint.colour(), component.parts);
+    }
+    colour_mixer.get_colour()
+}
+
+// The characteristics of a mixture are the parts weighted average of
+// those of its components.
+// NB: zero is reserved for unspecified (optional) characteristics and if
+// any component's is unspecified so is the mixture's
+pub fn mixture_characteristics<C: CharacteristicsInterface>(components: &[PaintComponent<C>]) -> C {
+    let total_parts: u32 = components.iter().map(|component| component.parts).sum();
+    let mut floats = vec![0.0_f64; C::tv_row_len()];
+    for component in components.iter() {
+        let weight = component.parts as f64 / total_parts as f64;
+        let c_floats = component.paint.characteristics().to_floats();
+        for (i, val) in floats.iter_mut().enumerate() {
+            *val += c_floats[i] * weight;
+        }
+    }
+    for component in components.iter() {
+        let c_floats = component.paint.characteristics().to_floats();
+        for (i, val) in floats.iter_mut().enumerate() {
+            if c_floats[i] == 0.0 {
+                *val = 0.0;
+            }
+        }
+    }
+    C::from_floats(&floats)
+}
+
 pub const MP_NAME: i32 = SP_NAME;
 pub const MP_NOTES: i32 = SP_NOTES;
 pub const MP_CHROMA: i32 = SP_CHROMA;
@@ -161,9 +214,10 @@ pub const MP_CHARS_0: i32 = 16;
 pub const MP_CHARS_1: i32 = 17;
 pub const MP_CHARS_2: i32 = 18;
 pub const MP_CHARS_3: i32 = 19;
+pub const MP_CHARS_4: i32 = 20;
 
 lazy_static! {
-    pub static ref MIXED_PAINT_ROW_SPEC: [glib::Type; 20] =
+    pub static ref MIXED_PAINT_ROW_SPEC: [glib::Type; 21] =
         [
             glib::Type::String,          // 0 Name
             glib::Type::String,          // 1 Notes
@@ -185,17 +239,43 @@ lazy_static! {
             glib::Type::String,          // 17 Characteristic #2
             glib::Type::String,          // 18 Characteristic #3
             glib::Type::String,          // 19 Characteristic #4
+            glib::Type::String,          // 20 Characteristic #5
         ];
 }
 
+// A mixture's recipe as it was before it was edited
+#[derive(Debug, Clone)]
+pub struct RecipeRevision<C: CharacteristicsInterface> {
+    pub colour: Colour,
+    pub components: Rc<Vec<PaintComponent<C>>>,
+    pub replaced: DateTime<Local>,
+}
+
+impl<C: CharacteristicsInterface> RecipeRevision<C> {
+    pub fn description(&self) -> String {
+        let parts: Vec<String> = self
+            .components
+            .iter()
+            .map(|component| format!("{} x {}", component.parts, component.paint.name()))
+            .collect();
+        format!(
+            "{} (replaced {}): {}",
+            self.colour.into_css_hex(),
+            self.replaced.format("%Y-%m-%d %H:%M"),
+            parts.join(", ")
+        )
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct MixedPaintCore<C: CharacteristicsInterface> {
-    colour: Colour,
+    colour: Cell<Colour>,
     name: String,
     notes: RefCell<String>,
-    characteristics: C,
+    characteristics: Cell<C>,
     target_colour: Option<TargetColour>,
-    components: Rc<Vec<PaintComponent<C>>>,
+    components: RefCell<Rc<Vec<PaintComponent<C>>>>,
+    recipe_history: Vec<RecipeRevision<C>>,
 }
 
 impl<C: CharacteristicsInterface> PartialEq for MixedPaintCore<C> {
@@ -224,7 +304,7 @@ impl<C: CharacteristicsInterface> MixedPaintCore<C> {
     }
 
     pub fn uses_paint(&self, paint: &Paint<C>) -> bool {
-        for component in self.components.iter() {
+        for component in self.components.borrow().iter() {
             if *paint == component.paint {
                 return true;
             } else if let Paint::Mixed(ref mixed_paint) = component.paint {
@@ -246,7 +326,7 @@ impl<C: CharacteristicsInterface> MixedPaintCore<C> {
 
     pub fn series_paints_used(&self) -> Vec<SeriesPaint<C>> {
         let mut spu: Vec<SeriesPaint<C>> = Vec::new();
-        for component in self.components.iter() {
+        for component in self.components.borrow().iter() {
             match component.paint {
                 Paint::Series(ref series_paint) => {
                     if let Err(index) = spu.binary_search(series_paint) {
@@ -268,6 +348,31 @@ impl<C: CharacteristicsInterface> MixedPaintCore<C> {
         spu
     }
 
+    // The (possibly fractional) parts of each series paint in the mixture
+    // with mixed paint components expanded into their own series paints.
+    pub fn series_parts(&self) -> Vec<(SeriesPaint<C>, f64)> {
+        let mut series_parts: Vec<(SeriesPaint<C>, f64)> = Vec::new();
+        let mut add = |series_paint: &SeriesPaint<C>, parts: f64| match series_parts
+            .binary_search_by(|sp| sp.0.cmp(series_paint))
+        {
+            Ok(index) => series_parts[index].1 += parts,
+            Err(index) => series_parts.insert(index, (series_paint.clone(), parts)),
+        };
+        for component in self.components.borrow().iter() {
+            match component.paint {
+                Paint::Series(ref series_paint) => add(series_paint, component.parts as f64),
+                Paint::Mixed(ref mixed_paint) => {
+                    let sub_parts = mixed_paint.series_parts();
+                    let total: f64 = sub_parts.iter().map(|sp| sp.1).sum();
+                    for (series_paint, parts) in sub_parts.iter() {
+                        add(series_paint, component.parts as f64 * parts / total);
+                    }
+                }
+            }
+        }
+        series_parts
+    }
+
     pub fn matched_colour(&self) -> Option<Colour> {
         if let Some(ref target_colour) = self.target_colour {
             Some(target_colour.colour())
@@ -284,8 +389,75 @@ impl<C: CharacteristicsInterface> MixedPaintCore<C> {
         }
     }
 
+    pub fn target_provenance(&self) -> Option<TargetProvenance> {
+        self.target_colour
+            .as_ref()
+            .and_then(|target_colour| target_colour.provenance())
+    }
+
     pub fn components(&self) -> Rc<Vec<PaintComponent<C>>> {
-        self.components.clone()
+        self.components.borrow().clone()
+    }
+
+    // Swap in the current versions of component series paints (e.g. after
+    // their collection has been corrected and reloaded) returning whether
+    // any of them differ from those being replaced.
+    pub fn update_series_components(&self, current: &[SeriesPaint<C>]) -> bool {
+        let mut changed = false;
+        let components: Vec<PaintComponent<C>> = self
+            .components
+            .borrow()
+            .iter()
+            .map(|component| match component.paint {
+                Paint::Series(ref series_paint) => {
+                    match current.iter().find(|paint| *paint == series_paint) {
+                        Some(paint) => {
+                            if paint.colour() != series_paint.colour()
+                                || paint.characteristics() != series_paint.characteristics()
+                            {
+                                changed = true;
+                            }
+                            PaintComponent {
+                                paint: Paint::Series(paint.clone()),
+                                parts: component.parts,
+                            }
+                        }
+                        None => component.clone(),
+                    }
+                }
+                Paint::Mixed(_) => component.clone(),
+            })
+            .collect();
+        *self.components.borrow_mut() = Rc::new(components);
+        changed
+    }
+
+    // Re-derive the colour from the current components returning whether it changed
+    pub fn recompute_colour(&self, mixing_model: MixingModel) -> bool {
+        match mixture_colour(&self.components.borrow(), mixing_model) {
+            Some(colour) if colour != self.colour.get() => {
+                self.colour.set(colour);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Re-derive the characteristics from the current components returning
+    // whether they changed
+    pub fn recompute_characteristics(&self) -> bool {
+        let characteristics = mixture_characteristics(&self.components.borrow());
+        if characteristics != self.characteristics.get() {
+            self.characteristics.set(characteristics);
+            true
+        } else {
+            false
+        }
+    }
+
+    // Earlier recipes (oldest first) if the mixture has been edited
+    pub fn recipe_history(&self) -> &[RecipeRevision<C>] {
+        &self.recipe_history
     }
 }
 
@@ -293,7 +465,7 @@ pub type MixedPaint<C> = Rc<MixedPaintCore<C>>;
 
 impl<C: CharacteristicsInterface> ColouredItemInterface for MixedPaint<C> {
     fn colour(&self) -> Colour {
-        self.colour.clone()
+        self.colour.get()
     }
 }
 
@@ -314,7 +486,7 @@ where
     }
 
     fn characteristics(&self) -> C {
-        self.characteristics.clone()
+        self.characteristics.get()
     }
 
     fn tv_row_len() -> usize {
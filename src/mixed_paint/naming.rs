@@ -0,0 +1,106 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+// How new mixtures are named e.g. "Mix #007" or "Batch 7 (2017-12-25)".
+
+use std::fmt;
+use std::rc::Rc;
+
+use chrono::prelude::*;
+
+#[derive(Clone)]
+pub enum MixedPaintNamingPolicy {
+    Numbered {
+        prefix: String,
+        width: usize,
+        date_stamp: bool,
+    },
+    // Given the mixture's id number (which starts at 1)
+    Custom(Rc<dyn Fn(u32) -> String>),
+}
+
+impl Default for MixedPaintNamingPolicy {
+    fn default() -> MixedPaintNamingPolicy {
+        MixedPaintNamingPolicy::numbered("Mix #", 3)
+    }
+}
+
+impl fmt::Debug for MixedPaintNamingPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MixedPaintNamingPolicy::Numbered {
+                prefix,
+                width,
+                date_stamp,
+            } => f
+                .debug_struct("Numbered")
+                .field("prefix", prefix)
+                .field("width", width)
+                .field("date_stamp", date_stamp)
+                .finish(),
+            MixedPaintNamingPolicy::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+impl MixedPaintNamingPolicy {
+    pub fn numbered(prefix: &str, width: usize) -> MixedPaintNamingPolicy {
+        MixedPaintNamingPolicy::Numbered {
+            prefix: prefix.to_string(),
+            width,
+            date_stamp: false,
+        }
+    }
+
+    pub fn dated(prefix: &str, width: usize) -> MixedPaintNamingPolicy {
+        MixedPaintNamingPolicy::Numbered {
+            prefix: prefix.to_string(),
+            width,
+            date_stamp: true,
+        }
+    }
+
+    pub fn custom<F: Fn(u32) -> String + 'static>(namer: F) -> MixedPaintNamingPolicy {
+        MixedPaintNamingPolicy::Custom(Rc::new(namer))
+    }
+
+    pub fn name_for(&self, id: u32) -> String {
+        match self {
+            MixedPaintNamingPolicy::Numbered {
+                prefix,
+                width,
+                date_stamp,
+            } => {
+                let name = format!("{}{:0width$}", prefix, id, width = width);
+                if *date_stamp {
+                    format!("{} ({})", name, Local::now().format("%Y-%m-%d"))
+                } else {
+                    name
+                }
+            }
+            MixedPaintNamingPolicy::Custom(namer) => namer(id),
+        }
+    }
+}
+
+// e.g. "Target #007" for "Mix #007"
+pub fn target_name_for(mix_name: &str) -> String {
+    format!("Target {}", mix_name.trim_start_matches("Mix "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mixture_names() {
+        let policy = MixedPaintNamingPolicy::default();
+        assert_eq!(policy.name_for(7), "Mix #007");
+        assert_eq!(target_name_for(&policy.name_for(7)), "Target #007");
+        let policy = MixedPaintNamingPolicy::numbered("Batch ", 0);
+        assert_eq!(policy.name_for(12), "Batch 12");
+        let policy = MixedPaintNamingPolicy::dated("Batch ", 2);
+        assert!(policy.name_for(3).starts_with("Batch 03 ("));
+        let policy = MixedPaintNamingPolicy::custom(|id| format!("Wash {}", id * 10));
+        assert_eq!(policy.name_for(2), "Wash 20");
+    }
+}
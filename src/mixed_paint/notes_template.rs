@@ -0,0 +1,138 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+use std::rc::Rc;
+
+use pw_gix::{
+    gtk::{self, prelude::*},
+    gtkx::dialog::*,
+    recollections::{recall, remember},
+    wrapper::*,
+};
+
+use crate::app_name;
+
+pub const TARGET_PLACEHOLDER: &str = "{target}";
+pub const DATE_PLACEHOLDER: &str = "{date}";
+pub const MIX_ID_PLACEHOLDER: &str = "{mix_id}";
+pub const COLOUR_NAME_PLACEHOLDER: &str = "{colour_name}";
+
+const NOTES_TEMPLATES_RECOLLECTION: &str = "mixed_paint_notes_templates";
+// Templates are single line so this can't clash with their content
+const NOTES_TEMPLATES_SEPARATOR: char = '\u{1e}';
+
+pub static DEFAULT_NOTES_TEMPLATES: &[&str] = &[
+    "Basecoat for {target}",
+    "Shade for {target}",
+    "Highlight 1 of {target}",
+    "Highlight 2 of {target}",
+    "Mix {mix_id} ({date})",
+];
+
+#[derive(Debug, Default, Clone)]
+pub struct NotesTemplateFields {
+    pub target_name: String,
+    pub date: String,
+    pub mix_id: String,
+    pub colour_name: String,
+}
+
+pub fn expand_notes_template(template: &str, fields: &NotesTemplateFields) -> String {
+    template
+        .replace(TARGET_PLACEHOLDER, &fields.target_name)
+        .replace(DATE_PLACEHOLDER, &fields.date)
+        .replace(MIX_ID_PLACEHOLDER, &fields.mix_id)
+        .replace(COLOUR_NAME_PLACEHOLDER, &fields.colour_name)
+}
+
+fn templates_from_text(text: &str, separator: char) -> Vec<String> {
+    text.split(separator)
+        .map(|template| template.trim())
+        .filter(|template| template.len() > 0)
+        .map(|template| template.to_string())
+        .collect()
+}
+
+pub fn recall_notes_templates() -> Vec<String> {
+    if let Some(text) = recall(NOTES_TEMPLATES_RECOLLECTION) {
+        templates_from_text(&text, NOTES_TEMPLATES_SEPARATOR)
+    } else {
+        DEFAULT_NOTES_TEMPLATES
+            .iter()
+            .map(|template| template.to_string())
+            .collect()
+    }
+}
+
+pub fn remember_notes_templates(templates: &[String]) {
+    let text = templates.join(&NOTES_TEMPLATES_SEPARATOR.to_string());
+    remember(NOTES_TEMPLATES_RECOLLECTION, &text);
+}
+
+// Let the user edit the templates (one per line) and save them if accepted.
+pub fn edit_notes_templates<W: WidgetWrapper>(caller: &Rc<W>) {
+    let title = format!("{}: Mixed Paint Notes Templates", app_name());
+    let dialog = caller.new_dialog_with_buttons(
+        Some(&title),
+        gtk::DialogFlags::DESTROY_WITH_PARENT,
+        CANCEL_OK_BUTTONS,
+    );
+    dialog.set_size_from_recollections("notes_templates_dialog", (400, 250));
+    let label = gtk::Label::new(Some(&format!(
+        "One template per line. Placeholders: {} {} {} {}",
+        TARGET_PLACEHOLDER, DATE_PLACEHOLDER, MIX_ID_PLACEHOLDER, COLOUR_NAME_PLACEHOLDER
+    )));
+    let text_view = gtk::TextView::new();
+    if let Some(buffer) = text_view.get_buffer() {
+        buffer.set_text(&recall_notes_templates().join("\n"));
+    }
+    let adj: Option<&gtk::Adjustment> = None;
+    let scrolled_window = gtk::ScrolledWindow::new(adj, adj);
+    scrolled_window.add(&text_view);
+    let content_area = dialog.get_content_area();
+    content_area.pack_start(&label, false, false, 0);
+    content_area.pack_start(&scrolled_window, true, true, 0);
+    content_area.show_all();
+    if gtk::ResponseType::from(dialog.run()) == gtk::ResponseType::Ok {
+        if let Some(buffer) = text_view.get_buffer() {
+            let (start, end) = buffer.get_bounds();
+            if let Some(text) = buffer.get_text(&start, &end, false) {
+                remember_notes_templates(&templates_from_text(&text, '\n'));
+            }
+        }
+    }
+    unsafe { dialog.destroy() };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand_notes_template_placeholders() {
+        let fields = NotesTemplateFields {
+            target_name: "Sky".to_string(),
+            date: "2017-12-25".to_string(),
+            mix_id: "#007".to_string(),
+            colour_name: "Deep Warm Olive".to_string(),
+        };
+        assert_eq!(
+            expand_notes_template("Highlight 1 of {target}", &fields),
+            "Highlight 1 of Sky"
+        );
+        assert_eq!(
+            expand_notes_template("Mix {mix_id} ({date}) for {target}", &fields),
+            "Mix #007 (2017-12-25) for Sky"
+        );
+        assert_eq!(
+            expand_notes_template("{colour_name} shade", &fields),
+            "Deep Warm Olive shade"
+        );
+        assert_eq!(expand_notes_template("No fields", &fields), "No fields");
+    }
+
+    #[test]
+    fn templates_from_text_skips_blank_lines() {
+        let templates = templates_from_text("Basecoat for {target}\n\n  Shade \n", '\n');
+        assert_eq!(templates, vec!["Basecoat for {target}", "Shade"]);
+    }
+}
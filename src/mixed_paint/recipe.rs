@@ -0,0 +1,362 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+// Scaling a mixture's recipe (given in parts) to the volume of each
+// component needed to make a desired total amount of the mixture.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use pw_gix::{
+    gtk::{self, prelude::*},
+    recollections::{recall, remember},
+};
+
+use crate::app_name;
+use crate::basic_paint::*;
+
+use super::*;
+
+pub const ML_PER_DROP: f64 = 0.05;
+pub const ML_PER_FLUID_OUNCE: f64 = 29.5735;
+
+// Anything less than about a drop can't be measured with any reliability
+pub const MIN_MEASURABLE_ML: f64 = 0.05;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VolumeUnit {
+    Millilitres,
+    Drops,
+    FluidOunces,
+}
+
+impl Default for VolumeUnit {
+    fn default() -> VolumeUnit {
+        VolumeUnit::Millilitres
+    }
+}
+
+static VOLUME_UNIT_VALUES: &[VolumeUnit] = &[
+    VolumeUnit::Millilitres,
+    VolumeUnit::Drops,
+    VolumeUnit::FluidOunces,
+];
+
+impl VolumeUnit {
+    pub fn values() -> Vec<VolumeUnit> {
+        VOLUME_UNIT_VALUES.to_vec()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            VolumeUnit::Millilitres => "ml",
+            VolumeUnit::Drops => "drops",
+            VolumeUnit::FluidOunces => "fl oz",
+        }
+    }
+
+    pub fn millilitres(&self, quantity: f64) -> f64 {
+        match *self {
+            VolumeUnit::Millilitres => quantity,
+            VolumeUnit::Drops => quantity * ML_PER_DROP,
+            VolumeUnit::FluidOunces => quantity * ML_PER_FLUID_OUNCE,
+        }
+    }
+
+    // The number of decimal places that it's sensible to measure to
+    pub fn digits(&self) -> u32 {
+        match *self {
+            VolumeUnit::Millilitres => 1,
+            VolumeUnit::Drops => 0,
+            VolumeUnit::FluidOunces => 2,
+        }
+    }
+
+    pub fn resolution(&self) -> f64 {
+        10.0_f64.powi(-(self.digits() as i32))
+    }
+
+    pub fn recall(key: &str) -> VolumeUnit {
+        if let Some(text) = recall(key) {
+            if let Ok(unit) = VolumeUnit::from_str(&text) {
+                return unit;
+            }
+        };
+        VolumeUnit::default()
+    }
+
+    pub fn remember(&self, key: &str) {
+        remember(key, self.name())
+    }
+}
+
+impl FromStr for VolumeUnit {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<VolumeUnit, String> {
+        for unit in VOLUME_UNIT_VALUES.iter() {
+            if unit.name() == string {
+                return Ok(*unit);
+            }
+        }
+        Err(format!("{}: unknown volume unit", string))
+    }
+}
+
+impl fmt::Display for VolumeUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScaledComponent {
+    pub name: String,
+    pub parts: u32,
+    pub quantity: f64,
+}
+
+impl ScaledComponent {
+    pub fn is_measurable(&self, unit: VolumeUnit) -> bool {
+        unit.millilitres(self.quantity) >= MIN_MEASURABLE_ML - 1.0e-9
+    }
+}
+
+// The quantities are rounded to the unit's resolution using the largest
+// remainder method so that they still add up to the (rounded) total.
+pub fn scale_recipe(parts: &[(String, u32)], total: f64, unit: VolumeUnit) -> Vec<ScaledComponent> {
+    let total_parts: u32 = parts.iter().map(|(_, p)| p).sum();
+    if total_parts == 0 || total <= 0.0 {
+        return parts
+            .iter()
+            .map(|(name, p)| ScaledComponent {
+                name: name.clone(),
+                parts: *p,
+                quantity: 0.0,
+            })
+            .collect();
+    }
+    let resolution = unit.resolution();
+    let total_steps = (total / resolution).round() as u64;
+    let exact: Vec<f64> = parts
+        .iter()
+        .map(|(_, p)| total_steps as f64 * *p as f64 / total_parts as f64)
+        .collect();
+    let mut steps: Vec<u64> = exact.iter().map(|x| x.floor() as u64).collect();
+    let allocated: u64 = steps.iter().sum();
+    let mut by_remainder: Vec<usize> = (0..parts.len()).collect();
+    by_remainder.sort_by(|a, b| {
+        let ra = exact[*a] - exact[*a].floor();
+        let rb = exact[*b] - exact[*b].floor();
+        rb.partial_cmp(&ra).unwrap_or(Ordering::Equal)
+    });
+    for index in by_remainder.iter().take((total_steps - allocated) as usize) {
+        steps[*index] += 1;
+    }
+    parts
+        .iter()
+        .zip(steps.iter())
+        .map(|((name, p), s)| ScaledComponent {
+            name: name.clone(),
+            parts: *p,
+            quantity: *s as f64 * resolution,
+        })
+        .collect()
+}
+
+pub fn scaled_recipe_text(components: &[ScaledComponent], unit: VolumeUnit) -> String {
+    let digits = unit.digits() as usize;
+    let amounts: Vec<String> = components
+        .iter()
+        .map(|c| format!("{:.*} {}", digits, c.quantity, unit))
+        .collect();
+    let total: f64 = components.iter().map(|c| c.quantity).sum();
+    let total_amount = format!("{:.*} {}", digits, total, unit);
+    let width = amounts
+        .iter()
+        .map(|amount| amount.len())
+        .max()
+        .unwrap_or(0)
+        .max(total_amount.len());
+    let mut text = String::new();
+    for (component, amount) in components.iter().zip(amounts.iter()) {
+        text += &format!(
+            "{:>width$}  {} ({} part{}){}\n",
+            amount,
+            component.name,
+            component.parts,
+            if component.parts == 1 { "" } else { "s" },
+            if component.is_measurable(unit) {
+                ""
+            } else {
+                " *"
+            },
+            width = width
+        );
+    }
+    text += &format!("{:>width$}  Total\n", total_amount, width = width);
+    text
+}
+
+pub fn unmeasurable_warning(components: &[ScaledComponent], unit: VolumeUnit) -> Option<String> {
+    let names: Vec<&str> = components
+        .iter()
+        .filter(|c| !c.is_measurable(unit))
+        .map(|c| c.name.as_str())
+        .collect();
+    if names.len() == 0 {
+        None
+    } else {
+        Some(format!(
+            "* less than {} ml (about a drop) which is too little to measure: {}.\nConsider making a larger amount.",
+            MIN_MEASURABLE_ML,
+            names.join(", ")
+        ))
+    }
+}
+
+const UNIT_RECOLLECTION: &str = "recipe::volume_unit";
+const TOTAL_RECOLLECTION: &str = "recipe::total";
+
+pub fn scale_recipe_dialog<C: CharacteristicsInterface>(
+    paint: &MixedPaint<C>,
+    parent: &gtk::Dialog,
+) {
+    let parts: Vec<(String, u32)> = paint
+        .components()
+        .iter()
+        .map(|component| (component.paint.name(), component.parts))
+        .collect();
+    let title = format!("{}: Scale Recipe: {}", app_name(), paint.name());
+    let dialog = gtk::Dialog::with_buttons(
+        Some(title.as_str()),
+        Some(parent),
+        gtk::DialogFlags::DESTROY_WITH_PARENT | gtk::DialogFlags::MODAL,
+        &[("Close", gtk::ResponseType::Close)],
+    );
+    let total_spin = gtk::SpinButton::with_range(0.0, 10000.0, 1.0);
+    total_spin.set_tooltip_text(Some("The total amount of the mixture wanted"));
+    let unit_combo = gtk::ComboBoxText::new();
+    for unit in VolumeUnit::values() {
+        unit_combo.append(Some(unit.name()), unit.name());
+    }
+    let unit = VolumeUnit::recall(UNIT_RECOLLECTION);
+    unit_combo.set_active_id(Some(unit.name()));
+    total_spin.set_digits(unit.digits());
+    let total = recall(TOTAL_RECOLLECTION)
+        .and_then(|text| f64::from_str(&text).ok())
+        .unwrap_or(10.0);
+    total_spin.set_value(total);
+    let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 2);
+    hbox.pack_start(&gtk::Label::new(Some("Total:")), false, false, 0);
+    hbox.pack_start(&total_spin, true, true, 0);
+    hbox.pack_start(&unit_combo, false, false, 0);
+
+    let recipe_view = gtk::TextView::new();
+    recipe_view.set_editable(false);
+    recipe_view.set_monospace(true);
+    let warning_label = gtk::Label::new(None);
+    warning_label.set_line_wrap(true);
+    warning_label.set_xalign(0.0);
+
+    let update = {
+        let total_spin = total_spin.clone();
+        let unit_combo = unit_combo.clone();
+        let recipe_view = recipe_view.clone();
+        let warning_label = warning_label.clone();
+        move || {
+            let unit = unit_combo
+                .get_active_id()
+                .and_then(|id| VolumeUnit::from_str(&id).ok())
+                .unwrap_or_default();
+            let scaled = scale_recipe(&parts, total_spin.get_value(), unit);
+            if let Some(buffer) = recipe_view.get_buffer() {
+                buffer.set_text(&scaled_recipe_text(&scaled, unit));
+            }
+            let warning = unmeasurable_warning(&scaled, unit).unwrap_or_default();
+            warning_label.set_text(&warning);
+        }
+    };
+    update();
+    let update = Rc::new(update);
+    let update_c = update.clone();
+    total_spin.connect_value_changed(move |_| update_c());
+    let total_spin_c = total_spin.clone();
+    unit_combo.connect_changed(move |combo| {
+        if let Some(unit) = combo
+            .get_active_id()
+            .and_then(|id| VolumeUnit::from_str(&id).ok())
+        {
+            total_spin_c.set_digits(unit.digits());
+        }
+        update()
+    });
+
+    let content_area = dialog.get_content_area();
+    content_area.pack_start(&hbox, false, false, 0);
+    content_area.pack_start(&recipe_view, true, true, 0);
+    content_area.pack_start(&warning_label, false, false, 0);
+    content_area.show_all();
+    dialog.run();
+    if let Some(unit) = unit_combo
+        .get_active_id()
+        .and_then(|id| VolumeUnit::from_str(&id).ok())
+    {
+        unit.remember(UNIT_RECOLLECTION);
+    }
+    remember(TOTAL_RECOLLECTION, &total_spin.get_value().to_string());
+    unsafe { dialog.destroy() };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recipe() -> Vec<(String, u32)> {
+        vec![
+            ("White".to_string(), 20),
+            ("Red".to_string(), 9),
+            ("Black".to_string(), 1),
+        ]
+    }
+
+    #[test]
+    fn volume_unit_names() {
+        for unit in VolumeUnit::values() {
+            assert_eq!(VolumeUnit::from_str(unit.name()), Ok(unit));
+        }
+        assert!(VolumeUnit::from_str("cups").is_err());
+        assert_eq!(VolumeUnit::Drops.millilitres(20.0), 1.0);
+    }
+
+    #[test]
+    fn recipe_scaling() {
+        let scaled = scale_recipe(&recipe(), 10.0, VolumeUnit::Millilitres);
+        let quantities: Vec<f64> = scaled.iter().map(|c| c.quantity).collect();
+        assert!((quantities[0] - 6.7).abs() < 1.0e-9);
+        assert!((quantities[1] - 3.0).abs() < 1.0e-9);
+        assert!((quantities[2] - 0.3).abs() < 1.0e-9);
+        assert!((quantities.iter().sum::<f64>() - 10.0).abs() < 1.0e-9);
+        assert!(unmeasurable_warning(&scaled, VolumeUnit::Millilitres).is_none());
+        // rounding to whole drops still gives some of each
+        let scaled = scale_recipe(&recipe(), 20.0, VolumeUnit::Drops);
+        assert_eq!(scaled[2].quantity, 1.0);
+        let scaled = scale_recipe(&recipe(), 10.0, VolumeUnit::Drops);
+        assert_eq!(scaled.iter().map(|c| c.quantity).sum::<f64>(), 10.0);
+        assert_eq!(scaled[2].quantity, 0.0);
+        assert!(!scaled[2].is_measurable(VolumeUnit::Drops));
+        let warning = unmeasurable_warning(&scaled, VolumeUnit::Drops).unwrap();
+        assert!(warning.contains("Black"));
+        assert!(!warning.contains("Red"));
+    }
+
+    #[test]
+    fn scaled_recipe_printing() {
+        let scaled = scale_recipe(&recipe()[1..], 5.0, VolumeUnit::Millilitres);
+        assert_eq!(
+            scaled_recipe_text(&scaled, VolumeUnit::Millilitres),
+            "4.5 ml  Red (9 parts)\n0.5 ml  Black (1 part)\n5.0 ml  Total\n"
+        );
+    }
+}
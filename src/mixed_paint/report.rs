@@ -0,0 +1,356 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+
+use chrono::prelude::*;
+
+use crate::basic_paint::*;
+use crate::series_paint::*;
+
+use super::*;
+
+// A snapshot of a mixing session handed to custom report sections
+pub struct MixSession<C: CharacteristicsInterface> {
+    pub date: DateTime<Local>,
+    pub notes: String,
+    pub target_name: String,
+    pub series_paints: Vec<SeriesPaint<C>>,
+    pub mixed_paints: Vec<MixedPaint<C>>,
+}
+
+// Generates (pango markup) chunks to be appended to the mixer's report
+// e.g. studio branding, job numbers or client sign-off blocks
+pub type ReportSectionGenerator<C> = Box<dyn Fn(&MixSession<C>) -> Vec<String>>;
+
+pub fn report_section_chunks<C: CharacteristicsInterface>(
+    session: &MixSession<C>,
+    generators: &[ReportSectionGenerator<C>],
+) -> Vec<String> {
+    generators
+        .iter()
+        .flat_map(|generator| generator(session))
+        .filter(|chunk| chunk.len() > 0)
+        .collect()
+}
+
+// PDF REPORTS
+// A4 in points
+pub const PDF_PAGE_WIDTH: f64 = 595.0;
+pub const PDF_PAGE_HEIGHT: f64 = 842.0;
+const PDF_MARGIN: f64 = 50.0;
+const PDF_FONT_SIZE: f64 = 10.0;
+const PDF_LINE_HEIGHT: f64 = 16.0;
+const PDF_SWATCH_WIDTH: f64 = 24.0;
+const PDF_PARTS_WIDTH: f64 = 50.0;
+
+// The layout independent content of a report (one entry per line)
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReportLine {
+    Heading(String),
+    Text(String),
+    // Colour swatches followed by a description
+    Swatches(Vec<RGB>, String),
+    // A row of a mixture's components table
+    Component { parts: u32, rgb: RGB, name: String },
+    Blank,
+}
+
+impl ReportLine {
+    fn height(&self) -> f64 {
+        match *self {
+            ReportLine::Heading(_) => PDF_LINE_HEIGHT * 1.5,
+            _ => PDF_LINE_HEIGHT,
+        }
+    }
+}
+
+// Long text is broken (at spaces where possible) into lines of at most "max_chars"
+pub fn wrapped_text_lines(text: &str, max_chars: usize) -> Vec<String> {
+    let mut lines = vec![];
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            if line.len() > 0 && line.chars().count() + word.chars().count() + 1 > max_chars {
+                lines.push(line);
+                line = String::new();
+            }
+            if line.len() > 0 {
+                line.push(' ');
+            }
+            line += word;
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+// The custom (pango markup) report sections as plain text
+pub fn markup_to_text(markup: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for ch in markup.chars() {
+        match ch {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => text.push(ch),
+            _ => (),
+        }
+    }
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+pub fn text_report_lines(text: &str) -> Vec<ReportLine> {
+    let max_chars = ((PDF_PAGE_WIDTH - 2.0 * PDF_MARGIN) / (PDF_FONT_SIZE * 0.55)) as usize;
+    wrapped_text_lines(text, max_chars)
+        .into_iter()
+        .map(ReportLine::Text)
+        .collect()
+}
+
+fn with_notes(name: String, notes: String) -> String {
+    if notes.len() > 0 {
+        format!("{} {}", name, notes)
+    } else {
+        name
+    }
+}
+
+// The paints used and the mixtures (with their components)
+pub fn mix_session_report_lines<C: CharacteristicsInterface>(
+    session: &MixSession<C>,
+    policy: DisplayNamePolicy,
+) -> Vec<ReportLine> {
+    let mut lines = vec![ReportLine::Heading(format!(
+        "Mix/Match Description: {}",
+        session.date.format("%X: %A %x")
+    ))];
+    if session.notes.len() > 0 {
+        lines.extend(text_report_lines(&session.notes));
+    }
+    lines.push(ReportLine::Blank);
+    lines.push(ReportLine::Heading("Paint Colours:".to_string()));
+    for paint in session.series_paints.iter() {
+        lines.push(ReportLine::Swatches(
+            vec![paint.rgb()],
+            with_notes(paint.display_name(policy), paint.notes()),
+        ));
+        lines.extend(text_report_lines(&paint.characteristics().to_string()));
+    }
+    lines.push(ReportLine::Blank);
+    lines.push(ReportLine::Heading("Mixed Colours:".to_string()));
+    for paint in session.mixed_paints.iter() {
+        lines.push(ReportLine::Swatches(
+            vec![paint.rgb(), paint.monochrome_rgb(), paint.max_chroma_rgb()],
+            with_notes(paint.name(), paint.notes()),
+        ));
+        if let Some(colour) = paint.matched_colour() {
+            lines.push(ReportLine::Swatches(
+                vec![
+                    colour.rgb(),
+                    colour.monochrome_rgb(),
+                    colour.max_chroma_rgb(),
+                ],
+                "Matched Colour".to_string(),
+            ));
+        }
+        if let Some(provenance) = paint.target_provenance() {
+            lines.extend(text_report_lines(&provenance.description()));
+        }
+        for component in paint.components().iter() {
+            lines.push(ReportLine::Component {
+                parts: component.parts,
+                rgb: component.paint.rgb(),
+                name: component.paint.name(),
+            });
+        }
+        lines.extend(text_report_lines(&paint.characteristics().to_string()));
+        lines.push(ReportLine::Blank);
+    }
+    lines
+}
+
+// The ranges of lines that fit on each page
+pub fn paginate(lines: &[ReportLine], page_height: f64) -> Vec<Range<usize>> {
+    let available = page_height - 2.0 * PDF_MARGIN;
+    let mut pages = vec![];
+    let mut start = 0;
+    let mut used = 0.0;
+    for (index, line) in lines.iter().enumerate() {
+        if used + line.height() > available && index > start {
+            pages.push(start..index);
+            start = index;
+            used = 0.0;
+        }
+        used += line.height();
+    }
+    if start < lines.len() {
+        pages.push(start..lines.len());
+    }
+    pages
+}
+
+pub fn pdf_export_available() -> bool {
+    cfg!(feature = "pdf_export")
+}
+
+#[cfg(feature = "pdf_export")]
+mod pdf {
+    use std::io;
+    use std::path::Path;
+
+    use pw_gix::cairo;
+
+    use crate::colour::*;
+
+    use super::*;
+
+    fn to_io_error(err: cairo::Status) -> io::Error {
+        io::Error::new(io::ErrorKind::Other, format!("{:?}", err))
+    }
+
+    // NB: swatches are in sRGB (not the display's colour space)
+    fn draw_swatch(cairo_context: &cairo::Context, x: f64, y: f64, rgb: &RGB) {
+        let height = PDF_LINE_HEIGHT - 4.0;
+        cairo_context.rectangle(x, y + 2.0, PDF_SWATCH_WIDTH, height);
+        cairo_context.set_source_rgb(rgb[CCI::Red], rgb[CCI::Green], rgb[CCI::Blue]);
+        cairo_context.fill_preserve();
+        cairo_context.set_source_rgb(0.0, 0.0, 0.0);
+        cairo_context.set_line_width(0.5);
+        cairo_context.stroke();
+    }
+
+    fn draw_text(cairo_context: &cairo::Context, x: f64, y: f64, text: &str, bold: bool) {
+        let weight = if bold {
+            cairo::FontWeight::Bold
+        } else {
+            cairo::FontWeight::Normal
+        };
+        cairo_context.select_font_face("Sans", cairo::FontSlant::Normal, weight);
+        cairo_context.set_source_rgb(0.0, 0.0, 0.0);
+        cairo_context.move_to(x, y + PDF_LINE_HEIGHT * 0.75);
+        cairo_context.show_text(text);
+    }
+
+    fn draw_line(cairo_context: &cairo::Context, y: f64, line: &ReportLine) {
+        let x = PDF_MARGIN;
+        match line {
+            ReportLine::Heading(text) => {
+                cairo_context.set_font_size(PDF_FONT_SIZE * 1.2);
+                draw_text(cairo_context, x, y + PDF_LINE_HEIGHT * 0.25, text, true);
+                cairo_context.set_font_size(PDF_FONT_SIZE);
+            }
+            ReportLine::Text(text) => draw_text(cairo_context, x, y, text, false),
+            ReportLine::Swatches(rgbs, text) => {
+                let mut x = x;
+                for rgb in rgbs.iter() {
+                    draw_swatch(cairo_context, x, y, rgb);
+                    x += PDF_SWATCH_WIDTH + 4.0;
+                }
+                draw_text(cairo_context, x + 4.0, y, text, false);
+            }
+            ReportLine::Component { parts, rgb, name } => {
+                let parts_text = format!("{}", parts);
+                let extents = cairo_context.text_extents(&parts_text);
+                let parts_x = x + PDF_PARTS_WIDTH - extents.x_advance;
+                draw_text(cairo_context, parts_x, y, &parts_text, false);
+                let x = x + PDF_PARTS_WIDTH + 8.0;
+                draw_swatch(cairo_context, x, y, rgb);
+                draw_text(cairo_context, x + PDF_SWATCH_WIDTH + 8.0, y, name, false);
+            }
+            ReportLine::Blank => (),
+        }
+    }
+
+    pub fn write_pdf_report(path: &Path, lines: &[ReportLine]) -> io::Result<()> {
+        let surface =
+            cairo::PdfSurface::new(PDF_PAGE_WIDTH, PDF_PAGE_HEIGHT, path).map_err(to_io_error)?;
+        let cairo_context = cairo::Context::new(&surface);
+        cairo_context.set_font_size(PDF_FONT_SIZE);
+        for page in paginate(lines, PDF_PAGE_HEIGHT) {
+            let mut y = PDF_MARGIN;
+            for line in lines[page].iter() {
+                draw_line(&cairo_context, y, line);
+                y += line.height();
+            }
+            cairo_context.show_page();
+        }
+        drop(cairo_context);
+        surface.finish();
+        Ok(())
+    }
+}
+
+// Render the report to a PDF file (independent of any printer)
+#[cfg(feature = "pdf_export")]
+pub fn write_pdf_report(path: &Path, lines: &[ReportLine]) -> io::Result<()> {
+    pdf::write_pdf_report(path, lines)
+}
+
+#[cfg(not(feature = "pdf_export"))]
+pub fn write_pdf_report(path: &Path, _lines: &[ReportLine]) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        format!("{:?}: PDF export requires the \"pdf_export\" feature", path),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_paint::ModelPaintCharacteristics;
+
+    #[test]
+    fn custom_report_sections() {
+        let session = MixSession::<ModelPaintCharacteristics> {
+            date: Local::now(),
+            notes: "Job 42".to_string(),
+            target_name: String::new(),
+            series_paints: vec![],
+            mixed_paints: vec![],
+        };
+        let generators: Vec<ReportSectionGenerator<ModelPaintCharacteristics>> = vec![
+            Box::new(|session| vec![format!("<b>Job:</b> {}\n", session.notes)]),
+            Box::new(|_| vec![String::new()]),
+            Box::new(|_| vec!["Signed: ________\n".to_string(), String::new()]),
+        ];
+        assert_eq!(
+            report_section_chunks(&session, &generators),
+            vec![
+                "<b>Job:</b> Job 42\n".to_string(),
+                "Signed: ________\n".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn report_text_layout() {
+        assert_eq!(
+            wrapped_text_lines("the quick brown fox\njumps", 10),
+            vec!["the quick", "brown fox", "jumps"]
+        );
+        assert_eq!(wrapped_text_lines("", 10), Vec::<String>::new());
+        assert_eq!(
+            markup_to_text("<b>Job:</b> 42 &amp; 43 &lt;urgent&gt;"),
+            "Job: 42 & 43 <urgent>"
+        );
+    }
+
+    #[test]
+    fn report_pagination() {
+        let lines: Vec<ReportLine> = (0..100)
+            .map(|i| ReportLine::Text(format!("line {}", i)))
+            .collect();
+        let per_page = ((PDF_PAGE_HEIGHT - 2.0 * PDF_MARGIN) / PDF_LINE_HEIGHT) as usize;
+        let pages = paginate(&lines, PDF_PAGE_HEIGHT);
+        assert_eq!(pages[0], 0..per_page);
+        assert_eq!(pages.last().unwrap().end, 100);
+        assert_eq!(pages.len(), (100 + per_page - 1) / per_page);
+        assert!(paginate(&[], PDF_PAGE_HEIGHT).is_empty());
+    }
+}
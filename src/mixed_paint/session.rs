@@ -0,0 +1,574 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use chrono::prelude::*;
+use serde_derive::*;
+
+use crate::basic_paint::*;
+use crate::colour::*;
+use crate::error::*;
+use crate::series_paint::*;
+
+use super::*;
+
+pub const SESSION_FORMAT_VERSION: u32 = 1;
+
+// Series paints are identified by name (so that they can be found again
+// when their series is reloaded) and mixed paints by their name in the
+// saved session (as they'll be renamed when restored)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum SessionComponent {
+    Series {
+        manufacturer: String,
+        series: String,
+        paint: String,
+        parts: u32,
+    },
+    Mixed {
+        name: String,
+        parts: u32,
+    },
+}
+
+impl SessionComponent {
+    fn description(&self) -> String {
+        match self {
+            SessionComponent::Series {
+                manufacturer,
+                series,
+                paint,
+                ..
+            } => format!("{} ({} {})", paint, manufacturer, series),
+            SessionComponent::Mixed { name, .. } => name.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionMix {
+    pub name: String,
+    pub notes: String,
+    pub rgb: String,
+    pub target_rgb: Option<String>,
+    // Absent from sessions saved before provenance was recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_provenance: Option<TargetProvenance>,
+    pub components: Vec<SessionComponent>,
+}
+
+impl SessionMix {
+    pub fn target_colour(&self) -> Option<Colour> {
+        self.target_rgb
+            .as_ref()
+            .and_then(|text| rgb_from_css_hex(text))
+            .map(Colour::from)
+    }
+}
+
+// Neither the order of the components nor the mixture's notes matter
+fn same_recipe(mix: &SessionMix, other: &SessionMix) -> bool {
+    mix.target_rgb == other.target_rgb
+        && mix.components.len() == other.components.len()
+        && mix
+            .components
+            .iter()
+            .all(|component| other.components.contains(component))
+}
+
+// The outcome of merging another session into the current one
+pub struct SessionMerge<C: CharacteristicsInterface> {
+    pub added: Vec<MixedPaint<C>>,
+    // e.g. "Mix #3 (same as Mix #1)"
+    pub skipped: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MixingSession {
+    pub format_version: u32,
+    pub saved: String,
+    pub notes: String,
+    pub mixes: Vec<SessionMix>,
+}
+
+// "paints" reordered (where necessary) so that those used as components
+// precede the mixtures using them.  NB: a mixture whose recipe has been
+// replaced keeps its place but may now use a later mixture.
+fn dependency_order<C: CharacteristicsInterface>(paints: &[MixedPaint<C>]) -> Vec<MixedPaint<C>> {
+    fn visit<C: CharacteristicsInterface>(
+        paint: &MixedPaint<C>,
+        paints: &[MixedPaint<C>],
+        ordered: &mut Vec<MixedPaint<C>>,
+    ) {
+        if ordered.contains(paint) {
+            return;
+        }
+        for component in paint.components().iter() {
+            if let Paint::Mixed(ref mixed_paint) = component.paint {
+                if paints.contains(mixed_paint) {
+                    visit(mixed_paint, paints, ordered);
+                }
+            }
+        }
+        ordered.push(paint.clone());
+    }
+    let mut ordered: Vec<MixedPaint<C>> = Vec::with_capacity(paints.len());
+    for paint in paints.iter() {
+        visit(paint, paints, &mut ordered);
+    }
+    ordered
+}
+
+fn session_mix<C: CharacteristicsInterface>(paint: &MixedPaint<C>) -> SessionMix {
+    SessionMix {
+        name: paint.name(),
+        notes: paint.notes(),
+        rgb: paint.rgb().into_css_hex(),
+        target_rgb: paint
+            .matched_colour()
+            .map(|colour| colour.rgb().into_css_hex()),
+        target_provenance: paint.target_provenance(),
+        components: paint
+            .components()
+            .iter()
+            .map(|component| match component.paint {
+                Paint::Series(ref series_paint) => {
+                    let colln_id = series_paint.colln_id();
+                    SessionComponent::Series {
+                        manufacturer: colln_id.colln_owner(),
+                        series: colln_id.colln_name(),
+                        paint: series_paint.name(),
+                        parts: component.parts,
+                    }
+                }
+                Paint::Mixed(ref mixed_paint) => SessionComponent::Mixed {
+                    name: mixed_paint.name(),
+                    parts: component.parts,
+                },
+            })
+            .collect(),
+    }
+}
+
+impl MixingSession {
+    // Mixtures are recorded in the order they were made except that
+    // those used as components always precede the mixtures using them
+    pub fn from_mixed_paints<C: CharacteristicsInterface>(
+        paints: &[MixedPaint<C>],
+        notes: &str,
+    ) -> MixingSession {
+        let mixes = dependency_order(paints).iter().map(session_mix).collect();
+        MixingSession {
+            format_version: SESSION_FORMAT_VERSION,
+            saved: Local::now().format("%Y-%m-%d %H:%M").to_string(),
+            notes: notes.to_string(),
+            mixes,
+        }
+    }
+
+    fn find_series_paint<C: CharacteristicsInterface>(
+        component: &SessionComponent,
+        available: &[SeriesPaint<C>],
+    ) -> Option<SeriesPaint<C>> {
+        if let SessionComponent::Series {
+            manufacturer,
+            series,
+            paint,
+            ..
+        } = component
+        {
+            available
+                .iter()
+                .find(|sp| {
+                    let colln_id = sp.colln_id();
+                    sp.name() == *paint
+                        && colln_id.colln_name() == *series
+                        && colln_id.colln_owner() == *manufacturer
+                })
+                .cloned()
+        } else {
+            None
+        }
+    }
+
+    // Descriptions of the series paints that aren't available
+    pub fn missing_series_paints<C: CharacteristicsInterface>(
+        &self,
+        available: &[SeriesPaint<C>],
+    ) -> Vec<String> {
+        let mut missing: Vec<String> = vec![];
+        for mix in self.mixes.iter() {
+            for component in mix.components.iter() {
+                if let SessionComponent::Series { .. } = component {
+                    if Self::find_series_paint(component, available).is_none() {
+                        let description = component.description();
+                        if !missing.contains(&description) {
+                            missing.push(description);
+                        }
+                    }
+                }
+            }
+        }
+        missing
+    }
+
+    // Remake the mixtures (in order) using "add" to create each one from
+    // its notes, components, target colour and the target's provenance.
+    pub fn restore<C, F>(
+        &self,
+        available: &[SeriesPaint<C>],
+        mut add: F,
+    ) -> Result<Vec<MixedPaint<C>>, PaintError<C>>
+    where
+        C: CharacteristicsInterface,
+        F: FnMut(
+            &str,
+            Vec<(SeriesPaint<C>, u32)>,
+            Vec<(MixedPaint<C>, u32)>,
+            Option<Colour>,
+            Option<TargetProvenance>,
+        ) -> Result<MixedPaint<C>, PaintError<C>>,
+    {
+        let missing = self.missing_series_paints(available);
+        if missing.len() > 0 {
+            return Err(PaintErrorType::NotFound(missing.join(", ")).into());
+        }
+        let mut restored: Vec<MixedPaint<C>> = vec![];
+        let mut renamed: HashMap<String, MixedPaint<C>> = HashMap::new();
+        for mix in self.mixes.iter() {
+            let mut sp_components: Vec<(SeriesPaint<C>, u32)> = vec![];
+            let mut mp_components: Vec<(MixedPaint<C>, u32)> = vec![];
+            for component in mix.components.iter() {
+                match component {
+                    SessionComponent::Series { parts, .. } => {
+                        if let Some(paint) = Self::find_series_paint(component, available) {
+                            sp_components.push((paint, *parts));
+                        }
+                    }
+                    SessionComponent::Mixed { name, parts } => match renamed.get(name) {
+                        Some(paint) => mp_components.push((paint.clone(), *parts)),
+                        None => return Err(PaintErrorType::NotFound(name.clone()).into()),
+                    },
+                }
+            }
+            let paint = add(
+                &mix.notes,
+                sp_components,
+                mp_components,
+                mix.target_colour(),
+                mix.target_provenance.clone(),
+            )?;
+            renamed.insert(mix.name.clone(), paint.clone());
+            restored.push(paint);
+        }
+        Ok(restored)
+    }
+
+    // Add this session's mixtures to those already in "existing" using
+    // "add" (which gives them new names) re-linking their series paints to
+    // those available.  Mixtures with the same recipe and target as one
+    // that's already present are skipped and mixtures using them are made
+    // with the one present instead.
+    pub fn merge_into<C, F>(
+        &self,
+        available: &[SeriesPaint<C>],
+        existing: &[MixedPaint<C>],
+        mut add: F,
+    ) -> Result<SessionMerge<C>, PaintError<C>>
+    where
+        C: CharacteristicsInterface,
+        F: FnMut(
+            &str,
+            Vec<(SeriesPaint<C>, u32)>,
+            Vec<(MixedPaint<C>, u32)>,
+            Option<Colour>,
+            Option<TargetProvenance>,
+        ) -> Result<MixedPaint<C>, PaintError<C>>,
+    {
+        let missing = self.missing_series_paints(available);
+        if missing.len() > 0 {
+            return Err(PaintErrorType::NotFound(missing.join(", ")).into());
+        }
+        let mut known: Vec<(SessionMix, MixedPaint<C>)> = existing
+            .iter()
+            .map(|paint| (session_mix(paint), paint.clone()))
+            .collect();
+        let mut relinked: HashMap<String, MixedPaint<C>> = HashMap::new();
+        let mut merge = SessionMerge::<C> {
+            added: vec![],
+            skipped: vec![],
+        };
+        for mix in self.mixes.iter() {
+            // the recipe in terms of the current session's mixture names
+            let mut local_mix = mix.clone();
+            let mut sp_components: Vec<(SeriesPaint<C>, u32)> = vec![];
+            let mut mp_components: Vec<(MixedPaint<C>, u32)> = vec![];
+            for (index, component) in mix.components.iter().enumerate() {
+                match component {
+                    SessionComponent::Series { parts, .. } => {
+                        if let Some(paint) = Self::find_series_paint(component, available) {
+                            sp_components.push((paint, *parts));
+                        }
+                    }
+                    SessionComponent::Mixed { name, parts } => match relinked.get(name) {
+                        Some(paint) => {
+                            local_mix.components[index] = SessionComponent::Mixed {
+                                name: paint.name(),
+                                parts: *parts,
+                            };
+                            mp_components.push((paint.clone(), *parts));
+                        }
+                        None => return Err(PaintErrorType::NotFound(name.clone()).into()),
+                    },
+                }
+            }
+            if let Some((_, paint)) = known
+                .iter()
+                .find(|(known_mix, _)| same_recipe(&local_mix, known_mix))
+            {
+                merge
+                    .skipped
+                    .push(format!("{} (same as {})", mix.name, paint.name()));
+                relinked.insert(mix.name.clone(), paint.clone());
+                continue;
+            }
+            let paint = add(
+                &mix.notes,
+                sp_components,
+                mp_components,
+                mix.target_colour(),
+                mix.target_provenance.clone(),
+            )?;
+            local_mix.name = paint.name();
+            known.push((local_mix, paint.clone()));
+            relinked.insert(mix.name.clone(), paint.clone());
+            merge.added.push(paint);
+        }
+        Ok(merge)
+    }
+}
+
+pub fn read_session(path: &Path) -> io::Result<MixingSession> {
+    let mut file = File::open(path)?;
+    let mut text = String::new();
+    file.read_to_string(&mut text)?;
+    let session: MixingSession = serde_json::from_str(&text)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    if session.format_version > SESSION_FORMAT_VERSION {
+        let msg = format!(
+            "session format version {} is newer than this program understands",
+            session.format_version
+        );
+        return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+    }
+    Ok(session)
+}
+
+pub fn write_session(path: &Path, session: &MixingSession) -> io::Result<()> {
+    let text = serde_json::to_string_pretty(session)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    let mut file = File::create(path)?;
+    file.write_all(text.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::art_paint::*;
+    use crate::colln_paint::collection::*;
+    use crate::mixed_paint::collection::*;
+    use std::str::FromStr;
+
+    fn series_paints() -> Vec<ArtSeriesPaint> {
+        let text = "Manufacturer: Imaginary\nSeries: Series\n\
+             ArtPaint(name=\"Black\", rgb=#000000, transparency=\"O\", permanence=\"A\", notes=\"\")\n\
+             ArtPaint(name=\"White\", rgb=#FFFFFF, transparency=\"O\", permanence=\"A\", notes=\"\")\n";
+        ArtPaintSeries::from_spec(&ArtPaintSeriesSpec::from_str(text).unwrap()).get_paints()
+    }
+
+    #[test]
+    fn session_round_trip() {
+        let paints = series_paints();
+        let factory = MixedPaintFactory::<ArtPaintCharacteristics>::create(MixingModel::SRGB);
+        let provenance = TargetProvenance::Standard {
+            standard: "Greys".to_string(),
+            sponsor: "Imaginary".to_string(),
+            paint: "Mid Grey".to_string(),
+        };
+        let grey = factory
+            .add_paint_with_provenance(
+                "grey",
+                vec![(paints[0].clone(), 1), (paints[1].clone(), 1)],
+                vec![],
+                Some(Colour::from(RGB::from([0.5, 0.5, 0.5]))),
+                Some(provenance.clone()),
+            )
+            .unwrap();
+        factory
+            .add_paint(
+                "tint",
+                vec![(paints[1].clone(), 1)],
+                vec![(grey.clone(), 1)],
+                None,
+            )
+            .unwrap();
+        let session = MixingSession::from_mixed_paints(&factory.get_paints(), "evening");
+        let text = serde_json::to_string(&session).unwrap();
+        let session: MixingSession = serde_json::from_str(&text).unwrap();
+        assert_eq!(session.mixes.len(), 2);
+        assert_eq!(session.notes, "evening");
+
+        let new_factory = MixedPaintFactory::<ArtPaintCharacteristics>::create(MixingModel::SRGB);
+        let restored = session
+            .restore(&paints, |notes, sp, mp, target, o_provenance| {
+                new_factory.add_paint_with_provenance(notes, sp, mp, target, o_provenance)
+            })
+            .unwrap();
+        assert_eq!(restored.len(), 2);
+        for (old, new) in factory.get_paints().iter().zip(restored.iter()) {
+            assert_eq!(old.notes(), new.notes());
+            assert_eq!(old.colour(), new.colour());
+            assert_eq!(
+                old.matched_colour().is_some(),
+                new.matched_colour().is_some()
+            );
+            assert_eq!(old.components().len(), new.components().len());
+            assert_eq!(old.target_provenance(), new.target_provenance());
+        }
+        assert_eq!(restored[0].target_provenance(), Some(provenance));
+        assert!(restored[1].uses_mixed_paint(&restored[0]));
+
+        let missing = session.missing_series_paints(&paints[1..]);
+        assert_eq!(missing, vec!["Black (Imaginary Series)".to_string()]);
+        assert!(session
+            .restore(&paints[1..], |notes, sp, mp, target, o_provenance| {
+                new_factory.add_paint_with_provenance(notes, sp, mp, target, o_provenance)
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn session_with_replaced_recipe_round_trip() {
+        let paints = series_paints();
+        let factory = MixedPaintFactory::<ArtPaintCharacteristics>::create(MixingModel::SRGB);
+        let first = factory
+            .add_paint("first", vec![(paints[0].clone(), 1)], vec![], None)
+            .unwrap();
+        factory
+            .add_paint("second", vec![(paints[1].clone(), 1)], vec![], None)
+            .unwrap();
+        let third = factory
+            .add_paint(
+                "third",
+                vec![(paints[0].clone(), 1), (paints[1].clone(), 2)],
+                vec![],
+                None,
+            )
+            .unwrap();
+        // Mix #1 re-edited to use Mix #3 (and so keeping its place)
+        factory
+            .replace_recipe(
+                &first,
+                "first",
+                vec![(paints[1].clone(), 1)],
+                vec![(third.clone(), 1)],
+                None,
+            )
+            .unwrap();
+        let session = MixingSession::from_mixed_paints(&factory.get_paints(), "");
+        let names: Vec<&str> = session.mixes.iter().map(|mix| mix.notes.as_str()).collect();
+        assert_eq!(names, vec!["third", "first", "second"]);
+        let text = serde_json::to_string(&session).unwrap();
+        let session: MixingSession = serde_json::from_str(&text).unwrap();
+
+        let new_factory = MixedPaintFactory::<ArtPaintCharacteristics>::create(MixingModel::SRGB);
+        let restored = session
+            .restore(&paints, |notes, sp, mp, target, o_provenance| {
+                new_factory.add_paint_with_provenance(notes, sp, mp, target, o_provenance)
+            })
+            .unwrap();
+        assert_eq!(restored.len(), 3);
+        assert!(restored[1].uses_mixed_paint(&restored[0]));
+
+        let other_factory = MixedPaintFactory::<ArtPaintCharacteristics>::create(MixingModel::SRGB);
+        let merge = session
+            .merge_into(&paints, &[], |notes, sp, mp, target, o_provenance| {
+                other_factory.add_paint_with_provenance(notes, sp, mp, target, o_provenance)
+            })
+            .unwrap();
+        assert_eq!(merge.added.len(), 3);
+    }
+
+    #[test]
+    fn session_merge() {
+        let paints = series_paints();
+        let factory = MixedPaintFactory::<ArtPaintCharacteristics>::create(MixingModel::SRGB);
+        let grey = factory
+            .add_paint(
+                "grey",
+                vec![(paints[0].clone(), 1), (paints[1].clone(), 1)],
+                vec![],
+                None,
+            )
+            .unwrap();
+        factory
+            .add_paint("tint", vec![(paints[1].clone(), 2)], vec![(grey, 1)], None)
+            .unwrap();
+        let session = MixingSession::from_mixed_paints(&factory.get_paints(), "");
+
+        // another session with the same grey (made differently) and a new mix
+        let other_factory = MixedPaintFactory::<ArtPaintCharacteristics>::create(MixingModel::SRGB);
+        other_factory
+            .add_paint("dark", vec![(paints[0].clone(), 3)], vec![], None)
+            .unwrap();
+        let other_grey = other_factory
+            .add_paint(
+                "same grey",
+                vec![(paints[1].clone(), 1), (paints[0].clone(), 1)],
+                vec![],
+                None,
+            )
+            .unwrap();
+        other_factory
+            .add_paint(
+                "pale",
+                vec![(paints[1].clone(), 3)],
+                vec![(other_grey, 1)],
+                None,
+            )
+            .unwrap();
+        let other_session = MixingSession::from_mixed_paints(&other_factory.get_paints(), "");
+
+        let merge = other_session
+            .merge_into(
+                &paints,
+                &factory.get_paints(),
+                |notes, sp, mp, target, o_provenance| {
+                    factory.add_paint_with_provenance(notes, sp, mp, target, o_provenance)
+                },
+            )
+            .unwrap();
+        assert_eq!(merge.added.len(), 2);
+        assert_eq!(merge.skipped.len(), 1);
+        assert_eq!(factory.get_paints().len(), 4);
+        // "pale" is re-linked to the grey that was already present
+        assert!(merge.added[1].uses_mixed_paint(&factory.get_paints()[0]));
+
+        // merging the original session again adds nothing
+        let merge = session
+            .merge_into(
+                &paints,
+                &factory.get_paints(),
+                |notes, sp, mp, target, o_provenance| {
+                    factory.add_paint_with_provenance(notes, sp, mp, target, o_provenance)
+                },
+            )
+            .unwrap();
+        assert_eq!(merge.added.len(), 0);
+        assert_eq!(merge.skipped.len(), 2);
+    }
+}
@@ -0,0 +1,201 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+// Find the (integer) parts of a set of paints whose mixture comes closest
+// (by CIE76 delta E) to a target colour.
+
+use num::Integer;
+
+use crate::colour::*;
+use crate::colour_difference::delta_e;
+use crate::colour_mix::*;
+
+pub const DEFAULT_MAX_TOTAL_PARTS: u32 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SolverConfig {
+    pub max_total_parts: u32,
+    pub mixing_model: MixingModel,
+}
+
+impl Default for SolverConfig {
+    fn default() -> SolverConfig {
+        SolverConfig {
+            max_total_parts: DEFAULT_MAX_TOTAL_PARTS,
+            mixing_model: MixingModel::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Solution {
+    // In the same order as the colours given to the solver
+    pub parts: Vec<u32>,
+    pub colour: Colour,
+    pub delta_e: f64,
+}
+
+struct Solver<'a> {
+    colours: &'a [Colour],
+    target: RGB,
+    config: &'a SolverConfig,
+}
+
+impl<'a> Solver<'a> {
+    fn distance(&self, parts: &[u32]) -> Option<(Colour, f64)> {
+        let mut colour_mixer = ColourMixer::with_model(self.config.mixing_model);
+        for (colour, parts) in self.colours.iter().zip(parts.iter()) {
+            if *parts > 0 {
+                colour_mixer.add(colour, *parts);
+            }
+        }
+        let colour = colour_mixer.get_colour()?;
+        let distance = delta_e(&colour.rgb(), &self.target);
+        Some((colour, distance))
+    }
+
+    fn is_better(&self, parts: &[u32], best: f64) -> Option<f64> {
+        match self.distance(parts) {
+            Some((_, distance)) if distance < best - 1.0e-9 => Some(distance),
+            _ => None,
+        }
+    }
+
+    // Greedily add the part that helps most (noting the best mixture
+    // passed through on the way) then improve that by single part moves
+    // until none of them help.
+    fn solve(&self) -> Option<Solution> {
+        let n = self.colours.len();
+        if n == 0 || self.config.max_total_parts == 0 {
+            return None;
+        }
+        let mut parts = vec![0_u32; n];
+        let mut best_parts = parts.clone();
+        let mut best = std::f64::MAX;
+        for _ in 0..self.config.max_total_parts {
+            let mut step: Option<(usize, f64)> = None;
+            for i in 0..n {
+                parts[i] += 1;
+                if let Some((_, distance)) = self.distance(&parts) {
+                    if step.map_or(true, |(_, d)| distance < d) {
+                        step = Some((i, distance));
+                    }
+                }
+                parts[i] -= 1;
+            }
+            if let Some((i, distance)) = step {
+                parts[i] += 1;
+                if distance < best {
+                    best = distance;
+                    best_parts = parts.clone();
+                }
+            }
+        }
+
+        let mut parts = best_parts;
+        loop {
+            let total: u32 = parts.iter().sum();
+            let mut improved = false;
+            for i in 0..n {
+                for j in 0..n {
+                    if i == j || parts[j] == 0 {
+                        continue;
+                    }
+                    parts[i] += 1;
+                    parts[j] -= 1;
+                    if let Some(distance) = self.is_better(&parts, best) {
+                        best = distance;
+                        improved = true;
+                    } else {
+                        parts[i] -= 1;
+                        parts[j] += 1;
+                    }
+                }
+                if total < self.config.max_total_parts {
+                    parts[i] += 1;
+                    if let Some(distance) = self.is_better(&parts, best) {
+                        best = distance;
+                        improved = true;
+                        break;
+                    }
+                    parts[i] -= 1;
+                }
+                if parts[i] > 0 && total > 1 {
+                    parts[i] -= 1;
+                    if let Some(distance) = self.is_better(&parts, best) {
+                        best = distance;
+                        improved = true;
+                        break;
+                    }
+                    parts[i] += 1;
+                }
+            }
+            if !improved {
+                break;
+            }
+        }
+
+        let gcd = parts.iter().fold(0, |gcd, p| gcd.gcd(p));
+        if gcd > 1 {
+            for p in parts.iter_mut() {
+                *p /= gcd;
+            }
+        }
+        let (colour, delta_e) = self.distance(&parts)?;
+        Some(Solution {
+            parts,
+            colour,
+            delta_e,
+        })
+    }
+}
+
+pub fn solve_parts(colours: &[Colour], target: &Colour, config: &SolverConfig) -> Option<Solution> {
+    Solver {
+        colours,
+        target: target.rgb(),
+        config,
+    }
+    .solve()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn colour(rgb: [f64; 3]) -> Colour {
+        Colour::from(RGB::from(rgb))
+    }
+
+    #[test]
+    fn solver_finds_exact_mixtures() {
+        let config = SolverConfig::default();
+        let colours = vec![colour([0.0, 0.0, 0.0]), colour([1.0, 1.0, 1.0])];
+        let solution = solve_parts(&colours, &colour([0.5, 0.5, 0.5]), &config).unwrap();
+        assert_eq!(solution.parts, vec![1, 1]);
+        assert!(solution.delta_e < 1.0e-6);
+        let solution = solve_parts(&colours, &colour([0.75, 0.75, 0.75]), &config).unwrap();
+        assert_eq!(solution.parts, vec![1, 3]);
+        let solution = solve_parts(&colours, &colour([1.0, 1.0, 1.0]), &config).unwrap();
+        assert_eq!(solution.parts, vec![0, 1]);
+    }
+
+    #[test]
+    fn solver_respects_limits() {
+        let colours = vec![
+            colour([1.0, 0.0, 0.0]),
+            colour([0.0, 0.0, 1.0]),
+            colour([1.0, 1.0, 1.0]),
+        ];
+        let target = colour([0.6, 0.1, 0.35]);
+        for max_total_parts in 1..8 {
+            let config = SolverConfig {
+                max_total_parts,
+                ..SolverConfig::default()
+            };
+            let solution = solve_parts(&colours, &target, &config).unwrap();
+            let total = solution.parts.iter().sum::<u32>();
+            assert!(total > 0 && total <= max_total_parts);
+        }
+        assert!(solve_parts(&[], &target, &SolverConfig::default()).is_none());
+    }
+}
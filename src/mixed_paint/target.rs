@@ -11,18 +11,53 @@ use pw_gix::{
 };
 
 use colour_math_gtk::coloured::*;
+use serde_derive::*;
 
 use crate::app_name;
 use crate::basic_paint::*;
 use crate::colour::*;
 use crate::colour_edit::*;
+use crate::colour_names::notes_with_css_colour;
 use crate::dialogue::*;
 
+// Where a target colour came from so that recipes can be traced back
+// to their source specification
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum TargetProvenance {
+    Standard {
+        standard: String,
+        sponsor: String,
+        paint: String,
+    },
+    ImageSamples {
+        samples: Vec<SampleSource>,
+    },
+}
+
+impl TargetProvenance {
+    pub fn description(&self) -> String {
+        match self {
+            TargetProvenance::Standard {
+                standard,
+                sponsor,
+                paint,
+            } => format!("Standard: {} ({}, {})", paint, standard, sponsor),
+            TargetProvenance::ImageSamples { samples } => {
+                let sources: Vec<String> =
+                    samples.iter().map(|sample| sample.description()).collect();
+                format!("Image Samples: {}", sources.join("; "))
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct TargetColourCore {
     name: String,
     notes: String,
     colour: Colour,
+    provenance: Option<TargetProvenance>,
 }
 
 impl TargetColourCore {
@@ -34,6 +69,10 @@ impl TargetColourCore {
         self.notes.clone()
     }
 
+    pub fn provenance(&self) -> Option<TargetProvenance> {
+        self.provenance.clone()
+    }
+
     pub fn tooltip_text(&self) -> String {
         format!("{}: {}", self.name, self.notes)
     }
@@ -69,14 +108,30 @@ impl ColouredItemInterface for TargetColour {
 
 pub trait TargetColourInterface {
     fn create(colour: &Colour, name: &str, notes: &str) -> TargetColour;
+    fn create_with_provenance(
+        colour: &Colour,
+        name: &str,
+        notes: &str,
+        o_provenance: Option<TargetProvenance>,
+    ) -> TargetColour;
 }
 
 impl TargetColourInterface for TargetColour {
     fn create(colour: &Colour, name: &str, notes: &str) -> TargetColour {
+        TargetColour::create_with_provenance(colour, name, notes, None)
+    }
+
+    fn create_with_provenance(
+        colour: &Colour,
+        name: &str,
+        notes: &str,
+        o_provenance: Option<TargetProvenance>,
+    ) -> TargetColour {
         Rc::new(TargetColourCore {
             colour: colour.clone(),
             name: name.to_string(),
             notes: notes.to_string(),
+            provenance: o_provenance,
         })
     }
 }
@@ -118,7 +173,7 @@ where
         colour: &TargetColour,
         caller: &Rc<W>,
     ) -> TargetColourDisplayDialog<A> {
-        let dialog = new_display_dialog(&colour.name(), caller, &[]);
+        let dialog = new_display_dialog(&colour.name(), "target_colour_display", caller, &[]);
         dialog.set_size_from_recollections("target_colour_display", (60, 180));
         let content_area = dialog.get_content_area();
         let vbox = gtk::Box::new(gtk::Orientation::Vertical, 0);
@@ -128,6 +183,12 @@ where
         let label = gtk::Label::new(Some(colour.notes().as_str()));
         label.set_widget_colour(&colour.colour());
         vbox.pack_start(&label, true, true, 0);
+        if let Some(provenance) = colour.provenance() {
+            let label = gtk::Label::new(Some(provenance.description().as_str()));
+            label.set_widget_colour(&colour.colour());
+            label.set_line_wrap(true);
+            vbox.pack_start(&label, true, true, 0);
+        }
         content_area.pack_start(&vbox, true, true, 0);
         let cads = A::create();
         cads.set_colour(Some(&colour.colour()));
@@ -186,6 +247,21 @@ where
             colour_editor,
             notes,
         });
+        // a colour picked from the screen is probably from a reference image
+        let ntcd_c = ntcd.clone();
+        ntcd.colour_editor.connect_screen_colour_picked(move |rgb| {
+            if ntcd_c.notes.get_text().len() == 0 {
+                let notes = format!("Picked from screen ({})", rgb.into_css_hex());
+                ntcd_c.notes.set_text(&notes);
+            }
+        });
+
+        let ntcd_c = ntcd.clone();
+        ntcd.colour_editor.connect_named_colour_chosen(move |name| {
+            let notes = notes_with_css_colour(&ntcd_c.notes.get_text(), name);
+            ntcd_c.notes.set_text(&notes);
+        });
+
         let ntcd_c = ntcd.clone();
         ntcd.notes.connect_changed(move |entry| {
             ntcd_c.dialog.set_response_sensitive(
@@ -203,12 +279,26 @@ where
     A: ColourAttributesInterface,
 {
     pub fn get_new_target(&self) -> Option<(String, Colour)> {
+        self.get_new_target_with_provenance()
+            .map(|(notes, colour, _)| (notes, colour))
+    }
+
+    // The provenance records the image samples (if any) used for the colour
+    pub fn get_new_target_with_provenance(
+        &self,
+    ) -> Option<(String, Colour, Option<TargetProvenance>)> {
         if gtk::ResponseType::from(self.dialog.run()) == gtk::ResponseType::Ok {
             let notes = self.notes.get_text();
             if notes.len() > 0 {
                 let colour = self.colour_editor.get_colour();
+                let samples = self.colour_editor.sample_sources();
+                let o_provenance = if samples.len() > 0 {
+                    Some(TargetProvenance::ImageSamples { samples })
+                } else {
+                    None
+                };
                 unsafe { self.dialog.destroy() };
-                return Some((String::from(notes), colour));
+                return Some((String::from(notes), colour, o_provenance));
             }
         };
         unsafe { self.dialog.destroy() };
@@ -218,8 +308,46 @@ where
 
 #[cfg(test)]
 mod tests {
-    //use super::*;
+    use super::*;
+    use std::path::PathBuf;
 
     #[test]
-    fn it_works() {}
+    fn provenance_descriptions() {
+        let provenance = TargetProvenance::Standard {
+            standard: "FS 595C".to_string(),
+            sponsor: "US Government".to_string(),
+            paint: "34087".to_string(),
+        };
+        assert_eq!(
+            provenance.description(),
+            "Standard: 34087 (FS 595C, US Government)"
+        );
+        let provenance = TargetProvenance::ImageSamples {
+            samples: vec![
+                SampleSource {
+                    path: Some(PathBuf::from("/photos/tank.png")),
+                    x: 10.0,
+                    y: 20.4,
+                    width: 32,
+                    height: 16,
+                },
+                SampleSource {
+                    path: None,
+                    x: 0.0,
+                    y: 0.0,
+                    width: 8,
+                    height: 8,
+                },
+            ],
+        };
+        assert_eq!(
+            provenance.description(),
+            "Image Samples: /photos/tank.png (32x16 at 10, 20); clipboard (8x8 at 0, 0)"
+        );
+        let text = serde_json::to_string(&provenance).unwrap();
+        assert_eq!(
+            serde_json::from_str::<TargetProvenance>(&text).unwrap(),
+            provenance
+        );
+    }
 }
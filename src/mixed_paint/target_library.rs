@@ -0,0 +1,328 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+// A persistent library of named target colours (e.g. those needed for a
+// project) so that they can be reused as the target for new mixtures.
+
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::rc::Rc;
+
+use chrono::prelude::*;
+use serde_derive::*;
+
+use pw_gix::{
+    gdk,
+    glib::StaticType,
+    gtk::{self, prelude::*},
+    gtkx::dialog::*,
+    wrapper::*,
+};
+
+use crate::app_name;
+use crate::basic_paint::*;
+use crate::colour::*;
+
+use super::target::TargetProvenance;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedTarget {
+    pub name: String,
+    #[serde(default)]
+    pub notes: String,
+    pub rgb: String,
+    pub added: String,
+    #[serde(default)]
+    pub provenance: Option<TargetProvenance>,
+}
+
+impl SavedTarget {
+    pub fn new(
+        name: &str,
+        notes: &str,
+        colour: &Colour,
+        provenance: Option<TargetProvenance>,
+    ) -> SavedTarget {
+        SavedTarget {
+            name: name.to_string(),
+            notes: notes.to_string(),
+            rgb: colour.rgb().into_css_hex(),
+            added: Local::today().naive_local().format("%Y-%m-%d").to_string(),
+            provenance,
+        }
+    }
+
+    pub fn rgb(&self) -> RGB {
+        rgb_from_css_hex(&self.rgb).unwrap_or(RGB::BLACK)
+    }
+
+    pub fn colour(&self) -> Colour {
+        Colour::from(self.rgb())
+    }
+
+    pub fn tooltip_text(&self) -> String {
+        match self.provenance {
+            Some(ref provenance) => format!(
+                "{}\n{}\nAdded: {}",
+                self.notes,
+                provenance.description(),
+                self.added
+            ),
+            None => format!("{}\nAdded: {}", self.notes, self.added),
+        }
+    }
+
+    // Case insensitive search of the name and notes for each of the
+    // space separated words in "text"
+    pub fn matches_text(&self, text: &str) -> bool {
+        let name = self.name.to_lowercase();
+        let notes = self.notes.to_lowercase();
+        text.split_whitespace().all(|word| {
+            let word = word.to_lowercase();
+            name.contains(&word) || notes.contains(&word)
+        })
+    }
+}
+
+pub fn read_target_library(path: &Path) -> io::Result<Vec<SavedTarget>> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let mut file = File::open(path)?;
+    let mut text = String::new();
+    file.read_to_string(&mut text)?;
+    serde_json::from_str(&text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+pub fn write_target_library(path: &Path, targets: &[SavedTarget]) -> io::Result<()> {
+    let text = serde_json::to_string_pretty(targets)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    let mut file = File::create(path)?;
+    file.write_all(text.as_bytes())
+}
+
+// A target with the same name is replaced (rather than duplicated)
+pub fn add_saved_target(targets: &mut Vec<SavedTarget>, target: SavedTarget) {
+    match targets.iter().position(|t| t.name == target.name) {
+        Some(index) => targets[index] = target,
+        None => targets.push(target),
+    }
+}
+
+// Ask for the name and notes for a target being added to the library
+pub fn ask_saved_target_details<W: WidgetWrapper>(
+    default_name: &str,
+    caller: &W,
+) -> Option<(String, String)> {
+    let title = format!("{}: Save Target Colour", app_name());
+    let dialog = caller.new_dialog_with_buttons(
+        Some(&title),
+        gtk::DialogFlags::DESTROY_WITH_PARENT,
+        CANCEL_OK_BUTTONS,
+    );
+    let name_entry = gtk::Entry::new();
+    name_entry.set_text(default_name);
+    let notes_entry = gtk::Entry::new();
+    notes_entry.set_tooltip_text(Some("e.g. the project or part that needs this colour"));
+    let grid = gtk::Grid::new();
+    grid.set_row_spacing(2);
+    grid.set_column_spacing(4);
+    grid.attach(&gtk::Label::new(Some("Name:")), 0, 0, 1, 1);
+    grid.attach(&name_entry, 1, 0, 1, 1);
+    grid.attach(&gtk::Label::new(Some("Notes:")), 0, 1, 1, 1);
+    grid.attach(&notes_entry, 1, 1, 1, 1);
+    name_entry.set_hexpand(true);
+    let content_area = dialog.get_content_area();
+    content_area.pack_start(&grid, true, true, 0);
+    content_area.show_all();
+    let mut o_details: Option<(String, String)> = None;
+    if gtk::ResponseType::from(dialog.run()) == gtk::ResponseType::Ok {
+        let name = name_entry.get_text().trim().to_string();
+        if name.len() > 0 {
+            o_details = Some((name, notes_entry.get_text().trim().to_string()));
+        }
+    }
+    unsafe { dialog.destroy() };
+    o_details
+}
+
+const TL_INDEX: u32 = 0;
+const TL_RGB: u32 = 1;
+const TL_NAME: u32 = 2;
+const TL_NOTES: u32 = 3;
+const TL_TOOLTIP: u32 = 4;
+
+fn text_column(title: &str, col_id: u32) -> gtk::TreeViewColumn {
+    let col = gtk::TreeViewColumn::new();
+    col.set_title(title);
+    col.set_resizable(true);
+    let cell = gtk::CellRendererText::new();
+    col.pack_start(&cell, true);
+    col.add_attribute(&cell, "text", col_id as i32);
+    col
+}
+
+// Browse (and add to or prune) the library.  "o_current" is the current
+// target (if any) which may be saved.  Returns the chosen target (if any)
+// and whether the library was changed.
+pub fn manage_target_library<W: WidgetWrapper>(
+    targets: &mut Vec<SavedTarget>,
+    o_current: Option<(String, Colour, Option<TargetProvenance>)>,
+    caller: &W,
+) -> (Option<SavedTarget>, bool) {
+    let title = format!("{}: Target Colour Library", app_name());
+    let dialog = caller.new_dialog_with_buttons(
+        Some(&title),
+        gtk::DialogFlags::DESTROY_WITH_PARENT,
+        &[
+            ("Close", gtk::ResponseType::Cancel),
+            ("Use", gtk::ResponseType::Ok),
+        ],
+    );
+    dialog.set_size_from_recollections("target_library_dialog", (480, 360));
+    let save_btn = dialog.add_button("Save Current", gtk::ResponseType::Other(1));
+    save_btn.set_tooltip_text(Some("Add the current target colour to the library"));
+    save_btn.set_sensitive(o_current.is_some());
+    let delete_btn = dialog.add_button("Delete", gtk::ResponseType::Other(0));
+    delete_btn.set_tooltip_text(Some("Remove the selected target from the library"));
+    let search_entry = gtk::SearchEntry::new();
+    search_entry.set_tooltip_text(Some("Show only targets with matching names or notes"));
+    let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 2);
+    hbox.pack_start(&gtk::Label::new(Some("Search:")), false, false, 0);
+    hbox.pack_start(&search_entry, true, true, 0);
+
+    let list_store = gtk::ListStore::new(&[
+        u32::static_type(),
+        gdk::RGBA::static_type(),
+        String::static_type(),
+        String::static_type(),
+        String::static_type(),
+    ]);
+    let view = gtk::TreeView::with_model(&list_store);
+    view.set_headers_visible(true);
+    view.set_tooltip_column(TL_TOOLTIP as i32);
+    view.get_selection().set_mode(gtk::SelectionMode::Single);
+    view.append_column(&swatch_tv_column(TL_RGB as i32));
+    view.append_column(&text_column("Name", TL_NAME));
+    view.append_column(&text_column("Notes", TL_NOTES));
+    let adj: Option<&gtk::Adjustment> = None;
+    let scrolled_window = gtk::ScrolledWindow::new(adj, adj);
+    scrolled_window.add(&view);
+
+    let shown = Rc::new(RefCell::new(targets.clone()));
+    let refill = {
+        let shown = shown.clone();
+        let list_store = list_store.clone();
+        let search_entry = search_entry.clone();
+        Rc::new(move || {
+            list_store.clear();
+            let text = search_entry.get_text();
+            for (index, target) in shown.borrow().iter().enumerate() {
+                if !target.matches_text(&text) {
+                    continue;
+                }
+                list_store.insert_with_values(
+                    None,
+                    &[TL_INDEX, TL_RGB, TL_NAME, TL_NOTES, TL_TOOLTIP],
+                    &[
+                        &(index as u32),
+                        &target.rgb().into_gdk_rgba(),
+                        &target.name,
+                        &target.notes,
+                        &target.tooltip_text(),
+                    ],
+                );
+            }
+        })
+    };
+    refill();
+    let refill_c = refill.clone();
+    search_entry.connect_search_changed(move |_| refill_c());
+
+    let content_area = dialog.get_content_area();
+    content_area.pack_start(&hbox, false, false, 0);
+    content_area.pack_start(&scrolled_window, true, true, 0);
+    content_area.show_all();
+
+    let selected_index = || -> Option<usize> {
+        let (model, iter) = view.get_selection().get_selected()?;
+        model
+            .get_value(&iter, TL_INDEX as i32)
+            .get::<u32>()
+            .ok()
+            .and_then(|index| index)
+            .map(|index| index as usize)
+    };
+    let mut changed = false;
+    let mut o_chosen: Option<SavedTarget> = None;
+    loop {
+        match gtk::ResponseType::from(dialog.run()) {
+            gtk::ResponseType::Other(0) => {
+                if let Some(index) = selected_index() {
+                    let name = shown.borrow()[index].name.clone();
+                    let question = format!("Remove \"{}\" from the target library?", name);
+                    if caller.ask_confirm_action(&question, None) {
+                        shown.borrow_mut().remove(index);
+                        changed = true;
+                        refill();
+                    }
+                }
+            }
+            gtk::ResponseType::Other(1) => {
+                if let Some((ref name, ref colour, ref o_provenance)) = o_current {
+                    if let Some((name, notes)) = ask_saved_target_details(name, caller) {
+                        let target = SavedTarget::new(&name, &notes, colour, o_provenance.clone());
+                        add_saved_target(&mut shown.borrow_mut(), target);
+                        changed = true;
+                        refill();
+                    }
+                }
+            }
+            gtk::ResponseType::Ok => {
+                if let Some(index) = selected_index() {
+                    o_chosen = Some(shown.borrow()[index].clone());
+                    break;
+                }
+            }
+            _ => break,
+        }
+    }
+    unsafe { dialog.destroy() };
+    if changed {
+        *targets = shown.borrow().clone();
+    }
+    (o_chosen, changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn target_library_add_search_and_serialise() {
+        let mut targets: Vec<SavedTarget> = vec![];
+        let sky = Colour::from(rgb_from_css_hex("#80A0FF").unwrap());
+        add_saved_target(
+            &mut targets,
+            SavedTarget::new("Sky", "Project X backdrop", &sky, None),
+        );
+        add_saved_target(
+            &mut targets,
+            SavedTarget::new("Rust", "weathering", &Colour::from(RGB::RED), None),
+        );
+        add_saved_target(&mut targets, SavedTarget::new("Sky", "dawn", &sky, None));
+        assert_eq!(targets.len(), 2);
+        assert_eq!(targets[0].notes, "dawn");
+        assert!(targets[1].matches_text("WEATHER"));
+        assert!(!targets[1].matches_text("rust dawn"));
+        let text = serde_json::to_string(&targets).unwrap();
+        let read: Vec<SavedTarget> = serde_json::from_str(&text).unwrap();
+        assert_eq!(read, targets);
+        assert_eq!(read[0].rgb().into_css_hex(), "#80A0FF");
+        let read: Vec<SavedTarget> =
+            serde_json::from_str(r##"[{"name": "Old", "rgb": "#000000", "added": "2017-12-25"}]"##)
+                .unwrap();
+        assert_eq!(read[0].provenance, None);
+    }
+}
@@ -0,0 +1,277 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+// Randomly generated practice targets (that are known to be achievable
+// with the available paints) and scoring of the user's attempts at them.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use pw_gix::recollections::{recall, remember};
+
+use crate::basic_paint::*;
+use crate::colour::*;
+use crate::colour_difference::*;
+use crate::colour_mix::*;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PracticeDifficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Default for PracticeDifficulty {
+    fn default() -> PracticeDifficulty {
+        PracticeDifficulty::Easy
+    }
+}
+
+static PRACTICE_DIFFICULTY_VALUES: &[PracticeDifficulty] = &[
+    PracticeDifficulty::Easy,
+    PracticeDifficulty::Medium,
+    PracticeDifficulty::Hard,
+];
+
+impl PracticeDifficulty {
+    pub fn values() -> Vec<PracticeDifficulty> {
+        PRACTICE_DIFFICULTY_VALUES.to_vec()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            PracticeDifficulty::Easy => "easy",
+            PracticeDifficulty::Medium => "medium",
+            PracticeDifficulty::Hard => "hard",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match *self {
+            PracticeDifficulty::Easy => "Easy",
+            PracticeDifficulty::Medium => "Medium",
+            PracticeDifficulty::Hard => "Hard",
+        }
+    }
+
+    pub fn tooltip_text(&self) -> &'static str {
+        match *self {
+            PracticeDifficulty::Easy => "Targets mixed from two paints in simple proportions",
+            PracticeDifficulty::Medium => "Targets mixed from up to three paints",
+            PracticeDifficulty::Hard => "Targets mixed from up to four paints in fine proportions",
+        }
+    }
+
+    // The most paints used to mix a target
+    pub fn max_paints(&self) -> usize {
+        match *self {
+            PracticeDifficulty::Easy => 2,
+            PracticeDifficulty::Medium => 3,
+            PracticeDifficulty::Hard => 4,
+        }
+    }
+
+    // The most parts of any one paint used to mix a target
+    pub fn max_parts(&self) -> u32 {
+        match *self {
+            PracticeDifficulty::Easy => 3,
+            PracticeDifficulty::Medium => 6,
+            PracticeDifficulty::Hard => 12,
+        }
+    }
+
+    pub fn recall(key: &str) -> PracticeDifficulty {
+        if let Some(text) = recall(key) {
+            if let Ok(difficulty) = PracticeDifficulty::from_str(&text) {
+                return difficulty;
+            }
+        };
+        PracticeDifficulty::default()
+    }
+
+    pub fn remember(&self, key: &str) {
+        remember(key, self.name())
+    }
+}
+
+impl FromStr for PracticeDifficulty {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<PracticeDifficulty, String> {
+        for difficulty in PRACTICE_DIFFICULTY_VALUES.iter() {
+            if difficulty.name() == string {
+                return Ok(*difficulty);
+            }
+        }
+        Err(format!("{}: unknown practice difficulty", string))
+    }
+}
+
+impl fmt::Display for PracticeDifficulty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.label())
+    }
+}
+
+// A small (xorshift64*) generator is all that's needed to pick targets
+pub struct PracticeRng(u64);
+
+impl PracticeRng {
+    pub fn new(seed: u64) -> PracticeRng {
+        PracticeRng(seed.max(1))
+    }
+
+    pub fn from_time() -> PracticeRng {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos() as u64)
+            .unwrap_or(0x2545_F491_4F6C_DD1D);
+        PracticeRng::new(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    // A number in the range 0..n
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n.max(1) as u64) as usize
+    }
+}
+
+pub struct PracticeTarget<P> {
+    pub colour: Colour,
+    pub recipe: Vec<(P, u32)>,
+    pub difficulty: PracticeDifficulty,
+}
+
+impl<P: ColouredItemInterface> PracticeTarget<P> {
+    pub fn recipe_text<F: Fn(&P) -> String>(&self, name_for: F) -> String {
+        let items: Vec<String> = self
+            .recipe
+            .iter()
+            .map(|(paint, parts)| format!("{:3}: {}", parts, name_for(paint)))
+            .collect();
+        items.join("\n")
+    }
+}
+
+// Targets are real mixtures of the given paints so are always achievable
+pub fn generate_practice_target<P: ColouredItemInterface + Clone>(
+    paints: &[P],
+    difficulty: PracticeDifficulty,
+    model: MixingModel,
+    rng: &mut PracticeRng,
+) -> Option<PracticeTarget<P>> {
+    if paints.len() < 2 {
+        return None;
+    }
+    let max_paints = difficulty.max_paints().min(paints.len());
+    let n_paints = 2 + rng.below(max_paints - 1);
+    let mut indices: Vec<usize> = (0..paints.len()).collect();
+    let mut recipe: Vec<(P, u32)> = Vec::new();
+    let mut colour_mixer = ColourMixer::with_model(model);
+    for _ in 0..n_paints {
+        let index = indices.remove(rng.below(indices.len()));
+        let parts = 1 + rng.below(difficulty.max_parts() as usize) as u32;
+        colour_mixer.add(&paints[index].colour(), parts);
+        recipe.push((paints[index].clone(), parts));
+    }
+    let colour = colour_mixer.get_colour()?;
+    Some(PracticeTarget {
+        colour,
+        recipe,
+        difficulty,
+    })
+}
+
+// Mixtures this different (or more) from the target score nothing
+pub const PRACTICE_ZERO_SCORE_DELTA_E: f64 = 40.0;
+
+pub fn practice_score(target: &Colour, mixture: &Colour) -> u32 {
+    let delta_e = delta_e(&target.rgb(), &mixture.rgb());
+    (100.0 * (1.0 - delta_e / PRACTICE_ZERO_SCORE_DELTA_E).max(0.0)).round() as u32
+}
+
+pub fn practice_score_comment(score: u32) -> &'static str {
+    match score {
+        95..=100 => "Excellent: practically indistinguishable.",
+        85..=94 => "Very good: a close match.",
+        70..=84 => "Good: noticeably different side by side.",
+        50..=69 => "Fair: in the right neighbourhood.",
+        _ => "Keep practising.",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone)]
+    struct TestPaint(Colour);
+
+    impl ColouredItemInterface for TestPaint {
+        fn colour(&self) -> Colour {
+            self.0.clone()
+        }
+    }
+
+    fn paints() -> Vec<TestPaint> {
+        vec![
+            TestPaint(Colour::from(RGB::RED)),
+            TestPaint(Colour::from(RGB::from([1.0, 1.0, 0.0]))),
+            TestPaint(Colour::from(RGB::BLUE)),
+            TestPaint(Colour::from(RGB::WHITE)),
+            TestPaint(Colour::from(RGB::BLACK)),
+        ]
+    }
+
+    #[test]
+    fn practice_targets_are_mixtures() {
+        let paints = paints();
+        let mut rng = PracticeRng::new(42);
+        for difficulty in PracticeDifficulty::values() {
+            for _ in 0..20 {
+                let target =
+                    generate_practice_target(&paints, difficulty, MixingModel::SRGB, &mut rng)
+                        .unwrap();
+                assert!(target.recipe.len() >= 2);
+                assert!(target.recipe.len() <= difficulty.max_paints());
+                let mut colour_mixer = ColourMixer::new();
+                for (paint, parts) in target.recipe.iter() {
+                    assert!(*parts >= 1 && *parts <= difficulty.max_parts());
+                    colour_mixer.add(&paint.0, *parts);
+                }
+                assert_eq!(
+                    colour_mixer.get_colour().unwrap().rgb(),
+                    target.colour.rgb()
+                );
+            }
+        }
+        assert!(generate_practice_target(
+            &paints[..1],
+            PracticeDifficulty::Easy,
+            MixingModel::SRGB,
+            &mut rng
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn practice_scores() {
+        let red = Colour::from(RGB::RED);
+        assert_eq!(practice_score(&red, &red), 100);
+        assert_eq!(practice_score(&red, &Colour::from(RGB::BLUE)), 0);
+        let nearly_red = Colour::from(RGB::from([0.95, 0.0, 0.0]));
+        assert!(practice_score(&red, &nearly_red) > 85);
+        for difficulty in PracticeDifficulty::values() {
+            assert_eq!(
+                PracticeDifficulty::from_str(difficulty.name()),
+                Ok(difficulty)
+            );
+        }
+    }
+}
@@ -5,6 +5,8 @@ use std::fmt;
 use std::rc::Rc;
 use std::str::FromStr;
 
+use serde_derive::*;
+
 use pw_gix::{
     glib,
     gtk::{self, prelude::*},
@@ -28,19 +30,22 @@ pub use crate::basic_paint::entry::*;
 pub use crate::mixed_paint::mixer::*;
 pub use crate::struct_traits::SimpleCreation;
 
-#[derive(Debug, PartialEq, Hash, Clone, Copy)]
+#[derive(Debug, PartialEq, Hash, Clone, Copy, Serialize, Deserialize)]
 pub struct ModelPaintCharacteristics {
     pub finish: Finish,
     pub transparency: Transparency,
     pub fluorescence: Fluorescence,
     pub metallic: Metallic,
+    // NB: optional so it's only written when it's specified
+    #[serde(default)]
+    pub drying_time: DryingTime,
 }
 
 impl CharacteristicsInterface for ModelPaintCharacteristics {
     type Entry = ModelPaintCharacteristicsEntryCore;
 
     fn tv_row_len() -> usize {
-        4
+        5
     }
 
     fn tv_columns(start_col_id: i32) -> Vec<gtk::TreeViewColumn> {
@@ -82,6 +87,15 @@ impl CharacteristicsInterface for ModelPaintCharacteristics {
             cfw,
             false,
         ));
+        cols.push(simple_text_column(
+            "Dr.",
+            start_col_id + 4,
+            start_col_id + 4,
+            6,
+            7,
+            cfw,
+            false,
+        ));
         cols
     }
 
@@ -91,6 +105,7 @@ impl CharacteristicsInterface for ModelPaintCharacteristics {
             transparency: Transparency::from(floats[1]),
             fluorescence: Fluorescence::from(floats[2]),
             metallic: Metallic::from(floats[3]),
+            drying_time: DryingTime::from(floats[4]),
         }
     }
 
@@ -100,6 +115,11 @@ impl CharacteristicsInterface for ModelPaintCharacteristics {
         rows.push(self.transparency.abbrev().to_value());
         rows.push(self.metallic.abbrev().to_value());
         rows.push(self.fluorescence.abbrev().to_value());
+        if self.drying_time.is_specified() {
+            rows.push(self.drying_time.abbrev().to_value());
+        } else {
+            rows.push("".to_value());
+        }
         rows
     }
 
@@ -113,6 +133,11 @@ impl CharacteristicsInterface for ModelPaintCharacteristics {
         vbox.pack_start(&label, false, false, 1);
         let label = gtk::Label::new(Some(self.metallic.description()));
         vbox.pack_start(&label, false, false, 1);
+        if self.drying_time.is_specified() {
+            let text = format!("Drying: {}", self.drying_time.description());
+            let label = gtk::Label::new(Some(text.as_str()));
+            vbox.pack_start(&label, false, false, 1);
+        }
         vbox.show_all();
         vbox
     }
@@ -123,6 +148,7 @@ impl CharacteristicsInterface for ModelPaintCharacteristics {
             self.transparency.into(),
             self.fluorescence.into(),
             self.metallic.into(),
+            self.drying_time.into(),
         ]
     }
 
@@ -141,13 +167,28 @@ impl CharacteristicsInterface for ModelPaintCharacteristics {
             Ok(mc) => mc,
             Err(_) => Metallic::Nonmetallic,
         };
+        let drying_time = DryingTime::from_str(string).unwrap_or_default();
         Ok(ModelPaintCharacteristics {
             finish,
             transparency,
             fluorescence,
             metallic,
+            drying_time,
         })
     }
+
+    fn paint_types() -> Vec<&'static str> {
+        vec!["ModelPaint", "NamedColour"]
+    }
+
+    fn legacy_assumptions() -> Vec<(&'static str, &'static str)> {
+        vec![
+            ("finish", "G"),
+            ("transparency", "O"),
+            ("fluorescence", "NF"),
+            ("metallic", "NM"),
+        ]
+    }
 }
 
 impl fmt::Display for ModelPaintCharacteristics {
@@ -159,7 +200,11 @@ impl fmt::Display for ModelPaintCharacteristics {
             self.transparency.to_string(),
             self.fluorescence.to_string(),
             self.metallic.to_string(),
-        )
+        )?;
+        if self.drying_time.is_specified() {
+            write!(f, ", {}", self.drying_time.to_string())?;
+        }
+        Ok(())
     }
 }
 
@@ -169,6 +214,7 @@ pub struct ModelPaintCharacteristicsEntryCore {
     transparency_entry: TransparencyEntry,
     fluorescence_entry: FluorescenceEntry,
     metallic_entry: MetallicEntry,
+    drying_time_entry: DryingTimeEntry,
     changed_callbacks: RefCell<Vec<Box<dyn Fn()>>>,
 }
 
@@ -190,6 +236,7 @@ impl CharacteristicsEntryInterface<ModelPaintCharacteristics>
             transparency_entry: TransparencyEntry::create(),
             fluorescence_entry: FluorescenceEntry::create(),
             metallic_entry: MetallicEntry::create(),
+            drying_time_entry: DryingTimeEntry::create(),
             changed_callbacks: RefCell::new(Vec::new()),
         });
         let cei_c = cei.clone();
@@ -208,10 +255,15 @@ impl CharacteristicsEntryInterface<ModelPaintCharacteristics>
         cei.metallic_entry
             .combo_box_text()
             .connect_changed(move |_| cei_c.inform_changed());
+        let cei_c = cei.clone();
+        cei.drying_time_entry
+            .combo_box_text()
+            .connect_changed(move |_| cei_c.inform_changed());
         cei.finish_entry.combo_box_text().set_hexpand(true);
         cei.transparency_entry.combo_box_text().set_hexpand(true);
         cei.fluorescence_entry.combo_box_text().set_hexpand(true);
         cei.metallic_entry.combo_box_text().set_hexpand(true);
+        cei.drying_time_entry.combo_box_text().set_hexpand(true);
         let label = gtk::Label::new(Some(Finish::prompt().as_str()));
         label.set_halign(gtk::Align::End);
         cei.grid.attach(&label, 0, 0, 1, 1);
@@ -252,6 +304,16 @@ impl CharacteristicsEntryInterface<ModelPaintCharacteristics>
             1,
             1,
         );
+        let label = gtk::Label::new(Some(DryingTime::prompt().as_str()));
+        label.set_halign(gtk::Align::End);
+        cei.grid.attach(&label, 0, 4, 1, 1);
+        cei.grid.attach_next_to(
+            &cei.drying_time_entry.combo_box_text(),
+            Some(&label),
+            gtk::PositionType::Right,
+            1,
+            1,
+        );
 
         cei.grid.show_all();
         cei
@@ -282,11 +344,13 @@ impl CharacteristicsEntryInterface<ModelPaintCharacteristics>
         } else {
             return None;
         };
+        let drying_time = self.drying_time_entry.get_value().unwrap_or_default();
         Some(ModelPaintCharacteristics {
             finish,
             transparency,
             fluorescence,
             metallic,
+            drying_time,
         })
     }
 
@@ -299,11 +363,14 @@ impl CharacteristicsEntryInterface<ModelPaintCharacteristics>
                 .set_value(Some(characteristics.fluorescence));
             self.metallic_entry
                 .set_value(Some(characteristics.metallic));
+            self.drying_time_entry
+                .set_value(Some(characteristics.drying_time));
         } else {
             self.finish_entry.set_value(None);
             self.transparency_entry.set_value(None);
             self.fluorescence_entry.set_value(None);
             self.metallic_entry.set_value(None);
+            self.drying_time_entry.set_value(None);
         }
     }
 
@@ -405,6 +472,10 @@ impl MixerConfig for ModelPaintMixerConfig {
     fn mixing_mode() -> MixingMode {
         MixingMode::MatchTarget
     }
+
+    fn recollection_name_for(item_name: &str) -> String {
+        format!("model_paint_mixer::{}", item_name)
+    }
 }
 
 pub type ModelSeriesPaint = SeriesPaint<ModelPaintCharacteristics>;
@@ -480,6 +551,29 @@ NamedColour(name=\"XF 4: Yellow Green *\", rgb=RGB(0xAA00, 0xAE00, 0x4000), tran
         }
     }
 
+    #[test]
+    fn paint_model_paint_drying_time() {
+        let test_str = r#"ModelPaint(name="XF 1: Flat Black *", rgb=RGB16(red=0x2D00, green=0x2B00, blue=0x3000), transparency="O", finish="F", metallic="NM", fluorescence="NF", drying_time="S", notes="")"#;
+        let spec = ModelSeriesPaintSpec::from_str(test_str).unwrap();
+        assert_eq!(spec.characteristics.drying_time, DryingTime::Slow);
+        let text = spec.characteristics.to_string();
+        assert!(text.ends_with(", drying_time=\"S\""));
+        assert_eq!(
+            ModelPaintCharacteristics::from_str(&text).unwrap(),
+            spec.characteristics
+        );
+        let floats = spec.characteristics.to_floats();
+        assert_eq!(
+            ModelPaintCharacteristics::from_floats(&floats),
+            spec.characteristics
+        );
+
+        let test_str = r#"ModelPaint(name="XF 1: Flat Black *", rgb=RGB16(red=0x2D00, green=0x2B00, blue=0x3000), transparency="O", finish="F", metallic="NM", fluorescence="NF", notes="")"#;
+        let spec = ModelSeriesPaintSpec::from_str(test_str).unwrap();
+        assert_eq!(spec.characteristics.drying_time, DryingTime::Unspecified);
+        assert!(!spec.characteristics.to_string().contains("drying_time"));
+    }
+
     #[test]
     fn paint_model_paint_obsolete() {
         let test_str = r#"NamedColour(name="XF 2: Flat White *", rgb=RGB16(0xF800, 0xFA00, 0xF600), transparency="O", finish="F")"#.to_string();
@@ -0,0 +1,252 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+// A run time registry of the paint types available to an application so
+// that those defined outside this crate can be offered alongside the
+// compiled in art and model paint types.
+
+use std::cell::RefCell;
+use std::path::Path;
+use std::rc::Rc;
+
+use pw_gix::{
+    gtk::{self, prelude::*},
+    wrapper::*,
+};
+
+use crate::art_paint::*;
+use crate::basic_paint::*;
+use crate::mixed_paint::mixer::*;
+use crate::model_paint::*;
+use crate::series_paint::*;
+use crate::struct_traits::SimpleCreation;
+
+pub trait PaintTypeBundle: 'static {
+    type Attributes: ColourAttributesInterface + 'static;
+    type Characteristics: CharacteristicsInterface + 'static;
+    type MixerConfig: MixerConfig + 'static;
+
+    // Must be unique within the registry
+    fn type_id() -> &'static str;
+    fn type_label() -> String;
+
+    fn type_description() -> String {
+        String::new()
+    }
+
+    fn series_label() -> String {
+        "Series".to_string()
+    }
+}
+
+#[derive(Clone)]
+pub struct PaintTypeDefn {
+    pub type_id: &'static str,
+    pub label: String,
+    pub description: String,
+    pub series_label: String,
+    pub create_series_editor: Rc<dyn Fn() -> gtk::Widget>,
+    pub create_mixer: Rc<dyn Fn(&Path, Option<&Path>) -> gtk::Widget>,
+}
+
+impl PaintTypeDefn {
+    pub fn from_bundle<B: PaintTypeBundle>() -> PaintTypeDefn {
+        PaintTypeDefn {
+            type_id: B::type_id(),
+            label: B::type_label(),
+            description: B::type_description(),
+            series_label: B::series_label(),
+            create_series_editor: Rc::new(|| {
+                SeriesPaintEditor::<B::Attributes, B::Characteristics>::create()
+                    .pwo()
+                    .upcast::<gtk::Widget>()
+            }),
+            create_mixer: Rc::new(|series_data_path, standards_data_path| {
+                PaintMixer::<B::Attributes, B::Characteristics, B::MixerConfig>::create(
+                    series_data_path,
+                    standards_data_path,
+                )
+                .pwo()
+                .upcast::<gtk::Widget>()
+            }),
+        }
+    }
+}
+
+impl std::fmt::Debug for PaintTypeDefn {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("PaintTypeDefn")
+            .field("type_id", &self.type_id)
+            .field("label", &self.label)
+            .finish()
+    }
+}
+
+pub struct ArtPaintType;
+
+impl PaintTypeBundle for ArtPaintType {
+    type Attributes = ArtPaintAttributes;
+    type Characteristics = ArtPaintCharacteristics;
+    type MixerConfig = ArtPaintMixerConfig;
+
+    fn type_id() -> &'static str {
+        "art_paint"
+    }
+
+    fn type_label() -> String {
+        "Art Paint".to_string()
+    }
+
+    fn type_description() -> String {
+        "Artists' paints with permanence and transparency".to_string()
+    }
+}
+
+pub struct ModelPaintType;
+
+impl PaintTypeBundle for ModelPaintType {
+    type Attributes = ModelPaintAttributes;
+    type Characteristics = ModelPaintCharacteristics;
+    type MixerConfig = ModelPaintMixerConfig;
+
+    fn type_id() -> &'static str {
+        "model_paint"
+    }
+
+    fn type_label() -> String {
+        "Model Paint".to_string()
+    }
+
+    fn type_description() -> String {
+        "Modellers' paints with finish, transparency, fluorescence, metallic and (optionally) drying time".to_string()
+    }
+}
+
+// GTK objects aren't thread safe so each (GUI) thread has its own registry
+thread_local! {
+    static PAINT_TYPE_REGISTRY: RefCell<Vec<PaintTypeDefn>> = RefCell::new(vec![
+        PaintTypeDefn::from_bundle::<ArtPaintType>(),
+        PaintTypeDefn::from_bundle::<ModelPaintType>(),
+    ]);
+}
+
+// Returns false (and leaves the registry unchanged) if the type's id is
+// already in use.
+pub fn register_paint_type<B: PaintTypeBundle>() -> bool {
+    register_paint_type_defn(PaintTypeDefn::from_bundle::<B>())
+}
+
+pub fn register_paint_type_defn(defn: PaintTypeDefn) -> bool {
+    PAINT_TYPE_REGISTRY.with(|registry| {
+        let mut registry = registry.borrow_mut();
+        if registry.iter().any(|d| d.type_id == defn.type_id) {
+            false
+        } else {
+            registry.push(defn);
+            true
+        }
+    })
+}
+
+pub fn registered_paint_types() -> Vec<PaintTypeDefn> {
+    PAINT_TYPE_REGISTRY.with(|registry| registry.borrow().clone())
+}
+
+pub fn registered_paint_type(type_id: &str) -> Option<PaintTypeDefn> {
+    PAINT_TYPE_REGISTRY.with(|registry| {
+        registry
+            .borrow()
+            .iter()
+            .find(|defn| defn.type_id == type_id)
+            .cloned()
+    })
+}
+
+// PAINT TYPE PICKER
+// For use in "new collection" dialogs etc.
+#[derive(PWO, Wrapper)]
+pub struct PaintTypePickerCore {
+    combo: gtk::ComboBoxText,
+}
+
+impl PaintTypePickerCore {
+    // Pick up types registered since creation
+    pub fn update(&self) {
+        let selected = self.combo.get_active_id();
+        self.combo.remove_all();
+        for defn in registered_paint_types().iter() {
+            self.combo.append(Some(defn.type_id), &defn.label);
+        }
+        if let Some(id) = selected {
+            self.combo.set_active_id(Some(&id));
+        }
+        if self.combo.get_active_id().is_none() {
+            self.combo.set_active(Some(0));
+        }
+    }
+
+    pub fn selected_type(&self) -> Option<PaintTypeDefn> {
+        let id = self.combo.get_active_id()?;
+        registered_paint_type(&id)
+    }
+
+    pub fn set_selected_type(&self, type_id: &str) -> bool {
+        self.combo.set_active_id(Some(type_id))
+    }
+
+    pub fn connect_changed<F: 'static + Fn(Option<PaintTypeDefn>)>(&self, callback: F) {
+        self.combo
+            .connect_changed(move |combo| match combo.get_active_id() {
+                Some(id) => callback(registered_paint_type(&id)),
+                None => callback(None),
+            });
+    }
+}
+
+pub type PaintTypePicker = Rc<PaintTypePickerCore>;
+
+impl SimpleCreation for PaintTypePicker {
+    fn create() -> PaintTypePicker {
+        let ptp = Rc::new(PaintTypePickerCore {
+            combo: gtk::ComboBoxText::new(),
+        });
+        ptp.combo
+            .set_tooltip_text(Some("The type of paint in the new collection"));
+        ptp.update();
+        ptp
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct ExtraPaintType;
+
+    impl PaintTypeBundle for ExtraPaintType {
+        type Attributes = ArtPaintAttributes;
+        type Characteristics = ArtPaintCharacteristics;
+        type MixerConfig = ArtPaintMixerConfig;
+
+        fn type_id() -> &'static str {
+            "extra_paint"
+        }
+
+        fn type_label() -> String {
+            "Extra Paint".to_string()
+        }
+    }
+
+    #[test]
+    fn paint_type_registry() {
+        let ids: Vec<&str> = registered_paint_types().iter().map(|d| d.type_id).collect();
+        assert_eq!(ids, vec!["art_paint", "model_paint"]);
+        assert!(register_paint_type::<ExtraPaintType>());
+        assert!(!register_paint_type::<ExtraPaintType>());
+        assert!(!register_paint_type::<ArtPaintType>());
+        assert_eq!(registered_paint_types().len(), 3);
+        let defn = registered_paint_type("extra_paint").unwrap();
+        assert_eq!(defn.label, "Extra Paint");
+        assert_eq!(defn.series_label, "Series");
+        assert!(registered_paint_type("no_such_paint").is_none());
+    }
+}
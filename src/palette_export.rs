@@ -0,0 +1,91 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+// Exporting paints as GIMP palette (.gpl) files (which Inkscape and
+// Krita also read) for use in digital mock-ups.
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+use pw_gix::{
+    recollections::{recall, remember},
+    wrapper::*,
+};
+
+use crate::colour::*;
+
+const PALETTE_FILE_RECOLLECTION: &str = "palette_export::last_file";
+
+// Names are one line (the rest of the colour's line) in the format
+fn single_line(text: &str) -> String {
+    text.split(|c| c == '\n' || c == '\r')
+        .filter(|part| part.len() > 0)
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+pub fn gpl_palette_text(palette_name: &str, entries: &[(String, RGB)]) -> String {
+    let mut text = format!(
+        "GIMP Palette\nName: {}\nColumns: 0\n#\n",
+        single_line(palette_name)
+    );
+    for (name, rgb) in entries.iter() {
+        let (red, green, blue, _) = rgb.into_u8_rgba();
+        text += &format!("{:3} {:3} {:3}\t{}\n", red, green, blue, single_line(name));
+    }
+    text
+}
+
+pub fn write_gpl_palette(
+    path: &Path,
+    palette_name: &str,
+    entries: &[(String, RGB)],
+) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(gpl_palette_text(palette_name, entries).as_bytes())
+}
+
+// Ask where to save the palette and have "write" write it there
+pub fn export_gpl_palette<W, F>(caller: &W, write: F)
+where
+    W: WidgetWrapper,
+    F: Fn(&Path) -> io::Result<()>,
+{
+    let o_last_file = recall(PALETTE_FILE_RECOLLECTION);
+    let last_file = if let Some(ref text) = o_last_file {
+        Some(text.as_str())
+    } else {
+        None
+    };
+    if let Some(path) = caller.ask_file_path(Some("GIMP Palette File (.gpl):"), last_file, false) {
+        let path = if path.extension().is_none() {
+            path.with_extension("gpl")
+        } else {
+            path
+        };
+        match write(&path) {
+            Ok(()) => remember(PALETTE_FILE_RECOLLECTION, &path.to_string_lossy()),
+            Err(err) => caller.report_error("Failed to export palette", &err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gpl_palette_format() {
+        let entries = vec![
+            ("Red".to_string(), RGB::RED),
+            ("Two\nLines".to_string(), RGB::from([0.5, 0.5, 0.5])),
+        ];
+        let text = gpl_palette_text("Tamiya (Flat Acrylic)", &entries);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "GIMP Palette");
+        assert_eq!(lines[1], "Name: Tamiya (Flat Acrylic)");
+        assert_eq!(lines[4], "255   0   0\tRed");
+        assert_eq!(lines[5], "128 128 128\tTwo Lines");
+        assert_eq!(lines.len(), 6);
+    }
+}
@@ -0,0 +1,174 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+// Most recently used (MRU) file lists remembered between sessions.  Each
+// list is identified by its recollection key (e.g. one per collection type).
+
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use pw_gix::{
+    gdk,
+    gtk::{self, prelude::*},
+    recollections::{recall, remember},
+};
+
+pub const DEFAULT_RECENT_FILES_MAX_LENGTH: usize = 10;
+
+const RECENT_FILES_MAX_LENGTH_RECOLLECTION: &str = "recent_files::max_length";
+// Paths don't contain control characters so this can't clash with them
+const RECENT_FILES_SEPARATOR: char = '\u{1e}';
+
+pub fn recent_files_max_length() -> usize {
+    recall(RECENT_FILES_MAX_LENGTH_RECOLLECTION)
+        .and_then(|text| text.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_RECENT_FILES_MAX_LENGTH)
+}
+
+// NB: existing lists are trimmed the next time that they're read or updated
+pub fn set_recent_files_max_length(max_length: usize) {
+    remember(
+        RECENT_FILES_MAX_LENGTH_RECOLLECTION,
+        &max_length.to_string(),
+    );
+}
+
+pub fn recent_files_from_text(text: &str, max_length: usize) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = vec![];
+    for item in text
+        .split(RECENT_FILES_SEPARATOR)
+        .filter(|item| item.len() > 0)
+    {
+        let path = PathBuf::from(item);
+        if !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+    paths.truncate(max_length);
+    paths
+}
+
+pub fn recent_files_text(paths: &[PathBuf]) -> String {
+    let items: Vec<String> = paths
+        .iter()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    items.join(&RECENT_FILES_SEPARATOR.to_string())
+}
+
+// Put "path" at the front of the list (removing any earlier occurrence)
+pub fn with_recent_file(paths: &[PathBuf], path: &Path, max_length: usize) -> Vec<PathBuf> {
+    let mut new_paths = vec![path.to_path_buf()];
+    for p in paths.iter().filter(|p| p.as_path() != path) {
+        new_paths.push(p.clone());
+    }
+    new_paths.truncate(max_length);
+    new_paths
+}
+
+// Most recent first
+pub fn recent_files(key: &str) -> Vec<PathBuf> {
+    match recall(key) {
+        Some(text) => recent_files_from_text(&text, recent_files_max_length()),
+        None => vec![],
+    }
+}
+
+fn remember_recent_files(key: &str, paths: &[PathBuf]) {
+    remember(key, &recent_files_text(paths));
+}
+
+pub fn add_recent_file(key: &str, path: &Path) {
+    let paths = with_recent_file(&recent_files(key), path, recent_files_max_length());
+    remember_recent_files(key, &paths);
+}
+
+pub fn remove_recent_file(key: &str, path: &Path) {
+    let paths: Vec<PathBuf> = recent_files(key)
+        .into_iter()
+        .filter(|p| p.as_path() != path)
+        .collect();
+    remember_recent_files(key, &paths);
+}
+
+pub fn clear_recent_files(key: &str) {
+    remember(key, "");
+}
+
+// A button that pops up the (current) list of recent files for "key" and
+// passes the chosen one to "callback".
+pub fn recent_files_button<F: Fn(&Path) + 'static>(
+    key: &str,
+    tooltip_text: &str,
+    callback: F,
+) -> gtk::Button {
+    let button = gtk::Button::new();
+    button.set_image(Some(&gtk::Image::from_icon_name(
+        Some("document-open-recent"),
+        gtk::IconSize::Button,
+    )));
+    button.set_tooltip_text(Some(tooltip_text));
+    let menu = gtk::Menu::new();
+    let key = key.to_string();
+    let callback = Rc::new(callback);
+    button.connect_clicked(move |button| {
+        for child in menu.get_children().iter() {
+            menu.remove(child);
+        }
+        let paths = recent_files(&key);
+        for path in paths.iter() {
+            let label = match path.file_name() {
+                Some(file_name) => file_name.to_string_lossy().to_string(),
+                None => path.to_string_lossy().to_string(),
+            };
+            let item = gtk::MenuItem::with_label(&label);
+            item.set_tooltip_text(Some(&path.to_string_lossy()));
+            let callback_c = callback.clone();
+            let path_c = path.clone();
+            item.connect_activate(move |_| callback_c(&path_c));
+            menu.append(&item);
+        }
+        if paths.len() == 0 {
+            let item = gtk::MenuItem::with_label("(No Recent Files)");
+            item.set_sensitive(false);
+            menu.append(&item);
+        }
+        menu.append(&gtk::SeparatorMenuItem::new());
+        let item = gtk::MenuItem::with_label("Clear Recent Files");
+        item.set_sensitive(paths.len() > 0);
+        let key_c = key.clone();
+        item.connect_activate(move |_| clear_recent_files(&key_c));
+        menu.append(&item);
+        menu.show_all();
+        menu.popup_at_widget(
+            button,
+            gdk::Gravity::SouthWest,
+            gdk::Gravity::NorthWest,
+            None,
+        );
+    });
+    button
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recent_file_lists() {
+        let paths = vec![PathBuf::from("/a/b.epc"), PathBuf::from("/c/d.epc")];
+        let text = recent_files_text(&paths);
+        assert_eq!(recent_files_from_text(&text, 10), paths);
+        assert_eq!(recent_files_from_text(&text, 1), paths[..1].to_vec());
+        assert_eq!(recent_files_from_text("", 10), Vec::<PathBuf>::new());
+        let paths = with_recent_file(&paths, Path::new("/c/d.epc"), 10);
+        assert_eq!(
+            paths,
+            vec![PathBuf::from("/c/d.epc"), PathBuf::from("/a/b.epc")]
+        );
+        let paths = with_recent_file(&paths, Path::new("/e/f.epc"), 2);
+        assert_eq!(
+            paths,
+            vec![PathBuf::from("/e/f.epc"), PathBuf::from("/c/d.epc")]
+        );
+    }
+}
@@ -0,0 +1,207 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+// An eyedropper that takes the colour of a pixel (or the average colour
+// of a small square of pixels) from anywhere on the screen e.g. from a
+// reference image displayed by another application.
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use pw_gix::{
+    gdk, glib,
+    gtk::{self, prelude::*},
+    recollections::{recall, remember},
+    wrapper::*,
+};
+
+use crate::cairox::*;
+use crate::colour::*;
+use crate::colour_difference::colour_spread;
+use crate::struct_traits::SimpleCreation;
+
+pub const SAMPLE_SIDES: &[i32] = &[1, 3, 5, 9, 15];
+
+const SAMPLE_SIDE_RECOLLECTION: &str = "screen_picker::sample_side";
+
+// The (x, y, width, height) of the square of (at most) "side" pixels
+// centred on "centre" and clipped to a screen of "screen_size"
+pub fn screen_sample_rect(
+    centre: (i32, i32),
+    side: i32,
+    screen_size: (i32, i32),
+) -> Option<(i32, i32, i32, i32)> {
+    let half = side.max(1) / 2;
+    let x0 = (centre.0 - half).max(0);
+    let y0 = (centre.1 - half).max(0);
+    let x1 = (centre.0 + half + 1).min(screen_size.0);
+    let y1 = (centre.1 + half + 1).min(screen_size.1);
+    if x1 > x0 && y1 > y0 {
+        Some((x0, y0, x1 - x0, y1 - y0))
+    } else {
+        None
+    }
+}
+
+// Wait (with a crosshair pointer) for the user to click somewhere on the
+// screen and return the position in root window coordinates.  Escape or
+// any button other than the first cancels.
+fn wait_for_screen_click() -> Option<(i32, i32)> {
+    let display = gdk::Display::get_default()?;
+    let seat = display.get_default_seat()?;
+    // an off screen window to receive the events while the pointer is grabbed
+    let window = gtk::Window::new(gtk::WindowType::Popup);
+    window.move_(-100, -100);
+    window.resize(1, 1);
+    window.add_events(gdk::EventMask::BUTTON_PRESS_MASK | gdk::EventMask::KEY_PRESS_MASK);
+    window.show();
+    let gdk_window = window.get_window()?;
+    let cursor = gdk::Cursor::new_for_display(&display, gdk::CursorType::Crosshair);
+    let status = seat.grab(
+        &gdk_window,
+        gdk::SeatCapabilities::ALL,
+        false,
+        Some(&cursor),
+        None,
+        None,
+    );
+    if status != gdk::GrabStatus::Success {
+        unsafe { window.destroy() };
+        return None;
+    }
+    let main_loop = glib::MainLoop::new(None, false);
+    let position: Rc<Cell<Option<(i32, i32)>>> = Rc::new(Cell::new(None));
+
+    let main_loop_c = main_loop.clone();
+    let position_c = position.clone();
+    window.connect_button_press_event(move |_, event| {
+        if event.get_button() == 1 {
+            let (x, y) = event.get_root();
+            position_c.set(Some((x as i32, y as i32)));
+        }
+        main_loop_c.quit();
+        Inhibit(true)
+    });
+    let main_loop_c = main_loop.clone();
+    window.connect_key_press_event(move |_, event| {
+        if event.get_keyval() == gdk::keys::constants::Escape {
+            main_loop_c.quit();
+        }
+        Inhibit(true)
+    });
+
+    main_loop.run();
+    seat.ungrab();
+    unsafe { window.destroy() };
+    position.get()
+}
+
+// The average colour of the square of "side" pixels around the point
+// the user clicks on (or None if they cancel)
+pub fn pick_screen_colour(side: i32) -> Option<RGB> {
+    let (x, y) = wait_for_screen_click()?;
+    let root = gdk::get_default_root_window();
+    let screen_size = (root.get_width(), root.get_height());
+    let (x, y, width, height) = screen_sample_rect((x, y), side, screen_size)?;
+    let pixbuf = gdk::pixbuf_get_from_window(&root, x, y, width, height)?;
+    colour_spread(&pixbuf_rgbs(&pixbuf)).map(|spread| spread.mean)
+}
+
+// A button to start picking and a choice of the size of the sampled area
+#[derive(PWO, Wrapper)]
+pub struct ScreenPickerCore {
+    hbox: gtk::Box,
+    pick_btn: gtk::Button,
+    side_combo: gtk::ComboBoxText,
+    picked_callbacks: RefCell<Vec<Box<dyn Fn(&RGB)>>>,
+}
+
+pub type ScreenPicker = Rc<ScreenPickerCore>;
+
+impl ScreenPickerCore {
+    pub fn sample_side(&self) -> i32 {
+        self.side_combo
+            .get_active_id()
+            .and_then(|id| id.parse::<i32>().ok())
+            .unwrap_or(1)
+    }
+
+    fn pick(&self) {
+        if let Some(rgb) = pick_screen_colour(self.sample_side()) {
+            for callback in self.picked_callbacks.borrow().iter() {
+                callback(&rgb);
+            }
+        }
+    }
+
+    pub fn connect_picked<F: 'static + Fn(&RGB)>(&self, callback: F) {
+        self.picked_callbacks.borrow_mut().push(Box::new(callback))
+    }
+}
+
+impl SimpleCreation for ScreenPicker {
+    fn create() -> ScreenPicker {
+        let sp = Rc::new(ScreenPickerCore {
+            hbox: gtk::Box::new(gtk::Orientation::Horizontal, 2),
+            pick_btn: gtk::Button::with_label("Pick From Screen"),
+            side_combo: gtk::ComboBoxText::new(),
+            picked_callbacks: RefCell::new(Vec::new()),
+        });
+        sp.pick_btn.set_tooltip_text(Some(
+            "Click anywhere on the screen (e.g. on a reference image in another application) to take its colour.  Press Escape to cancel.",
+        ));
+        sp.side_combo.set_tooltip_text(Some(
+            "The size of the square of pixels whose average colour is taken",
+        ));
+        let side = recall(SAMPLE_SIDE_RECOLLECTION)
+            .and_then(|text| text.parse::<i32>().ok())
+            .unwrap_or(1);
+        for sample_side in SAMPLE_SIDES.iter() {
+            let id = sample_side.to_string();
+            let label = if *sample_side == 1 {
+                "1 pixel".to_string()
+            } else {
+                format!("{0}x{0} pixels", sample_side)
+            };
+            sp.side_combo.append(Some(id.as_str()), &label);
+        }
+        if !sp.side_combo.set_active_id(Some(side.to_string().as_str())) {
+            sp.side_combo.set_active(Some(0));
+        }
+        sp.hbox.pack_start(&sp.pick_btn, true, true, 0);
+        sp.hbox.pack_start(&sp.side_combo, false, false, 0);
+        sp.hbox.show_all();
+
+        sp.side_combo.connect_changed(|combo| {
+            if let Some(id) = combo.get_active_id() {
+                remember(SAMPLE_SIDE_RECOLLECTION, &id);
+            }
+        });
+
+        let sp_c = sp.clone();
+        sp.pick_btn.connect_clicked(move |_| sp_c.pick());
+
+        sp
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn screen_sample_rects() {
+        assert_eq!(
+            screen_sample_rect((10, 10), 1, (100, 100)),
+            Some((10, 10, 1, 1))
+        );
+        assert_eq!(
+            screen_sample_rect((10, 10), 5, (100, 100)),
+            Some((8, 8, 5, 5))
+        );
+        assert_eq!(
+            screen_sample_rect((0, 99), 5, (100, 100)),
+            Some((0, 97, 3, 3))
+        );
+        assert_eq!(screen_sample_rect((200, 10), 3, (100, 100)), None);
+    }
+}
@@ -1,6 +1,6 @@
 // Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
 use pw_gix::{
@@ -15,9 +15,12 @@ use crate::colln_paint::binder::*;
 use crate::colln_paint::collection::*;
 pub use crate::colln_paint::display::*;
 use crate::colln_paint::editor::*;
+pub use crate::colln_paint::inventory::{ask_inventory, Inventory, InventoryInterface};
 use crate::colln_paint::*;
 use crate::colour::*;
+use crate::error::*;
 use crate::icons::series_paint_xpm::*;
+use crate::shape::{ColouredItemFilter, FilteredItemDisplay};
 
 #[derive(Debug, PartialEq, PartialOrd, Eq, Ord, Clone, Default, Hash)]
 pub struct PaintSeriesId {
@@ -59,6 +62,10 @@ impl CollnIdInterface for PaintSeriesId {
         "Add this paint to the paint mixing area.".to_string()
     }
 
+    fn paint_kind_label() -> String {
+        "Series Paint".to_string()
+    }
+
     fn recollection_name_for(item_name: &str) -> String {
         format!("series_paint::{}", item_name)
     }
@@ -79,6 +86,7 @@ impl CollnIdInterface for PaintSeriesId {
 pub type SeriesPaint<C> = CollnPaint<C, PaintSeriesId>;
 pub type SeriesPaintColln<C> = CollnPaintColln<C, PaintSeriesId>;
 pub type SeriesPaintCollnSpec<C> = PaintCollnSpec<C, PaintSeriesId>;
+pub type SeriesPaintCollnBuilder<C> = PaintCollnBuilder<C, PaintSeriesId>;
 
 pub type SeriesPaintCollnBinder<A, C> = CollnPaintCollnBinder<A, C, PaintSeriesId>;
 pub type SeriesPaintDisplayDialog<A, C> = CollnPaintDisplayDialog<A, C, PaintSeriesId>;
@@ -113,9 +121,83 @@ where
         self.binder.set_target_colour(ocolour)
     }
 
+    pub fn get_series_paints(&self) -> Vec<SeriesPaint<C>> {
+        self.binder.get_all_paints()
+    }
+
     pub fn connect_add_paint<F: 'static + Fn(&SeriesPaint<C>)>(&self, callback: F) {
         self.binder.connect_paint_selected(callback)
     }
+
+    // Used to grey out or hide paints that have already been added
+    pub fn set_membership_test<F: 'static + Fn(&SeriesPaint<C>) -> bool>(&self, test: F) {
+        self.binder.set_membership_test(Some(Rc::new(test)));
+    }
+
+    pub fn refresh_membership(&self) {
+        self.binder.refresh_membership();
+    }
+
+    // Adds (sortable) columns showing how often each paint has been used
+    pub fn set_usage_source<F: 'static + Fn(&SeriesPaint<C>) -> PaintUsage>(&self, source: F) {
+        self.binder.set_usage_source(Some(Rc::new(source)));
+    }
+
+    pub fn refresh_usage(&self) {
+        // NB: this rebuilds the rows
+        self.binder.refresh_membership();
+    }
+
+    pub fn connect_collns_changed<F: 'static + Fn()>(&self, callback: F) {
+        self.binder.connect_collns_changed(callback)
+    }
+
+    pub fn load_status_tooltip_text(&self) -> String {
+        let mut text = format!("{}\n{}", TOOLTIP_TEXT, self.binder.load_status_text());
+        if self.binder.load_failures().len() > 0 {
+            text += "\nRight click for details of the failures.";
+        }
+        text
+    }
+
+    pub fn show_load_failures(&self) {
+        self.binder.show_load_failures()
+    }
+
+    pub fn recent_colln_files(&self) -> Vec<PathBuf> {
+        self.binder.recent_colln_files()
+    }
+
+    pub fn clear_recent_colln_files(&self) {
+        self.binder.clear_recent_colln_files()
+    }
+
+    // e.g. only paints that are in stock
+    pub fn set_filter(&self, filter: Option<ColouredItemFilter<SeriesPaint<C>>>) {
+        self.binder.set_filter(filter);
+    }
+
+    pub fn set_filtered_display(&self, filtered_display: FilteredItemDisplay) {
+        self.binder.set_filtered_display(filtered_display);
+    }
+
+    pub fn set_paint_inventory(
+        &self,
+        paint: &SeriesPaint<C>,
+        o_inventory: Option<Inventory>,
+    ) -> Result<(), PaintError<C>> {
+        self.binder.update_paint_inventory(paint, o_inventory)
+    }
+
+    fn edit_inventory(&self, paint: &SeriesPaint<C>) {
+        let o_inventory = paint.inventory();
+        if let Some(inventory) = ask_inventory(&*self.binder, &paint.name(), o_inventory.as_ref()) {
+            if let Err(err) = self.set_paint_inventory(paint, Some(inventory)) {
+                self.binder
+                    .report_error("Failed to record the inventory", &err);
+            }
+        }
+    }
 }
 
 pub type SeriesPaintManager<A, C> = Rc<SeriesPaintManagerCore<A, C>>;
@@ -149,23 +231,47 @@ where
         window.add(&binder.pwo());
 
         let spm = Rc::new(SeriesPaintManagerCore::<A, C> { window, binder });
+        let spm_c = spm.clone();
+        spm.binder
+            .set_inventory_edit(Some(Rc::new(move |paint| spm_c.edit_inventory(paint))));
 
         spm
     }
 
     fn button(&self) -> gtk::Button {
         let button = gtk::Button::new();
-        button.set_tooltip_text(Some(TOOLTIP_TEXT));
         button.set_image(Some(&series_paint_image(24)));
+        button.set_always_show_image(true);
+        button.set_label(&self.binder.load_status_badge());
+        button.set_tooltip_text(Some(self.load_status_tooltip_text().as_str()));
+        let spm_c = self.clone();
+        let button_c = button.clone();
+        self.binder.connect_load_status_changed(move || {
+            button_c.set_label(&spm_c.binder.load_status_badge());
+            button_c.set_tooltip_text(Some(spm_c.load_status_tooltip_text().as_str()));
+        });
         let spm_c = self.clone();
         button.connect_clicked(move |_| spm_c.window.present());
+        let spm_c = self.clone();
+        button.connect_button_press_event(move |_, event| {
+            if event.get_button() == 3 && spm_c.binder.load_failures().len() > 0 {
+                spm_c.show_load_failures();
+                return gtk::Inhibit(true);
+            }
+            gtk::Inhibit(false)
+        });
         button
     }
 
     fn tool_button(&self) -> gtk::ToolButton {
         let tool_button =
             gtk::ToolButton::new(Some(&series_paint_image(24)), Some("Series Paint Manager"));
-        tool_button.set_tooltip_text(Some(TOOLTIP_TEXT));
+        tool_button.set_tooltip_text(Some(self.load_status_tooltip_text().as_str()));
+        let spm_c = self.clone();
+        let tool_button_c = tool_button.clone();
+        self.binder.connect_load_status_changed(move || {
+            tool_button_c.set_tooltip_text(Some(spm_c.load_status_tooltip_text().as_str()));
+        });
         let spm_c = self.clone();
         tool_button.connect_clicked(move |_| spm_c.window.present());
         tool_button
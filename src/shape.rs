@@ -1,8 +1,9 @@
 // Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::fmt::Debug;
 use std::marker::PhantomData;
+use std::rc::Rc;
 
 use cairo;
 
@@ -23,27 +24,46 @@ pub trait GeometryInterface {
     fn transform(&self, point: Point) -> Point;
     fn reverse_transform(&self, point: Point) -> Point;
     fn scaled(&self, value: f64) -> f64;
+    // The inverse of scaled()
+    fn reverse_scaled(&self, length: f64) -> f64;
+
+    // The drawn size of a dimension of the shape at "xy" (which may be
+    // scaled up or down from its nominal size)
+    fn shape_scaled(&self, value: f64, _xy: Point) -> f64 {
+        self.scaled(value)
+    }
 }
 
-const SHAPE_SIDE: f64 = 0.06;
-const SHAPE_RADIUS: f64 = SHAPE_SIDE / 2.0;
+pub const SHAPE_SIDE: f64 = 0.06;
+pub const SHAPE_RADIUS: f64 = SHAPE_SIDE / 2.0;
+
+// Shapes drawn closer together than this fraction of their size are clustered
+const CLUSTER_OVERLAP: f64 = 0.75;
+const BADGE_FONT_SIZE: f64 = 9.0;
+
+// Dimmed shapes are washed out towards a light grey
+pub fn dimmed_rgb(rgb: &RGB) -> RGB {
+    *rgb * 0.3 + RGB::WHITE * 0.5
+}
 
 pub trait ColourShapeInterface {
     fn xy(&self) -> Point;
     fn fill_rgb(&self) -> RGB;
     fn shape_type(&self) -> ShapeType;
 
-    fn encloses(&self, xy: Point) -> bool {
+    // "xy" is in the same (untransformed) space as the shape
+    fn encloses<G: GeometryInterface>(&self, canvas: &G, xy: Point) -> bool {
+        let reach = canvas.reverse_scaled(canvas.shape_scaled(SHAPE_RADIUS, self.xy()));
         match self.shape_type() {
             ShapeType::Square => {
                 let delta_xy = self.xy() - xy;
-                delta_xy.x().abs() < SHAPE_RADIUS && delta_xy.y().abs() < SHAPE_RADIUS
+                delta_xy.x().abs() < reach && delta_xy.y().abs() < reach
             }
             ShapeType::Diamond => {
                 let delta_xy = (self.xy() - xy).rotate_45_deg();
-                delta_xy.x().abs() < SHAPE_RADIUS && delta_xy.y().abs() < SHAPE_RADIUS
+                delta_xy.x().abs() < reach && delta_xy.y().abs() < reach
             }
-            _ => (self.xy() - xy).hypot() < SHAPE_RADIUS,
+            _ => (self.xy() - xy).hypot() < reach,
         }
     }
 
@@ -52,79 +72,128 @@ pub trait ColourShapeInterface {
     }
 
     fn draw<G: GeometryInterface>(&self, canvas: &G, cairo_context: &cairo::Context) {
-        let fill_rgb = self.fill_rgb();
-        let outline_rgb = fill_rgb.best_foreground_rgb();
         let point = canvas.transform(self.xy());
-        let side = canvas.scaled(SHAPE_SIDE);
-        match self.shape_type() {
-            ShapeType::Square => {
-                cairo_context.set_source_rgb(
-                    fill_rgb[CCI::Red],
-                    fill_rgb[CCI::Green],
-                    fill_rgb[CCI::Blue],
-                );
-                cairo_context.draw_square(point, side, true);
-                cairo_context.set_source_rgb(
-                    outline_rgb[CCI::Red],
-                    outline_rgb[CCI::Green],
-                    outline_rgb[CCI::Blue],
-                );
-                cairo_context.draw_square(point, side, false);
-            }
-            ShapeType::Diamond => {
-                cairo_context.set_source_rgb(
-                    fill_rgb[CCI::Red],
-                    fill_rgb[CCI::Green],
-                    fill_rgb[CCI::Blue],
-                );
-                cairo_context.draw_diamond(point, side, true);
-                cairo_context.set_source_rgb(
-                    outline_rgb[CCI::Red],
-                    outline_rgb[CCI::Green],
-                    outline_rgb[CCI::Blue],
-                );
-                cairo_context.draw_diamond(point, side, false);
-            }
-            ShapeType::Circle => {
-                let radius = canvas.scaled(SHAPE_RADIUS);
-                cairo_context.set_source_rgb(
-                    fill_rgb[CCI::Red],
-                    fill_rgb[CCI::Green],
-                    fill_rgb[CCI::Blue],
-                );
-                cairo_context.draw_circle(point, radius, true);
-                cairo_context.set_source_rgb(
-                    outline_rgb[CCI::Red],
-                    outline_rgb[CCI::Green],
-                    outline_rgb[CCI::Blue],
-                );
-                cairo_context.draw_circle(point, radius, false);
-            }
-            ShapeType::BackSight => {
-                let radius = canvas.scaled(SHAPE_RADIUS);
-                cairo_context.set_source_rgb(
-                    fill_rgb[CCI::Red],
-                    fill_rgb[CCI::Green],
-                    fill_rgb[CCI::Blue],
-                );
-                cairo_context.draw_circle(point, radius, true);
-                cairo_context.set_source_rgb(
-                    outline_rgb[CCI::Red],
-                    outline_rgb[CCI::Green],
-                    outline_rgb[CCI::Blue],
-                );
-                cairo_context.draw_circle(point, radius, false);
-
-                let half_len = canvas.scaled(SHAPE_SIDE);
-                let rel_end = Point(half_len, 0.0);
-                cairo_context.draw_line(point + rel_end, point - rel_end);
-                let rel_end = Point(0.0, half_len);
-                cairo_context.draw_line(point + rel_end, point - rel_end);
-            }
+        let side = canvas.shape_scaled(SHAPE_SIDE, self.xy());
+        draw_shape_at(
+            cairo_context,
+            self.shape_type(),
+            point,
+            side,
+            &self.fill_rgb(),
+        );
+    }
+
+    fn draw_dimmed<G: GeometryInterface>(&self, canvas: &G, cairo_context: &cairo::Context) {
+        let point = canvas.transform(self.xy());
+        let side = canvas.shape_scaled(SHAPE_SIDE, self.xy());
+        draw_shape_at(
+            cairo_context,
+            self.shape_type(),
+            point,
+            side,
+            &dimmed_rgb(&self.fill_rgb()),
+        );
+    }
+}
+
+// Draw a shape (e.g. a legend's sample) at "point" in drawing area coordinates
+pub fn draw_shape_at(
+    cairo_context: &cairo::Context,
+    shape_type: ShapeType,
+    point: Point,
+    side: f64,
+    fill_rgb: &RGB,
+) {
+    let outline_rgb = fill_rgb.best_foreground_rgb();
+    let radius = side / 2.0;
+    match shape_type {
+        ShapeType::Square => {
+            cairo_context.set_source_rgb(
+                fill_rgb[CCI::Red],
+                fill_rgb[CCI::Green],
+                fill_rgb[CCI::Blue],
+            );
+            cairo_context.draw_square(point, side, true);
+            cairo_context.set_source_rgb(
+                outline_rgb[CCI::Red],
+                outline_rgb[CCI::Green],
+                outline_rgb[CCI::Blue],
+            );
+            cairo_context.draw_square(point, side, false);
+        }
+        ShapeType::Diamond => {
+            cairo_context.set_source_rgb(
+                fill_rgb[CCI::Red],
+                fill_rgb[CCI::Green],
+                fill_rgb[CCI::Blue],
+            );
+            cairo_context.draw_diamond(point, side, true);
+            cairo_context.set_source_rgb(
+                outline_rgb[CCI::Red],
+                outline_rgb[CCI::Green],
+                outline_rgb[CCI::Blue],
+            );
+            cairo_context.draw_diamond(point, side, false);
+        }
+        ShapeType::Circle => {
+            cairo_context.set_source_rgb(
+                fill_rgb[CCI::Red],
+                fill_rgb[CCI::Green],
+                fill_rgb[CCI::Blue],
+            );
+            cairo_context.draw_circle(point, radius, true);
+            cairo_context.set_source_rgb(
+                outline_rgb[CCI::Red],
+                outline_rgb[CCI::Green],
+                outline_rgb[CCI::Blue],
+            );
+            cairo_context.draw_circle(point, radius, false);
+        }
+        ShapeType::BackSight => {
+            cairo_context.set_source_rgb(
+                fill_rgb[CCI::Red],
+                fill_rgb[CCI::Green],
+                fill_rgb[CCI::Blue],
+            );
+            cairo_context.draw_circle(point, radius, true);
+            cairo_context.set_source_rgb(
+                outline_rgb[CCI::Red],
+                outline_rgb[CCI::Green],
+                outline_rgb[CCI::Blue],
+            );
+            cairo_context.draw_circle(point, radius, false);
+
+            let half_len = side;
+            let rel_end = Point(half_len, 0.0);
+            cairo_context.draw_line(point + rel_end, point - rel_end);
+            let rel_end = Point(0.0, half_len);
+            cairo_context.draw_line(point + rel_end, point - rel_end);
         }
     }
 }
 
+// A count (e.g. of a cluster's shapes) at the top right of the shape drawn
+// at "point" in drawing area coordinates
+pub fn draw_count_badge(cairo_context: &cairo::Context, point: Point, side: f64, count: usize) {
+    let text = count.to_string();
+    cairo_context.save();
+    cairo_context.set_font_size(BADGE_FONT_SIZE);
+    let extents = cairo_context.text_extents(&text);
+    let radius = extents.width.max(extents.height) / 2.0 + 2.0;
+    let centre = point + Point(side / 2.0, -side / 2.0);
+    cairo_context.set_source_rgb(1.0, 1.0, 1.0);
+    cairo_context.draw_circle(centre, radius, true);
+    cairo_context.set_source_rgb(0.0, 0.0, 0.0);
+    cairo_context.set_line_width(1.0);
+    cairo_context.draw_circle(centre, radius, false);
+    cairo_context.move_to(
+        centre.0 - extents.width / 2.0 - extents.x_bearing,
+        centre.1 - extents.height / 2.0 - extents.y_bearing,
+    );
+    cairo_context.show_text(&text);
+    cairo_context.restore();
+}
+
 pub trait ColouredItemShapeInterface<CI>: ColourShapeInterface
 where
     CI: ColouredItemInterface + Ord,
@@ -133,14 +202,108 @@ where
     fn coloured_item(&self) -> CI;
 
     fn colour_xy(colour: Colour, attr: ScalarAttribute) -> Point {
-        if let Some(hue) = colour.hue() {
-            let radius = colour.scalar_attribute(attr);
-            let angle: normalised_angles::Angle<f64> = hue.angle().into();
-            Point::from((angle, radius))
-        } else {
-            let value = colour.value();
-            Point(-1.05, 1.0 - 2.0 * value)
+        colour_wheel_xy(&colour, attr)
+    }
+}
+
+// Where a colour sits on a hue/attribute wheel (greys go to the left of it)
+pub fn colour_wheel_xy(colour: &Colour, attr: ScalarAttribute) -> Point {
+    if let Some(hue) = colour.hue() {
+        let radius = colour.scalar_attribute(attr);
+        let angle: normalised_angles::Angle<f64> = hue.angle().into();
+        Point::from((angle, radius))
+    } else {
+        let value = colour.value();
+        Point(-1.05, 1.0 - 2.0 * value)
+    }
+}
+
+// Where a colour sits for the purposes of the area reachable by mixing.
+// Greys go in the centre which is right for chroma and near enough (for
+// a guide) for the other attributes.
+pub fn colour_gamut_xy(colour: &Colour, attr: ScalarAttribute) -> Point {
+    if colour.hue().is_some() {
+        colour_wheel_xy(colour, attr)
+    } else {
+        Point(0.0, 0.0)
+    }
+}
+
+fn cross(o: Point, a: Point, b: Point) -> f64 {
+    (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+}
+
+// The convex hull (anticlockwise without collinear points) of "points"
+// using Andrew's monotone chain algorithm
+pub fn convex_hull(points: &[Point]) -> Vec<Point> {
+    let mut points = points.to_vec();
+    points.sort_by(|a, b| {
+        a.0.partial_cmp(&b.0)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+    });
+    points.dedup_by(|a, b| (*a - *b).hypot() < 1.0e-9);
+    if points.len() < 3 {
+        return points;
+    }
+    let mut hull: Vec<Point> = Vec::with_capacity(points.len() * 2);
+    // the lower chain left to right
+    for point in points.iter() {
+        while hull.len() >= 2 && cross(hull[hull.len() - 2], hull[hull.len() - 1], *point) <= 0.0 {
+            hull.pop();
+        }
+        hull.push(*point);
+    }
+    // then the upper chain right to left (without disturbing the lower)
+    let lower_len = hull.len() + 1;
+    for point in points.iter().rev().skip(1) {
+        while hull.len() >= lower_len
+            && cross(hull[hull.len() - 2], hull[hull.len() - 1], *point) <= 0.0
+        {
+            hull.pop();
+        }
+        hull.push(*point);
+    }
+    // the first point is repeated at the end
+    hull.pop();
+    hull
+}
+
+// Whether "point" is inside (or on the edge of) a hull made by convex_hull()
+pub fn hull_contains(hull: &[Point], point: Point) -> bool {
+    const EPSILON: f64 = 1.0e-9;
+    match hull.len() {
+        0 => false,
+        1 => (hull[0] - point).hypot() < EPSILON,
+        2 => {
+            let length = (hull[1] - hull[0]).hypot();
+            cross(hull[0], hull[1], point).abs() < EPSILON * length.max(1.0)
+                && (point - hull[0]).hypot() + (hull[1] - point).hypot() < length + EPSILON
         }
+        _ => (0..hull.len()).all(|i| cross(hull[i], hull[(i + 1) % hull.len()], point) >= -EPSILON),
+    }
+}
+
+// Applications can restrict what's shown (e.g. only paints in stock)
+pub type ColouredItemFilter<CI> = Box<dyn Fn(&CI) -> bool>;
+// For filters applied to several lists (e.g. all of a widget's wheels)
+pub type SharedColouredItemFilter<CI> = Rc<dyn Fn(&CI) -> bool>;
+
+pub fn boxed_filter<CI: 'static>(filter: &SharedColouredItemFilter<CI>) -> ColouredItemFilter<CI> {
+    let filter = filter.clone();
+    Box::new(move |coloured_item| filter(coloured_item))
+}
+
+// How the items rejected by a filter are drawn
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FilteredItemDisplay {
+    Dim,
+    Hide,
+}
+
+impl Default for FilteredItemDisplay {
+    fn default() -> FilteredItemDisplay {
+        FilteredItemDisplay::Hide
     }
 }
 
@@ -151,6 +314,14 @@ where
 {
     attr: ScalarAttribute,
     shapes: RefCell<Vec<PS>>,
+    // index of the shape found by the last successful hit test and how
+    // close to it the pointer must stay for it to still be the nearest
+    last_hit: Cell<Option<(usize, f64)>>,
+    clustering: Cell<bool>,
+    // the members of the cluster that the user has asked to see
+    expanded: RefCell<Vec<CI>>,
+    filter: RefCell<Option<ColouredItemFilter<CI>>>,
+    filtered_display: Cell<FilteredItemDisplay>,
     changed_callbacks: RefCell<Vec<Box<dyn Fn()>>>,
     pc: PhantomData<CI>,
 }
@@ -164,15 +335,171 @@ where
         ColouredItemSpapeList::<CI, PS> {
             attr: attr,
             shapes: RefCell::new(Vec::new()),
+            last_hit: Cell::new(None),
+            clustering: Cell::new(false),
+            expanded: RefCell::new(Vec::new()),
+            filter: RefCell::new(None),
+            filtered_display: Cell::new(FilteredItemDisplay::default()),
             changed_callbacks: RefCell::new(Vec::new()),
             pc: PhantomData,
         }
     }
 
     pub fn clear(&self) {
+        self.last_hit.set(None);
+        self.expanded.borrow_mut().clear();
         self.shapes.borrow_mut().clear()
     }
 
+    pub fn is_clustering(&self) -> bool {
+        self.clustering.get()
+    }
+
+    pub fn set_clustering(&self, clustering: bool) {
+        self.clustering.set(clustering);
+        self.expanded.borrow_mut().clear();
+        self.last_hit.set(None);
+        self.inform_changed();
+    }
+
+    pub fn has_filter(&self) -> bool {
+        self.filter.borrow().is_some()
+    }
+
+    pub fn set_filter(&self, filter: Option<ColouredItemFilter<CI>>) {
+        *self.filter.borrow_mut() = filter;
+        self.expanded.borrow_mut().clear();
+        self.last_hit.set(None);
+        self.inform_changed();
+    }
+
+    pub fn filtered_display(&self) -> FilteredItemDisplay {
+        self.filtered_display.get()
+    }
+
+    pub fn set_filtered_display(&self, filtered_display: FilteredItemDisplay) {
+        self.filtered_display.set(filtered_display);
+        self.expanded.borrow_mut().clear();
+        self.last_hit.set(None);
+        self.inform_changed();
+    }
+
+    pub fn passes_filter(&self, coloured_item: &CI) -> bool {
+        match *self.filter.borrow() {
+            Some(ref filter) => filter(coloured_item),
+            None => true,
+        }
+    }
+
+    fn is_filtered_out(&self, shape: &PS) -> bool {
+        self.filtered_display.get() == FilteredItemDisplay::Hide
+            && !self.passes_filter(&shape.coloured_item())
+    }
+
+    fn draw_shape<G: GeometryInterface>(
+        &self,
+        shape: &PS,
+        canvas: &G,
+        cairo_context: &cairo::Context,
+    ) {
+        if self.passes_filter(&shape.coloured_item()) {
+            shape.draw(canvas, cairo_context);
+        } else {
+            shape.draw_dimmed(canvas, cairo_context);
+        }
+    }
+
+    // Groups of (indices of) shapes that overlap as drawn i.e. at the
+    // current zoom.  A group's first member (its anchor) represents it
+    // and the members of the expanded cluster are never grouped.  Shapes
+    // hidden by the filter aren't in any group.
+    fn clusters<G: GeometryInterface>(&self, canvas: &G) -> Vec<Vec<usize>> {
+        let shapes = self.shapes.borrow();
+        let expanded = self.expanded.borrow();
+        let mut clusters: Vec<Vec<usize>> = Vec::new();
+        let mut anchors: Vec<Option<(Point, f64)>> = Vec::new();
+        for (index, shape) in shapes.iter().enumerate() {
+            if self.is_filtered_out(shape) {
+                continue;
+            }
+            if expanded.contains(&shape.coloured_item()) {
+                clusters.push(vec![index]);
+                anchors.push(None);
+                continue;
+            }
+            let point = canvas.transform(shape.xy());
+            let reach = canvas.shape_scaled(SHAPE_SIDE, shape.xy()) * CLUSTER_OVERLAP;
+            let o_cluster = anchors.iter().position(|o_anchor| match o_anchor {
+                Some((anchor, anchor_reach)) => (*anchor - point).hypot() < anchor_reach.max(reach),
+                None => false,
+            });
+            if let Some(cluster) = o_cluster {
+                clusters[cluster].push(index);
+            } else {
+                clusters.push(vec![index]);
+                anchors.push(Some((point, reach)));
+            }
+        }
+        clusters
+    }
+
+    // The shapes hidden by the filter or behind their cluster's anchor
+    fn hidden_indices<G: GeometryInterface>(&self, canvas: &G) -> Vec<usize> {
+        let mut hidden: Vec<usize> = self
+            .shapes
+            .borrow()
+            .iter()
+            .enumerate()
+            .filter(|(_, shape)| self.is_filtered_out(shape))
+            .map(|(index, _)| index)
+            .collect();
+        if self.clustering.get() {
+            hidden.extend(
+                self.clusters(canvas)
+                    .into_iter()
+                    .flat_map(|cluster| cluster.into_iter().skip(1)),
+            );
+        }
+        hidden
+    }
+
+    // Show the members of the (collapsed) cluster at "xy" or, if "xy" is
+    // clear of the expanded cluster, collapse it again.  Returns whether
+    // what's drawn has changed.
+    pub fn expand_cluster_at<G: GeometryInterface>(&self, canvas: &G, xy: Point) -> bool {
+        if !self.clustering.get() {
+            return false;
+        }
+        let o_members: Option<Vec<CI>> = {
+            let shapes = self.shapes.borrow();
+            self.clusters(canvas)
+                .into_iter()
+                .find(|cluster| cluster.len() > 1 && shapes[cluster[0]].encloses(canvas, xy))
+                .map(|cluster| {
+                    cluster
+                        .iter()
+                        .map(|index| shapes[*index].coloured_item())
+                        .collect()
+                })
+        };
+        if let Some(members) = o_members {
+            *self.expanded.borrow_mut() = members;
+        } else {
+            let in_expanded = {
+                let expanded = self.expanded.borrow();
+                self.shapes.borrow().iter().any(|shape| {
+                    expanded.contains(&shape.coloured_item()) && shape.encloses(canvas, xy)
+                })
+            };
+            if in_expanded || self.expanded.borrow().len() == 0 {
+                return false;
+            }
+            self.expanded.borrow_mut().clear();
+        }
+        self.last_hit.set(None);
+        true
+    }
+
     pub fn len(&self) -> usize {
         self.shapes.borrow().len()
     }
@@ -190,6 +517,7 @@ where
     pub fn add_coloured_item(&self, coloured_item: &CI) {
         if let Err(index) = self.find_coloured_item(coloured_item) {
             let shape = PS::new(coloured_item, self.attr);
+            self.last_hit.set(None);
             self.shapes.borrow_mut().insert(index, shape);
             self.inform_changed();
         } else {
@@ -200,6 +528,7 @@ where
     pub fn remove_coloured_item(&self, coloured_item: &CI) {
         match self.find_coloured_item(coloured_item) {
             Ok(index) => {
+                self.last_hit.set(None);
                 self.shapes.borrow_mut().remove(index);
                 self.inform_changed();
             }
@@ -218,19 +547,69 @@ where
     }
 
     pub fn draw<G: GeometryInterface>(&self, canvas: &G, cairo_context: &cairo::Context) {
-        for shape in self.shapes.borrow().iter() {
-            shape.draw(canvas, cairo_context);
+        if !self.clustering.get() {
+            for shape in self.shapes.borrow().iter() {
+                if !self.is_filtered_out(shape) {
+                    self.draw_shape(shape, canvas, cairo_context);
+                }
+            }
+            return;
         }
+        let shapes = self.shapes.borrow();
+        for cluster in self.clusters(canvas).iter() {
+            let anchor = &shapes[cluster[0]];
+            self.draw_shape(anchor, canvas, cairo_context);
+            if cluster.len() > 1 {
+                let point = canvas.transform(anchor.xy());
+                let side = canvas.shape_scaled(SHAPE_SIDE, anchor.xy());
+                draw_count_badge(cairo_context, point, side, cluster.len());
+            }
+        }
+    }
+
+    // The items (not hidden by the filter) in the order in which they're
+    // held and where they're drawn
+    pub fn coloured_items_xy(&self) -> Vec<(CI, Point)> {
+        self.shapes
+            .borrow()
+            .iter()
+            .filter(|shape| !self.is_filtered_out(shape))
+            .map(|shape| (shape.coloured_item(), shape.xy()))
+            .collect()
+    }
+
+    pub fn coloured_item_xy(&self, coloured_item: &CI) -> Option<Point> {
+        let index = self.find_coloured_item(coloured_item).ok()?;
+        Some(self.shapes.borrow()[index].xy())
     }
 
-    pub fn get_coloured_item_at(&self, xy: Point) -> Option<(CI, f64)> {
+    // While the pointer stays within the shape last hit and closer to its
+    // centre than half the distance to any other visible shape's centre
+    // that shape must still be the nearest (saving a search of the list)
+    pub fn get_coloured_item_at<G: GeometryInterface>(
+        &self,
+        canvas: &G,
+        xy: Point,
+    ) -> Option<(CI, f64)> {
+        // NB: what's hidden by clustering changes with the zoom
+        if let (Some((index, clearance)), false) = (self.last_hit.get(), self.clustering.get()) {
+            let shapes = self.shapes.borrow();
+            if let Some(shape) = shapes.get(index) {
+                let range = shape.distance_to(xy);
+                if range < clearance && shape.encloses(canvas, xy) {
+                    return Some((shape.coloured_item(), range));
+                }
+            }
+        }
+        let hidden = self.hidden_indices(canvas);
         let mut candidates: Vec<usize> = Vec::new();
         for (index, shape) in self.shapes.borrow().iter().enumerate() {
-            if shape.encloses(xy) {
+            if shape.encloses(canvas, xy) && !hidden.contains(&index) {
                 candidates.push(index);
             }
         }
         if candidates.len() == 0 {
+            self.last_hit.set(None);
             None
         } else {
             let shapes = self.shapes.borrow();
@@ -243,7 +622,14 @@ where
                     index = *i;
                 }
             }
-            Some((self.shapes.borrow()[index].coloured_item(), range))
+            let clearance = shapes
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| *i != index && !hidden.contains(i))
+                .map(|(_, shape)| shape.distance_to(shapes[index].xy()) / 2.0)
+                .fold(f64::INFINITY, f64::min);
+            self.last_hit.set(Some((index, clearance)));
+            Some((shapes[index].coloured_item(), range))
         }
     }
 
@@ -260,8 +646,179 @@ where
 
 #[cfg(test)]
 mod tests {
-    //use super::*;
+    use super::*;
+    use crate::art_paint::*;
+    use crate::basic_paint::hue_wheel::*;
+
+    // A wheel of radius 100 pixels centred on the origin
+    struct TestCanvas;
+
+    impl GeometryInterface for TestCanvas {
+        fn transform(&self, point: Point) -> Point {
+            Point(point.0 * 100.0, point.1 * 100.0)
+        }
+
+        fn reverse_transform(&self, point: Point) -> Point {
+            Point(point.0 / 100.0, point.1 / 100.0)
+        }
+
+        fn scaled(&self, value: f64) -> f64 {
+            value * 100.0
+        }
+
+        fn reverse_scaled(&self, length: f64) -> f64 {
+            length / 100.0
+        }
+    }
+
+    fn paint(name: &str, rgb: [f64; 3]) -> BasicPaint<ArtPaintCharacteristics> {
+        let spec = BasicPaintSpec::<ArtPaintCharacteristics> {
+            rgb: RGB::from(rgb),
+            name: name.to_string(),
+            notes: String::new(),
+            characteristics: ArtPaintCharacteristics {
+                permanence: Permanence::A,
+                transparency: Transparency::Opaque,
+            },
+        };
+        BasicPaint::<ArtPaintCharacteristics>::from_spec(&spec)
+    }
 
     #[test]
-    fn it_works() {}
+    fn overlapping_shapes_cluster() {
+        let list = BasicPaintShapeList::<ArtPaintCharacteristics>::new(ScalarAttribute::Value);
+        let red = paint("Red", [0.8, 0.1, 0.1]);
+        let near_red = paint("Near Red", [0.8, 0.11, 0.1]);
+        let blue = paint("Blue", [0.1, 0.1, 0.8]);
+        for paint in [&red, &near_red, &blue].iter() {
+            list.add_coloured_item(paint);
+        }
+        let canvas = TestCanvas;
+        assert_eq!(list.hidden_indices(&canvas).len(), 0);
+        list.set_clustering(true);
+        assert_eq!(list.hidden_indices(&canvas).len(), 1);
+        let red_xy = list.coloured_item_xy(&red).unwrap();
+        let blue_xy = list.coloured_item_xy(&blue).unwrap();
+        assert!(!list.expand_cluster_at(&canvas, blue_xy));
+        assert!(list.expand_cluster_at(&canvas, red_xy));
+        assert_eq!(list.hidden_indices(&canvas).len(), 0);
+        assert!(list.expand_cluster_at(&canvas, Point(0.0, 0.0)));
+        assert_eq!(list.hidden_indices(&canvas).len(), 1);
+    }
+
+    #[test]
+    fn nearest_overlapping_shape_is_hit() {
+        let list = BasicPaintShapeList::<ArtPaintCharacteristics>::new(ScalarAttribute::Value);
+        let red = paint("Red", [0.8, 0.1, 0.1]);
+        let near_red = paint("Near Red", [0.8, 0.11, 0.1]);
+        list.add_coloured_item(&red);
+        list.add_coloured_item(&near_red);
+        let canvas = TestCanvas;
+        let red_xy = list.coloured_item_xy(&red).unwrap();
+        let near_red_xy = list.coloured_item_xy(&near_red).unwrap();
+        assert_eq!(list.get_coloured_item_at(&canvas, red_xy).unwrap().0, red);
+        assert_eq!(
+            list.get_coloured_item_at(&canvas, near_red_xy).unwrap().0,
+            near_red
+        );
+    }
+
+    #[test]
+    fn last_hit_reused_only_while_nearest() {
+        let list = BasicPaintShapeList::<ArtPaintCharacteristics>::new(ScalarAttribute::Value);
+        let red = paint("Red", [0.8, 0.1, 0.1]);
+        let near_red = paint("Near Red", [0.8, 0.11, 0.1]);
+        list.add_coloured_item(&red);
+        list.add_coloured_item(&near_red);
+        let canvas = TestCanvas;
+        let red_xy = list.coloured_item_xy(&red).unwrap();
+        let near_red_xy = list.coloured_item_xy(&near_red).unwrap();
+        assert_eq!(list.get_coloured_item_at(&canvas, red_xy).unwrap().0, red);
+        // still inside red's shape but nearer to near red's centre
+        let between = red_xy + (near_red_xy - red_xy) * 0.75;
+        assert_eq!(
+            list.get_coloured_item_at(&canvas, between).unwrap().0,
+            near_red
+        );
+        let back = red_xy + (near_red_xy - red_xy) * 0.25;
+        assert_eq!(list.get_coloured_item_at(&canvas, back).unwrap().0, red);
+    }
+
+    #[test]
+    fn filtered_shapes() {
+        let list = BasicPaintShapeList::<ArtPaintCharacteristics>::new(ScalarAttribute::Value);
+        let red = paint("Red", [0.8, 0.1, 0.1]);
+        let blue = paint("Blue", [0.1, 0.1, 0.8]);
+        list.add_coloured_item(&red);
+        list.add_coloured_item(&blue);
+        let canvas = TestCanvas;
+        let red_xy = list.coloured_item_xy(&red).unwrap();
+        list.set_filter(Some(Box::new(
+            |paint: &BasicPaint<ArtPaintCharacteristics>| paint.name() != "Red",
+        )));
+        assert!(!list.passes_filter(&red));
+        assert!(list.passes_filter(&blue));
+        assert_eq!(list.hidden_indices(&canvas).len(), 1);
+        assert_eq!(list.coloured_items_xy().len(), 1);
+        assert!(list.get_coloured_item_at(&canvas, red_xy).is_none());
+        list.set_filtered_display(FilteredItemDisplay::Dim);
+        assert_eq!(list.hidden_indices(&canvas).len(), 0);
+        assert_eq!(list.get_coloured_item_at(&canvas, red_xy).unwrap().0, red);
+        list.set_filter(None);
+        assert!(list.passes_filter(&red));
+    }
+
+    #[test]
+    fn gamut_hulls() {
+        let points = vec![
+            Point(0.0, 0.0),
+            Point(1.0, 0.0),
+            Point(0.5, 0.2),
+            Point(1.0, 1.0),
+            Point(0.0, 1.0),
+            Point(0.5, 1.0),
+            Point(1.0, 1.0),
+        ];
+        let hull = convex_hull(&points);
+        assert_eq!(hull.len(), 4);
+        assert!(hull_contains(&hull, Point(0.5, 0.5)));
+        assert!(hull_contains(&hull, Point(1.0, 0.5)));
+        assert!(!hull_contains(&hull, Point(1.1, 0.5)));
+        let line = convex_hull(&[Point(0.0, 0.0), Point(1.0, 1.0)]);
+        assert!(hull_contains(&line, Point(0.5, 0.5)));
+        assert!(!hull_contains(&line, Point(0.5, 0.6)));
+        assert!(!hull_contains(&convex_hull(&[]), Point(0.0, 0.0)));
+        let grey = Colour::from(RGB::from([0.5, 0.5, 0.5]));
+        assert!(colour_gamut_xy(&grey, ScalarAttribute::Chroma).hypot() < 1.0e-9);
+    }
+
+    #[test]
+    fn convex_hull_edge_cases() {
+        let xys = |points: &[Point]| -> Vec<(f64, f64)> {
+            convex_hull(points).iter().map(|p| (p.0, p.1)).collect()
+        };
+        let square = [
+            Point(1.0, 1.0),
+            Point(0.0, 0.0),
+            Point(0.0, 1.0),
+            Point(1.0, 0.0),
+            Point(0.5, 0.5),
+        ];
+        assert_eq!(
+            xys(&square),
+            vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]
+        );
+        let collinear = [Point(0.0, 0.0), Point(2.0, 2.0), Point(1.0, 1.0)];
+        assert_eq!(xys(&collinear), vec![(0.0, 0.0), (2.0, 2.0)]);
+        assert!(hull_contains(&convex_hull(&collinear), Point(1.5, 1.5)));
+        let duplicates = [
+            Point(0.0, 0.0),
+            Point(1.0, 0.0),
+            Point(0.0, 0.0),
+            Point(0.0, 1.0),
+            Point(1.0, 0.0),
+        ];
+        assert_eq!(xys(&duplicates), vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]);
+        assert_eq!(xys(&[Point(0.5, 0.5), Point(0.5, 0.5)]), vec![(0.5, 0.5)]);
+    }
 }
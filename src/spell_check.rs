@@ -0,0 +1,69 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+//! Optional spell checking of notes entries (requires the "spell_check"
+//! feature and the gspell library).  Without the feature these are no-ops.
+
+use pw_gix::{
+    gtk,
+    recollections::{recall, remember},
+};
+
+const SPELL_CHECK_LANGUAGE_KEY: &str = "spell_check::language";
+
+/// The language code (e.g. "en_AU") to check against.  If not set the
+/// user's locale is used.
+pub fn spell_check_language() -> Option<String> {
+    match recall(SPELL_CHECK_LANGUAGE_KEY) {
+        Some(code) if code.len() > 0 => Some(code),
+        _ => None,
+    }
+}
+
+/// Entries that already have spell checking keep their original language.
+pub fn set_spell_check_language(o_code: Option<&str>) {
+    remember(SPELL_CHECK_LANGUAGE_KEY, o_code.unwrap_or(""));
+}
+
+#[cfg(feature = "spell_check")]
+pub fn spell_check_available() -> bool {
+    true
+}
+
+#[cfg(not(feature = "spell_check"))]
+pub fn spell_check_available() -> bool {
+    false
+}
+
+#[cfg(feature = "spell_check")]
+pub fn available_spell_check_languages() -> Vec<String> {
+    gspell::Language::get_available()
+        .iter()
+        .map(|language| language.get_code().to_string())
+        .collect()
+}
+
+#[cfg(not(feature = "spell_check"))]
+pub fn available_spell_check_languages() -> Vec<String> {
+    vec![]
+}
+
+#[cfg(feature = "spell_check")]
+pub fn enable_spell_checking(entry: &gtk::Entry) {
+    use gspell::{EntryBufferExt, EntryExt};
+    use pw_gix::gtk::prelude::*;
+
+    let o_language = match spell_check_language() {
+        Some(code) => gspell::Language::lookup(&code),
+        None => None,
+    };
+    let checker = gspell::Checker::new(o_language.as_ref());
+    if let Some(buffer) = gspell::EntryBuffer::get_from_gtk_entry_buffer(&entry.get_buffer()) {
+        buffer.set_spell_checker(Some(&checker));
+    }
+    if let Some(gspell_entry) = gspell::Entry::get_from_gtk_entry(entry) {
+        gspell_entry.set_inline_spell_checking(true);
+    }
+}
+
+#[cfg(not(feature = "spell_check"))]
+pub fn enable_spell_checking(_entry: &gtk::Entry) {}
@@ -1,12 +1,15 @@
 // Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
 
-use std::path::Path;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
 use pw_gix::{
+    gdk,
     gdk_pixbuf::Pixbuf,
+    glib::{self, StaticType, ToValue},
     gtk::{self, prelude::*},
-    gtkx::window::*,
+    gtkx::{list_store::*, tree_view_column::*, window::*},
     wrapper::*,
 };
 
@@ -16,7 +19,10 @@ use crate::colln_paint::collection::*;
 pub use crate::colln_paint::display::*;
 use crate::colln_paint::editor::*;
 use crate::colln_paint::*;
+use crate::colour::*;
+use crate::colour_difference::*;
 use crate::icons::paint_standard_xpms::*;
+use crate::shape::{ColouredItemFilter, FilteredItemDisplay};
 
 #[derive(Debug, PartialEq, PartialOrd, Eq, Ord, Clone, Default, Hash)]
 pub struct PaintStandardId {
@@ -58,6 +64,10 @@ impl CollnIdInterface for PaintStandardId {
         "Set the target colour in the mixing area from this paint.".to_string()
     }
 
+    fn paint_kind_label() -> String {
+        "Standard".to_string()
+    }
+
     fn recollection_name_for(item_name: &str) -> String {
         format!("paint_standard::{}", item_name)
     }
@@ -78,6 +88,7 @@ impl CollnIdInterface for PaintStandardId {
 pub type PaintStandard<C> = CollnPaint<C, PaintStandardId>;
 pub type PaintStandardColln<C> = CollnPaintColln<C, PaintStandardId>;
 pub type PaintStandardCollnSpec<C> = PaintCollnSpec<C, PaintStandardId>;
+pub type PaintStandardCollnBuilder<C> = PaintCollnBuilder<C, PaintStandardId>;
 
 pub type PaintStandardCollnBinder<A, C> = CollnPaintCollnBinder<A, C, PaintStandardId>;
 pub type PaintStandardDisplayDialog<A, C> = CollnPaintDisplayDialog<A, C, PaintStandardId>;
@@ -108,9 +119,50 @@ where
         self.binder.set_initiate_select_ok(value);
     }
 
+    pub fn set_target_colour(&self, ocolour: Option<&Colour>) {
+        self.binder.set_target_colour(ocolour)
+    }
+
     pub fn connect_set_target_from<F: 'static + Fn(&PaintStandard<C>)>(&self, callback: F) {
         self.binder.connect_paint_selected(callback)
     }
+
+    pub fn get_standards(&self) -> Vec<PaintStandard<C>> {
+        self.binder.get_all_paints()
+    }
+
+    pub fn connect_standards_changed<F: 'static + Fn()>(&self, callback: F) {
+        self.binder.connect_collns_changed(callback)
+    }
+
+    pub fn load_status_tooltip_text(&self) -> String {
+        let mut text = format!("{}\n{}", TOOLTIP_TEXT, self.binder.load_status_text());
+        if self.binder.load_failures().len() > 0 {
+            text += "\nRight click for details of the failures.";
+        }
+        text
+    }
+
+    pub fn show_load_failures(&self) {
+        self.binder.show_load_failures()
+    }
+
+    pub fn recent_colln_files(&self) -> Vec<PathBuf> {
+        self.binder.recent_colln_files()
+    }
+
+    pub fn clear_recent_colln_files(&self) {
+        self.binder.clear_recent_colln_files()
+    }
+
+    // e.g. only paints that are in stock
+    pub fn set_filter(&self, filter: Option<ColouredItemFilter<PaintStandard<C>>>) {
+        self.binder.set_filter(filter);
+    }
+
+    pub fn set_filtered_display(&self, filtered_display: FilteredItemDisplay) {
+        self.binder.set_filtered_display(filtered_display);
+    }
 }
 
 pub type PaintStandardManager<A, C> = Rc<PaintStandardManagerCore<A, C>>;
@@ -150,10 +202,26 @@ where
 
     fn button(&self) -> gtk::Button {
         let button = gtk::Button::new();
-        button.set_tooltip_text(Some(TOOLTIP_TEXT));
         button.set_image(Some(&paint_standard_image(24)));
+        button.set_always_show_image(true);
+        button.set_label(&self.binder.load_status_badge());
+        button.set_tooltip_text(Some(self.load_status_tooltip_text().as_str()));
+        let spm_c = self.clone();
+        let button_c = button.clone();
+        self.binder.connect_load_status_changed(move || {
+            button_c.set_label(&spm_c.binder.load_status_badge());
+            button_c.set_tooltip_text(Some(spm_c.load_status_tooltip_text().as_str()));
+        });
         let spm_c = self.clone();
         button.connect_clicked(move |_| spm_c.window.present());
+        let spm_c = self.clone();
+        button.connect_button_press_event(move |_, event| {
+            if event.get_button() == 3 && spm_c.binder.load_failures().len() > 0 {
+                spm_c.show_load_failures();
+                return gtk::Inhibit(true);
+            }
+            gtk::Inhibit(false)
+        });
         button
     }
 
@@ -162,13 +230,156 @@ where
             Some(&paint_standard_image(24)),
             Some("Paint Standards Manager"),
         );
-        tool_button.set_tooltip_text(Some(TOOLTIP_TEXT));
+        tool_button.set_tooltip_text(Some(self.load_status_tooltip_text().as_str()));
+        let spm_c = self.clone();
+        let tool_button_c = tool_button.clone();
+        self.binder.connect_load_status_changed(move || {
+            tool_button_c.set_tooltip_text(Some(spm_c.load_status_tooltip_text().as_str()));
+        });
         let spm_c = self.clone();
         tool_button.connect_clicked(move |_| spm_c.window.present());
         tool_button
     }
 }
 
+// Standards ranked by their distance from a colour (e.g. the current mix)
+pub const MAX_PROXIMITY_ROWS: usize = 10;
+
+const PSP_NAME: i32 = 0;
+const PSP_STANDARD: i32 = 1;
+const PSP_DELTA_E: i32 = 2;
+const PSP_RGB: i32 = 3;
+const PSP_RGB_FG: i32 = 4;
+
+lazy_static! {
+    static ref PAINT_STANDARD_PROXIMITY_ROW_SPEC: [glib::Type; 5] = [
+        glib::Type::String,       // 0 Name
+        glib::Type::String,       // 1 Standard
+        glib::Type::String,       // 2 Delta E
+        gdk::RGBA::static_type(), // 3 RGB
+        gdk::RGBA::static_type(), // 4 FG for RGB
+    ];
+}
+
+#[derive(PWO, Wrapper)]
+pub struct PaintStandardProximityListCore<A, C>
+where
+    A: ColourAttributesInterface + 'static,
+    C: CharacteristicsInterface + 'static,
+{
+    scrolled_window: gtk::ScrolledWindow,
+    list_store: gtk::ListStore,
+    view: gtk::TreeView,
+    manager: PaintStandardManager<A, C>,
+    colour: RefCell<Option<Colour>>,
+}
+
+impl<A, C> PaintStandardProximityListCore<A, C>
+where
+    A: ColourAttributesInterface + 'static,
+    C: CharacteristicsInterface + 'static,
+{
+    pub fn set_colour(&self, o_colour: Option<&Colour>) {
+        *self.colour.borrow_mut() = o_colour.cloned();
+        self.update();
+    }
+
+    fn update(&self) {
+        self.list_store.clear();
+        if let Some(ref colour) = *self.colour.borrow() {
+            let rgb = colour.rgb();
+            let mut ranked: Vec<(f64, PaintStandard<C>)> = self
+                .manager
+                .get_standards()
+                .into_iter()
+                .map(|paint| (delta_e(&rgb, &paint.rgb()), paint))
+                .collect();
+            ranked.sort_by(|a, b| {
+                a.0.partial_cmp(&b.0)
+                    .unwrap_or_else(|| panic!("File: {:?} Line: {:?}", file!(), line!()))
+            });
+            for (distance, paint) in ranked.iter().take(MAX_PROXIMITY_ROWS) {
+                let rgba = paint.rgb().into_gdk_rgba();
+                let frgba = paint.rgb().best_foreground_rgb().into_gdk_rgba();
+                let row = vec![
+                    paint.name().to_value(),
+                    paint.colln_id().colln_name().to_value(),
+                    format!("{:.1}", distance).to_value(),
+                    rgba.to_value(),
+                    frgba.to_value(),
+                ];
+                self.list_store.append_row(&row);
+            }
+        }
+    }
+}
+
+pub type PaintStandardProximityList<A, C> = Rc<PaintStandardProximityListCore<A, C>>;
+
+pub trait PaintStandardProximityListInterface<A, C>
+where
+    A: ColourAttributesInterface + 'static,
+    C: CharacteristicsInterface + 'static,
+{
+    fn create(manager: &PaintStandardManager<A, C>) -> PaintStandardProximityList<A, C>;
+}
+
+impl<A, C> PaintStandardProximityListInterface<A, C> for PaintStandardProximityList<A, C>
+where
+    A: ColourAttributesInterface + 'static,
+    C: CharacteristicsInterface + 'static,
+{
+    fn create(manager: &PaintStandardManager<A, C>) -> PaintStandardProximityList<A, C> {
+        let list_store = gtk::ListStore::new(&PAINT_STANDARD_PROXIMITY_ROW_SPEC[..]);
+        let view = gtk::TreeView::with_model(&list_store);
+        view.set_headers_visible(true);
+        view.get_selection().set_mode(gtk::SelectionMode::None);
+        view.append_column(&swatch_tv_column(PSP_RGB));
+        view.append_column(&simple_text_column(
+            "Name", PSP_NAME, -1, PSP_RGB, PSP_RGB_FG, -1, true,
+        ));
+        view.append_column(&simple_text_column(
+            "Standard",
+            PSP_STANDARD,
+            -1,
+            PSP_RGB,
+            PSP_RGB_FG,
+            -1,
+            true,
+        ));
+        view.append_column(&simple_text_column(
+            "Delta E",
+            PSP_DELTA_E,
+            -1,
+            -1,
+            -1,
+            60,
+            false,
+        ));
+        let adj: Option<&gtk::Adjustment> = None;
+        let scrolled_window = gtk::ScrolledWindow::new(adj, adj);
+        scrolled_window.add(&view);
+        scrolled_window.set_min_content_height(120);
+        scrolled_window.set_tooltip_text(Some(
+            "The loaded standards closest to the current mixed colour",
+        ));
+        scrolled_window.show_all();
+
+        let pspl = Rc::new(PaintStandardProximityListCore::<A, C> {
+            scrolled_window,
+            list_store,
+            view,
+            manager: manager.clone(),
+            colour: RefCell::new(None),
+        });
+
+        let pspl_c = pspl.clone();
+        manager.connect_standards_changed(move || pspl_c.update());
+
+        pspl
+    }
+}
+
 #[cfg(test)]
 mod tests {
     //use super::*;
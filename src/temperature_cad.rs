@@ -0,0 +1,128 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+// A colour attribute display (in the style of those in colour_math_gtk)
+// for the correlated colour temperature of a colour (in Kelvin).
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use pw_gix::{
+    cairo,
+    gtk::{self, prelude::*},
+    wrapper::*,
+};
+
+use crate::cairox::*;
+use crate::colour::*;
+
+const N_GRADIENT_STEPS: usize = 64;
+const INDICATOR_SIZE: f64 = 8.0;
+
+// The position (0.0 to 1.0) of "kelvin" along the display.  A mired
+// (reciprocal) scale is used as it's closer to how the eye sees the change.
+pub fn colour_temperature_fraction(kelvin: f64) -> f64 {
+    let kelvin = kelvin
+        .max(MIN_COLOUR_TEMPERATURE)
+        .min(MAX_COLOUR_TEMPERATURE);
+    let min_mired = 1.0e6 / MAX_COLOUR_TEMPERATURE;
+    let max_mired = 1.0e6 / MIN_COLOUR_TEMPERATURE;
+    (max_mired - 1.0e6 / kelvin) / (max_mired - min_mired)
+}
+
+pub fn colour_temperature_at_fraction(fraction: f64) -> f64 {
+    let min_mired = 1.0e6 / MAX_COLOUR_TEMPERATURE;
+    let max_mired = 1.0e6 / MIN_COLOUR_TEMPERATURE;
+    1.0e6 / (max_mired - fraction.max(0.0).min(1.0) * (max_mired - min_mired))
+}
+
+#[derive(PWO, Wrapper)]
+pub struct ColourTemperatureCAD {
+    drawing_area: gtk::DrawingArea,
+    rgb: RefCell<Option<RGB>>,
+    target_rgb: RefCell<Option<RGB>>,
+}
+
+impl ColourTemperatureCAD {
+    pub fn new() -> Rc<ColourTemperatureCAD> {
+        let drawing_area = gtk::DrawingArea::new();
+        drawing_area.set_size_request(90, 30);
+        let cad = Rc::new(ColourTemperatureCAD {
+            drawing_area,
+            rgb: RefCell::new(None),
+            target_rgb: RefCell::new(None),
+        });
+
+        let cad_c = cad.clone();
+        cad.drawing_area.connect_draw(move |da, cctx| {
+            cad_c.draw(da, cctx);
+            gtk::Inhibit(true)
+        });
+
+        cad
+    }
+
+    pub fn set_rgb(&self, rgb: Option<&RGB>) {
+        *self.rgb.borrow_mut() = rgb.cloned();
+        self.drawing_area.queue_draw();
+    }
+
+    pub fn set_target_rgb(&self, target_rgb: Option<&RGB>) {
+        *self.target_rgb.borrow_mut() = target_rgb.cloned();
+        self.drawing_area.queue_draw();
+    }
+
+    fn draw(&self, drawing_area: &gtk::DrawingArea, cairo_context: &cairo::Context) {
+        let width = drawing_area.get_allocated_width() as f64;
+        let height = drawing_area.get_allocated_height() as f64;
+        let step_width = width / N_GRADIENT_STEPS as f64;
+        for i in 0..N_GRADIENT_STEPS {
+            let fraction = (i as f64 + 0.5) / N_GRADIENT_STEPS as f64;
+            let rgb = colour_temperature_rgb(colour_temperature_at_fraction(fraction));
+            cairo_context.set_source_colour_rgb(&rgb);
+            // overlap slightly to avoid hairline gaps between the steps
+            cairo_context.rectangle(i as f64 * step_width, 0.0, step_width + 1.0, height);
+            cairo_context.fill();
+        }
+        cairo_context.set_source_colour_rgb(&RGB::BLACK);
+        if let Some(ref target_rgb) = *self.target_rgb.borrow() {
+            if let Some(kelvin) = colour_temperature(target_rgb) {
+                let x = colour_temperature_fraction(kelvin) * width;
+                cairo_context.draw_indicator(Point(x, height), Dirn::Up, INDICATOR_SIZE);
+            }
+        }
+        let label = match *self.rgb.borrow() {
+            Some(ref rgb) => match colour_temperature(rgb) {
+                Some(kelvin) => {
+                    let x = colour_temperature_fraction(kelvin) * width;
+                    cairo_context.draw_indicator(Point(x, 0.0), Dirn::Down, INDICATOR_SIZE);
+                    format!("{:.0} K", kelvin)
+                }
+                None => "- K".to_string(),
+            },
+            None => "Temperature".to_string(),
+        };
+        cairo_context.set_font_size(12.0);
+        let extents = cairo_context.text_extents(&label);
+        cairo_context.move_to(
+            (width - extents.width) / 2.0,
+            (height + extents.height) / 2.0,
+        );
+        cairo_context.show_text(&label);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn temperature_fractions() {
+        assert_eq!(colour_temperature_fraction(MIN_COLOUR_TEMPERATURE), 0.0);
+        assert!((colour_temperature_fraction(MAX_COLOUR_TEMPERATURE) - 1.0).abs() < 1.0e-10);
+        assert_eq!(colour_temperature_fraction(100.0), 0.0);
+        for kelvin in [1500.0, 3200.0, 6500.0, 12000.0].iter() {
+            let fraction = colour_temperature_fraction(*kelvin);
+            assert!((colour_temperature_at_fraction(fraction) - kelvin).abs() < 1.0e-6);
+        }
+    }
+}
@@ -0,0 +1,140 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+use pw_gix::gtk::{self, prelude::*};
+
+// Common colour words so that e.g. searching "ao" finds "青"
+pub static COLOUR_WORD_TRANSLITERATIONS: &[(&str, &str)] = &[
+    ("青", "ao"),
+    ("赤", "aka"),
+    ("黄", "ki"),
+    ("緑", "midori"),
+    ("白", "shiro"),
+    ("黒", "kuro"),
+    ("茶", "cha"),
+    ("紫", "murasaki"),
+    ("灰", "hai"),
+    ("橙", "daidai"),
+    ("桃", "momo"),
+    ("紺", "kon"),
+    ("金", "kin"),
+    ("銀", "gin"),
+];
+
+// Characters that don't decompose into a base letter plus marks
+static LETTER_FOLDS: &[(char, &str)] = &[
+    ('ß', "ss"),
+    ('æ', "ae"),
+    ('œ', "oe"),
+    ('ø', "o"),
+    ('đ', "d"),
+    ('ł', "l"),
+    ('ı', "i"),
+];
+
+// Compatibility decomposition, removal of combining marks and case folding
+pub fn normalise(text: &str) -> String {
+    let mut result = String::new();
+    for c in text.nfkd().filter(|c| !is_combining_mark(*c)) {
+        for lc in c.to_lowercase() {
+            if let Some((_, fold)) = LETTER_FOLDS.iter().find(|(from, _)| *from == lc) {
+                result.push_str(fold);
+            } else {
+                result.push(lc);
+            }
+        }
+    }
+    result
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchNormaliser {
+    transliterations: Vec<(String, String)>,
+}
+
+impl Default for SearchNormaliser {
+    fn default() -> SearchNormaliser {
+        SearchNormaliser::with_transliterations(COLOUR_WORD_TRANSLITERATIONS)
+    }
+}
+
+impl SearchNormaliser {
+    pub fn without_transliteration() -> SearchNormaliser {
+        SearchNormaliser {
+            transliterations: Vec::new(),
+        }
+    }
+
+    pub fn with_transliterations(transliterations: &[(&str, &str)]) -> SearchNormaliser {
+        SearchNormaliser {
+            transliterations: transliterations
+                .iter()
+                .map(|(from, to)| (normalise(from), normalise(to)))
+                .collect(),
+        }
+    }
+
+    pub fn add_transliteration(&mut self, from: &str, to: &str) {
+        self.transliterations.push((normalise(from), normalise(to)));
+    }
+
+    pub fn normalise(&self, text: &str) -> String {
+        let mut result = normalise(text);
+        for (from, to) in self.transliterations.iter() {
+            result = result.replace(from.as_str(), to.as_str());
+        }
+        result
+    }
+
+    pub fn matches(&self, text: &str, key: &str) -> bool {
+        let key = self.normalise(key);
+        key.len() == 0 || self.normalise(text).contains(&key)
+    }
+}
+
+// Enable the tree view's interactive search on the given text columns
+// matching keys against their normalised content.
+pub fn set_normalised_search(view: &gtk::TreeView, text_ids: &[i32], normaliser: SearchNormaliser) {
+    view.set_enable_search(true);
+    if let Some(text_id) = text_ids.first() {
+        view.set_search_column(*text_id);
+    }
+    let text_ids = text_ids.to_vec();
+    view.set_search_equal_func(move |model, _, key, iter| {
+        for text_id in text_ids.iter() {
+            if let Ok(Some(text)) = model.get_value(iter, *text_id).get::<String>() {
+                if normaliser.matches(&text, key) {
+                    // NB: GTK expects false when the row matches
+                    return false;
+                }
+            }
+        }
+        true
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalise_strips_accents_and_case() {
+        assert_eq!(normalise("Grün"), "grun");
+        assert_eq!(normalise("Crème Brûlée"), "creme brulee");
+        assert_eq!(normalise("Weiß"), "weiss");
+        assert_eq!(normalise("ＡＢＣ"), "abc");
+    }
+
+    #[test]
+    fn search_normaliser_transliterates() {
+        let normaliser = SearchNormaliser::default();
+        assert!(normaliser.matches("Grün", "grun"));
+        assert!(normaliser.matches("青", "ao"));
+        assert!(normaliser.matches("群青 (Ultramarine)", "ultra"));
+        assert!(!normaliser.matches("赤", "ao"));
+        let plain = SearchNormaliser::without_transliteration();
+        assert!(!plain.matches("青", "ao"));
+        assert!(plain.matches("青", "青"));
+    }
+}
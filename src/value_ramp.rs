@@ -0,0 +1,165 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+use std::rc::Rc;
+
+use pw_gix::{
+    glib::StaticType,
+    gtk::{self, prelude::*},
+    wrapper::*,
+};
+
+use crate::app_name;
+use crate::colour::*;
+use crate::colour_mix::srgb_to_linear;
+
+// A step is uneven if it differs from the mean step by more than this fraction
+pub const UNEVEN_STEP_TOLERANCE: f64 = 0.5;
+
+pub fn relative_luminance(rgb: &RGB) -> f64 {
+    0.2126 * srgb_to_linear(rgb[CCI::Red])
+        + 0.7152 * srgb_to_linear(rgb[CCI::Green])
+        + 0.0722 * srgb_to_linear(rgb[CCI::Blue])
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RampStep {
+    pub from: String,
+    pub to: String,
+    pub value_step: f64,
+    pub luminance_step: f64,
+    pub uneven: bool,
+}
+
+// Order the members from darkest to lightest and report the steps between them.
+pub fn audit_value_ramp(members: &[(String, RGB)], tolerance: f64) -> Vec<RampStep> {
+    let mut members = members.to_vec();
+    members.sort_by(|a, b| {
+        a.1.value()
+            .partial_cmp(&b.1.value())
+            .unwrap_or_else(|| panic!("File: {:?} Line: {:?}", file!(), line!()))
+    });
+    let mut steps: Vec<RampStep> = members
+        .windows(2)
+        .map(|pair| RampStep {
+            from: pair[0].0.clone(),
+            to: pair[1].0.clone(),
+            value_step: pair[1].1.value() - pair[0].1.value(),
+            luminance_step: relative_luminance(&pair[1].1) - relative_luminance(&pair[0].1),
+            uneven: false,
+        })
+        .collect();
+    if steps.len() > 1 {
+        let mean = steps.iter().map(|step| step.value_step).sum::<f64>() / steps.len() as f64;
+        for step in steps.iter_mut() {
+            step.uneven = (step.value_step - mean).abs() > tolerance * mean;
+        }
+    }
+    steps
+}
+
+pub fn show_value_ramp_audit<W: WidgetWrapper>(members: &[(String, RGB)], caller: &Rc<W>) {
+    let steps = audit_value_ramp(members, UNEVEN_STEP_TOLERANCE);
+    let title = format!("{}: Value Ramp Audit", app_name());
+    let dialog = caller.new_dialog_with_buttons(
+        Some(&title),
+        gtk::DialogFlags::DESTROY_WITH_PARENT,
+        &[("Close", gtk::ResponseType::Close)],
+    );
+    dialog.set_size_from_recollections("value_ramp_audit_dialog", (450, 300));
+
+    let list_store = gtk::ListStore::new(&[
+        String::static_type(),
+        String::static_type(),
+        String::static_type(),
+        String::static_type(),
+        String::static_type(),
+    ]);
+    for step in steps.iter() {
+        let flag = if step.uneven { "Uneven" } else { "" };
+        list_store.insert_with_values(
+            None,
+            &[0, 1, 2, 3, 4],
+            &[
+                &step.from,
+                &step.to,
+                &format!("{:.3}", step.value_step),
+                &format!("{:.3}", step.luminance_step),
+                &flag,
+            ],
+        );
+    }
+    let view = gtk::TreeView::with_model(&list_store);
+    view.set_headers_visible(true);
+    view.get_selection().set_mode(gtk::SelectionMode::None);
+    for (col_id, title) in ["From", "To", "Value Step", "Luminance Step", ""]
+        .iter()
+        .enumerate()
+    {
+        let col = gtk::TreeViewColumn::new();
+        col.set_title(title);
+        col.set_resizable(true);
+        let cell = gtk::CellRendererText::new();
+        col.pack_start(&cell, true);
+        col.add_attribute(&cell, "text", col_id as i32);
+        view.append_column(&col);
+    }
+    let adj: Option<&gtk::Adjustment> = None;
+    let scrolled_window = gtk::ScrolledWindow::new(adj, adj);
+    scrolled_window.add(&view);
+
+    let uneven_count = steps.iter().filter(|step| step.uneven).count();
+    let summary = if uneven_count > 0 {
+        format!(
+            "{} of {} steps are uneven (darkest to lightest).",
+            uneven_count,
+            steps.len()
+        )
+    } else {
+        format!("All {} steps are even (darkest to lightest).", steps.len())
+    };
+    let content_area = dialog.get_content_area();
+    content_area.pack_start(&gtk::Label::new(Some(&summary)), false, false, 0);
+    content_area.pack_start(&scrolled_window, true, true, 0);
+    content_area.show_all();
+    dialog.run();
+    unsafe { dialog.destroy() };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_luminance_extremes() {
+        assert_eq!(relative_luminance(&RGB::BLACK), 0.0);
+        assert!((relative_luminance(&RGB::WHITE) - 1.0).abs() < 1.0e-9);
+        assert!(relative_luminance(&RGB::GREEN) > relative_luminance(&RGB::RED));
+    }
+
+    #[test]
+    fn audit_value_ramp_flags_uneven_steps() {
+        let members = vec![
+            ("Light".to_string(), RGB::WHITE),
+            ("Dark".to_string(), RGB::WHITE * 0.1),
+            ("Mid".to_string(), RGB::WHITE * 0.3),
+            ("Middling".to_string(), RGB::WHITE * 0.5),
+        ];
+        let steps = audit_value_ramp(&members, UNEVEN_STEP_TOLERANCE);
+        assert_eq!(steps.len(), 3);
+        assert_eq!(steps[0].from, "Dark");
+        assert_eq!(steps[2].to, "Light");
+        assert!(steps.iter().all(|step| step.value_step > 0.0));
+        assert!(!steps[0].uneven);
+        assert!(!steps[1].uneven);
+        assert!(steps[2].uneven);
+    }
+
+    #[test]
+    fn audit_value_ramp_even_steps() {
+        let members: Vec<(String, RGB)> = (1..5)
+            .map(|i| (format!("Step {}", i), RGB::WHITE * (i as f64 * 0.2)))
+            .collect();
+        let steps = audit_value_ramp(&members, UNEVEN_STEP_TOLERANCE);
+        assert!(steps.iter().all(|step| !step.uneven));
+    }
+}
@@ -0,0 +1,276 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+use std::rc::Rc;
+use std::str::FromStr;
+
+use chrono::prelude::*;
+
+use pw_gix::{
+    gdk,
+    glib::StaticType,
+    gtk::{self, prelude::*},
+    gtkx::dialog::*,
+    recollections::{recall, remember},
+    wrapper::*,
+};
+
+use crate::app_name;
+use crate::basic_paint::*;
+use crate::colour::*;
+use crate::mixed_paint::trainer::PracticeRng;
+
+pub const VISION_PROFILE_KEY: &str = "vision_check::profile";
+pub const GREY_PATCH_COUNT: usize = 8;
+pub const HUE_PATCH_COUNT: usize = 12;
+
+// Closely spaced mid greys (darkest first)
+pub fn grey_patches() -> Vec<RGB> {
+    (0..GREY_PATCH_COUNT)
+        .map(|i| {
+            let value = 0.35 + 0.3 * i as f64 / (GREY_PATCH_COUNT - 1) as f64;
+            RGB::from([value, value, value])
+        })
+        .collect()
+}
+
+// Low chroma colours equally spaced around the hue circle (red first)
+pub fn hue_patches() -> Vec<RGB> {
+    (0..HUE_PATCH_COUNT)
+        .map(|i| {
+            let sector = 6.0 * i as f64 / HUE_PATCH_COUNT as f64;
+            let fraction = sector - sector.floor();
+            let (red, green, blue) = match sector.floor() as u32 {
+                0 => (1.0, fraction, 0.0),
+                1 => (1.0 - fraction, 1.0, 0.0),
+                2 => (0.0, 1.0, fraction),
+                3 => (0.0, 1.0 - fraction, 1.0),
+                4 => (fraction, 0.0, 1.0),
+                _ => (1.0, 0.0, 1.0 - fraction),
+            };
+            let mix = |c: f64| 0.5 + 0.25 * (c - 0.5);
+            RGB::from([mix(red), mix(green), mix(blue)])
+        })
+        .collect()
+}
+
+// The excess distance travelled when visiting the patches in the
+// arranged order ("arranged" holds each patch's correct position).  A
+// perfect arrangement scores zero.  Hues are on a circle so either
+// direction around it is acceptable.
+pub fn ordering_error(arranged: &[usize], cyclic: bool) -> u32 {
+    let n = arranged.len();
+    if n < 2 {
+        return 0;
+    }
+    let distance = |a: usize, b: usize| {
+        let d = if a > b { a - b } else { b - a };
+        if cyclic {
+            d.min(n - d)
+        } else {
+            d
+        }
+    };
+    let travelled: usize = arranged
+        .windows(2)
+        .map(|pair| distance(pair[0], pair[1]))
+        .sum();
+    (travelled - (n - 1)) as u32
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VisionProfile {
+    pub observer: String,
+    pub grey_error: u32,
+    pub hue_error: u32,
+    pub date: NaiveDate,
+}
+
+impl VisionProfile {
+    pub fn rating(&self) -> &'static str {
+        match self.grey_error + self.hue_error {
+            0..=2 => "no notable confusions",
+            3..=8 => "minor confusions",
+            _ => "significant confusions",
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "{}: {} (grey error {}, hue error {}) checked {}",
+            self.observer,
+            self.rating(),
+            self.grey_error,
+            self.hue_error,
+            self.date.format("%Y-%m-%d")
+        )
+    }
+
+    pub fn recall() -> Option<VisionProfile> {
+        recall(VISION_PROFILE_KEY).and_then(|text| VisionProfile::from_str(&text).ok())
+    }
+
+    pub fn remember(&self) {
+        remember(VISION_PROFILE_KEY, &self.to_string());
+    }
+}
+
+impl FromStr for VisionProfile {
+    type Err = String;
+
+    // e.g. "2017-12-25,0,3,Peter"
+    fn from_str(string: &str) -> Result<VisionProfile, String> {
+        let fields: Vec<&str> = string.splitn(4, ',').collect();
+        if fields.len() != 4 {
+            return Err(format!("{}: malformed vision profile", string));
+        }
+        let date = NaiveDate::parse_from_str(fields[0], "%Y-%m-%d")
+            .map_err(|_| format!("{}: bad date", fields[0]))?;
+        let grey_error =
+            u32::from_str(fields[1]).map_err(|_| format!("{}: bad error", fields[1]))?;
+        let hue_error =
+            u32::from_str(fields[2]).map_err(|_| format!("{}: bad error", fields[2]))?;
+        Ok(VisionProfile {
+            observer: fields[3].to_string(),
+            grey_error,
+            hue_error,
+            date,
+        })
+    }
+}
+
+impl std::fmt::Display for VisionProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{},{},{},{}",
+            self.date.format("%Y-%m-%d"),
+            self.grey_error,
+            self.hue_error,
+            self.observer
+        )
+    }
+}
+
+// A row of swatches (in shuffled order) that the user drags into order
+fn arrangement_view(patches: &[RGB], rng: &mut PracticeRng) -> (gtk::TreeView, gtk::ListStore) {
+    let list_store = gtk::ListStore::new(&[gdk::RGBA::static_type(), u32::static_type()]);
+    let mut order: Vec<usize> = (0..patches.len()).collect();
+    for i in (1..order.len()).rev() {
+        order.swap(i, rng.below(i + 1));
+    }
+    for index in order.iter() {
+        list_store.insert_with_values(
+            None,
+            &[0, 1],
+            &[&patches[*index].into_gdk_rgba(), &(*index as u32)],
+        );
+    }
+    let view = gtk::TreeView::with_model(&list_store);
+    view.set_headers_visible(false);
+    view.set_reorderable(true);
+    view.append_column(&swatch_tv_column(0));
+    (view, list_store)
+}
+
+fn arranged_order(list_store: &gtk::ListStore) -> Vec<usize> {
+    let mut arranged: Vec<usize> = vec![];
+    if let Some(iter) = list_store.get_iter_first() {
+        loop {
+            if let Ok(Some(index)) = list_store.get_value(&iter, 1).get::<u32>() {
+                arranged.push(index as usize);
+            }
+            if !list_store.iter_next(&iter) {
+                break;
+            }
+        }
+    }
+    arranged
+}
+
+// Let the user arrange grey and hue patches and record the result
+pub fn run_vision_check<W: WidgetWrapper>(caller: &Rc<W>) -> Option<VisionProfile> {
+    let title = format!("{}: Vision Check", app_name());
+    let dialog = caller.new_dialog_with_buttons(
+        Some(&title),
+        gtk::DialogFlags::DESTROY_WITH_PARENT,
+        CANCEL_OK_BUTTONS,
+    );
+    dialog.set_size_from_recollections("vision_check_dialog", (360, 520));
+    let content_area = dialog.get_content_area();
+    let observer_entry = gtk::Entry::new();
+    let observer = match VisionProfile::recall() {
+        Some(profile) => profile.observer,
+        None => std::env::var("USER").unwrap_or_default(),
+    };
+    observer_entry.set_text(&observer);
+    let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 2);
+    hbox.pack_start(&gtk::Label::new(Some("Observer:")), false, false, 0);
+    hbox.pack_start(&observer_entry, true, true, 0);
+    content_area.pack_start(&hbox, false, false, 0);
+    let instructions = gtk::Label::new(Some(
+        "Drag the greys into order from darkest to lightest and the\ncolours into hue order (starting anywhere, going either way).",
+    ));
+    content_area.pack_start(&instructions, false, false, 2);
+    let mut rng = PracticeRng::from_time();
+    let (grey_view, grey_store) = arrangement_view(&grey_patches(), &mut rng);
+    let (hue_view, hue_store) = arrangement_view(&hue_patches(), &mut rng);
+    let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 8);
+    let frame = gtk::Frame::new(Some("Greys"));
+    frame.add(&grey_view);
+    hbox.pack_start(&frame, true, true, 0);
+    let frame = gtk::Frame::new(Some("Hues"));
+    frame.add(&hue_view);
+    hbox.pack_start(&frame, true, true, 0);
+    content_area.pack_start(&hbox, true, true, 0);
+    content_area.show_all();
+    let mut o_profile: Option<VisionProfile> = None;
+    if gtk::ResponseType::from(dialog.run()) == gtk::ResponseType::Ok {
+        // Reversed greys are still correctly ordered
+        let mut greys = arranged_order(&grey_store);
+        if greys.first() > greys.last() {
+            greys.reverse();
+        }
+        let profile = VisionProfile {
+            observer: observer_entry.get_text().trim().replace(',', " "),
+            grey_error: ordering_error(&greys, false),
+            hue_error: ordering_error(&arranged_order(&hue_store), true),
+            date: Local::today().naive_local(),
+        };
+        profile.remember();
+        o_profile = Some(profile);
+    }
+    unsafe { dialog.destroy() };
+    o_profile
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ordering_errors() {
+        assert_eq!(ordering_error(&[0, 1, 2, 3], false), 0);
+        assert_eq!(ordering_error(&[0, 2, 1, 3], false), 2);
+        assert_eq!(ordering_error(&[2, 3, 0, 1], true), 0);
+        assert_eq!(ordering_error(&[3, 2, 1, 0], true), 0);
+        assert_eq!(ordering_error(&[0, 2, 1, 3], true), 2);
+        assert_eq!(grey_patches().len(), GREY_PATCH_COUNT);
+        assert_eq!(hue_patches().len(), HUE_PATCH_COUNT);
+    }
+
+    #[test]
+    fn vision_profile_round_trip() {
+        let profile = VisionProfile {
+            observer: "Someone Else".to_string(),
+            grey_error: 1,
+            hue_error: 4,
+            date: NaiveDate::from_ymd(2017, 12, 25),
+        };
+        assert_eq!(
+            VisionProfile::from_str(&profile.to_string()),
+            Ok(profile.clone())
+        );
+        assert_eq!(profile.rating(), "minor confusions");
+        assert!(VisionProfile::from_str("2017-12-25,1").is_err());
+    }
+}
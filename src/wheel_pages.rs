@@ -0,0 +1,169 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+use pw_gix::{
+    gtk::{self, prelude::*},
+    recollections::{recall, remember},
+};
+
+use crate::colour::*;
+
+// Wheel page configurations are recorded as a comma separated list of
+// attribute names in page order with hidden attributes prefixed by '-'
+// e.g. "Chroma,Value,-Warmth".  Available attributes that aren't mentioned
+// are shown (after the others) so that configurations can be shared by
+// paint types with different attributes.
+pub fn format_wheel_pages(visible: &[ScalarAttribute], available: &[ScalarAttribute]) -> String {
+    let mut items: Vec<String> = visible.iter().map(|attr| attr.to_string()).collect();
+    for attr in available.iter() {
+        if !visible.contains(attr) {
+            items.push(format!("-{}", attr));
+        }
+    }
+    items.join(",")
+}
+
+pub fn parse_wheel_pages(text: &str, available: &[ScalarAttribute]) -> Vec<ScalarAttribute> {
+    let mut visible: Vec<ScalarAttribute> = Vec::new();
+    let mut hidden: Vec<ScalarAttribute> = Vec::new();
+    for item in text.split(',').map(|item| item.trim()) {
+        let (name, is_hidden) = match item.strip_prefix('-') {
+            Some(name) => (name, true),
+            None => (item, false),
+        };
+        if let Some(attr) = available.iter().find(|attr| attr.to_string() == name) {
+            if visible.contains(attr) || hidden.contains(attr) {
+                continue;
+            } else if is_hidden {
+                hidden.push(*attr);
+            } else {
+                visible.push(*attr);
+            }
+        }
+    }
+    for attr in available.iter() {
+        if !visible.contains(attr) && !hidden.contains(attr) {
+            visible.push(*attr);
+        }
+    }
+    if visible.len() == 0 {
+        available.to_vec()
+    } else {
+        visible
+    }
+}
+
+// The available attributes from "attrs" (in order and without duplicates)
+pub fn valid_wheel_pages(
+    attrs: &[ScalarAttribute],
+    available: &[ScalarAttribute],
+) -> Vec<ScalarAttribute> {
+    let mut visible: Vec<ScalarAttribute> = Vec::new();
+    for attr in attrs.iter() {
+        if available.contains(attr) && !visible.contains(attr) {
+            visible.push(*attr);
+        }
+    }
+    if visible.len() == 0 {
+        available.to_vec()
+    } else {
+        visible
+    }
+}
+
+pub fn recall_wheel_pages(key: &str, available: &[ScalarAttribute]) -> Vec<ScalarAttribute> {
+    match recall(key) {
+        Some(text) => parse_wheel_pages(&text, available),
+        None => available.to_vec(),
+    }
+}
+
+pub fn remember_wheel_pages(key: &str, visible: &[ScalarAttribute], available: &[ScalarAttribute]) {
+    remember(key, &format_wheel_pages(visible, available));
+}
+
+pub fn wheel_page_label(attr: ScalarAttribute) -> gtk::Label {
+    let label_text = format!("Hue/{} Wheel", attr.to_string());
+    gtk::Label::new(Some(label_text.as_str()))
+}
+
+// (Re)populate "notebook" with the pages for the "visible" attributes in
+// the given order.  The pages remain owned by their wheels when removed.
+pub fn arrange_wheel_pages<P: IsA<gtk::Widget>>(
+    notebook: &gtk::Notebook,
+    pages: &[(ScalarAttribute, P)],
+    visible: &[ScalarAttribute],
+) {
+    for (_, page) in pages.iter() {
+        if let Some(page_num) = notebook.page_num(page) {
+            notebook.remove_page(Some(page_num));
+        }
+    }
+    for attr in visible.iter() {
+        if let Some((_, page)) = pages.iter().find(|(page_attr, _)| page_attr == attr) {
+            notebook.append_page(page, Some(&wheel_page_label(*attr)));
+            notebook.set_tab_reorderable(page, true);
+        }
+    }
+}
+
+// The attributes of the pages currently in "notebook" in page order
+pub fn wheel_page_order<P: IsA<gtk::Widget>>(
+    notebook: &gtk::Notebook,
+    pages: &[(ScalarAttribute, P)],
+) -> Vec<ScalarAttribute> {
+    let mut order: Vec<(u32, ScalarAttribute)> = pages
+        .iter()
+        .filter_map(|(attr, page)| notebook.page_num(page).map(|page_num| (page_num, *attr)))
+        .collect();
+    order.sort_by_key(|(page_num, _)| *page_num);
+    order.into_iter().map(|(_, attr)| attr).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AVAILABLE: [ScalarAttribute; 3] = [
+        ScalarAttribute::Value,
+        ScalarAttribute::Chroma,
+        ScalarAttribute::Warmth,
+    ];
+
+    #[test]
+    fn wheel_pages_round_trip() {
+        let visible = vec![ScalarAttribute::Warmth, ScalarAttribute::Value];
+        let text = format_wheel_pages(&visible, &AVAILABLE);
+        assert_eq!(text, "Warmth,Value,-Chroma");
+        assert_eq!(parse_wheel_pages(&text, &AVAILABLE), visible);
+    }
+
+    #[test]
+    fn wheel_pages_tolerate_other_attributes() {
+        // Unmentioned attributes are appended and unknown ones ignored
+        let text = "Greyness,Chroma,-Value";
+        assert_eq!(
+            parse_wheel_pages(text, &AVAILABLE),
+            vec![ScalarAttribute::Chroma, ScalarAttribute::Warmth]
+        );
+        // Hiding everything isn't allowed
+        assert_eq!(
+            parse_wheel_pages("-Value,-Chroma,-Warmth", &AVAILABLE),
+            AVAILABLE.to_vec()
+        );
+        assert_eq!(parse_wheel_pages("", &AVAILABLE), AVAILABLE.to_vec());
+    }
+
+    #[test]
+    fn wheel_pages_validation() {
+        let attrs = vec![
+            ScalarAttribute::Chroma,
+            ScalarAttribute::Greyness,
+            ScalarAttribute::Chroma,
+        ];
+        assert_eq!(
+            valid_wheel_pages(&attrs, &AVAILABLE),
+            vec![ScalarAttribute::Chroma]
+        );
+        assert_eq!(valid_wheel_pages(&[], &AVAILABLE), AVAILABLE.to_vec());
+    }
+}
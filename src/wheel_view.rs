@@ -0,0 +1,195 @@
+// Copyright 2017 Peter Williams <pwil3058@gmail.com> <pwil3058@bigpond.net.au>
+
+//! A read only hue/attribute wheel for arbitrary coloured items so that
+//! applications can display data that isn't a paint collection.
+
+use std::cell::RefCell;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+
+use pw_gix::{
+    gtk::{self, prelude::*},
+    wrapper::*,
+};
+
+use crate::basic_paint::*;
+use crate::cairox::*;
+use crate::colour::*;
+use crate::graticule::*;
+use crate::shape::*;
+
+struct ItemShape {
+    colour: Colour,
+    label: Option<String>,
+    xy: Point,
+}
+
+impl ColourShapeInterface for ItemShape {
+    fn xy(&self) -> Point {
+        self.xy
+    }
+
+    fn fill_rgb(&self) -> RGB {
+        self.colour.rgb()
+    }
+
+    fn shape_type(&self) -> ShapeType {
+        ShapeType::Circle
+    }
+}
+
+impl ItemShape {
+    fn tooltip_text(&self) -> String {
+        let hex = self.colour.rgb().into_css_hex();
+        match self.label {
+            Some(ref label) => format!("{}\n{}", label, hex),
+            None => hex,
+        }
+    }
+}
+
+#[derive(Wrapper)]
+pub struct PaintWheelViewCore {
+    graticule: Graticule,
+    shapes: RefCell<Vec<ItemShape>>,
+}
+
+impl PackableWidgetObject for PaintWheelViewCore {
+    type PWT = gtk::DrawingArea;
+
+    fn pwo(&self) -> Self::PWT {
+        self.graticule.drawing_area().clone()
+    }
+}
+
+pub type PaintWheelView = Rc<PaintWheelViewCore>;
+
+pub trait PaintWheelViewInterface {
+    fn create(attr: ScalarAttribute) -> PaintWheelView;
+
+    fn from_paints<CI: ColouredItemInterface>(
+        items: &[CI],
+        attr: ScalarAttribute,
+    ) -> PaintWheelView {
+        let view = Self::create(attr);
+        view.set_items(items);
+        view
+    }
+
+    // The labels are shown in the items' tooltips
+    fn from_labelled_paints<CI: ColouredItemInterface>(
+        items: &[(CI, String)],
+        attr: ScalarAttribute,
+    ) -> PaintWheelView {
+        let view = Self::create(attr);
+        view.set_labelled_items(items);
+        view
+    }
+}
+
+impl PaintWheelViewInterface for PaintWheelView {
+    fn create(attr: ScalarAttribute) -> PaintWheelView {
+        let view = Rc::new(PaintWheelViewCore {
+            graticule: Graticule::create(attr),
+            shapes: RefCell::new(Vec::new()),
+        });
+
+        let view_c = view.clone();
+        view.graticule.drawing_area().connect_query_tooltip(
+            move |_, x, y, _, tooltip| match view_c
+                .graticule
+                .throttled_tooltip_text(Point(x as f64, y as f64), |raw_xy| {
+                    view_c.get_tooltip_text_at((raw_xy.x(), raw_xy.y()))
+                }) {
+                Some(text) => {
+                    tooltip.set_text(Some(text.as_str()));
+                    true
+                }
+                None => false,
+            },
+        );
+
+        let view_c = view.clone();
+        view.graticule
+            .connect_draw(move |graticule, cairo_context| {
+                cairo_context.set_line_width(2.0);
+                for shape in view_c.shapes.borrow().iter() {
+                    shape.draw(graticule, cairo_context);
+                }
+            });
+        view
+    }
+}
+
+impl PaintWheelViewCore {
+    pub fn attr(&self) -> ScalarAttribute {
+        self.graticule.attr()
+    }
+
+    // The whole wheel at "size" x "size" pixels as PNG or SVG (by extension)
+    pub fn render_to_file(&self, path: &Path, size: i32) -> io::Result<()> {
+        self.graticule.render_to_file(path, size)
+    }
+
+    pub fn len(&self) -> usize {
+        self.shapes.borrow().len()
+    }
+
+    fn set_shapes(&self, shapes: Vec<ItemShape>) {
+        *self.shapes.borrow_mut() = shapes;
+        self.graticule.queue_draw();
+    }
+
+    pub fn set_items<CI: ColouredItemInterface>(&self, items: &[CI]) {
+        let attr = self.attr();
+        let shapes = items
+            .iter()
+            .map(|item| {
+                let colour = item.colour();
+                let xy = colour_wheel_xy(&colour, attr);
+                ItemShape {
+                    colour,
+                    label: None,
+                    xy,
+                }
+            })
+            .collect();
+        self.set_shapes(shapes);
+    }
+
+    pub fn set_labelled_items<CI: ColouredItemInterface>(&self, items: &[(CI, String)]) {
+        let attr = self.attr();
+        let shapes = items
+            .iter()
+            .map(|(item, label)| {
+                let colour = item.colour();
+                let xy = colour_wheel_xy(&colour, attr);
+                ItemShape {
+                    colour,
+                    label: Some(label.to_string()),
+                    xy,
+                }
+            })
+            .collect();
+        self.set_shapes(shapes);
+    }
+
+    pub fn set_target_colour(&self, o_colour: Option<&Colour>) {
+        self.graticule.set_current_target_colour(o_colour);
+    }
+
+    fn get_tooltip_text_at(&self, posn: (f64, f64)) -> Option<String> {
+        let point = self.graticule.reverse_transform(Point::from(posn));
+        self.shapes
+            .borrow()
+            .iter()
+            .filter(|shape| shape.encloses(point))
+            .min_by(|a, b| {
+                a.distance_to(point)
+                    .partial_cmp(&b.distance_to(point))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .map(|shape| shape.tooltip_text())
+    }
+}